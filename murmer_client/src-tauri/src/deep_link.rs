@@ -0,0 +1,99 @@
+//! `murmer://join` links.
+//!
+//! A link such as `murmer://join?server=wss://chat.example/ws&channel=general`
+//! opens the app and connects to `server`, switching to `channel` once the
+//! channel list arrives. Links reach the app through `tauri-plugin-deep-link`:
+//! either as the launch URL, or (via the single-instance plugin) from a second
+//! launch, which is routed to the running instance instead of starting a new
+//! one. Only well-formed join links are forwarded; anything else is logged and
+//! dropped so the webview never sees arbitrary input.
+//!
+//! A valid link shows and focuses the main window, is kept in [`PendingJoin`]
+//! and emits `deep-link-join` with a [`JoinLink`] payload. A link from the
+//! launch itself arrives before the webview is listening, so the client
+//! collects the pending link with the `take_deep_link_join` command both on
+//! startup and on every event; taking it clears it, so a link is acted on
+//! only once.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+/// The URL scheme registered for Murmer links.
+pub const SCHEME: &str = "murmer";
+
+/// Event emitted to the webview for every valid join link.
+const JOIN_EVENT: &str = "deep-link-join";
+
+/// A validated `murmer://join` link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JoinLink {
+    /// WebSocket URL of the server (`ws://` or `wss://`).
+    pub server: String,
+    /// Name of the text channel to open, if the link names one.
+    pub channel: Option<String>,
+}
+
+/// The last join link not yet collected by the client.
+#[derive(Default)]
+pub struct PendingJoin(Mutex<Option<JoinLink>>);
+
+/// Parse and validate a join link. Returns `None` unless the scheme is
+/// `murmer`, the action is `join` and `server` is an absolute `ws`/`wss` URL.
+pub fn parse_join(url: &Url) -> Option<JoinLink> {
+    if url.scheme() != SCHEME || url.host_str() != Some("join") {
+        return None;
+    }
+    let param = |key: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let server = Url::parse(&param("server")?).ok()?;
+    if !matches!(server.scheme(), "ws" | "wss") || server.host_str().is_none() {
+        return None;
+    }
+    Some(JoinLink {
+        server: server.to_string(),
+        channel: param("channel"),
+    })
+}
+
+/// Forward the first valid join link among `urls` to the webview.
+fn handle_urls<R: Runtime>(app: &AppHandle<R>, urls: &[Url]) {
+    let Some(link) = urls.iter().find_map(parse_join) else {
+        tracing::warn!("ignoring unrecognised deep link: {urls:?}");
+        return;
+    };
+    if let Ok(mut pending) = app.state::<PendingJoin>().0.lock() {
+        *pending = Some(link.clone());
+    }
+    crate::show_main_window(app);
+    let _ = app.emit(JOIN_EVENT, link);
+}
+
+/// Hook up link handling during setup: register the scheme where the
+/// installer does not (Linux, and Windows dev builds), route links opened
+/// while running, and handle the link the app was launched with.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+    if let Err(e) = app.deep_link().register_all() {
+        tracing::warn!("could not register the {SCHEME}:// scheme: {e}");
+    }
+
+    let handle = app.clone();
+    app.deep_link()
+        .on_open_url(move |event| handle_urls(&handle, &event.urls()));
+
+    if let Ok(Some(urls)) = app.deep_link().get_current() {
+        handle_urls(app, &urls);
+    }
+}
+
+/// Hand over (and forget) the join link that has not been collected yet.
+pub fn take_pending<R: Runtime>(app: &AppHandle<R>) -> Option<JoinLink> {
+    app.state::<PendingJoin>().0.lock().ok()?.take()
+}
@@ -2,6 +2,16 @@
 //!
 //! Sets up the system tray and window event handlers before running the app.
 
+#[cfg(desktop)]
+mod deep_link;
+mod monitor;
+#[cfg(desktop)]
+mod notifications;
+#[cfg(desktop)]
+mod ptt;
+#[cfg(desktop)]
+mod settings;
+
 /// Id the tray is registered under so `set_tray_theme` can look it back up.
 const TRAY_ID: &str = "main";
 
@@ -19,16 +29,291 @@ fn tray_icon(theme: &str) -> tauri::Result<tauri::image::Image<'static>> {
     tauri::image::Image::from_bytes(bytes)
 }
 
+/// What the tray icon is rendered from: the theme picks the logo variant and
+/// a nonzero unread count adds the badge and changes the tooltip.
+#[derive(Default)]
+struct TrayStatus {
+    theme: String,
+    unread: u32,
+}
+
+#[derive(Default)]
+struct TrayState(std::sync::Mutex<TrayStatus>);
+
+/// Colour of the unread dot painted onto the tray icon.
+const BADGE_RGBA: [u8; 4] = [0xe5, 0x48, 0x4d, 0xff];
+
+/// Paints an unread dot into the top-right corner of a tray icon, so no
+/// separate badged artwork has to be kept in sync with the logo.
+fn with_badge(icon: &tauri::image::Image<'_>) -> tauri::image::Image<'static> {
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+    let radius = width.min(height) as f32 * 0.22;
+    let (cx, cy) = (width as f32 - radius, radius);
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                let i = ((y * width + x) * 4) as usize;
+                rgba[i..i + 4].copy_from_slice(&BADGE_RGBA);
+            }
+        }
+    }
+    tauri::image::Image::new_owned(rgba, width, height)
+}
+
+fn tray_tooltip(unread: u32) -> String {
+    if unread == 0 {
+        "Murmer".to_string()
+    } else {
+        format!("Murmer — {unread} unread")
+    }
+}
+
+/// Applies `change` to the tray status and re-renders icon and tooltip.
+#[cfg(desktop)]
+fn update_tray(app: &tauri::AppHandle, change: impl FnOnce(&mut TrayStatus)) -> Result<(), String> {
+    use tauri::Manager;
+
+    let state = app.state::<TrayState>();
+    let mut status = state.0.lock().map_err(|e| e.to_string())?;
+    change(&mut status);
+    let tray = app
+        .tray_by_id(TRAY_ID)
+        .ok_or_else(|| "tray icon not found".to_string())?;
+    let mut icon = tray_icon(&status.theme).map_err(|e| e.to_string())?;
+    if status.unread > 0 {
+        icon = with_badge(&icon);
+    }
+    tray.set_icon(Some(icon)).map_err(|e| e.to_string())?;
+    tray.set_tooltip(Some(tray_tooltip(status.unread)))
+        .map_err(|e| e.to_string())
+}
+
 /// Switches the tray icon to the light or dark logo. Called by the client
 /// whenever the theme store changes (see `src/lib/stores/theme.ts`); any value
 /// other than "light" falls back to the dark logo.
 #[tauri::command]
 fn set_tray_theme(app: tauri::AppHandle, theme: String) -> Result<(), String> {
-    let tray = app
-        .tray_by_id(TRAY_ID)
-        .ok_or_else(|| "tray icon not found".to_string())?;
-    let icon = tray_icon(&theme).map_err(|e| e.to_string())?;
-    tray.set_icon(Some(icon)).map_err(|e| e.to_string())
+    #[cfg(desktop)]
+    return update_tray(&app, |status| status.theme = theme);
+    #[cfg(not(desktop))]
+    {
+        let _ = (app, theme);
+        Ok(())
+    }
+}
+
+/// Shows the unread total in the tray: "Murmer — N unread" as tooltip and a
+/// badged icon while `count > 0`. Called by the client whenever the total
+/// changes (see `src/lib/stores/trayBadge.ts`); focusing the main window
+/// clears it.
+#[tauri::command]
+fn set_unread_count(app: tauri::AppHandle, count: u32) -> Result<(), String> {
+    #[cfg(desktop)]
+    return update_tray(&app, |status| status.unread = count);
+    #[cfg(not(desktop))]
+    {
+        let _ = (app, count);
+        Ok(())
+    }
+}
+
+/// Brings the main window back from the tray or from behind other windows.
+fn show_main_window<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    use tauri::Manager;
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Hides the main window to the tray or quits the app.
+#[cfg(desktop)]
+fn apply_close_choice<R: tauri::Runtime>(app: &tauri::AppHandle<R>, choice: settings::CloseChoice) {
+    use tauri::Manager;
+
+    match choice {
+        settings::CloseChoice::Minimize => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.hide();
+            }
+        }
+        settings::CloseChoice::Close => app.exit(0),
+    }
+}
+
+/// Closing the main window (the close itself is already prevented): apply
+/// the remembered choice, or ask whether to minimize to the tray or quit, and
+/// whether to remember the answer.
+#[cfg(desktop)]
+fn close_requested<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    use settings::{CloseChoice, CloseSettings};
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+
+    if let Some(choice) = settings::load_close(app).remembered() {
+        apply_close_choice(app, choice);
+        return;
+    }
+
+    let app = app.clone();
+    app.dialog()
+        .message("Keep Murmer running in the tray, or quit?")
+        .title("Close Murmer")
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Minimize to tray".to_string(),
+            "Quit".to_string(),
+        ))
+        .show(move |minimize| {
+            let choice = if minimize {
+                CloseChoice::Minimize
+            } else {
+                CloseChoice::Close
+            };
+            app.dialog()
+                .message("Do this every time without asking? You can reset it in Settings.")
+                .title("Remember choice")
+                .buttons(MessageDialogButtons::YesNo)
+                .show(move |remember| {
+                    let preference = CloseSettings {
+                        choice: Some(choice),
+                        ask_again: !remember,
+                    };
+                    if let Err(e) = settings::save_close(&app, &preference) {
+                        tracing::warn!("could not save close preference: {e}");
+                    }
+                    apply_close_choice(&app, choice);
+                });
+        });
+}
+
+/// Forgets the remembered close-window choice so the next close asks again.
+/// Called from the desktop section of the settings modal.
+#[tauri::command]
+fn reset_close_preference(app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(desktop)]
+    return settings::save_close(&app, &settings::CloseSettings::default());
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        Ok(())
+    }
+}
+
+/// Returns the `murmer://join` link the app was opened with (or that arrived
+/// since the last call), clearing it. See `deep_link.rs`.
+#[tauri::command]
+fn take_deep_link_join(app: tauri::AppHandle) -> Option<serde_json::Value> {
+    #[cfg(desktop)]
+    return deep_link::take_pending(&app).and_then(|link| serde_json::to_value(link).ok());
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        None
+    }
+}
+
+/// Starts probing the chat server at `url` (a `ws://`/`wss://` address) and
+/// emitting `connection-state` events, replacing any running monitor. See
+/// `monitor.rs`.
+#[tauri::command]
+fn start_connection_monitor(app: tauri::AppHandle, url: String) -> Result<(), String> {
+    monitor::start(&app, &url)
+}
+
+/// Stops the monitor started by `start_connection_monitor`.
+#[tauri::command]
+fn stop_connection_monitor(app: tauri::AppHandle) {
+    monitor::stop(&app);
+}
+
+/// Shows a native notification for a mention or direct message. Suppressed
+/// (returning `false`) while `do_not_disturb` is set — the client passes it
+/// when the user's status is busy — or while the main window has focus.
+#[tauri::command]
+fn notify(
+    app: tauri::AppHandle,
+    title: String,
+    body: String,
+    do_not_disturb: bool,
+) -> Result<bool, String> {
+    #[cfg(desktop)]
+    return notifications::notify(&app, &title, &body, do_not_disturb);
+    #[cfg(not(desktop))]
+    {
+        let _ = (app, title, body, do_not_disturb);
+        Ok(false)
+    }
+}
+
+/// Argument the OS login item passes when it starts Murmer, so a start at
+/// login can go straight to the tray instead of opening the window.
+const AUTOSTART_ARG: &str = "--autostart";
+
+/// Whether this process was started by the login item rather than the user.
+fn launched_by_autostart(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == AUTOSTART_ARG)
+}
+
+/// Registers Murmer to start (minimized to the tray) when the user logs in.
+/// Off until enabled from the desktop section of the settings modal.
+#[tauri::command]
+fn enable_autostart(app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(desktop)]
+    {
+        use tauri_plugin_autostart::ManagerExt;
+        app.autolaunch().enable().map_err(|e| e.to_string())
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        Err("autostart is not supported on this platform".to_string())
+    }
+}
+
+/// Removes the login item added by `enable_autostart`.
+#[tauri::command]
+fn disable_autostart(app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(desktop)]
+    {
+        use tauri_plugin_autostart::ManagerExt;
+        app.autolaunch().disable().map_err(|e| e.to_string())
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        Ok(())
+    }
+}
+
+/// Whether Murmer is currently registered to start at login.
+#[tauri::command]
+fn is_autostart_enabled(app: tauri::AppHandle) -> Result<bool, String> {
+    #[cfg(desktop)]
+    {
+        use tauri_plugin_autostart::ManagerExt;
+        app.autolaunch().is_enabled().map_err(|e| e.to_string())
+    }
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        Ok(false)
+    }
+}
+
+/// Binds system-wide push-to-talk to `shortcut` (an accelerator such as
+/// "Ctrl+Space"), or clears it with `null`. See `ptt.rs`.
+#[tauri::command]
+fn set_ptt_shortcut(app: tauri::AppHandle, shortcut: Option<String>) -> Result<(), String> {
+    #[cfg(desktop)]
+    return ptt::set(&app, shortcut.as_deref());
+    #[cfg(not(desktop))]
+    {
+        let _ = (app, shortcut);
+        Err("global shortcuts are not supported on this platform".to_string())
+    }
 }
 
 /// WebKitGTK's DMA-BUF renderer is known to glitch or fall back to software
@@ -59,7 +344,7 @@ fn apply_webkitgtk_workarounds() {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() -> tauri::Result<()> {
     use tauri::{
-        Manager,
+        Manager, RunEvent, WindowEvent,
         menu::{MenuBuilder, MenuItemBuilder},
         tray::{TrayIconBuilder, TrayIconEvent},
     };
@@ -78,16 +363,80 @@ pub fn run() -> tauri::Result<()> {
     #[cfg(target_os = "linux")]
     apply_webkitgtk_workarounds();
 
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // Must be the first plugin: a second launch (e.g. from clicking a
+    // `murmer://` link) exits early and its link is forwarded to this
+    // instance's deep-link handler.
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        show_main_window(app)
+    }));
+    let builder = builder
         .plugin(WindowStateBuilder::default().build())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
-        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
-        .invoke_handler(tauri::generate_handler![set_tray_theme])
+        .plugin(tauri_plugin_deep_link::init())
+        .manage(monitor::ConnectionMonitor::default());
+    // Global shortcuts only exist on desktop; the handler is what turns the
+    // push-to-talk shortcut into `ptt-start` / `ptt-stop` events.
+    #[cfg(desktop)]
+    let builder = builder
+        .plugin(ptt::plugin())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            Some(vec![AUTOSTART_ARG]),
+        ))
+        .manage(ptt::PttShortcut::default())
+        .manage(deep_link::PendingJoin::default())
+        .manage(TrayState::default())
+        .on_window_event(|window, event| {
+            if window.label() != "main" {
+                return;
+            }
+            match event {
+                // The user is looking at the app again: drop the unread badge.
+                WindowEvent::Focused(true) => {
+                    let _ = update_tray(window.app_handle(), |status| status.unread = 0);
+                }
+                WindowEvent::CloseRequested { api, .. } => {
+                    api.prevent_close();
+                    close_requested(window.app_handle());
+                }
+                WindowEvent::Destroyed => monitor::stop(window.app_handle()),
+                _ => {}
+            }
+        });
+
+    builder
+        .invoke_handler(tauri::generate_handler![
+            set_tray_theme,
+            set_unread_count,
+            set_ptt_shortcut,
+            notify,
+            reset_close_preference,
+            enable_autostart,
+            disable_autostart,
+            is_autostart_enabled,
+            take_deep_link_join,
+            start_connection_monitor,
+            stop_connection_monitor
+        ])
         .setup(|app| {
+            #[cfg(desktop)]
+            ptt::restore(app.handle());
+            #[cfg(desktop)]
+            deep_link::init(app.handle());
+            // Started at login: stay in the tray until the user opens it.
+            #[cfg(desktop)]
+            if launched_by_autostart(std::env::args())
+                && let Some(window) = app.get_webview_window("main")
+            {
+                let _ = window.hide();
+            }
+
             // create tray menu
             let open = MenuItemBuilder::with_id("open", "Open").build(app)?;
             let quit = MenuItemBuilder::with_id("quit", "Close").build(app)?;
@@ -102,37 +451,40 @@ pub fn run() -> tauri::Result<()> {
             // user is on the light theme.
             TrayIconBuilder::with_id(TRAY_ID)
                 .menu(&tray_menu)
-                .tooltip("Murmer")
+                .tooltip(tray_tooltip(0))
                 .icon(tray_icon("dark")?)
                 .build(app)?;
             Ok(())
         })
         .on_menu_event(|app, event| match event.id().as_ref() {
-            "open" => {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
-            }
+            "open" => show_main_window(app),
             "quit" => app.exit(0),
             _ => {}
         })
         .on_tray_icon_event(|app, event| {
-            if let TrayIconEvent::DoubleClick { .. } = event
-                && let Some(window) = app.get_webview_window("main")
-            {
-                let _ = window.show();
-                let _ = window.set_focus();
+            if let TrayIconEvent::DoubleClick { .. } = event {
+                show_main_window(app);
             }
         })
-        .run(tauri::generate_context!())?;
+        .build(tauri::generate_context!())?
+        .run(|app, event| match event {
+            RunEvent::Exit => {
+                monitor::stop(app);
+                #[cfg(desktop)]
+                ptt::unregister_all(app);
+            }
+            // Clicking a notification (or the dock icon) reactivates the app.
+            #[cfg(target_os = "macos")]
+            RunEvent::Reopen { .. } => show_main_window(app),
+            _ => {}
+        });
 
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::tray_icon;
+    use super::{BADGE_RGBA, launched_by_autostart, tray_icon, tray_tooltip, with_badge};
     use std::str::FromStr;
     use tauri_plugin_global_shortcut::Shortcut;
 
@@ -161,6 +513,134 @@ mod tests {
         assert_eq!(fallback.rgba(), dark.rgba());
     }
 
+    /// Push-to-talk bindings come from the client's PTT key setting; blank
+    /// clears the binding and garbage is reported instead of registered.
+    #[cfg(desktop)]
+    #[test]
+    fn ptt_bindings_parse_or_clear() {
+        use super::ptt::parse_binding;
+        assert!(parse_binding(Some("Ctrl+Space")).unwrap().is_some());
+        assert!(parse_binding(Some("F13")).unwrap().is_some());
+        assert!(parse_binding(None).unwrap().is_none());
+        assert!(parse_binding(Some("  ")).unwrap().is_none());
+        assert!(parse_binding(Some("Ctrl+NotAKey")).is_err());
+    }
+
+    /// The unread badge is drawn over the logo at runtime; it must keep the
+    /// icon size and only cover the top-right corner.
+    #[test]
+    fn unread_badge_marks_only_the_corner() {
+        let icon = tray_icon("dark").expect("dark icon");
+        let badged = with_badge(&icon);
+        assert_eq!(
+            (badged.width(), badged.height()),
+            (icon.width(), icon.height())
+        );
+        let pixel = |image: &tauri::image::Image<'_>, x: u32, y: u32| {
+            let i = ((y * image.width() + x) * 4) as usize;
+            image.rgba()[i..i + 4].to_vec()
+        };
+        assert_eq!(pixel(&badged, 56, 8), BADGE_RGBA);
+        assert_eq!(pixel(&badged, 4, 60), pixel(&icon, 4, 60));
+        assert_eq!(tray_tooltip(0), "Murmer");
+        assert_eq!(tray_tooltip(3), "Murmer — 3 unread");
+    }
+
+    /// Busy users and a focused window both suppress notifications.
+    #[cfg(desktop)]
+    #[test]
+    fn notifications_respect_do_not_disturb_and_focus() {
+        use super::notifications::should_notify;
+        assert!(should_notify(false, false));
+        assert!(!should_notify(true, false));
+        assert!(!should_notify(false, true));
+        assert!(!should_notify(true, true));
+    }
+
+    /// Without a stored preference (or with "ask again") the dialog shows;
+    /// only a remembered choice skips it.
+    #[cfg(desktop)]
+    #[test]
+    fn close_dialog_is_skipped_only_for_remembered_choices() {
+        use super::settings::{CloseChoice, CloseSettings};
+        assert_eq!(CloseSettings::default().remembered(), None);
+        let asked = CloseSettings {
+            choice: Some(CloseChoice::Minimize),
+            ask_again: true,
+        };
+        assert_eq!(asked.remembered(), None);
+        let remembered = CloseSettings {
+            choice: Some(CloseChoice::Close),
+            ask_again: false,
+        };
+        assert_eq!(remembered.remembered(), Some(CloseChoice::Close));
+        let json = serde_json::to_string(&remembered).unwrap();
+        assert_eq!(json, r#"{"choice":"close","askAgain":false}"#);
+    }
+
+    /// Join links must name a WebSocket server; anything else is dropped
+    /// before it reaches the webview.
+    #[cfg(desktop)]
+    #[test]
+    fn join_links_are_validated() {
+        use super::deep_link::{JoinLink, parse_join};
+        let parse = |link: &str| parse_join(&tauri::Url::parse(link).unwrap());
+        assert_eq!(
+            parse("murmer://join?server=wss://chat.example/ws&channel=general"),
+            Some(JoinLink {
+                server: "wss://chat.example/ws".into(),
+                channel: Some("general".into()),
+            })
+        );
+        assert_eq!(
+            parse("murmer://join?server=ws%3A%2F%2Flocalhost%3A3001%2Fws&channel="),
+            Some(JoinLink {
+                server: "ws://localhost:3001/ws".into(),
+                channel: None,
+            })
+        );
+        assert_eq!(parse("murmer://join?channel=general"), None);
+        assert_eq!(parse("murmer://join?server=https://chat.example/ws"), None);
+        assert_eq!(parse("murmer://join?server=not a url"), None);
+        assert_eq!(parse("murmer://invite?url=wss://chat.example/ws"), None);
+        assert_eq!(parse("https://join?server=wss://chat.example/ws"), None);
+    }
+
+    /// Reconnect probes back off exponentially from 1s and level off at 30s.
+    #[test]
+    fn connection_monitor_backs_off_and_resolves_addresses() {
+        use super::monitor::{backoff_delay, probe_address};
+        use std::time::Duration;
+        let secs: Vec<_> = (1..=7).map(|n| backoff_delay(n).as_secs()).collect();
+        assert_eq!(secs, [1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(backoff_delay(u32::MAX), Duration::from_secs(30));
+
+        assert_eq!(
+            probe_address("wss://chat.example/ws").as_deref(),
+            Ok("chat.example:443")
+        );
+        assert_eq!(
+            probe_address("ws://localhost:3001/ws").as_deref(),
+            Ok("localhost:3001")
+        );
+        assert_eq!(probe_address("ws://[::1]/ws").as_deref(), Ok("[::1]:80"));
+        assert!(probe_address("https://chat.example").is_err());
+        assert!(probe_address("nonsense").is_err());
+    }
+
+    /// Only the login item's argument hides the window at startup.
+    #[test]
+    fn autostart_is_detected_from_the_launch_argument() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(launched_by_autostart(
+            args(&["murmer", "--autostart"]).into_iter()
+        ));
+        assert!(!launched_by_autostart(args(&["murmer"]).into_iter()));
+        assert!(!launched_by_autostart(
+            args(&["murmer", "--autostart=no"]).into_iter()
+        ));
+    }
+
     /// The web client registers its hotkey combos (see
     /// `src/lib/stores/hotkeys.ts`) verbatim as global shortcuts, so the
     /// combo format must stay parseable by the global-shortcut plugin.
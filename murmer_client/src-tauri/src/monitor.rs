@@ -0,0 +1,139 @@
+//! Native reachability monitor for the chat server.
+//!
+//! `start_connection_monitor(url)` spawns a task that probes the server with
+//! a TCP connect to the WebSocket URL's host and port. While the server is
+//! unreachable it retries with exponential backoff; once reachable it keeps
+//! re-probing at a fixed interval to notice the next outage. Each attempt
+//! while unreachable, and the switch to reachable, is emitted to the webview
+//! as a `connection-state` event, so the client only reacts (reconnecting
+//! its WebSocket on `online`) instead of running its own retry timers. Only
+//! one monitor runs at a time: starting a new one or calling
+//! `stop_connection_monitor` aborts the previous task, and the monitor is
+//! also stopped when the main window is destroyed.
+
+use std::{sync::Mutex, time::Duration};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime, Url, async_runtime::JoinHandle};
+
+/// Event carrying a [`StateEvent`].
+const EVENT: &str = "connection-state";
+
+/// Delay before the first retry; doubled per failed probe up to [`MAX_BACKOFF`].
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// How often a reachable server is probed again.
+const ONLINE_INTERVAL: Duration = Duration::from_secs(15);
+/// A probe that has not connected by then counts as a failure.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The running monitor task, if any.
+#[derive(Default)]
+pub struct ConnectionMonitor(Mutex<Option<JoinHandle<()>>>);
+
+/// What the monitor last found: probing, reachable or unreachable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Connecting,
+    Online,
+    Offline,
+}
+
+/// Payload of a `connection-state` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StateEvent<'a> {
+    state: State,
+    url: &'a str,
+    /// Failed probes in a row.
+    attempt: u32,
+    /// When offline, how long until the next probe.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_in_ms: Option<u64>,
+}
+
+/// Backoff before the probe following `failures` failed ones (1-based).
+pub fn backoff_delay(failures: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+    INITIAL_BACKOFF.saturating_mul(factor).min(MAX_BACKOFF)
+}
+
+/// The `host:port` a WebSocket URL connects to.
+pub fn probe_address(url: &str) -> Result<String, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("invalid server URL '{url}': {e}"))?;
+    if !matches!(parsed.scheme(), "ws" | "wss") {
+        return Err(format!("not a WebSocket URL: '{url}'"));
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| format!("server URL has no host: '{url}'"))?;
+    let port = parsed
+        .port_or_known_default()
+        .ok_or_else(|| format!("server URL has no port: '{url}'"))?;
+    // `host_str` keeps the brackets around IPv6 literals, as `connect` wants.
+    Ok(format!("{host}:{port}"))
+}
+
+async fn probe(address: &str) -> bool {
+    matches!(
+        tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect(address)).await,
+        Ok(Ok(_))
+    )
+}
+
+async fn run<R: Runtime>(app: AppHandle<R>, url: String, address: String) {
+    let emit = |state, attempt, retry_in: Option<Duration>| {
+        let _ = app.emit(
+            EVENT,
+            StateEvent {
+                state,
+                url: &url,
+                attempt,
+                retry_in_ms: retry_in.map(|d| d.as_millis() as u64),
+            },
+        );
+    };
+    let mut failures = 0u32;
+    let mut last = None;
+    loop {
+        if last != Some(State::Online) {
+            emit(State::Connecting, failures, None);
+        }
+        if probe(&address).await {
+            if last != Some(State::Online) {
+                emit(State::Online, 0, None);
+            }
+            failures = 0;
+            last = Some(State::Online);
+            tokio::time::sleep(ONLINE_INTERVAL).await;
+        } else {
+            failures = failures.saturating_add(1);
+            let delay = backoff_delay(failures);
+            emit(State::Offline, failures, Some(delay));
+            last = Some(State::Offline);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Start monitoring `url`, replacing any running monitor.
+pub fn start<R: Runtime>(app: &AppHandle<R>, url: &str) -> Result<(), String> {
+    let address = probe_address(url)?;
+    let task = tauri::async_runtime::spawn(run(app.clone(), url.to_string(), address));
+    let state = app.state::<ConnectionMonitor>();
+    let mut current = state.0.lock().map_err(|e| e.to_string())?;
+    if let Some(previous) = current.replace(task) {
+        previous.abort();
+    }
+    Ok(())
+}
+
+/// Stop the running monitor, if any.
+pub fn stop<R: Runtime>(app: &AppHandle<R>) {
+    if let Ok(mut current) = app.state::<ConnectionMonitor>().0.lock()
+        && let Some(task) = current.take()
+    {
+        task.abort();
+    }
+}
@@ -0,0 +1,71 @@
+//! Native desktop notifications for mentions and direct messages.
+//!
+//! The client calls the `notify` command for every notification-worthy event;
+//! this module decides whether to show it. Nothing is shown while the user is
+//! busy ("Do Not Disturb") or already looking at the main window. Clicking a
+//! notification brings the main window back, like the tray's "Open" entry.
+
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Whether a notification should be shown at all.
+pub fn should_notify(do_not_disturb: bool, window_focused: bool) -> bool {
+    !do_not_disturb && !window_focused
+}
+
+fn main_window_focused<R: Runtime>(app: &AppHandle<R>) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+/// Show a notification unless suppressed; returns whether one was shown.
+pub fn notify<R: Runtime>(
+    app: &AppHandle<R>,
+    title: &str,
+    body: &str,
+    do_not_disturb: bool,
+) -> Result<bool, String> {
+    if !should_notify(do_not_disturb, main_window_focused(app)) {
+        return Ok(false);
+    }
+    show(app, title, body)?;
+    Ok(true)
+}
+
+/// On Linux the notification plugin has no click callback, so the
+/// notification is sent through the notification daemon directly with a
+/// default action, and a click (the "default" action) shows the window.
+#[cfg(target_os = "linux")]
+fn show<R: Runtime>(app: &AppHandle<R>, title: &str, body: &str) -> Result<(), String> {
+    let handle = notify_rust::Notification::new()
+        .appname("Murmer")
+        .summary(title)
+        .body(body)
+        .action("default", "Open")
+        .show()
+        .map_err(|e| e.to_string())?;
+    let app = app.clone();
+    // Blocks until the notification is clicked, dismissed or expires.
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if action == "default" {
+                crate::show_main_window(&app);
+            }
+        });
+    });
+    Ok(())
+}
+
+/// Elsewhere clicking a notification activates the app; macOS reports that
+/// as `RunEvent::Reopen`, which shows the window (see `run` in `lib.rs`).
+#[cfg(not(target_os = "linux"))]
+fn show<R: Runtime>(app: &AppHandle<R>, title: &str, body: &str) -> Result<(), String> {
+    use tauri_plugin_notification::NotificationExt;
+
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,106 @@
+//! System-wide push-to-talk.
+//!
+//! The web client's PTT key only works while the window has focus. Here the
+//! binding is registered as an OS global shortcut instead: pressing it emits
+//! `ptt-start` to the webview and releasing it emits `ptt-stop`, wherever the
+//! focus is. The binding is persisted to `ptt.json` in the app config
+//! directory so it is active again on the next start before the webview has
+//! loaded.
+
+use std::{str::FromStr, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::settings;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+/// Settings file holding the persisted binding.
+const SETTINGS_FILE: &str = "ptt.json";
+
+/// The currently registered push-to-talk shortcut, if any.
+#[derive(Default)]
+pub struct PttShortcut(Mutex<Option<Shortcut>>);
+
+#[derive(Default, Serialize, Deserialize)]
+struct PttSettings {
+    shortcut: Option<String>,
+}
+
+/// Global-shortcut plugin whose handler turns presses of the registered PTT
+/// shortcut into `ptt-start` / `ptt-stop` events. Shortcuts the web client
+/// registers itself are left to their own callbacks.
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app, shortcut, event: ShortcutEvent| {
+            let state = app.state::<PttShortcut>();
+            let is_ptt = state.0.lock().is_ok_and(|s| s.as_ref() == Some(shortcut));
+            if !is_ptt {
+                return;
+            }
+            let name = match event.state() {
+                ShortcutState::Pressed => "ptt-start",
+                ShortcutState::Released => "ptt-stop",
+            };
+            let _ = app.emit(name, ());
+        })
+        .build()
+}
+
+/// Parse an accelerator such as `Ctrl+Space`; `None` or an empty string
+/// clears the binding.
+pub fn parse_binding(binding: Option<&str>) -> Result<Option<Shortcut>, String> {
+    match binding.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(binding) => Shortcut::from_str(binding)
+            .map(Some)
+            .map_err(|e| format!("invalid shortcut '{binding}': {e}")),
+    }
+}
+
+/// Swap the registered PTT shortcut for `next`, unregistering the old one.
+fn apply<R: Runtime>(app: &AppHandle<R>, next: Option<Shortcut>) -> Result<(), String> {
+    let state = app.state::<PttShortcut>();
+    let mut current = state.0.lock().map_err(|e| e.to_string())?;
+    if *current == next {
+        return Ok(());
+    }
+    if let Some(old) = current.take() {
+        let _ = app.global_shortcut().unregister(old);
+    }
+    if let Some(shortcut) = next {
+        app.global_shortcut()
+            .register(shortcut)
+            .map_err(|e| e.to_string())?;
+        *current = Some(shortcut);
+    }
+    Ok(())
+}
+
+/// Change the PTT binding and persist it. Called by the client whenever its
+/// PTT key setting changes (see `src/lib/voice/globalPtt.ts`).
+pub fn set<R: Runtime>(app: &AppHandle<R>, binding: Option<&str>) -> Result<(), String> {
+    let shortcut = parse_binding(binding)?;
+    let settings = PttSettings {
+        shortcut: shortcut
+            .is_some()
+            .then(|| binding.unwrap_or_default().trim().to_string()),
+    };
+    apply(app, shortcut)?;
+    settings::save(app, SETTINGS_FILE, &settings)
+}
+
+/// Register the persisted binding at startup. A missing or unreadable file
+/// just leaves push-to-talk unbound until the client sets it.
+pub fn restore<R: Runtime>(app: &AppHandle<R>) {
+    let settings: PttSettings = settings::load(app, SETTINGS_FILE);
+    if let Err(e) = parse_binding(settings.shortcut.as_deref()).and_then(|s| apply(app, s)) {
+        tracing::warn!("could not restore push-to-talk shortcut: {e}");
+    }
+}
+
+/// Release every global shortcut; called when the app exits so the OS does
+/// not keep them reserved if the process lingers.
+pub fn unregister_all<R: Runtime>(app: &AppHandle<R>) {
+    let _ = app.global_shortcut().unregister_all();
+}
@@ -0,0 +1,81 @@
+//! Small JSON settings files in the app config directory, for preferences the
+//! native shell needs before (or without) the webview: the push-to-talk
+//! binding and what closing the main window does.
+
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// File holding the remembered close-window choice.
+const CLOSE_SETTINGS_FILE: &str = "close.json";
+
+fn path<R: Runtime>(app: &AppHandle<R>, file: &str) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(file))
+}
+
+/// Read a settings file. A missing or unreadable file yields the defaults.
+pub fn load<T: DeserializeOwned + Default, R: Runtime>(app: &AppHandle<R>, file: &str) -> T {
+    path(app, file)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Write a settings file, creating the config directory if needed.
+pub fn save<T: Serialize, R: Runtime>(
+    app: &AppHandle<R>,
+    file: &str,
+    settings: &T,
+) -> Result<(), String> {
+    let Some(path) = path(app, file) else {
+        return Err("no app config directory".to_string());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+/// What closing the main window does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloseChoice {
+    /// Hide the window; the app keeps running in the tray.
+    Minimize,
+    /// Quit the app.
+    Close,
+}
+
+/// The last close-window choice and whether to ask again next time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseSettings {
+    pub choice: Option<CloseChoice>,
+    pub ask_again: bool,
+}
+
+impl Default for CloseSettings {
+    fn default() -> Self {
+        Self {
+            choice: None,
+            ask_again: true,
+        }
+    }
+}
+
+impl CloseSettings {
+    /// The choice to apply without showing the dialog, if one was remembered.
+    pub fn remembered(&self) -> Option<CloseChoice> {
+        if self.ask_again { None } else { self.choice }
+    }
+}
+
+pub fn load_close<R: Runtime>(app: &AppHandle<R>) -> CloseSettings {
+    load(app, CLOSE_SETTINGS_FILE)
+}
+
+pub fn save_close<R: Runtime>(app: &AppHandle<R>, settings: &CloseSettings) -> Result<(), String> {
+    save(app, CLOSE_SETTINGS_FILE, settings)
+}
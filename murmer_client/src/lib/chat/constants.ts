@@ -13,16 +13,24 @@ export const MAX_SERVER_ICON_BYTES = 1024 * 1024;
 
 /* User avatar size limit; must match the server's validation. */
 export const MAX_AVATAR_BYTES = 1024 * 1024;
+export const MAX_DISPLAY_NAME_LENGTH = 32;
+export const MAX_BIO_LENGTH = 300;
 
-/* The channel every server is seeded with. The server places new connections
-   into it and refuses to delete it, so the client can rely on it existing. */
+/* The default channel name when the server does not flag one. Servers mark
+   their configured default channel with `isDefault` in `channel-list`; this
+   name only covers servers too old to send the flag. */
 export const DEFAULT_CHANNEL_NAME = 'general';
 
 export const MESSAGE_INPUT_MAX_HEIGHT = 360;
 export const MAX_TOPIC_LENGTH = 256;
 export const PIN_PREVIEW_LIMIT = 120;
+export const MAX_FLAG_REASON_LENGTH = 500;
+export const MAX_SLOW_MODE_SECONDS = 21_600;
+export const MAX_CHANNEL_SUBSCRIBERS = 10_000;
 export const MIN_EPHEMERAL_SECONDS = 5;
 export const MAX_EPHEMERAL_SECONDS = 86_400;
+/** Furthest ahead a message may be scheduled; the server enforces 30 days. */
+export const MAX_SCHEDULE_MINUTES = 43_200;
 
 export const VOICE_QUALITY_PRESETS: Array<{
   quality: string;
@@ -85,6 +93,18 @@ export const HELP_COMMANDS: Array<{
       'Send a message that automatically deletes itself after the requested duration.',
     aliases: ['/temp <seconds> <message>']
   },
+  {
+    usage: '/schedule <minutes> <message>',
+    description: 'Post a message in this channel later, up to 30 days ahead.'
+  },
+  {
+    usage: '/scheduled',
+    description: 'List your scheduled messages.'
+  },
+  {
+    usage: '/unschedule <number>',
+    description: 'Cancel a scheduled message by its number in the /scheduled list.'
+  },
   {
     usage: '/search [query]',
     description: 'Open the search overlay and optionally pre-fill it with a query.'
@@ -273,3 +273,15 @@ export function ensureStatus(
   return (map[user] ?? fallback) as UserStatus;
 }
 
+
+export function formatLastSeen(lastSeen: string | undefined, now: number): string | null {
+  if (!lastSeen) return null;
+  const parsed = Date.parse(lastSeen);
+  if (Number.isNaN(parsed)) return null;
+  const minutes = Math.floor(Math.max(now - parsed, 0) / 60_000);
+  if (minutes < 1) return 'Last seen just now';
+  if (minutes < 60) return `Last seen ${minutes}m ago`;
+  const hours = Math.floor(minutes / 60);
+  if (hours < 24) return `Last seen ${hours}h ago`;
+  return `Last seen ${Math.floor(hours / 24)}d ago`;
+}
@@ -1,6 +1,7 @@
 /**
  * Translation of server error codes (the `message` field of
- * `{"type":"error"}` frames) into user-facing text.
+ * `{"type":"error"}` frames) into user-facing text. Frames also carry a
+ * stable numeric `code` (`ErrorCode` in the server's `ws/errors.rs`).
  */
 
 const SERVER_ERROR_MESSAGES: Record<string, string> = {
@@ -11,6 +12,7 @@ const SERVER_ERROR_MESSAGES: Record<string, string> = {
   'replay-attack': 'Authentication failed. Please try connecting again.',
   'invalid-signature': 'Authentication failed: invalid signature.',
   'invalid-signature-format': 'Authentication failed: invalid signature.',
+  'missing-auth-fields': 'Authentication failed: the login request was incomplete.',
   'invalid-public-key': 'Authentication failed: invalid key.',
   'invalid-key-length': 'Authentication failed: invalid key.',
   'invalid-encoding': 'Authentication failed: invalid key encoding.',
@@ -23,10 +25,24 @@ const SERVER_ERROR_MESSAGES: Record<string, string> = {
   'channel-deletion-failed': 'The server could not delete the channel. Please try again.',
   'cannot-delete-general': 'The general channel cannot be deleted.',
   'unknown-channel': 'That channel no longer exists.',
+  'channel-name-taken': 'Another channel already uses that name.',
+  'cannot-rename-default-channel': 'The default channel cannot be renamed.',
+  'channel-rename-failed': 'The server could not rename the channel. Please try again.',
+  'content-blocked': 'Your message contains a word that is not allowed on this server.',
   'message-rate-limit': 'You are sending messages too quickly. Please slow down.',
+  'unsupported-protocol': 'This client is too old for the server. Please update Murmer.',
+  'channel-rate-limit': 'You are creating channels too quickly. Please wait a minute.',
+  'channel-limit-reached': 'This server has reached its channel limit.',
+  'channel-full': 'That channel is full. Try again when someone leaves.',
+  'invalid-subscriber-limit': 'The member limit must be between 1 and 10000, or empty for no limit.',
+  'subscriber-limit-update-failed': 'Could not update the member limit for this channel.',
+  'reaction-rate-limit': 'You are reacting too quickly. Please slow down.',
+  'too-many-reactions': 'That message already has the maximum number of different reactions.',
   'message-too-long': 'That message is too long to send.',
+  'message-too-large': 'That message (with its attachments) is too large to send.',
   'invalid-voice-quality': 'Invalid voice quality setting.',
   'invalid-voice-bitrate': 'Invalid voice bitrate setting.',
+  'voice-mesh-limit': 'That voice channel is full.',
   'unknown-voice-channel': 'That voice channel no longer exists.',
   'voice-channel-update-failed': 'The server could not update the voice channel.',
   'role-permission-denied': 'You do not have permission to manage roles on this server.',
@@ -40,11 +56,22 @@ const SERVER_ERROR_MESSAGES: Record<string, string> = {
   'invalid-role-color': 'That role color is not a valid hex color.',
   'invalid-role-permissions': 'Those permissions are not valid.',
   'send-permission-denied': 'You do not have permission to send messages in this channel.',
+  'channel-post-denied': 'Only certain roles can post in this channel.',
+  'slow-mode-active': 'This channel is in slow mode.',
+  'invalid-slow-mode': 'Slow mode must be between 0 and 21600 seconds.',
+  'slow-mode-update-failed': 'The server could not update slow mode.',
+  'flag-target-not-found': 'That message no longer exists.',
+  'invalid-flag-reason': 'Report reasons are limited to 500 characters.',
+  'already-flagged': 'You already reported this message.',
+  'flag-not-found': 'That report was already resolved.',
+  'flag-failed': 'Failed to process the report. Please try again.',
+  'flag-permission-denied': 'You do not have permission to review reports.',
   'channel-override-permission-denied':
     'You do not have permission to edit this channel’s permissions.',
   'invalid-channel-override': 'That channel permission change was invalid.',
   'override-target-not-found': 'That role or user could not be found.',
   'channel-override-failed': 'The server could not update the channel permissions. Please try again.',
+  'channel-access-denied': 'That channel is private and you are not a member.',
   'invalid-category-name': 'That category name is not allowed.',
   'category-creation-failed': 'The server could not create the category. Please try again.',
   'category-rename-failed': 'The server could not rename the category. Please try again.',
@@ -53,6 +80,14 @@ const SERVER_ERROR_MESSAGES: Record<string, string> = {
   'channel-move-failed': 'The server could not move the channel. Please try again.',
   'invalid-channel-topic': 'That channel topic is not allowed.',
   'topic-update-failed': 'The server could not update the channel topic.',
+  'invalid-display-name': 'Display names are limited to 32 characters on one line.',
+  'invalid-bio': 'Bios are limited to 300 characters.',
+  'profile-update-failed': 'The server could not save your profile.',
+  'profile-not-found': 'That user has no profile on this server.',
+  'invalid-retention': 'Message retention must be between 1 and 3650 days.',
+  'invalid-post-roles': 'Choose existing roles to restrict posting to.',
+  'post-roles-update-failed': 'Failed to update who can post in this channel.',
+  'retention-update-failed': 'The server could not update the message retention.',
   'moderation-permission-denied': 'You do not have permission for that moderation action.',
   'moderation-target-not-found': 'That user is not connected to the server.',
   'moderation-target-protected': 'That user cannot be moderated by you.',
@@ -60,16 +95,22 @@ const SERVER_ERROR_MESSAGES: Record<string, string> = {
   'moderation-failed': 'The server could not complete the moderation action.',
   muted: 'You are muted and cannot send messages right now.',
   'not-authenticated': 'You are not authenticated with this server.',
+  'identity-mismatch': 'That request was made on behalf of another user.',
   'invalid-status': 'That status is not recognised.',
   'invalid-message-id': 'That message could not be found.',
   'message-not-found': 'That message no longer exists.',
+  'invalid-permalink': 'That message link is not valid.',
   'message-wrong-channel': 'That message belongs to a different channel.',
   'message-permission-denied': 'You do not have permission to modify that message.',
   'message-delete-failed': 'The server could not delete the message. Please try again.',
+  'purge-confirmation-required': 'Confirm the purge before deleting these messages.',
   'message-edit-failed': 'The server could not edit the message. Please try again.',
   'invalid-message-text': 'That message text is not allowed.',
   'invalid-reaction-action': 'That reaction could not be applied.',
   'invalid-emoji': 'That emoji is not allowed.',
+  'read-marker-failed': 'Could not save your read position.',
+  'receipts-update-failed': 'Could not update read receipts for this channel.',
+  'reaction-rejected': 'This server only accepts its listed emoji as reactions.',
   'reaction-failed': 'The server could not update the reaction. Please try again.',
   'dm-target-not-found': 'That user is not known on this server.',
   'invalid-dm-payload': 'That direct message could not be sent (malformed encrypted payload).',
@@ -110,7 +151,12 @@ const SERVER_ERROR_MESSAGES: Record<string, string> = {
   'wiki-slug-taken': 'A wiki page with that name already exists in this channel.',
   'wiki-page-not-found': 'That wiki page no longer exists.',
   'wiki-page-limit-reached': 'This channel has reached its wiki page limit.',
-  'wiki-save-failed': 'The server could not update the wiki. Please try again.'
+  'wiki-save-failed': 'The server could not update the wiki. Please try again.',
+  'payload-too-complex': 'That message is too complex to send.',
+  'invalid-payload': 'The server could not read that request.',
+  'invalid-schedule': 'Scheduled messages need a time in the future, at most 30 days ahead.',
+  'scheduled-not-found': 'That scheduled message was already sent or cancelled.',
+  'too-many-scheduled': 'You already have the maximum number of scheduled messages. Cancel one first.'
 };
 
 /**
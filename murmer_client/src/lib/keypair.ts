@@ -35,3 +35,22 @@ export function sign(text: string, secret: string): string {
   const sig = nacl.sign.detached(msg, fromBase64(secret));
   return toBase64(sig);
 }
+
+/**
+ * Identity headers for HTTP requests such as `/upload`: the user name, a
+ * timestamp and a signature over `"<METHOD> <path> <timestamp>"`, which the
+ * server checks against the key the name is bound to.
+ */
+export function signedRequestHeaders(
+  user: string,
+  method: string,
+  path: string
+): Record<string, string> {
+  const kp = loadKeyPair();
+  const ts = Date.now().toString();
+  return {
+    'X-Murmer-User': user,
+    'X-Murmer-Timestamp': ts,
+    'X-Murmer-Signature': sign(`${method} ${path} ${ts}`, kp.secretKey)
+  };
+}
@@ -53,6 +53,16 @@ export function fetchLinkPreview(url: string): Promise<LinkPreviewData | null> {
   return promise;
 }
 
+/**
+ * Store a preview the server unfurled for a chat message (`link-preview`)
+ * so rendering the link does not request it again.
+ */
+export function primeLinkPreview(data: LinkPreviewData) {
+  if (data && typeof data.url === 'string' && (data.title || data.description)) {
+    previewCache.set(data.url, Promise.resolve(data));
+  }
+}
+
 /**
  * Resolve a Giphy link to a directly embeddable .gif URL, or null when the
  * link is not a Giphy GIF. Only giphy.com hosts are accepted so this path
@@ -1,27 +1,37 @@
 import { browser } from '$app/environment';
-import {
-  isPermissionGranted,
-  requestPermission,
-  sendNotification
-} from '@tauri-apps/plugin-notification';
+import { isPermissionGranted, requestPermission } from '@tauri-apps/plugin-notification';
+
+/** Set while the user's status is busy; see `stores/status.ts`. */
+let doNotDisturb = false;
+
+export function setDoNotDisturb(value: boolean) {
+  doNotDisturb = value;
+}
 
 export async function notify(title: string, body?: string) {
   if (!browser) return;
 
-  // Prefer Tauri's notification API when available
+  // Prefer the desktop shell's `notify` command when available. It also
+  // drops the notification while the window is focused, and a click on it
+  // brings the window back.
   if ('__TAURI__' in window) {
+    if (doNotDisturb) return;
     let granted = await isPermissionGranted();
     if (!granted) {
       const permission = await requestPermission();
       granted = permission === 'granted';
     }
     if (granted) {
-      sendNotification({ title, body });
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke('notify', { title, body: body ?? '', doNotDisturb }).catch((e) =>
+        console.error('Failed to show notification', e)
+      );
     }
     return;
   }
 
   // Fallback to Web Notifications when running in a browser
+  if (doNotDisturb || document.hasFocus()) return;
   if (typeof Notification === 'undefined') return;
   if (Notification.permission === 'granted') {
     new Notification(title, { body });
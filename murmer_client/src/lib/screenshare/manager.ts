@@ -6,6 +6,7 @@
  * Uses the same signaling infrastructure as voice chat.
  */
 import { chat } from '../stores/chat';
+import { iceConfig, refreshIceServers } from '../voice/iceServers';
 import type { Message, ScreenShareSettings, ScreenSharePeer } from '../types';
 
 const DEFAULT_SETTINGS: ScreenShareSettings = {
@@ -37,10 +38,6 @@ export class ScreenShareManager {
   /** Server-enforced bitrate cap in bits per second (null = no cap). */
   private serverMaxBitrate: number | null = null;
 
-  private config: RTCConfiguration = {
-    iceServers: [{ urls: 'stun:stun.l.google.com:19302' }]
-  };
-
   constructor() {
     this.setupSignaling();
   }
@@ -90,6 +87,7 @@ export class ScreenShareManager {
     this.userName = user;
     this.channelId = channelId;
     this.settings = { ...DEFAULT_SETTINGS, ...settings };
+    await refreshIceServers(user);
 
     try {
       const stream = await navigator.mediaDevices.getDisplayMedia({
@@ -187,7 +185,7 @@ export class ScreenShareManager {
   private async createPeer(userId: string, initiator: boolean): Promise<RTCPeerConnection> {
     if (this.peers[userId]) return this.peers[userId];
 
-    const pc = new RTCPeerConnection(this.config);
+    const pc = new RTCPeerConnection(iceConfig());
     this.peers[userId] = pc;
 
     if (this.localStream) {
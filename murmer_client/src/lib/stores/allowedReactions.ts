@@ -0,0 +1,49 @@
+import { writable } from 'svelte/store';
+import { chat } from './chat';
+import { connection } from './connection';
+import type { Message } from '../types';
+
+/**
+ * Reactions the server accepts, from the `allowed-reactions` frame sent after
+ * authentication and re-broadcast with every custom emoji change. In strict
+ * mode only `unicode` and `custom` (`:name:` shortcodes) may be added; a
+ * permissive server takes any short token and sends no Unicode list.
+ */
+export interface AllowedReactions {
+  strict: boolean;
+  unicode: Set<string>;
+  custom: Set<string>;
+}
+
+const EMPTY: AllowedReactions = { strict: false, unicode: new Set(), custom: new Set() };
+
+function strings(value: unknown): string[] {
+  return Array.isArray(value) ? value.filter((v): v is string => typeof v === 'string') : [];
+}
+
+function createAllowedReactionStore() {
+  const { subscribe, set } = writable<AllowedReactions>(EMPTY);
+
+  chat.on('allowed-reactions', (msg: Message) => {
+    const raw = msg as any;
+    set({
+      strict: raw.strict === true,
+      unicode: new Set(strings(raw.unicode)),
+      custom: new Set(strings(raw.custom))
+    });
+  });
+
+  connection.subscribe((state) => {
+    if (state !== 'connected') set(EMPTY);
+  });
+
+  return { subscribe };
+}
+
+export const allowedReactions = createAllowedReactionStore();
+
+/** Whether the server would accept `emoji` as a new reaction. */
+export function isReactionAllowed(emoji: string, allowed: AllowedReactions): boolean {
+  if (!allowed.strict) return true;
+  return allowed.unicode.has(emoji) || allowed.custom.has(emoji);
+}
@@ -7,6 +7,8 @@ import type { Message } from '../types';
  * URLs relative to the connected server's HTTP base. The server sends an
  * `avatar-snapshot` after authentication and broadcasts `avatar-update`
  * frames on change; setting the own avatar is confirmed by that broadcast.
+ * The snapshot also carries `/identicon/<key>.png` URLs for every user with a
+ * bound key, kept in the separate `identicons` store as the fallback picture.
  */
 
 /** Only accept upload paths; anything else cannot be a valid avatar and
@@ -15,10 +17,24 @@ function isUploadUrl(value: unknown): value is string {
   return typeof value === 'string' && value.startsWith('/files/');
 }
 
+function isIdenticonUrl(value: unknown): value is string {
+  return typeof value === 'string' && /^\/identicon\/[A-Za-z0-9_-]+\.png$/.test(value);
+}
+
+export const identicons = writable<Record<string, string>>({});
+
 function createAvatarStore() {
   const { subscribe, set, update } = writable<Record<string, string>>({});
 
   chat.on('avatar-snapshot', (msg: Message) => {
+    const fallbacks = (msg as any).identicons;
+    if (fallbacks && typeof fallbacks === 'object') {
+      const normalized: Record<string, string> = {};
+      for (const [user, url] of Object.entries(fallbacks as Record<string, unknown>)) {
+        if (isIdenticonUrl(url)) normalized[user] = url;
+      }
+      identicons.set(normalized);
+    }
     const raw = (msg as any).avatars;
     if (!raw || typeof raw !== 'object') return;
     const normalized: Record<string, string> = {};
@@ -2,6 +2,10 @@ import { writable } from 'svelte/store';
 import { chat } from './chat';
 import type { Message, ChannelInfo } from '../types';
 
+function parsePostRoles(raw: unknown): number[] | null {
+  return Array.isArray(raw) ? raw.filter((id): id is number => typeof id === 'number') : null;
+}
+
 function createChannelStore() {
   const { subscribe, set, update } = writable<ChannelInfo[]>([]);
 
@@ -15,7 +19,12 @@ function createChannelStore() {
           name: typeof item.name === 'string' ? item.name : '',
           categoryId: typeof item.categoryId === 'number' ? item.categoryId : null,
           position: typeof item.position === 'number' ? item.position : 0,
-          private: item.private === true
+          private: item.private === true,
+          postRoles: parsePostRoles(item.postRoles),
+          slowModeSeconds: typeof item.slowModeSeconds === 'number' ? item.slowModeSeconds : 0,
+          receiptsEnabled: item.receiptsEnabled === true,
+          maxSubscribers: typeof item.maxSubscribers === 'number' ? item.maxSubscribers : null,
+          isDefault: item.isDefault === true
         }));
       set(items);
     }
@@ -37,6 +46,42 @@ function createChannelStore() {
     }
   });
 
+  chat.on('channel-post-roles', (msg: Message) => {
+    const raw = msg as any;
+    if (typeof raw.channelId !== 'number') return;
+    const postRoles = parsePostRoles(raw.roleIds);
+    update((chs) => chs.map((c) => (c.id === raw.channelId ? { ...c, postRoles } : c)));
+  });
+
+  chat.on('slow-mode-update', (msg: Message) => {
+    const raw = msg as any;
+    if (typeof raw.channelId !== 'number' || typeof raw.seconds !== 'number') return;
+    update((chs) =>
+      chs.map((c) => (c.id === raw.channelId ? { ...c, slowModeSeconds: raw.seconds } : c))
+    );
+  });
+
+  chat.on('channel-receipts', (msg: Message) => {
+    const raw = msg as any;
+    if (typeof raw.channelId !== 'number' || typeof raw.enabled !== 'boolean') return;
+    update((chs) =>
+      chs.map((c) => (c.id === raw.channelId ? { ...c, receiptsEnabled: raw.enabled } : c))
+    );
+  });
+
+  chat.on('channel-max-subscribers', (msg: Message) => {
+    const raw = msg as any;
+    if (typeof raw.channelId !== 'number') return;
+    const maxSubscribers = typeof raw.max === 'number' ? raw.max : null;
+    update((chs) => chs.map((c) => (c.id === raw.channelId ? { ...c, maxSubscribers } : c)));
+  });
+
+  chat.on('channel-rename', (msg: Message) => {
+    const raw = msg as any;
+    if (typeof raw.channelId !== 'number' || typeof raw.name !== 'string') return;
+    update((chs) => chs.map((c) => (c.id === raw.channelId ? { ...c, name: raw.name } : c)));
+  });
+
   chat.on('channel-remove', (msg: Message) => {
     const id = (msg as any).channelId;
     if (typeof id === 'number') {
@@ -61,6 +106,13 @@ function createChannelStore() {
     }
   });
 
+  /* Deleting a category leaves its channels in place, uncategorized. */
+  chat.on('category-remove', (msg: Message) => {
+    const id = (msg as any).id;
+    if (typeof id !== 'number') return;
+    update((chs) => chs.map((c) => (c.categoryId === id ? { ...c, categoryId: null } : c)));
+  });
+
   chat.on('channel-reorder', (msg: Message) => {
     const raw = msg as any;
     if (raw.voice === true) return;
@@ -89,6 +141,11 @@ function createChannelStore() {
     chat.sendRaw({ type: 'delete-channel', channelId });
   }
 
+  /** Rename a channel; its history stays with it. */
+  function rename(channelId: number, name: string) {
+    chat.sendRaw({ type: 'rename-channel', channelId, name });
+  }
+
   function move(channelId: number, categoryId: number | null, voice = false) {
     chat.sendRaw({ type: 'move-channel', channelId, categoryId, voice });
   }
@@ -99,7 +156,39 @@ function createChannelStore() {
     chat.sendRaw({ type: 'reorder-channels', categoryId, order, voice });
   }
 
-  return { subscribe, set, create, remove, move, reorder };
+  /** Restrict posting in a channel to `roleIds`, or lift the restriction with `null`. */
+  function setPostRoles(channelId: number, roleIds: number[] | null) {
+    chat.sendRaw({ type: 'set-channel-post-roles', channelId, roleIds });
+  }
+
+  /** Make members wait `seconds` between posts in a channel; 0 turns slow mode off. */
+  function setSlowMode(channelId: number, seconds: number) {
+    chat.sendRaw({ type: 'set-slow-mode', channelId, seconds });
+  }
+
+  /** Turn read receipts for a channel on or off. */
+  function setReceipts(channelId: number, enabled: boolean) {
+    chat.sendRaw({ type: 'set-channel-receipts', channelId, enabled });
+  }
+
+  /** Cap how many connections may have a channel open at once; `null` lifts the cap. */
+  function setMaxSubscribers(channelId: number, max: number | null) {
+    chat.sendRaw({ type: 'set-channel-max-subscribers', channelId, max });
+  }
+
+  return {
+    subscribe,
+    set,
+    create,
+    remove,
+    rename,
+    move,
+    reorder,
+    setPostRoles,
+    setSlowMode,
+    setReceipts,
+    setMaxSubscribers
+  };
 }
 
 export const channels = createChannelStore();
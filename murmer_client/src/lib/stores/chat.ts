@@ -14,6 +14,7 @@ import { pinned } from './pins';
 import { peerKeys } from './peerKeys';
 import { decryptDm, encryptDm } from '../dm-crypto';
 import { loadKeyPair } from '../keypair';
+import { primeLinkPreview, type LinkPreviewData } from '../link-preview';
 
 /** Maximum number of search results to request from server */
 const MAX_SEARCH_RESULTS = 200;
@@ -170,6 +171,13 @@ function createChatStore() {
         break;
       }
 
+      case 'link-preview': {
+        if (typeof msg.preview === 'object' && msg.preview !== null) {
+          primeLinkPreview(msg.preview as LinkPreviewData);
+        }
+        break;
+      }
+
       case 'message-edited': {
         const messageId = msg.id as number | undefined;
         if (typeof messageId === 'number' && typeof msg.text === 'string') {
@@ -184,12 +192,41 @@ function createChatStore() {
 
       case 'message-deleted': {
         const messageId = (msg.id as number | undefined) ?? (msg.messageId as number | undefined);
-        if (typeof messageId === 'number') {
+        if (typeof messageId !== 'number') break;
+        if (msg.tombstone === true) {
+          update((messages) =>
+            messages.map((m) =>
+              m.id === messageId
+                ? {
+                    type: m.type,
+                    id: m.id,
+                    channelId: m.channelId,
+                    threadId: m.threadId,
+                    time: m.time,
+                    timestamp: m.timestamp,
+                    reactions: m.reactions,
+                    deleted: true
+                  }
+                : m
+            )
+          );
+        } else {
           update((messages) => messages.filter((m) => m.id !== messageId));
         }
         break;
       }
 
+      case 'messages-deleted': {
+        const ids = Array.isArray(msg.ids) ? (msg.ids as unknown[]) : [];
+        const removed = new Set(ids.filter((id): id is number => typeof id === 'number'));
+        if (removed.size > 0) {
+          update((messages) =>
+            messages.filter((m) => typeof m.id !== 'number' || !removed.has(m.id))
+          );
+        }
+        break;
+      }
+
       case 'search-results': {
         const payload = msg as any;
         const requestId = Number(payload.requestId);
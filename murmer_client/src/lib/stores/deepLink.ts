@@ -0,0 +1,86 @@
+import { writable, get } from 'svelte/store';
+import { goto } from '$app/navigation';
+import { servers, selectedServer } from './servers';
+import { session } from './session';
+import { dialogs } from './dialogs';
+import { normalizeServerUrl } from '../utils';
+
+/**
+ * `murmer://join?server=wss://...&channel=general` links in the Tauri shell.
+ * The Rust side (src-tauri/src/deep_link.rs) validates the link, brings the
+ * window forward and emits `deep-link-join`; here the server is selected and
+ * the chat view is (re)opened on it. Any web page can fire such a link, so a
+ * server not already in the list is only added once the user confirms. The channel is
+ * left in `pendingJoinChannel` for the chat page to switch to once the
+ * channel list has arrived. No-op in the plain browser.
+ */
+
+const isTauri = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
+
+interface JoinLink {
+  server: string;
+  channel: string | null;
+}
+
+/** Channel name from the last join link, cleared by the chat page. */
+export const pendingJoinChannel = writable<string | null>(null);
+
+async function join(link: JoinLink) {
+  const url = normalizeServerUrl(link.server);
+  if (!servers.get(url)) {
+    const trusted = await dialogs.confirm({
+      title: 'Add this server?',
+      message: `A link wants to add ${url} and connect to it with your identity. Only continue if you trust this server.`,
+      confirmLabel: 'Add server'
+    });
+    if (!trusted) return;
+    servers.add({ url, name: url });
+  }
+  pendingJoinChannel.set(link.channel);
+  const switching = get(selectedServer) !== url;
+  selectedServer.set(url);
+  if (!get(session).user) {
+    // The login page continues to the server list with this one selected.
+    await goto('/login');
+    return;
+  }
+  if (window.location.pathname === '/chat') {
+    // Same server: the chat page just switches channel. Another server:
+    // leave and come back so the page reconnects from scratch.
+    if (!switching) return;
+    await goto('/servers', { replaceState: true });
+  }
+  await goto('/chat');
+}
+
+/** Act on the join link waiting in the shell, if any. */
+async function collect() {
+  const { invoke } = await import('@tauri-apps/api/core');
+  const link = await invoke<JoinLink | null>('take_deep_link_join');
+  if (link) await join(link);
+}
+
+/**
+ * Handle the link the app was launched with, then every link opened while it
+ * runs. Returns a function that stops listening.
+ */
+export function listenDeepLinks(): () => void {
+  if (!isTauri) return () => {};
+  let unlisten: (() => void) | null = null;
+  let stopped = false;
+  const report = (e: unknown) => console.error('Failed to handle deep link', e);
+  collect().catch(report);
+  import('@tauri-apps/api/event')
+    .then(async ({ listen }) => {
+      // The payload is also kept by the shell; taking it from there means a
+      // link that arrived during startup is not handled twice.
+      const stop = await listen('deep-link-join', () => void collect().catch(report));
+      if (stopped) stop();
+      else unlisten = stop;
+    })
+    .catch(report);
+  return () => {
+    stopped = true;
+    unlisten?.();
+  };
+}
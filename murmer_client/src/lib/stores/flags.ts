@@ -0,0 +1,79 @@
+import { writable } from 'svelte/store';
+import { chat } from './chat';
+import type { Message } from '../types';
+
+/**
+ * Message reports awaiting moderator review. Only connections holding Manage
+ * messages receive `flag-list`, `flag-notice` and `flag-resolved`, so for
+ * everyone else this store stays empty; reporting works for all users.
+ */
+export interface MessageFlag {
+  messageId: number;
+  channelId: number | null;
+  reporter: string;
+  reason: string;
+  at: string;
+  author?: string;
+  text?: string;
+}
+
+function parseFlag(raw: unknown): MessageFlag | null {
+  if (typeof raw !== 'object' || raw === null) return null;
+  const flag = raw as Record<string, unknown>;
+  if (typeof flag.messageId !== 'number' || typeof flag.reporter !== 'string') return null;
+  return {
+    messageId: flag.messageId,
+    channelId: typeof flag.channelId === 'number' ? flag.channelId : null,
+    reporter: flag.reporter,
+    reason: typeof flag.reason === 'string' ? flag.reason : '',
+    at: typeof flag.at === 'string' ? flag.at : new Date().toISOString(),
+    author: typeof flag.author === 'string' ? flag.author : undefined,
+    text: typeof flag.text === 'string' ? flag.text : undefined
+  };
+}
+
+function createFlagStore() {
+  const { subscribe, set, update } = writable<MessageFlag[]>([]);
+
+  chat.on('flag-list', (msg: Message) => {
+    const list = (msg as any).flags;
+    if (!Array.isArray(list)) return;
+    set(list.map(parseFlag).filter((flag): flag is MessageFlag => flag !== null));
+  });
+
+  chat.on('flag-notice', (msg: Message) => {
+    const flag = parseFlag(msg);
+    if (!flag) return;
+    update((flags) =>
+      flags.some((f) => f.messageId === flag.messageId && f.reporter === flag.reporter)
+        ? flags
+        : [...flags, flag]
+    );
+  });
+
+  chat.on('flag-resolved', (msg: Message) => {
+    const id = (msg as any).messageId;
+    if (typeof id === 'number') {
+      update((flags) => flags.filter((f) => f.messageId !== id));
+    }
+  });
+
+  /** Report a message to the moderators. */
+  function report(messageId: number, reason: string) {
+    chat.sendRaw({ type: 'flag-message', messageId, reason: reason.trim() });
+  }
+
+  /** Ask the server for the open flags (moderators only). */
+  function refresh() {
+    chat.sendRaw({ type: 'list-flags' });
+  }
+
+  /** Close every open flag on a message; the broadcast updates the store. */
+  function resolve(messageId: number) {
+    chat.sendRaw({ type: 'resolve-flag', messageId });
+  }
+
+  return { subscribe, report, refresh, resolve };
+}
+
+export const flags = createFlagStore();
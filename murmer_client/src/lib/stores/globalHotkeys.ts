@@ -36,6 +36,11 @@ type ActionCallbacks = Partial<Record<HotkeyActionId, () => void>>;
 
 let callbacks: ActionCallbacks = {};
 let suspended = false;
+/**
+ * Accelerators registered by this module. Only these are released on resync:
+ * the push-to-talk shortcut is registered by the Rust side and must survive.
+ */
+let registered: string[] = [];
 
 // register/unregister are async; funnel every resync through one promise
 // chain so overlapping updates can't interleave their plugin calls.
@@ -49,8 +54,8 @@ function resync() {
 }
 
 async function applyBindings() {
-  const { register, unregisterAll } = await import('@tauri-apps/plugin-global-shortcut');
-  await unregisterAll();
+  const { register, unregister } = await import('@tauri-apps/plugin-global-shortcut');
+  if (registered.length > 0) await unregister(registered.splice(0));
   if (suspended || !get(globalHotkeysEnabled)) return;
 
   const bindings = get(hotkeys);
@@ -63,9 +68,11 @@ async function applyBindings() {
     // would swallow that key in every application — keep it in-app only.
     if (!firesWhileTyping(combo)) continue;
     try {
-      await register(comboToAccelerator(combo), (event) => {
+      const accelerator = comboToAccelerator(combo);
+      await register(accelerator, (event) => {
         if (event.state === 'Pressed') callback();
       });
+      registered.push(accelerator);
     } catch (e) {
       console.warn(`Could not register global hotkey "${combo}":`, e);
     }
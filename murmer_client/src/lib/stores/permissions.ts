@@ -2,7 +2,7 @@ import { derived } from 'svelte/store';
 import { roleDefinitions } from './roleDefinitions';
 import { userRoleIds } from './roles';
 import { session } from './session';
-import { computeMask, computeTopPosition, hasPermission } from '../chat/permissions';
+import { computeMask, computeTopPosition, hasPermission, PERMISSIONS } from '../chat/permissions';
 
 /** The current user's effective permission bitmask. */
 export const myPermissions = derived(
@@ -27,3 +27,19 @@ export const myTopPosition = derived(
  * the user's roles grant `flag`. Purely cosmetic — the server re-checks.
  */
 export const can = derived(myPermissions, (mask) => (flag: number) => hasPermission(mask, flag));
+
+/**
+ * Reactive posting check for role-gated channels: `$canPostIn(postRoles)` is
+ * true when the channel is unrestricted, the user is an administrator, or they
+ * hold one of the listed roles. Purely cosmetic — the server re-checks.
+ */
+export const canPostIn = derived(
+  [myPermissions, userRoleIds, session],
+  ([mask, assignments, sess]) =>
+    (postRoles: number[] | null | undefined) => {
+      if (!postRoles) return true;
+      if ((mask & PERMISSIONS.ADMINISTRATOR) !== 0) return true;
+      const ids = sess.user ? (assignments[sess.user] ?? []) : [];
+      return ids.some((id) => postRoles.includes(id));
+    }
+);
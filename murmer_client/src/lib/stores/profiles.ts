@@ -0,0 +1,65 @@
+import { writable } from 'svelte/store';
+import { chat } from './chat';
+import type { Message } from '../types';
+
+/**
+ * Per-user display names and bios, keyed by user name. The server sends a
+ * `profile-snapshot` after authentication, broadcasts `profile-update` on
+ * change and answers `get-profile` with a `profile` frame. Avatars have their
+ * own store. Empty strings mean "unset"; show the user name instead.
+ */
+export interface Profile {
+  displayName: string;
+  bio: string;
+}
+
+function parseProfile(raw: unknown): Profile | null {
+  if (typeof raw !== 'object' || raw === null) return null;
+  const card = raw as Record<string, unknown>;
+  return {
+    displayName: typeof card.displayName === 'string' ? card.displayName : '',
+    bio: typeof card.bio === 'string' ? card.bio : ''
+  };
+}
+
+function createProfileStore() {
+  const { subscribe, set, update } = writable<Record<string, Profile>>({});
+
+  function store(user: unknown, raw: unknown) {
+    const profile = parseProfile(raw);
+    if (typeof user !== 'string' || !profile) return;
+    update((map) => {
+      if (profile.displayName || profile.bio) return { ...map, [user]: profile };
+      const { [user]: _removed, ...rest } = map;
+      return rest;
+    });
+  }
+
+  chat.on('profile-snapshot', (msg: Message) => {
+    const raw = (msg as any).profiles;
+    if (!raw || typeof raw !== 'object') return;
+    const normalized: Record<string, Profile> = {};
+    for (const [user, card] of Object.entries(raw as Record<string, unknown>)) {
+      const profile = parseProfile(card);
+      if (profile) normalized[user] = profile;
+    }
+    set(normalized);
+  });
+
+  chat.on('profile-update', (msg: Message) => store(msg.user, msg));
+  chat.on('profile', (msg: Message) => store(msg.user, msg));
+
+  /** Update the own display name and/or bio; omitted fields stay as they are. */
+  function setSelf(changes: Partial<Profile>) {
+    chat.sendRaw({ type: 'set-profile', ...changes });
+  }
+
+  /** Ask the server for one user's full card. */
+  function fetch(user: string) {
+    chat.sendRaw({ type: 'get-profile', user });
+  }
+
+  return { subscribe, setSelf, fetch };
+}
+
+export const profiles = createProfileStore();
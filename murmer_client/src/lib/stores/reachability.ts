@@ -0,0 +1,56 @@
+import { writable } from 'svelte/store';
+
+/**
+ * Server reachability as reported by the Tauri shell's connection monitor
+ * (src-tauri/src/monitor.rs). While the chat connection is down the shell
+ * probes the server with exponential backoff and emits `connection-state`
+ * events; the chat page reconnects as soon as one says `online`. In the plain
+ * browser there is no monitor and the store stays `null`.
+ */
+
+const isTauri = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
+
+export interface Reachability {
+  state: 'connecting' | 'online' | 'offline';
+  url: string;
+  /** Failed probes in a row. */
+  attempt: number;
+  /** When offline, milliseconds until the next probe. */
+  retryInMs?: number;
+}
+
+/** Latest monitor report, or `null` while no monitor is running. */
+export const reachability = writable<Reachability | null>(null);
+
+/**
+ * Start the shell's monitor for `url` and call `onOnline` whenever it finds
+ * the server reachable. Returns a function that stops the monitor.
+ */
+export function monitorServer(url: string, onOnline: () => void): () => void {
+  if (!isTauri) return () => {};
+  let unlisten: (() => void) | null = null;
+  let stopped = false;
+  Promise.all([import('@tauri-apps/api/event'), import('@tauri-apps/api/core')])
+    .then(async ([{ listen }, { invoke }]) => {
+      const stop = await listen<Reachability>('connection-state', ({ payload }) => {
+        if (stopped || payload.url !== url) return;
+        reachability.set(payload);
+        if (payload.state === 'online') onOnline();
+      });
+      if (stopped) {
+        stop();
+        return;
+      }
+      unlisten = stop;
+      await invoke('start_connection_monitor', { url });
+    })
+    .catch((e) => console.error('Failed to start connection monitor', e));
+  return () => {
+    stopped = true;
+    unlisten?.();
+    reachability.set(null);
+    import('@tauri-apps/api/core')
+      .then(({ invoke }) => invoke('stop_connection_monitor'))
+      .catch((e) => console.error('Failed to stop connection monitor', e));
+  };
+}
@@ -0,0 +1,47 @@
+import { writable } from 'svelte/store';
+import { chat } from './chat';
+import { connection } from './connection';
+import type { Message } from '../types';
+
+/**
+ * "Seen by" lists for the user's own messages, keyed by message id. Only
+ * channels with read receipts enabled report read positions (`mark-read`);
+ * the server batches the resulting `read-receipt-update` frames every few
+ * seconds and sends each author only the receipts for their own messages.
+ */
+function createReceiptStore() {
+  const { subscribe, set, update } = writable<Record<number, string[]>>({});
+  /** channelId -> newest message id already reported, to skip repeats. */
+  let reported: Record<number, number> = {};
+
+  chat.on('read-receipt-update', (msg: Message) => {
+    const list = (msg as any).receipts;
+    if (!Array.isArray(list)) return;
+    update((current) => {
+      const next = { ...current };
+      for (const entry of list) {
+        if (typeof entry?.messageId !== 'number' || !Array.isArray(entry.readers)) continue;
+        next[entry.messageId] = entry.readers.filter((r: unknown) => typeof r === 'string');
+      }
+      return next;
+    });
+  });
+
+  connection.subscribe((state) => {
+    if (state !== 'connected') {
+      set({});
+      reported = {};
+    }
+  });
+
+  /** Report that everything up to `messageId` in a channel has been read. */
+  function markRead(channelId: number, messageId: number) {
+    if ((reported[channelId] ?? 0) >= messageId) return;
+    reported[channelId] = messageId;
+    chat.sendRaw({ type: 'mark-read', channelId, messageId });
+  }
+
+  return { subscribe, markRead };
+}
+
+export const readReceipts = createReceiptStore();
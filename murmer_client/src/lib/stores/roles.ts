@@ -52,3 +52,24 @@ export const roles = derived(
     return map;
   }
 );
+
+/**
+ * Each user's rank (username → highest assigned role position), for ordering
+ * the member list. Users without roles are absent and rank lowest.
+ */
+export const userRanks = derived(
+  [roleDefinitions, userRoleIds],
+  ([defs, assignments]) => {
+    const positions = new Map(defs.map((d) => [d.id, d.position]));
+    const ranks: Record<string, number> = {};
+    for (const [user, ids] of Object.entries(assignments)) {
+      let best: number | undefined;
+      for (const id of ids) {
+        const position = positions.get(id);
+        if (position !== undefined && (best === undefined || position > best)) best = position;
+      }
+      if (best !== undefined) ranks[user] = best;
+    }
+    return ranks;
+  }
+);
@@ -0,0 +1,71 @@
+import { writable } from 'svelte/store';
+import { chat } from './chat';
+import type { Message } from '../types';
+
+/**
+ * The current user's messages queued for later delivery ("schedule send").
+ * The server acknowledges each scheduled message and answers `list-scheduled`
+ * with the full queue; delivered messages drop out on the next refresh.
+ */
+export interface ScheduledMessage {
+  token: string;
+  channelId: number | null;
+  scheduleAt: string;
+  text: string;
+}
+
+function parseScheduled(raw: unknown): ScheduledMessage | null {
+  if (typeof raw !== 'object' || raw === null) return null;
+  const item = raw as Record<string, unknown>;
+  if (typeof item.token !== 'string' || typeof item.scheduleAt !== 'string') return null;
+  return {
+    token: item.token,
+    channelId: typeof item.channelId === 'number' ? item.channelId : null,
+    scheduleAt: item.scheduleAt,
+    text: typeof item.text === 'string' ? item.text : ''
+  };
+}
+
+function createScheduledStore() {
+  const { subscribe, set, update } = writable<ScheduledMessage[]>([]);
+
+  chat.on('scheduled-list', (msg: Message) => {
+    const list = (msg as any).messages;
+    if (!Array.isArray(list)) return;
+    set(list.map(parseScheduled).filter((item): item is ScheduledMessage => item !== null));
+  });
+
+  chat.on('scheduled-cancelled', (msg: Message) => {
+    const token = (msg as any).token;
+    if (typeof token === 'string') {
+      update((items) => items.filter((item) => item.token !== token));
+    }
+  });
+
+  /** Queue `text` for delivery to the current channel at `at`. */
+  function schedule(user: string, text: string, at: Date) {
+    const now = new Date();
+    chat.sendRaw({
+      type: 'chat',
+      user,
+      text,
+      time: now.toLocaleTimeString(),
+      timestamp: now.toISOString(),
+      scheduleAt: at.toISOString()
+    });
+  }
+
+  /** Ask the server for the pending queue. */
+  function refresh() {
+    chat.sendRaw({ type: 'list-scheduled' });
+  }
+
+  /** Cancel a pending message; the server confirms with `scheduled-cancelled`. */
+  function cancel(token: string) {
+    chat.sendRaw({ type: 'cancel-scheduled', token });
+  }
+
+  return { subscribe, schedule, refresh, cancel };
+}
+
+export const scheduledMessages = createScheduledStore();
@@ -5,14 +5,16 @@ import { connection } from './connection';
 import { selectedServer } from './servers';
 import { dialogs } from './dialogs';
 import type { Message } from '../types';
+import { APP_VERSION, PROTOCOL_VERSION, compareVersions } from '../version';
 
 /**
  * Server identity (name, description, welcome message, icon) configured by
  * Admins/Owners in the server dashboard. The server sends a `server-identity`
  * frame after authentication and broadcasts it on every change; editing is
- * role-checked server-side. A trimmed copy is cached per server URL in
- * localStorage so the server selection page can show the identity of servers
- * the user is not currently connected to.
+ * role-checked server-side. The frame also carries the server version (unless
+ * the server hides it) so an older server can be flagged. A trimmed copy is
+ * cached per server URL in localStorage so the server selection page can show
+ * the identity of servers the user is not currently connected to.
  */
 
 export interface ServerIdentity {
@@ -21,6 +23,8 @@ export interface ServerIdentity {
   welcomeMessage: string;
   /** `/files/<key>` upload URL, or null when no icon is configured. */
   icon: string | null;
+  /** Server version, or null when the server hides it. */
+  version: string | null;
 }
 
 /** Cached subset shown on the server selection page. */
@@ -51,7 +55,8 @@ function parseIdentity(msg: Message): ServerIdentity {
     // Only accept upload paths; anything else cannot be a valid icon and
     // must not end up concatenated onto the server's HTTP base URL.
     icon:
-      typeof payload.icon === 'string' && payload.icon.startsWith('/files/') ? payload.icon : null
+      typeof payload.icon === 'string' && payload.icon.startsWith('/files/') ? payload.icon : null,
+    version: typeof payload.version === 'string' ? payload.version : null
   };
 }
 
@@ -62,6 +67,10 @@ function createServerIdentityStores() {
 
   chat.on('server-identity', (msg: Message) => {
     const parsed = parseIdentity(msg);
+    if (parsed.version && compareVersions(parsed.version, APP_VERSION) < 0) {
+      // Older servers may not understand frames this client sends.
+      console.warn(`Server runs ${parsed.version}, older than this client (${APP_VERSION})`);
+    }
     identity.set(parsed);
     const url = get(selectedServer);
     if (!url) return;
@@ -77,6 +86,16 @@ function createServerIdentityStores() {
     });
   });
 
+  // Sent after presence with the negotiated version and the server's range.
+  chat.on('protocol', (msg: Message) => {
+    const payload = msg as any;
+    if (typeof payload.maxVersion === 'number' && payload.maxVersion < PROTOCOL_VERSION) {
+      console.warn(
+        `Server speaks protocol ${payload.maxVersion}, older than this client (${PROTOCOL_VERSION})`
+      );
+    }
+  });
+
   // The welcome message is delivered once, to members connecting to this
   // server for the very first time.
   chat.on('welcome', (msg: Message) => {
@@ -1,6 +1,7 @@
 import { get, writable } from 'svelte/store';
 import { chat } from './chat';
 import { session } from './session';
+import { setDoNotDisturb } from '../notify';
 import type { Message, UserStatus } from '../types';
 
 export const USER_STATUS_VALUES = ['online', 'away', 'busy', 'offline'] as const;
@@ -27,6 +28,45 @@ function normalizeStatus(value: unknown): UserStatus | null {
   return USER_STATUS_SET.has(lowered) ? lowered : null;
 }
 
+function parseLastSeen(value: unknown): string | null {
+  return typeof value === 'string' && !Number.isNaN(Date.parse(value)) ? value : null;
+}
+
+/**
+ * When each offline user was last active (RFC 3339), from `user-list`
+ * directory entries and `status-update`. The server omits connected users.
+ */
+function createLastSeenStore() {
+  const { subscribe, update } = writable<Record<string, string>>({});
+
+  chat.on('user-list', (msg: Message) => {
+    const raw = (msg as any).users;
+    if (!Array.isArray(raw)) return;
+    update((map) => {
+      const next = { ...map };
+      for (const entry of raw) {
+        if (!entry || typeof entry.name !== 'string') continue;
+        const seen = parseLastSeen(entry.lastSeen);
+        if (seen) next[entry.name] = seen;
+        else delete next[entry.name];
+      }
+      return next;
+    });
+  });
+
+  chat.on('status-update', (msg: Message) => {
+    const user = typeof msg.user === 'string' ? msg.user : null;
+    if (!user) return;
+    const seen = parseLastSeen((msg as any).lastSeen);
+    update((map) => {
+      const { [user]: _previous, ...rest } = map;
+      return seen ? { ...rest, [user]: seen } : rest;
+    });
+  });
+
+  return { subscribe };
+}
+
 function createStatusStore() {
   const { subscribe, set, update } = writable<Record<string, UserStatus>>({});
 
@@ -72,3 +112,11 @@ function createStatusStore() {
 }
 
 export const statuses = createStatusStore();
+
+// Busy doubles as "Do Not Disturb": desktop notifications are suppressed.
+statuses.subscribe((map) => {
+  const user = get(session).user;
+  setDoNotDisturb(user ? map[user] === 'busy' : false);
+});
+
+export const lastSeen = createLastSeenStore();
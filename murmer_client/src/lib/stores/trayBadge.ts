@@ -0,0 +1,23 @@
+import { derived } from 'svelte/store';
+import { unread } from './unread';
+import { dmUnreadTotal } from './dm';
+
+/**
+ * Mirrors the unread total (channels plus direct messages) into the desktop
+ * tray icon's badge and tooltip. No-op outside the Tauri shell; the shell
+ * clears the badge itself whenever the main window gains focus.
+ */
+
+const isTauri = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
+
+export const unreadTotal = derived([unread, dmUnreadTotal], ([$unread, $dm]) =>
+  Object.values($unread).reduce((sum, info) => sum + info.count, $dm)
+);
+
+if (isTauri) {
+  unreadTotal.subscribe((count) => {
+    import('@tauri-apps/api/core')
+      .then(({ invoke }) => invoke('set_unread_count', { count }))
+      .catch((e) => console.error('Failed to update tray badge', e));
+  });
+}
@@ -1,19 +1,106 @@
-import { writable, derived } from 'svelte/store';
+import { writable, derived, get } from 'svelte/store';
 import { chat } from './chat';
+import { connection } from './connection';
 import type { Message } from '../types';
 import { onlineUsers } from './online';
 
-function createAllUserStore() {
-  const { subscribe, set } = writable<string[]>([]);
-  chat.on('online-users', (msg: Message) => {
-    if (Array.isArray((msg as any).all)) {
-      set((msg as any).all as string[]);
+/** Users requested per `list-users` page (the server caps pages at 100). */
+const DIRECTORY_PAGE_SIZE = 50;
+const MAX_DIRECTORY_PAGE = 100;
+/** Presence changes arrive in bursts; refresh the directory once they settle. */
+const REFRESH_DEBOUNCE_MS = 1000;
+
+export interface DirectoryUser {
+  name: string;
+  online: boolean;
+  status: string;
+  roleIds: number[];
+}
+
+interface DirectoryState {
+  users: DirectoryUser[];
+  total: number;
+}
+
+function parseUser(raw: unknown): DirectoryUser | null {
+  if (typeof raw !== 'object' || raw === null) return null;
+  const entry = raw as Record<string, unknown>;
+  if (typeof entry.name !== 'string') return null;
+  return {
+    name: entry.name,
+    online: entry.online === true,
+    status: typeof entry.status === 'string' ? entry.status : 'offline',
+    roleIds: Array.isArray(entry.roleIds)
+      ? entry.roleIds.filter((id): id is number => typeof id === 'number')
+      : []
+  };
+}
+
+/**
+ * The server no longer ships every known user with `online-users`; the
+ * directory is paged in with `list-users` instead. This store holds the
+ * pages loaded so far and re-requests them when presence changes, so the
+ * offline list stays current without downloading the whole roster.
+ */
+function createDirectoryStore() {
+  const store = writable<DirectoryState>({ users: [], total: 0 });
+  let refreshTimer: ReturnType<typeof setTimeout> | null = null;
+
+  function request(offset: number, limit: number) {
+    chat.sendRaw({ type: 'list-users', query: '', offset, limit });
+  }
+
+  chat.on('user-list', (msg: Message) => {
+    const raw = msg as any;
+    if (raw.query) return;
+    const offset = typeof raw.offset === 'number' ? raw.offset : 0;
+    const total = typeof raw.total === 'number' ? raw.total : 0;
+    const page = Array.isArray(raw.users)
+      ? raw.users.map(parseUser).filter((u: DirectoryUser | null): u is DirectoryUser => u !== null)
+      : [];
+    store.update((current) => {
+      const kept = offset === 0 ? [] : current.users.slice(0, offset);
+      return { users: [...kept, ...page], total };
+    });
+  });
+
+  chat.on('online-users', () => {
+    if (refreshTimer) clearTimeout(refreshTimer);
+    refreshTimer = setTimeout(() => {
+      refreshTimer = null;
+      const loaded = get(store).users.length;
+      request(0, Math.min(Math.max(loaded, DIRECTORY_PAGE_SIZE), MAX_DIRECTORY_PAGE));
+    }, REFRESH_DEBOUNCE_MS);
+  });
+
+  connection.subscribe((state) => {
+    if (state !== 'connected') {
+      if (refreshTimer) clearTimeout(refreshTimer);
+      refreshTimer = null;
+      store.set({ users: [], total: 0 });
     }
   });
-  return { subscribe };
+
+  return {
+    subscribe: store.subscribe,
+    /** Request the next page of users, if any remain. */
+    loadMore() {
+      const { users, total } = get(store);
+      if (users.length < total) request(users.length, DIRECTORY_PAGE_SIZE);
+    }
+  };
 }
 
-export const allUsers = createAllUserStore();
-export const offlineUsers = derived([allUsers, onlineUsers], ([$all, $online]) =>
-  $all.filter((u) => !$online.includes(u))
+export const directory = createDirectoryStore();
+export const offlineUsers = derived([directory, onlineUsers], ([$directory, $online]) =>
+  $directory.users.filter((u) => !$online.includes(u.name)).map((u) => u.name)
+);
+/** Offline users on the server, including those not loaded yet. */
+export const offlineCount = derived([directory, onlineUsers], ([$directory, $online]) =>
+  Math.max($directory.total - $online.length, 0)
+);
+/** Whether more directory pages can be requested. */
+export const hasMoreUsers = derived(
+  directory,
+  ($directory) => $directory.users.length < $directory.total
 );
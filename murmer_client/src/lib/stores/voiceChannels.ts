@@ -89,6 +89,13 @@ function createVoiceChannelStore() {
     );
   });
 
+  /* Deleting a category leaves its channels in place, uncategorized. */
+  chat.on('category-remove', (msg: Message) => {
+    const id = (msg as any).id;
+    if (typeof id !== 'number') return;
+    update((chs) => chs.map((c) => (c.categoryId === id ? { ...c, categoryId: null } : c)));
+  });
+
   chat.on('channel-reorder', (msg: Message) => {
     const raw = msg as any;
     if (raw.voice !== true) return;
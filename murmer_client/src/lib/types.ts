@@ -35,6 +35,8 @@ export interface Message {
   ciphertext?: string;
   /** Set when a DM ciphertext failed to decrypt; render a placeholder. */
   decryptFailed?: boolean;
+  /** Soft-deleted message kept as a placeholder (tombstone). */
+  deleted?: boolean;
   [key: string]: unknown;
 }
 
@@ -120,6 +122,16 @@ export interface ChannelInfo {
   position: number;
   /** True when the channel restricts View for @everyone (shows a lock). */
   private?: boolean;
+  /** Roles allowed to post here (administrators always can); absent lets everyone post. */
+  postRoles?: number[] | null;
+  /** Seconds members must wait between posts; 0 or absent means slow mode is off. */
+  slowModeSeconds?: number;
+  /** Whether senders are told who has read their recent messages here. */
+  receiptsEnabled?: boolean;
+  /** How many connections may have the channel open at once; null or absent means no limit. */
+  maxSubscribers?: number | null;
+  /** The server's default channel, where new connections land. */
+  isDefault?: boolean;
 }
 
 export interface ScreenShareSettings {
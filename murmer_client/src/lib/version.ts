@@ -1,2 +1,19 @@
 import pkg from '../../package.json';
 export const APP_VERSION = pkg.version as string;
+/** WebSocket protocol version sent in `presence` (see the server's `ws::protocol`). */
+export const PROTOCOL_VERSION = 2;
+
+/**
+ * Compare two dotted numeric versions (`2026.723.0`). Returns a negative
+ * number when `a` is older than `b`, positive when newer, 0 when equal.
+ * Non-numeric parts count as 0.
+ */
+export function compareVersions(a: string, b: string): number {
+  const left = a.split('.').map((part) => parseInt(part, 10) || 0);
+  const right = b.split('.').map((part) => parseInt(part, 10) || 0);
+  for (let i = 0; i < Math.max(left.length, right.length); i++) {
+    const diff = (left[i] ?? 0) - (right[i] ?? 0);
+    if (diff !== 0) return diff;
+  }
+  return 0;
+}
@@ -0,0 +1,84 @@
+import { pttKey } from '../stores/settings';
+import { firesWhileTyping } from '../stores/hotkeys';
+
+/**
+ * System-wide push-to-talk in the Tauri shell. The PTT key is registered as
+ * an OS global shortcut on the Rust side (src-tauri/src/ptt.rs), which emits
+ * `ptt-start` / `ptt-stop` while another application has focus. In the plain
+ * browser nothing happens and the in-window key listener is the only path.
+ */
+
+const isTauri = typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
+
+let suspended = false;
+let currentKey: string | null = null;
+
+// invoke is async; funnel updates through one chain so they apply in order.
+let queue: Promise<void> = Promise.resolve();
+
+/** Our combo format calls the Windows/Cmd key "Meta", the plugin "Super". */
+function toAccelerator(combo: string): string {
+  return combo
+    .split('+')
+    .map((part) => (part === 'Meta' ? 'Super' : part))
+    .join('+');
+}
+
+function sync() {
+  if (!isTauri) return;
+  // A bare key (the default "Space") would be swallowed in every other
+  // application, so only combos with a modifier or function key go global.
+  const shortcut =
+    !suspended && currentKey && firesWhileTyping(currentKey) ? toAccelerator(currentKey) : null;
+  queue = queue
+    .then(async () => {
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke('set_ptt_shortcut', { shortcut });
+    })
+    .catch((e) => console.warn('Could not register global push-to-talk:', e));
+}
+
+/**
+ * Forward global PTT presses to `onChange`. Returns a function that stops
+ * listening.
+ */
+export function listenGlobalPtt(onChange: (pressed: boolean) => void): () => void {
+  if (!isTauri) return () => {};
+  const stops: Array<() => void> = [];
+  let stopped = false;
+  import('@tauri-apps/api/event')
+    .then(async ({ listen }) => {
+      for (const [name, pressed] of [
+        ['ptt-start', true],
+        ['ptt-stop', false]
+      ] as const) {
+        const unlisten = await listen(name, () => onChange(pressed));
+        if (stopped) unlisten();
+        else stops.push(unlisten);
+      }
+    })
+    .catch((e) => console.error('Failed to listen for global push-to-talk:', e));
+  return () => {
+    stopped = true;
+    for (const stop of stops.splice(0)) stop();
+  };
+}
+
+/**
+ * Release the global PTT shortcut while the settings modal captures a new
+ * key; a registered combo would be consumed by the OS and never reach it.
+ */
+export function suspendGlobalPtt() {
+  suspended = true;
+  sync();
+}
+
+export function resumeGlobalPtt() {
+  suspended = false;
+  sync();
+}
+
+pttKey.subscribe((key) => {
+  currentKey = key;
+  sync();
+});
@@ -0,0 +1,51 @@
+/**
+ * ICE server configuration shared by voice and screen sharing.
+ *
+ * A public STUN server is always included. When the server issues TURN
+ * credentials (`GET /turn-credentials`, signed like uploads) they are added
+ * so peers behind strict NATs can relay. Credentials are cached until shortly
+ * before they expire; peer connections read the cached set synchronously.
+ */
+import { get } from 'svelte/store';
+import { selectedServer } from '../stores/servers';
+import { httpBaseFromWs } from '../server-url';
+import { signedRequestHeaders } from '../keypair';
+
+const STUN_SERVER: RTCIceServer = { urls: 'stun:stun.l.google.com:19302' };
+/** Refresh credentials this long before the server says they expire. */
+const EXPIRY_MARGIN_MS = 60_000;
+
+let turnServer: RTCIceServer | null = null;
+let turnServerFor: string | null = null;
+let turnExpiresAt = 0;
+
+/** Configuration for a new `RTCPeerConnection`. */
+export function iceConfig(): RTCConfiguration {
+  return { iceServers: turnServer ? [STUN_SERVER, turnServer] : [STUN_SERVER] };
+}
+
+/**
+ * Fetch TURN credentials for `user` unless valid ones are cached. Failures
+ * (no TURN configured, older server, network) leave STUN only.
+ */
+export async function refreshIceServers(user: string): Promise<void> {
+  const server = get(selectedServer);
+  if (!server) return;
+  const key = `${server}|${user}`;
+  if (turnServerFor === key && Date.now() < turnExpiresAt - EXPIRY_MARGIN_MS) return;
+  turnServer = null;
+  turnServerFor = null;
+  try {
+    const res = await fetch(httpBaseFromWs(server) + '/turn-credentials', {
+      headers: signedRequestHeaders(user, 'GET', '/turn-credentials')
+    });
+    if (!res.ok) return;
+    const body = await res.json();
+    if (!Array.isArray(body.urls) || typeof body.username !== 'string') return;
+    turnServer = { urls: body.urls, username: body.username, credential: body.credential };
+    turnServerFor = key;
+    turnExpiresAt = Date.now() + (typeof body.ttl === 'number' ? body.ttl : 0) * 1000;
+  } catch {
+    // Keep STUN only.
+  }
+}
@@ -6,6 +6,7 @@
  * currently connected.
  */
 import { chat } from '../stores/chat';
+import { iceConfig, refreshIceServers } from './iceServers';
 import {
   volume,
   inputDeviceId,
@@ -25,6 +26,7 @@ import { get } from 'svelte/store';
 import type { Message, RemotePeer, ConnectionStats, VoiceChannelInfo } from '../types';
 import { VoiceActivityDetector } from './vad';
 import { PushToTalkManager } from './ptt';
+import { listenGlobalPtt } from './globalPtt';
 
 const DEFAULT_AUDIO_BITRATE = 64_000;
 
@@ -56,10 +58,6 @@ export class VoiceManager {
   private muteSound = new Audio('/sounds/mute_sound.wav');
   private unmuteSound = new Audio('/sounds/unmute_sound.wav');
 
-  private config: RTCConfiguration = {
-    iceServers: [{ urls: 'stun:stun.l.google.com:19302' }]
-  };
-
   private channelConfig: VoiceChannelInfo | null = null;
 
   constructor() {
@@ -116,6 +114,7 @@ export class VoiceManager {
       isPttActive.set(isPressed);
       this.updateTransmissionState();
     });
+    listenGlobalPtt((pressed) => this.ptt?.setPressed(pressed));
 
     chat.on('voice-channel-update', (msg) => {
       const chId = (msg as any).channelId;
@@ -410,7 +409,7 @@ export class VoiceManager {
     peersList: RemotePeer[]
   ): Promise<RTCPeerConnection> {
     if (this.peers[id]) return this.peers[id];
-    const pc = new RTCPeerConnection(this.config);
+    const pc = new RTCPeerConnection(iceConfig());
     this.peers[id] = pc;
     if (this.localStream) {
       for (const track of this.localStream.getTracks()) {
@@ -477,6 +476,7 @@ export class VoiceManager {
     const audio: MediaTrackConstraints = this.micProcessingConstraints();
     if (device) audio.deviceId = { exact: device };
     const rawStream = await navigator.mediaDevices.getUserMedia({ audio });
+    await refreshIceServers(user);
 
     this.userName = user;
     this.channelId = channelId;
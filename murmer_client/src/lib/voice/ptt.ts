@@ -36,6 +36,16 @@ export class PushToTalkManager {
     return this.isPressed;
   }
 
+  /**
+   * Set the pressed state from outside the window's key events, e.g. the
+   * system-wide shortcut in the desktop app
+   */
+  setPressed(isPressed: boolean) {
+    if (this.isPressed === isPressed) return;
+    this.isPressed = isPressed;
+    this.notifyListeners(isPressed);
+  }
+
   /**
    * Subscribe to PTT state changes
    */
@@ -21,6 +21,25 @@ export interface CloseInfo {
 /** Abort connection attempts that have not opened within this window. */
 const CONNECT_TIMEOUT_MS = 10_000;
 
+/** Whether a binary frame starts with the gzip magic bytes `1f 8b`. */
+function isGzip(bytes: Uint8Array): boolean {
+  return bytes.length > 2 && bytes[0] === 0x1f && bytes[1] === 0x8b;
+}
+
+/**
+ * Decode a frame's payload. Text frames are JSON; binary frames are
+ * gzipped JSON (large `history` payloads, requested with
+ * `compression: 'gzip'` in `presence`).
+ */
+async function decodeFrame(data: unknown): Promise<string | null> {
+  if (typeof data === 'string') return data;
+  if (!(data instanceof ArrayBuffer)) return null;
+  const bytes = new Uint8Array(data);
+  if (!isGzip(bytes)) return null;
+  const stream = new Blob([bytes]).stream().pipeThrough(new DecompressionStream('gzip'));
+  return new Response(stream).text();
+}
+
 /**
  * Manages a WebSocket connection with event handlers and lifecycle management.
  */
@@ -30,6 +49,8 @@ export class WebSocketManager {
   private handlers: Record<string, MessageHandler[]> = {};
   /** Sockets closed locally via disconnect(), so their close events can be told apart. */
   private intentionallyClosed = new WeakSet<WebSocket>();
+  /** Frames are dispatched in arrival order even when one needs async decompression. */
+  private inbox: Promise<void> = Promise.resolve();
 
   /**
    * Connect to a WebSocket URL.
@@ -57,6 +78,7 @@ export class WebSocketManager {
     if (import.meta.env.DEV) console.log('Connecting to WebSocket', url);
 
     this.socket = new WebSocket(url);
+    this.socket.binaryType = 'arraybuffer';
     const socket = this.socket;
     let opened = false;
 
@@ -78,19 +100,16 @@ export class WebSocketManager {
 
     this.socket.addEventListener('message', (ev) => {
       if (import.meta.env.DEV) console.log('Received:', ev.data);
-      try {
-        const msg: Message = JSON.parse(ev.data);
-        onMessage(msg);
-
-        // Trigger registered handlers
-        if (msg.type && this.handlers[msg.type]) {
-          for (const handler of this.handlers[msg.type]) {
-            handler(msg);
-          }
+      this.inbox = this.inbox.then(async () => {
+        try {
+          const text = await decodeFrame(ev.data);
+          if (text === null) return;
+          const msg: Message = JSON.parse(text);
+          this.dispatch(msg, onMessage);
+        } catch (error) {
+          console.error('Failed to parse WebSocket message:', error);
         }
-      } catch (error) {
-        console.error('Failed to parse WebSocket message:', error);
-      }
+      });
     });
 
     this.socket.addEventListener('close', () => {
@@ -111,6 +130,31 @@ export class WebSocketManager {
     });
   }
 
+  /**
+   * Hand a message to `onMessage` and the registered handlers. An
+   * `init-state` bundle is unpacked and each frame in it dispatched in order,
+   * exactly as if it had arrived on its own.
+   */
+  private dispatch(msg: Message, onMessage: MessageHandler): void {
+    if (msg.type === 'init-state') {
+      const frames = (msg as any).frames;
+      if (Array.isArray(frames)) {
+        for (const frame of frames) {
+          if (typeof frame === 'object' && frame !== null) this.dispatch(frame, onMessage);
+        }
+      }
+      return;
+    }
+    onMessage(msg);
+
+    // Trigger registered handlers
+    if (msg.type && this.handlers[msg.type]) {
+      for (const handler of this.handlers[msg.type]) {
+        handler(msg);
+      }
+    }
+  }
+
   /**
    * Send a JSON message over the WebSocket connection.
    * @param data - Data to serialize and send
@@ -0,0 +1,55 @@
+//! Records build metadata for `GET /version`.
+//!
+//! - `MURMER_GIT_SHA`: the commit being built. Taken from the environment when
+//!   set (Docker builds have no `.git`; pass `--build-arg GIT_SHA=...`),
+//!   otherwise from `git rev-parse HEAD`, falling back to `unknown`.
+//! - `MURMER_BUILD_EPOCH`: Unix time of the build, or `SOURCE_DATE_EPOCH` for
+//!   reproducible builds.
+
+use std::{
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let out = String::from_utf8(output.stdout).ok()?;
+    let out = out.trim();
+    (!out.is_empty()).then(|| out.to_string())
+}
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=MURMER_GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let sha = std::env::var("MURMER_GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.trim().is_empty())
+        .or_else(|| git(&["rev-parse", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=MURMER_GIT_SHA={}", sha.trim());
+
+    // Rebuild when HEAD moves (checkout or new commit on the current branch).
+    if let Some(head) = git(&["rev-parse", "--git-path", "HEAD"]) {
+        println!("cargo:rerun-if-changed={head}");
+    }
+    if let Some(branch) = git(&["symbolic-ref", "-q", "HEAD"])
+        && let Some(path) = git(&["rev-parse", "--git-path", &branch])
+    {
+        println!("cargo:rerun-if-changed={path}");
+    }
+
+    let epoch = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=MURMER_BUILD_EPOCH={epoch}");
+}
@@ -4,24 +4,113 @@
 //! set and assigns a role to a user by their public key. It is the primary way
 //! to bootstrap the first Owner before the dashboard is reachable; the role is
 //! added to any existing assignments rather than replacing them.
+//!
+//! `DELETE /role` takes the same `key` and removes the named `role` from it,
+//! or every role it holds when `role` is omitted.
+//!
+//! `GET /roles?limit=&offset=` pages through every role assignment, together
+//! with the names of connected users bound to each key, so admins can review
+//! who holds which privileges.
+//!
+//! `PATCH /role/permissions` edits a role definition's permission mask by
+//! name, so scripted deployments can tune custom moderation roles without the
+//! dashboard. The Owner role stays locked to `ADMINISTRATOR`.
+//!
+//! `GET /audit?limit=&offset=` pages through the audit log, newest first:
+//! role changes, channel creation and deletion, moderator message deletions,
+//! kicks, bans and mutes, each with who did it and when.
+//!
+//! `GET /flags?limit=&offset=` pages through open message flags (user
+//! reports awaiting moderator review), oldest first, with the flagged
+//! message's author and text when it still exists.
+//!
+//! `GET /stats` returns a cheap snapshot of connection and channel counts for
+//! operators to poll.
+//!
+//! `POST /admin/resync` reloads voice channels, channel overrides and the
+//! per-channel broadcast senders from the database after manual edits, keeping
+//! live voice occupancy, and tells clients to rebuild their channel lists.
+//!
+//! `POST /admin/reload-wordlist` re-reads `WORDLIST_PATH` and swaps in the
+//! new word filter, so banned words can be edited without a restart. A list
+//! that fails to load leaves the current filter in place.
+//!
+//! `GET /history?channelId=&limit=&before=` reads a text channel's history
+//! with reactions, oldest first; `oldestId` is the cursor for the next
+//! (older) page via `before`.
+//!
+//! `POST /emoji` registers a custom emoji (`{name, url}`, where `url` is a
+//! previously uploaded `/files/<key>` image) without going through a
+//! connected client; the refreshed `emoji-list` is pushed to everyone.
+//!
+//! `POST /announce` takes `{message, level}` and pushes a
+//! `server-announcement` frame to every connected client; the announcement
+//! is also replayed on `presence` until `DELETE /announce` clears it.
 
 use axum::{
-    extract::{Json, State},
+    Router,
+    extract::{DefaultBodyLimit, Json, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
+    routing::{get, patch, post},
 };
 use axum_extra::{
     TypedHeader,
     headers::{Authorization, authorization::Bearer},
 };
-use serde::Deserialize;
-use std::sync::Arc;
-use subtle::ConstantTimeEq;
-use tracing::error;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc};
+use tracing::{error, info};
 
+use crate::permissions::{self, Permissions};
 use crate::roles::default_color;
-use crate::ws::helpers;
-use crate::{AppState, db};
+use crate::ws::{constants, helpers, validation};
+use crate::{AppState, VoiceChannelState, db, security, word_filter};
+
+/// Largest JSON body accepted by the role endpoints. A role assignment is a
+/// key, a name and a color, so anything bigger is rejected with 413 before it
+/// is buffered.
+pub const ROLE_BODY_LIMIT: usize = 4 * 1024;
+
+/// Maximum announcement length in characters.
+pub const MAX_ANNOUNCEMENT_CHARS: usize = 1000;
+/// Announcement levels clients know how to render.
+pub const ANNOUNCEMENT_LEVELS: &[&str] = &["info", "warning", "critical"];
+/// Largest JSON body accepted by `/announce`: the message cap in 4-byte
+/// characters plus room for escaping and the level.
+const ANNOUNCE_BODY_LIMIT: usize = 16 * 1024;
+
+/// Routes for the admin endpoints, each with an explicit body limit.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/role",
+            post(set_role)
+                .delete(remove_role)
+                .layer(DefaultBodyLimit::max(ROLE_BODY_LIMIT)),
+        )
+        .route("/roles", get(list_roles))
+        .route("/audit", get(list_audit))
+        .route("/flags", get(list_flags))
+        .route("/stats", get(server_stats))
+        .route("/admin/resync", post(resync))
+        .route("/admin/reload-wordlist", post(reload_wordlist))
+        .route("/history", get(history))
+        .route(
+            "/emoji",
+            post(add_emoji).layer(DefaultBodyLimit::max(ROLE_BODY_LIMIT)),
+        )
+        .route(
+            "/announce",
+            post(announce)
+                .delete(clear_announcement)
+                .layer(DefaultBodyLimit::max(ANNOUNCE_BODY_LIMIT)),
+        )
+        .route(
+            "/role/permissions",
+            patch(set_role_permissions).layer(DefaultBodyLimit::max(ROLE_BODY_LIMIT)),
+        )
+}
 
 #[derive(Debug, Deserialize)]
 pub struct RoleBody {
@@ -30,23 +119,165 @@ pub struct RoleBody {
     pub color: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RemoveRoleBody {
+    pub key: String,
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RolePermissionsBody {
+    pub role: String,
+    pub permissions: Permissions,
+}
+
+/// Query for `GET /history`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryQuery {
+    pub channel_id: i32,
+    pub limit: Option<i64>,
+    pub before: Option<i64>,
+}
+
+/// Page returned by `GET /history`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub messages: Vec<serde_json::Value>,
+    /// Id of the oldest message in this page; pass it as `before` to fetch the
+    /// next page. `None` once the page is empty.
+    pub oldest_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmojiBody {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnnounceBody {
+    pub message: String,
+    pub level: String,
+}
+
+/// Occupancy of one voice channel in the `GET /stats` snapshot.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceOccupancy {
+    pub id: i32,
+    pub name: String,
+    pub participants: usize,
+}
+
+/// Snapshot returned by `GET /stats`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStats {
+    /// Open WebSocket connections, authenticated or not.
+    pub connected_sockets: usize,
+    pub online_users: usize,
+    pub known_users: usize,
+    pub text_channels: i64,
+    pub voice_channels: Vec<VoiceOccupancy>,
+}
+
+/// Counts returned by `POST /admin/resync`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResyncSummary {
+    pub text_channels: usize,
+    pub voice_channels: usize,
+    /// Voice channels dropped from memory because their row is gone.
+    pub removed_voice_channels: usize,
+}
+
+/// Result of `POST /admin/reload-wordlist`.
+#[derive(Debug, Serialize)]
+pub struct WordlistSummary {
+    /// Entries in the reloaded list; `0` when `WORDLIST_PATH` is unset.
+    pub words: usize,
+}
+
+/// Actor recorded in the audit log for actions taken with the `ADMIN_TOKEN`.
+pub const ADMIN_ACTOR: &str = "admin";
+
+/// Default page size for `GET /roles`.
+const DEFAULT_ROLES_PAGE: i64 = 100;
+/// Largest page size `GET /roles` will return.
+const MAX_ROLES_PAGE: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct RolesQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleAssignmentEntry {
+    pub key: String,
+    pub role: String,
+    pub color: Option<String>,
+    /// Connected users currently bound to `key`.
+    pub users: Vec<String>,
+}
+
+/// Default page size for `GET /audit`.
+const DEFAULT_AUDIT_PAGE: i64 = 100;
+/// Largest page size `GET /audit` will return.
+const MAX_AUDIT_PAGE: i64 = 500;
+
+/// Query for `GET /audit`.
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// One audit log entry as returned by `GET /audit`.
+#[derive(Debug, Serialize)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>,
+    pub details: serde_json::Value,
+    pub at: String,
+}
+
+/// Default page size for `GET /flags`.
+const DEFAULT_FLAGS_PAGE: i64 = 100;
+/// Largest page size `GET /flags` will return.
+const MAX_FLAGS_PAGE: i64 = 500;
+
+/// Query for `GET /flags`.
+#[derive(Debug, Deserialize)]
+pub struct FlagsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// One open message flag as returned by `GET /flags`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagEntry {
+    pub message_id: i64,
+    pub channel_id: Option<i32>,
+    pub reporter: String,
+    pub reason: String,
+    pub at: String,
+    pub author: Option<String>,
+    pub text: Option<String>,
+}
+
 #[tracing::instrument(skip(state, bearer), fields(key = %body.key, role = %body.role))]
 pub async fn set_role(
     State(state): State<Arc<AppState>>,
     TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
     Json(body): Json<RoleBody>,
 ) -> impl IntoResponse {
-    // Use constant-time comparison to prevent timing attacks
-    let authorized = if let Some(expected_token) = &state.admin_token {
-        expected_token
-            .as_bytes()
-            .ct_eq(bearer.token().as_bytes())
-            .into()
-    } else {
-        false
-    };
-
-    if !authorized {
+    if !security::is_admin_token(&state, bearer.token()) {
         return StatusCode::UNAUTHORIZED;
     }
 
@@ -61,7 +292,14 @@ pub async fn set_role(
     };
 
     // Reflect a possibly newly created role definition in memory.
-    state.role_defs.lock().await.insert(def.id, def.clone());
+    state.role_defs.write().await.insert(def.id, def.clone());
+    helpers::record_audit(
+        &state,
+        ADMIN_ACTOR,
+        "assign-role",
+        Some(&body.key),
+        serde_json::json!({ "role": body.role }),
+    );
 
     // Update the in-memory assignments of any currently-connected users bound
     // to this key.
@@ -74,7 +312,7 @@ pub async fn set_role(
             .collect()
     };
     {
-        let mut assignments = state.user_roles.lock().await;
+        let mut assignments = state.user_roles.write().await;
         for user in &affected {
             let entry = assignments.entry(user.clone()).or_default();
             if !entry.contains(&def.id) {
@@ -87,7 +325,7 @@ pub async fn set_role(
     for user in affected {
         let ids = state
             .user_roles
-            .lock()
+            .read()
             .await
             .get(&user)
             .cloned()
@@ -96,3 +334,550 @@ pub async fn set_role(
     }
     StatusCode::OK
 }
+
+/// List role assignments, `limit` (default 100, at most 500) at a time.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn list_roles(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<RolesQuery>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ROLES_PAGE)
+        .clamp(1, MAX_ROLES_PAGE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let assignments = match db::list_role_assignments(&state.db, limit, offset).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to list role assignments: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let user_keys = state.user_keys.lock().await;
+    let entries: Vec<RoleAssignmentEntry> = assignments
+        .into_iter()
+        .map(|a| {
+            let mut users: Vec<String> = user_keys
+                .iter()
+                .filter(|(_, key)| **key == a.key)
+                .map(|(user, _)| user.clone())
+                .collect();
+            users.sort();
+            RoleAssignmentEntry {
+                key: a.key,
+                role: a.role,
+                color: a.color,
+                users,
+            }
+        })
+        .collect();
+    Json(entries).into_response()
+}
+
+/// List audit log entries newest first, `limit` (default 100, at most 500)
+/// at a time.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn list_audit(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<AuditQuery>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_PAGE)
+        .clamp(1, MAX_AUDIT_PAGE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match db::list_audit(&state.db, limit, offset).await {
+        Ok(entries) => Json(
+            entries
+                .into_iter()
+                .map(|e| AuditLogEntry {
+                    id: e.id,
+                    actor: e.actor,
+                    action: e.action,
+                    target: e.target,
+                    details: e.details,
+                    at: e.at,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => {
+            error!("Failed to list audit log: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// List open message flags oldest first, `limit` (default 100, at most 500)
+/// at a time.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn list_flags(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<FlagsQuery>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_FLAGS_PAGE)
+        .clamp(1, MAX_FLAGS_PAGE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match db::list_open_flags(&state.db, limit, offset).await {
+        Ok(flags) => Json(
+            flags
+                .into_iter()
+                .map(|f| FlagEntry {
+                    message_id: f.message_id,
+                    channel_id: f.channel_id,
+                    reporter: f.reporter,
+                    reason: f.reason,
+                    at: f.at,
+                    author: f.author,
+                    text: f.text,
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => {
+            error!("Failed to list message flags: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Report connection and channel counts. Each map is locked only long enough
+/// to read its size, so the endpoint is cheap enough to poll every few
+/// seconds.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn server_stats(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let text_channels = match db::count_channels(&state.db).await {
+        Ok(count) => count,
+        Err(e) => {
+            error!("Failed to count channels: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let online_users = state.users.lock().await.len();
+    let known_users = state.known_users.read().await.len();
+    let mut voice_channels: Vec<VoiceOccupancy> = state
+        .voice_channels
+        .read()
+        .await
+        .iter()
+        .map(|(id, info)| VoiceOccupancy {
+            id: *id,
+            name: info.name.clone(),
+            participants: info.users.len(),
+        })
+        .collect();
+    voice_channels.sort_by_key(|v| v.id);
+
+    Json(ServerStats {
+        // Every socket subscribes to the global broadcast for its lifetime.
+        connected_sockets: state.tx.receiver_count(),
+        online_users,
+        known_users,
+        text_channels,
+        voice_channels,
+    })
+    .into_response()
+}
+
+/// Rebuild channel state from the database without a restart. Voice channels
+/// are merged: configuration comes from the database, but participants of
+/// channels that still exist are kept so live calls are not dropped. Channels
+/// whose rows were removed lose their broadcast sender and screen shares.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn resync(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let overrides = match db::load_all_overrides(&state.db).await {
+        Ok(overrides) => overrides,
+        Err(e) => {
+            error!("Failed to reload channel overrides: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    // Load everything before touching in-memory state, so a failed query
+    // cannot be mistaken for "every channel was deleted".
+    let text_ids: HashSet<i32> = match db::get_channels(&state.db).await {
+        Ok(channels) => channels.into_iter().map(|ch| ch.id).collect(),
+        Err(e) => {
+            error!("Failed to reload text channels: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let records = match db::get_voice_channels(&state.db).await {
+        Ok(records) => records,
+        Err(e) => {
+            error!("Failed to reload voice channels: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let removed: Vec<i32> = {
+        let mut voice = state.voice_channels.write().await;
+        let mut previous = std::mem::take(&mut *voice);
+        for record in records {
+            let users = previous
+                .remove(&record.id)
+                .map(|info| info.users)
+                .unwrap_or_default();
+            voice.insert(
+                record.id,
+                VoiceChannelState {
+                    name: record.name,
+                    users,
+                    quality: record.quality,
+                    bitrate: record.bitrate,
+                    category_id: record.category_id,
+                    position: record.position,
+                },
+            );
+        }
+        previous.into_keys().collect()
+    };
+    {
+        let mut shares = state.active_screen_shares.lock().await;
+        for id in &removed {
+            shares.remove(id);
+        }
+    }
+    state
+        .channels
+        .lock()
+        .await
+        .retain(|id, _| text_ids.contains(id));
+    *state.channel_overrides.lock().await = overrides;
+
+    helpers::broadcast_channels_refresh(&state).await;
+    let voice_ids: Vec<i32> = state.voice_channels.read().await.keys().copied().collect();
+    for id in voice_ids {
+        helpers::broadcast_voice(&state, id).await;
+    }
+
+    let summary = ResyncSummary {
+        text_channels: text_ids.len(),
+        voice_channels: state.voice_channels.read().await.len(),
+        removed_voice_channels: removed.len(),
+    };
+    info!(
+        text = summary.text_channels,
+        voice = summary.voice_channels,
+        removed = summary.removed_voice_channels,
+        "Resynced channel state from database"
+    );
+    Json(summary).into_response()
+}
+
+/// Reload the word filter from `WORDLIST_PATH`. Unsetting the path disables
+/// the filter; a list that cannot be read or compiled keeps the old one and
+/// answers 500.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn reload_wordlist(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let filter = match word_filter::load_configured() {
+        Ok(filter) => filter,
+        Err(e) => {
+            error!("Failed to reload word list: {e:#}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let words = filter.as_ref().map_or(0, |filter| filter.len());
+    *state.word_filter.write().await = filter;
+    info!(words, "Reloaded word filter");
+    Json(WordlistSummary { words }).into_response()
+}
+
+/// Remove a role (or all roles) from a public key and push the new assignment
+/// to any connected users bound to it, so clients revert their color.
+///
+/// Returns 404 when the named role does not exist or the key did not hold it.
+#[tracing::instrument(skip(state, bearer), fields(key = %body.key, role = ?body.role))]
+pub async fn remove_role(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Json(body): Json<RemoveRoleBody>,
+) -> impl IntoResponse {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let role_id = match &body.role {
+        Some(name) => match db::get_role_def_by_name(&state.db, name).await {
+            Ok(Some(def)) => Some(def.id),
+            Ok(None) => return StatusCode::NOT_FOUND,
+            Err(e) => {
+                error!("Failed to look up role {name}: {e}");
+                return StatusCode::INTERNAL_SERVER_ERROR;
+            }
+        },
+        None => None,
+    };
+
+    match db::remove_user_role(&state.db, &body.key, role_id).await {
+        Ok(0) => return StatusCode::NOT_FOUND,
+        Ok(_) => helpers::record_audit(
+            &state,
+            ADMIN_ACTOR,
+            "remove-role",
+            Some(&body.key),
+            serde_json::json!({ "role": body.role }),
+        ),
+        Err(e) => {
+            error!("Failed to remove role for user {}: {}", body.key, e);
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    }
+
+    let affected: Vec<String> = {
+        let user_keys = state.user_keys.lock().await;
+        user_keys
+            .iter()
+            .filter(|(_, key)| *key == &body.key)
+            .map(|(user, _)| user.clone())
+            .collect()
+    };
+    for user in affected {
+        let ids = {
+            let mut assignments = state.user_roles.write().await;
+            let entry = assignments.entry(user.clone()).or_default();
+            match role_id {
+                Some(id) => entry.retain(|r| *r != id),
+                None => entry.clear(),
+            }
+            entry.clone()
+        };
+        helpers::broadcast_user_roles(&state, &user, &ids).await;
+    }
+    StatusCode::OK
+}
+
+/// Replace the permission mask of the role named `body.role`.
+///
+/// Returns 404 for an unknown role, 400 for a mask with undefined bits and
+/// 409 for the Owner role, whose mask is fixed.
+#[tracing::instrument(skip(state, bearer), fields(role = %body.role))]
+pub async fn set_role_permissions(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Json(body): Json<RolePermissionsBody>,
+) -> impl IntoResponse {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED;
+    }
+    if !permissions::is_valid_mask(body.permissions) {
+        return StatusCode::BAD_REQUEST;
+    }
+
+    let def = match db::get_role_def_by_name(&state.db, &body.role).await {
+        Ok(Some(def)) => def,
+        Ok(None) => return StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to look up role {}: {}", body.role, e);
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    };
+    if def.is_owner {
+        return StatusCode::CONFLICT;
+    }
+
+    if let Err(e) = db::update_role_def(
+        &state.db,
+        def.id,
+        &def.name,
+        def.color.as_deref(),
+        body.permissions,
+    )
+    .await
+    {
+        error!("Failed to update permissions of role {}: {}", def.name, e);
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    if let Some(cached) = state.role_defs.write().await.get_mut(&def.id) {
+        cached.permissions = body.permissions;
+    }
+    helpers::record_audit(
+        &state,
+        ADMIN_ACTOR,
+        "set-role-permissions",
+        Some(&def.name),
+        serde_json::json!({ "from": def.permissions, "to": body.permissions }),
+    );
+    helpers::broadcast_role_definitions(&state).await;
+    StatusCode::OK
+}
+
+/// Broadcast an operator announcement to every connected client and make it
+/// the active one. Returns 400 for an empty or over-long message or an
+/// unknown level.
+#[tracing::instrument(skip(state, bearer, body), fields(level = %body.level))]
+pub async fn announce(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Json(body): Json<AnnounceBody>,
+) -> impl IntoResponse {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED;
+    }
+    let message = body.message.trim();
+    if message.is_empty()
+        || message.chars().count() > MAX_ANNOUNCEMENT_CHARS
+        || !ANNOUNCEMENT_LEVELS.contains(&body.level.as_str())
+    {
+        return StatusCode::BAD_REQUEST;
+    }
+
+    let announcement = match db::add_announcement(&state.db, message, &body.level).await {
+        Ok(announcement) => announcement,
+        Err(e) => {
+            error!("Failed to store announcement: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    };
+    let _ = state.tx.send(helpers::announcement_frame(&announcement));
+    StatusCode::OK
+}
+
+/// Read a page of a text channel's history. History can include private
+/// channels, so this is limited to the admin token like the other endpoints
+/// here. Returns 404 for an unknown channel.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn history(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if db::get_channel_by_id(&state.db, query.channel_id)
+        .await
+        .is_none()
+    {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let limit = query
+        .limit
+        .unwrap_or(constants::DEFAULT_HISTORY_LIMIT)
+        .clamp(1, constants::MAX_HISTORY_LIMIT);
+    match db::history_messages(&state.db, query.channel_id, query.before, limit).await {
+        Ok(messages) => {
+            let oldest_id = messages
+                .first()
+                .and_then(|m| m.get("id"))
+                .and_then(|id| id.as_i64());
+            Json(HistoryPage {
+                messages,
+                oldest_id,
+            })
+            .into_response()
+        }
+        Err(e) => {
+            error!("Failed to load history: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Register a custom emoji from an uploaded image, applying the same checks
+/// as the `add-emoji` WebSocket frame. Returns 400 for a bad name or URL, 409
+/// when the name is taken and 507 when the emoji limit is reached.
+#[tracing::instrument(skip(state, bearer, body), fields(name = %body.name))]
+pub async fn add_emoji(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Json(body): Json<EmojiBody>,
+) -> impl IntoResponse {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED;
+    }
+    let name = body.name.trim().to_ascii_lowercase();
+    if !validation::validate_emoji_name(&name) {
+        return StatusCode::BAD_REQUEST;
+    }
+    let Some(key) = validation::upload_key_from_url(&body.url) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    match tokio::fs::metadata(state.upload_dir.join(key)).await {
+        Ok(meta) if meta.is_file() && meta.len() <= constants::MAX_EMOJI_FILE_BYTES => {}
+        Ok(_) | Err(_) => return StatusCode::BAD_REQUEST,
+    }
+
+    match db::count_emojis(&state.db).await {
+        Ok(count) if count >= constants::MAX_CUSTOM_EMOJIS => {
+            return StatusCode::INSUFFICIENT_STORAGE;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to count emojis: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    }
+    match db::add_emoji(&state.db, &name, &body.url, "admin").await {
+        Ok(true) => {
+            helpers::broadcast_emojis(&state).await;
+            StatusCode::CREATED
+        }
+        Ok(false) => StatusCode::CONFLICT,
+        Err(e) => {
+            error!("Failed to add emoji {name}: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Clear the active announcement and tell connected clients to dismiss it.
+/// Returns 404 when nothing was active.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn clear_announcement(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> impl IntoResponse {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED;
+    }
+    match db::clear_announcements(&state.db).await {
+        Ok(0) => StatusCode::NOT_FOUND,
+        Ok(_) => {
+            let _ = state
+                .tx
+                .send(serde_json::json!({ "type": "server-announcement-cleared" }).to_string());
+            StatusCode::OK
+        }
+        Err(e) => {
+            error!("Failed to clear announcements: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
@@ -45,7 +45,6 @@ use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use serde_json::{Map, Value};
 use std::collections::HashMap;
 use std::sync::Arc;
-use subtle::ConstantTimeEq;
 use tracing::error;
 
 use super::{
@@ -61,8 +60,9 @@ const MAX_BOT_MESSAGE_LENGTH: usize = 4000;
 const MAX_BOT_DESCRIPTION_LENGTH: usize = 256;
 const MIN_EPHEMERAL_SECONDS: i64 = 5;
 const MAX_EPHEMERAL_SECONDS: i64 = 86_400;
-const DEFAULT_MESSAGE_LIMIT: i64 = 50;
-const MAX_MESSAGE_LIMIT: i64 = 200;
+// Page sizes follow WebSocket `load-history` and `GET /history`.
+const DEFAULT_MESSAGE_LIMIT: i64 = ws::constants::DEFAULT_HISTORY_LIMIT;
+const MAX_MESSAGE_LIMIT: i64 = ws::constants::MAX_HISTORY_LIMIT;
 // The following mirror the WebSocket handler limits in `ws::constants`
 // (private to the ws module) so bots behave identically to regular clients.
 const MAX_SEARCH_RESULTS: i64 = 200;
@@ -74,13 +74,6 @@ fn json_error(status: StatusCode, message: &str) -> Response {
     (status, Json(serde_json::json!({"error": message}))).into_response()
 }
 
-fn verify_admin(state: &AppState, token: &str) -> bool {
-    state
-        .admin_token
-        .as_ref()
-        .is_some_and(|expected| expected.as_bytes().ct_eq(token.as_bytes()).into())
-}
-
 async fn verify_bot(state: &AppState, token: &str) -> Option<BotRecord> {
     let h = hash_token(token);
     bot_db::get_bot_by_token_hash(&state.db, &h)
@@ -130,7 +123,7 @@ async fn create_bot(
     TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
     Json(body): Json<CreateBotRequest>,
 ) -> Response {
-    if !verify_admin(&state, bearer.token()) {
+    if !security::is_admin_token(&state, bearer.token()) {
         return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
     }
 
@@ -182,7 +175,7 @@ async fn list_bots(
     State(state): State<Arc<AppState>>,
     TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
 ) -> Response {
-    if !verify_admin(&state, bearer.token()) {
+    if !security::is_admin_token(&state, bearer.token()) {
         return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
     }
 
@@ -203,7 +196,7 @@ async fn get_bot(
     TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
     Path(bot_id): Path<String>,
 ) -> Response {
-    if !verify_admin(&state, bearer.token()) {
+    if !security::is_admin_token(&state, bearer.token()) {
         return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
     }
 
@@ -223,7 +216,7 @@ async fn update_bot_handler(
     Path(bot_id): Path<String>,
     Json(body): Json<UpdateBotRequest>,
 ) -> Response {
-    if !verify_admin(&state, bearer.token()) {
+    if !security::is_admin_token(&state, bearer.token()) {
         return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
     }
 
@@ -271,7 +264,7 @@ async fn delete_bot_handler(
     TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
     Path(bot_id): Path<String>,
 ) -> Response {
-    if !verify_admin(&state, bearer.token()) {
+    if !security::is_admin_token(&state, bearer.token()) {
         return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
     }
 
@@ -290,7 +283,7 @@ async fn reset_bot_token(
     TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
     Path(bot_id): Path<String>,
 ) -> Response {
-    if !verify_admin(&state, bearer.token()) {
+    if !security::is_admin_token(&state, bearer.token()) {
         return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
     }
 
@@ -326,7 +319,13 @@ async fn list_channels_handler(
         return json_error(StatusCode::FORBIDDEN, "missing-permission:read_channels");
     }
 
-    let channels = db::get_channels(&state.db).await;
+    let channels = match db::get_channels(&state.db).await {
+        Ok(channels) => channels,
+        Err(e) => {
+            error!("Failed to list channels: {e}");
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "list-failed");
+        }
+    };
     let data: Vec<Value> = channels
         .iter()
         .map(|ch| {
@@ -409,7 +408,7 @@ async fn send_message(
     }
 
     let rate_key = format!("bot::{}", bot.id);
-    if !security::check_message_rate_limit(&state.rate_limiter, &rate_key).await {
+    if !security::check_message_rate_limit(&state.rate_limiter, &rate_key, &[]).await {
         return json_error(StatusCode::TOO_MANY_REQUESTS, "rate-limit-exceeded");
     }
 
@@ -487,7 +486,9 @@ async fn send_message(
     }
 
     let content = msg.to_string();
-    let id = match db::insert_message(&state.db, channel_id, &content).await {
+    let id = match db::insert_message_expiring(&state.db, channel_id, &content, ephemeral_expiry)
+        .await
+    {
         Ok(id) => id,
         Err(e) => {
             error!("Failed to insert bot message: {e}");
@@ -498,6 +499,7 @@ async fn send_message(
 
     let chan_tx = ws::helpers::get_or_create_channel(&state, channel_id).await;
     let _ = chan_tx.send(msg.to_string());
+    crate::webhooks::dispatch(&state, channel_id, msg.to_string());
 
     // Announce the message globally so clients viewing other channels can
     // update unread counts, mirroring the WebSocket chat handler.
@@ -555,13 +557,22 @@ async fn delete_message_handler(
         return json_error(StatusCode::FORBIDDEN, "missing-permission:manage_messages");
     }
 
-    match db::delete_message(&state.db, message_id).await {
+    let soft = crate::config::soft_delete_messages();
+    let deleted = if soft {
+        db::soft_delete_message(&state.db, message_id, &bot.name).await
+    } else {
+        db::delete_message(&state.db, message_id).await
+    };
+    match deleted {
         Ok(true) => {
-            let payload = serde_json::json!({
+            let mut payload = serde_json::json!({
                 "type": "message-deleted",
                 "id": message_id,
                 "channelId": channel_id,
             });
+            if soft {
+                payload["tombstone"] = Value::Bool(true);
+            }
             let chan_tx = ws::helpers::get_or_create_channel(&state, channel_id).await;
             let _ = chan_tx.send(payload.to_string());
             StatusCode::NO_CONTENT.into_response()
@@ -590,22 +601,23 @@ async fn add_reaction_handler(
     }
 
     let emoji = body.emoji.trim();
-    // Custom emoji shortcodes (`:name:`) may exceed the 16-byte cap that
-    // bounds regular unicode reactions, mirroring the WS handler.
+    // Custom emoji shortcodes (`:name:`) are checked against the registry
+    // below; anything else must be a single Unicode emoji, mirroring the WS handler.
     let shortcode = ws::validation::is_emoji_shortcode(emoji);
-    if !shortcode
-        && (emoji.is_empty()
-            || emoji.len() > 16
-            || emoji.chars().any(|c| c.is_control() || c.is_whitespace()))
-    {
+    if !shortcode && !ws::validation::validate_reaction_emoji(emoji) {
         return json_error(StatusCode::BAD_REQUEST, "invalid-emoji");
     }
+    let strict = crate::config::strict_emoji();
+    if !shortcode && !ws::validation::is_recognized_reaction(emoji, strict) {
+        return json_error(StatusCode::BAD_REQUEST, "reaction-rejected");
+    }
 
     // Shortcode reactions require the custom emoji to actually exist so junk
     // shortcodes cannot be planted.
     if shortcode {
         match db::emoji_exists(&state.db, emoji.trim_matches(':')).await {
             Ok(true) => {}
+            Ok(false) if strict => return json_error(StatusCode::BAD_REQUEST, "reaction-rejected"),
             Ok(false) => return json_error(StatusCode::BAD_REQUEST, "invalid-emoji"),
             Err(e) => {
                 error!("db emoji lookup error: {e}");
@@ -627,27 +639,26 @@ async fn add_reaction_handler(
         return json_error(StatusCode::NOT_FOUND, "message-not-found");
     }
 
-    if let Err(e) = db::add_reaction(&state.db, message_id, &bot.name, emoji).await {
-        error!("db reaction error: {e}");
-        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "reaction-failed");
-    }
-
-    let reactions = match db::get_reaction_summary(&state.db, message_id).await {
-        Ok(map) => map,
+    let change = match db::apply_reaction(
+        &state.db,
+        &state.reaction_seq,
+        message_id,
+        &bot.name,
+        emoji,
+        true,
+    )
+    .await
+    {
+        Ok(Some(change)) => change,
+        Ok(None) => return json_error(StatusCode::BAD_REQUEST, "too-many-reactions"),
         Err(e) => {
-            error!("db reaction summary error: {e}");
+            error!("db reaction error: {e}");
             return json_error(StatusCode::INTERNAL_SERVER_ERROR, "reaction-failed");
         }
     };
 
-    let payload = serde_json::json!({
-        "type": "reaction-update",
-        "channelId": channel_id,
-        "messageId": message_id,
-        "reactions": reactions,
-    });
-    let chan_tx = ws::helpers::get_or_create_channel(&state, channel_id).await;
-    let _ = chan_tx.send(payload.to_string());
+    let reactions = change.reactions.clone();
+    ws::helpers::queue_reaction_change(&state, channel_id, message_id, change);
 
     Json(serde_json::json!({"data": {"messageId": message_id, "reactions": reactions}}))
         .into_response()
@@ -680,27 +691,27 @@ async fn remove_reaction_handler(
         return json_error(StatusCode::NOT_FOUND, "message-not-found");
     }
 
-    if let Err(e) = db::remove_reaction(&state.db, message_id, &bot.name, &emoji).await {
-        error!("db reaction removal error: {e}");
-        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "reaction-failed");
-    }
-
-    let reactions = match db::get_reaction_summary(&state.db, message_id).await {
-        Ok(map) => map,
+    let change = match db::apply_reaction(
+        &state.db,
+        &state.reaction_seq,
+        message_id,
+        &bot.name,
+        &emoji,
+        false,
+    )
+    .await
+    {
+        // Removal is never refused by the cap, so `None` cannot occur.
+        Ok(Some(change)) => change,
+        Ok(None) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, "reaction-failed"),
         Err(e) => {
-            error!("db reaction summary error: {e}");
+            error!("db reaction removal error: {e}");
             return json_error(StatusCode::INTERNAL_SERVER_ERROR, "reaction-failed");
         }
     };
 
-    let payload = serde_json::json!({
-        "type": "reaction-update",
-        "channelId": channel_id,
-        "messageId": message_id,
-        "reactions": reactions,
-    });
-    let chan_tx = ws::helpers::get_or_create_channel(&state, channel_id).await;
-    let _ = chan_tx.send(payload.to_string());
+    let reactions = change.reactions.clone();
+    ws::helpers::queue_reaction_change(&state, channel_id, message_id, change);
 
     Json(serde_json::json!({"data": {"messageId": message_id, "reactions": reactions}}))
         .into_response()
@@ -1109,7 +1120,7 @@ async fn delete_channel_handler(
         None => return json_error(StatusCode::NOT_FOUND, "channel-not-found"),
     };
 
-    if record.name == "general" {
+    if record.name == crate::config::default_channel() {
         return json_error(StatusCode::FORBIDDEN, "cannot-delete-general");
     }
 
@@ -1144,7 +1155,7 @@ async fn typing_handler(
     // rate-limit window but under a separate key, so a chatty typing loop
     // cannot exhaust the bot's message budget (and vice versa).
     let rate_key = format!("bot-typing::{}", bot.id);
-    if !security::check_message_rate_limit(&state.rate_limiter, &rate_key).await {
+    if !security::check_message_rate_limit(&state.rate_limiter, &rate_key, &[]).await {
         return json_error(StatusCode::TOO_MANY_REQUESTS, "rate-limit-exceeded");
     }
 
@@ -1207,7 +1218,7 @@ async fn list_users(
     }
 
     let (online, all) = ws::helpers::get_user_lists(&state).await;
-    let statuses: HashMap<String, String> = state.statuses.lock().await.clone();
+    let statuses: HashMap<String, String> = state.statuses.read().await.clone();
 
     Json(serde_json::json!({
         "data": {
@@ -1228,7 +1239,13 @@ async fn server_info(
     }
 
     let online_count = state.users.lock().await.len();
-    let channels = db::get_channels(&state.db).await;
+    let channels = match db::get_channels(&state.db).await {
+        Ok(channels) => channels,
+        Err(e) => {
+            error!("Failed to list channels: {e}");
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "list-failed");
+        }
+    };
     let data: Vec<Value> = channels
         .iter()
         .map(|ch| {
@@ -1242,7 +1259,7 @@ async fn server_info(
 
     Json(serde_json::json!({
         "data": {
-            "version": env!("CARGO_PKG_VERSION"),
+            "version": crate::config::server_version(),
             "bot_api_version": "1",
             "online_users": online_count,
             "channels": data,
@@ -1,13 +1,109 @@
 //! Server configuration management.
 //!
-//! This module handles loading and validating configuration from environment variables.
+//! This module handles loading and validating configuration from environment
+//! variables and an optional TOML file. Startup settings live in [`Config`];
+//! tunables consulted at the point of use are read by the free functions below
+//! (mirroring the rate-limit getters in [`crate::security`]), so they can be
+//! adjusted without threading state.
+//!
+//! Every setting is looked up through [`var`]: the environment variable wins,
+//! otherwise the value from the config file (`CONFIG_FILE`, default
+//! `./murmer.toml`) is used. File keys are the environment variable names in
+//! lowercase, e.g. `bind_address = "0.0.0.0:3001"` or
+//! `cors_allow_origins = ["http://localhost:1420"]`.
 
-use anyhow::{Context, Result};
-use axum::http::{HeaderValue, Method, header};
-use std::{env, net::SocketAddr, path::PathBuf};
+use anyhow::{Context, Result, bail};
+use axum::http::{HeaderName, HeaderValue, Method, header};
+use std::{
+    collections::HashMap,
+    env,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{OnceLock, RwLock},
+    time::Duration,
+};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 
-/// Server configuration loaded from environment variables.
+/// Config file read when `CONFIG_FILE` is not set. Missing is fine.
+pub const DEFAULT_CONFIG_FILE: &str = "murmer.toml";
+
+/// Values loaded from the config file, keyed by environment variable name.
+fn file_values() -> &'static RwLock<HashMap<String, String>> {
+    static VALUES: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+    VALUES.get_or_init(Default::default)
+}
+
+/// Look up a setting: the environment variable `name` if set, else the
+/// matching key from the config file.
+pub fn var(name: &str) -> Option<String> {
+    env::var(name).ok().or_else(|| {
+        file_values()
+            .read()
+            .ok()
+            .and_then(|values| values.get(name).cloned())
+    })
+}
+
+/// Parse config file contents into settings keyed by environment variable
+/// name. Strings, numbers and booleans map to their text form; arrays of
+/// those are joined with commas, matching the list-valued variables.
+pub fn parse_config_file(contents: &str) -> Result<HashMap<String, String>> {
+    let table: toml::Table = contents.parse()?;
+    let mut values = HashMap::new();
+    for (key, value) in table {
+        let text = match &value {
+            toml::Value::Array(items) => items
+                .iter()
+                .map(scalar_text)
+                .collect::<Option<Vec<_>>>()
+                .map(|items| items.join(",")),
+            other => scalar_text(other),
+        };
+        let Some(text) = text else {
+            bail!("unsupported value for '{key}': expected a string, number, boolean or list");
+        };
+        values.insert(key.to_ascii_uppercase(), text);
+    }
+    Ok(values)
+}
+
+fn scalar_text(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(i) => Some(i.to_string()),
+        toml::Value::Float(f) => Some(f.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Load the config file named by `CONFIG_FILE` (default `./murmer.toml`) so
+/// [`var`] can fall back to it. A missing default file is not an error; an
+/// explicitly named file that is missing, or any file that fails to parse, is.
+pub fn load_config_file() -> Result<()> {
+    let (path, explicit) = match env::var("CONFIG_FILE") {
+        Ok(path) if !path.is_empty() => (PathBuf::from(path), true),
+        _ => (PathBuf::from(DEFAULT_CONFIG_FILE), false),
+    };
+    if !explicit && !path.exists() {
+        return Ok(());
+    }
+    load_config_file_from(&path)
+}
+
+/// Load a specific config file, replacing any previously loaded values.
+pub fn load_config_file_from(path: &Path) -> Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file '{}'", path.display()))?;
+    let values = parse_config_file(&contents)
+        .with_context(|| format!("failed to parse config file '{}'", path.display()))?;
+    if let Ok(mut current) = file_values().write() {
+        *current = values;
+    }
+    Ok(())
+}
+
+/// Server configuration loaded from environment variables and the config file.
 #[derive(Debug, Clone)]
 pub struct Config {
     /// Socket address to bind the server to.
@@ -21,7 +117,229 @@ pub struct Config {
     /// Optional admin token for role management.
     pub admin_token: Option<String>,
     /// CORS allowlist (None means CORS is disabled).
-    cors_allowlist: Option<Vec<HeaderValue>>,
+    cors_allowlist: Option<CorsOrigins>,
+    /// Methods allowed in CORS requests.
+    cors_methods: Vec<Method>,
+    /// Request headers allowed in CORS requests.
+    cors_headers: Vec<HeaderName>,
+    /// Whether CORS responses allow credentials (cookies, HTTP auth).
+    cors_allow_credentials: bool,
+}
+
+/// Origins allowed to make CORS requests.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CorsOrigins {
+    /// Any origin (`*`, only with `CORS_ALLOW_ANY_ORIGIN`); never credentialed.
+    Any,
+    /// Exact origins plus subdomain patterns.
+    List {
+        exact: Vec<HeaderValue>,
+        patterns: Vec<OriginPattern>,
+    },
+}
+
+impl CorsOrigins {
+    /// Whether a request `Origin` header value is allowed.
+    pub fn allows(&self, origin: &HeaderValue) -> bool {
+        match self {
+            Self::Any => true,
+            Self::List { exact, patterns } => {
+                exact.contains(origin)
+                    || origin
+                        .to_str()
+                        .is_ok_and(|origin| patterns.iter().any(|p| p.matches(origin)))
+            }
+        }
+    }
+}
+
+/// A `scheme://*.domain[:port]` origin pattern. The `*` stands for one or
+/// more subdomain labels, so `https://*.example.com` matches
+/// `https://app.example.com` and `https://a.b.example.com` but neither
+/// `https://example.com` nor `http://app.example.com`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OriginPattern {
+    /// `scheme://`, lowercase.
+    prefix: String,
+    /// `.domain[:port]`, lowercase.
+    suffix: String,
+}
+
+impl OriginPattern {
+    /// Parse a pattern; `None` when `*` appears anywhere but as the whole
+    /// leftmost host label or the rest is not a plausible origin.
+    fn parse(pattern: &str) -> Option<Self> {
+        let lower = pattern.to_ascii_lowercase();
+        let (scheme, rest) = lower.split_once("://")?;
+        let suffix = rest.strip_prefix('*')?;
+        let valid_scheme = !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+        let domain = suffix.strip_prefix('.')?;
+        let host = domain.split_once(':').map_or(domain, |(host, port)| {
+            if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) {
+                host
+            } else {
+                ""
+            }
+        });
+        // At least two labels after the wildcard, so `https://*.com` and the
+        // like cannot open CORS to a whole TLD.
+        let labels: Vec<&str> = host.split('.').collect();
+        if !valid_scheme || labels.len() < 2 || !labels.iter().all(|l| is_host_label(l)) {
+            return None;
+        }
+        Some(Self {
+            prefix: format!("{scheme}://"),
+            suffix: suffix.to_owned(),
+        })
+    }
+
+    /// Whether an origin matches, comparing ASCII case-insensitively.
+    pub fn matches(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        origin
+            .strip_prefix(&self.prefix)
+            .and_then(|rest| rest.strip_suffix(&self.suffix))
+            .is_some_and(|sub| !sub.is_empty() && sub.split('.').all(is_host_label))
+    }
+}
+
+/// A DNS label: 1 to 63 letters, digits or hyphens, not starting or ending
+/// with a hyphen.
+fn is_host_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Parse a comma-separated origin allowlist, as in `CORS_ALLOW_ORIGINS`.
+/// Entries are exact origins or `scheme://*.domain` patterns (see
+/// [`OriginPattern`]). A bare `*` allows every origin and is refused unless
+/// `allow_any` (`CORS_ALLOW_ANY_ORIGIN`) is set, and then may not be mixed
+/// with other entries. An empty list is `None`, meaning CORS stays off.
+pub fn parse_cors_origins(raw: &str, allow_any: bool) -> Result<Option<CorsOrigins>> {
+    let entries: Vec<&str> = list_entries(raw).collect();
+    if entries.contains(&"*") {
+        if !allow_any {
+            bail!("'*' in CORS_ALLOW_ORIGINS requires CORS_ALLOW_ANY_ORIGIN=true");
+        }
+        if entries.len() > 1 {
+            bail!("'*' in CORS_ALLOW_ORIGINS cannot be combined with other origins");
+        }
+        return Ok(Some(CorsOrigins::Any));
+    }
+    let mut exact = Vec::new();
+    let mut patterns = Vec::new();
+    for entry in entries {
+        if entry.contains('*') {
+            let pattern = OriginPattern::parse(entry).with_context(|| {
+                format!(
+                    "invalid pattern '{entry}' in CORS_ALLOW_ORIGINS: \
+                     expected scheme://*.domain[:port]"
+                )
+            })?;
+            patterns.push(pattern);
+        } else {
+            exact.push(
+                HeaderValue::from_str(entry)
+                    .with_context(|| format!("invalid origin '{entry}' in CORS_ALLOW_ORIGINS"))?,
+            );
+        }
+    }
+    Ok(
+        (!exact.is_empty() || !patterns.is_empty())
+            .then_some(CorsOrigins::List { exact, patterns }),
+    )
+}
+
+/// Methods allowed in CORS requests when `CORS_ALLOW_METHODS` is unset.
+pub fn default_cors_methods() -> Vec<Method> {
+    vec![
+        Method::GET,
+        Method::POST,
+        Method::PATCH,
+        Method::DELETE,
+        Method::OPTIONS,
+    ]
+}
+
+/// Request headers allowed in CORS requests when `CORS_ALLOW_HEADERS` is
+/// unset: the standard ones plus the signed-upload headers.
+pub fn default_cors_headers() -> Vec<HeaderName> {
+    vec![
+        header::CONTENT_TYPE,
+        header::AUTHORIZATION,
+        HeaderName::from_static(crate::security::USER_HEADER),
+        HeaderName::from_static(crate::security::TIMESTAMP_HEADER),
+        HeaderName::from_static(crate::security::SIGNATURE_HEADER),
+    ]
+}
+
+/// Split a comma-separated setting into its trimmed, non-empty entries.
+fn list_entries(raw: &str) -> impl Iterator<Item = &str> {
+    raw.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// Parse a comma-separated list of HTTP methods, as in `CORS_ALLOW_METHODS`.
+/// Names are case-insensitive and must be valid method tokens; the `*`
+/// wildcard is refused because it cannot be combined with credentials.
+/// An empty list is `None`, meaning the defaults apply.
+pub fn parse_cors_methods(raw: &str) -> Result<Option<Vec<Method>>> {
+    let mut methods = Vec::new();
+    for entry in list_entries(raw) {
+        if entry == "*" {
+            bail!("wildcard '*' is not supported in CORS_ALLOW_METHODS; list the methods");
+        }
+        let method = Method::from_bytes(entry.to_ascii_uppercase().as_bytes())
+            .with_context(|| format!("invalid method '{entry}' in CORS_ALLOW_METHODS"))?;
+        if !methods.contains(&method) {
+            methods.push(method);
+        }
+    }
+    Ok((!methods.is_empty()).then_some(methods))
+}
+
+/// Parse a comma-separated list of request header names, as in
+/// `CORS_ALLOW_HEADERS`. Same rules as [`parse_cors_methods`].
+pub fn parse_cors_headers(raw: &str) -> Result<Option<Vec<HeaderName>>> {
+    let mut headers = Vec::new();
+    for entry in list_entries(raw) {
+        if entry == "*" {
+            bail!("wildcard '*' is not supported in CORS_ALLOW_HEADERS; list the headers");
+        }
+        let name = HeaderName::from_bytes(entry.as_bytes())
+            .with_context(|| format!("invalid header name '{entry}' in CORS_ALLOW_HEADERS"))?;
+        if !headers.contains(&name) {
+            headers.push(name);
+        }
+    }
+    Ok((!headers.is_empty()).then_some(headers))
+}
+
+/// Parse a boolean setting: `true`/`1`/`yes`/`on` or `false`/`0`/`no`/`off`,
+/// case-insensitive. Anything else is an error naming the variable.
+pub fn parse_bool_setting(name: &str, raw: &str) -> Result<bool> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => bail!("invalid value '{raw}' for {name}: expected true or false"),
+    }
+}
+
+/// Read an optional boolean setting with [`parse_bool_setting`], falling
+/// back to `default` (with a warning) when it is unset or unparseable.
+fn bool_setting(name: &str, default: bool) -> bool {
+    let Some(raw) = var(name) else {
+        return default;
+    };
+    parse_bool_setting(name, &raw).unwrap_or_else(|e| {
+        tracing::warn!("{e}; using {default}");
+        default
+    })
 }
 
 impl Config {
@@ -35,22 +353,55 @@ impl Config {
     /// - `SERVER_PASSWORD` (optional): Password required for client authentication
     /// - `ADMIN_TOKEN` (optional): Token for administrative operations
     /// - `CORS_ALLOW_ORIGINS` (optional): Comma-separated list of allowed origins
+    ///   or `scheme://*.domain` patterns; a bare `*` needs `CORS_ALLOW_ANY_ORIGIN`
+    /// - `CORS_ALLOW_METHODS` / `CORS_ALLOW_HEADERS` (optional): Comma-separated
+    ///   methods and request headers allowed cross-origin (defaults in
+    ///   [`default_cors_methods`] and [`default_cors_headers`])
+    /// - `CORS_ALLOW_CREDENTIALS` (optional): Allow credentialed CORS requests
+    ///   (default: `false`)
+    /// - `LOG_FORMAT` (optional): `json` for one JSON object per log line, with
+    ///   span fields such as `client_addr` included; anything else keeps the
+    ///   compact human format. Read once at startup by [`json_logs`].
     pub fn from_env() -> Result<Self> {
-        let database_path = env::var("DATABASE_PATH").unwrap_or_else(|_| "murmer.db".to_string());
+        let database_path = var("DATABASE_PATH").unwrap_or_else(|| "murmer.db".to_string());
 
-        let bind_addr = env::var("BIND_ADDRESS")
-            .unwrap_or_else(|_| "0.0.0.0:3001".to_string())
+        let bind_addr = var("BIND_ADDRESS")
+            .unwrap_or_else(|| "0.0.0.0:3001".to_string())
             .parse()
             .context("failed to parse BIND_ADDRESS as a socket address")?;
 
-        let upload_dir = env::var("UPLOAD_DIR")
+        let upload_dir = var("UPLOAD_DIR")
             .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from("uploads"));
+            .unwrap_or_else(|| PathBuf::from("uploads"));
 
-        let password = env::var("SERVER_PASSWORD").ok().filter(|s| !s.is_empty());
-        let admin_token = env::var("ADMIN_TOKEN").ok().filter(|s| !s.is_empty());
+        let password = var("SERVER_PASSWORD").filter(|s| !s.is_empty());
+        let admin_token = var("ADMIN_TOKEN").filter(|s| !s.is_empty());
 
-        let cors_allowlist = Self::parse_cors_origins()?;
+        let allow_any_origin = match var("CORS_ALLOW_ANY_ORIGIN") {
+            Some(raw) => parse_bool_setting("CORS_ALLOW_ANY_ORIGIN", &raw)?,
+            None => false,
+        };
+        let cors_allowlist = match var("CORS_ALLOW_ORIGINS") {
+            Some(raw) => parse_cors_origins(&raw, allow_any_origin)?,
+            None => None,
+        };
+        let cors_methods = match var("CORS_ALLOW_METHODS") {
+            Some(raw) => parse_cors_methods(&raw)?,
+            None => None,
+        }
+        .unwrap_or_else(default_cors_methods);
+        let cors_headers = match var("CORS_ALLOW_HEADERS") {
+            Some(raw) => parse_cors_headers(&raw)?,
+            None => None,
+        }
+        .unwrap_or_else(default_cors_headers);
+        let cors_allow_credentials = match var("CORS_ALLOW_CREDENTIALS") {
+            Some(raw) => parse_bool_setting("CORS_ALLOW_CREDENTIALS", &raw)?,
+            None => false,
+        };
+        if cors_allow_credentials && cors_allowlist == Some(CorsOrigins::Any) {
+            bail!("CORS_ALLOW_CREDENTIALS cannot be enabled when CORS_ALLOW_ORIGINS is '*'");
+        }
 
         Ok(Self {
             bind_addr,
@@ -59,54 +410,349 @@ impl Config {
             password,
             admin_token,
             cors_allowlist,
+            cors_methods,
+            cors_headers,
+            cors_allow_credentials,
         })
     }
 
-    /// Parse CORS_ALLOW_ORIGINS environment variable.
-    fn parse_cors_origins() -> Result<Option<Vec<HeaderValue>>> {
-        match env::var("CORS_ALLOW_ORIGINS") {
-            Ok(raw) => {
-                let mut origins = Vec::new();
-                for origin in raw.split(',') {
-                    let trimmed = origin.trim();
-                    if trimmed.is_empty() {
-                        continue;
-                    }
-                    origins.push(HeaderValue::from_str(trimmed).with_context(|| {
-                        format!("invalid origin '{trimmed}' in CORS_ALLOW_ORIGINS")
-                    })?);
-                }
-                Ok(if origins.is_empty() {
-                    None
-                } else {
-                    Some(origins)
-                })
-            }
-            Err(_) => Ok(None),
-        }
-    }
-
     /// Build a CORS layer if CORS is configured.
     ///
     /// Returns `None` if CORS is disabled (production default).
     pub fn cors_layer(&self) -> Option<CorsLayer> {
         self.cors_allowlist.as_ref().map(|origins| {
-            let allowed = AllowOrigin::list(origins.clone());
+            let allowed = match origins {
+                CorsOrigins::Any => AllowOrigin::any(),
+                CorsOrigins::List { exact, patterns } if patterns.is_empty() => {
+                    AllowOrigin::list(exact.clone())
+                }
+                CorsOrigins::List { .. } => {
+                    let origins = origins.clone();
+                    AllowOrigin::predicate(move |origin, _| origins.allows(origin))
+                }
+            };
             CorsLayer::new()
                 .allow_origin(allowed)
-                .allow_methods([
-                    Method::GET,
-                    Method::POST,
-                    Method::PATCH,
-                    Method::DELETE,
-                    Method::OPTIONS,
-                ])
-                .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+                .allow_methods(self.cors_methods.clone())
+                .allow_headers(self.cors_headers.clone())
+                .allow_credentials(self.cors_allow_credentials)
+                // Lets the client tell a quota rejection from an oversized file.
+                .expose_headers([HeaderName::from_static(
+                    crate::upload::QUOTA_EXCEEDED_HEADER,
+                )])
         })
     }
 
     /// Get the list of allowed CORS origins for logging purposes.
-    pub fn cors_origins(&self) -> Option<&Vec<HeaderValue>> {
+    pub fn cors_origins(&self) -> Option<&CorsOrigins> {
         self.cors_allowlist.as_ref()
     }
 }
+
+/// Get the name of the default text channel: seeded on first start, where
+/// new connections land, and protected from deletion and from being made
+/// private.
+///
+/// Reads from the `DEFAULT_CHANNEL` environment variable, defaulting to
+/// `general`. Names that are not valid channel names fall back to the
+/// default.
+pub fn default_channel() -> String {
+    var("DEFAULT_CHANNEL")
+        .map(|name| name.trim().to_string())
+        .filter(|name| crate::security::validate_channel_name(name))
+        .unwrap_or_else(|| "general".to_string())
+}
+
+/// Get the window in hours over which `get-channel-activity` counts messages.
+///
+/// Reads from the `CHANNEL_ACTIVITY_WINDOW_HOURS` environment variable,
+/// defaulting to 24 ("messages today"). Clamped to 1..=720 (30 days).
+pub fn channel_activity_window_hours() -> i64 {
+    var("CHANNEL_ACTIVITY_WINDOW_HOURS")
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or(24)
+        .clamp(1, 720)
+}
+
+/// Get the maximum nesting depth accepted in an incoming WebSocket frame.
+///
+/// Reads from the `WS_MAX_JSON_DEPTH` environment variable, defaulting to 32.
+/// Clamped to 2..=128 (serde_json refuses anything deeper while parsing).
+pub fn ws_max_json_depth() -> usize {
+    var("WS_MAX_JSON_DEPTH")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(32)
+        .clamp(2, 128)
+}
+
+/// Get the maximum number of JSON values accepted in an incoming WebSocket
+/// frame, counting every object, array and scalar.
+///
+/// Reads from the `WS_MAX_JSON_NODES` environment variable, defaulting to
+/// 10000. Values below 16 are raised to 16 so ordinary frames still pass.
+pub fn ws_max_json_nodes() -> usize {
+    var("WS_MAX_JSON_NODES")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(10_000)
+        .max(16)
+}
+
+/// Get the interval between server-initiated WebSocket Ping frames.
+///
+/// Reads `HEARTBEAT_INTERVAL` in seconds, defaulting to 30. Clamped to
+/// 1..=3600.
+pub fn heartbeat_interval() -> Duration {
+    let secs = var("HEARTBEAT_INTERVAL")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(30)
+        .clamp(1, 3600);
+    Duration::from_secs(secs)
+}
+
+/// Get how long a WebSocket may go without answering a Ping before it is
+/// treated as dead and disconnected.
+///
+/// Reads `HEARTBEAT_TIMEOUT` in seconds, defaulting to 90. Never shorter than
+/// [`heartbeat_interval`], so a healthy client always gets a Ping to answer.
+pub fn heartbeat_timeout() -> Duration {
+    let secs = var("HEARTBEAT_TIMEOUT")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(90);
+    Duration::from_secs(secs).max(heartbeat_interval())
+}
+
+/// Get the names of text channels hidden from search.
+///
+/// Reads the comma-separated `SEARCH_EXCLUDED_CHANNELS` environment variable
+/// (e.g. `audit,system`). Names are matched case-insensitively; members with
+/// `MANAGE_MESSAGES` in a channel can still search it.
+pub fn search_excluded_channels() -> Vec<String> {
+    var("SEARCH_EXCLUDED_CHANNELS")
+        .map(|v| {
+            v.split(',')
+                .map(|name| name.trim().to_lowercase())
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The crate version to advertise to clients and in the `Server` header, or
+/// `None` when the operator hides it.
+///
+/// Set `HIDE_SERVER_VERSION` to `true`/`1`/`yes`/`on` to stop revealing the
+/// exact build, e.g. on internet-facing deployments.
+pub fn server_version() -> Option<&'static str> {
+    let hidden = bool_setting("HIDE_SERVER_VERSION", false);
+    (!hidden).then_some(env!("CARGO_PKG_VERSION"))
+}
+
+/// Whether logs should be emitted as JSON for log aggregation.
+///
+/// Reads from the `LOG_FORMAT` environment variable; only `json`
+/// (case-insensitive) enables it, everything else keeps the compact format.
+pub fn json_logs() -> bool {
+    var("LOG_FORMAT")
+        .map(|v| v.trim().eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+/// Whether reactions must be real Unicode emoji or registered custom emoji.
+///
+/// Reads from the `STRICT_EMOJI` environment variable, defaulting to off
+/// (any short token without whitespace is accepted, as before);
+/// `true`/`1`/`yes`/`on` enables it. Only adding is checked, so reactions
+/// stored under the permissive rules stay removable.
+pub fn strict_emoji() -> bool {
+    bool_setting("STRICT_EMOJI", false)
+}
+
+/// Whether the server unfurls links posted in chat.
+///
+/// Reads from the `ENABLE_LINK_PREVIEWS` environment variable, defaulting to
+/// off; `true`/`1`/`yes`/`on` makes the server fetch the first URL of each
+/// new message and broadcast a `link-preview` for it.
+pub fn link_previews_enabled() -> bool {
+    bool_setting("ENABLE_LINK_PREVIEWS", false)
+}
+
+/// Whether deleting a message leaves a tombstone instead of removing the row.
+///
+/// Reads from the `MESSAGE_DELETE_MODE` environment variable: `soft`
+/// (case-insensitive) keeps deleted messages as `{"deleted": true}`
+/// placeholders, anything else keeps the hard delete. Moderators can still
+/// hard-purge a message with `purge: true` in either mode.
+pub fn soft_delete_messages() -> bool {
+    var("MESSAGE_DELETE_MODE")
+        .map(|v| v.trim().eq_ignore_ascii_case("soft"))
+        .unwrap_or(false)
+}
+
+/// Whether WebSocket upgrades must arrive over TLS.
+///
+/// Reads from the `REQUIRE_SECURE_TRANSPORT` environment variable, defaulting
+/// to off; `true`/`1`/`yes`/`on` enables it. Loopback clients are exempt.
+pub fn require_secure_transport() -> bool {
+    bool_setting("REQUIRE_SECURE_TRANSPORT", false)
+}
+
+/// Get the reverse proxies whose `X-Forwarded-Proto` header is trusted, in
+/// addition to loopback.
+///
+/// Reads the comma-separated IP addresses in `TRUSTED_PROXIES`; entries that
+/// do not parse are ignored.
+pub fn trusted_proxies() -> Vec<std::net::IpAddr> {
+    var("TRUSTED_PROXIES")
+        .map(|v| {
+            v.split(',')
+                .filter_map(|ip| ip.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether non-image attachments (documents, archives, media) may be uploaded.
+///
+/// Reads from the `ALLOW_FILE_UPLOADS` environment variable, defaulting to
+/// enabled; `false`/`0`/`no`/`off` restricts `/upload` to images.
+pub fn allow_file_uploads() -> bool {
+    bool_setting("ALLOW_FILE_UPLOADS", true)
+}
+
+/// Get the number of days an unreferenced upload is kept before the reaper
+/// deletes it.
+///
+/// Reads from the `UPLOAD_RETENTION_DAYS` environment variable. Unset or `0`
+/// disables the reaper, keeping uploads forever.
+pub fn upload_retention_days() -> Option<u32> {
+    var("UPLOAD_RETENTION_DAYS")
+        .and_then(|s| s.parse::<u32>().ok())
+        .filter(|days| *days > 0)
+}
+
+/// Get the number of bytes one public key may upload per UTC day.
+///
+/// Reads from the `UPLOAD_QUOTA_BYTES_PER_DAY` environment variable. Unset or
+/// `0` disables the quota.
+pub fn upload_quota_bytes_per_day() -> Option<u64> {
+    var("UPLOAD_QUOTA_BYTES_PER_DAY")
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|bytes| *bytes > 0)
+}
+
+/// Get the largest width or height in pixels accepted for an uploaded image.
+///
+/// Reads from the `MAX_IMAGE_DIMENSION` environment variable, defaulting to
+/// 8000. Unparsable or `0` values fall back to the default.
+pub fn max_image_dimension() -> u32 {
+    var("MAX_IMAGE_DIMENSION")
+        .and_then(|s| s.parse::<u32>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(8_000)
+}
+
+/// Get the window in milliseconds over which reaction changes to one message
+/// are coalesced into a single `reaction-update` broadcast.
+///
+/// Reads from the `REACTION_BROADCAST_DEBOUNCE_MS` environment variable,
+/// defaulting to 250. `0` broadcasts every change immediately; values above
+/// 5000 are clamped so reactions never lag noticeably.
+pub fn reaction_broadcast_debounce_ms() -> u64 {
+    var("REACTION_BROADCAST_DEBOUNCE_MS")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(250)
+        .min(5_000)
+}
+
+/// Get the largest stored message frame in bytes.
+///
+/// Reads from the `MAX_MESSAGE_BYTES` environment variable, defaulting to
+/// 8192. Measured on the serialized JSON that is stored and broadcast, so
+/// attachments and reply quotes count as well as the text. Unparsable or `0`
+/// values fall back to the default.
+pub fn max_message_bytes() -> usize {
+    var("MAX_MESSAGE_BYTES")
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(8 * 1024)
+}
+
+/// Get the maximum number of participants in one voice channel.
+///
+/// Voice is a WebRTC mesh, so signaling and bandwidth grow with the square of
+/// the participant count. Reads from the `MAX_VOICE_MESH_PARTICIPANTS`
+/// environment variable; unset or `0` means no limit.
+pub fn max_voice_mesh_participants() -> Option<usize> {
+    var("MAX_VOICE_MESH_PARTICIPANTS")
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|max| *max > 0)
+}
+
+/// Get the shared secret for issuing TURN credentials.
+///
+/// Reads from the `TURN_SECRET` environment variable, which must match the
+/// `static-auth-secret` of the coturn server. Unset or empty disables
+/// `GET /turn-credentials`.
+pub fn turn_secret() -> Option<String> {
+    var("TURN_SECRET").filter(|s| !s.is_empty())
+}
+
+/// Get the TURN/STUN URLs handed out with the credentials.
+///
+/// Reads the comma-separated URLs in `TURN_URLS`, e.g.
+/// `turn:turn.example.com:3478,turns:turn.example.com:5349`; empty entries
+/// are skipped.
+pub fn turn_urls() -> Vec<String> {
+    var("TURN_URLS")
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Get how long issued TURN credentials stay valid.
+///
+/// Reads from the `TURN_CREDENTIAL_TTL_SECONDS` environment variable,
+/// defaulting to one hour. Unparseable or zero values fall back to the
+/// default.
+pub fn turn_credential_ttl() -> Duration {
+    let secs = var("TURN_CREDENTIAL_TTL_SECONDS")
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(3600);
+    Duration::from_secs(secs)
+}
+
+/// Get the path of the word list for the chat word filter.
+///
+/// Reads from the `WORDLIST_PATH` environment variable. Unset or empty
+/// disables the filter; see [`crate::word_filter`] for the file format.
+pub fn wordlist_path() -> Option<PathBuf> {
+    var("WORDLIST_PATH")
+        .filter(|s| !s.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Get what the word filter does with a message containing a banned word.
+///
+/// Reads from the `FILTER_MODE` environment variable: `mask`
+/// (case-insensitive) replaces matches with asterisks, anything else blocks
+/// the message.
+pub fn filter_mode() -> crate::word_filter::FilterMode {
+    match var("FILTER_MODE") {
+        Some(v) if v.trim().eq_ignore_ascii_case("mask") => crate::word_filter::FilterMode::Mask,
+        _ => crate::word_filter::FilterMode::Block,
+    }
+}
+
+/// Whether members with Manage Messages in a channel bypass the word filter.
+///
+/// Reads from the `FILTER_EXEMPT_MODERATORS` environment variable, defaulting
+/// to off; `true`/`1`/`yes`/`on` exempts them.
+pub fn filter_exempt_moderators() -> bool {
+    bool_setting("FILTER_EXEMPT_MODERATORS", false)
+}
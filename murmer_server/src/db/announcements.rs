@@ -0,0 +1,67 @@
+//! Server-wide announcements pushed by operators through `POST /announce`.
+//!
+//! Announcements never enter a channel's `messages` table. Only the newest
+//! `active` row matters: it is replayed to clients on `presence` until an
+//! operator clears it (or posts a newer one).
+
+use rusqlite::{OptionalExtension, params};
+
+use super::{Db, DbCall, DbError};
+
+/// A stored announcement.
+#[derive(Debug, Clone)]
+pub struct Announcement {
+    pub id: i64,
+    pub message: String,
+    /// One of `info`, `warning` or `critical` (validated by the endpoint).
+    pub level: String,
+    pub created_at: String,
+}
+
+fn row_to_announcement(row: &rusqlite::Row) -> rusqlite::Result<Announcement> {
+    Ok(Announcement {
+        id: row.get(0)?,
+        message: row.get(1)?,
+        level: row.get(2)?,
+        created_at: row.get(3)?,
+    })
+}
+
+/// Store a new announcement, making it the active one.
+pub async fn add_announcement(
+    db: &Db,
+    message: &str,
+    level: &str,
+) -> Result<Announcement, DbError> {
+    let message = message.to_owned();
+    let level = level.to_owned();
+    db.call_db(move |conn| {
+        conn.query_row(
+            "INSERT INTO announcements (message, level) VALUES (?1, ?2) \
+             RETURNING id, message, level, created_at",
+            params![message, level],
+            row_to_announcement,
+        )
+    })
+    .await
+}
+
+/// The newest announcement that has not been cleared, if any.
+pub async fn get_active_announcement(db: &Db) -> Result<Option<Announcement>, DbError> {
+    db.call_db(|conn| {
+        conn.query_row(
+            "SELECT id, message, level, created_at FROM announcements \
+             WHERE active = 1 ORDER BY id DESC LIMIT 1",
+            [],
+            row_to_announcement,
+        )
+        .optional()
+    })
+    .await
+}
+
+/// Deactivate every announcement. Returns how many were active.
+pub async fn clear_announcements(db: &Db) -> Result<usize, DbError> {
+    db.call_db(|conn| conn.execute("UPDATE announcements SET active = 0 WHERE active = 1", []))
+        .await
+}
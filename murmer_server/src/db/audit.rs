@@ -0,0 +1,69 @@
+//! Audit trail of moderation and administration actions.
+//!
+//! One row per action: who did it (`actor`, a user name or `admin` for the
+//! `ADMIN_TOKEN` endpoints), what they did (`action`, named after the
+//! WebSocket frame or endpoint that triggered it, e.g. `ban-user`), whom or
+//! what it targeted, and free-form JSON `details`. Rows are only ever
+//! appended; `GET /audit` pages through them newest first.
+
+use rusqlite::params;
+use serde_json::Value;
+
+use super::{Db, DbCall, DbError};
+
+/// A recorded action.
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub action: String,
+    /// The user, key or channel acted on, when there is one.
+    pub target: Option<String>,
+    pub details: Value,
+    pub at: String,
+}
+
+/// Append an entry to the audit log.
+pub async fn write_audit(
+    db: &Db,
+    actor: &str,
+    action: &str,
+    target: Option<&str>,
+    details: &Value,
+) -> Result<(), DbError> {
+    let actor = actor.to_owned();
+    let action = action.to_owned();
+    let target = target.map(str::to_owned);
+    let details = details.to_string();
+    db.call_db(move |conn| {
+        conn.execute(
+            "INSERT INTO audit_log (actor, action, target, details) VALUES (?1, ?2, ?3, ?4)",
+            params![actor, action, target, details],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Audit entries newest first, `limit` at a time starting `offset` rows in.
+pub async fn list_audit(db: &Db, limit: i64, offset: i64) -> Result<Vec<AuditEntry>, DbError> {
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, actor, action, target, details, at FROM audit_log \
+             ORDER BY id DESC LIMIT ?1 OFFSET ?2",
+        )?;
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            let details: String = row.get(4)?;
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                actor: row.get(1)?,
+                action: row.get(2)?,
+                target: row.get(3)?,
+                details: serde_json::from_str(&details).unwrap_or(Value::Null),
+                at: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    })
+    .await
+}
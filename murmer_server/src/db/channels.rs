@@ -116,25 +116,39 @@ pub struct ChannelRecord {
     pub category_id: Option<i32>,
     pub description: String,
     pub position: i32,
+    /// Ids of the roles allowed to post here; `None` lets everyone post.
+    pub post_roles: Option<Vec<i64>>,
+    /// Seconds each member must wait between posts here; 0 turns slow mode off.
+    pub slow_mode_seconds: i64,
+    /// Whether readers' markers are reported back to senders as receipts.
+    pub receipts_enabled: bool,
+    /// How many connections may have the channel open at once; `None` for
+    /// no limit.
+    pub max_subscribers: Option<i64>,
 }
 
-fn row_to_channel(row: &rusqlite::Row) -> rusqlite::Result<ChannelRecord> {
+pub(super) fn row_to_channel(row: &rusqlite::Row) -> rusqlite::Result<ChannelRecord> {
+    let post_roles: Option<String> = row.get(5)?;
     Ok(ChannelRecord {
         id: row.get(0)?,
         name: row.get(1)?,
         category_id: row.get(2)?,
         description: row.get(3)?,
         position: row.get(4)?,
+        post_roles: post_roles.and_then(|raw| serde_json::from_str(&raw).ok()),
+        slow_mode_seconds: row.get(6)?,
+        receipts_enabled: row.get(7)?,
+        max_subscribers: row.get(8)?,
     })
 }
 
 /// Retrieve the list of text channels with their category assignments,
 /// ordered by their custom position (per category) then name.
-pub async fn get_channels(db: &Db) -> Vec<ChannelRecord> {
+pub async fn get_channels(db: &Db) -> Result<Vec<ChannelRecord>, DbError> {
     db.call_db(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, name, category_id, description, position FROM channels \
-             ORDER BY position, name",
+            "SELECT id, name, category_id, description, position, post_roles, slow_mode_seconds, \
+             receipts_enabled, max_subscribers FROM channels ORDER BY position, name",
         )?;
         let rows = stmt
             .query_map([], row_to_channel)?
@@ -142,7 +156,12 @@ pub async fn get_channels(db: &Db) -> Vec<ChannelRecord> {
         Ok(rows)
     })
     .await
-    .unwrap_or_default()
+}
+
+/// Count the text channels.
+pub async fn count_channels(db: &Db) -> Result<i64, DbError> {
+    db.call_db(|conn| conn.query_row("SELECT COUNT(*) FROM channels", [], |row| row.get(0)))
+        .await
 }
 
 /// Look up a channel ID by name. Returns `None` if not found.
@@ -168,7 +187,8 @@ pub async fn get_channel_by_id(db: &Db, id: i32) -> Option<ChannelRecord> {
     db.call_db(move |conn| {
         let record = conn
             .query_row(
-                "SELECT id, name, category_id, description, position FROM channels WHERE id = ?1",
+                "SELECT id, name, category_id, description, position, post_roles, \
+                 slow_mode_seconds, receipts_enabled, max_subscribers FROM channels WHERE id = ?1",
                 params![id],
                 row_to_channel,
             )
@@ -193,7 +213,8 @@ pub async fn add_channel(
             "INSERT INTO channels (name, category_id, position) VALUES (?1, ?2, \
                 (SELECT COALESCE(MAX(position) + 1, 0) FROM channels WHERE category_id IS ?2)) \
              ON CONFLICT (name) DO NOTHING \
-             RETURNING id, name, category_id, description, position",
+             RETURNING id, name, category_id, description, position, post_roles, \
+                 slow_mode_seconds, receipts_enabled, max_subscribers",
         )?;
         let mut rows = stmt.query(params![name, category_id])?;
         match rows.next()? {
@@ -204,6 +225,42 @@ pub async fn add_channel(
     .await
 }
 
+/// Outcome of [`rename_channel`].
+pub enum RenameChannelResult {
+    Renamed,
+    NameTaken,
+    NotFound,
+}
+
+/// Rename a text channel. Messages, pins and settings are keyed by id, so
+/// the channel keeps its history under the new name.
+pub async fn rename_channel(db: &Db, id: i32, name: &str) -> Result<RenameChannelResult, DbError> {
+    let name = name.to_owned();
+    db.call_db(move |conn| {
+        let taken = conn
+            .query_row(
+                "SELECT 1 FROM channels WHERE name = ?2 AND id != ?1",
+                params![id, name],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if taken {
+            return Ok(RenameChannelResult::NameTaken);
+        }
+        let changed = conn.execute(
+            "UPDATE channels SET name = ?2 WHERE id = ?1",
+            params![id, name],
+        )?;
+        Ok(if changed > 0 {
+            RenameChannelResult::Renamed
+        } else {
+            RenameChannelResult::NotFound
+        })
+    })
+    .await
+}
+
 /// Update a text channel's description/topic. Returns true if a row was updated.
 pub async fn set_channel_description(db: &Db, id: i32, description: &str) -> Result<bool, DbError> {
     let description = description.to_owned();
@@ -217,6 +274,98 @@ pub async fn set_channel_description(db: &Db, id: i32, description: &str) -> Res
     .await
 }
 
+/// Set how many days a text channel keeps its messages (`None` keeps them
+/// forever). Returns `false` if the channel does not exist.
+pub async fn set_channel_retention(
+    db: &Db,
+    id: i32,
+    retention_days: Option<i64>,
+) -> Result<bool, DbError> {
+    db.call_db(move |conn| {
+        let count = conn.execute(
+            "UPDATE channels SET retention_days = ?2 WHERE id = ?1",
+            params![id, retention_days],
+        )?;
+        Ok(count > 0)
+    })
+    .await
+}
+
+/// Restrict posting in a text channel to the roles in `role_ids` (`None`
+/// lets everyone post again). Returns `false` if the channel does not exist.
+pub async fn set_channel_post_roles(
+    db: &Db,
+    id: i32,
+    role_ids: Option<&[i64]>,
+) -> Result<bool, DbError> {
+    let post_roles = role_ids.map(|ids| serde_json::json!(ids).to_string());
+    db.call_db(move |conn| {
+        let count = conn.execute(
+            "UPDATE channels SET post_roles = ?2 WHERE id = ?1",
+            params![id, post_roles],
+        )?;
+        Ok(count > 0)
+    })
+    .await
+}
+
+/// Set a text channel's slow mode interval in seconds (0 turns it off).
+/// Returns `false` if the channel does not exist.
+pub async fn set_channel_slow_mode(db: &Db, id: i32, seconds: i64) -> Result<bool, DbError> {
+    db.call_db(move |conn| {
+        let count = conn.execute(
+            "UPDATE channels SET slow_mode_seconds = ?2 WHERE id = ?1",
+            params![id, seconds],
+        )?;
+        Ok(count > 0)
+    })
+    .await
+}
+
+/// Turn read receipts on or off for a text channel. Returns `false` if the
+/// channel does not exist.
+pub async fn set_channel_receipts(db: &Db, id: i32, enabled: bool) -> Result<bool, DbError> {
+    db.call_db(move |conn| {
+        let count = conn.execute(
+            "UPDATE channels SET receipts_enabled = ?2 WHERE id = ?1",
+            params![id, enabled],
+        )?;
+        Ok(count > 0)
+    })
+    .await
+}
+
+/// Set how many connections may have a text channel open at once (`None`
+/// lifts the limit). Returns `false` if the channel does not exist.
+pub async fn set_channel_max_subscribers(
+    db: &Db,
+    id: i32,
+    max: Option<i64>,
+) -> Result<bool, DbError> {
+    db.call_db(move |conn| {
+        let count = conn.execute(
+            "UPDATE channels SET max_subscribers = ?2 WHERE id = ?1",
+            params![id, max],
+        )?;
+        Ok(count > 0)
+    })
+    .await
+}
+
+/// Text channels with a retention policy, as `(channel_id, retention_days)`.
+pub async fn get_channel_retentions(db: &Db) -> Result<Vec<(i32, i64)>, DbError> {
+    db.call_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, retention_days FROM channels WHERE retention_days IS NOT NULL ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+    .await
+}
+
 /// Move a text channel to the end of a different category (or out of any
 /// category). Returns the channel's new position, or `None` if it does not exist.
 pub async fn move_channel(
@@ -311,7 +460,7 @@ fn row_to_voice_channel(row: &rusqlite::Row) -> rusqlite::Result<VoiceChannelRec
 }
 
 /// Retrieve all voice channels ordered by their custom position then name.
-pub async fn get_voice_channels(db: &Db) -> Vec<VoiceChannelRecord> {
+pub async fn get_voice_channels(db: &Db) -> Result<Vec<VoiceChannelRecord>, DbError> {
     db.call_db(|conn| {
         let mut stmt = conn.prepare(
             "SELECT id, name, quality, bitrate, category_id, position FROM voice_channels \
@@ -323,7 +472,6 @@ pub async fn get_voice_channels(db: &Db) -> Vec<VoiceChannelRecord> {
         Ok(rows)
     })
     .await
-    .unwrap_or_default()
 }
 
 /// Look up a voice channel record by ID. Returns `None` if not found.
@@ -0,0 +1,96 @@
+//! Message reports ("flags") awaiting moderator review.
+//!
+//! One row per reporter and message: the composite primary key makes a second
+//! report of the same message by the same user a no-op. A flag stays open
+//! until a moderator resolves it, which stamps `resolved_by`/`resolved_at` on
+//! every open flag for that message. Like pins, flags reference messages by id
+//! without a foreign key, so a flag can outlive the message it points at; the
+//! listing then carries no message details.
+
+use rusqlite::params;
+use serde_json::Value;
+
+use super::{Db, DbCall, DbError, NOW_UTC};
+
+/// An open report, with the flagged message's author and text when the
+/// message still exists.
+#[derive(Debug, Clone)]
+pub struct FlagRecord {
+    pub message_id: i64,
+    pub channel_id: Option<i32>,
+    pub reporter: String,
+    pub reason: String,
+    pub at: String,
+    pub author: Option<String>,
+    pub text: Option<String>,
+}
+
+/// Record `reporter`'s flag on a message. Returns `false` when they already
+/// flagged it.
+pub async fn add_flag(
+    db: &Db,
+    message_id: i64,
+    reporter: &str,
+    reason: &str,
+) -> Result<bool, DbError> {
+    let reporter = reporter.to_owned();
+    let reason = reason.to_owned();
+    db.call_db(move |conn| {
+        let inserted = conn.execute(
+            "INSERT INTO message_flags (message_id, reporter, reason) VALUES (?1, ?2, ?3) \
+             ON CONFLICT (message_id, reporter) DO NOTHING",
+            params![message_id, reporter, reason],
+        )?;
+        Ok(inserted > 0)
+    })
+    .await
+}
+
+/// Open flags oldest first, `limit` at a time starting `offset` rows in.
+pub async fn list_open_flags(db: &Db, limit: i64, offset: i64) -> Result<Vec<FlagRecord>, DbError> {
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT f.message_id, m.channel_id, f.reporter, f.reason, f.at, m.content \
+             FROM message_flags f LEFT JOIN messages m ON m.id = f.message_id \
+             WHERE f.resolved_at IS NULL ORDER BY f.at, f.message_id LIMIT ?1 OFFSET ?2",
+        )?;
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            let content: Option<String> = row.get(5)?;
+            let message = content.and_then(|c| serde_json::from_str::<Value>(&c).ok());
+            let field = |name: &str| {
+                message
+                    .as_ref()
+                    .and_then(|m| m.get(name))
+                    .and_then(Value::as_str)
+                    .map(str::to_owned)
+            };
+            Ok(FlagRecord {
+                message_id: row.get(0)?,
+                channel_id: row.get(1)?,
+                reporter: row.get(2)?,
+                reason: row.get(3)?,
+                at: row.get(4)?,
+                author: field("user"),
+                text: field("text"),
+            })
+        })?;
+        rows.collect()
+    })
+    .await
+}
+
+/// Resolve every open flag on a message. Returns how many were resolved.
+pub async fn resolve_flags(db: &Db, message_id: i64, resolver: &str) -> Result<usize, DbError> {
+    let resolver = resolver.to_owned();
+    db.call_db(move |conn| {
+        let resolved = conn.execute(
+            &format!(
+                "UPDATE message_flags SET resolved_by = ?2, resolved_at = {NOW_UTC} \
+                 WHERE message_id = ?1 AND resolved_at IS NULL"
+            ),
+            params![message_id, resolver],
+        )?;
+        Ok(resolved)
+    })
+    .await
+}
@@ -0,0 +1,46 @@
+//! Link previews unfurled for chat messages, cached by URL.
+//!
+//! Each row holds the serialized OpenGraph preview of one URL and when it was
+//! fetched, so a link posted again within the cache window is answered
+//! without contacting the site. Only successful fetches are stored.
+
+use chrono::{Duration, Utc};
+use rusqlite::{OptionalExtension, params};
+
+use super::{Db, DbCall, DbError, NOW_UTC, sql_timestamp};
+
+/// The cached preview JSON for `url`, if it was fetched within `max_age`.
+pub async fn get_link_preview(
+    db: &Db,
+    url: &str,
+    max_age: Duration,
+) -> Result<Option<String>, DbError> {
+    let url = url.to_owned();
+    let cutoff = sql_timestamp(Utc::now() - max_age);
+    db.call_db(move |conn| {
+        conn.query_row(
+            "SELECT preview FROM link_previews WHERE url = ?1 AND fetched_at >= ?2",
+            params![url, cutoff],
+            |row| row.get(0),
+        )
+        .optional()
+    })
+    .await
+}
+
+/// Store (or refresh) the preview JSON for `url`.
+pub async fn store_link_preview(db: &Db, url: &str, preview: &str) -> Result<(), DbError> {
+    let (url, preview) = (url.to_owned(), preview.to_owned());
+    db.call_db(move |conn| {
+        conn.execute(
+            &format!(
+                "INSERT INTO link_previews (url, preview) VALUES (?1, ?2) \
+                 ON CONFLICT (url) DO UPDATE \
+                 SET preview = excluded.preview, fetched_at = {NOW_UTC}"
+            ),
+            params![url, preview],
+        )?;
+        Ok(())
+    })
+    .await
+}
@@ -3,13 +3,16 @@
 use std::collections::HashMap;
 
 use axum::extract::ws::{Message, WebSocket};
+use chrono::{DateTime, Utc};
 use futures::SinkExt;
-use rusqlite::params;
+use rusqlite::{OptionalExtension, params};
 use serde_json::Value;
 use tracing::error;
 
+use super::channels::row_to_channel;
 use super::reactions::get_reactions_for_messages;
-use super::{Db, DbCall, DbError};
+use super::uploads::link_message_uploads;
+use super::{ChannelRecord, Db, DbCall, DbError, NOW_UTC, sql_timestamp};
 
 fn row_to_id_content(row: &rusqlite::Row) -> rusqlite::Result<(i64, String)> {
     Ok((row.get(0)?, row.get(1)?))
@@ -44,57 +47,253 @@ pub async fn fetch_history(
     .await
 }
 
-/// Insert a message into a channel and return its id.
+/// A stored message row as read by the export stream.
+#[derive(Debug, Clone)]
+pub struct StoredMessage {
+    pub id: i64,
+    pub channel_id: i32,
+    pub content: String,
+    pub created_at: Option<String>,
+}
+
+/// Read up to `limit` messages with ids above `after`, oldest first, from one
+/// channel or from all of them. Exports page through the table with this so
+/// the connection is released between batches instead of being held for the
+/// whole dump.
+pub async fn fetch_messages_after(
+    db: &Db,
+    channel_id: Option<i32>,
+    after: i64,
+    limit: i64,
+) -> Result<Vec<StoredMessage>, DbError> {
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, channel_id, content, created_at FROM messages \
+             WHERE id > ?1 AND (?2 IS NULL OR channel_id = ?2) ORDER BY id LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![after, channel_id, limit], |row| {
+            Ok(StoredMessage {
+                id: row.get(0)?,
+                channel_id: row.get(1)?,
+                content: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    })
+    .await
+}
+
+/// A validated message from an export file, ready to be imported.
+#[derive(Debug, Clone)]
+pub struct ImportedMessage {
+    /// `<source>:<id>` the message had in its source instance; imports skip
+    /// ids already present.
+    pub external_id: String,
+    /// Target text channel by name, created when missing.
+    pub channel: String,
+    /// Message JSON without export-only fields; `channelId` is rewritten to
+    /// the local channel on insert.
+    pub content: Value,
+    pub created_at: DateTime<Utc>,
+    /// Emoji -> users, as in the export.
+    pub reactions: HashMap<String, Vec<String>>,
+}
+
+/// Result of [`import_messages`].
+#[derive(Default)]
+pub struct ImportOutcome {
+    /// Per input message: `true` when inserted, `false` when its
+    /// `external_id` was already present.
+    pub inserted: Vec<bool>,
+    /// Channels created because no channel of that name existed.
+    pub created_channels: Vec<ChannelRecord>,
+}
+
+/// Insert imported messages and their reactions in a single transaction,
+/// creating missing channels at the end of the uncategorized list. Messages
+/// whose `external_id` already exists are left untouched, so an import can be
+/// repeated safely.
+pub async fn import_messages(
+    db: &Db,
+    messages: Vec<ImportedMessage>,
+) -> Result<ImportOutcome, DbError> {
+    db.call_db(move |conn| {
+        let tx = conn.transaction()?;
+        let mut outcome = ImportOutcome::default();
+        let mut channel_ids: HashMap<String, i32> = HashMap::new();
+        for message in messages {
+            let channel_id = match channel_ids.get(&message.channel) {
+                Some(id) => *id,
+                None => {
+                    let created = tx
+                        .query_row(
+                            "INSERT INTO channels (name, position) VALUES (?1, \
+                                (SELECT COALESCE(MAX(position) + 1, 0) FROM channels \
+                                 WHERE category_id IS NULL)) \
+                             ON CONFLICT (name) DO NOTHING \
+                             RETURNING id, name, category_id, description, position, post_roles, \
+                             slow_mode_seconds, receipts_enabled, max_subscribers",
+                            params![message.channel],
+                            row_to_channel,
+                        )
+                        .optional()?;
+                    let id = match created {
+                        Some(record) => {
+                            let id = record.id;
+                            outcome.created_channels.push(record);
+                            id
+                        }
+                        None => tx.query_row(
+                            "SELECT id FROM channels WHERE name = ?1",
+                            params![message.channel],
+                            |row| row.get(0),
+                        )?,
+                    };
+                    channel_ids.insert(message.channel.clone(), id);
+                    id
+                }
+            };
+            let content = message.content.to_string();
+            let id: Option<i64> = tx
+                .query_row(
+                    "INSERT INTO messages (channel_id, content, created_at, external_id) \
+                     VALUES (?1, json_set(?2, '$.channelId', ?1), ?3, ?4) \
+                     ON CONFLICT (external_id) WHERE external_id IS NOT NULL DO NOTHING \
+                     RETURNING id",
+                    params![
+                        channel_id,
+                        content,
+                        sql_timestamp(message.created_at),
+                        message.external_id,
+                    ],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if let Some(id) = id {
+                link_message_uploads(&tx, id, &content)?;
+                for (emoji, users) in &message.reactions {
+                    for user in users {
+                        tx.execute(
+                            "INSERT OR IGNORE INTO reactions (message_id, user_name, emoji) \
+                             VALUES (?1, ?2, ?3)",
+                            params![id, user, emoji],
+                        )?;
+                    }
+                }
+            }
+            outcome.inserted.push(id.is_some());
+        }
+        tx.commit()?;
+        Ok(outcome)
+    })
+    .await
+}
+
+/// Insert a message into a channel and return its id. The server-side insert
+/// time is recorded in `created_at`.
 pub async fn insert_message(db: &Db, channel_id: i32, content: &str) -> Result<i64, DbError> {
+    insert_message_expiring(db, channel_id, content, None).await
+}
+
+/// Insert a message that is deleted by the ephemeral sweeper once `expires_at`
+/// passes (`None` stores a regular message). Returns the new id.
+pub async fn insert_message_expiring(
+    db: &Db,
+    channel_id: i32,
+    content: &str,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<i64, DbError> {
     let content = content.to_owned();
+    let expires_at = expires_at.map(sql_timestamp);
     db.call_db(move |conn| {
-        let id = conn.query_row(
-            "INSERT INTO messages (channel_id, content) VALUES (?1, ?2) RETURNING id",
-            params![channel_id, content],
+        let tx = conn.transaction()?;
+        let id = tx.query_row(
+            &format!(
+                "INSERT INTO messages (channel_id, content, created_at, expires_at) \
+                 VALUES (?1, ?2, {NOW_UTC}, ?3) RETURNING id"
+            ),
+            params![channel_id, content, expires_at],
             |row| row.get(0),
         )?;
+        link_message_uploads(&tx, id, &content)?;
+        tx.commit()?;
         Ok(id)
     })
     .await
 }
 
-/// Send a slice of messages over the WebSocket as a `history` payload.
-pub async fn send_history(
+/// Count the messages stored per channel since `since`, keyed by channel id.
+/// Channels without recent messages are absent from the map.
+pub async fn count_messages_since(
+    db: &Db,
+    since: DateTime<Utc>,
+) -> Result<HashMap<i32, i64>, DbError> {
+    let since = sql_timestamp(since);
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT channel_id, COUNT(*) FROM messages WHERE created_at >= ?1 \
+             GROUP BY channel_id",
+        )?;
+        let rows = stmt.query_map(params![since], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    })
+    .await
+}
+
+/// Load a slice of a channel's history, oldest first, each message carrying
+/// its id and current reactions. Shared by the WebSocket `history` payload
+/// and the `GET /history` endpoint.
+pub async fn history_messages(
     db: &Db,
-    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
     channel_id: i32,
     before: Option<i64>,
     limit: i64,
-) {
-    match fetch_history(db, channel_id, before, limit).await {
-        Ok(rows) => {
-            let ids: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
-            let reaction_map = if ids.is_empty() {
+) -> Result<Vec<Value>, DbError> {
+    let rows = fetch_history(db, channel_id, before, limit).await?;
+    let ids: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
+    let reaction_map = if ids.is_empty() {
+        HashMap::new()
+    } else {
+        match get_reactions_for_messages(db, &ids).await {
+            Ok(map) => map,
+            Err(e) => {
+                error!("db reaction load error: {e}");
                 HashMap::new()
-            } else {
-                match get_reactions_for_messages(db, &ids).await {
-                    Ok(map) => map,
-                    Err(e) => {
-                        error!("db reaction load error: {e}");
-                        HashMap::new()
-                    }
-                }
-            };
+            }
+        }
+    };
 
-            let mut msgs = Vec::new();
-            for (id, content) in rows.into_iter().rev() {
-                if let Ok(mut val) = serde_json::from_str::<Value>(&content) {
-                    val["id"] = Value::from(id);
-                    if let Some(reactions) = reaction_map.get(&id)
-                        && let Ok(value) = serde_json::to_value(reactions)
-                    {
-                        val["reactions"] = value;
-                    }
-                    msgs.push(val);
-                }
+    let mut msgs = Vec::new();
+    for (id, content) in rows.into_iter().rev() {
+        if let Ok(mut val) = serde_json::from_str::<Value>(&content) {
+            val["id"] = Value::from(id);
+            if let Some(reactions) = reaction_map.get(&id)
+                && let Ok(value) = serde_json::to_value(reactions)
+            {
+                val["reactions"] = value;
             }
+            msgs.push(val);
+        }
+    }
+    Ok(msgs)
+}
+
+/// Send a slice of messages over the WebSocket as a `history` payload,
+/// gzipped into a binary frame when the client opted in with `gzip`.
+pub async fn send_history(
+    db: &Db,
+    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    channel_id: i32,
+    before: Option<i64>,
+    limit: i64,
+    gzip: bool,
+) {
+    match history_messages(db, channel_id, before, limit).await {
+        Ok(msgs) => {
             let payload = serde_json::json!({"type": "history", "messages": msgs});
-            let _ = sender.send(Message::Text(payload.to_string().into())).await;
+            let frame = crate::ws::encoding::history_frame(payload.to_string(), gzip);
+            let _ = sender.send(frame).await;
         }
         Err(e) => error!("db history error: {e}"),
     }
@@ -189,19 +388,156 @@ pub async fn fetch_thread(
         .collect())
 }
 
-/// List messages flagged as ephemeral, as `(id, channel_id, content)` rows.
-///
-/// Rows are prefiltered with LIKE on the serialized JSON; callers must verify
-/// the parsed `ephemeral` field (the pattern also matches the text appearing
-/// inside a message body).
-pub async fn get_ephemeral_messages(db: &Db) -> Result<Vec<(i64, i32, String)>, DbError> {
-    db.call_db(|conn| {
+/// Fetch a message together with up to `radius` messages on either side of it
+/// in the same channel, ordered oldest first. Empty when the message does not
+/// exist in that channel.
+pub async fn fetch_message_context(
+    db: &Db,
+    channel_id: i32,
+    message_id: i64,
+    radius: i64,
+) -> Result<Vec<(i64, String)>, DbError> {
+    db.call_db(move |conn| {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM messages WHERE id = ?1 AND channel_id = ?2)",
+            params![message_id, channel_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Ok(Vec::new());
+        }
+        let mut stmt = conn.prepare(
+            "SELECT id, content FROM (\
+                 SELECT id, content FROM messages WHERE channel_id = ?1 AND id < ?2 \
+                 ORDER BY id DESC LIMIT ?3) \
+             UNION ALL \
+             SELECT id, content FROM (\
+                 SELECT id, content FROM messages WHERE channel_id = ?1 AND id >= ?2 \
+                 ORDER BY id ASC LIMIT ?3 + 1) \
+             ORDER BY id ASC",
+        )?;
+        let rows = stmt
+            .query_map(params![channel_id, message_id, radius], row_to_id_content)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+    .await
+}
+
+/// List ephemeral messages whose expiry is at or before `now`, as
+/// `(id, channel_id)` rows, oldest expiry first.
+pub async fn get_expired_messages(db: &Db, now: DateTime<Utc>) -> Result<Vec<(i64, i32)>, DbError> {
+    let now = sql_timestamp(now);
+    db.call_db(move |conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, channel_id, content FROM messages \
-             WHERE content LIKE '%\"ephemeral\":true%'",
+            "SELECT id, channel_id FROM messages \
+             WHERE expires_at IS NOT NULL AND expires_at <= ?1 ORDER BY expires_at",
         )?;
         let rows = stmt
-            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .query_map(params![now], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+    .await
+}
+
+/// Delete a channel's messages created more than `days` ago, along with
+/// their pins and reactions. Selection goes by `created_at` alone: imported
+/// rows keep their original timestamps under fresh ids, so ids say nothing
+/// about age. Returns the deleted ids, oldest first.
+pub async fn purge_old_messages(db: &Db, channel_id: i32, days: i64) -> Result<Vec<i64>, DbError> {
+    let cutoff = sql_timestamp(Utc::now() - chrono::Duration::days(days));
+    db.call_db(move |conn| {
+        let tx = conn.transaction()?;
+        let ids = {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM messages WHERE channel_id = ?1 AND created_at < ?2 ORDER BY id",
+            )?;
+            stmt.query_map(params![channel_id, cutoff], |row| row.get(0))?
+                .collect::<Result<Vec<i64>, _>>()?
+        };
+        if ids.is_empty() {
+            return Ok(ids);
+        }
+        tx.execute(
+            "DELETE FROM pins WHERE message_id IN \
+             (SELECT id FROM messages WHERE channel_id = ?1 AND created_at < ?2)",
+            params![channel_id, cutoff],
+        )?;
+        tx.execute(
+            "DELETE FROM reactions WHERE message_id IN \
+             (SELECT id FROM messages WHERE channel_id = ?1 AND created_at < ?2)",
+            params![channel_id, cutoff],
+        )?;
+        tx.execute(
+            "DELETE FROM messages WHERE channel_id = ?1 AND created_at < ?2",
+            params![channel_id, cutoff],
+        )?;
+        tx.commit()?;
+        Ok(ids)
+    })
+    .await
+}
+
+/// Delete the newest `limit` messages whose stored `user` is `user`, in one
+/// channel or across all of them, along with their pins and reactions.
+/// Returns the deleted messages as `(id, channel_id)` pairs, newest first.
+pub async fn delete_messages_by_user(
+    db: &Db,
+    channel_id: Option<i32>,
+    user: &str,
+    limit: i64,
+) -> Result<Vec<(i64, i32)>, DbError> {
+    let user = user.to_owned();
+    db.call_db(move |conn| {
+        let tx = conn.transaction()?;
+        let deleted = {
+            let mut stmt = tx.prepare(
+                "SELECT id, channel_id FROM messages \
+                 WHERE json_extract(content, '$.user') = ?1 \
+                 AND (?2 IS NULL OR channel_id = ?2) \
+                 ORDER BY id DESC LIMIT ?3",
+            )?;
+            stmt.query_map(params![user, channel_id, limit], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<Vec<(i64, i32)>, _>>()?
+        };
+        {
+            let mut pins = tx.prepare("DELETE FROM pins WHERE message_id = ?1")?;
+            let mut reactions = tx.prepare("DELETE FROM reactions WHERE message_id = ?1")?;
+            let mut messages = tx.prepare("DELETE FROM messages WHERE id = ?1")?;
+            for (id, _) in &deleted {
+                pins.execute(params![id])?;
+                reactions.execute(params![id])?;
+                messages.execute(params![id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(deleted)
+    })
+    .await
+}
+
+/// Fetch specific messages by id as `(id, channel_id, content)` rows, ordered
+/// by id. Ids that do not exist are simply absent from the result.
+pub async fn fetch_messages_by_ids(
+    db: &Db,
+    ids: &[i64],
+) -> Result<Vec<(i64, i32, String)>, DbError> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ids = ids.to_vec();
+    db.call_db(move |conn| {
+        let placeholders = vec!["?"; ids.len()].join(",");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, channel_id, content FROM messages WHERE id IN ({placeholders}) ORDER BY id"
+        ))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(ids.iter()), |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(rows)
     })
@@ -273,15 +609,74 @@ pub async fn update_message_content(
 ) -> Result<bool, DbError> {
     let content = content.to_owned();
     db.call_db(move |conn| {
-        let affected = conn.execute(
+        let tx = conn.transaction()?;
+        let affected = tx.execute(
             "UPDATE messages SET content = ?2 WHERE id = ?1",
             params![message_id, content],
         )?;
+        if affected > 0 {
+            link_message_uploads(&tx, message_id, &content)?;
+        }
+        tx.commit()?;
         Ok(affected > 0)
     })
     .await
 }
 
+/// Fields of a message payload that survive soft deletion: enough to keep
+/// the tombstone in place in its channel and thread.
+const TOMBSTONE_FIELDS: [&str; 5] = ["type", "channelId", "threadId", "time", "timestamp"];
+
+/// Replace a message with a `{"deleted": true}` tombstone, recording when
+/// and by whom. The id, reactions and thread position stay, so replies and
+/// history remain coherent; the author, text and attachments are dropped and
+/// any pin is removed. Returns `false` if the message does not exist or is
+/// already a tombstone.
+pub async fn soft_delete_message(
+    db: &Db,
+    message_id: i64,
+    deleted_by: &str,
+) -> Result<bool, DbError> {
+    let deleted_by = deleted_by.to_owned();
+    db.call_db(move |conn| {
+        let content: Option<String> = conn
+            .query_row(
+                "SELECT content FROM messages WHERE id = ?1 AND deleted_at IS NULL",
+                params![message_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(content) = content else {
+            return Ok(false);
+        };
+        let original = serde_json::from_str::<Value>(&content).unwrap_or(Value::Null);
+        let mut tombstone = serde_json::Map::new();
+        for field in TOMBSTONE_FIELDS {
+            if let Some(value) = original.get(field) {
+                tombstone.insert(field.to_owned(), value.clone());
+            }
+        }
+        tombstone.insert("deleted".to_owned(), Value::Bool(true));
+        let tombstone = Value::Object(tombstone).to_string();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM pins WHERE message_id = ?1",
+            params![message_id],
+        )?;
+        tx.execute(
+            &format!(
+                "UPDATE messages SET content = ?2, deleted_at = {NOW_UTC}, deleted_by = ?3 \
+                 WHERE id = ?1"
+            ),
+            params![message_id, tombstone, deleted_by],
+        )?;
+        link_message_uploads(&tx, message_id, &tombstone)?;
+        tx.commit()?;
+        Ok(true)
+    })
+    .await
+}
+
 /// Delete a message by ID, along with any pin referencing it.
 /// Returns `true` if a message row was removed.
 pub async fn delete_message(db: &Db, message_id: i64) -> Result<bool, DbError> {
@@ -2,65 +2,189 @@
 //!
 //! Persistence uses an embedded SQLite database. The connection runs on a
 //! dedicated thread (via `tokio-rusqlite`); [`Db`] is a cheap clonable handle
-//! that serializes all queries onto that thread.
+//! that serializes all queries onto that thread. If the thread dies (a query
+//! closure panicked), the next call reopens the database and swaps the new
+//! connection in for every clone of the handle. An in-memory database dies
+//! with its thread, so it is never reopened; every later call fails instead.
 //!
 //! Channels are identified by an auto-incrementing integer `id`. The `name`
 //! column remains for display purposes. Messages reference channels via
 //! `channel_id`.
 //!
 //! Submodules group queries by domain:
+//! - [`announcements`] – operator announcements replayed on connect
+//! - [`audit`] – append-only log of moderation and admin actions
 //! - [`channels`] – text channels, voice channels and categories
 //! - [`direct_messages`] – private messages between two users
 //! - [`emojis`] – custom server emoji registrations
+//! - [`flags`] – user reports of messages for moderator review
 //! - [`identity`] – server name, description, welcome message and icon
+//! - [`link_previews`] – cached OpenGraph previews of posted links
 //! - [`messages`] – message CRUD and history retrieval
 //! - [`moderation`] – ban and mute persistence
 //! - [`pins`] – persisted message pins per channel
+//! - [`profiles`] – display names and bios keyed by public key
+//! - [`read_markers`] – how far each user has read in each text channel
 //! - [`reactions`] – emoji reaction operations
 //! - [`roles`] – user role persistence
+//! - [`scheduled`] – messages queued for future delivery
 //! - [`screenshare`] – server-wide screen share bitrate cap
 //! - [`stats`] – lifetime user statistics (double opt-in gated)
+//! - [`uploads`] – ownership records and daily byte usage for uploads
 //! - [`users`] – user name to public key bindings
+//! - [`webhooks`] – outgoing webhook registrations per channel
 //! - [`wiki`] – per-channel Markdown wiki pages with revision history
 
+mod announcements;
+mod audit;
 mod channel_overrides;
 mod channels;
 mod direct_messages;
 mod emojis;
+mod flags;
 mod identity;
+mod link_previews;
 mod messages;
 mod moderation;
 mod pins;
+mod profiles;
 mod reactions;
+mod read_markers;
 mod roles;
+mod scheduled;
 mod screenshare;
 mod stats;
+mod uploads;
 mod users;
+mod webhooks;
 mod wiki;
 
+pub use announcements::*;
+pub use audit::*;
 pub use channel_overrides::*;
 pub use channels::*;
 pub use direct_messages::*;
 pub use emojis::*;
+pub use flags::*;
 pub use identity::*;
+pub use link_previews::*;
 pub use messages::*;
 pub use moderation::*;
 pub use pins::*;
+pub use profiles::*;
 pub use reactions::*;
+pub use read_markers::*;
 pub use roles::*;
+pub use scheduled::*;
 pub use screenshare::*;
 pub use stats::*;
+pub use uploads::*;
 pub use users::*;
+pub use webhooks::*;
 pub use wiki::*;
 
-use std::path::Path;
-use tracing::error;
+use std::{
+    path::Path,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Delay before retrying after the first failed reopen; doubles per failure.
+const RECONNECT_BACKOFF_BASE: Duration = Duration::from_millis(500);
+/// Upper bound on the delay between reopen attempts.
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Handle to the SQLite connection thread. Clonable and shared across tasks;
+/// all clones see a reconnect.
+#[derive(Clone, Debug)]
+pub struct Db {
+    conn: Arc<RwLock<Arc<tokio_rusqlite::Connection>>>,
+    path: Arc<str>,
+    reconnect: Arc<tokio::sync::Mutex<ReconnectState>>,
+    round_trips: Arc<AtomicU64>,
+}
+
+/// Backoff bookkeeping for reopening a dead connection.
+#[derive(Debug, Default)]
+struct ReconnectState {
+    failures: u32,
+    retry_at: Option<Instant>,
+}
 
-/// Handle to the SQLite connection thread. Clonable and shared across tasks.
-pub type Db = tokio_rusqlite::Connection;
 /// Error type returned by all database operations.
 pub type DbError = tokio_rusqlite::Error;
 
+impl Db {
+    /// Closures sent to the connection thread so far, across all clones.
+    /// Lets tests and diagnostics compare how chatty a code path is.
+    pub fn round_trips(&self) -> u64 {
+        self.round_trips.load(Ordering::Relaxed)
+    }
+
+    fn connection(&self) -> Arc<tokio_rusqlite::Connection> {
+        self.conn
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replace `dead` with a freshly opened connection, unless another caller
+    /// already did. While reopening keeps failing, attempts are spaced out
+    /// with exponential backoff and calls in between fail fast.
+    async fn reconnect(&self, dead: &Arc<tokio_rusqlite::Connection>) -> Result<(), DbError> {
+        let mut state = self.reconnect.lock().await;
+        if !Arc::ptr_eq(&self.connection(), dead) {
+            return Ok(());
+        }
+        if state.retry_at.is_some_and(|at| Instant::now() < at) {
+            return Err(DbError::ConnectionClosed);
+        }
+        if is_in_memory(&self.path) {
+            // Reopening would hand every clone a fresh, empty database.
+            if state.failures == 0 {
+                error!("In-memory database connection lost; its contents cannot be recovered");
+            }
+            state.failures += 1;
+            return Err(DbError::ConnectionClosed);
+        }
+
+        warn!("Database connection lost, reopening");
+        let reopened = async {
+            let conn = open_connection(&self.path).await?;
+            conn.call(apply_schema).await?;
+            Ok::<_, DbError>(conn)
+        }
+        .await;
+        match reopened {
+            Ok(conn) => {
+                *self
+                    .conn
+                    .write()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(conn);
+                *state = ReconnectState::default();
+                info!("Database connection re-established");
+                Ok(())
+            }
+            Err(e) => {
+                let delay = RECONNECT_BACKOFF_BASE
+                    .saturating_mul(1 << state.failures.min(6))
+                    .min(RECONNECT_BACKOFF_MAX);
+                state.failures += 1;
+                state.retry_at = Some(Instant::now() + delay);
+                error!(
+                    "Failed to reopen database, next attempt in {}ms: {e}",
+                    delay.as_millis()
+                );
+                Err(e)
+            }
+        }
+    }
+}
+
 /// Runs a closure on the connection thread with the error type pinned to
 /// [`rusqlite::Error`], which the generic `Connection::call` cannot infer
 /// when a closure never propagates an error.
@@ -72,12 +196,41 @@ pub trait DbCall {
 }
 
 impl DbCall for Db {
+    /// A call that fails because the connection thread is gone triggers a
+    /// reconnect and is retried once, provided the closure never ran on the
+    /// dead connection. A closure that was running when the thread died is
+    /// not replayed.
     async fn call_db<F, R>(&self, f: F) -> Result<R, DbError>
     where
         F: FnOnce(&mut rusqlite::Connection) -> rusqlite::Result<R> + Send + 'static,
         R: Send + 'static,
     {
-        self.call(f).await
+        // Parked here so a closure the dead connection never received can be
+        // handed to the reopened one.
+        let pending = Arc::new(std::sync::Mutex::new(Some(f)));
+        let mut retried = false;
+        self.round_trips.fetch_add(1, Ordering::Relaxed);
+        loop {
+            let conn = self.connection();
+            let job = pending.clone();
+            let result = conn
+                .call(move |c| match job.lock().ok().and_then(|mut f| f.take()) {
+                    Some(f) => f(c),
+                    None => Err(rusqlite::Error::InvalidQuery),
+                })
+                .await;
+            match result {
+                Err(DbError::ConnectionClosed) => {
+                    self.reconnect(&conn).await?;
+                    let unsent = pending.lock().is_ok_and(|f| f.is_some());
+                    if retried || !unsent {
+                        return Err(DbError::ConnectionClosed);
+                    }
+                    retried = true;
+                }
+                other => return other,
+            }
+        }
     }
 }
 
@@ -85,6 +238,11 @@ impl DbCall for Db {
 /// `chrono::DateTime<Utc>` and sort correctly as strings.
 const NOW_UTC: &str = "strftime('%Y-%m-%dT%H:%M:%fZ','now')";
 
+/// Format a timestamp exactly like [`NOW_UTC`], for comparisons in SQL.
+fn sql_timestamp(at: chrono::DateTime<chrono::Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%.3fZ").to_string()
+}
+
 /// Open (or create) the SQLite database at `db_path` and ensure the schema
 /// exists. Parent directories are created if missing.
 #[tracing::instrument(skip(db_path))]
@@ -98,24 +256,43 @@ pub async fn init(db_path: &str) -> Result<Db, DbError> {
         })?;
     }
 
-    let conn = Db::open(db_path).await?;
+    let db = Db {
+        conn: Arc::new(RwLock::new(Arc::new(open_connection(db_path).await?))),
+        path: db_path.into(),
+        reconnect: Default::default(),
+        round_trips: Default::default(),
+    };
+
+    run_schema(&db).await.map_err(|e| {
+        error!("Failed to initialize database schema: {e}");
+        e
+    })?;
+
+    Ok(db)
+}
+
+/// Whether `db_path` names an in-memory or temporary database, whose contents
+/// live only as long as the connection that opened it.
+fn is_in_memory(db_path: &str) -> bool {
+    db_path.is_empty()
+        || db_path == ":memory:"
+        || (db_path.starts_with("file:")
+            && (db_path.contains(":memory:") || db_path.contains("mode=memory")))
+}
 
-    conn.call_db(|conn| {
+/// Open the database file and apply the per-connection pragmas.
+async fn open_connection(db_path: &str) -> Result<tokio_rusqlite::Connection, DbError> {
+    let conn = tokio_rusqlite::Connection::open(db_path).await?;
+    conn.call(|conn| {
         // WAL keeps readers non-blocking; the busy timeout covers the rare
         // moment a checkpoint holds the write lock.
         conn.pragma_update(None, "journal_mode", "WAL")?;
         conn.pragma_update(None, "synchronous", "NORMAL")?;
         conn.pragma_update(None, "foreign_keys", "ON")?;
-        conn.busy_timeout(std::time::Duration::from_secs(5))?;
-        Ok(())
+        conn.busy_timeout(Duration::from_secs(5))?;
+        Ok::<_, rusqlite::Error>(())
     })
     .await?;
-
-    run_schema(&conn).await.map_err(|e| {
-        error!("Failed to initialize database schema: {e}");
-        e
-    })?;
-
     Ok(conn)
 }
 
@@ -144,9 +321,14 @@ fn ensure_column(
 /// full-text index. Idempotent; runs on every startup so schema additions
 /// apply to existing databases.
 pub async fn run_schema(db: &Db) -> Result<(), DbError> {
-    db.call_db(|conn| {
-        conn.execute_batch(&format!(
-            r#"CREATE TABLE IF NOT EXISTS categories (
+    db.call_db(apply_schema).await
+}
+
+/// Synchronous body of [`run_schema`], also applied to a connection reopened
+/// by [`Db::call_db`] before it replaces the dead one.
+fn apply_schema(conn: &mut rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(&format!(
+        r#"CREATE TABLE IF NOT EXISTS categories (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     name TEXT NOT NULL,
     position INTEGER NOT NULL DEFAULT 0
@@ -156,7 +338,12 @@ CREATE TABLE IF NOT EXISTS channels (
     name TEXT NOT NULL UNIQUE,
     category_id INTEGER REFERENCES categories(id) ON DELETE SET NULL,
     description TEXT NOT NULL DEFAULT '',
-    position INTEGER NOT NULL DEFAULT 0
+    position INTEGER NOT NULL DEFAULT 0,
+    retention_days INTEGER,
+    post_roles TEXT,
+    slow_mode_seconds INTEGER NOT NULL DEFAULT 0,
+    receipts_enabled INTEGER NOT NULL DEFAULT 0,
+    max_subscribers INTEGER
 );
 CREATE TABLE IF NOT EXISTS voice_channels (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -169,9 +356,16 @@ CREATE TABLE IF NOT EXISTS voice_channels (
 CREATE TABLE IF NOT EXISTS messages (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     channel_id INTEGER NOT NULL REFERENCES channels(id),
-    content TEXT NOT NULL
+    content TEXT NOT NULL,
+    created_at TEXT,
+    expires_at TEXT
 );
-CREATE INDEX IF NOT EXISTS idx_messages_channel_id ON messages (channel_id);
+-- History pages read `channel_id = ? [AND id < ?] ORDER BY id DESC`. Naming
+-- `id` in the index (rather than relying on the implicit trailing rowid)
+-- keeps the planner on it once ANALYZE statistics exist; otherwise it may
+-- walk the whole rowid range. Reaction lookups by message_id use the
+-- reactions primary key, whose leading column is message_id.
+CREATE INDEX IF NOT EXISTS idx_messages_channel_id_id ON messages (channel_id, id DESC);
 CREATE TABLE IF NOT EXISTS reactions (
     message_id INTEGER NOT NULL,
     user_name TEXT NOT NULL,
@@ -214,6 +408,12 @@ CREATE TABLE IF NOT EXISTS user_keys (
     avatar TEXT NOT NULL DEFAULT '',
     created_at TEXT NOT NULL DEFAULT ({NOW_UTC})
 );
+CREATE TABLE IF NOT EXISTS profiles (
+    public_key TEXT PRIMARY KEY,
+    display_name TEXT NOT NULL DEFAULT '',
+    bio TEXT NOT NULL DEFAULT '',
+    updated_at TEXT NOT NULL DEFAULT ({NOW_UTC})
+);
 CREATE TABLE IF NOT EXISTS bans (
     public_key TEXT PRIMARY KEY,
     user_name TEXT NOT NULL,
@@ -259,45 +459,182 @@ CREATE TABLE IF NOT EXISTS bots (
     active INTEGER NOT NULL DEFAULT 1,
     created_at TEXT NOT NULL DEFAULT ({NOW_UTC})
 );
-INSERT OR IGNORE INTO channels (name) VALUES ('general');
+CREATE TABLE IF NOT EXISTS outgoing_webhooks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    channel_id INTEGER NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT ({NOW_UTC})
+);
+CREATE INDEX IF NOT EXISTS idx_outgoing_webhooks_channel_id ON outgoing_webhooks (channel_id);
+CREATE TABLE IF NOT EXISTS scheduled_messages (
+    token TEXT PRIMARY KEY,
+    channel_id INTEGER NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    user_name TEXT NOT NULL,
+    content TEXT NOT NULL,
+    due_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT ({NOW_UTC})
+);
+CREATE INDEX IF NOT EXISTS idx_scheduled_messages_due_at ON scheduled_messages (due_at);
+CREATE INDEX IF NOT EXISTS idx_scheduled_messages_user_name ON scheduled_messages (user_name);
+CREATE TABLE IF NOT EXISTS uploads (
+    key TEXT PRIMARY KEY,
+    uploader TEXT,
+    uploader_key TEXT,
+    hash TEXT,
+    created_at TEXT NOT NULL DEFAULT ({NOW_UTC})
+);
+CREATE TABLE IF NOT EXISTS upload_usage (
+    public_key TEXT NOT NULL,
+    day TEXT NOT NULL,
+    bytes INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (public_key, day)
+);
+CREATE TABLE IF NOT EXISTS announcements (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message TEXT NOT NULL,
+    level TEXT NOT NULL,
+    active INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT ({NOW_UTC})
+);
+CREATE TABLE IF NOT EXISTS last_seen (
+    user_name TEXT PRIMARY KEY,
+    seen_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT,
+    details TEXT NOT NULL DEFAULT '{{}}',
+    at TEXT NOT NULL DEFAULT ({NOW_UTC})
+);
+CREATE TABLE IF NOT EXISTS message_flags (
+    message_id INTEGER NOT NULL,
+    reporter TEXT NOT NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    at TEXT NOT NULL DEFAULT ({NOW_UTC}),
+    resolved_by TEXT,
+    resolved_at TEXT,
+    PRIMARY KEY (message_id, reporter)
+);
+CREATE INDEX IF NOT EXISTS idx_message_flags_open
+    ON message_flags (at) WHERE resolved_at IS NULL;
+CREATE TABLE IF NOT EXISTS read_markers (
+    user_name TEXT NOT NULL,
+    channel_id INTEGER NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    message_id INTEGER NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT ({NOW_UTC}),
+    PRIMARY KEY (channel_id, user_name)
+);
+CREATE TABLE IF NOT EXISTS link_previews (
+    url TEXT PRIMARY KEY,
+    preview TEXT NOT NULL,
+    fetched_at TEXT NOT NULL DEFAULT ({NOW_UTC})
+);
 "#
-        ))?;
+    ))?;
+    conn.execute(
+        "INSERT OR IGNORE INTO channels (name) VALUES (?1)",
+        [crate::config::default_channel()],
+    )?;
 
-        conn.execute_batch(&stats::stats_schema())?;
-        conn.execute_batch(&wiki::wiki_schema())?;
+    conn.execute_batch(&stats::stats_schema())?;
+    conn.execute_batch(&wiki::wiki_schema())?;
 
-        // Seed built-in roles and migrate any legacy single-role assignments
-        // into role_definitions/user_roles. Runs once (marker-guarded); depends
-        // on server_settings, created by stats_schema above.
-        roles::migrate_roles(conn)?;
+    // Seed built-in roles and migrate any legacy single-role assignments
+    // into role_definitions/user_roles. Runs once (marker-guarded); depends
+    // on server_settings, created by stats_schema above.
+    roles::migrate_roles(conn)?;
 
-        // Columns added after a table first shipped; CREATE TABLE IF NOT
-        // EXISTS does not extend existing tables.
-        ensure_column(conn, "channels", "position", "INTEGER NOT NULL DEFAULT 0")?;
-        ensure_column(
-            conn,
-            "voice_channels",
-            "position",
-            "INTEGER NOT NULL DEFAULT 0",
-        )?;
-        ensure_column(conn, "user_keys", "avatar", "TEXT NOT NULL DEFAULT ''")?;
+    // Columns added after a table first shipped; CREATE TABLE IF NOT
+    // EXISTS does not extend existing tables.
+    ensure_column(conn, "channels", "position", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "channels", "retention_days", "INTEGER")?;
+    ensure_column(conn, "channels", "post_roles", "TEXT")?;
+    ensure_column(
+        conn,
+        "channels",
+        "slow_mode_seconds",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    ensure_column(
+        conn,
+        "channels",
+        "receipts_enabled",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    ensure_column(conn, "channels", "max_subscribers", "INTEGER")?;
+    ensure_column(
+        conn,
+        "voice_channels",
+        "position",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    ensure_column(conn, "user_keys", "avatar", "TEXT NOT NULL DEFAULT ''")?;
+    ensure_column(conn, "uploads", "uploader_key", "TEXT")?;
+    ensure_column(conn, "uploads", "hash", "TEXT")?;
+    // Content is deduplicated per uploader, not globally.
+    conn.execute_batch("DROP INDEX IF EXISTS idx_uploads_hash;")?;
+    conn.execute_batch(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_uploads_uploader_hash \
+         ON uploads (uploader_key, hash) WHERE hash IS NOT NULL;",
+    )?;
+    // SQLite cannot add a column with a non-constant default, so the
+    // insert stamps `created_at` itself; rows predating it stay NULL.
+    ensure_column(conn, "messages", "created_at", "TEXT")?;
+    // Superseded by idx_messages_channel_id_id, which covers its prefix.
+    conn.execute_batch("DROP INDEX IF EXISTS idx_messages_channel_id;")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_messages_channel_created \
+         ON messages (channel_id, created_at);",
+    )?;
+    // Ephemeral messages carry their deletion time in `expires_at` so the
+    // sweeper can find them with an index scan. Rows stored before the
+    // column existed are backfilled from their JSON `expiresAt`; an
+    // unparseable expiry makes the message expire right away.
+    ensure_column(conn, "messages", "expires_at", "TEXT")?;
+    conn.execute_batch(&format!(
+        r#"CREATE INDEX IF NOT EXISTS idx_messages_expires_at
+    ON messages (expires_at) WHERE expires_at IS NOT NULL;
+UPDATE messages
+    SET expires_at = coalesce(
+        strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(content, '$.expiresAt')),
+        {NOW_UTC})
+    WHERE expires_at IS NULL
+      AND content LIKE '%"ephemeral":true%'
+      AND json_valid(content)
+      AND json_extract(content, '$.ephemeral') = 1;"#
+    ))?;
+    // Imported messages remember the id they had in their source export,
+    // prefixed with the source instance, so re-running an import skips rows
+    // it already brought in.
+    ensure_column(conn, "messages", "external_id", "TEXT")?;
+    conn.execute_batch(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_messages_external_id \
+         ON messages (external_id) WHERE external_id IS NOT NULL;",
+    )?;
+    // Soft-deleted messages keep their row as a tombstone; see
+    // `messages::soft_delete_message`.
+    ensure_column(conn, "messages", "deleted_at", "TEXT")?;
+    ensure_column(conn, "messages", "deleted_by", "TEXT")?;
 
-        // One-time wipe of pre-E2EE plaintext direct messages: DMs are
-        // end-to-end encrypted now, so old plaintext rows can neither be
-        // rendered by the client nor converted server-side. The marker in
-        // server_settings keeps the wipe from ever running twice.
-        conn.execute_batch(
-            r#"DELETE FROM direct_messages
+    // One-time wipe of pre-E2EE plaintext direct messages: DMs are
+    // end-to-end encrypted now, so old plaintext rows can neither be
+    // rendered by the client nor converted server-side. The marker in
+    // server_settings keeps the wipe from ever running twice.
+    conn.execute_batch(
+        r#"DELETE FROM direct_messages
     WHERE NOT EXISTS (SELECT 1 FROM server_settings WHERE key = 'dm_e2ee');
 INSERT OR IGNORE INTO server_settings (key, value) VALUES ('dm_e2ee', '1');"#,
-        )?;
+    )?;
 
-        // Full-text index over the `text` field of the message JSON, kept in
-        // sync by triggers. The backfill covers databases created before the
-        // index existed (and is a no-op afterwards). `json_valid` guards the
-        // triggers because a malformed row would otherwise abort the write.
-        conn.execute_batch(
-            r#"CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(text);
+    // Full-text index over the `text` field of the message JSON, kept in
+    // sync by triggers. The backfill covers databases created before the
+    // index existed (and is a no-op afterwards). `json_valid` guards the
+    // triggers because a malformed row would otherwise abort the write.
+    conn.execute_batch(
+        r#"CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(text);
 CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
     INSERT INTO messages_fts (rowid, text)
     VALUES (new.id, CASE WHEN json_valid(new.content)
@@ -316,8 +653,26 @@ SELECT id, CASE WHEN json_valid(content)
     THEN coalesce(json_extract(content, '$.text'), '') ELSE '' END
 FROM messages WHERE id NOT IN (SELECT rowid FROM messages_fts);
 "#,
-        )?;
-        Ok(())
-    })
-    .await
+    )?;
+
+    // Upload keys each message mentions, for the upload reaper. Messages
+    // stored before the table existed are linked once, when it is created.
+    let linked: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'message_uploads'",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS message_uploads (
+    upload_key TEXT NOT NULL,
+    message_id INTEGER NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    PRIMARY KEY (upload_key, message_id)
+);
+CREATE INDEX IF NOT EXISTS idx_message_uploads_message_id ON message_uploads (message_id);
+CREATE INDEX IF NOT EXISTS idx_uploads_created_at ON uploads (created_at);",
+    )?;
+    if linked == 0 {
+        uploads::backfill_message_uploads(conn)?;
+    }
+    Ok(())
 }
@@ -0,0 +1,87 @@
+//! User profile fields beyond the name: display name and bio.
+//!
+//! Profiles are keyed by public key rather than name so they follow the
+//! keypair. Only names bound in `user_keys` can carry one, and lookups by
+//! name join through that binding. The avatar stays on the binding row (see
+//! [`super::users`]) where the upload cleanup already tracks it; profile
+//! reads include it so callers get the whole card at once. Empty strings mean
+//! "unset".
+
+use rusqlite::{OptionalExtension, params};
+
+use super::{Db, DbCall, DbError, NOW_UTC};
+
+/// A user's public profile card.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserProfile {
+    pub user_name: String,
+    pub display_name: String,
+    pub bio: String,
+    /// `/files/<key>` URL of the avatar, or empty when unset.
+    pub avatar: String,
+}
+
+const SELECT_PROFILE: &str = "SELECT k.user_name, COALESCE(p.display_name, ''), \
+     COALESCE(p.bio, ''), k.avatar \
+     FROM user_keys k LEFT JOIN profiles p ON p.public_key = k.public_key";
+
+fn row_to_profile(row: &rusqlite::Row) -> rusqlite::Result<UserProfile> {
+    Ok(UserProfile {
+        user_name: row.get(0)?,
+        display_name: row.get(1)?,
+        bio: row.get(2)?,
+        avatar: row.get(3)?,
+    })
+}
+
+/// Store the display name and bio for `public_key`, replacing earlier values.
+pub async fn upsert_profile(
+    db: &Db,
+    public_key: &str,
+    display_name: &str,
+    bio: &str,
+) -> Result<(), DbError> {
+    let public_key = public_key.to_owned();
+    let display_name = display_name.to_owned();
+    let bio = bio.to_owned();
+    db.call_db(move |conn| {
+        conn.execute(
+            &format!(
+                "INSERT INTO profiles (public_key, display_name, bio) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT (public_key) DO UPDATE SET display_name = excluded.display_name, \
+                 bio = excluded.bio, updated_at = {NOW_UTC}"
+            ),
+            params![public_key, display_name, bio],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// The profile of a bound user name. `None` when the name has no binding;
+/// bound users without a profile row get empty fields.
+pub async fn get_profile(db: &Db, user_name: &str) -> Result<Option<UserProfile>, DbError> {
+    let user_name = user_name.to_owned();
+    db.call_db(move |conn| {
+        conn.query_row(
+            &format!("{SELECT_PROFILE} WHERE k.user_name = ?1"),
+            params![user_name],
+            row_to_profile,
+        )
+        .optional()
+    })
+    .await
+}
+
+/// Every user with a display name or bio, for the snapshot sent to new
+/// clients. Avatars have their own snapshot.
+pub async fn get_all_profiles(db: &Db) -> Result<Vec<UserProfile>, DbError> {
+    db.call_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "{SELECT_PROFILE} WHERE p.display_name <> '' OR p.bio <> '' ORDER BY k.user_name"
+        ))?;
+        let rows = stmt.query_map([], row_to_profile)?;
+        rows.collect()
+    })
+    .await
+}
@@ -2,6 +2,8 @@
 
 use rusqlite::params;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use super::{Db, DbCall, DbError};
 
@@ -63,21 +65,113 @@ pub async fn get_reaction_summary(
     Ok(map.remove(&message_id).unwrap_or_default())
 }
 
+/// Maximum number of distinct emojis one message may carry.
+pub const MAX_DISTINCT_REACTIONS: i64 = 20;
+
 /// Add a reaction to a message. Duplicate reactions by the same user are ignored.
+///
+/// Returns `false` without adding anything when the message already carries
+/// [`MAX_DISTINCT_REACTIONS`] distinct emojis and `emoji` is not one of them;
+/// joining an existing emoji is always allowed. The count and insert share a
+/// transaction so concurrent reactions cannot overshoot the cap.
 pub async fn add_reaction(
     db: &Db,
     message_id: i64,
     user: &str,
     emoji: &str,
-) -> Result<(), DbError> {
+) -> Result<bool, DbError> {
     let user = user.to_owned();
     let emoji = emoji.to_owned();
     db.call_db(move |conn| {
-        conn.execute(
-            "INSERT OR IGNORE INTO reactions (message_id, user_name, emoji) VALUES (?1, ?2, ?3)",
-            params![message_id, user, emoji],
-        )?;
-        Ok(())
+        let tx = conn.transaction()?;
+        let added = insert_capped(&tx, message_id, &user, &emoji)?;
+        tx.commit()?;
+        Ok(added)
+    })
+    .await
+}
+
+/// Insert a reaction unless it would exceed [`MAX_DISTINCT_REACTIONS`].
+fn insert_capped(
+    tx: &rusqlite::Transaction,
+    message_id: i64,
+    user: &str,
+    emoji: &str,
+) -> rusqlite::Result<bool> {
+    let (distinct, present): (i64, bool) = tx.query_row(
+        "SELECT COUNT(DISTINCT emoji), COALESCE(MAX(emoji = ?2), 0) \
+         FROM reactions WHERE message_id = ?1",
+        params![message_id, emoji],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if !present && distinct >= MAX_DISTINCT_REACTIONS {
+        return Ok(false);
+    }
+    tx.execute(
+        "INSERT OR IGNORE INTO reactions (message_id, user_name, emoji) VALUES (?1, ?2, ?3)",
+        params![message_id, user, emoji],
+    )?;
+    Ok(true)
+}
+
+/// A message's reactions right after a change made by [`apply_reaction`].
+#[derive(Debug, Clone)]
+pub struct ReactionChange {
+    /// Position of the change among all [`apply_reaction`] calls. Assigned on
+    /// the connection thread, so it follows the order SQLite applied them: of
+    /// two summaries for one message, the higher `seq` is the current one.
+    pub seq: u64,
+    /// Emoji -> users, as returned by [`get_reaction_summary`].
+    pub reactions: HashMap<String, Vec<String>>,
+}
+
+/// Add (`add = true`) or remove a reaction and read the message's resulting
+/// reactions in the same transaction, saving the separate summary query a
+/// broadcast would otherwise need. The change's `seq` is taken from `seq`
+/// (`AppState::reaction_seq`). Returns `None` when adding is refused by the
+/// [`MAX_DISTINCT_REACTIONS`] cap.
+pub async fn apply_reaction(
+    db: &Db,
+    seq: &Arc<AtomicU64>,
+    message_id: i64,
+    user: &str,
+    emoji: &str,
+    add: bool,
+) -> Result<Option<ReactionChange>, DbError> {
+    let seq = Arc::clone(seq);
+    let user = user.to_owned();
+    let emoji = emoji.to_owned();
+    db.call_db(move |conn| {
+        let tx = conn.transaction()?;
+        if add {
+            if !insert_capped(&tx, message_id, &user, &emoji)? {
+                return Ok(None);
+            }
+        } else {
+            tx.execute(
+                "DELETE FROM reactions WHERE message_id = ?1 AND user_name = ?2 AND emoji = ?3",
+                params![message_id, user, emoji],
+            )?;
+        }
+        let mut reactions: HashMap<String, Vec<String>> = HashMap::new();
+        {
+            let mut stmt = tx.prepare(
+                "SELECT emoji, user_name FROM reactions WHERE message_id = ?1 \
+                 ORDER BY emoji, user_name",
+            )?;
+            let rows = stmt.query_map(params![message_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            for row in rows {
+                let (emoji, user) = row?;
+                reactions.entry(emoji).or_default().push(user);
+            }
+        }
+        tx.commit()?;
+        Ok(Some(ReactionChange {
+            seq: seq.fetch_add(1, Ordering::Relaxed),
+            reactions,
+        }))
     })
     .await
 }
@@ -0,0 +1,112 @@
+//! Per-user read positions in text channels.
+//!
+//! One row per user and channel holding the id of the newest message the user
+//! has read. Markers only move forward, so a client that reports an older
+//! position (another device, a stale tab) never rewinds it. Channels with
+//! receipts enabled turn these rows into "seen by" lists for recent messages.
+
+use rusqlite::params;
+
+use super::{Db, DbCall, DbError, NOW_UTC};
+
+/// Who has read one message, for the receipt sent to its author.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageReceipt {
+    pub message_id: i64,
+    pub author: String,
+    /// Users whose marker is at or past the message, author excluded, sorted.
+    pub readers: Vec<String>,
+}
+
+/// Move `user`'s marker in a channel up to `message_id`. Returns `false` when
+/// the marker was already there or further along.
+pub async fn advance_read_marker(
+    db: &Db,
+    user: &str,
+    channel_id: i32,
+    message_id: i64,
+) -> Result<bool, DbError> {
+    let user = user.to_owned();
+    db.call_db(move |conn| {
+        let changed = conn.execute(
+            &format!(
+                "INSERT INTO read_markers (user_name, channel_id, message_id) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT (channel_id, user_name) DO UPDATE \
+                 SET message_id = excluded.message_id, updated_at = {NOW_UTC} \
+                 WHERE excluded.message_id > read_markers.message_id"
+            ),
+            params![user, channel_id, message_id],
+        )?;
+        Ok(changed > 0)
+    })
+    .await
+}
+
+/// A user's marker in a channel, if they have one.
+pub async fn get_read_marker(db: &Db, user: &str, channel_id: i32) -> Result<Option<i64>, DbError> {
+    let user = user.to_owned();
+    db.call_db(move |conn| {
+        let marker = conn
+            .query_row(
+                "SELECT message_id FROM read_markers WHERE channel_id = ?1 AND user_name = ?2",
+                params![channel_id, user],
+                |row| row.get(0),
+            )
+            .ok();
+        Ok(marker)
+    })
+    .await
+}
+
+/// Receipts for the newest `window` messages of a channel, oldest first.
+/// Messages nobody but their author has read yet are left out.
+pub async fn get_read_receipts(
+    db: &Db,
+    channel_id: i32,
+    window: i64,
+) -> Result<Vec<MessageReceipt>, DbError> {
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT user_name, message_id FROM read_markers WHERE channel_id = ?1 \
+             ORDER BY user_name",
+        )?;
+        let markers = stmt
+            .query_map(params![channel_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        if markers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT id, json_extract(content, '$.user') FROM messages \
+             WHERE channel_id = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let messages = stmt
+            .query_map(params![channel_id, window], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut receipts: Vec<MessageReceipt> = messages
+            .into_iter()
+            .filter_map(|(message_id, author)| {
+                let author = author?;
+                let readers: Vec<String> = markers
+                    .iter()
+                    .filter(|(user, read)| *read >= message_id && *user != author)
+                    .map(|(user, _)| user.clone())
+                    .collect();
+                (!readers.is_empty()).then_some(MessageReceipt {
+                    message_id,
+                    author,
+                    readers,
+                })
+            })
+            .collect();
+        receipts.reverse();
+        Ok(receipts)
+    })
+    .await
+}
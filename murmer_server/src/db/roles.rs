@@ -184,6 +184,52 @@ pub async fn add_user_role(db: &Db, key: &str, role_id: i64) -> Result<(), DbErr
     .await
 }
 
+/// Remove a role assignment from a key, or every assignment when `role_id` is
+/// `None`. Returns how many assignments were removed.
+pub async fn remove_user_role(db: &Db, key: &str, role_id: Option<i64>) -> Result<usize, DbError> {
+    let key = key.to_owned();
+    db.call_db(move |conn| match role_id {
+        Some(id) => conn.execute(
+            "DELETE FROM user_roles WHERE public_key = ?1 AND role_id = ?2",
+            params![key, id],
+        ),
+        None => conn.execute("DELETE FROM user_roles WHERE public_key = ?1", params![key]),
+    })
+    .await
+}
+
+/// One role held by one key, as listed by the admin `GET /roles` audit view.
+pub struct RoleAssignment {
+    pub key: String,
+    pub role: String,
+    pub color: Option<String>,
+}
+
+/// A page of role assignments ordered by key and role position (highest
+/// first), for auditing who holds which role.
+pub async fn list_role_assignments(
+    db: &Db,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<RoleAssignment>, DbError> {
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT ur.public_key, rd.name, rd.color FROM user_roles ur \
+             JOIN role_definitions rd ON rd.id = ur.role_id \
+             ORDER BY ur.public_key, rd.position DESC LIMIT ?1 OFFSET ?2",
+        )?;
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            Ok(RoleAssignment {
+                key: row.get(0)?,
+                role: row.get(1)?,
+                color: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    })
+    .await
+}
+
 /// Find a role definition by name, creating a baseline custom role (positioned
 /// just below Owner) if none exists, then assign it to `key`. Returns the
 /// resolved definition so callers can update in-memory state and broadcast it.
@@ -0,0 +1,117 @@
+//! Messages queued for future delivery ("schedule send").
+//!
+//! A row holds the fully prepared chat frame until `due_at`. The periodic
+//! message sweeper is the only delivery path; it removes due rows with
+//! `DELETE ... RETURNING`, so a message is never posted twice.
+
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+
+use super::{Db, DbCall, DbError, sql_timestamp};
+
+/// A chat message waiting to be posted.
+#[derive(Debug, Clone)]
+pub struct ScheduledMessage {
+    /// Random token handed to the sender for cancellation.
+    pub token: String,
+    pub channel_id: i32,
+    pub user_name: String,
+    /// Serialized chat frame, posted as-is apart from its timestamps.
+    pub content: String,
+    pub due_at: String,
+}
+
+const SELECT_COLS: &str = "token, channel_id, user_name, content, due_at";
+
+fn row_to_scheduled(row: &rusqlite::Row) -> rusqlite::Result<ScheduledMessage> {
+    Ok(ScheduledMessage {
+        token: row.get(0)?,
+        channel_id: row.get(1)?,
+        user_name: row.get(2)?,
+        content: row.get(3)?,
+        due_at: row.get(4)?,
+    })
+}
+
+/// Queue a message for delivery at `due_at`. Returns `false` without
+/// queueing when `user_name` already has `max_per_user` messages waiting.
+pub async fn add_scheduled_message(
+    db: &Db,
+    token: &str,
+    channel_id: i32,
+    user_name: &str,
+    content: &str,
+    due_at: DateTime<Utc>,
+    max_per_user: i64,
+) -> Result<bool, DbError> {
+    let token = token.to_owned();
+    let user_name = user_name.to_owned();
+    let content = content.to_owned();
+    let due_at = sql_timestamp(due_at);
+    db.call_db(move |conn| {
+        let inserted = conn.execute(
+            "INSERT INTO scheduled_messages (token, channel_id, user_name, content, due_at) \
+             SELECT ?1, ?2, ?3, ?4, ?5 \
+             WHERE (SELECT COUNT(*) FROM scheduled_messages WHERE user_name = ?3) < ?6",
+            params![token, channel_id, user_name, content, due_at, max_per_user],
+        )?;
+        Ok(inserted > 0)
+    })
+    .await
+}
+
+/// Cancel a queued message. Only its sender may cancel it; returns `true` if
+/// a row was removed.
+pub async fn cancel_scheduled_message(
+    db: &Db,
+    token: &str,
+    user_name: &str,
+) -> Result<bool, DbError> {
+    let token = token.to_owned();
+    let user_name = user_name.to_owned();
+    db.call_db(move |conn| {
+        let affected = conn.execute(
+            "DELETE FROM scheduled_messages WHERE token = ?1 AND user_name = ?2",
+            params![token, user_name],
+        )?;
+        Ok(affected > 0)
+    })
+    .await
+}
+
+/// A user's queued messages, soonest first.
+pub async fn list_scheduled_messages(
+    db: &Db,
+    user_name: &str,
+) -> Result<Vec<ScheduledMessage>, DbError> {
+    let user_name = user_name.to_owned();
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SELECT_COLS} FROM scheduled_messages WHERE user_name = ?1 \
+             ORDER BY due_at, token"
+        ))?;
+        let rows = stmt.query_map(params![user_name], row_to_scheduled)?;
+        rows.collect()
+    })
+    .await
+}
+
+/// Remove and return every queued message that is due by `now`, oldest first.
+pub async fn take_due_scheduled_messages(
+    db: &Db,
+    now: DateTime<Utc>,
+) -> Result<Vec<ScheduledMessage>, DbError> {
+    let now = sql_timestamp(now);
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(&format!(
+            "DELETE FROM scheduled_messages WHERE due_at <= ?1 RETURNING {SELECT_COLS}"
+        ))?;
+        let mut rows = stmt
+            .query_map(params![now], row_to_scheduled)?
+            .collect::<Result<Vec<_>, _>>()?;
+        // RETURNING yields rows in no particular order.
+        rows.sort_by(|a, b| a.due_at.cmp(&b.due_at));
+        Ok(rows)
+    })
+    .await
+}
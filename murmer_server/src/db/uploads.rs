@@ -0,0 +1,293 @@
+//! Ownership records for files stored under `UPLOAD_DIR`.
+//!
+//! One row per stored upload, keyed by the file name on disk. `uploader` is
+//! the verified user name that sent the file and `uploader_key` the public
+//! key that signed the request; both are `NULL` for uploads made with the
+//! admin token (and for anonymous uploads from before signing was required).
+//! `hash` is the hex SHA-256 of the file's bytes. It is unique per
+//! `uploader_key`, so content is stored once per uploader: a later upload of
+//! the same bytes by the same key is answered with the existing file, while
+//! anyone else gets a copy they own. Sharing a file across uploaders would
+//! let one of them delete it from under the other. The rows let
+//! `DELETE /upload/:key` check ownership and let the retention reaper find
+//! files that nothing references any more.
+//!
+//! `message_uploads` links each channel message to the upload keys its
+//! content mentions, so the reaper can tell whether a file is still posted
+//! without scanning every message. Links are rewritten whenever a message is
+//! stored, edited, imported or soft-deleted, and go away with the message.
+//!
+//! `upload_usage` counts the bytes each public key stored per UTC day, for the
+//! `UPLOAD_QUOTA_BYTES_PER_DAY` quota. Rows for earlier days are dropped as
+//! soon as a later day is written, so each key starts every day at zero.
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+
+use super::{Db, DbCall, DbError, sql_timestamp};
+
+/// A stored upload and who sent it.
+#[derive(Debug, Clone)]
+pub struct UploadRecord {
+    pub key: String,
+    pub uploader: Option<String>,
+    pub uploader_key: Option<String>,
+    pub hash: Option<String>,
+    pub created_at: String,
+}
+
+/// Record a freshly stored upload. Returns `false` without writing anything
+/// when an upload by the same `uploader_key` already holds `hash`; the caller
+/// then discards its copy and serves that one instead (see
+/// [`find_upload_by_hash`]). Checking and inserting is one statement, so two
+/// concurrent uploads of the same bytes cannot both be recorded. A `key` that
+/// is already recorded is an error: the record, and with it the right to
+/// delete the file, never moves to another uploader.
+pub async fn record_upload(
+    db: &Db,
+    key: &str,
+    uploader: Option<&str>,
+    uploader_key: Option<&str>,
+    hash: Option<&str>,
+) -> Result<bool, DbError> {
+    let key = key.to_owned();
+    let uploader = uploader.map(str::to_owned);
+    let uploader_key = uploader_key.map(str::to_owned);
+    let hash = hash.map(str::to_owned);
+    db.call_db(move |conn| {
+        let inserted = conn.execute(
+            "INSERT INTO uploads (key, uploader, uploader_key, hash) VALUES (?1, ?2, ?3, ?4)
+    ON CONFLICT (uploader_key, hash) WHERE hash IS NOT NULL DO NOTHING",
+            params![key, uploader, uploader_key, hash],
+        )?;
+        Ok(inserted > 0)
+    })
+    .await
+}
+
+fn upload_record(row: &rusqlite::Row<'_>) -> rusqlite::Result<UploadRecord> {
+    Ok(UploadRecord {
+        key: row.get(0)?,
+        uploader: row.get(1)?,
+        uploader_key: row.get(2)?,
+        hash: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// Look up the upload by `uploader_key` with the given content hash; `None`
+/// matches the uploads made with the admin token.
+pub async fn find_upload_by_hash(
+    db: &Db,
+    uploader_key: Option<&str>,
+    hash: &str,
+) -> Result<Option<UploadRecord>, DbError> {
+    let uploader_key = uploader_key.map(str::to_owned);
+    let hash = hash.to_owned();
+    db.call_db(move |conn| {
+        conn.query_row(
+            "SELECT key, uploader, uploader_key, hash, created_at FROM uploads
+    WHERE hash = ?1 AND uploader_key IS ?2",
+            params![hash, uploader_key],
+            upload_record,
+        )
+        .optional()
+    })
+    .await
+}
+
+/// Look up the ownership record for an upload key.
+pub async fn get_upload(db: &Db, key: &str) -> Result<Option<UploadRecord>, DbError> {
+    let key = key.to_owned();
+    db.call_db(move |conn| {
+        conn.query_row(
+            "SELECT key, uploader, uploader_key, hash, created_at FROM uploads WHERE key = ?1",
+            params![key],
+            upload_record,
+        )
+        .optional()
+    })
+    .await
+}
+
+/// Remove an upload record. Returns `true` if a row was deleted.
+pub async fn remove_upload(db: &Db, key: &str) -> Result<bool, DbError> {
+    let key = key.to_owned();
+    db.call_db(move |conn| {
+        let affected = conn.execute("DELETE FROM uploads WHERE key = ?1", params![key])?;
+        Ok(affected > 0)
+    })
+    .await
+}
+
+/// Whether `c` ends an upload key mentioned in message content: a character
+/// `sanitize` never leaves in a stored file name, or `?` starting a query.
+fn ends_upload_key(c: char) -> bool {
+    matches!(c, '"' | '\\' | '/' | '?' | '<' | '>' | ':' | '*' | '|') || c.is_control()
+}
+
+/// Upload keys `content` mentions as `/files/<key>`. Keys are stored file
+/// names, which may contain spaces, so each mention yields both the text up
+/// to the next [`ends_upload_key`] character and the text up to the next
+/// whitespace; either may be the key. Keys always end in their extension, so
+/// trailing punctuation (a closing parenthesis, a full stop) is dropped, and
+/// a link to a thumbnail counts for its original.
+fn referenced_upload_keys(content: &str) -> BTreeSet<&str> {
+    let mut keys = BTreeSet::new();
+    for (start, _) in content.match_indices("/files/") {
+        let rest = &content[start + "/files/".len()..];
+        let rest = &rest[..rest.find(ends_upload_key).unwrap_or(rest.len())];
+        let word = &rest[..rest.find(char::is_whitespace).unwrap_or(rest.len())];
+        for candidate in [rest, word] {
+            let key = candidate.trim_end_matches(|c: char| !c.is_alphanumeric());
+            if !key.is_empty() {
+                keys.insert(key);
+                if let Some(original) = key.strip_suffix(".thumb.webp") {
+                    keys.insert(original);
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// Replace the upload links of message `message_id` with the keys its
+/// `content` mentions. Called inside the statement's transaction by every
+/// write that stores message content.
+pub(super) fn link_message_uploads(
+    conn: &Connection,
+    message_id: i64,
+    content: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM message_uploads WHERE message_id = ?1",
+        params![message_id],
+    )?;
+    let mut insert = conn.prepare_cached(
+        "INSERT OR IGNORE INTO message_uploads (upload_key, message_id) VALUES (?1, ?2)",
+    )?;
+    for key in referenced_upload_keys(content) {
+        insert.execute(params![key, message_id])?;
+    }
+    Ok(())
+}
+
+/// Link every stored message to the uploads it mentions. Run once, when the
+/// `message_uploads` table is created on a database that already has messages.
+pub(super) fn backfill_message_uploads(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare("SELECT id, content FROM messages")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        let (id, content) = row?;
+        link_message_uploads(conn, id, &content)?;
+    }
+    Ok(())
+}
+
+/// Keys of up to `limit` uploads stored before `cutoff` that nothing points
+/// at, oldest first: no channel message links to it (see
+/// [`link_message_uploads`]), no wiki page mentions `/files/<key>`, and it is
+/// not in use as an avatar, custom emoji or server setting (the server icon).
+/// Direct messages are end-to-end encrypted and cannot be inspected, so
+/// files shared only there are treated as unreferenced.
+pub async fn unreferenced_uploads_before(
+    db: &Db,
+    cutoff: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<String>, DbError> {
+    let cutoff = sql_timestamp(cutoff);
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(
+            "SELECT u.key FROM uploads u WHERE u.created_at < ?1
+    AND NOT EXISTS (SELECT 1 FROM message_uploads r WHERE r.upload_key = u.key)
+    AND NOT EXISTS (SELECT 1 FROM wiki_pages w WHERE instr(w.body, '/files/' || u.key) > 0)
+    AND NOT EXISTS (SELECT 1 FROM user_keys k WHERE k.avatar = '/files/' || u.key)
+    AND NOT EXISTS (SELECT 1 FROM emojis e WHERE e.url = '/files/' || u.key)
+    AND NOT EXISTS (SELECT 1 FROM server_settings s WHERE s.value = '/files/' || u.key)
+ORDER BY u.created_at LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![cutoff, limit as i64], |row| row.get(0))?;
+        rows.collect()
+    })
+    .await
+}
+
+/// Bytes `public_key` has stored on `day`.
+pub async fn upload_bytes_used(db: &Db, public_key: &str, day: NaiveDate) -> Result<u64, DbError> {
+    let public_key = public_key.to_owned();
+    let day = day.to_string();
+    db.call_db(move |conn| {
+        let bytes: Option<i64> = conn
+            .query_row(
+                "SELECT bytes FROM upload_usage WHERE public_key = ?1 AND day = ?2",
+                params![public_key, day],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(bytes.unwrap_or(0).max(0) as u64)
+    })
+    .await
+}
+
+/// Count `bytes` against `public_key`'s usage for `day`, unless that would
+/// take it past `limit`. Returns whether the bytes were counted. Check and
+/// update run in one call on the database thread, so concurrent uploads
+/// cannot both slip under the limit.
+pub async fn reserve_upload_bytes(
+    db: &Db,
+    public_key: &str,
+    day: NaiveDate,
+    bytes: u64,
+    limit: u64,
+) -> Result<bool, DbError> {
+    let public_key = public_key.to_owned();
+    let day = day.to_string();
+    db.call_db(move |conn| {
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM upload_usage WHERE day < ?1", params![day])?;
+        let used: i64 = tx
+            .query_row(
+                "SELECT bytes FROM upload_usage WHERE public_key = ?1 AND day = ?2",
+                params![public_key, day],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(0);
+        let total = (used.max(0) as u64).saturating_add(bytes);
+        if total > limit {
+            return Ok(false);
+        }
+        tx.execute(
+            "INSERT INTO upload_usage (public_key, day, bytes) VALUES (?1, ?2, ?3)
+    ON CONFLICT(public_key, day) DO UPDATE SET bytes = excluded.bytes",
+            params![public_key, day, total as i64],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    })
+    .await
+}
+
+/// Give back bytes counted by [`reserve_upload_bytes`] for an upload that
+/// was not stored after all.
+pub async fn release_upload_bytes(
+    db: &Db,
+    public_key: &str,
+    day: NaiveDate,
+    bytes: u64,
+) -> Result<(), DbError> {
+    let public_key = public_key.to_owned();
+    let day = day.to_string();
+    db.call_db(move |conn| {
+        conn.execute(
+            "UPDATE upload_usage SET bytes = MAX(bytes - ?3, 0) WHERE public_key = ?1 AND day = ?2",
+            params![public_key, day, bytes as i64],
+        )?;
+        Ok(())
+    })
+    .await
+}
@@ -9,7 +9,11 @@
 //!
 //! The binding row also carries the user's avatar: a `/files/<key>` URL
 //! pointing at a validated upload, or an empty string when unset.
+//!
+//! `last_seen` records when each user was last active, keyed by name so bots
+//! without a binding are covered too.
 
+use chrono::{DateTime, Utc};
 use rusqlite::params;
 
 use super::{Db, DbCall, DbError};
@@ -92,6 +96,18 @@ pub async fn get_all_avatars(db: &Db) -> Result<Vec<(String, String)>, DbError>
     .await
 }
 
+/// Every name/key binding, for deriving the identicon of each user.
+pub async fn get_all_user_keys(db: &Db) -> Result<Vec<(String, String)>, DbError> {
+    db.call_db(|conn| {
+        let mut stmt = conn.prepare("SELECT user_name, public_key FROM user_keys")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+    .await
+}
+
 /// How many users currently reference an avatar URL. Replaced avatar files
 /// are only removed from disk when no other user still points at them.
 pub async fn count_avatar_references(db: &Db, avatar: &str) -> Result<i64, DbError> {
@@ -119,3 +135,33 @@ pub async fn unbind_user_name(db: &Db, user_name: &str) -> Result<bool, DbError>
     })
     .await
 }
+
+/// Record when a user was last active, replacing any earlier timestamp.
+pub async fn set_last_seen(
+    db: &Db,
+    user_name: &str,
+    seen_at: DateTime<Utc>,
+) -> Result<(), DbError> {
+    let user_name = user_name.to_owned();
+    db.call_db(move |conn| {
+        conn.execute(
+            "INSERT INTO last_seen (user_name, seen_at) VALUES (?1, ?2) \
+             ON CONFLICT (user_name) DO UPDATE SET seen_at = excluded.seen_at",
+            params![user_name, seen_at],
+        )?;
+        Ok(())
+    })
+    .await
+}
+
+/// Load every persisted last-seen timestamp as `(user_name, seen_at)` pairs.
+pub async fn get_all_last_seen(db: &Db) -> Result<Vec<(String, DateTime<Utc>)>, DbError> {
+    db.call_db(|conn| {
+        let mut stmt = conn.prepare("SELECT user_name, seen_at FROM last_seen")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    })
+    .await
+}
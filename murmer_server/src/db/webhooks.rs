@@ -0,0 +1,88 @@
+//! Outgoing webhook registrations.
+//!
+//! Each row forwards new messages of one text channel to an external URL,
+//! signed with the row's shared secret (see [`crate::webhooks`]).
+
+use rusqlite::params;
+
+use super::{Db, DbCall, DbError};
+
+/// An outgoing webhook registered for a text channel.
+#[derive(Debug, Clone)]
+pub struct OutgoingWebhook {
+    pub id: i64,
+    pub channel_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub created_at: String,
+}
+
+fn row_to_webhook(row: &rusqlite::Row) -> rusqlite::Result<OutgoingWebhook> {
+    Ok(OutgoingWebhook {
+        id: row.get(0)?,
+        channel_id: row.get(1)?,
+        url: row.get(2)?,
+        secret: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+const SELECT_COLS: &str = "id, channel_id, url, secret, created_at";
+
+/// Register an outgoing webhook for a channel and return the stored row.
+pub async fn add_outgoing_webhook(
+    db: &Db,
+    channel_id: i32,
+    url: &str,
+    secret: &str,
+) -> Result<OutgoingWebhook, DbError> {
+    let url = url.to_owned();
+    let secret = secret.to_owned();
+    db.call_db(move |conn| {
+        conn.query_row(
+            &format!(
+                "INSERT INTO outgoing_webhooks (channel_id, url, secret) VALUES (?1, ?2, ?3) \
+                 RETURNING {SELECT_COLS}"
+            ),
+            params![channel_id, url, secret],
+            row_to_webhook,
+        )
+    })
+    .await
+}
+
+/// List every registered outgoing webhook, oldest first.
+pub async fn list_outgoing_webhooks(db: &Db) -> Result<Vec<OutgoingWebhook>, DbError> {
+    db.call_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SELECT_COLS} FROM outgoing_webhooks ORDER BY id"
+        ))?;
+        let rows = stmt.query_map([], row_to_webhook)?;
+        rows.collect()
+    })
+    .await
+}
+
+/// Webhooks that should receive new messages posted to `channel_id`.
+pub async fn get_channel_webhooks(
+    db: &Db,
+    channel_id: i32,
+) -> Result<Vec<OutgoingWebhook>, DbError> {
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SELECT_COLS} FROM outgoing_webhooks WHERE channel_id = ?1 ORDER BY id"
+        ))?;
+        let rows = stmt.query_map(params![channel_id], row_to_webhook)?;
+        rows.collect()
+    })
+    .await
+}
+
+/// Remove an outgoing webhook. Returns `true` if a row was deleted.
+pub async fn remove_outgoing_webhook(db: &Db, id: i64) -> Result<bool, DbError> {
+    db.call_db(move |conn| {
+        let affected = conn.execute("DELETE FROM outgoing_webhooks WHERE id = ?1", params![id])?;
+        Ok(affected > 0)
+    })
+    .await
+}
@@ -0,0 +1,77 @@
+//! Read-only Server-Sent Events mirror of a text channel's live stream, for
+//! integrations such as dashboards that cannot speak the WebSocket protocol.
+//!
+//! `GET /events/{channel}` subscribes to the channel's broadcast sender (the
+//! same one connected clients join) and forwards every frame verbatim as an
+//! SSE `data:` line. Nothing can be posted through it. Like `/history` it
+//! exposes every channel, private ones included, so it is guarded by the
+//! `ADMIN_TOKEN` bearer.
+//!
+//! A subscriber that falls behind the broadcast buffer receives a
+//! `: lagged` comment naming how many frames it missed, and should refetch
+//! history (`GET /history`) to fill the gap. The subscription is dropped as
+//! soon as the client disconnects.
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::get,
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use futures::{Stream, stream};
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+
+use crate::AppState;
+use crate::db;
+use crate::security;
+use crate::ws::helpers;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/events/{channel}", get(events))
+}
+
+/// Stream a text channel's broadcast frames as Server-Sent Events. Returns
+/// 404 for an unknown channel id.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn events(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Path(channel): Path<i32>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if db::get_channel_by_id(&state.db, channel).await.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let rx = helpers::get_or_create_channel(&state, channel)
+        .await
+        .subscribe();
+    Sse::new(channel_events(rx))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Frames from a channel subscription as SSE events. Ends when the channel's
+/// sender is gone; a lag becomes a comment instead of ending the stream.
+pub fn channel_events(rx: Receiver<String>) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(rx, |mut rx| async move {
+        let event = match rx.recv().await {
+            Ok(frame) => Event::default().data(frame),
+            Err(RecvError::Lagged(skipped)) => {
+                Event::default().comment(format!("lagged {skipped}; refetch history"))
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), rx))
+    })
+}
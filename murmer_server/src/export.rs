@@ -0,0 +1,381 @@
+//! Bulk message export and import for backups, compliance requests and
+//! migrations between instances.
+//!
+//! `GET /export?channel=<id>&format=json|csv` downloads every stored message
+//! of one text channel, or of all channels when `channel` is omitted. It is
+//! guarded by the same `ADMIN_TOKEN` bearer as the [`crate::admin`] routes.
+//!
+//! The body is streamed: messages are read in id order in batches of
+//! [`EXPORT_BATCH_SIZE`], so memory stays bounded on large tables and the
+//! shared SQLite connection is free for live traffic between batches.
+//!
+//! - `json` (default) is an array of the stored message objects, each with
+//!   its `id`, `channelId`, `channel` name and a nested `reactions` map
+//!   (emoji -> users).
+//! - `csv` flattens messages to `id,channel,user,timestamp,content`, where
+//!   `content` is the message text.
+//!
+//! `POST /import?source=<instance>` takes the JSON export format back and
+//! inserts it in one transaction, matching channels by name and creating
+//! missing ones. Each message's source `id` (or an explicit `externalId`) is
+//! stored as its external id, namespaced as `<source>:<id>`, so importing the
+//! same file twice adds nothing the second time while exports from different
+//! instances never collide. Malformed records are skipped and reported by
+//! index instead of failing the whole import.
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{DefaultBodyLimit, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
+use tracing::{error, info};
+
+use crate::AppState;
+use crate::db::{self, Db, DbError, ImportedMessage, StoredMessage};
+use crate::security::{self, validate_channel_name};
+use crate::ws::helpers;
+
+/// Messages read from the database per batch while streaming an export.
+pub const EXPORT_BATCH_SIZE: i64 = 500;
+
+/// Largest `POST /import` body accepted, in bytes.
+pub const MAX_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Longest `source` name accepted by `POST /import`.
+pub const MAX_IMPORT_SOURCE_LENGTH: usize = 64;
+
+const CSV_HEADER: &str = "id,channel,user,timestamp,content\n";
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// Query for `GET /export`.
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// Channel id to export; every channel when omitted.
+    pub channel: Option<i32>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Query for `POST /import`.
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    /// Name of the instance the export came from; namespaces its message ids.
+    pub source: String,
+}
+
+/// Whether `source` can namespace external ids: non-empty, at most
+/// [`MAX_IMPORT_SOURCE_LENGTH`] bytes and free of the `:` separator.
+pub fn valid_import_source(source: &str) -> bool {
+    !source.is_empty()
+        && source.len() <= MAX_IMPORT_SOURCE_LENGTH
+        && !source.contains(':')
+        && !source.chars().any(char::is_whitespace)
+}
+
+/// A record `POST /import` skipped, by its index in the uploaded array.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct SkippedRecord {
+    pub index: usize,
+    pub reason: &'static str,
+}
+
+/// Response of `POST /import`.
+#[derive(Debug, Serialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub skipped: usize,
+    pub errors: Vec<SkippedRecord>,
+}
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/export", get(export)).route(
+        "/import",
+        post(import).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+    )
+}
+
+/// Stream a channel's (or the whole server's) message history as a JSON or
+/// CSV attachment. Returns 404 for an unknown channel id.
+#[tracing::instrument(skip(state, bearer))]
+pub async fn export(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if let Some(id) = query.channel
+        && db::get_channel_by_id(&state.db, id).await.is_none()
+    {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let (content_type, extension, prefix, suffix) = match query.format {
+        ExportFormat::Json => ("application/json", "json", "[", "]"),
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv", CSV_HEADER, ""),
+    };
+    let filename = match query.channel {
+        Some(id) => format!("murmer-channel-{id}.{extension}"),
+        None => format!("murmer-export.{extension}"),
+    };
+
+    let names: HashMap<i32, String> = match db::get_channels(&state.db).await {
+        Ok(channels) => channels.into_iter().map(|c| (c.id, c.name)).collect(),
+        Err(e) => {
+            error!("Failed to load channels for export: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let body = stream::once(async move { Ok(prefix.to_string()) })
+        .chain(batches(
+            state.db.clone(),
+            Arc::new(names),
+            query.channel,
+            query.format,
+        ))
+        .chain(stream::once(async move { Ok(suffix.to_string()) }));
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Encoded export batches in id order. A database error ends the stream with
+/// an error, which aborts the download rather than truncating it silently.
+fn batches(
+    db: Db,
+    names: Arc<HashMap<i32, String>>,
+    channel: Option<i32>,
+    format: ExportFormat,
+) -> impl futures::Stream<Item = Result<String, DbError>> {
+    // (last exported id, whether a JSON element has been written, finished)
+    stream::unfold((0i64, false, false), move |(after, wrote, done)| {
+        let db = db.clone();
+        let names = names.clone();
+        async move {
+            if done {
+                return None;
+            }
+            let rows = match db::fetch_messages_after(&db, channel, after, EXPORT_BATCH_SIZE).await
+            {
+                Ok(rows) => rows,
+                Err(e) => {
+                    error!("export failed after message {after}: {e}");
+                    return Some((Err(e), (after, wrote, true)));
+                }
+            };
+            let last = rows.last()?.id;
+            let chunk = match format {
+                ExportFormat::Json => json_batch(&db, &names, rows, wrote).await,
+                ExportFormat::Csv => rows.iter().map(csv_row).collect(),
+            };
+            Some((Ok(chunk), (last, true, false)))
+        }
+    })
+}
+
+/// Encode a batch as comma-separated JSON array elements with reactions
+/// attached, prefixed by a comma when earlier elements were written.
+async fn json_batch(
+    db: &Db,
+    names: &HashMap<i32, String>,
+    rows: Vec<StoredMessage>,
+    wrote: bool,
+) -> String {
+    let ids: Vec<i64> = rows.iter().map(|m| m.id).collect();
+    let mut reactions = db::get_reactions_for_messages(db, &ids)
+        .await
+        .unwrap_or_else(|e| {
+            error!("db reaction load error: {e}");
+            Default::default()
+        });
+    let mut out = String::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        let mut val = serde_json::from_str::<Value>(&row.content)
+            .unwrap_or_else(|_| Value::String(row.content.clone()));
+        if let Some(map) = val.as_object_mut() {
+            map.insert("id".into(), row.id.into());
+            map.insert("channelId".into(), row.channel_id.into());
+            if let Some(name) = names.get(&row.channel_id) {
+                map.insert("channel".into(), name.clone().into());
+            }
+            let message_reactions = reactions.remove(&row.id).unwrap_or_default();
+            map.insert(
+                "reactions".into(),
+                serde_json::to_value(message_reactions).unwrap_or_default(),
+            );
+        }
+        if wrote || i > 0 {
+            out.push(',');
+        }
+        out.push_str(&val.to_string());
+    }
+    out
+}
+
+/// One `id,channel,user,timestamp,content` line. The timestamp is the one
+/// stored with the message, falling back to the server insert time.
+pub fn csv_row(row: &StoredMessage) -> String {
+    let content = serde_json::from_str::<Value>(&row.content).unwrap_or(Value::Null);
+    let field = |key: &str| content.get(key).and_then(Value::as_str);
+    let timestamp = field("timestamp").or(row.created_at.as_deref());
+    format!(
+        "{},{},{},{},{}\n",
+        row.id,
+        row.channel_id,
+        csv_field(field("user").unwrap_or_default()),
+        csv_field(timestamp.unwrap_or_default()),
+        csv_field(field("text").unwrap_or_default()),
+    )
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Import messages in the JSON export format. Channels created along the way
+/// are announced to connected clients like any other new channel. Answers
+/// 400 when `source` is not a valid namespace.
+#[tracing::instrument(skip(state, bearer, records))]
+pub async fn import(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<ImportQuery>,
+    Json(records): Json<Vec<Value>>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if !valid_import_source(&query.source) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let mut errors = Vec::new();
+    let mut indices = Vec::new();
+    let mut messages = Vec::new();
+    for (index, record) in records.iter().enumerate() {
+        match parse_import_record(record, &query.source) {
+            Ok(message) => {
+                indices.push(index);
+                messages.push(message);
+            }
+            Err(reason) => errors.push(SkippedRecord { index, reason }),
+        }
+    }
+
+    let outcome = match db::import_messages(&state.db, messages).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            error!("Failed to import messages: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    for record in &outcome.created_channels {
+        helpers::get_or_create_channel(&state, record.id).await;
+        helpers::broadcast_new_channel(&state, record).await;
+    }
+
+    let mut imported = 0;
+    for (index, inserted) in indices.into_iter().zip(outcome.inserted) {
+        if inserted {
+            imported += 1;
+        } else {
+            errors.push(SkippedRecord {
+                index,
+                reason: "duplicate",
+            });
+        }
+    }
+    errors.sort_by_key(|e| e.index);
+    info!(
+        imported,
+        skipped = errors.len(),
+        "Imported messages from export"
+    );
+    Json(ImportSummary {
+        imported,
+        skipped: errors.len(),
+        errors,
+    })
+    .into_response()
+}
+
+/// Validate one exported message from the instance named `source`. The error
+/// is the reason reported for the skipped record.
+pub fn parse_import_record(record: &Value, source: &str) -> Result<ImportedMessage, &'static str> {
+    let map = record.as_object().ok_or("not-an-object")?;
+    let channel = map
+        .get("channel")
+        .and_then(Value::as_str)
+        .filter(|name| validate_channel_name(name))
+        .ok_or("invalid-channel")?;
+    if !map
+        .get("user")
+        .and_then(Value::as_str)
+        .is_some_and(|u| !u.is_empty())
+    {
+        return Err("missing-user");
+    }
+    let created_at = map
+        .get("timestamp")
+        .and_then(Value::as_str)
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or("invalid-timestamp")?;
+    let id = match (map.get("externalId"), map.get("id")) {
+        (Some(Value::String(id)), _) if !id.is_empty() => id.clone(),
+        (None, Some(Value::Number(id))) if id.is_i64() => id.to_string(),
+        _ => return Err("missing-id"),
+    };
+    let external_id = format!("{source}:{id}");
+    let reactions = match map.get("reactions") {
+        None | Some(Value::Null) => HashMap::new(),
+        Some(value) => serde_json::from_value::<HashMap<String, Vec<String>>>(value.clone())
+            .map_err(|_| "invalid-reactions")?,
+    };
+
+    let mut content = map.clone();
+    for key in ["id", "externalId", "channel", "reactions"] {
+        content.remove(key);
+    }
+    Ok(ImportedMessage {
+        external_id,
+        channel: channel.to_string(),
+        content: Value::Object(content),
+        created_at,
+        reactions,
+    })
+}
@@ -0,0 +1,24 @@
+//! Lightweight probe routes for load balancers and uptime monitors.
+//!
+//! - `/`        – liveness check kept for existing deployments
+//! - `/healthz` – liveness check for orchestrators
+//!
+//! Both answer `GET` and `HEAD` with `200 OK` and an empty body, so probes
+//! never touch the database or allocate a response body.
+
+use axum::{Router, http::StatusCode, routing::get};
+
+async fn ok() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Probe routes, generic over the router state so they can be merged into the
+/// main application router.
+pub fn router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/", get(ok).head(ok))
+        .route("/healthz", get(ok).head(ok))
+}
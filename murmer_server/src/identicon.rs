@@ -0,0 +1,149 @@
+//! Deterministic default avatars ("identicons") derived from a public key.
+//!
+//! `GET /identicon/{key}.png` answers a PNG of a mirrored 5×5 grid whose
+//! pattern and colour come from the SHA-256 of the user's Ed25519 public key,
+//! so every user has a distinct picture before uploading an avatar. The key
+//! may be standard (percent-encoded) or URL-safe base64 and must decode to 32
+//! bytes; anything else is a 404. Rendered images are cached under
+//! `UPLOAD_DIR/identicons/` by hash, never by the requested name, so the path
+//! segment cannot reach the filesystem. The output never changes for a key,
+//! which the long-lived `Cache-Control` header reflects.
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE, URL_SAFE_NO_PAD},
+};
+use image::{ImageFormat, Rgb, RgbImage};
+use sha2::{Digest, Sha256};
+use std::{io::Cursor, sync::Arc};
+use tracing::warn;
+
+use crate::AppState;
+
+/// Cells per side of the grid; the right half mirrors the left.
+const GRID: u32 = 5;
+/// Edge length of one cell in pixels.
+const CELL: u32 = 40;
+/// Blank border around the grid in pixels.
+const MARGIN: u32 = 20;
+/// Edge length of the rendered image in pixels.
+pub const IDENTICON_SIZE: u32 = GRID * CELL + 2 * MARGIN;
+const BACKGROUND: Rgb<u8> = Rgb([240, 240, 240]);
+/// Subdirectory of `UPLOAD_DIR` holding rendered identicons.
+const CACHE_DIR: &str = "identicons";
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/identicon/{file}", get(identicon))
+}
+
+/// Decode the `{key}.png` path segment into the 32-byte public key.
+pub fn parse_identicon_key(file: &str) -> Option<[u8; 32]> {
+    let key = file.strip_suffix(".png")?;
+    [&STANDARD, &URL_SAFE, &URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(key).ok())
+        .and_then(|bytes| bytes.try_into().ok())
+}
+
+/// The identicon URL for a base64 public key, in the URL-safe form that
+/// needs no escaping. `None` when the key is not a 32-byte base64 value.
+pub fn identicon_url(public_key: &str) -> Option<String> {
+    let key = parse_identicon_key(&format!("{public_key}.png"))?;
+    Some(format!("/identicon/{}.png", URL_SAFE_NO_PAD.encode(key)))
+}
+
+/// Convert a hue in degrees with fixed saturation and lightness to RGB.
+fn hue_to_rgb(hue: u16) -> Rgb<u8> {
+    const SATURATION: f32 = 0.55;
+    const LIGHTNESS: f32 = 0.5;
+    let chroma = (1.0 - (2.0 * LIGHTNESS - 1.0).abs()) * SATURATION;
+    let sector = f32::from(hue % 360) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = LIGHTNESS - chroma / 2.0;
+    let channel = |v: f32| ((v + m) * 255.0).round() as u8;
+    Rgb([channel(r), channel(g), channel(b)])
+}
+
+/// Render the identicon for a key hash as PNG bytes.
+pub fn render_identicon(hash: &[u8; 32]) -> Vec<u8> {
+    let colour = hue_to_rgb(u16::from_be_bytes([hash[30], hash[31]]));
+    let half = GRID.div_ceil(2);
+    let mut img = RgbImage::from_pixel(IDENTICON_SIZE, IDENTICON_SIZE, BACKGROUND);
+    for row in 0..GRID {
+        for col in 0..half {
+            if hash[(row * half + col) as usize] & 1 == 0 {
+                continue;
+            }
+            for mirrored in [col, GRID - 1 - col] {
+                let (x0, y0) = (MARGIN + mirrored * CELL, MARGIN + row * CELL);
+                for y in y0..y0 + CELL {
+                    for x in x0..x0 + CELL {
+                        img.put_pixel(x, y, colour);
+                    }
+                }
+            }
+        }
+    }
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encoding an in-memory PNG cannot fail");
+    png
+}
+
+fn png_response(bytes: Vec<u8>) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "image/png"),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        bytes,
+    )
+        .into_response()
+}
+
+/// Serve the identicon for a public key, rendering and caching it on first
+/// request. A cache write failure is logged and the image served anyway.
+pub async fn identicon(State(state): State<Arc<AppState>>, Path(file): Path<String>) -> Response {
+    let Some(key) = parse_identicon_key(&file) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let hash: [u8; 32] = Sha256::digest(key).into();
+    let name: String = hash.iter().map(|b| format!("{b:02x}")).collect();
+    let dir = state.upload_dir.join(CACHE_DIR);
+    let path = dir.join(format!("{name}.png"));
+
+    if let Ok(bytes) = tokio::fs::read(&path).await {
+        return png_response(bytes);
+    }
+
+    let bytes = render_identicon(&hash);
+    // Write to a temporary name first so a concurrent request never reads a
+    // half-written file.
+    let temp = dir.join(format!("{name}.{}.tmp", rand::random::<u32>()));
+    let cached = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&temp, &bytes).await?;
+        tokio::fs::rename(&temp, &path).await
+    }
+    .await;
+    if let Err(e) = cached {
+        warn!("Failed to cache identicon {name}: {e}");
+        let _ = tokio::fs::remove_file(&temp).await;
+    }
+    png_response(bytes)
+}
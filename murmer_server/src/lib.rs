@@ -8,20 +8,29 @@ pub mod bot;
 pub mod channel_overrides;
 pub mod config;
 pub mod db;
+pub mod events;
+pub mod export;
+pub mod health;
+pub mod identicon;
 pub mod link_preview;
 pub mod permissions;
 pub mod roles;
 pub mod security;
+pub mod turn;
 pub mod upload;
+pub mod version;
+pub mod webhooks;
+pub mod word_filter;
 pub mod ws;
 
 use std::{
     collections::{HashMap, HashSet, VecDeque},
+    net::IpAddr,
     path::PathBuf,
-    sync::Arc,
+    sync::{Arc, atomic::AtomicU64},
     time::Instant,
 };
-use tokio::sync::{Mutex, broadcast};
+use tokio::sync::{Mutex, RwLock, broadcast};
 
 pub use roles::RoleDef;
 
@@ -31,8 +40,27 @@ pub struct RateLimiter {
     pub message_times: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
     /// Authentication attempt timestamps per IP (ip -> timestamps).
     pub auth_attempts: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
-    /// Used nonces to prevent replay attacks (nonce -> first seen time).
-    pub used_nonces: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Used nonces to prevent replay attacks (nonce -> first use).
+    pub used_nonces: Arc<Mutex<HashMap<String, NonceRecord>>>,
+    /// Reaction toggle timestamps per user (user -> timestamps).
+    pub reaction_times: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
+    /// Last reaction toggle per user and message ((user, message id) -> time).
+    pub reaction_toggles: Arc<Mutex<HashMap<(String, i64), Instant>>>,
+    /// Text and voice channel creations per user (user -> timestamps).
+    pub channel_creations: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
+    /// Open WebSocket connections per client IP. A std mutex so the count can
+    /// be released from `Drop` (see [`security::ConnectionSlot`]).
+    pub connections: Arc<std::sync::Mutex<HashMap<IpAddr, usize>>>,
+}
+
+/// First use of an authentication nonce, kept to tell a legitimate retry of
+/// the same handshake apart from a replay.
+pub struct NonceRecord {
+    pub first_seen: Instant,
+    /// Client IP that first presented the nonce.
+    pub origin: String,
+    /// Whether the single allowed retry has been consumed.
+    pub retried: bool,
 }
 
 impl RateLimiter {
@@ -41,6 +69,10 @@ impl RateLimiter {
             message_times: Arc::new(Mutex::new(HashMap::new())),
             auth_attempts: Arc::new(Mutex::new(HashMap::new())),
             used_nonces: Arc::new(Mutex::new(HashMap::new())),
+            reaction_times: Arc::new(Mutex::new(HashMap::new())),
+            reaction_toggles: Arc::new(Mutex::new(HashMap::new())),
+            channel_creations: Arc::new(Mutex::new(HashMap::new())),
+            connections: Arc::new(std::sync::Mutex::new(HashMap::new())),
         }
     }
 }
@@ -86,20 +118,27 @@ pub struct AppState {
     pub channels: Arc<Mutex<HashMap<i32, broadcast::Sender<String>>>>,
     pub db: db::Db,
     pub users: Arc<Mutex<HashSet<String>>>,
-    pub known_users: Arc<Mutex<HashSet<String>>>,
+    /// Live authenticated connections per user name. A user with several
+    /// clients open stays in `users` until the last one disconnects.
+    pub connections: Arc<Mutex<HashMap<String, usize>>>,
+    pub known_users: Arc<RwLock<HashSet<String>>>,
     /// Voice channel state, keyed by voice channel ID.
-    pub voice_channels: Arc<Mutex<HashMap<i32, VoiceChannelState>>>,
+    pub voice_channels: Arc<RwLock<HashMap<i32, VoiceChannelState>>>,
     /// All role definitions, keyed by role id. Loaded at startup and mutated
     /// as roles are created/edited/deleted.
-    pub role_defs: Arc<Mutex<HashMap<i64, RoleDef>>>,
+    pub role_defs: Arc<RwLock<HashMap<i64, RoleDef>>>,
     /// Roles assigned to each connected user (username → role ids). Populated
     /// at authentication from the `user_roles` table.
-    pub user_roles: Arc<Mutex<HashMap<String, Vec<i64>>>>,
+    pub user_roles: Arc<RwLock<HashMap<String, Vec<i64>>>>,
     /// Per-channel permission overrides, keyed by (kind, channel id). Loaded at
     /// startup and mutated as channel permissions change.
     pub channel_overrides:
         Arc<Mutex<HashMap<(channel_overrides::ChannelKind, i32), channel_overrides::OverrideSet>>>,
-    pub statuses: Arc<Mutex<HashMap<String, String>>>,
+    pub statuses: Arc<RwLock<HashMap<String, String>>>,
+    /// When each user was last active: their latest message or disconnect.
+    /// Loaded at startup and persisted on disconnect; only reported for
+    /// users who are not connected.
+    pub last_seen: Arc<Mutex<HashMap<String, chrono::DateTime<chrono::Utc>>>>,
     pub user_keys: Arc<Mutex<HashMap<String, String>>>,
     /// Active mutes keyed by public key; `None` means muted indefinitely.
     pub mutes: Arc<Mutex<HashMap<String, Option<chrono::DateTime<chrono::Utc>>>>>,
@@ -114,8 +153,67 @@ pub struct AppState {
     pub voice_session_starts: Arc<Mutex<HashMap<String, Instant>>>,
     /// When each user started screen sharing; mirrors `voice_session_starts`.
     pub screenshare_session_starts: Arc<Mutex<HashMap<String, Instant>>>,
+    /// When each user last posted in each slow-mode channel, keyed by
+    /// (username, channel id). Entries for a channel are dropped whenever its
+    /// slow mode changes.
+    pub slow_mode_posts: Arc<Mutex<HashMap<(String, i32), Instant>>>,
+    /// Compiled `WORDLIST_PATH` word list; `None` when no list is configured.
+    /// Replaced wholesale by `POST /admin/reload-wordlist`.
+    pub word_filter: Arc<RwLock<Option<word_filter::WordFilter>>>,
+    /// Live connections that have each text channel open, keyed by channel
+    /// id. A std mutex so a subscription can be released in `Drop`; see
+    /// `ws::helpers::ChannelSubscription`.
+    pub channel_subscribers: Arc<std::sync::Mutex<HashMap<i32, usize>>>,
+    /// Next `seq` handed out by `db::apply_reaction`, ordering the reaction
+    /// summaries it returns.
+    pub reaction_seq: Arc<AtomicU64>,
+    /// Messages with a coalesced `reaction-update` already scheduled, with
+    /// the newest summary known for each. `None` means a change arrived
+    /// without one, so the broadcast must re-read the database.
+    pub pending_reaction_updates: Arc<std::sync::Mutex<HashMap<i64, Option<db::ReactionChange>>>>,
+    /// Channels with a `read-receipt-update` batch already scheduled.
+    pub pending_read_receipts: Arc<std::sync::Mutex<HashSet<i32>>>,
     pub upload_dir: PathBuf,
     pub password: Option<String>,
     pub admin_token: Option<String>,
     pub rate_limiter: RateLimiter,
 }
+
+impl AppState {
+    /// State with nothing loaded yet: no channels, roles, overrides, mutes or
+    /// word filter, no password and no admin token. Startup and tests fill in
+    /// what they need with struct update syntax.
+    pub fn new(tx: broadcast::Sender<String>, db: db::Db, upload_dir: PathBuf) -> Self {
+        Self {
+            tx,
+            channels: Arc::new(Mutex::new(HashMap::new())),
+            db,
+            users: Arc::new(Mutex::new(HashSet::new())),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            known_users: Arc::new(RwLock::new(HashSet::new())),
+            voice_channels: Arc::new(RwLock::new(HashMap::new())),
+            role_defs: Arc::new(RwLock::new(HashMap::new())),
+            user_roles: Arc::new(RwLock::new(HashMap::new())),
+            channel_overrides: Arc::new(Mutex::new(HashMap::new())),
+            statuses: Arc::new(RwLock::new(HashMap::new())),
+            last_seen: Arc::new(Mutex::new(HashMap::new())),
+            user_keys: Arc::new(Mutex::new(HashMap::new())),
+            mutes: Arc::new(Mutex::new(HashMap::new())),
+            active_screen_shares: Arc::new(Mutex::new(HashMap::new())),
+            voice_mutes: Arc::new(Mutex::new(HashMap::new())),
+            connection_stats: Arc::new(Mutex::new(HashMap::new())),
+            voice_session_starts: Arc::new(Mutex::new(HashMap::new())),
+            screenshare_session_starts: Arc::new(Mutex::new(HashMap::new())),
+            slow_mode_posts: Arc::new(Mutex::new(HashMap::new())),
+            word_filter: Arc::new(RwLock::new(None)),
+            channel_subscribers: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            reaction_seq: Arc::new(AtomicU64::new(0)),
+            pending_reaction_updates: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            pending_read_receipts: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            upload_dir,
+            password: None,
+            admin_token: None,
+            rate_limiter: RateLimiter::new(),
+        }
+    }
+}
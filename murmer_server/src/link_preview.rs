@@ -10,6 +10,12 @@
 //! first, every resolved address must be public, and the connection is pinned
 //! to the vetted address so a DNS rebind cannot redirect the request into the
 //! local network. Redirects are followed manually and re-vetted per hop.
+//!
+//! With `ENABLE_LINK_PREVIEWS` set, [`spawn_message_unfurl`] also runs the
+//! same fetch for the first URL of every new chat message and sends a
+//! `link-preview` frame (`messageId`, `channelId`, `preview`) to the channel.
+//! Those previews are cached in the `link_previews` table for
+//! [`STORED_PREVIEW_TTL`], so a link shared again is not refetched.
 
 use axum::{
     Json,
@@ -22,10 +28,12 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
-    sync::{Mutex, OnceLock},
+    sync::{Arc, Mutex, OnceLock},
     time::{Duration, Instant},
 };
-use tracing::debug;
+use tracing::{debug, error};
+
+use crate::{AppState, db};
 
 /// Maximum HTML bytes read from the target page.
 const MAX_BODY_BYTES: usize = 512 * 1024;
@@ -37,11 +45,13 @@ const MAX_REDIRECTS: usize = 3;
 const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
 /// Upper bound on cached entries; the cache is cleared when exceeded.
 const CACHE_MAX_ENTRIES: usize = 512;
+/// How long a preview unfurled for a message stays in `link_previews`.
+pub const STORED_PREVIEW_TTL: chrono::Duration = chrono::Duration::days(1);
 /// Upper bounds on returned field lengths (characters).
 const MAX_TITLE_LEN: usize = 300;
 const MAX_DESCRIPTION_LEN: usize = 500;
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Preview {
     url: String,
@@ -105,6 +115,79 @@ pub async fn link_preview(Query(q): Query<PreviewQuery>) -> Response {
     }
 }
 
+/// The first `http(s)://` URL in a message text, without trailing
+/// punctuation that usually ends the sentence rather than the link.
+pub fn first_url(text: &str) -> Option<&str> {
+    text.split(|c: char| c.is_whitespace() || c == '<' || c == '>')
+        .find(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'', '"']))
+}
+
+/// The preview for `raw`, from the `link_previews` table when a fresh one is
+/// stored, otherwise fetched (with the same URL and address checks as the
+/// endpoint) and stored. `None` when the URL is refused or the fetch fails.
+pub async fn unfurl(db: &db::Db, raw: &str) -> Option<Preview> {
+    let url = match validate_url(raw) {
+        Ok(url) => url,
+        Err(reason) => {
+            debug!(%reason, "not unfurling link");
+            return None;
+        }
+    };
+    let key = url.to_string();
+    match db::get_link_preview(db, &key, STORED_PREVIEW_TTL).await {
+        Ok(Some(stored)) => {
+            if let Ok(preview) = serde_json::from_str(&stored) {
+                return Some(preview);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => error!("failed to load cached link preview: {e}"),
+    }
+    let preview = match fetch_preview(url).await {
+        Ok(preview) => preview,
+        Err(reason) => {
+            debug!(%reason, "link unfurl failed");
+            return None;
+        }
+    };
+    if let Ok(json) = serde_json::to_string(&preview)
+        && let Err(e) = db::store_link_preview(db, &key, &json).await
+    {
+        error!("failed to cache link preview: {e}");
+    }
+    Some(preview)
+}
+
+/// Unfurl the first link of a just-stored chat message in the background and
+/// send the result to the message's channel as `link-preview`. Does nothing
+/// unless `ENABLE_LINK_PREVIEWS` is on or when the text has no link; pages
+/// without a title or description are not announced.
+pub fn spawn_message_unfurl(state: Arc<AppState>, channel_id: i32, message_id: i64, text: &str) {
+    if !crate::config::link_previews_enabled() {
+        return;
+    }
+    let Some(url) = first_url(text).map(str::to_owned) else {
+        return;
+    };
+    tokio::spawn(async move {
+        let Some(preview) = unfurl(&state.db, &url).await else {
+            return;
+        };
+        if preview.title.is_none() && preview.description.is_none() {
+            return;
+        }
+        let frame = serde_json::json!({
+            "type": "link-preview",
+            "messageId": message_id,
+            "channelId": channel_id,
+            "preview": preview,
+        });
+        let chan_tx = crate::ws::helpers::get_or_create_channel(&state, channel_id).await;
+        let _ = chan_tx.send(frame.to_string());
+    });
+}
+
 /// Parse and vet a user-supplied URL: http(s) only, no credentials, default
 /// ports only (blocks internal port scanning).
 fn validate_url(raw: &str) -> Result<Url, &'static str> {
@@ -520,4 +603,29 @@ mod tests {
             assert!(is_public_ip(ip.parse().unwrap()), "{ip} should be public");
         }
     }
+
+    /// Every spelling of an internal host is refused at resolution, before
+    /// any connection is attempted.
+    #[tokio::test]
+    async fn resolution_refuses_internal_hosts() {
+        for raw in [
+            "http://localhost/",
+            "http://127.0.0.1/",
+            "http://2130706433/",
+            "http://0x7f.0.0.1/",
+            "http://10.0.0.1/",
+            "http://172.31.255.255/",
+            "http://192.168.0.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[fd12::1]/",
+        ] {
+            let url = validate_url(raw).expect("well-formed URL");
+            assert!(
+                resolve_public(&url).await.is_err(),
+                "{raw} should be refused"
+            );
+        }
+    }
 }
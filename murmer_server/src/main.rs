@@ -1,9 +1,17 @@
 //! Murmer WebSocket server: provides text and voice chat over WebSocket with SQLite persistence.
 //!
+//! - `/`, `/healthz`: probe endpoints answering `GET`/`HEAD` with 200.
+//! - `/version`: crate name, version, git SHA and build time.
 //! - `/ws`: WebSocket endpoint for chat and voice events.
-//! - `/upload`: HTTP endpoint for uploading files.
+//! - `/upload`: HTTP endpoint for uploading files; `DELETE /upload/:key` removes one.
 //! - `/link-preview`: HTTP endpoint returning OpenGraph metadata for a URL.
-//! - `/role`: HTTP endpoint for managing user roles (requires `ADMIN_TOKEN`).
+//! - `/role`: HTTP endpoint for assigning (`POST`) and removing (`DELETE`) user
+//!   roles (requires `ADMIN_TOKEN`); `GET /roles` lists assignments.
+//! - `/role/permissions`: edit a role's permission mask (requires `ADMIN_TOKEN`).
+//! - `/stats`: connection and channel counts (requires `ADMIN_TOKEN`).
+//! - `/announce`: server-wide announcements (requires `ADMIN_TOKEN`).
+//! - `/admin/reload-wordlist`: re-read `WORDLIST_PATH` (requires `ADMIN_TOKEN`).
+//! - `/api/v1/webhooks`: outgoing webhook registration (requires `ADMIN_TOKEN`).
 //!
 //! Configuration via environment variables:
 //! - `DATABASE_PATH`: path to the SQLite database file (default: `murmer.db`).
@@ -12,19 +20,22 @@
 //! - `ADMIN_TOKEN`: token for admin role management.
 //! - `BIND_ADDRESS`: optional socket address to bind to (defaults to `0.0.0.0:3001`).
 //! - `CORS_ALLOW_ORIGINS`: comma separated list of origins allowed to access HTTP endpoints.
+//! - `CORS_ALLOW_METHODS` / `CORS_ALLOW_HEADERS` / `CORS_ALLOW_CREDENTIALS`: what those origins may send.
 //!
 //! Run with `cargo run` or via Docker Compose (`docker compose up --build`).
 use anyhow::{Context, Result};
 use axum::{
     Router,
     extract::DefaultBodyLimit,
-    http::{HeaderValue, StatusCode, header},
-    routing::{get, post},
+    http::{HeaderValue, header},
+    routing::{delete, get, post},
 };
 use dotenvy::dotenv;
 use murmer_server::{
-    AppState, RateLimiter, VoiceChannelState, admin, bot, config::Config, db, link_preview, upload,
-    ws,
+    AppState, VoiceChannelState, admin, bot,
+    config::{self, Config},
+    db, events, export, health, identicon, link_preview, turn, upload, version, webhooks,
+    word_filter, ws,
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -34,7 +45,7 @@ use std::{
 use tokio::{
     net::TcpListener,
     signal,
-    sync::{Mutex, broadcast},
+    sync::{Mutex, RwLock, broadcast},
 };
 use tower::ServiceBuilder;
 use tower_http::{
@@ -48,17 +59,28 @@ fn init_tracing() {
     INIT.get_or_init(|| {
         let filter = tracing_subscriber::EnvFilter::try_from_default_env()
             .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("murmer_server=info,axum=info"));
-        tracing_subscriber::fmt()
+        let builder = tracing_subscriber::fmt()
             .with_env_filter(filter)
-            .with_target(false)
-            .compact()
-            .init();
+            .with_target(false);
+        if config::json_logs() {
+            // Flatten event fields and keep the active span list so
+            // `#[instrument]` fields (e.g. `client_addr`) stay searchable.
+            builder
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(true)
+                .init();
+        } else {
+            builder.compact().init();
+        }
     });
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
+    config::load_config_file()?;
 
     let args: Vec<String> = std::env::args().collect();
     if args.len() >= 2 && args[1] == "set-role" {
@@ -78,7 +100,9 @@ async fn main() -> Result<()> {
         .await
         .context("failed to initialise database connection")?;
 
-    let existing_voice = db::get_voice_channels(&db_client).await;
+    let existing_voice = db::get_voice_channels(&db_client)
+        .await
+        .context("failed to load voice channels")?;
 
     let existing_mutes = db::get_all_mutes(&db_client).await.unwrap_or_default();
 
@@ -86,6 +110,13 @@ async fn main() -> Result<()> {
 
     let existing_overrides = db::load_all_overrides(&db_client).await.unwrap_or_default();
 
+    let existing_last_seen = db::get_all_last_seen(&db_client).await.unwrap_or_default();
+
+    let word_filter = word_filter::load_configured()?;
+    if let Some(filter) = &word_filter {
+        info!("Word filter loaded with {} entries", filter.len());
+    }
+
     tokio::fs::create_dir_all(&config.upload_dir)
         .await
         .with_context(|| {
@@ -96,12 +127,7 @@ async fn main() -> Result<()> {
         })?;
 
     let state = Arc::new(AppState {
-        tx: tx.clone(),
-        channels: Arc::new(Mutex::new(HashMap::new())),
-        db: db_client,
-        users: Arc::new(Mutex::new(HashSet::new())),
-        known_users: Arc::new(Mutex::new(HashSet::new())),
-        voice_channels: Arc::new(Mutex::new({
+        voice_channels: Arc::new(RwLock::new({
             let mut map = HashMap::new();
             for record in &existing_voice {
                 map.insert(
@@ -118,37 +144,30 @@ async fn main() -> Result<()> {
             }
             map
         })),
-        role_defs: Arc::new(Mutex::new(
+        role_defs: Arc::new(RwLock::new(
             existing_role_defs
                 .into_iter()
                 .map(|def| (def.id, def))
                 .collect(),
         )),
-        user_roles: Arc::new(Mutex::new(HashMap::new())),
         channel_overrides: Arc::new(Mutex::new(existing_overrides)),
-        statuses: Arc::new(Mutex::new(HashMap::new())),
-        user_keys: Arc::new(Mutex::new(HashMap::new())),
+        last_seen: Arc::new(Mutex::new(existing_last_seen.into_iter().collect())),
         mutes: Arc::new(Mutex::new(existing_mutes.into_iter().collect())),
-        active_screen_shares: Arc::new(Mutex::new(HashMap::new())),
-        voice_mutes: Arc::new(Mutex::new(HashMap::new())),
-        connection_stats: Arc::new(Mutex::new(HashMap::new())),
-        voice_session_starts: Arc::new(Mutex::new(HashMap::new())),
-        screenshare_session_starts: Arc::new(Mutex::new(HashMap::new())),
-        upload_dir: config.upload_dir.clone(),
+        word_filter: Arc::new(RwLock::new(word_filter)),
         password: config.password.clone(),
         admin_token: config.admin_token.clone(),
-        rate_limiter: RateLimiter::default(),
+        ..AppState::new(tx.clone(), db_client, config.upload_dir.clone())
     });
 
-    // Ephemeral deletion timers only live in memory; re-arm any that were
-    // lost to a restart (expired ones are deleted immediately).
-    ws::helpers::resume_ephemeral_deletions(&state).await;
+    // The sweeper posts scheduled messages and deletes ephemeral ones whose
+    // in-memory timer was lost, including whatever fell due during downtime.
+    ws::helpers::spawn_message_sweeper(state.clone());
+    ws::helpers::spawn_retention_purger(state.clone());
+    upload::spawn_upload_reaper(state.clone());
 
     let mut router = Router::new()
-        .route(
-            "/",
-            get(|| async { StatusCode::OK }).head(|| async { StatusCode::OK }),
-        )
+        .merge(health::router())
+        .merge(version::router())
         .route(
             "/ws",
             get(ws::ws_handler).layer(DefaultBodyLimit::disable()),
@@ -156,12 +175,18 @@ async fn main() -> Result<()> {
         .route(
             "/upload",
             post(upload::upload).layer(DefaultBodyLimit::max(
-                upload::MAX_FILE_SIZE + (1024_usize * 1024),
+                upload::MAX_FILE_SIZE.max(upload::MAX_FILE_SIZE_DOCS) + (1024_usize * 1024),
             )),
         )
+        .route("/upload/{key}", delete(upload::delete_upload))
         .route("/link-preview", get(link_preview::link_preview))
-        .route("/role", post(admin::set_role))
+        .merge(admin::router())
+        .merge(export::router())
+        .merge(events::router())
+        .merge(identicon::router())
+        .merge(turn::router())
         .merge(bot::routes::router())
+        .merge(webhooks::router())
         .nest_service(
             "/files",
             ServeDir::new(&config.upload_dir).append_index_html_on_directories(false),
@@ -187,6 +212,12 @@ async fn main() -> Result<()> {
         .layer(TraceLayer::new_for_http())
         .layer(security_headers);
 
+    if let Some(version) = config::server_version()
+        && let Ok(value) = HeaderValue::from_str(&format!("murmer/{version}"))
+    {
+        router = router.layer(SetResponseHeaderLayer::overriding(header::SERVER, value));
+    }
+
     if let Some(cors) = config.cors_layer() {
         if let Some(origins) = config.cors_origins() {
             info!(?origins, "CORS enabled for configured origins");
@@ -232,7 +263,7 @@ async fn cli_set_role(args: &[String]) -> Result<()> {
         .cloned()
         .or_else(|| murmer_server::roles::default_color(role));
 
-    let db_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "murmer.db".to_string());
+    let db_path = config::var("DATABASE_PATH").unwrap_or_else(|| "murmer.db".to_string());
     let client = db::init(&db_path)
         .await
         .context("failed to connect to database")?;
@@ -262,7 +293,7 @@ async fn cli_unbind_name(args: &[String]) -> Result<()> {
         std::process::exit(1);
     };
 
-    let db_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "murmer.db".to_string());
+    let db_path = config::var("DATABASE_PATH").unwrap_or_else(|| "murmer.db".to_string());
     let client = db::init(&db_path)
         .await
         .context("failed to connect to database")?;
@@ -1,28 +1,72 @@
 //! Security utilities for rate limiting and replay attack prevention.
 
-use crate::RateLimiter;
+use crate::{AppState, NonceRecord, RateLimiter};
+use axum::{
+    Extension,
+    extract::ConnectInfo,
+    http::{HeaderMap, StatusCode},
+};
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
-use tracing::warn;
+use subtle::ConstantTimeEq;
+use tracing::{error, warn};
 
 /// Get the maximum number of messages allowed per user per minute.
 ///
 /// Reads from the `MAX_MESSAGES_PER_MINUTE` environment variable, defaulting to 30.
 pub fn get_max_messages_per_minute() -> usize {
-    std::env::var("MAX_MESSAGES_PER_MINUTE")
-        .ok()
+    crate::config::var("MAX_MESSAGES_PER_MINUTE")
         .and_then(|s| s.parse().ok())
         .unwrap_or(30)
 }
 
+/// Built-in staff roles that get twice the default message limit unless
+/// `ROLE_MESSAGE_LIMITS` says otherwise.
+const ELEVATED_RATE_LIMIT_ROLES: &[&str] = &["Mod", "Admin", "Owner"];
+
+/// Get the per-minute message limit for a role.
+///
+/// Explicit limits come from `ROLE_MESSAGE_LIMITS`, a comma-separated list of
+/// `Role=limit` pairs (role names match case-insensitively), e.g.
+/// `Trusted=60,Admin=120`. Without an entry, the built-in Mod, Admin and
+/// Owner roles get twice `MAX_MESSAGES_PER_MINUTE`; every other role, and
+/// `None` for a user with no roles, gets the default.
+pub fn get_max_messages_for_role(role: Option<&str>) -> usize {
+    let default = get_max_messages_per_minute();
+    let Some(role) = role else {
+        return default;
+    };
+    let configured = crate::config::var("ROLE_MESSAGE_LIMITS").and_then(|raw| {
+        raw.split(',').find_map(|entry| {
+            let (name, limit) = entry.split_once('=')?;
+            if name.trim().eq_ignore_ascii_case(role) {
+                limit.trim().parse().ok()
+            } else {
+                None
+            }
+        })
+    });
+    configured.unwrap_or_else(|| {
+        if ELEVATED_RATE_LIMIT_ROLES
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(role))
+        {
+            default.saturating_mul(2)
+        } else {
+            default
+        }
+    })
+}
+
 /// Get the maximum number of authentication attempts allowed per IP per minute.
 ///
 /// Reads from the `MAX_AUTH_ATTEMPTS_PER_MINUTE` environment variable, defaulting to 5.
 pub fn get_max_auth_attempts_per_minute() -> usize {
-    std::env::var("MAX_AUTH_ATTEMPTS_PER_MINUTE")
-        .ok()
+    crate::config::var("MAX_AUTH_ATTEMPTS_PER_MINUTE")
         .and_then(|s| s.parse().ok())
         .unwrap_or(5)
 }
@@ -31,12 +75,89 @@ pub fn get_max_auth_attempts_per_minute() -> usize {
 ///
 /// Reads from the `NONCE_EXPIRY_SECONDS` environment variable, defaulting to 300 (5 minutes).
 pub fn get_nonce_expiry_seconds() -> u64 {
-    std::env::var("NONCE_EXPIRY_SECONDS")
-        .ok()
+    crate::config::var("NONCE_EXPIRY_SECONDS")
         .and_then(|s| s.parse().ok())
         .unwrap_or(300) // 5 minutes
 }
 
+/// Get how far, in seconds, a signed authentication timestamp may differ from
+/// the server clock.
+///
+/// Reads from the `AUTH_TIMESTAMP_WINDOW_SECONDS` environment variable,
+/// defaulting to 60. Clamped to 1..=3600 so a typo cannot accept signatures
+/// of any age. Keep `NONCE_EXPIRY_SECONDS` at least twice this window, or a
+/// nonce may be forgotten while its timestamp is still accepted.
+pub fn get_auth_timestamp_window_seconds() -> i64 {
+    crate::config::var("AUTH_TIMESTAMP_WINDOW_SECONDS")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(60)
+        .clamp(1, 3600)
+}
+
+/// Get the grace window in seconds during which the client that first used a
+/// nonce may present it once more (a retried handshake).
+///
+/// Reads from the `NONCE_RETRY_GRACE_SECONDS` environment variable, defaulting
+/// to 0 (retries disabled). The client is identified by its peer IP, which a
+/// reverse proxy shares between all clients, so only enable this when clients
+/// connect directly.
+pub fn get_nonce_retry_grace_seconds() -> u64 {
+    crate::config::var("NONCE_RETRY_GRACE_SECONDS")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Get the maximum number of reaction toggles allowed per user per minute.
+///
+/// Reads from the `MAX_REACTIONS_PER_MINUTE` environment variable, defaulting to 50.
+pub fn get_max_reactions_per_minute() -> usize {
+    crate::config::var("MAX_REACTIONS_PER_MINUTE")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(50)
+}
+
+/// Get the minimum delay in milliseconds between two reaction toggles by the
+/// same user on the same message.
+///
+/// Reads from the `REACTION_TOGGLE_COOLDOWN_MS` environment variable,
+/// defaulting to 500. `0` disables the cooldown.
+pub fn get_reaction_toggle_cooldown_ms() -> u64 {
+    crate::config::var("REACTION_TOGGLE_COOLDOWN_MS")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(500)
+}
+
+/// Get the maximum number of text and voice channels one user may create per
+/// minute.
+///
+/// Reads from the `MAX_CHANNEL_CREATIONS_PER_MINUTE` environment variable,
+/// defaulting to 10.
+pub fn get_max_channel_creations_per_minute() -> usize {
+    crate::config::var("MAX_CHANNEL_CREATIONS_PER_MINUTE")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(10)
+}
+
+/// Get the maximum number of channels on the server, text and voice together.
+///
+/// Reads from the `MAX_CHANNELS` environment variable, defaulting to 500.
+/// `0` disables the cap.
+pub fn get_max_channels() -> usize {
+    crate::config::var("MAX_CHANNELS")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(500)
+}
+
+/// Get the maximum number of simultaneous WebSocket connections per client IP.
+///
+/// Reads from the `MAX_CONNECTIONS_PER_IP` environment variable, defaulting to
+/// 20. `0` disables the limit.
+pub fn get_max_connections_per_ip() -> usize {
+    crate::config::var("MAX_CONNECTIONS_PER_IP")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(20)
+}
+
 /// Clean up timestamps older than the cutoff time from a VecDeque.
 ///
 /// This is a helper function to reduce duplication between different rate limiters.
@@ -88,16 +209,26 @@ pub async fn check_auth_rate_limit(rate_limiter: &RateLimiter, ip: &str) -> bool
 /// Check if a user is rate limited for messages.
 ///
 /// This function implements a sliding window rate limiter that allows up to
-/// `MAX_MESSAGES_PER_MINUTE` messages per user within a 60-second window.
+/// `MAX_MESSAGES_PER_MINUTE` messages per user within a 60-second window, or
+/// the most generous [`get_max_messages_for_role`] limit among `roles`.
 ///
 /// # Arguments
 /// * `rate_limiter` - The shared rate limiter state
 /// * `user` - The username to check
+/// * `roles` - Names of the roles the user holds (empty for none, and for bots)
 ///
 /// # Returns
 /// * `true` if the message should be allowed
 /// * `false` if the rate limit has been exceeded
-pub async fn check_message_rate_limit(rate_limiter: &RateLimiter, user: &str) -> bool {
+pub async fn check_message_rate_limit(
+    rate_limiter: &RateLimiter,
+    user: &str,
+    roles: &[String],
+) -> bool {
+    let limit = roles
+        .iter()
+        .map(|role| get_max_messages_for_role(Some(role)))
+        .fold(get_max_messages_for_role(None), usize::max);
     let now = Instant::now();
     let mut message_times = rate_limiter.message_times.lock().await;
     let cutoff = now - Duration::from_secs(60);
@@ -110,7 +241,7 @@ pub async fn check_message_rate_limit(rate_limiter: &RateLimiter, user: &str) ->
     });
 
     let current_messages = message_times.get(user).map_or(0, |v| v.len());
-    if current_messages >= get_max_messages_per_minute() {
+    if current_messages >= limit {
         warn!("Rate limit exceeded for messages from user: {}", user);
         return false;
     }
@@ -122,41 +253,212 @@ pub async fn check_message_rate_limit(rate_limiter: &RateLimiter, user: &str) ->
     true
 }
 
+/// Check if a user is rate limited for reaction toggles across all messages.
+///
+/// Same sliding 60-second window as [`check_message_rate_limit`], allowing up
+/// to `MAX_REACTIONS_PER_MINUTE` adds and removes per user. Complements the
+/// per-message cooldown in [`check_reaction_cooldown`], which alone would let
+/// a client spray reactions over many different messages.
+///
+/// # Returns
+/// * `true` if the toggle should be allowed
+/// * `false` if the rate limit has been exceeded
+pub async fn check_reaction_rate_limit(rate_limiter: &RateLimiter, user: &str) -> bool {
+    let now = Instant::now();
+    let mut reaction_times = rate_limiter.reaction_times.lock().await;
+    let cutoff = now - Duration::from_secs(60);
+
+    reaction_times.retain(|_, timestamps| {
+        cleanup_old_timestamps(timestamps, cutoff);
+        !timestamps.is_empty()
+    });
+
+    let current = reaction_times.get(user).map_or(0, |v| v.len());
+    if current >= get_max_reactions_per_minute() {
+        warn!("Rate limit exceeded for reactions from user: {}", user);
+        return false;
+    }
+
+    reaction_times
+        .entry(user.to_string())
+        .or_default()
+        .push_back(now);
+    true
+}
+
+/// Check if a user is rate limited for creating channels.
+///
+/// Same sliding 60-second window as [`check_message_rate_limit`], allowing up
+/// to `MAX_CHANNEL_CREATIONS_PER_MINUTE` text and voice channel creations per
+/// user combined. Every creation is broadcast to all clients, so this keeps a
+/// single account with Manage Channels from flooding the channel list.
+///
+/// # Returns
+/// * `true` if the creation should be allowed
+/// * `false` if the rate limit has been exceeded
+pub async fn check_channel_creation_rate_limit(rate_limiter: &RateLimiter, user: &str) -> bool {
+    let now = Instant::now();
+    let mut creations = rate_limiter.channel_creations.lock().await;
+    let cutoff = now - Duration::from_secs(60);
+
+    creations.retain(|_, timestamps| {
+        cleanup_old_timestamps(timestamps, cutoff);
+        !timestamps.is_empty()
+    });
+
+    let current = creations.get(user).map_or(0, |v| v.len());
+    if current >= get_max_channel_creations_per_minute() {
+        warn!(
+            "Rate limit exceeded for channel creation from user: {}",
+            user
+        );
+        return false;
+    }
+
+    creations
+        .entry(user.to_string())
+        .or_default()
+        .push_back(now);
+    true
+}
+
+/// Check whether a user may toggle a reaction on a message yet.
+///
+/// Rapid add/remove toggling on one message would otherwise fan out a
+/// `reaction-update` broadcast to the whole channel for every click. A user
+/// may toggle reactions on a given message once per
+/// `REACTION_TOGGLE_COOLDOWN_MS`; other messages are unaffected.
+///
+/// # Returns
+/// * `true` if the toggle should be applied (and has been recorded)
+/// * `false` if the user is still cooling down on this message
+pub async fn check_reaction_cooldown(
+    rate_limiter: &RateLimiter,
+    user: &str,
+    message_id: i64,
+) -> bool {
+    let cooldown = Duration::from_millis(get_reaction_toggle_cooldown_ms());
+    if cooldown.is_zero() {
+        return true;
+    }
+    let now = Instant::now();
+    let mut toggles = rate_limiter.reaction_toggles.lock().await;
+
+    // Entries past the cooldown carry no information; drop them so the map
+    // only holds the last few hundred milliseconds of activity.
+    toggles.retain(|_, last| now.duration_since(*last) < cooldown);
+
+    let key = (user.to_string(), message_id);
+    if toggles.contains_key(&key) {
+        return false;
+    }
+    toggles.insert(key, now);
+    true
+}
+
+/// One open WebSocket connection counted against its IP. The count is
+/// released when the slot is dropped, including when an upgrade never
+/// completes.
+pub struct ConnectionSlot {
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut connections = self
+            .connections
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(count) = connections.get_mut(&self.ip) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Reserve a connection slot for `ip`.
+///
+/// Checked and counted in one step before the upgrade, so a burst of parallel
+/// upgrades cannot overshoot `MAX_CONNECTIONS_PER_IP`.
+///
+/// # Returns
+/// * `Some(slot)` if the connection is allowed; keep it for the socket's lifetime
+/// * `None` if the IP already holds the maximum number of connections
+pub fn acquire_connection_slot(rate_limiter: &RateLimiter, ip: IpAddr) -> Option<ConnectionSlot> {
+    let max = get_max_connections_per_ip();
+    let mut connections = rate_limiter
+        .connections
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let count = connections.entry(ip).or_insert(0);
+    if max > 0 && *count >= max {
+        warn!("Connection limit exceeded for IP: {}", ip);
+        return None;
+    }
+    *count += 1;
+    Some(ConnectionSlot {
+        connections: rate_limiter.connections.clone(),
+        ip,
+    })
+}
+
 /// Check if a nonce has been used and store it for replay attack prevention.
 ///
 /// This function implements a sliding window for nonce validation. Nonces expire after
 /// `NONCE_EXPIRY_SECONDS` (default: 300 seconds). If a nonce is already in the cache,
 /// it indicates a replay attack attempt.
 ///
+/// One exception covers a client retrying its handshake after a network blip
+/// with the same signed timestamp: the origin that first presented the nonce
+/// may present it exactly once more within `NONCE_RETRY_GRACE_SECONDS`. Any
+/// other origin, a second retry or a retry after the grace window is still
+/// rejected, so a captured signature cannot be replayed from elsewhere.
+///
 /// # Arguments
 /// * `rate_limiter` - The shared rate limiter state containing used nonces
 /// * `nonce` - The nonce string to check (typically derived from user's public key and timestamp)
+/// * `origin` - The client IP presenting the nonce
 ///
 /// # Returns
-/// * `true` if the nonce is valid and has been stored
+/// * `true` if the nonce is valid (new, or an allowed retry) and has been stored
 /// * `false` if the nonce has already been used (potential replay attack)
-pub async fn check_and_store_nonce(rate_limiter: &RateLimiter, nonce: &str) -> bool {
+pub async fn check_and_store_nonce(rate_limiter: &RateLimiter, nonce: &str, origin: &str) -> bool {
     let now = Instant::now();
     let mut used_nonces = rate_limiter.used_nonces.lock().await;
 
     let expiry_cutoff = now - Duration::from_secs(get_nonce_expiry_seconds());
-    used_nonces.retain(|_, &mut expiry_time| expiry_time > expiry_cutoff);
+    used_nonces.retain(|_, record| record.first_seen > expiry_cutoff);
 
-    if used_nonces.contains_key(nonce) {
+    if let Some(record) = used_nonces.get_mut(nonce) {
+        let grace = Duration::from_secs(get_nonce_retry_grace_seconds());
+        if !record.retried && record.origin == origin && now - record.first_seen < grace {
+            record.retried = true;
+            return true;
+        }
         warn!("Replay attack detected - nonce already used: {}", nonce);
         return false;
     }
 
-    used_nonces.insert(nonce.to_string(), now);
+    used_nonces.insert(
+        nonce.to_string(),
+        NonceRecord {
+            first_seen: now,
+            origin: origin.to_string(),
+            retried: false,
+        },
+    );
     true
 }
 
 /// Validate that a timestamp string is within an acceptable time range.
 ///
 /// Parses a timestamp (milliseconds since Unix epoch) and requires it to be
-/// within ±60 seconds of the current time. Combined with the nonce store this
-/// bounds the replay window: a signature older than the window is rejected
-/// here, a fresh one can only be used once.
+/// within ±[`get_auth_timestamp_window_seconds`] of the current time. Combined
+/// with the nonce store this bounds the replay window: a signature older than
+/// the window is rejected here, a fresh one can only be used once.
 ///
 /// # Arguments
 /// * `timestamp_str` - The timestamp string to validate (milliseconds since Unix epoch)
@@ -170,13 +472,282 @@ pub fn validate_timestamp(timestamp_str: &str) -> Result<i64, &'static str> {
         .map_err(|_| "Invalid timestamp format")?;
 
     let now = chrono::Utc::now().timestamp_millis();
-    if (now - timestamp).abs() > 60_000 {
+    if (now - timestamp).abs() > get_auth_timestamp_window_seconds() * 1000 {
         return Err("Timestamp outside acceptable window");
     }
 
     Ok(timestamp)
 }
 
+/// Whether a request arrived over TLS.
+///
+/// A `https`/`wss` request scheme counts directly. Behind a reverse proxy the
+/// `X-Forwarded-Proto` value decides, but only when the peer is a trusted
+/// proxy (loopback or listed in `trusted_proxies`); anyone else could simply
+/// forge the header. A loopback peer without the header is a local client,
+/// whose traffic never leaves the machine, and is accepted too.
+pub fn is_secure_transport(
+    peer: std::net::IpAddr,
+    scheme: Option<&str>,
+    forwarded_proto: Option<&str>,
+    trusted_proxies: &[std::net::IpAddr],
+) -> bool {
+    if scheme.is_some_and(|s| s.eq_ignore_ascii_case("https") || s.eq_ignore_ascii_case("wss")) {
+        return true;
+    }
+    let trusted = peer.is_loopback() || trusted_proxies.contains(&peer);
+    match forwarded_proto {
+        // Proxies append to whatever the client sent, so only the last value
+        // is the one our trusted peer set; earlier entries may be forged.
+        Some(proto) if trusted => proto
+            .rsplit(',')
+            .next()
+            .map(str::trim)
+            .is_some_and(|p| p.eq_ignore_ascii_case("https") || p.eq_ignore_ascii_case("wss")),
+        Some(_) => false,
+        None => peer.is_loopback(),
+    }
+}
+
+/// Byte length of an Ed25519 public key.
+const ED25519_KEY_BYTES: usize = 32;
+/// Byte length of an Ed25519 signature.
+const ED25519_SIGNATURE_BYTES: usize = 64;
+
+/// Decode an Ed25519 key or signature of `len` bytes. Standard base64 is the
+/// primary form; a string of exactly `2 * len` hex digits is read as hex for
+/// clients and tools that produce it. Base64 of `len` bytes is never that
+/// long, so the two forms cannot be confused.
+pub fn decode_key_material(value: &str, len: usize) -> Option<Vec<u8>> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    if value.len() == 2 * len && value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return hex::decode(value).ok();
+    }
+    general_purpose::STANDARD.decode(value).ok()
+}
+
+/// The standard base64 form of a public key sent as base64 or hex, which is
+/// how keys are stored and compared. `None` when it is not a 32-byte key.
+pub fn canonical_public_key(public_key: &str) -> Option<String> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    decode_key_material(public_key, ED25519_KEY_BYTES)
+        .filter(|bytes| bytes.len() == ED25519_KEY_BYTES)
+        .map(|bytes| general_purpose::STANDARD.encode(bytes))
+}
+
+/// Verify an Ed25519 `signature` of `message` against a public key, both
+/// base64 or hex. Malformed keys or signatures simply fail verification.
+pub fn verify_ed25519(public_key: &str, signature: &str, message: &[u8]) -> bool {
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    let (Some(pk_bytes), Some(sig_bytes)) = (
+        decode_key_material(public_key, ED25519_KEY_BYTES),
+        decode_key_material(signature, ED25519_SIGNATURE_BYTES),
+    ) else {
+        return false;
+    };
+    let Ok(pk_array) = pk_bytes.as_slice().try_into() else {
+        return false;
+    };
+    let (Ok(key), Ok(signature)) = (
+        VerifyingKey::from_bytes(&pk_array),
+        Signature::from_slice(&sig_bytes),
+    ) else {
+        return false;
+    };
+    key.verify(message, &signature).is_ok()
+}
+
+/// Why [`verify_signed_proof`] rejected a proof of key ownership.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofError {
+    /// The timestamp is malformed or outside the accepted window.
+    InvalidTimestamp,
+    /// The same key already signed this message (replay).
+    Replay,
+    /// The key or signature is neither valid base64 nor hex.
+    InvalidEncoding,
+    /// The decoded public key is not 32 bytes.
+    InvalidKeyLength,
+    /// The bytes do not form an Ed25519 public key.
+    InvalidPublicKey,
+    /// The decoded signature is not 64 bytes.
+    InvalidSignatureFormat,
+    /// The signature does not verify against the key.
+    InvalidSignature,
+}
+
+/// Verify a signed proof of key ownership, as sent with a WebSocket
+/// `presence` frame (where `message` is the timestamp itself) or with an
+/// upload request (where it is [`signed_request_message`]).
+///
+/// The timestamp must pass [`validate_timestamp`], the key may sign each
+/// message only once ([`check_and_store_nonce`], keyed on key and message,
+/// with `origin` the client IP), and `signature` must be a valid Ed25519
+/// signature of `message` by `public_key` (both base64, or hex as accepted
+/// by [`decode_key_material`]).
+///
+/// # Returns
+/// * `Ok(i64)` - The parsed timestamp if the proof is valid
+/// * `Err(ProofError)` - The first check that failed
+pub async fn verify_signed_proof(
+    rate_limiter: &RateLimiter,
+    public_key: &str,
+    signature: &str,
+    timestamp: &str,
+    message: &str,
+    origin: &str,
+) -> Result<i64, ProofError> {
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    let timestamp = match validate_timestamp(timestamp) {
+        Ok(ts) => ts,
+        Err(err) => {
+            error!("Authentication failed - {}: {}", err, timestamp);
+            return Err(ProofError::InvalidTimestamp);
+        }
+    };
+
+    // Key the nonce on the canonical key so the same key cannot replay a
+    // message by switching between base64 and hex.
+    let nonce_key = canonical_public_key(public_key).unwrap_or_else(|| public_key.to_string());
+    let nonce = format!("{}:{}", nonce_key, message);
+    if !check_and_store_nonce(rate_limiter, &nonce, origin).await {
+        return Err(ProofError::Replay);
+    }
+
+    let (Some(pk_bytes), Some(sig_bytes)) = (
+        decode_key_material(public_key, ED25519_KEY_BYTES),
+        decode_key_material(signature, ED25519_SIGNATURE_BYTES),
+    ) else {
+        error!("Authentication failed - invalid key encoding");
+        return Err(ProofError::InvalidEncoding);
+    };
+
+    let Ok(pk_array) = pk_bytes.as_slice().try_into() else {
+        error!(
+            "Authentication failed - public key wrong length: {}",
+            pk_bytes.len()
+        );
+        return Err(ProofError::InvalidKeyLength);
+    };
+
+    let key = match VerifyingKey::from_bytes(&pk_array) {
+        Ok(key) => key,
+        Err(e) => {
+            error!("Authentication failed - invalid public key: {}", e);
+            return Err(ProofError::InvalidPublicKey);
+        }
+    };
+
+    let signature = match Signature::from_slice(&sig_bytes) {
+        Ok(signature) => signature,
+        Err(e) => {
+            error!("Authentication failed - invalid signature format: {}", e);
+            return Err(ProofError::InvalidSignatureFormat);
+        }
+    };
+
+    if key.verify(message.as_bytes(), &signature).is_err() {
+        error!(
+            "Authentication failed - signature verification failed for key: {}",
+            public_key
+        );
+        return Err(ProofError::InvalidSignature);
+    }
+
+    Ok(timestamp)
+}
+
+/// Header naming the user who sends a signed request.
+pub const USER_HEADER: &str = "x-murmer-user";
+/// Header carrying the signed request's timestamp (milliseconds since epoch).
+pub const TIMESTAMP_HEADER: &str = "x-murmer-timestamp";
+/// Header carrying the base64 Ed25519 signature of the request.
+pub const SIGNATURE_HEADER: &str = "x-murmer-signature";
+
+/// The text a client signs to identify itself on an upload request, e.g.
+/// `POST /upload 1700000000000`. For upload deletions the path holds the
+/// decoded key.
+pub fn signed_request_message(method: &str, path: &str, timestamp: &str) -> String {
+    format!("{method} {path} {timestamp}")
+}
+
+/// The verified sender of a signed request.
+pub(crate) struct Signer {
+    pub(crate) user: String,
+    pub(crate) public_key: String,
+}
+
+/// Resolve the user behind a signed request. Returns `Ok(None)` when the
+/// request carries no identity headers and `Err(UNAUTHORIZED)` when they are
+/// incomplete, stale, replayed, or do not verify against the key the name is
+/// bound to.
+pub(crate) async fn request_identity(
+    state: &AppState,
+    headers: &HeaderMap,
+    origin: &str,
+    method: &str,
+    path: &str,
+) -> Result<Option<Signer>, StatusCode> {
+    let get = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let (user, timestamp, signature) = match (
+        get(USER_HEADER),
+        get(TIMESTAMP_HEADER),
+        get(SIGNATURE_HEADER),
+    ) {
+        (None, None, None) => return Ok(None),
+        (Some(user), Some(ts), Some(sig)) => (user, ts, sig),
+        _ => return Err(StatusCode::UNAUTHORIZED),
+    };
+    let public_key = match crate::db::get_user_key(&state.db, user).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            error!("Failed to look up key for {user}: {e}");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let message = signed_request_message(method, path, timestamp);
+    if let Err(err) = verify_signed_proof(
+        &state.rate_limiter,
+        &public_key,
+        signature,
+        timestamp,
+        &message,
+        origin,
+    )
+    .await
+    {
+        warn!("Rejected signed {method} {path} for {user}: {err:?}");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(Some(Signer {
+        user: user.to_string(),
+        public_key,
+    }))
+}
+
+/// Client IP used as the replay-nonce origin. Requests that did not come
+/// through the server's listener (e.g. tests) share one placeholder origin.
+pub(crate) fn request_origin(connect_info: Option<Extension<ConnectInfo<SocketAddr>>>) -> String {
+    connect_info.map_or_else(
+        || "unknown".to_string(),
+        |Extension(ConnectInfo(addr))| addr.ip().to_string(),
+    )
+}
+
+/// Whether `token` matches `ADMIN_TOKEN`. Uses a constant-time comparison to
+/// prevent timing attacks; always false when no admin token is configured.
+pub(crate) fn is_admin_token(state: &AppState, token: &str) -> bool {
+    state
+        .admin_token
+        .as_ref()
+        .is_some_and(|expected| expected.as_bytes().ct_eq(token.as_bytes()).into())
+}
+
 /// Generic name validator for security.
 ///
 /// Validates that a name:
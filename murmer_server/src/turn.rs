@@ -0,0 +1,100 @@
+//! Short-lived TURN credentials for WebRTC voice and screen sharing.
+//!
+//! Peers behind symmetric NATs cannot connect directly and need a relay.
+//! `GET /turn-credentials` hands signed-in users credentials for a coturn
+//! server configured with `use-auth-secret` (the "TURN REST API" scheme):
+//! the username is `<expiry>:<user>`, where `<expiry>` is a Unix timestamp,
+//! and the password is the base64 HMAC-SHA1 of that username keyed with the
+//! shared `TURN_SECRET`. coturn recomputes the HMAC and rejects the pair once
+//! the expiry passes, so nothing needs to be stored here.
+//!
+//! Requests are signed like `/upload` (see [`crate::upload`]); unsigned ones
+//! get `401` so anonymous callers cannot use the relay. Without `TURN_SECRET`
+//! and `TURN_URLS` the endpoint answers `404`. The response has the shape of
+//! an `RTCIceServer` plus the `ttl` in seconds, so clients can pass it to
+//! `RTCPeerConnection` as is.
+
+use axum::{
+    Extension, Json, Router,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use hmac::{Hmac, KeyInit, Mac};
+use serde::Serialize;
+use sha1::Sha1;
+use std::{net::SocketAddr, sync::Arc, time::SystemTime};
+
+use crate::{AppState, config, security};
+
+/// Path of the credential endpoint; also the signed request path.
+pub const TURN_CREDENTIALS_PATH: &str = "/turn-credentials";
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route(TURN_CREDENTIALS_PATH, get(turn_credentials))
+}
+
+/// Credentials for one user, in `RTCIceServer` form.
+#[derive(Debug, Serialize)]
+pub struct TurnCredentials {
+    pub urls: Vec<String>,
+    pub username: String,
+    pub credential: String,
+    /// Seconds until the credentials expire.
+    pub ttl: u64,
+}
+
+/// The coturn REST password for `username`: base64 HMAC-SHA1 keyed with the
+/// shared secret.
+pub fn turn_password(secret: &str, username: &str) -> String {
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(username.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// Credentials for `user` valid for `ttl_secs` from `now` (Unix seconds).
+pub fn issue_credentials(
+    secret: &str,
+    urls: Vec<String>,
+    user: &str,
+    now: u64,
+    ttl_secs: u64,
+) -> TurnCredentials {
+    let username = format!("{}:{user}", now + ttl_secs);
+    TurnCredentials {
+        urls,
+        credential: turn_password(secret, &username),
+        username,
+        ttl: ttl_secs,
+    }
+}
+
+/// Issue TURN credentials to the user who signed the request.
+pub async fn turn_credentials(
+    State(state): State<Arc<AppState>>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
+) -> Response {
+    let urls = config::turn_urls();
+    let Some(secret) = config::turn_secret().filter(|_| !urls.is_empty()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let origin = security::request_origin(connect_info);
+    let user =
+        match security::request_identity(&state, &headers, &origin, "GET", TURN_CREDENTIALS_PATH)
+            .await
+        {
+            Ok(Some(signer)) => signer.user,
+            Ok(None) => return StatusCode::UNAUTHORIZED.into_response(),
+            Err(status) => return status.into_response(),
+        };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let ttl = config::turn_credential_ttl().as_secs();
+    Json(issue_credentials(&secret, urls, &user, now, ttl)).into_response()
+}
@@ -3,25 +3,83 @@
 //! Files are sanitized and saved under the `UPLOAD_DIR` directory. Images are
 //! validated by magic bytes; other attachments are restricted to a safe-list
 //! of extensions so active content (HTML, SVG, scripts) can never be served
-//! back from `/files` and executed in a browser context. The returned JSON
-//! contains a relative URL that clients can combine with the server URL to
-//! fetch the file later.
+//! back from `/files` and executed in a browser context. PDFs and ZIP-based
+//! formats are magic-byte checked as well. Non-image attachments can be turned
+//! off with `ALLOW_FILE_UPLOADS=false` and have their own, larger size limit.
+//! The returned JSON contains a relative URL that clients can combine with the
+//! server URL to fetch the file later, plus the detected `contentType` so the
+//! client can pick an icon instead of an `<img>`.
+//!
+//! Images wider or taller than `MAX_IMAGE_DIMENSION` (8000px by default) are
+//! refused with `413`, judged from the header alone so a small file that
+//! declares a huge canvas never gets decoded.
+//!
+//! Still images additionally get a downscaled WebP thumbnail stored next to
+//! the original as `<key>.thumb.webp` and returned as `thumbUrl`, so chat
+//! previews do not download the full-size file. GIFs keep their animation by
+//! using the original as the thumbnail; images that cannot be decoded are
+//! stored without one.
+//!
+//! Uploads must be signed. Clients identify themselves with three headers:
+//! the user name, a millisecond timestamp and an Ed25519 signature over
+//! `"<METHOD> <path> <timestamp>"` made with the key the name is bound to.
+//! The proof goes through the same [`security::verify_signed_proof`] as the
+//! WebSocket `presence` handshake, so the timestamp window and replay nonces
+//! apply too. Unsigned requests get `401` unless they carry the `ADMIN_TOKEN`
+//! bearer. Every stored file gets a row in the `uploads` table naming its
+//! uploader and the public key that signed the request.
+//! Identical content is stored once. Each file's SHA-256 is recorded, and an
+//! upload whose bytes match a stored file is answered with that file's URL
+//! (under the new upload's name) without writing anything or counting
+//! against the quota. Deleting such a shared file removes it for everyone
+//! who uploaded it.
+//!
+//! With `UPLOAD_QUOTA_BYTES_PER_DAY` set, each public key may store at most
+//! that many bytes per UTC day; an upload over it gets `413` with an
+//! `X-Quota-Exceeded` header. Admin-token uploads are not counted.
+//! `DELETE /upload/:key` removes a file for its uploader, or for anyone
+//! holding the `ADMIN_TOKEN`. With `UPLOAD_RETENTION_DAYS` set, a background
+//! reaper deletes tracked files older than that which nothing references.
 
 use axum::{
-    Json,
-    extract::{Multipart, State},
-    http::StatusCode,
+    Extension, Json,
+    extract::{ConnectInfo, Multipart, Path, State},
+    http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Response},
 };
+use chrono::NaiveDate;
+use image::{ImageReader, Limits, codecs::webp::WebPEncoder, imageops::FilterType};
 use sanitize_filename::sanitize;
-use std::sync::Arc;
-use tracing::{error, warn};
+use sha2::{Digest, Sha256};
+use std::{io::Cursor, net::SocketAddr, path::Path as FsPath, sync::Arc, time::Duration};
+use tracing::{error, info, warn};
 
-use crate::AppState;
+use crate::{AppState, db, security};
 
-/// Maximum file size in bytes (10MB)
+/// Maximum image file size in bytes (10MB)
 pub const MAX_FILE_SIZE: usize = 10 * 1024 * 1024;
 
+/// Maximum size in bytes for non-image attachments (25MB)
+pub const MAX_FILE_SIZE_DOCS: usize = 25 * 1024 * 1024;
+
+/// Longest edge in pixels of generated thumbnails.
+pub const THUMBNAIL_MAX_EDGE: u32 = 320;
+
+/// Largest image dimension the thumbnailer will decode. Bounds the memory a
+/// small but highly compressed upload (a decompression bomb) can claim.
+const THUMBNAIL_MAX_SOURCE_EDGE: u32 = 16_384;
+
+/// Header set on the `413` returned when an upload would take the sender
+/// past `UPLOAD_QUOTA_BYTES_PER_DAY`, telling it apart from an oversized file.
+pub const QUOTA_EXCEEDED_HEADER: &str = "x-quota-exceeded";
+
+/// How often the retention reaper scans for unreferenced uploads.
+const REAPER_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Most uploads the reaper removes per pass, so one pass holds the database
+/// only briefly; a backlog is worked off over the following passes.
+const REAPER_BATCH: usize = 500;
+
 /// Allowed MIME types for image uploads
 static ALLOWED_IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
 
@@ -40,8 +98,15 @@ static ALLOWED_ATTACHMENT_EXTENSIONS: &[&str] = &[
     "mp4", "webm", "mkv", "mov", "avi",
 ];
 
+/// Attachment extensions whose contents must carry the PDF signature.
+static PDF_EXTENSIONS: &[&str] = &["pdf"];
+
+/// Attachment extensions that are ZIP containers (office documents included)
+/// and must carry the ZIP signature.
+static ZIP_EXTENSIONS: &[&str] = &["zip", "docx", "xlsx", "pptx", "odt", "ods", "odp"];
+
 /// Detect file type by magic bytes
-fn detect_file_type(data: &[u8]) -> Option<&'static str> {
+pub fn detect_file_type(data: &[u8]) -> Option<&'static str> {
     if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
         Some("image/jpeg")
     } else if data.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
@@ -50,6 +115,12 @@ fn detect_file_type(data: &[u8]) -> Option<&'static str> {
         Some("image/gif")
     } else if data.len() >= 12 && &data[8..12] == b"WEBP" {
         Some("image/webp")
+    } else if data.starts_with(b"%PDF") {
+        Some("application/pdf")
+    } else if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        // Local file header, or the end-of-central-directory record that
+        // starts an empty archive.
+        Some("application/zip")
     } else {
         None
     }
@@ -70,6 +141,21 @@ enum UploadKind {
     Attachment,
 }
 
+/// Validate an attachment's contents against its extension and return the
+/// content type reported to the client. PDFs and ZIP containers must match
+/// their signature; other safe-listed types cannot be sniffed reliably and are
+/// reported as opaque binary data.
+fn attachment_content_type(ext: &str, data: &[u8]) -> Option<&'static str> {
+    let detected = detect_file_type(data);
+    if PDF_EXTENSIONS.contains(&ext) {
+        return detected.filter(|t| *t == "application/pdf");
+    }
+    if ZIP_EXTENSIONS.contains(&ext) {
+        return detected.filter(|t| *t == "application/zip");
+    }
+    Some("application/octet-stream")
+}
+
 fn classify_extension(filename: &str) -> Option<UploadKind> {
     let ext = file_extension(filename)?;
     if ALLOWED_IMAGE_EXTENSIONS.contains(&ext.as_str()) {
@@ -81,8 +167,298 @@ fn classify_extension(filename: &str) -> Option<UploadKind> {
     }
 }
 
-#[tracing::instrument(skip(state, multipart))]
-pub async fn upload(State(state): State<Arc<AppState>>, mut multipart: Multipart) -> Response {
+/// Width and height declared in an image's header, read without decoding
+/// any pixels.
+pub fn image_dimensions(data: &[u8]) -> image::ImageResult<(u32, u32)> {
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    // Only the header is read here, so the allocation limits that guard a
+    // full decode do not apply; the caller compares the result itself.
+    reader.no_limits();
+    reader.into_dimensions()
+}
+
+/// Render a WebP thumbnail whose longest edge is at most
+/// [`THUMBNAIL_MAX_EDGE`]. Returns `Ok(None)` when the image already fits, in
+/// which case the original serves as its own thumbnail. CPU-bound; run it on a
+/// blocking thread.
+pub fn render_thumbnail(data: &[u8]) -> image::ImageResult<Option<Vec<u8>>> {
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(THUMBNAIL_MAX_SOURCE_EDGE);
+    limits.max_image_height = Some(THUMBNAIL_MAX_SOURCE_EDGE);
+    reader.limits(limits);
+    let img = reader.decode()?;
+    if img.width() <= THUMBNAIL_MAX_EDGE && img.height() <= THUMBNAIL_MAX_EDGE {
+        return Ok(None);
+    }
+    let thumb = img
+        .resize(THUMBNAIL_MAX_EDGE, THUMBNAIL_MAX_EDGE, FilterType::Triangle)
+        .into_rgba8();
+    let mut out = Vec::new();
+    WebPEncoder::new_lossless(&mut out).encode(
+        thumb.as_raw(),
+        thumb.width(),
+        thumb.height(),
+        image::ExtendedColorType::Rgba8,
+    )?;
+    Ok(Some(out))
+}
+
+/// Produce the thumbnail URL for a freshly stored image. Falls back to the
+/// original URL for GIFs and images that already fit, and to `None` when the
+/// image cannot be decoded or the thumbnail cannot be written.
+async fn store_thumbnail(
+    state: &AppState,
+    key: &str,
+    url: &str,
+    data: axum::body::Bytes,
+) -> Option<String> {
+    if detect_file_type(&data) == Some("image/gif") {
+        return Some(url.to_string());
+    }
+    match tokio::task::spawn_blocking(move || render_thumbnail(&data)).await {
+        Ok(Ok(Some(bytes))) => {
+            let thumb_key = format!("{key}.thumb.webp");
+            match tokio::fs::write(state.upload_dir.join(&thumb_key), bytes).await {
+                Ok(()) => Some(format!("/files/{thumb_key}")),
+                Err(e) => {
+                    error!("Failed to write thumbnail for {key}: {e}");
+                    None
+                }
+            }
+        }
+        Ok(Ok(None)) => Some(url.to_string()),
+        Ok(Err(e)) => {
+            warn!("Could not decode {key} for thumbnailing: {e}");
+            None
+        }
+        Err(e) => {
+            error!("Thumbnail task failed for {key}: {e}");
+            None
+        }
+    }
+}
+
+fn is_admin(state: &AppState, headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| security::is_admin_token(state, token))
+}
+
+/// Whether `key` names a single file directly inside the upload directory.
+/// Keys are re-sanitized exactly as at upload time, so anything that would
+/// have been rewritten (separators, `..`, reserved names) is refused.
+fn is_storage_key(key: &str) -> bool {
+    !key.is_empty() && !key.starts_with('.') && sanitize(key) == key
+}
+
+/// Delete a stored file and its thumbnail. Returns whether the file existed.
+async fn remove_stored_files(upload_dir: &FsPath, key: &str) -> std::io::Result<bool> {
+    let existed = match tokio::fs::remove_file(upload_dir.join(key)).await {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => return Err(e),
+    };
+    match tokio::fs::remove_file(upload_dir.join(format!("{key}.thumb.webp"))).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    Ok(existed)
+}
+
+/// `DELETE /upload/:key`: remove a file for its uploader or an admin.
+#[tracing::instrument(skip(state, connect_info, headers))]
+pub async fn delete_upload(
+    State(state): State<Arc<AppState>>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_storage_key(&key) {
+        warn!("Rejected deletion of invalid upload key: {key}");
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let record = match db::get_upload(&state.db, &key).await {
+        Ok(record) => record,
+        Err(e) => {
+            error!("Failed to load upload record for {key}: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if !is_admin(&state, &headers) {
+        let origin = security::request_origin(connect_info);
+        let path = format!("/upload/{key}");
+        let identity =
+            match security::request_identity(&state, &headers, &origin, "DELETE", &path).await {
+                Ok(Some(signer)) => signer.user,
+                Ok(None) => return StatusCode::UNAUTHORIZED.into_response(),
+                Err(status) => return status.into_response(),
+            };
+        let Some(record) = &record else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        if record.uploader.as_deref() != Some(identity.as_str()) {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+    }
+
+    let existed = match remove_stored_files(&state.upload_dir, &key).await {
+        Ok(existed) => existed,
+        Err(e) => {
+            error!("Failed to delete upload {key}: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if let Err(e) = db::remove_upload(&state.db, &key).await {
+        error!("Failed to remove upload record for {key}: {e}");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    if existed || record.is_some() {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Delete tracked uploads older than `retention_days` that nothing
+/// references. Returns the number of files removed.
+pub async fn reap_unreferenced_uploads(state: &AppState, retention_days: u32) -> usize {
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(i64::from(retention_days));
+    let keys = match db::unreferenced_uploads_before(&state.db, cutoff, REAPER_BATCH).await {
+        Ok(keys) => keys,
+        Err(e) => {
+            error!("Failed to list unreferenced uploads: {e}");
+            return 0;
+        }
+    };
+    let mut removed = 0;
+    for key in keys {
+        // Rows are only ever written with sanitized keys; re-check anyway so
+        // a tampered database cannot point the reaper outside UPLOAD_DIR.
+        if !is_storage_key(&key) {
+            warn!("Skipping invalid upload key in database: {key}");
+            continue;
+        }
+        if let Err(e) = remove_stored_files(&state.upload_dir, &key).await {
+            error!("Failed to reap upload {key}: {e}");
+            continue;
+        }
+        match db::remove_upload(&state.db, &key).await {
+            Ok(_) => removed += 1,
+            Err(e) => error!("Failed to remove upload record for {key}: {e}"),
+        }
+    }
+    removed
+}
+
+/// Spawn the retention reaper when `UPLOAD_RETENTION_DAYS` is set.
+pub fn spawn_upload_reaper(state: Arc<AppState>) {
+    let Some(days) = crate::config::upload_retention_days() else {
+        return;
+    };
+    info!("Removing unreferenced uploads older than {days} days");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REAPER_INTERVAL);
+        loop {
+            interval.tick().await;
+            let removed = reap_unreferenced_uploads(&state, days).await;
+            if removed > 0 {
+                info!("Reaped {removed} unreferenced uploads");
+            }
+        }
+    });
+}
+
+/// Hex SHA-256 of an upload's bytes, computed on a blocking thread since
+/// attachments can be tens of megabytes.
+async fn content_hash(data: axum::body::Bytes) -> Result<String, tokio::task::JoinError> {
+    tokio::task::spawn_blocking(move || {
+        Sha256::digest(&data)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    })
+    .await
+}
+
+/// URL and thumbnail URL of an already stored file, for an upload answered
+/// with it: its generated thumbnail if there is one, else the file itself.
+async fn existing_upload(
+    state: &AppState,
+    key: &str,
+    kind: &UploadKind,
+) -> (String, Option<String>) {
+    let url = format!("/files/{key}");
+    let thumb_url = match kind {
+        UploadKind::Image => {
+            let thumb_key = format!("{key}.thumb.webp");
+            let has_thumb = tokio::fs::try_exists(state.upload_dir.join(&thumb_key))
+                .await
+                .unwrap_or(false);
+            Some(if has_thumb {
+                format!("/files/{thumb_key}")
+            } else {
+                url.clone()
+            })
+        }
+        UploadKind::Attachment => None,
+    };
+    (url, thumb_url)
+}
+
+/// The JSON reply describing a stored upload.
+fn upload_response(
+    (url, thumb_url): (String, Option<String>),
+    name: &str,
+    size: usize,
+    content_type: &str,
+    kind: &UploadKind,
+) -> Response {
+    Json(serde_json::json!({
+        "url": url,
+        "thumbUrl": thumb_url,
+        "name": name,
+        "size": size,
+        "contentType": content_type,
+        "kind": match kind {
+            UploadKind::Image => "image",
+            UploadKind::Attachment => "file",
+        },
+    }))
+    .into_response()
+}
+
+/// Hand back quota counted for an upload that could not be stored.
+async fn release_quota(state: &AppState, quota: Option<(&str, NaiveDate)>, bytes: usize) {
+    if let Some((public_key, day)) = quota
+        && let Err(e) = db::release_upload_bytes(&state.db, public_key, day, bytes as u64).await
+    {
+        error!("Failed to release upload quota for {public_key}: {e}");
+    }
+}
+
+#[tracing::instrument(skip(state, connect_info, headers, multipart))]
+pub async fn upload(
+    State(state): State<Arc<AppState>>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Response {
+    // Checked before any bytes are read: anonymous uploads are refused, and
+    // only the admin token may upload without a signature.
+    let origin = security::request_origin(connect_info);
+    let uploader =
+        match security::request_identity(&state, &headers, &origin, "POST", "/upload").await {
+            Ok(Some(signer)) => Some(signer),
+            Ok(None) if is_admin(&state, &headers) => None,
+            Ok(None) => return StatusCode::UNAUTHORIZED.into_response(),
+            Err(status) => return status.into_response(),
+        };
+
     let field = match multipart.next_field().await {
         Ok(Some(field)) => field,
         Ok(None) => return StatusCode::BAD_REQUEST.into_response(),
@@ -106,6 +482,11 @@ pub async fn upload(State(state): State<Arc<AppState>>, mut multipart: Multipart
         return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
     };
 
+    if matches!(kind, UploadKind::Attachment) && !crate::config::allow_file_uploads() {
+        warn!("Rejected attachment upload (disabled): {}", filename);
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    }
+
     let data = match field.bytes().await {
         Ok(bytes) => bytes,
         Err(err) => {
@@ -114,7 +495,11 @@ pub async fn upload(State(state): State<Arc<AppState>>, mut multipart: Multipart
         }
     };
 
-    if data.len() > MAX_FILE_SIZE {
+    let size_limit = match kind {
+        UploadKind::Image => MAX_FILE_SIZE,
+        UploadKind::Attachment => MAX_FILE_SIZE_DOCS,
+    };
+    if data.len() > size_limit {
         warn!("Rejected upload exceeding size limit: {} bytes", data.len());
         return StatusCode::PAYLOAD_TOO_LARGE.into_response();
     }
@@ -126,44 +511,156 @@ pub async fn upload(State(state): State<Arc<AppState>>, mut multipart: Multipart
 
     // Image extensions must also pass magic-byte validation so a mislabelled
     // file cannot masquerade as an image.
-    if matches!(kind, UploadKind::Image)
-        && !detect_file_type(&data).is_some_and(|t| ALLOWED_IMAGE_TYPES.contains(&t))
-    {
+    let content_type = match kind {
+        UploadKind::Image => detect_file_type(&data).filter(|t| ALLOWED_IMAGE_TYPES.contains(t)),
+        UploadKind::Attachment => {
+            let ext = file_extension(&filename).unwrap_or_default();
+            attachment_content_type(&ext, &data)
+        }
+    };
+    let Some(content_type) = content_type else {
         warn!("Rejected upload with invalid file type for: {}", filename);
         return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    };
+
+    // A few kilobytes can declare an image of billions of pixels; refuse
+    // those before the thumbnailer (or any client) tries to decode them.
+    // Images whose header cannot be read are left to the thumbnailer, which
+    // stores them without a preview.
+    if matches!(kind, UploadKind::Image) {
+        let max = crate::config::max_image_dimension();
+        match image_dimensions(&data) {
+            Ok((width, height)) if width > max || height > max => {
+                warn!("Rejected {width}x{height} image over {max}px: {filename}");
+                return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Could not read dimensions of {filename}: {e}"),
+        }
+    }
+
+    // Identical bytes from the same uploader are stored once: answer with
+    // their file already holding them. Other uploaders get their own copy, so
+    // nobody can delete a file someone else's message points at. A record
+    // whose file has gone missing is dropped so it can be replaced.
+    let uploader_key = uploader.as_ref().map(|s| s.public_key.as_str());
+    let hash = match content_hash(data.clone()).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            error!("Failed to hash upload {filename}: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    match db::find_upload_by_hash(&state.db, uploader_key, &hash).await {
+        Ok(Some(existing)) => {
+            if tokio::fs::try_exists(state.upload_dir.join(&existing.key))
+                .await
+                .unwrap_or(false)
+            {
+                info!("Serving duplicate upload {filename} from {}", existing.key);
+                let stored = existing_upload(&state, &existing.key, &kind).await;
+                return upload_response(stored, &filename, data.len(), content_type, &kind);
+            }
+            if let Err(e) = db::remove_upload(&state.db, &existing.key).await {
+                error!("Failed to drop stale upload record {}: {e}", existing.key);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => error!("Failed to look up upload hash: {e}"),
     }
 
-    let key = format!("{}-{}", chrono::Utc::now().timestamp_millis(), filename);
+    // Counted before anything is written, and handed back below if the file
+    // cannot be stored, so the quota tracks the bytes actually on disk.
+    let quota = match (&uploader, crate::config::upload_quota_bytes_per_day()) {
+        (Some(signer), Some(limit)) => {
+            let day = chrono::Utc::now().date_naive();
+            let bytes = data.len() as u64;
+            match db::reserve_upload_bytes(&state.db, &signer.public_key, day, bytes, limit).await {
+                Ok(true) => Some((signer.public_key.as_str(), day)),
+                Ok(false) => {
+                    warn!("Rejected upload from {} over the daily quota", signer.user);
+                    return (
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        [(QUOTA_EXCEEDED_HEADER, "daily")],
+                    )
+                        .into_response();
+                }
+                Err(e) => {
+                    error!("Failed to check upload quota for {}: {e}", signer.user);
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            }
+        }
+        _ => None,
+    };
+    // The random part keeps same-millisecond uploads of the same name apart.
+    let nonce = hex::encode(rand::random::<[u8; 8]>());
+    let key = format!(
+        "{}-{nonce}-{filename}",
+        chrono::Utc::now().timestamp_millis()
+    );
     let path = state.upload_dir.join(&key);
-    // Append ".tmp" rather than replacing the extension: with_extension()
-    // would map same-millisecond uploads of "a.pdf" and "a.zip" onto the same
-    // temp file and let the concurrent writes corrupt each other.
     let temp_path = state.upload_dir.join(format!("{key}.tmp"));
 
-    match tokio::fs::write(temp_path.as_path(), &data).await {
-        Ok(_) => match tokio::fs::rename(temp_path.as_path(), &path).await {
-            Ok(_) => {
-                let url = format!("/files/{}", key);
-                Json(serde_json::json!({
-                    "url": url,
-                    "name": filename,
-                    "size": data.len(),
-                    "kind": match kind {
-                        UploadKind::Image => "image",
-                        UploadKind::Attachment => "file",
-                    },
-                }))
-                .into_response()
-            }
-            Err(e) => {
-                error!("Failed to move uploaded file: {}", e);
-                let _ = tokio::fs::remove_file(temp_path).await;
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    // Linking instead of renaming fails if the key is somehow taken, so an
+    // existing file is never overwritten.
+    let stored = match tokio::fs::write(temp_path.as_path(), &data).await {
+        Ok(_) => tokio::fs::hard_link(temp_path.as_path(), &path).await,
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(temp_path.as_path()).await;
+    if let Err(e) = stored {
+        error!("Failed to store uploaded file: {}", e);
+        release_quota(&state, quota, data.len()).await;
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    let recorded = db::record_upload(
+        &state.db,
+        &key,
+        uploader.as_ref().map(|s| s.user.as_str()),
+        uploader_key,
+        Some(&hash),
+    )
+    .await;
+    match recorded {
+        Ok(true) => {}
+        Ok(false) => {
+            // A concurrent upload of the same bytes by the same uploader was
+            // recorded first; keep its file and discard this copy.
+            if let Err(e) = remove_stored_files(&state.upload_dir, &key).await {
+                error!("Failed to discard duplicate upload {key}: {e}");
             }
-        },
+            release_quota(&state, quota, data.len()).await;
+            return match db::find_upload_by_hash(&state.db, uploader_key, &hash).await {
+                Ok(Some(existing)) => {
+                    let stored = existing_upload(&state, &existing.key, &kind).await;
+                    upload_response(stored, &filename, data.len(), content_type, &kind)
+                }
+                Ok(None) => StatusCode::CONFLICT.into_response(),
+                Err(e) => {
+                    error!("Failed to look up upload hash: {e}");
+                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                }
+            };
+        }
         Err(e) => {
-            error!("Failed to write uploaded file: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            // Without a record nobody could delete the file and the reaper
+            // would never see it, so it is not kept.
+            error!("Failed to record upload {key}: {e}");
+            if let Err(e) = remove_stored_files(&state.upload_dir, &key).await {
+                error!("Failed to discard unrecorded upload {key}: {e}");
+            }
+            release_quota(&state, quota, data.len()).await;
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     }
+
+    let url = format!("/files/{}", key);
+    let size = data.len();
+    let thumb_url = match kind {
+        UploadKind::Image => store_thumbnail(&state, &key, &url, data).await,
+        UploadKind::Attachment => None,
+    };
+    upload_response((url, thumb_url), &filename, size, content_type, &kind)
 }
@@ -0,0 +1,60 @@
+//! Build and version information.
+//!
+//! `GET /version` answers `{name, version, git_sha, build_time}` so operators
+//! and clients can tell which build they are talking to. The git SHA and build
+//! time are captured by `build.rs`. The route is unauthenticated since none of
+//! this is sensitive, but it answers `404` when `HIDE_SERVER_VERSION` is set,
+//! like the other places the version is advertised.
+
+use axum::{
+    Json, Router,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{DateTime, SecondsFormat};
+use serde::Serialize;
+
+use crate::config;
+
+/// Commit the server was built from, or `unknown` outside a git checkout.
+pub const GIT_SHA: &str = env!("MURMER_GIT_SHA");
+
+/// Build metadata returned by `GET /version`.
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    /// RFC 3339 UTC time of the build.
+    pub build_time: String,
+}
+
+/// Metadata of the running build.
+pub fn version_info() -> VersionInfo {
+    let epoch = env!("MURMER_BUILD_EPOCH").parse().unwrap_or_default();
+    VersionInfo {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: GIT_SHA,
+        build_time: DateTime::from_timestamp(epoch, 0)
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .unwrap_or_default(),
+    }
+}
+
+async fn version() -> Response {
+    if config::server_version().is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Json(version_info()).into_response()
+}
+
+/// The `/version` route, generic over the router state so it can be merged
+/// into the main application router.
+pub fn router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new().route("/version", get(version))
+}
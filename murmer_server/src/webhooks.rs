@@ -0,0 +1,266 @@
+//! Outgoing webhooks: forward new channel messages to external URLs.
+//!
+//! Operators register a URL per text channel (admin endpoints below, guarded
+//! by `ADMIN_TOKEN`). Every message stored in that channel is POSTed to the URL
+//! as JSON with an `X-Murmer-Signature: sha256=<hex>` header, the HMAC-SHA256
+//! of the raw body keyed with the webhook's secret, so receivers can verify the
+//! request came from this server.
+//!
+//! Delivery runs on background tasks with a bounded retry, so a slow or dead
+//! endpoint never blocks the chat loop. Only operators can register URLs, so
+//! unlike `/link-preview` the targets are not restricted to public addresses.
+//!
+//! Admin endpoints:
+//!   - `POST   /api/v1/webhooks`       – register a webhook (`channel_id`, `url`, optional `secret`)
+//!   - `GET    /api/v1/webhooks`       – list webhooks (secrets are never returned)
+//!   - `DELETE /api/v1/webhooks/:id`   – remove a webhook
+
+use axum::{
+    Router,
+    extract::{DefaultBodyLimit, Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, post},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use base64::Engine as _;
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tracing::{error, warn};
+
+use crate::{AppState, db, security};
+
+/// Header carrying the hex HMAC-SHA256 of the request body.
+pub const SIGNATURE_HEADER: &str = "x-murmer-signature";
+/// Total delivery attempts per message before giving up.
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubled after every failed attempt.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Timeout for a single delivery request.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum length of a registered URL.
+const MAX_WEBHOOK_URL_LENGTH: usize = 2048;
+/// Maximum length of a caller-supplied secret.
+const MAX_WEBHOOK_SECRET_LENGTH: usize = 256;
+/// Largest JSON body accepted when registering a webhook: room for the URL and
+/// secret limits above plus JSON overhead. Bigger bodies are rejected with 413.
+pub const WEBHOOK_BODY_LIMIT: usize = 8 * 1024;
+
+/// Compute the signature header value (`sha256=<hex>`) for a request body.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body.as_bytes());
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("sha256={digest}")
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// Forward a stored message to every webhook registered for its channel.
+/// Returns immediately; the lookup and all deliveries run in the background.
+pub fn dispatch(state: &Arc<AppState>, channel_id: i32, payload: String) {
+    let db = state.db.clone();
+    tokio::spawn(async move {
+        let hooks = match db::get_channel_webhooks(&db, channel_id).await {
+            Ok(hooks) => hooks,
+            Err(e) => {
+                error!("failed to load webhooks for channel {channel_id}: {e}");
+                return;
+            }
+        };
+        let payload = Arc::new(payload);
+        for hook in hooks {
+            let payload = Arc::clone(&payload);
+            tokio::spawn(async move { deliver(&hook, &payload).await });
+        }
+    });
+}
+
+/// POST the payload to one webhook, retrying with exponential backoff.
+async fn deliver(hook: &db::OutgoingWebhook, payload: &str) {
+    let signature = sign(&hook.secret, payload);
+    let mut delay = INITIAL_RETRY_DELAY;
+    for attempt in 1..=MAX_DELIVERY_ATTEMPTS {
+        let result = client()
+            .post(&hook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .body(payload.to_owned())
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => warn!(
+                webhook = hook.id,
+                attempt,
+                status = %response.status(),
+                "webhook delivery rejected"
+            ),
+            Err(e) => warn!(webhook = hook.id, attempt, "webhook delivery failed: {e}"),
+        }
+        if attempt < MAX_DELIVERY_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    error!(
+        webhook = hook.id,
+        "giving up on webhook delivery after {MAX_DELIVERY_ATTEMPTS} attempts"
+    );
+}
+
+/// Accept only absolute http(s) URLs without embedded credentials.
+fn validate_webhook_url(raw: &str) -> bool {
+    if raw.len() > MAX_WEBHOOK_URL_LENGTH {
+        return false;
+    }
+    let Ok(url) = Url::parse(raw) else {
+        return false;
+    };
+    (url.scheme() == "http" || url.scheme() == "https")
+        && url.host_str().is_some()
+        && url.username().is_empty()
+        && url.password().is_none()
+}
+
+fn generate_secret() -> String {
+    let bytes: [u8; 32] = rand::random();
+    let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+    format!("whsec_{encoded}")
+}
+
+fn json_error(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({"error": message}))).into_response()
+}
+
+fn webhook_info(hook: &db::OutgoingWebhook) -> serde_json::Value {
+    serde_json::json!({
+        "id": hook.id,
+        "channel_id": hook.channel_id,
+        "url": hook.url,
+        "created_at": hook.created_at,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub channel_id: i32,
+    pub url: String,
+    /// Shared signing secret; generated when omitted.
+    pub secret: Option<String>,
+}
+
+async fn create_webhook(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Json(body): Json<CreateWebhookRequest>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
+    }
+
+    let url = body.url.trim();
+    if !validate_webhook_url(url) {
+        return json_error(StatusCode::BAD_REQUEST, "invalid-webhook-url");
+    }
+    let secret = match body.secret {
+        Some(s) if s.is_empty() || s.len() > MAX_WEBHOOK_SECRET_LENGTH => {
+            return json_error(StatusCode::BAD_REQUEST, "invalid-webhook-secret");
+        }
+        Some(s) => s,
+        None => generate_secret(),
+    };
+    if db::get_channel_by_id(&state.db, body.channel_id)
+        .await
+        .is_none()
+    {
+        return json_error(StatusCode::NOT_FOUND, "channel-not-found");
+    }
+
+    match db::add_outgoing_webhook(&state.db, body.channel_id, url, &secret).await {
+        Ok(hook) => {
+            // The secret is shown once, at creation, like bot tokens.
+            let mut info = webhook_info(&hook);
+            info["secret"] = serde_json::Value::String(secret);
+            (StatusCode::CREATED, Json(serde_json::json!({"data": info}))).into_response()
+        }
+        Err(e) => {
+            error!("Failed to create webhook: {e}");
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "webhook-creation-failed")
+        }
+    }
+}
+
+async fn list_webhooks(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
+    }
+
+    match db::list_outgoing_webhooks(&state.db).await {
+        Ok(hooks) => {
+            let data: Vec<_> = hooks.iter().map(webhook_info).collect();
+            Json(serde_json::json!({"data": data})).into_response()
+        }
+        Err(e) => {
+            error!("Failed to list webhooks: {e}");
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "list-failed")
+        }
+    }
+}
+
+async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i64>,
+) -> Response {
+    if !security::is_admin_token(&state, bearer.token()) {
+        return json_error(StatusCode::UNAUTHORIZED, "invalid-admin-token");
+    }
+
+    match db::remove_outgoing_webhook(&state.db, id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => json_error(StatusCode::NOT_FOUND, "webhook-not-found"),
+        Err(e) => {
+            error!("Failed to delete webhook {id}: {e}");
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "webhook-deletion-failed")
+        }
+    }
+}
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/v1/webhooks",
+            post(create_webhook)
+                .get(list_webhooks)
+                .layer(DefaultBodyLimit::max(WEBHOOK_BODY_LIMIT)),
+        )
+        .route("/api/v1/webhooks/{id}", delete(delete_webhook))
+}
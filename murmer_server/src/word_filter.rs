@@ -0,0 +1,179 @@
+//! Configurable word filter for chat messages.
+//!
+//! `WORDLIST_PATH` names a text file with one banned word or phrase per line;
+//! blank lines and lines starting with `#` are ignored. The list is compiled
+//! once into an Aho-Corasick automaton, loaded at startup into
+//! [`crate::AppState::word_filter`] and rebuilt by `POST /admin/reload-wordlist`.
+//!
+//! Matching is case-insensitive and whole-word: a match only counts when the
+//! characters on either side of it are not letters, digits or `_`, so `ass`
+//! blocks "ass" and "ASS!" but not "class". `FILTER_MODE` decides what happens
+//! to a message that matches: `block` rejects it with `content-blocked`, `mask`
+//! replaces every character of each match with `*` before the message is
+//! stored and broadcast.
+
+use aho_corasick::AhoCorasick;
+use anyhow::{Context, Result};
+use std::{ops::Range, path::Path};
+
+use crate::config;
+
+/// What to do with a message that contains a banned word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Reject the message.
+    Block,
+    /// Replace the matches with asterisks.
+    Mask,
+}
+
+/// Result of running a message through the filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filtered {
+    /// No banned words.
+    Clean,
+    /// The text with every match masked.
+    Masked(String),
+    /// The message must be rejected.
+    Blocked,
+}
+
+/// A compiled word list.
+pub struct WordFilter {
+    automaton: AhoCorasick,
+    words: usize,
+}
+
+impl WordFilter {
+    /// Compile a filter for `words`. Empty entries are skipped.
+    pub fn new<I, S>(words: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns: Vec<String> = words
+            .into_iter()
+            .map(|word| fold_case(word.as_ref().trim()))
+            .filter(|word| !word.is_empty())
+            .collect();
+        let automaton = AhoCorasick::new(&patterns).context("failed to build word filter")?;
+        Ok(Self {
+            automaton,
+            words: patterns.len(),
+        })
+    }
+
+    /// Compile a filter from the contents of a word list file.
+    pub fn from_wordlist(contents: &str) -> Result<Self> {
+        Self::new(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.starts_with('#')),
+        )
+    }
+
+    /// Read and compile the word list at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read word list '{}'", path.display()))?;
+        Self::from_wordlist(&contents)
+    }
+
+    /// Number of banned words and phrases in the list.
+    pub fn len(&self) -> usize {
+        self.words
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words == 0
+    }
+
+    /// Byte ranges of the whole-word matches in `text`, sorted and merged.
+    fn matches(&self, text: &str) -> Vec<Range<usize>> {
+        // Overlapping search, so a longer entry that fails the word-boundary
+        // check cannot hide a shorter one that passes.
+        let folded = fold_case(text);
+        let mut ranges: Vec<Range<usize>> = self
+            .automaton
+            .find_overlapping_iter(&folded)
+            .map(|m| m.range())
+            .filter(|range| is_whole_word(text, range))
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
+    /// Whether `text` contains a banned word.
+    pub fn is_match(&self, text: &str) -> bool {
+        !self.matches(text).is_empty()
+    }
+
+    /// `text` with every character of each match replaced by `*`.
+    pub fn mask(&self, text: &str) -> String {
+        let mut masked = String::with_capacity(text.len());
+        let mut last = 0;
+        for range in self.matches(text) {
+            masked.push_str(&text[last..range.start]);
+            masked.extend(text[range.clone()].chars().map(|_| '*'));
+            last = range.end;
+        }
+        masked.push_str(&text[last..]);
+        masked
+    }
+
+    /// Apply `mode` to `text`.
+    pub fn apply(&self, mode: FilterMode, text: &str) -> Filtered {
+        match mode {
+            FilterMode::Block if self.is_match(text) => Filtered::Blocked,
+            FilterMode::Mask => {
+                let masked = self.mask(text);
+                if masked == text {
+                    Filtered::Clean
+                } else {
+                    Filtered::Masked(masked)
+                }
+            }
+            FilterMode::Block => Filtered::Clean,
+        }
+    }
+}
+
+/// Load the word list named by `WORDLIST_PATH`, or `None` when it is unset.
+pub fn load_configured() -> Result<Option<WordFilter>> {
+    config::wordlist_path()
+        .map(|path| WordFilter::load(&path))
+        .transpose()
+}
+
+/// Lowercase `text` without changing any byte offsets: characters whose
+/// lowercase form has a different UTF-8 length are left as they are, so match
+/// ranges found in the folded text apply to the original.
+fn fold_case(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            let mut lower = c.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(l), None) if l.len_utf8() == c.len_utf8() => l,
+                _ => c,
+            }
+        })
+        .collect()
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_whole_word(text: &str, range: &Range<usize>) -> bool {
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+}
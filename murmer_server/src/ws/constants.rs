@@ -21,6 +21,11 @@ pub const MIN_EMOJI_NAME_LEN: usize = 2;
 /// Maximum length of a custom emoji name.
 pub const MAX_EMOJI_NAME_LEN: usize = 32;
 
+/// Maximum byte length of a Unicode reaction. The longest standard emoji
+/// (ZWJ sequences with skin tones) is well under this; the cap only bounds
+/// a single grapheme padded with combining marks.
+pub const MAX_REACTION_EMOJI_BYTES: usize = 64;
+
 /// Maximum length in bytes for the server display name.
 pub const MAX_SERVER_NAME_LENGTH: usize = 64;
 
@@ -36,6 +41,12 @@ pub const MAX_SERVER_ICON_BYTES: u64 = 1024 * 1024;
 /// Maximum file size in bytes for a user avatar image.
 pub const MAX_AVATAR_BYTES: u64 = 1024 * 1024;
 
+/// Maximum length in characters for a profile display name.
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 32;
+
+/// Maximum length in characters for a profile bio.
+pub const MAX_BIO_LENGTH: usize = 300;
+
 /// File extensions accepted for image uploads referenced over the WebSocket
 /// (custom emojis, server icon). Subset of the upload endpoint's safe-list.
 pub const UPLOAD_IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
@@ -78,6 +89,17 @@ pub const MIN_EPHEMERAL_SECONDS: i64 = 5;
 /// Maximum duration in seconds for ephemeral messages.
 pub const MAX_EPHEMERAL_SECONDS: i64 = 86_400;
 
+/// How often the sweeper posts due scheduled messages and deletes expired
+/// ephemeral messages whose timer was lost. Scheduled messages have no timer
+/// of their own, so this is also how late one may be posted.
+pub const MESSAGE_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Furthest into the future a message may be scheduled (30 days).
+pub const MAX_SCHEDULE_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// Most scheduled messages one user may have waiting at a time.
+pub const MAX_PENDING_SCHEDULED_PER_USER: i64 = 25;
+
 /// Maximum length in bytes for a chat message's text content.
 pub const MAX_MESSAGE_LENGTH: usize = 4000;
 
@@ -91,6 +113,19 @@ pub const DM_CIPHERTEXT_OVERHEAD_BYTES: usize = 16;
 /// Maximum length in bytes for a channel topic/description.
 pub const MAX_TOPIC_LENGTH: usize = 256;
 
+/// Bounds accepted for a channel's message retention in days.
+pub const MIN_RETENTION_DAYS: i64 = 1;
+pub const MAX_RETENTION_DAYS: i64 = 3650;
+
+/// Longest slow mode interval a channel may use, in seconds (six hours).
+pub const MAX_SLOW_MODE_SECONDS: i64 = 21_600;
+
+/// Largest per-channel subscriber limit that may be configured.
+pub const MAX_CHANNEL_SUBSCRIBERS: i64 = 10_000;
+
+/// How often channels with a retention policy are purged of old messages.
+pub const RETENTION_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
 /// Maximum number of search results to return.
 pub const MAX_SEARCH_RESULTS: i64 = 200;
 
@@ -100,15 +135,47 @@ pub const MAX_HISTORY_LIMIT: i64 = 200;
 /// Maximum number of characters preserved in a reply's quoted snippet.
 pub const MAX_REPLY_PREVIEW_CHARS: usize = 200;
 
+/// Maximum number of ids accepted by a single `get-messages` request.
+pub const MAX_BULK_FETCH_IDS: usize = 100;
+
+/// Most messages removed by one `purge-user-messages` request (newest first).
+pub const MAX_PURGE_MESSAGES: i64 = 1000;
+
+/// Messages sent on each side of the target when resolving a permalink.
+pub const PERMALINK_CONTEXT_RADIUS: i64 = 10;
+
+/// Users returned by `list-users` when no limit is given.
+pub const DEFAULT_USER_DIRECTORY_LIMIT: usize = 50;
+
+/// Maximum number of users returned by a single `list-users` request.
+pub const MAX_USER_DIRECTORY_LIMIT: usize = 100;
+
 /// Maximum number of messages returned for a single thread.
 pub const MAX_THREAD_MESSAGES: i64 = 200;
 
+/// Minimum interval between in-memory last-seen refreshes from a single
+/// connection; disconnecting records the exact time regardless.
+pub const LAST_SEEN_TOUCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// Minimum interval between typing broadcasts from a single connection.
 pub const TYPING_BROADCAST_INTERVAL_MS: u64 = 1_000;
 
 /// Maximum number of pinned messages per channel.
 pub const MAX_PINS_PER_CHANNEL: i64 = 25;
 
+/// Maximum length in characters for the reason given when flagging a message.
+pub const MAX_FLAG_REASON_LENGTH: usize = 500;
+
+/// Most open flags returned by one `list-flags` request.
+pub const MAX_FLAG_LIST: i64 = 200;
+
+/// How long read-marker changes in a receipt-enabled channel are collected
+/// before the resulting `read-receipt-update` frames are sent.
+pub const READ_RECEIPT_BATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Number of a channel's newest messages whose authors receive receipts.
+pub const READ_RECEIPT_WINDOW: i64 = 50;
+
 /// Default number of messages to load when no limit is specified.
 pub const DEFAULT_HISTORY_LIMIT: i64 = 50;
 
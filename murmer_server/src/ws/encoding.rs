@@ -0,0 +1,88 @@
+//! Per-connection frame encoding.
+//!
+//! Text frames are always JSON. A client may also send MessagePack in binary
+//! frames, and can ask for broadcast events to be delivered the same way by
+//! adding `"encoding": "msgpack"` to its `presence` message. Both formats
+//! decode into the same [`Value`], so handlers never see the difference.
+//! Direct replies to a request are still sent as JSON text, so a MessagePack
+//! client must accept both frame kinds.
+//!
+//! WebSocket frames are not compressed by the HTTP `CompressionLayer`, and
+//! the WebSocket layer does not negotiate `permessage-deflate`. A `presence`
+//! carrying `"compression": "gzip"` instead opts in to gzipped `history`
+//! payloads: the JSON frame is gzipped and sent as a binary message, which a
+//! client tells apart from MessagePack by the gzip magic bytes `1f 8b` (a
+//! MessagePack frame is always a map and never starts with `0x1f`).
+
+use std::io::Write;
+
+use axum::extract::ws::Message;
+use flate2::{Compression, write::GzEncoder};
+use serde_json::Value;
+
+use super::inbound::HandshakeOptions;
+
+/// History payloads shorter than this are sent as plain text even when the
+/// client asked for compression; gzip overhead outweighs the gain.
+pub const MIN_COMPRESSED_HISTORY_BYTES: usize = 1024;
+
+/// Encoding used for events forwarded to a connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEncoding {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+impl FrameEncoding {
+    /// The encoding requested by a `presence` message; JSON unless it
+    /// carries `"encoding": "msgpack"`.
+    pub fn requested(options: &HandshakeOptions) -> Self {
+        match options.encoding.as_deref() {
+            Some("msgpack") => FrameEncoding::MsgPack,
+            _ => FrameEncoding::Json,
+        }
+    }
+
+    /// Wrap a serialized JSON frame for sending. With MessagePack the frame
+    /// is re-encoded into a binary message; anything that fails to convert
+    /// is sent as JSON text rather than dropped.
+    pub fn frame(self, json: String) -> Message {
+        if self == FrameEncoding::MsgPack
+            && let Ok(value) = serde_json::from_str::<Value>(&json)
+            && let Ok(bytes) = rmp_serde::to_vec_named(&value)
+        {
+            return Message::Binary(bytes.into());
+        }
+        Message::Text(json.into())
+    }
+}
+
+/// Decode a MessagePack binary frame into a JSON value.
+pub fn decode_msgpack(bytes: &[u8]) -> Option<Value> {
+    rmp_serde::from_slice(bytes).ok()
+}
+
+/// Whether a `presence` message opts in to gzipped `history` payloads.
+pub fn history_compression_requested(options: &HandshakeOptions) -> bool {
+    options.compression.as_deref() == Some("gzip")
+}
+
+/// Wrap a serialized `history` frame for sending. With `gzip` set and a
+/// payload of at least [`MIN_COMPRESSED_HISTORY_BYTES`] it is sent as a
+/// gzipped binary message; otherwise, or if compression fails, as JSON text.
+pub fn history_frame(json: String, gzip: bool) -> Message {
+    if gzip
+        && json.len() >= MIN_COMPRESSED_HISTORY_BYTES
+        && let Ok(bytes) = gzip_bytes(json.as_bytes())
+    {
+        return Message::Binary(bytes.into());
+    }
+    Message::Text(json.into())
+}
+
+fn gzip_bytes(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
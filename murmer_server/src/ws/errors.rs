@@ -1,346 +1,601 @@
-//! WebSocket error message constants.
+//! WebSocket error codes and frames.
 //!
-//! Pre-serialized `{"type":"error","message":"<code>"}` frames, sent to a
-//! single client via [`crate::ws::helpers::send_error`]. The client maps each
-//! code to user-facing text in `murmer_client/src/lib/errors.ts` — keep the
-//! two files in sync when adding codes.
+//! Every error the server sends is registered once in the table below with a
+//! stable numeric code and its kebab-case message, and goes to a single
+//! client via [`crate::ws::helpers::send_error`] as
+//! `{"type":"error","code":<n>,"message":"<message>"}`. Codes are never
+//! renumbered or reused, so clients can branch on `code`; `message` is kept
+//! for older clients. The client maps each message to user-facing text in
+//! `murmer_client/src/lib/errors.ts` — keep the two files in sync when adding
+//! codes, and give a new code the next unused number.
+
+/// Expand the error table into [`ErrorCode`], a pre-serialized constant per
+/// entry that names one, and the [`FRAMES`] list of those constants.
+macro_rules! error_codes {
+    ($(
+        $(#[$meta:meta])*
+        $variant:ident = $code:literal, $message:literal $(, $konst:ident)?;
+    )*) => {
+        /// Stable numeric identifier of every error the server can send.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[repr(u16)]
+        pub enum ErrorCode {
+            $($(#[$meta])* $variant = $code,)*
+        }
+
+        impl ErrorCode {
+            /// Every registered code, in table order.
+            pub const ALL: &[ErrorCode] = &[$(ErrorCode::$variant),*];
+
+            /// The `message` string sent alongside the code.
+            pub const fn message(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $message,)*
+                }
+            }
+        }
+
+        $(error_frame!($(#[$meta])* $code, $message $(, $konst)?);)*
+
+        /// Every pre-serialized error constant with its code.
+        pub const FRAMES: &[(ErrorCode, &str)] = &[$($((ErrorCode::$variant, $konst),)?)*];
+    };
+}
+
+/// One table entry's constant; entries without a name (errors that carry
+/// extra fields) get a builder function instead.
+macro_rules! error_frame {
+    ($(#[$meta:meta])* $code:literal, $message:literal) => {};
+    ($(#[$meta:meta])* $code:literal, $message:literal, $konst:ident) => {
+        $(#[$meta])*
+        pub const $konst: &str = concat!(
+            r#"{"type":"error","code":"#,
+            $code,
+            r#","message":""#,
+            $message,
+            r#""}"#
+        );
+    };
+}
 
-/// Client attempted to send a message without authenticating first.
-pub const UNAUTHENTICATED: &str = r#"{"type":"error","message":"unauthenticated"}"#;
+error_codes! {
+    /// Client attempted to send a message without authenticating first.
+    Unauthenticated = 1, "unauthenticated", UNAUTHENTICATED;
 
-/// The provided server password did not match.
-pub const INVALID_PASSWORD: &str = r#"{"type":"error","message":"invalid-password"}"#;
+    /// The provided server password did not match.
+    InvalidPassword = 2, "invalid-password", INVALID_PASSWORD;
 
-/// Authentication rate limit exceeded.
-pub const AUTH_RATE_LIMIT: &str = r#"{"type":"error","message":"auth-rate-limit"}"#;
+    /// Authentication rate limit exceeded.
+    AuthRateLimit = 3, "auth-rate-limit", AUTH_RATE_LIMIT;
 
-/// Timestamp is outside the acceptable window.
-pub const INVALID_TIMESTAMP: &str = r#"{"type":"error","message":"invalid-timestamp"}"#;
+    /// Timestamp is outside the acceptable window. Carries the skew the server
+    /// allows so clients can point the user at their device clock.
+    InvalidTimestamp = 4, "invalid-timestamp";
 
-/// Nonce has already been used (replay attack detected).
-pub const REPLAY_ATTACK: &str = r#"{"type":"error","message":"replay-attack"}"#;
+    /// Nonce has already been used (replay attack detected).
+    ReplayAttack = 5, "replay-attack", REPLAY_ATTACK;
 
-/// Signature verification failed.
-pub const INVALID_SIGNATURE: &str = r#"{"type":"error","message":"invalid-signature"}"#;
+    /// Signature verification failed.
+    InvalidSignature = 6, "invalid-signature", INVALID_SIGNATURE;
 
-/// Signature format is invalid.
-pub const INVALID_SIGNATURE_FORMAT: &str =
-    r#"{"type":"error","message":"invalid-signature-format"}"#;
+    /// Signature format is invalid.
+    InvalidSignatureFormat = 7, "invalid-signature-format", INVALID_SIGNATURE_FORMAT;
 
-/// Public key format is invalid.
-pub const INVALID_PUBLIC_KEY: &str = r#"{"type":"error","message":"invalid-public-key"}"#;
+    /// Public key format is invalid.
+    InvalidPublicKey = 8, "invalid-public-key", INVALID_PUBLIC_KEY;
 
-/// Public key has incorrect length.
-pub const INVALID_KEY_LENGTH: &str = r#"{"type":"error","message":"invalid-key-length"}"#;
+    /// Public key has incorrect length.
+    InvalidKeyLength = 9, "invalid-key-length", INVALID_KEY_LENGTH;
 
-/// Base64 encoding is invalid.
-pub const INVALID_ENCODING: &str = r#"{"type":"error","message":"invalid-encoding"}"#;
+    /// Base64 encoding is invalid.
+    InvalidEncoding = 10, "invalid-encoding", INVALID_ENCODING;
 
-/// Username validation failed.
-pub const INVALID_USERNAME: &str = r#"{"type":"error","message":"invalid-username"}"#;
+    /// Username validation failed.
+    InvalidUsername = 11, "invalid-username", INVALID_USERNAME;
 
-/// Username is bound to a different public key for this server session.
-pub const USERNAME_TAKEN: &str = r#"{"type":"error","message":"username-taken"}"#;
+    /// Username is bound to a different public key for this server session.
+    UsernameTaken = 12, "username-taken", USERNAME_TAKEN;
 
-/// Channel name validation failed.
-pub const INVALID_CHANNEL_NAME: &str = r#"{"type":"error","message":"invalid-channel-name"}"#;
+    /// Channel name validation failed.
+    InvalidChannelName = 13, "invalid-channel-name", INVALID_CHANNEL_NAME;
 
-/// User lacks permission to manage channels.
-pub const CHANNEL_PERMISSION_DENIED: &str =
-    r#"{"type":"error","message":"channel-permission-denied"}"#;
+    /// User lacks permission to manage channels.
+    ChannelPermissionDenied = 14, "channel-permission-denied", CHANNEL_PERMISSION_DENIED;
 
-/// Failed to create channel in database.
-pub const CHANNEL_CREATION_FAILED: &str = r#"{"type":"error","message":"channel-creation-failed"}"#;
+    /// Failed to create channel in database.
+    ChannelCreationFailed = 15, "channel-creation-failed", CHANNEL_CREATION_FAILED;
 
-/// Failed to delete channel from database.
-pub const CHANNEL_DELETION_FAILED: &str = r#"{"type":"error","message":"channel-deletion-failed"}"#;
+    /// Failed to delete channel from database.
+    ChannelDeletionFailed = 16, "channel-deletion-failed", CHANNEL_DELETION_FAILED;
 
-/// Cannot delete the general channel.
-pub const CANNOT_DELETE_GENERAL: &str = r#"{"type":"error","message":"cannot-delete-general"}"#;
+    /// Cannot delete the default channel (`DEFAULT_CHANNEL`).
+    CannotDeleteGeneral = 17, "cannot-delete-general", CANNOT_DELETE_GENERAL;
 
-/// Message rate limit exceeded.
-pub const MESSAGE_RATE_LIMIT: &str = r#"{"type":"error","message":"message-rate-limit"}"#;
+    /// Message rate limit exceeded.
+    MessageRateLimit = 18, "message-rate-limit", MESSAGE_RATE_LIMIT;
 
-/// Sender's roles do not grant permission to send messages.
-pub const SEND_PERMISSION_DENIED: &str = r#"{"type":"error","message":"send-permission-denied"}"#;
+    /// Sender's roles do not grant permission to send messages.
+    SendPermissionDenied = 19, "send-permission-denied", SEND_PERMISSION_DENIED;
 
-/// Message content exceeds the maximum allowed length.
-pub const MESSAGE_TOO_LONG: &str = r#"{"type":"error","message":"message-too-long"}"#;
+    /// Reaction toggled again on the same message before the cooldown elapsed.
+    ReactionRateLimit = 20, "reaction-rate-limit", REACTION_RATE_LIMIT;
 
-/// Voice quality parameter is invalid.
-pub const INVALID_VOICE_QUALITY: &str = r#"{"type":"error","message":"invalid-voice-quality"}"#;
+    /// Message already carries the maximum number of distinct reaction emojis.
+    TooManyReactions = 21, "too-many-reactions", TOO_MANY_REACTIONS;
 
-/// Voice bitrate parameter is invalid.
-pub const INVALID_VOICE_BITRATE: &str = r#"{"type":"error","message":"invalid-voice-bitrate"}"#;
+    /// Message content exceeds the maximum allowed length.
+    MessageTooLong = 22, "message-too-long", MESSAGE_TOO_LONG;
 
-/// Voice channel does not exist.
-pub const UNKNOWN_VOICE_CHANNEL: &str = r#"{"type":"error","message":"unknown-voice-channel"}"#;
+    /// Serialized message exceeds `MAX_MESSAGE_BYTES`.
+    MessageTooLarge = 23, "message-too-large", MESSAGE_TOO_LARGE;
 
-/// Failed to update voice channel configuration.
-pub const VOICE_CHANNEL_UPDATE_FAILED: &str =
-    r#"{"type":"error","message":"voice-channel-update-failed"}"#;
+    /// Voice quality parameter is invalid.
+    InvalidVoiceQuality = 24, "invalid-voice-quality", INVALID_VOICE_QUALITY;
 
-/// User lacks permission to manage roles.
-pub const ROLE_PERMISSION_DENIED: &str = r#"{"type":"error","message":"role-permission-denied"}"#;
+    /// Voice bitrate parameter is invalid.
+    InvalidVoiceBitrate = 25, "invalid-voice-bitrate", INVALID_VOICE_BITRATE;
 
-/// Target user for role change is not connected.
-pub const ROLE_TARGET_NOT_FOUND: &str = r#"{"type":"error","message":"role-target-not-found"}"#;
+    /// Voice channel already holds the maximum number of mesh participants.
+    VoiceMeshLimit = 26, "voice-mesh-limit", VOICE_MESH_LIMIT;
 
-/// Failed to update role in database.
-pub const ROLE_UPDATE_FAILED: &str = r#"{"type":"error","message":"role-update-failed"}"#;
+    /// Voice channel does not exist.
+    UnknownVoiceChannel = 27, "unknown-voice-channel", UNKNOWN_VOICE_CHANNEL;
 
-/// The referenced role definition does not exist.
-pub const ROLE_NOT_FOUND: &str = r#"{"type":"error","message":"role-not-found"}"#;
+    /// Failed to update voice channel configuration.
+    VoiceChannelUpdateFailed = 28, "voice-channel-update-failed", VOICE_CHANNEL_UPDATE_FAILED;
 
-/// A role with this name already exists.
-pub const ROLE_NAME_TAKEN: &str = r#"{"type":"error","message":"role-name-taken"}"#;
+    /// User lacks permission to manage roles.
+    RolePermissionDenied = 29, "role-permission-denied", ROLE_PERMISSION_DENIED;
 
-/// The role is protected (the default `@everyone` or the Owner role) and
-/// cannot be deleted or reassigned.
-pub const ROLE_PROTECTED: &str = r#"{"type":"error","message":"role-protected"}"#;
+    /// Target user for role change is not connected.
+    RoleTargetNotFound = 30, "role-target-not-found", ROLE_TARGET_NOT_FOUND;
 
-/// The server has reached its role limit.
-pub const ROLE_LIMIT_REACHED: &str = r#"{"type":"error","message":"role-limit-reached"}"#;
+    /// Failed to update role in database.
+    RoleUpdateFailed = 31, "role-update-failed", ROLE_UPDATE_FAILED;
 
-/// Role name failed validation.
-pub const INVALID_ROLE_NAME: &str = r#"{"type":"error","message":"invalid-role-name"}"#;
+    /// The referenced role definition does not exist.
+    RoleNotFound = 32, "role-not-found", ROLE_NOT_FOUND;
 
-/// Role color failed validation.
-pub const INVALID_ROLE_COLOR: &str = r#"{"type":"error","message":"invalid-role-color"}"#;
+    /// A role with this name already exists.
+    RoleNameTaken = 33, "role-name-taken", ROLE_NAME_TAKEN;
 
-/// Role permission mask contained unknown bits.
-pub const INVALID_ROLE_PERMISSIONS: &str =
-    r#"{"type":"error","message":"invalid-role-permissions"}"#;
+    /// The role is protected (the default `@everyone` or the Owner role) and
+    /// cannot be deleted or reassigned.
+    RoleProtected = 34, "role-protected", ROLE_PROTECTED;
 
-/// Category name validation failed.
-pub const INVALID_CATEGORY_NAME: &str = r#"{"type":"error","message":"invalid-category-name"}"#;
+    /// The server has reached its role limit.
+    RoleLimitReached = 35, "role-limit-reached", ROLE_LIMIT_REACHED;
 
-/// Failed to create category in database.
-pub const CATEGORY_CREATION_FAILED: &str =
-    r#"{"type":"error","message":"category-creation-failed"}"#;
+    /// Role name failed validation.
+    InvalidRoleName = 36, "invalid-role-name", INVALID_ROLE_NAME;
 
-/// Failed to rename category in database.
-pub const CATEGORY_RENAME_FAILED: &str = r#"{"type":"error","message":"category-rename-failed"}"#;
+    /// Role color failed validation.
+    InvalidRoleColor = 37, "invalid-role-color", INVALID_ROLE_COLOR;
 
-/// Failed to delete category from database.
-pub const CATEGORY_DELETION_FAILED: &str =
-    r#"{"type":"error","message":"category-deletion-failed"}"#;
+    /// Role permission mask contained unknown bits.
+    InvalidRolePermissions = 38, "invalid-role-permissions", INVALID_ROLE_PERMISSIONS;
 
-/// The referenced category does not exist.
-pub const UNKNOWN_CATEGORY: &str = r#"{"type":"error","message":"unknown-category"}"#;
+    /// Category name validation failed.
+    InvalidCategoryName = 39, "invalid-category-name", INVALID_CATEGORY_NAME;
 
-/// Failed to move channel to category.
-pub const CHANNEL_MOVE_FAILED: &str = r#"{"type":"error","message":"channel-move-failed"}"#;
+    /// Failed to create category in database.
+    CategoryCreationFailed = 40, "category-creation-failed", CATEGORY_CREATION_FAILED;
 
-/// Reorder request was malformed or referenced unknown channels/categories.
-pub const REORDER_FAILED: &str = r#"{"type":"error","message":"reorder-failed"}"#;
+    /// Failed to rename category in database.
+    CategoryRenameFailed = 41, "category-rename-failed", CATEGORY_RENAME_FAILED;
 
-/// User lacks permission to edit a channel's permission overrides.
-pub const CHANNEL_OVERRIDE_PERMISSION_DENIED: &str =
-    r#"{"type":"error","message":"channel-override-permission-denied"}"#;
+    /// Failed to delete category from database.
+    CategoryDeletionFailed = 42, "category-deletion-failed", CATEGORY_DELETION_FAILED;
 
-/// A channel override request was malformed (bad target, kind or mask).
-pub const INVALID_CHANNEL_OVERRIDE: &str =
-    r#"{"type":"error","message":"invalid-channel-override"}"#;
+    /// The referenced category does not exist.
+    UnknownCategory = 43, "unknown-category", UNKNOWN_CATEGORY;
 
-/// The target of a channel override (role or user) could not be resolved.
-pub const OVERRIDE_TARGET_NOT_FOUND: &str =
-    r#"{"type":"error","message":"override-target-not-found"}"#;
+    /// Failed to move channel to category.
+    ChannelMoveFailed = 44, "channel-move-failed", CHANNEL_MOVE_FAILED;
 
-/// Failed to persist a channel override change.
-pub const CHANNEL_OVERRIDE_FAILED: &str = r#"{"type":"error","message":"channel-override-failed"}"#;
+    /// Reorder request was malformed or referenced unknown channels/categories.
+    ReorderFailed = 45, "reorder-failed", REORDER_FAILED;
 
-/// Avatar reference is not a stored upload within the size cap.
-pub const INVALID_AVATAR: &str = r#"{"type":"error","message":"invalid-avatar"}"#;
+    /// User lacks permission to edit a channel's permission overrides.
+    ChannelOverridePermissionDenied = 46, "channel-override-permission-denied", CHANNEL_OVERRIDE_PERMISSION_DENIED;
 
-/// Failed to persist the avatar change.
-pub const AVATAR_UPDATE_FAILED: &str = r#"{"type":"error","message":"avatar-update-failed"}"#;
+    /// A channel override request was malformed (bad target, kind or mask).
+    InvalidChannelOverride = 47, "invalid-channel-override", INVALID_CHANNEL_OVERRIDE;
 
-/// Channel topic validation failed.
-pub const INVALID_CHANNEL_TOPIC: &str = r#"{"type":"error","message":"invalid-channel-topic"}"#;
+    /// The target of a channel override (role or user) could not be resolved.
+    OverrideTargetNotFound = 48, "override-target-not-found", OVERRIDE_TARGET_NOT_FOUND;
 
-/// The referenced channel does not exist.
-pub const UNKNOWN_CHANNEL: &str = r#"{"type":"error","message":"unknown-channel"}"#;
+    /// Failed to persist a channel override change.
+    ChannelOverrideFailed = 49, "channel-override-failed", CHANNEL_OVERRIDE_FAILED;
 
-/// Failed to update the channel topic in the database.
-pub const TOPIC_UPDATE_FAILED: &str = r#"{"type":"error","message":"topic-update-failed"}"#;
+    /// The channel is private and the user is not one of its members.
+    ChannelAccessDenied = 50, "channel-access-denied", CHANNEL_ACCESS_DENIED;
 
-/// User lacks permission for moderation actions (kick, ban, mute).
-pub const MODERATION_PERMISSION_DENIED: &str =
-    r#"{"type":"error","message":"moderation-permission-denied"}"#;
+    /// Avatar reference is not a stored upload within the size cap.
+    InvalidAvatar = 51, "invalid-avatar", INVALID_AVATAR;
 
-/// Moderation target could not be resolved to a connected user.
-pub const MODERATION_TARGET_NOT_FOUND: &str =
-    r#"{"type":"error","message":"moderation-target-not-found"}"#;
+    /// Failed to persist the avatar change.
+    AvatarUpdateFailed = 52, "avatar-update-failed", AVATAR_UPDATE_FAILED;
 
-/// Moderation target outranks or equals the requester and is protected.
-pub const MODERATION_TARGET_PROTECTED: &str =
-    r#"{"type":"error","message":"moderation-target-protected"}"#;
+    /// Display name is too long or contains control characters.
+    InvalidDisplayName = 53, "invalid-display-name", INVALID_DISPLAY_NAME;
 
-/// Moderation actions cannot target the requester themselves.
-pub const CANNOT_MODERATE_SELF: &str = r#"{"type":"error","message":"cannot-moderate-self"}"#;
+    /// Bio is too long or contains control characters other than newlines.
+    InvalidBio = 54, "invalid-bio", INVALID_BIO;
 
-/// Failed to persist a moderation action.
-pub const MODERATION_FAILED: &str = r#"{"type":"error","message":"moderation-failed"}"#;
+    /// Failed to persist the profile change (or the requester has no key binding).
+    ProfileUpdateFailed = 55, "profile-update-failed", PROFILE_UPDATE_FAILED;
 
-/// Connection rejected because the user is banned.
-pub const BANNED: &str = r#"{"type":"error","message":"banned"}"#;
+    /// The requested user has no profile (unknown name or no key binding).
+    ProfileNotFound = 56, "profile-not-found", PROFILE_NOT_FOUND;
 
-/// Direct message target is not a known user on this server.
-pub const DM_TARGET_NOT_FOUND: &str = r#"{"type":"error","message":"dm-target-not-found"}"#;
+    /// Channel topic validation failed.
+    InvalidChannelTopic = 57, "invalid-channel-topic", INVALID_CHANNEL_TOPIC;
 
-/// Direct messages cannot be sent to oneself.
-pub const CANNOT_DM_SELF: &str = r#"{"type":"error","message":"cannot-dm-self"}"#;
+    /// The referenced channel does not exist.
+    UnknownChannel = 58, "unknown-channel", UNKNOWN_CHANNEL;
 
-/// Direct message frame is missing or carries malformed encryption fields.
-pub const INVALID_DM_PAYLOAD: &str = r#"{"type":"error","message":"invalid-dm-payload"}"#;
+    /// Failed to update the channel topic in the database.
+    TopicUpdateFailed = 59, "topic-update-failed", TOPIC_UPDATE_FAILED;
 
-/// Failed to persist a direct message.
-pub const DM_SEND_FAILED: &str = r#"{"type":"error","message":"dm-send-failed"}"#;
+    /// Retention must be null or between 1 and 3650 days.
+    InvalidRetention = 60, "invalid-retention", INVALID_RETENTION;
 
-/// Failed to load a direct message conversation.
-pub const DM_HISTORY_FAILED: &str = r#"{"type":"error","message":"dm-history-failed"}"#;
+    /// Failed to store the channel retention policy.
+    RetentionUpdateFailed = 61, "retention-update-failed", RETENTION_UPDATE_FAILED;
 
-/// Pin target does not exist (unknown or deleted message).
-pub const PIN_TARGET_NOT_FOUND: &str = r#"{"type":"error","message":"pin-target-not-found"}"#;
+    /// Slow mode must be between 0 and 21600 seconds.
+    InvalidSlowMode = 62, "invalid-slow-mode", INVALID_SLOW_MODE;
 
-/// The channel already carries the maximum number of pins.
-pub const PIN_LIMIT_REACHED: &str = r#"{"type":"error","message":"pin-limit-reached"}"#;
+    /// Failed to store the channel slow mode interval.
+    SlowModeUpdateFailed = 63, "slow-mode-update-failed", SLOW_MODE_UPDATE_FAILED;
 
-/// Failed to persist a pin change.
-pub const PIN_FAILED: &str = r#"{"type":"error","message":"pin-failed"}"#;
+    /// The channel is in slow mode and the sender posted there too recently;
+    /// `retryAfter` is how many seconds remain.
+    SlowModeActive = 64, "slow-mode-active";
 
-/// Request requires an authenticated user name (presence not yet processed).
-pub const NOT_AUTHENTICATED: &str = r#"{"type":"error","message":"not-authenticated"}"#;
+    /// Failed to store a read marker.
+    ReadMarkerFailed = 65, "read-marker-failed", READ_MARKER_FAILED;
 
-/// Status update carried a missing or unknown status value.
-pub const INVALID_STATUS: &str = r#"{"type":"error","message":"invalid-status"}"#;
+    /// Failed to store the channel's read receipt setting.
+    ReceiptsUpdateFailed = 66, "receipts-update-failed", RECEIPTS_UPDATE_FAILED;
 
-/// Sender is muted and may not send messages.
-pub const MUTED: &str = r#"{"type":"error","message":"muted"}"#;
+    /// `roleIds` must be null or a list of existing role ids.
+    InvalidPostRoles = 67, "invalid-post-roles", INVALID_POST_ROLES;
 
-/// Message ID is missing, malformed or out of range.
-pub const INVALID_MESSAGE_ID: &str = r#"{"type":"error","message":"invalid-message-id"}"#;
+    /// Failed to store the channel posting restriction.
+    PostRolesUpdateFailed = 68, "post-roles-update-failed", POST_ROLES_UPDATE_FAILED;
 
-/// The referenced message does not exist.
-pub const MESSAGE_NOT_FOUND: &str = r#"{"type":"error","message":"message-not-found"}"#;
+    /// The channel only accepts posts from certain roles, none of which the
+    /// requester holds.
+    ChannelPostDenied = 69, "channel-post-denied", CHANNEL_POST_DENIED;
 
-/// The referenced message belongs to a different channel.
-pub const MESSAGE_WRONG_CHANNEL: &str = r#"{"type":"error","message":"message-wrong-channel"}"#;
+    /// User lacks permission for moderation actions (kick, ban, mute).
+    ModerationPermissionDenied = 70, "moderation-permission-denied", MODERATION_PERMISSION_DENIED;
 
-/// Requester may not delete or edit this message.
-pub const MESSAGE_PERMISSION_DENIED: &str =
-    r#"{"type":"error","message":"message-permission-denied"}"#;
+    /// Moderation target could not be resolved to a connected user.
+    ModerationTargetNotFound = 71, "moderation-target-not-found", MODERATION_TARGET_NOT_FOUND;
 
-/// Failed to delete a message.
-pub const MESSAGE_DELETE_FAILED: &str = r#"{"type":"error","message":"message-delete-failed"}"#;
+    /// Moderation target outranks or equals the requester and is protected.
+    ModerationTargetProtected = 72, "moderation-target-protected", MODERATION_TARGET_PROTECTED;
 
-/// Failed to edit a message.
-pub const MESSAGE_EDIT_FAILED: &str = r#"{"type":"error","message":"message-edit-failed"}"#;
+    /// Moderation actions cannot target the requester themselves.
+    CannotModerateSelf = 73, "cannot-moderate-self", CANNOT_MODERATE_SELF;
 
-/// Replacement message text is missing or invalid.
-pub const INVALID_MESSAGE_TEXT: &str = r#"{"type":"error","message":"invalid-message-text"}"#;
+    /// Failed to persist a moderation action.
+    ModerationFailed = 74, "moderation-failed", MODERATION_FAILED;
 
-/// Reaction action was neither `add` nor `remove`.
-pub const INVALID_REACTION_ACTION: &str = r#"{"type":"error","message":"invalid-reaction-action"}"#;
+    /// Connection rejected because the user is banned.
+    Banned = 75, "banned", BANNED;
 
-/// Reaction emoji is missing or malformed.
-pub const INVALID_EMOJI: &str = r#"{"type":"error","message":"invalid-emoji"}"#;
+    /// Direct message target is not a known user on this server.
+    DmTargetNotFound = 76, "dm-target-not-found", DM_TARGET_NOT_FOUND;
 
-/// Failed to persist or load reactions.
-pub const REACTION_FAILED: &str = r#"{"type":"error","message":"reaction-failed"}"#;
+    /// Direct messages cannot be sent to oneself.
+    CannotDmSelf = 77, "cannot-dm-self", CANNOT_DM_SELF;
 
-/// The message a reply targets no longer exists.
-pub const REPLY_TARGET_NOT_FOUND: &str = r#"{"type":"error","message":"reply-target-not-found"}"#;
+    /// Direct message frame is missing or carries malformed encryption fields.
+    InvalidDmPayload = 78, "invalid-dm-payload", INVALID_DM_PAYLOAD;
 
-/// Failed to load a thread.
-pub const THREAD_LOAD_FAILED: &str = r#"{"type":"error","message":"thread-load-failed"}"#;
+    /// Failed to persist a direct message.
+    DmSendFailed = 79, "dm-send-failed", DM_SEND_FAILED;
 
-/// User lacks permission to manage custom server emojis.
-pub const EMOJI_PERMISSION_DENIED: &str = r#"{"type":"error","message":"emoji-permission-denied"}"#;
+    /// Failed to load a direct message conversation.
+    DmHistoryFailed = 80, "dm-history-failed", DM_HISTORY_FAILED;
 
-/// Custom emoji name failed validation.
-pub const INVALID_EMOJI_NAME: &str = r#"{"type":"error","message":"invalid-emoji-name"}"#;
+    /// Pin target does not exist (unknown or deleted message).
+    PinTargetNotFound = 81, "pin-target-not-found", PIN_TARGET_NOT_FOUND;
 
-/// Custom emoji URL does not point at a valid uploaded image.
-pub const INVALID_EMOJI_URL: &str = r#"{"type":"error","message":"invalid-emoji-url"}"#;
+    /// The channel already carries the maximum number of pins.
+    PinLimitReached = 82, "pin-limit-reached", PIN_LIMIT_REACHED;
 
-/// A custom emoji with this name already exists.
-pub const EMOJI_NAME_TAKEN: &str = r#"{"type":"error","message":"emoji-name-taken"}"#;
+    /// Failed to persist a pin change.
+    PinFailed = 83, "pin-failed", PIN_FAILED;
 
-/// The server has reached its custom emoji limit.
-pub const EMOJI_LIMIT_REACHED: &str = r#"{"type":"error","message":"emoji-limit-reached"}"#;
+    /// Flag target does not exist or is in a channel the reporter cannot see.
+    FlagTargetNotFound = 84, "flag-target-not-found", FLAG_TARGET_NOT_FOUND;
 
-/// Failed to persist a custom emoji change.
-pub const EMOJI_UPDATE_FAILED: &str = r#"{"type":"error","message":"emoji-update-failed"}"#;
+    /// The flag reason is too long.
+    InvalidFlagReason = 85, "invalid-flag-reason", INVALID_FLAG_REASON;
 
-/// The referenced custom emoji does not exist.
-pub const EMOJI_NOT_FOUND: &str = r#"{"type":"error","message":"emoji-not-found"}"#;
+    /// The reporter already flagged this message.
+    AlreadyFlagged = 86, "already-flagged", ALREADY_FLAGGED;
 
-/// User lacks permission to edit the server identity.
-pub const IDENTITY_PERMISSION_DENIED: &str =
-    r#"{"type":"error","message":"identity-permission-denied"}"#;
+    /// Resolving found no open flags on the message.
+    FlagNotFound = 87, "flag-not-found", FLAG_NOT_FOUND;
 
-/// Server name failed validation.
-pub const INVALID_SERVER_NAME: &str = r#"{"type":"error","message":"invalid-server-name"}"#;
+    /// Failed to store or load flags.
+    FlagFailed = 88, "flag-failed", FLAG_FAILED;
 
-/// Server description failed validation.
-pub const INVALID_SERVER_DESCRIPTION: &str =
-    r#"{"type":"error","message":"invalid-server-description"}"#;
+    /// Listing or resolving flags requires `MANAGE_MESSAGES`.
+    FlagPermissionDenied = 89, "flag-permission-denied", FLAG_PERMISSION_DENIED;
 
-/// Welcome message failed validation.
-pub const INVALID_WELCOME_MESSAGE: &str = r#"{"type":"error","message":"invalid-welcome-message"}"#;
+    /// Request requires an authenticated user name (presence not yet processed).
+    NotAuthenticated = 90, "not-authenticated", NOT_AUTHENTICATED;
 
-/// Server icon URL does not point at a valid uploaded image.
-pub const INVALID_SERVER_ICON: &str = r#"{"type":"error","message":"invalid-server-icon"}"#;
+    /// A voice or signaling frame named a user other than the connection's own.
+    IdentityMismatch = 91, "identity-mismatch", IDENTITY_MISMATCH;
 
-/// Failed to persist a server identity change.
-pub const IDENTITY_UPDATE_FAILED: &str = r#"{"type":"error","message":"identity-update-failed"}"#;
+    /// Status update carried a missing or unknown status value.
+    InvalidStatus = 92, "invalid-status", INVALID_STATUS;
 
-/// Bot presence frame did not include a token.
-pub const MISSING_BOT_TOKEN: &str = r#"{"type":"error","message":"missing-bot-token"}"#;
+    /// Sender is muted and may not send messages.
+    Muted = 93, "muted", MUTED;
 
-/// Bot token is unknown or the bot is deactivated.
-pub const INVALID_BOT_TOKEN: &str = r#"{"type":"error","message":"invalid-bot-token"}"#;
+    /// Message ID is missing, malformed or out of range.
+    InvalidMessageId = 94, "invalid-message-id", INVALID_MESSAGE_ID;
 
-/// User lacks permission to change the server-wide stats toggle.
-pub const STATS_PERMISSION_DENIED: &str = r#"{"type":"error","message":"stats-permission-denied"}"#;
+    /// The referenced message does not exist.
+    MessageNotFound = 95, "message-not-found", MESSAGE_NOT_FOUND;
 
-/// Requested stats are not available (tracking disabled or target not opted in).
-pub const STATS_NOT_AVAILABLE: &str = r#"{"type":"error","message":"stats-not-available"}"#;
+    /// Permalink is malformed.
+    InvalidPermalink = 96, "invalid-permalink", INVALID_PERMALINK;
 
-/// Failed to persist or load stat tracking data.
-pub const STATS_UPDATE_FAILED: &str = r#"{"type":"error","message":"stats-update-failed"}"#;
+    /// The referenced message belongs to a different channel.
+    MessageWrongChannel = 97, "message-wrong-channel", MESSAGE_WRONG_CHANNEL;
 
-/// User lacks permission to change the screen share bitrate cap.
-pub const SCREENSHARE_PERMISSION_DENIED: &str =
-    r#"{"type":"error","message":"screenshare-permission-denied"}"#;
+    /// Requester may not delete or edit this message.
+    MessagePermissionDenied = 98, "message-permission-denied", MESSAGE_PERMISSION_DENIED;
 
-/// Screen share bitrate cap failed validation.
-pub const INVALID_SCREENSHARE_BITRATE: &str =
-    r#"{"type":"error","message":"invalid-screenshare-bitrate"}"#;
+    /// Failed to delete a message.
+    MessageDeleteFailed = 99, "message-delete-failed", MESSAGE_DELETE_FAILED;
 
-/// Failed to persist or load the screen share configuration.
-pub const SCREENSHARE_UPDATE_FAILED: &str =
-    r#"{"type":"error","message":"screenshare-update-failed"}"#;
+    /// `purge-user-messages` was sent without `confirm: true`.
+    PurgeConfirmationRequired = 100, "purge-confirmation-required", PURGE_CONFIRMATION_REQUIRED;
 
-/// Wiki page slug failed validation.
-pub const INVALID_WIKI_SLUG: &str = r#"{"type":"error","message":"invalid-wiki-slug"}"#;
+    /// Failed to edit a message.
+    MessageEditFailed = 101, "message-edit-failed", MESSAGE_EDIT_FAILED;
 
-/// Wiki page title failed validation.
-pub const INVALID_WIKI_TITLE: &str = r#"{"type":"error","message":"invalid-wiki-title"}"#;
+    /// Replacement message text is missing or invalid.
+    InvalidMessageText = 102, "invalid-message-text", INVALID_MESSAGE_TEXT;
 
-/// Wiki page body exceeds the maximum allowed size.
-pub const WIKI_BODY_TOO_LARGE: &str = r#"{"type":"error","message":"wiki-body-too-large"}"#;
+    /// Reaction action was neither `add` nor `remove`.
+    InvalidReactionAction = 103, "invalid-reaction-action", INVALID_REACTION_ACTION;
 
-/// A wiki page with this slug already exists in the channel.
-pub const WIKI_SLUG_TAKEN: &str = r#"{"type":"error","message":"wiki-slug-taken"}"#;
+    /// Reaction emoji is missing or malformed.
+    InvalidEmoji = 104, "invalid-emoji", INVALID_EMOJI;
 
-/// The referenced wiki page does not exist.
-pub const WIKI_PAGE_NOT_FOUND: &str = r#"{"type":"error","message":"wiki-page-not-found"}"#;
+    /// Reaction is not a known Unicode emoji or registered custom emoji while
+    /// `STRICT_EMOJI` is on.
+    ReactionRejected = 105, "reaction-rejected", REACTION_REJECTED;
 
-/// The channel has reached its wiki page limit.
-pub const WIKI_PAGE_LIMIT_REACHED: &str = r#"{"type":"error","message":"wiki-page-limit-reached"}"#;
+    /// Failed to persist or load reactions.
+    ReactionFailed = 106, "reaction-failed", REACTION_FAILED;
 
-/// Failed to persist or load wiki data.
-pub const WIKI_SAVE_FAILED: &str = r#"{"type":"error","message":"wiki-save-failed"}"#;
+    /// The message a reply targets no longer exists.
+    ReplyTargetNotFound = 107, "reply-target-not-found", REPLY_TARGET_NOT_FOUND;
+
+    /// Failed to load a thread.
+    ThreadLoadFailed = 108, "thread-load-failed", THREAD_LOAD_FAILED;
+
+    /// User lacks permission to manage custom server emojis.
+    EmojiPermissionDenied = 109, "emoji-permission-denied", EMOJI_PERMISSION_DENIED;
+
+    /// Custom emoji name failed validation.
+    InvalidEmojiName = 110, "invalid-emoji-name", INVALID_EMOJI_NAME;
+
+    /// Custom emoji URL does not point at a valid uploaded image.
+    InvalidEmojiUrl = 111, "invalid-emoji-url", INVALID_EMOJI_URL;
+
+    /// A custom emoji with this name already exists.
+    EmojiNameTaken = 112, "emoji-name-taken", EMOJI_NAME_TAKEN;
+
+    /// The server has reached its custom emoji limit.
+    EmojiLimitReached = 113, "emoji-limit-reached", EMOJI_LIMIT_REACHED;
+
+    /// Failed to persist a custom emoji change.
+    EmojiUpdateFailed = 114, "emoji-update-failed", EMOJI_UPDATE_FAILED;
+
+    /// The referenced custom emoji does not exist.
+    EmojiNotFound = 115, "emoji-not-found", EMOJI_NOT_FOUND;
+
+    /// User lacks permission to edit the server identity.
+    IdentityPermissionDenied = 116, "identity-permission-denied", IDENTITY_PERMISSION_DENIED;
+
+    /// Server name failed validation.
+    InvalidServerName = 117, "invalid-server-name", INVALID_SERVER_NAME;
+
+    /// Server description failed validation.
+    InvalidServerDescription = 118, "invalid-server-description", INVALID_SERVER_DESCRIPTION;
+
+    /// Welcome message failed validation.
+    InvalidWelcomeMessage = 119, "invalid-welcome-message", INVALID_WELCOME_MESSAGE;
+
+    /// Server icon URL does not point at a valid uploaded image.
+    InvalidServerIcon = 120, "invalid-server-icon", INVALID_SERVER_ICON;
+
+    /// Failed to persist a server identity change.
+    IdentityUpdateFailed = 121, "identity-update-failed", IDENTITY_UPDATE_FAILED;
+
+    /// Bot presence frame did not include a token.
+    MissingBotToken = 122, "missing-bot-token", MISSING_BOT_TOKEN;
+
+    /// Bot token is unknown or the bot is deactivated.
+    InvalidBotToken = 123, "invalid-bot-token", INVALID_BOT_TOKEN;
+
+    /// User lacks permission to change the server-wide stats toggle.
+    StatsPermissionDenied = 124, "stats-permission-denied", STATS_PERMISSION_DENIED;
+
+    /// Requested stats are not available (tracking disabled or target not opted in).
+    StatsNotAvailable = 125, "stats-not-available", STATS_NOT_AVAILABLE;
+
+    /// Failed to persist or load stat tracking data.
+    StatsUpdateFailed = 126, "stats-update-failed", STATS_UPDATE_FAILED;
+
+    /// User lacks permission to change the screen share bitrate cap.
+    ScreensharePermissionDenied = 127, "screenshare-permission-denied", SCREENSHARE_PERMISSION_DENIED;
+
+    /// Screen share bitrate cap failed validation.
+    InvalidScreenshareBitrate = 128, "invalid-screenshare-bitrate", INVALID_SCREENSHARE_BITRATE;
+
+    /// Failed to persist or load the screen share configuration.
+    ScreenshareUpdateFailed = 129, "screenshare-update-failed", SCREENSHARE_UPDATE_FAILED;
+
+    /// Wiki page slug failed validation.
+    InvalidWikiSlug = 130, "invalid-wiki-slug", INVALID_WIKI_SLUG;
+
+    /// Wiki page title failed validation.
+    InvalidWikiTitle = 131, "invalid-wiki-title", INVALID_WIKI_TITLE;
+
+    /// Wiki page body exceeds the maximum allowed size.
+    WikiBodyTooLarge = 132, "wiki-body-too-large", WIKI_BODY_TOO_LARGE;
+
+    /// A wiki page with this slug already exists in the channel.
+    WikiSlugTaken = 133, "wiki-slug-taken", WIKI_SLUG_TAKEN;
+
+    /// The referenced wiki page does not exist.
+    WikiPageNotFound = 134, "wiki-page-not-found", WIKI_PAGE_NOT_FOUND;
+
+    /// The channel has reached its wiki page limit.
+    WikiPageLimitReached = 135, "wiki-page-limit-reached", WIKI_PAGE_LIMIT_REACHED;
+
+    /// `scheduleAt` is not an RFC 3339 time in the future, or lies more than
+    /// 30 days ahead.
+    InvalidSchedule = 136, "invalid-schedule", INVALID_SCHEDULE;
+
+    /// No pending scheduled message of this user has the given token.
+    ScheduledNotFound = 137, "scheduled-not-found", SCHEDULED_NOT_FOUND;
+
+    /// The frame nests too deeply or contains too many JSON values.
+    PayloadTooComplex = 138, "payload-too-complex", PAYLOAD_TOO_COMPLEX;
+
+    /// Failed to persist or load wiki data.
+    WikiSaveFailed = 139, "wiki-save-failed", WIKI_SAVE_FAILED;
+
+    /// A frame of a known type is missing a field or has one of the wrong
+    /// JSON type. Carries the offending `frameType`.
+    InvalidPayload = 140, "invalid-payload";
+
+    /// A `presence` frame lacks the user name or part of the key proof.
+    /// Carries the missing `fields`; the connection stays open.
+    MissingAuthFields = 141, "missing-auth-fields";
+
+    /// `rename-channel` to a name another text channel already uses.
+    ChannelNameTaken = 142, "channel-name-taken", CHANNEL_NAME_TAKEN;
+
+    /// The configured default channel is looked up by name and cannot be
+    /// renamed.
+    CannotRenameDefaultChannel = 143, "cannot-rename-default-channel", CANNOT_RENAME_DEFAULT_CHANNEL;
+
+    /// Database failure while renaming a text channel.
+    ChannelRenameFailed = 144, "channel-rename-failed", CHANNEL_RENAME_FAILED;
+
+    /// The message contains a word from the word list and `FILTER_MODE` is
+    /// `block`.
+    ContentBlocked = 145, "content-blocked", CONTENT_BLOCKED;
+
+    /// More than `MAX_CHANNEL_CREATIONS_PER_MINUTE` text and voice channels
+    /// created within a minute.
+    ChannelRateLimit = 146, "channel-rate-limit", CHANNEL_RATE_LIMIT;
+
+    /// The server already has `MAX_CHANNELS` text and voice channels.
+    ChannelLimitReached = 147, "channel-limit-reached", CHANNEL_LIMIT_REACHED;
+
+    /// The `protocolVersion` in `presence` is below the oldest supported one
+    /// (or not a version number). Carries the supported `minVersion` and
+    /// `maxVersion`; the connection is closed.
+    UnsupportedProtocol = 148, "unsupported-protocol";
+
+    /// The channel already has as many subscribers as its `maxSubscribers`.
+    ChannelFull = 149, "channel-full", CHANNEL_FULL;
+
+    /// A subscriber limit must be null or between 1 and 10000.
+    InvalidSubscriberLimit = 150, "invalid-subscriber-limit", INVALID_SUBSCRIBER_LIMIT;
+
+    /// Failed to store the channel's subscriber limit.
+    SubscriberLimitUpdateFailed = 151, "subscriber-limit-update-failed", SUBSCRIBER_LIMIT_UPDATE_FAILED;
+
+    /// The sender already has `MAX_PENDING_SCHEDULED_PER_USER` messages
+    /// waiting to be posted.
+    TooManyScheduled = 152, "too-many-scheduled", TOO_MANY_SCHEDULED;
+}
+
+/// Serialize the error frame for `code`. The constants are the same frames
+/// built at compile time; use this when the code is only known at runtime.
+pub fn error_payload(code: ErrorCode) -> String {
+    format!(
+        r#"{{"type":"error","code":{},"message":"{}"}}"#,
+        code as u16,
+        code.message()
+    )
+}
+
+/// The [`ErrorCode::InvalidTimestamp`] frame with the clock skew the server
+/// allows.
+pub fn invalid_timestamp(allowed_skew_seconds: i64) -> String {
+    serde_json::json!({
+        "type": "error",
+        "code": ErrorCode::InvalidTimestamp as u16,
+        "message": ErrorCode::InvalidTimestamp.message(),
+        "allowedSkewSeconds": allowed_skew_seconds,
+    })
+    .to_string()
+}
+
+/// The [`ErrorCode::SlowModeActive`] frame with the seconds left before the
+/// sender may post again.
+pub fn slow_mode_active(retry_after_seconds: u64) -> String {
+    serde_json::json!({
+        "type": "error",
+        "code": ErrorCode::SlowModeActive as u16,
+        "message": ErrorCode::SlowModeActive.message(),
+        "retryAfter": retry_after_seconds,
+    })
+    .to_string()
+}
+
+/// The [`ErrorCode::UnsupportedProtocol`] frame with the supported range.
+pub fn unsupported_protocol() -> String {
+    use crate::ws::protocol::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+    serde_json::json!({
+        "type": "error",
+        "code": ErrorCode::UnsupportedProtocol as u16,
+        "message": ErrorCode::UnsupportedProtocol.message(),
+        "minVersion": MIN_PROTOCOL_VERSION,
+        "maxVersion": PROTOCOL_VERSION,
+    })
+    .to_string()
+}
+
+/// The [`ErrorCode::InvalidPayload`] frame naming the frame type that could
+/// not be decoded.
+pub fn invalid_payload(frame_type: &str) -> String {
+    serde_json::json!({
+        "type": "error",
+        "code": ErrorCode::InvalidPayload as u16,
+        "message": ErrorCode::InvalidPayload.message(),
+        "frameType": frame_type,
+    })
+    .to_string()
+}
+
+/// The [`ErrorCode::MissingAuthFields`] frame listing the absent `fields`.
+pub fn missing_auth_fields(fields: &[&str]) -> String {
+    serde_json::json!({
+        "type": "error",
+        "code": ErrorCode::MissingAuthFields as u16,
+        "message": ErrorCode::MissingAuthFields.message(),
+        "fields": fields,
+    })
+    .to_string()
+}
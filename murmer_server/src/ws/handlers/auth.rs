@@ -1,12 +1,17 @@
 //! Authentication handlers for user and bot presence.
 
-use crate::ws::{constants::*, errors, helpers::*};
-use crate::{AppState, bot, db, security};
+use crate::security::{self, ProofError};
+use crate::ws::{
+    constants::*,
+    encoding::history_compression_requested,
+    errors,
+    helpers::*,
+    inbound::{BotPresenceMsg, PresenceMsg},
+    validation,
+};
+use crate::{AppState, bot, db};
 use axum::extract::ws::{Message, WebSocket};
-use base64::{Engine as _, engine::general_purpose};
-use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use futures::stream::SplitSink;
-use serde_json::Value;
 use std::sync::Arc;
 use subtle::ConstantTimeEq;
 use tracing::error;
@@ -18,13 +23,13 @@ use tracing::error;
 async fn verify_key_proof(
     sender: &mut SplitSink<WebSocket, Message>,
     state: &Arc<AppState>,
-    v: &Value,
+    m: &PresenceMsg,
     client_ip: &str,
 ) -> Result<(), ()> {
     let (Some(pk), Some(sig), Some(ts)) = (
-        v.get("publicKey").and_then(|p| p.as_str()),
-        v.get("signature").and_then(|s| s.as_str()),
-        v.get("timestamp").and_then(|t| t.as_str()),
+        m.public_key.as_deref(),
+        m.signature.as_deref(),
+        m.timestamp.as_deref(),
     ) else {
         send_error(sender, errors::INVALID_SIGNATURE).await;
         return Err(());
@@ -35,222 +40,204 @@ async fn verify_key_proof(
         return Err(());
     }
 
-    let timestamp = match security::validate_timestamp(ts) {
-        Ok(ts) => ts,
-        Err(err) => {
-            error!("Authentication failed - {}: {}", err, ts);
-            send_error(sender, errors::INVALID_TIMESTAMP).await;
-            return Err(());
-        }
-    };
-
-    let nonce = format!("{}:{}", pk, timestamp);
-    if !security::check_and_store_nonce(&state.rate_limiter, &nonce).await {
-        send_error(sender, errors::REPLAY_ATTACK).await;
-        return Err(());
-    }
-
-    let (Ok(pk_bytes), Ok(sig_bytes)) = (
-        general_purpose::STANDARD.decode(pk),
-        general_purpose::STANDARD.decode(sig),
-    ) else {
-        error!("Authentication failed - invalid base64 encoding");
-        send_error(sender, errors::INVALID_ENCODING).await;
-        return Err(());
-    };
-
-    let Ok(pk_array) = pk_bytes.as_slice().try_into() else {
-        error!(
-            "Authentication failed - public key wrong length: {}",
-            pk_bytes.len()
-        );
-        send_error(sender, errors::INVALID_KEY_LENGTH).await;
-        return Err(());
+    // The presence proof signs the bare timestamp.
+    let Err(err) =
+        security::verify_signed_proof(&state.rate_limiter, pk, sig, ts, ts, client_ip).await
+    else {
+        return Ok(());
     };
-
-    let key = match VerifyingKey::from_bytes(&pk_array) {
-        Ok(key) => key,
-        Err(e) => {
-            error!("Authentication failed - invalid public key: {}", e);
-            send_error(sender, errors::INVALID_PUBLIC_KEY).await;
-            return Err(());
+    let timestamp_reply;
+    let reply = match err {
+        ProofError::InvalidTimestamp => {
+            timestamp_reply =
+                errors::invalid_timestamp(security::get_auth_timestamp_window_seconds());
+            &timestamp_reply
         }
+        ProofError::Replay => errors::REPLAY_ATTACK,
+        ProofError::InvalidEncoding => errors::INVALID_ENCODING,
+        ProofError::InvalidKeyLength => errors::INVALID_KEY_LENGTH,
+        ProofError::InvalidPublicKey => errors::INVALID_PUBLIC_KEY,
+        ProofError::InvalidSignatureFormat => errors::INVALID_SIGNATURE_FORMAT,
+        ProofError::InvalidSignature => errors::INVALID_SIGNATURE,
     };
-
-    let signature = match Signature::from_slice(&sig_bytes) {
-        Ok(signature) => signature,
-        Err(e) => {
-            error!("Authentication failed - invalid signature format: {}", e);
-            send_error(sender, errors::INVALID_SIGNATURE_FORMAT).await;
-            return Err(());
-        }
-    };
-
-    if key.verify(ts.as_bytes(), &signature).is_err() {
-        error!(
-            "Authentication failed - signature verification failed for key: {}",
-            pk
-        );
-        send_error(sender, errors::INVALID_SIGNATURE).await;
-        return Err(());
-    }
-
-    Ok(())
+    send_error(sender, reply).await;
+    Err(())
 }
 
 /// Handle user presence (authentication) message.
 pub(super) async fn handle_presence(
     sender: &mut SplitSink<WebSocket, Message>,
     state: &Arc<AppState>,
-    v: &mut Value,
+    m: &PresenceMsg,
     authenticated: &mut bool,
     user_name: &mut Option<String>,
     client_ip: &str,
     default_channel_id: i32,
 ) -> Result<(), ()> {
     if !*authenticated && let Some(required) = &state.password {
-        let provided = v.get("password").and_then(|p| p.as_str()).unwrap_or("");
+        let provided = m.password.as_deref().unwrap_or("");
         if !bool::from(provided.as_bytes().ct_eq(required.as_bytes())) {
             send_error(sender, errors::INVALID_PASSWORD).await;
             return Err(());
         }
     }
 
+    // Check for every field authentication needs up front, so an incomplete
+    // frame gets one clear answer instead of depending on which branch below
+    // it happens to reach. The socket stays open for a corrected retry.
+    let key_required = !*authenticated && state.password.is_some();
+    let u = match validation::presence_user(m, key_required) {
+        Ok(u) => u,
+        Err(missing) => {
+            send_error(sender, &errors::missing_auth_fields(&missing)).await;
+            return Ok(());
+        }
+    };
+
     // A claimed public key must always be proven, even on an open server or a
     // repeated presence frame: roles, bans and moderation identity attach to
-    // the key, so accepting it unverified would allow impersonation.
-    let verified_key = if v.get("publicKey").is_some() {
-        verify_key_proof(sender, state, v, client_ip).await?;
-        *authenticated = true;
-        v.get("publicKey")
-            .and_then(|p| p.as_str())
-            .map(str::to_string)
+    // the key, so accepting it unverified would allow impersonation. Without
+    // a key there is nothing to verify; servers without a password accept
+    // the connection as an anonymous (role-less) user.
+    // Keys sent in hex are stored in the canonical base64 form so bindings,
+    // roles and bans match whichever encoding the client uses.
+    let verified_key = if let Some(pk) = m.public_key.as_deref() {
+        verify_key_proof(sender, state, m, client_ip).await?;
+        security::canonical_public_key(pk)
     } else {
-        // Without a key there is nothing to verify; servers without a
-        // password accept the connection as an anonymous (role-less) user.
-        if state.password.is_none() {
-            *authenticated = true;
-        }
         None
     };
+    *authenticated = true;
 
-    if *authenticated {
-        if let Some(u) = v.get("user").and_then(|u| u.as_str()) {
-            if !security::validate_user_name(u) {
-                error!("Invalid user name: {}", u);
-                send_error(sender, errors::INVALID_USERNAME).await;
-                return Err(());
-            }
+    if !security::validate_user_name(u) {
+        error!("Invalid user name: {}", u);
+        send_error(sender, errors::INVALID_USERNAME).await;
+        return Err(());
+    }
 
-            // A user name stays permanently bound to the first verified
-            // public key that used it (persisted in the database).
-            // Reconnecting with the same key is fine, but any other key — or
-            // no key at all — may not take the name over: roles attach to
-            // names in memory, so a takeover would let the new connection
-            // inherit the previous owner's privileges. The operator can
-            // release a binding with the `unbind-name` CLI subcommand.
-            match db::get_user_key(&state.db, u).await {
-                Ok(Some(bound)) if verified_key.as_deref() != Some(bound.as_str()) => {
-                    error!("Rejected presence for {u}: name is bound to another key");
-                    send_error(sender, errors::USERNAME_TAKEN).await;
-                    return Err(());
-                }
-                Ok(_) => {}
-                Err(e) => {
-                    error!("Failed to check name binding for {u}: {e}");
-                }
-            }
+    // A user name stays permanently bound to the first verified
+    // public key that used it (persisted in the database).
+    // Reconnecting with the same key is fine, but any other key — or
+    // no key at all — may not take the name over: roles attach to
+    // names in memory, so a takeover would let the new connection
+    // inherit the previous owner's privileges. The operator can
+    // release a binding with the `unbind-name` CLI subcommand.
+    match db::get_user_key(&state.db, u).await {
+        Ok(Some(bound)) if verified_key.as_deref() != Some(bound.as_str()) => {
+            error!("Rejected presence for {u}: name is bound to another key");
+            send_error(sender, errors::USERNAME_TAKEN).await;
+            return Err(());
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to check name binding for {u}: {e}");
+        }
+    }
 
-            // Reject banned users before they are registered as present.
-            match db::is_banned(&state.db, verified_key.as_deref(), u).await {
-                Ok(true) => {
-                    error!("Rejected banned user: {}", u);
-                    send_error(sender, errors::BANNED).await;
-                    return Err(());
-                }
-                Ok(false) => {}
-                Err(e) => {
-                    error!("Failed to check ban state for {u}: {e}");
-                }
-            }
+    // Reject banned users before they are registered as present.
+    match db::is_banned(&state.db, verified_key.as_deref(), u).await {
+        Ok(true) => {
+            error!("Rejected banned user: {}", u);
+            send_error(sender, errors::BANNED).await;
+            return Err(());
+        }
+        Ok(false) => {}
+        Err(e) => {
+            error!("Failed to check ban state for {u}: {e}");
+        }
+    }
 
-            // Claim the name for this key (no-op when already bound). A newly
-            // created binding marks a first-time member, who receives the
-            // configured welcome message below. Anonymous connections (no
-            // key) have no persistent identity, so they never trigger it.
-            let mut first_connection = false;
-            if let Some(pk) = verified_key.as_deref() {
-                match db::bind_user_key(&state.db, u, pk).await {
-                    Ok(newly_bound) => first_connection = newly_bound,
-                    Err(e) => error!("Failed to persist name binding for {u}: {e}"),
-                }
-            }
+    // Claim the name for this key (no-op when already bound). A newly
+    // created binding marks a first-time member, who receives the
+    // configured welcome message below. Anonymous connections (no
+    // key) have no persistent identity, so they never trigger it.
+    let mut first_connection = false;
+    if let Some(pk) = verified_key.as_deref() {
+        match db::bind_user_key(&state.db, u, pk).await {
+            Ok(newly_bound) => first_connection = newly_bound,
+            Err(e) => error!("Failed to persist name binding for {u}: {e}"),
+        }
+    }
 
-            state.users.lock().await.insert(u.to_string());
-            state.known_users.lock().await.insert(u.to_string());
-            state
-                .statuses
-                .lock()
-                .await
-                .insert(u.to_string(), "online".to_string());
+    claim_connection(state, user_name.as_deref(), u).await;
+    state.users.lock().await.insert(u.to_string());
+    state.known_users.write().await.insert(u.to_string());
+    state
+        .statuses
+        .write()
+        .await
+        .insert(u.to_string(), "online".to_string());
 
-            broadcast_status(state, u, "online").await;
-            broadcast_users(state).await;
-            *user_name = Some(u.to_string());
+    broadcast_status(state, u, "online").await;
+    broadcast_users(state).await;
+    *user_name = Some(u.to_string());
 
-            if let Some(pk) = verified_key.as_deref() {
-                state
-                    .user_keys
-                    .lock()
-                    .await
-                    .insert(u.to_string(), pk.to_string());
+    if let Some(pk) = verified_key.as_deref() {
+        state
+            .user_keys
+            .lock()
+            .await
+            .insert(u.to_string(), pk.to_string());
 
-                // Load this key's role assignments from the database (the
-                // source of truth) into memory and announce them. An empty set
-                // also covers roles revoked while the user was offline.
-                let role_ids = db::get_user_role_ids(&state.db, pk)
-                    .await
-                    .unwrap_or_default();
-                state
-                    .user_roles
-                    .lock()
-                    .await
-                    .insert(u.to_string(), role_ids.clone());
-                broadcast_user_roles(state, u, &role_ids).await;
-            }
+        // Load this key's role assignments from the database (the
+        // source of truth) into memory and announce them. An empty set
+        // also covers roles revoked while the user was offline.
+        let role_ids = db::get_user_role_ids(&state.db, pk)
+            .await
+            .unwrap_or_default();
+        state
+            .user_roles
+            .write()
+            .await
+            .insert(u.to_string(), role_ids.clone());
+        broadcast_user_roles(state, u, &role_ids).await;
+    }
 
-            send_role_definitions(state, sender).await;
-            send_all_user_roles(state, sender).await;
-            send_all_statuses(state, sender).await;
-            super::profile::send_all_avatars(state, sender).await;
-            send_categories(state, sender).await;
-            send_channels(state, sender, Some(u)).await;
-            send_emojis(state, sender).await;
-            send_voice_channels(state, sender, Some(u)).await;
-            send_users(state, sender).await;
-            send_all_voice(state, sender).await;
-            super::identity::send_server_identity(state, sender).await;
-            if first_connection {
-                super::identity::send_welcome(state, sender).await;
-            }
-            super::stats::send_stats_config(state, sender, u).await;
-            super::screenshare::send_screenshare_config(state, sender).await;
-            db::send_history(
-                &state.db,
-                sender,
-                default_channel_id,
-                None,
-                DEFAULT_HISTORY_LIMIT,
-            )
-            .await;
-            // The connection starts in the default channel without an
-            // explicit join, so its wiki snapshot has to be sent here.
-            super::wiki::send_wiki_index(state, sender, default_channel_id).await;
-        }
+    let bundled = m.options.init_state;
+    if bundled {
+        send_init_state(
+            state,
+            sender,
+            Some(u),
+            default_channel_id,
+            history_compression_requested(&m.options),
+        )
+        .await;
     } else {
-        send_error(sender, errors::INVALID_SIGNATURE).await;
-        return Err(());
+        send_role_definitions(state, sender).await;
+        send_all_user_roles(state, sender).await;
+        send_all_statuses(state, sender).await;
+        send_categories(state, sender).await;
+        send_channels(state, sender, Some(u)).await;
+        send_voice_channels(state, sender, Some(u)).await;
+        send_users(state, sender).await;
+        send_all_voice(state, sender).await;
     }
+    super::profile::send_all_avatars(state, sender).await;
+    super::profile::send_all_profiles(state, sender).await;
+    send_emojis(state, sender).await;
+    send_allowed_reactions(state, sender).await;
+    super::identity::send_server_identity(state, sender).await;
+    send_active_announcement(state, sender).await;
+    if first_connection {
+        super::identity::send_welcome(state, sender).await;
+    }
+    super::stats::send_stats_config(state, sender, u).await;
+    super::screenshare::send_screenshare_config(state, sender).await;
+    if !bundled {
+        db::send_history(
+            &state.db,
+            sender,
+            default_channel_id,
+            None,
+            DEFAULT_HISTORY_LIMIT,
+            history_compression_requested(&m.options),
+        )
+        .await;
+    }
+    // The connection starts in the default channel without an
+    // explicit join, so its wiki snapshot has to be sent here.
+    super::wiki::send_wiki_index(state, sender, default_channel_id).await;
 
     Ok(())
 }
@@ -259,12 +246,12 @@ pub(super) async fn handle_presence(
 pub(super) async fn handle_bot_presence(
     sender: &mut SplitSink<WebSocket, Message>,
     state: &Arc<AppState>,
-    v: &Value,
+    m: &BotPresenceMsg,
     authenticated: &mut bool,
     user_name: &mut Option<String>,
     default_channel_id: i32,
 ) -> Result<(), ()> {
-    let token = match v.get("token").and_then(|t| t.as_str()) {
+    let token = match m.token.as_deref() {
         Some(t) => t,
         None => {
             send_error(sender, errors::MISSING_BOT_TOKEN).await;
@@ -284,11 +271,12 @@ pub(super) async fn handle_bot_presence(
     *authenticated = true;
     let bot_name = record.name.clone();
 
+    claim_connection(state, user_name.as_deref(), &bot_name).await;
     state.users.lock().await.insert(bot_name.clone());
-    state.known_users.lock().await.insert(bot_name.clone());
+    state.known_users.write().await.insert(bot_name.clone());
     state
         .statuses
-        .lock()
+        .write()
         .await
         .insert(bot_name.clone(), "online".to_string());
 
@@ -296,24 +284,41 @@ pub(super) async fn handle_bot_presence(
     broadcast_users(state).await;
     *user_name = Some(bot_name);
 
-    send_role_definitions(state, sender).await;
-    send_all_user_roles(state, sender).await;
-    send_all_statuses(state, sender).await;
+    let bundled = m.options.init_state;
+    if bundled {
+        send_init_state(
+            state,
+            sender,
+            user_name.as_deref(),
+            default_channel_id,
+            history_compression_requested(&m.options),
+        )
+        .await;
+    } else {
+        send_role_definitions(state, sender).await;
+        send_all_user_roles(state, sender).await;
+        send_all_statuses(state, sender).await;
+        send_channels(state, sender, user_name.as_deref()).await;
+        send_voice_channels(state, sender, user_name.as_deref()).await;
+        send_users(state, sender).await;
+        send_all_voice(state, sender).await;
+    }
     super::profile::send_all_avatars(state, sender).await;
-    send_channels(state, sender, user_name.as_deref()).await;
+    super::profile::send_all_profiles(state, sender).await;
     send_emojis(state, sender).await;
-    send_voice_channels(state, sender, user_name.as_deref()).await;
-    send_users(state, sender).await;
-    send_all_voice(state, sender).await;
+    send_allowed_reactions(state, sender).await;
     super::identity::send_server_identity(state, sender).await;
-    db::send_history(
-        &state.db,
-        sender,
-        default_channel_id,
-        None,
-        DEFAULT_HISTORY_LIMIT,
-    )
-    .await;
+    if !bundled {
+        db::send_history(
+            &state.db,
+            sender,
+            default_channel_id,
+            None,
+            DEFAULT_HISTORY_LIMIT,
+            history_compression_requested(&m.options),
+        )
+        .await;
+    }
     super::wiki::send_wiki_index(state, sender, default_channel_id).await;
 
     Ok(())
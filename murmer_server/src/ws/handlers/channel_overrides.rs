@@ -185,6 +185,16 @@ pub(super) async fn handle_set_channel_override(
         }
     };
 
+    // The default channel is the landing channel for everyone and must stay
+    // public.
+    if target_type == "everyone"
+        && deny & permissions::VIEW_CHANNELS != 0
+        && is_default_channel(state, kind, channel_id).await
+    {
+        send_error(sender, errors::INVALID_CHANNEL_OVERRIDE).await;
+        return;
+    }
+
     // An empty override is a removal.
     if allow == 0 && deny == 0 {
         remove_and_notify(state, sender, kind, channel_id, target_type, &target_id).await;
@@ -211,6 +221,100 @@ pub(super) async fn handle_set_channel_override(
     send_channel_overrides(state, sender, kind, channel_id).await;
 }
 
+/// Whether a channel reference points at the configured default text
+/// channel, which always stays public.
+async fn is_default_channel(state: &Arc<AppState>, kind: ChannelKind, channel_id: i32) -> bool {
+    kind == ChannelKind::Text
+        && db::get_channel_by_id(&state.db, channel_id)
+            .await
+            .is_some_and(|record| record.name == crate::config::default_channel())
+}
+
+/// Resolve the `user` field of a membership frame to a name + public key.
+async fn member_target(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+) -> Option<(String, String)> {
+    let Some(user) = v.get("user").and_then(|u| u.as_str()) else {
+        send_error(sender, errors::INVALID_CHANNEL_OVERRIDE).await;
+        return None;
+    };
+    let Some(key) = lookup_user_key(state, user).await else {
+        send_error(sender, errors::OVERRIDE_TARGET_NOT_FOUND).await;
+        return None;
+    };
+    Some((user.to_string(), key))
+}
+
+/// Handle `invite-to-channel`: add a user to a channel's member list, i.e.
+/// grant them View + Write/Talk through a user override. Membership only
+/// matters for private channels, but inviting to a public one is harmless.
+pub(super) async fn handle_invite_to_channel(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let Some(_requester) = require_channel_manager(state, sender, user_name).await else {
+        return;
+    };
+    let Some((kind, channel_id)) = channel_ref(v) else {
+        send_error(sender, errors::INVALID_CHANNEL_OVERRIDE).await;
+        return;
+    };
+    if is_default_channel(state, kind, channel_id).await {
+        send_error(sender, errors::INVALID_CHANNEL_OVERRIDE).await;
+        return;
+    }
+    let Some((user, key)) = member_target(state, sender, v).await else {
+        return;
+    };
+    if !store_override(
+        state,
+        kind,
+        channel_id,
+        "user",
+        &key,
+        &user,
+        permissions::CHANNEL_OVERRIDABLE,
+        0,
+    )
+    .await
+    {
+        send_error(sender, errors::CHANNEL_OVERRIDE_FAILED).await;
+        return;
+    }
+    broadcast_channels_refresh(state).await;
+    send_channel_overrides(state, sender, kind, channel_id).await;
+}
+
+/// Handle `remove-from-channel`: drop a user's membership override. On a
+/// private channel this hides it from them again unless a role still grants
+/// View.
+pub(super) async fn handle_remove_from_channel(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let Some(_requester) = require_channel_manager(state, sender, user_name).await else {
+        return;
+    };
+    let Some((kind, channel_id)) = channel_ref(v) else {
+        send_error(sender, errors::INVALID_CHANNEL_OVERRIDE).await;
+        return;
+    };
+    if is_default_channel(state, kind, channel_id).await {
+        send_error(sender, errors::INVALID_CHANNEL_OVERRIDE).await;
+        return;
+    }
+    let Some((_user, key)) = member_target(state, sender, v).await else {
+        return;
+    };
+    remove_and_notify(state, sender, kind, channel_id, "user", &key).await;
+}
+
 /// Handle `remove-channel-override`.
 pub(super) async fn handle_remove_channel_override(
     state: &Arc<AppState>,
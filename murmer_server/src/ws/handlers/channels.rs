@@ -1,26 +1,53 @@
 //! Handlers for text channel, voice channel and category management.
 
 use crate::channel_overrides::ChannelKind;
-use crate::ws::{constants::*, errors, helpers::*, validation::*};
+use crate::ws::{constants::*, errors, helpers::*, inbound::*, validation::*};
 use crate::{AppState, VoiceChannelState, db, security};
 use axum::extract::ws::{Message, WebSocket};
 use futures::stream::SplitSink;
-use serde_json::Value;
 use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::error;
 
+/// Whether `requester` may create another channel: the server must be below
+/// `MAX_CHANNELS` (text and voice together) and the requester within the
+/// per-minute creation limit. Sends the matching error when not.
+async fn may_create_channel(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    requester: &str,
+) -> bool {
+    let max_channels = security::get_max_channels();
+    if max_channels > 0 {
+        let text = match db::count_channels(&state.db).await {
+            Ok(count) => count as usize,
+            Err(e) => {
+                error!("db count channels error: {e}");
+                send_error(sender, errors::CHANNEL_CREATION_FAILED).await;
+                return false;
+            }
+        };
+        let voice = state.voice_channels.read().await.len();
+        if text + voice >= max_channels {
+            send_error(sender, errors::CHANNEL_LIMIT_REACHED).await;
+            return false;
+        }
+    }
+    if !security::check_channel_creation_rate_limit(&state.rate_limiter, requester).await {
+        send_error(sender, errors::CHANNEL_RATE_LIMIT).await;
+        return false;
+    }
+    true
+}
+
 /// Handle create channel request.
 pub(super) async fn handle_create_channel(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &CreateChannelMsg,
     user_name: &Option<String>,
 ) {
-    let Some(ch) = v.get("name").and_then(|c| c.as_str()) else {
-        return;
-    };
-
+    let ch = m.name.as_str();
     if !security::validate_channel_name(ch) {
         error!("Invalid channel name: {}", ch);
         send_error(sender, errors::INVALID_CHANNEL_NAME).await;
@@ -42,14 +69,13 @@ pub(super) async fn handle_create_channel(
         return;
     }
 
-    let category_id = v
-        .get("categoryId")
-        .and_then(|c| c.as_i64())
-        .map(|id| id as i32);
+    if !may_create_channel(state, sender, requester).await {
+        return;
+    }
 
-    let private = v.get("private").and_then(|p| p.as_bool()).unwrap_or(false);
+    let private = m.private;
 
-    match db::add_channel(&state.db, ch, category_id).await {
+    match db::add_channel(&state.db, ch, m.category_id).await {
         Ok(Some(record)) => {
             get_or_create_channel(state, record.id).await;
             // Seed the private overrides before announcing so the channel-add
@@ -64,6 +90,13 @@ pub(super) async fn handle_create_channel(
                 .await;
             }
             broadcast_new_channel(state, &record).await;
+            record_audit(
+                state,
+                requester,
+                "create-channel",
+                Some(&record.name),
+                serde_json::json!({ "channelId": record.id, "private": private }),
+            );
             if private {
                 broadcast_channels_refresh(state).await;
             }
@@ -83,29 +116,22 @@ pub(super) async fn handle_create_channel(
 pub(super) async fn handle_delete_channel(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: ChannelTarget,
     user_name: &Option<String>,
     channel_id: &mut i32,
     chan_tx: &mut tokio::sync::broadcast::Sender<String>,
     chan_rx: &mut tokio::sync::broadcast::Receiver<String>,
+    chan_sub: &mut ChannelSubscription,
     default_channel_id: i32,
-) -> Result<(), ()> {
-    let Some(ch_id) = v
-        .get("channelId")
-        .and_then(|c| c.as_i64())
-        .map(|c| c as i32)
-    else {
-        return Ok(());
-    };
-
-    let record = match db::get_channel_by_id(&state.db, ch_id).await {
-        Some(r) => r,
-        None => return Ok(()),
+) {
+    let ch_id = m.channel_id;
+    let Some(record) = db::get_channel_by_id(&state.db, ch_id).await else {
+        return;
     };
 
-    if record.name == "general" {
+    if record.name == crate::config::default_channel() {
         send_error(sender, errors::CANNOT_DELETE_GENERAL).await;
-        return Err(());
+        return;
     }
 
     let requester = match user_name.as_deref() {
@@ -113,14 +139,14 @@ pub(super) async fn handle_delete_channel(
         None => {
             error!("delete-channel requested before presence was fully processed");
             send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
-            return Err(());
+            return;
         }
     };
 
     if !has_permission(state, requester, crate::permissions::MANAGE_CHANNELS).await {
         error!("User {requester} attempted to delete channel without permission");
         send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
-        return Err(());
+        return;
     }
 
     match db::remove_channel(&state.db, ch_id).await {
@@ -132,31 +158,35 @@ pub(super) async fn handle_delete_channel(
             super::channel_overrides::cleanup_channel(state, ChannelKind::Text, ch_id).await;
             state.channels.lock().await.remove(&ch_id);
             broadcast_remove_channel(state, ch_id).await;
+            record_audit(
+                state,
+                requester,
+                "delete-channel",
+                Some(&record.name),
+                serde_json::json!({ "channelId": ch_id }),
+            );
             if *channel_id == ch_id {
                 *channel_id = default_channel_id;
                 *chan_tx = get_or_create_channel(state, *channel_id).await;
                 *chan_rx = chan_tx.subscribe();
+                *chan_sub = ChannelSubscription::acquire_unbounded(state, *channel_id);
             }
         }
     }
-
-    Ok(())
 }
 
-/// Handle move channel to a category (or remove from category).
-pub(super) async fn handle_move_channel(
+/// Handle `rename-channel`. Only the name changes; history, pins and
+/// settings stay with the channel id.
+pub(super) async fn handle_rename_channel(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &RenameChannelMsg,
     user_name: &Option<String>,
 ) {
-    let Some(ch_id) = v
-        .get("channelId")
-        .and_then(|c| c.as_i64())
-        .map(|c| c as i32)
-    else {
+    if !security::validate_channel_name(&m.name) {
+        send_error(sender, errors::INVALID_CHANNEL_NAME).await;
         return;
-    };
+    }
 
     let requester = match user_name.as_deref() {
         Some(n) => n,
@@ -167,24 +197,72 @@ pub(super) async fn handle_move_channel(
     };
 
     if !has_permission(state, requester, crate::permissions::MANAGE_CHANNELS).await {
+        error!("User {requester} attempted to rename channel without permission");
         send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
         return;
     }
 
-    let category_id = if v.get("categoryId").is_some_and(|c| c.is_null()) {
-        None
-    } else {
-        v.get("categoryId")
-            .and_then(|c| c.as_i64())
-            .map(|i| i as i32)
+    let Some(record) = db::get_channel_by_id(&state.db, m.channel_id).await else {
+        send_error(sender, errors::UNKNOWN_CHANNEL).await;
+        return;
     };
+    if record.name == crate::config::default_channel() {
+        send_error(sender, errors::CANNOT_RENAME_DEFAULT_CHANNEL).await;
+        return;
+    }
 
-    let is_voice = v.get("voice").and_then(|v| v.as_bool()).unwrap_or(false);
+    match db::rename_channel(&state.db, m.channel_id, &m.name).await {
+        Ok(db::RenameChannelResult::Renamed) => {
+            broadcast_rename_channel(state, m.channel_id, &m.name).await;
+            record_audit(
+                state,
+                requester,
+                "rename-channel",
+                Some(&m.name),
+                serde_json::json!({ "channelId": m.channel_id, "from": record.name }),
+            );
+        }
+        Ok(db::RenameChannelResult::NameTaken) => {
+            send_error(sender, errors::CHANNEL_NAME_TAKEN).await;
+        }
+        Ok(db::RenameChannelResult::NotFound) => {
+            send_error(sender, errors::UNKNOWN_CHANNEL).await;
+        }
+        Err(e) => {
+            error!("db rename channel error: {e}");
+            send_error(sender, errors::CHANNEL_RENAME_FAILED).await;
+        }
+    }
+}
+
+/// Handle move channel to a category (or remove from category).
+pub(super) async fn handle_move_channel(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    m: &MoveChannelMsg,
+    user_name: &Option<String>,
+) {
+    let ch_id = m.channel_id;
+    let requester = match user_name.as_deref() {
+        Some(n) => n,
+        None => {
+            send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+            return;
+        }
+    };
+
+    if !has_permission(state, requester, crate::permissions::MANAGE_CHANNELS).await {
+        send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+        return;
+    }
+
+    let category_id = m.category_id;
+    let is_voice = m.voice;
 
     let result = if is_voice {
         match db::move_voice_channel(&state.db, ch_id, category_id).await {
             Ok(Some(position)) => {
-                let mut map = state.voice_channels.lock().await;
+                let mut map = state.voice_channels.write().await;
                 if let Some(entry) = map.get_mut(&ch_id) {
                     entry.category_id = category_id;
                     entry.position = position;
@@ -211,23 +289,11 @@ pub(super) async fn handle_move_channel(
     }
 }
 
-/// Parse a reorder frame's `order` field: a non-empty array of unique channel
-/// or category ids, bounded by [`MAX_REORDER_IDS`].
-fn parse_reorder_ids(v: &Value) -> Option<Vec<i32>> {
-    let list = v.get("order")?.as_array()?;
-    if list.is_empty() || list.len() > MAX_REORDER_IDS {
-        return None;
-    }
-    let mut ids = Vec::with_capacity(list.len());
+/// Check a reorder frame's `order`: a non-empty list of unique channel or
+/// category ids, bounded by [`MAX_REORDER_IDS`].
+fn valid_reorder_ids(ids: &[i32]) -> bool {
     let mut seen = HashSet::new();
-    for entry in list {
-        let id = i32::try_from(entry.as_i64()?).ok()?;
-        if !seen.insert(id) {
-            return None;
-        }
-        ids.push(id);
-    }
-    Some(ids)
+    !ids.is_empty() && ids.len() <= MAX_REORDER_IDS && ids.iter().all(|id| seen.insert(*id))
 }
 
 /// Handle reorder of one category's text or voice channels. The listed
@@ -236,13 +302,14 @@ fn parse_reorder_ids(v: &Value) -> Option<Vec<i32>> {
 pub(super) async fn handle_reorder_channels(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &ReorderMsg,
     user_name: &Option<String>,
 ) {
-    let Some(ids) = parse_reorder_ids(v) else {
+    if !valid_reorder_ids(&m.order) {
         send_error(sender, errors::REORDER_FAILED).await;
         return;
-    };
+    }
+    let ids = &m.order;
 
     let requester = match user_name.as_deref() {
         Some(n) => n,
@@ -258,16 +325,13 @@ pub(super) async fn handle_reorder_channels(
         return;
     }
 
-    let category_id = v
-        .get("categoryId")
-        .and_then(|c| c.as_i64())
-        .map(|i| i as i32);
-    let is_voice = v.get("voice").and_then(|v| v.as_bool()).unwrap_or(false);
+    let category_id = m.category_id;
+    let is_voice = m.voice;
 
     match db::reorder_channels(&state.db, category_id, ids.clone(), is_voice).await {
         Ok(true) => {
             if is_voice {
-                let mut map = state.voice_channels.lock().await;
+                let mut map = state.voice_channels.write().await;
                 for (index, id) in ids.iter().enumerate() {
                     if let Some(entry) = map.get_mut(id) {
                         entry.category_id = category_id;
@@ -275,7 +339,7 @@ pub(super) async fn handle_reorder_channels(
                     }
                 }
             }
-            broadcast_channel_reorder(state, category_id, &ids, is_voice).await;
+            broadcast_channel_reorder(state, category_id, ids, is_voice).await;
         }
         Ok(false) => {
             send_error(sender, errors::REORDER_FAILED).await;
@@ -291,13 +355,14 @@ pub(super) async fn handle_reorder_channels(
 pub(super) async fn handle_reorder_categories(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &ReorderMsg,
     user_name: &Option<String>,
 ) {
-    let Some(ids) = parse_reorder_ids(v) else {
+    if !valid_reorder_ids(&m.order) {
         send_error(sender, errors::REORDER_FAILED).await;
         return;
-    };
+    }
+    let ids = &m.order;
 
     let requester = match user_name.as_deref() {
         Some(n) => n,
@@ -315,7 +380,7 @@ pub(super) async fn handle_reorder_categories(
 
     match db::reorder_categories(&state.db, ids.clone()).await {
         Ok(true) => {
-            broadcast_category_reorder(state, &ids).await;
+            broadcast_category_reorder(state, ids).await;
         }
         Ok(false) => {
             send_error(sender, errors::REORDER_FAILED).await;
@@ -331,21 +396,11 @@ pub(super) async fn handle_reorder_categories(
 pub(super) async fn handle_set_channel_topic(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &ChannelTopicMsg,
     user_name: &Option<String>,
 ) {
-    let Some(ch_id) = v
-        .get("channelId")
-        .and_then(|c| c.as_i64())
-        .map(|c| c as i32)
-    else {
-        return;
-    };
-    let Some(raw_topic) = v.get("topic").and_then(|t| t.as_str()) else {
-        return;
-    };
-
-    let topic = raw_topic.trim();
+    let ch_id = m.channel_id;
+    let topic = m.topic.trim();
     if !validate_channel_topic(topic) {
         send_error(sender, errors::INVALID_CHANNEL_TOPIC).await;
         return;
@@ -379,17 +434,298 @@ pub(super) async fn handle_set_channel_topic(
     }
 }
 
-/// Handle create voice channel request.
-pub(super) async fn handle_create_voice_channel(
+/// Handle `set-channel-retention`: keep a text channel's messages for
+/// `retentionDays` (1 to 3650), or forever when it is `null`.
+pub(super) async fn handle_set_channel_retention(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &ChannelRetentionMsg,
     user_name: &Option<String>,
 ) {
-    let Some(ch) = v.get("name").and_then(|c| c.as_str()) else {
+    let ch_id = m.channel_id;
+    let retention_days = m.retention_days;
+    if retention_days.is_some_and(|days| !validate_retention_days(days)) {
+        send_error(sender, errors::INVALID_RETENTION).await;
         return;
+    }
+
+    let requester = match user_name.as_deref() {
+        Some(n) => n,
+        None => {
+            send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+            return;
+        }
     };
 
+    if !has_permission(state, requester, crate::permissions::MANAGE_CHANNELS).await {
+        error!("User {requester} attempted to set channel retention without permission");
+        send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+        return;
+    }
+
+    match db::set_channel_retention(&state.db, ch_id, retention_days).await {
+        Ok(true) => {
+            let payload = serde_json::json!({
+                "type": "channel-retention",
+                "channelId": ch_id,
+                "retentionDays": retention_days,
+            });
+            let _ = state.tx.send(payload.to_string());
+        }
+        Ok(false) => {
+            send_error(sender, errors::UNKNOWN_CHANNEL).await;
+        }
+        Err(e) => {
+            error!("db set channel retention error: {e}");
+            send_error(sender, errors::RETENTION_UPDATE_FAILED).await;
+        }
+    }
+}
+
+/// Handle `set-slow-mode`: make members wait `seconds` (0 to 21600) between
+/// posts in a text channel; 0 turns slow mode off. Existing cooldowns in the
+/// channel are cleared so the new interval applies from the next post.
+pub(super) async fn handle_set_slow_mode(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    m: &SlowModeMsg,
+    user_name: &Option<String>,
+) {
+    let (ch_id, seconds) = (m.channel_id, m.seconds);
+    if !validate_slow_mode_seconds(seconds) {
+        send_error(sender, errors::INVALID_SLOW_MODE).await;
+        return;
+    }
+
+    let requester = match user_name.as_deref() {
+        Some(n) => n,
+        None => {
+            send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+            return;
+        }
+    };
+
+    if !has_permission(state, requester, crate::permissions::MANAGE_CHANNELS).await {
+        error!("User {requester} attempted to set slow mode without permission");
+        send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+        return;
+    }
+
+    match db::set_channel_slow_mode(&state.db, ch_id, seconds).await {
+        Ok(true) => {
+            state
+                .slow_mode_posts
+                .lock()
+                .await
+                .retain(|(_, channel), _| *channel != ch_id);
+            let payload = serde_json::json!({
+                "type": "slow-mode-update",
+                "channelId": ch_id,
+                "seconds": seconds,
+            });
+            let _ = state.tx.send(payload.to_string());
+            record_audit(
+                state,
+                requester,
+                "set-slow-mode",
+                Some(&ch_id.to_string()),
+                serde_json::json!({ "channelId": ch_id, "seconds": seconds }),
+            );
+        }
+        Ok(false) => {
+            send_error(sender, errors::UNKNOWN_CHANNEL).await;
+        }
+        Err(e) => {
+            error!("db set slow mode error: {e}");
+            send_error(sender, errors::SLOW_MODE_UPDATE_FAILED).await;
+        }
+    }
+}
+
+/// Handle `set-channel-receipts`: turn read receipts for a text channel on or
+/// off (`enabled`). Off by default, since every read in a busy channel would
+/// otherwise fan out to its senders.
+pub(super) async fn handle_set_channel_receipts(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    m: &ChannelReceiptsMsg,
+    user_name: &Option<String>,
+) {
+    let (ch_id, enabled) = (m.channel_id, m.enabled);
+
+    let requester = match user_name.as_deref() {
+        Some(n) => n,
+        None => {
+            send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+            return;
+        }
+    };
+
+    if !has_permission(state, requester, crate::permissions::MANAGE_CHANNELS).await {
+        error!("User {requester} attempted to set read receipts without permission");
+        send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+        return;
+    }
+
+    match db::set_channel_receipts(&state.db, ch_id, enabled).await {
+        Ok(true) => {
+            let payload = serde_json::json!({
+                "type": "channel-receipts",
+                "channelId": ch_id,
+                "enabled": enabled,
+            });
+            let _ = state.tx.send(payload.to_string());
+            record_audit(
+                state,
+                requester,
+                "set-channel-receipts",
+                Some(&ch_id.to_string()),
+                serde_json::json!({ "channelId": ch_id, "enabled": enabled }),
+            );
+        }
+        Ok(false) => {
+            send_error(sender, errors::UNKNOWN_CHANNEL).await;
+        }
+        Err(e) => {
+            error!("db set channel receipts error: {e}");
+            send_error(sender, errors::RECEIPTS_UPDATE_FAILED).await;
+        }
+    }
+}
+
+/// Handle `set-channel-max-subscribers`: cap how many connections may have a
+/// text channel open at once (`max`, 1 to 10000), or lift the cap when it is
+/// `null`. Only later joins are refused; connections already in the channel
+/// stay even if they now exceed the cap.
+pub(super) async fn handle_set_channel_max_subscribers(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    m: &ChannelMaxSubscribersMsg,
+    user_name: &Option<String>,
+) {
+    let (ch_id, max) = (m.channel_id, m.max);
+    if max.is_some_and(|max| !validate_max_subscribers(max)) {
+        send_error(sender, errors::INVALID_SUBSCRIBER_LIMIT).await;
+        return;
+    }
+
+    let requester = match user_name.as_deref() {
+        Some(n) => n,
+        None => {
+            send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+            return;
+        }
+    };
+
+    if !has_permission(state, requester, crate::permissions::MANAGE_CHANNELS).await {
+        error!("User {requester} attempted to set a subscriber limit without permission");
+        send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+        return;
+    }
+
+    match db::set_channel_max_subscribers(&state.db, ch_id, max).await {
+        Ok(true) => {
+            let payload = serde_json::json!({
+                "type": "channel-max-subscribers",
+                "channelId": ch_id,
+                "max": max,
+            });
+            let _ = state.tx.send(payload.to_string());
+            record_audit(
+                state,
+                requester,
+                "set-channel-max-subscribers",
+                Some(&ch_id.to_string()),
+                serde_json::json!({ "channelId": ch_id, "max": max }),
+            );
+        }
+        Ok(false) => {
+            send_error(sender, errors::UNKNOWN_CHANNEL).await;
+        }
+        Err(e) => {
+            error!("db set channel max subscribers error: {e}");
+            send_error(sender, errors::SUBSCRIBER_LIMIT_UPDATE_FAILED).await;
+        }
+    }
+}
+
+/// Handle `set-channel-post-roles`: restrict posting in a text channel to the
+/// roles in `roleIds` (administrators can always post), or lift the
+/// restriction when it is `null`. Everyone who can see the channel can still
+/// read it.
+pub(super) async fn handle_set_channel_post_roles(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    m: &ChannelPostRolesMsg,
+    user_name: &Option<String>,
+) {
+    let ch_id = m.channel_id;
+    let role_ids = match &m.role_ids {
+        None => None,
+        Some(raw) => {
+            let known = {
+                let defs = state.role_defs.read().await;
+                raw.iter().all(|id| defs.contains_key(id))
+            };
+            if !known {
+                send_error(sender, errors::INVALID_POST_ROLES).await;
+                return;
+            }
+            let mut ids = raw.clone();
+            ids.sort_unstable();
+            ids.dedup();
+            Some(ids)
+        }
+    };
+
+    let requester = match user_name.as_deref() {
+        Some(n) => n,
+        None => {
+            send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+            return;
+        }
+    };
+
+    if !has_permission(state, requester, crate::permissions::MANAGE_CHANNELS).await {
+        error!("User {requester} attempted to set channel post roles without permission");
+        send_error(sender, errors::CHANNEL_PERMISSION_DENIED).await;
+        return;
+    }
+
+    match db::set_channel_post_roles(&state.db, ch_id, role_ids.as_deref()).await {
+        Ok(true) => {
+            let payload = serde_json::json!({
+                "type": "channel-post-roles",
+                "channelId": ch_id,
+                "roleIds": role_ids,
+            });
+            let _ = state.tx.send(payload.to_string());
+            record_audit(
+                state,
+                requester,
+                "set-channel-post-roles",
+                Some(&ch_id.to_string()),
+                serde_json::json!({ "channelId": ch_id, "roleIds": role_ids }),
+            );
+        }
+        Ok(false) => {
+            send_error(sender, errors::UNKNOWN_CHANNEL).await;
+        }
+        Err(e) => {
+            error!("db set channel post roles error: {e}");
+            send_error(sender, errors::POST_ROLES_UPDATE_FAILED).await;
+        }
+    }
+}
+
+/// Handle create voice channel request.
+pub(super) async fn handle_create_voice_channel(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    m: &CreateVoiceChannelMsg,
+    user_name: &Option<String>,
+) {
+    let ch = m.name.as_str();
     if !security::validate_channel_name(ch) {
         error!("Invalid voice channel name: {}", ch);
         send_error(sender, errors::INVALID_CHANNEL_NAME).await;
@@ -411,9 +747,9 @@ pub(super) async fn handle_create_voice_channel(
         return;
     }
 
-    let quality_value = v
-        .get("quality")
-        .and_then(|q| q.as_str())
+    let quality_value = m
+        .quality
+        .as_deref()
         .map(str::trim)
         .filter(|q| !q.is_empty())
         .map(str::to_string)
@@ -423,9 +759,9 @@ pub(super) async fn handle_create_voice_channel(
         return;
     }
 
-    let bitrate_value = match v.get("bitrate") {
-        Some(val) if val.is_null() => None,
-        Some(val) => match val.as_i64().and_then(validate_bitrate) {
+    let bitrate_value = match m.bitrate {
+        Some(None) => None,
+        Some(Some(raw)) => match validate_bitrate(raw) {
             Some(valid) => Some(valid),
             None => {
                 send_error(sender, errors::INVALID_VOICE_BITRATE).await;
@@ -435,14 +771,13 @@ pub(super) async fn handle_create_voice_channel(
         None => Some(DEFAULT_VOICE_BITRATE),
     };
 
-    let category_id = v
-        .get("categoryId")
-        .and_then(|c| c.as_i64())
-        .map(|id| id as i32);
+    if !may_create_channel(state, sender, requester).await {
+        return;
+    }
 
-    let private = v.get("private").and_then(|p| p.as_bool()).unwrap_or(false);
+    let private = m.private;
 
-    match db::add_voice_channel(&state.db, ch, &quality_value, bitrate_value, category_id).await {
+    match db::add_voice_channel(&state.db, ch, &quality_value, bitrate_value, m.category_id).await {
         Ok(Some(record)) => {
             let info = VoiceChannelState {
                 name: record.name.clone(),
@@ -454,7 +789,7 @@ pub(super) async fn handle_create_voice_channel(
             };
             state
                 .voice_channels
-                .lock()
+                .write()
                 .await
                 .insert(record.id, info.clone());
             if private {
@@ -467,6 +802,13 @@ pub(super) async fn handle_create_voice_channel(
                 .await;
             }
             broadcast_new_voice_channel(state, record.id, &info).await;
+            record_audit(
+                state,
+                requester,
+                "create-voice-channel",
+                Some(&record.name),
+                serde_json::json!({ "channelId": record.id, "private": private }),
+            );
             if private {
                 broadcast_channels_refresh(state).await;
             }
@@ -482,16 +824,10 @@ pub(super) async fn handle_create_voice_channel(
 pub(super) async fn handle_update_voice_channel(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &UpdateVoiceChannelMsg,
     user_name: &Option<String>,
 ) {
-    let Some(ch_id) = v
-        .get("channelId")
-        .and_then(|c| c.as_i64())
-        .map(|c| c as i32)
-    else {
-        return;
-    };
+    let ch_id = m.channel_id;
 
     let requester = match user_name.as_deref() {
         Some(name) => name,
@@ -508,7 +844,7 @@ pub(super) async fn handle_update_voice_channel(
         return;
     }
 
-    let quality_override = if let Some(raw) = v.get("quality").and_then(|q| q.as_str()) {
+    let quality_override = if let Some(raw) = m.quality.as_deref() {
         let trimmed = raw.trim();
         if !validate_voice_quality(trimmed) {
             send_error(sender, errors::INVALID_VOICE_QUALITY).await;
@@ -519,28 +855,23 @@ pub(super) async fn handle_update_voice_channel(
         None
     };
 
-    let bitrate_override = if let Some(val) = v.get("bitrate") {
-        if val.is_null() {
-            Some(None)
-        } else {
-            match val.as_i64().and_then(validate_bitrate) {
-                Some(valid) => Some(Some(valid)),
-                None => {
-                    send_error(sender, errors::INVALID_VOICE_BITRATE).await;
-                    return;
-                }
+    let bitrate_override = match m.bitrate {
+        Some(Some(raw)) => match validate_bitrate(raw) {
+            Some(valid) => Some(Some(valid)),
+            None => {
+                send_error(sender, errors::INVALID_VOICE_BITRATE).await;
+                return;
             }
-        }
-    } else {
-        None
+        },
+        Some(None) => Some(None),
+        None => None,
     };
 
-    let current = state.voice_channels.lock().await;
-    let Some(existing) = current.get(&ch_id).cloned() else {
+    let current = state.voice_channels.read().await.get(&ch_id).cloned();
+    let Some(existing) = current else {
         send_error(sender, errors::UNKNOWN_VOICE_CHANNEL).await;
         return;
     };
-    drop(current);
 
     let next_quality = quality_override.unwrap_or_else(|| existing.quality.clone());
     let next_bitrate = match bitrate_override {
@@ -550,7 +881,7 @@ pub(super) async fn handle_update_voice_channel(
 
     match db::update_voice_channel(&state.db, ch_id, &next_quality, next_bitrate).await {
         Ok(true) => {
-            let mut map = state.voice_channels.lock().await;
+            let mut map = state.voice_channels.write().await;
             if let Some(entry) = map.get_mut(&ch_id) {
                 entry.quality = next_quality.clone();
                 entry.bitrate = next_bitrate;
@@ -573,17 +904,11 @@ pub(super) async fn handle_update_voice_channel(
 pub(super) async fn handle_delete_voice_channel(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: ChannelTarget,
     user_name: &Option<String>,
     voice_channel: &mut Option<i32>,
 ) {
-    let Some(ch_id) = v
-        .get("channelId")
-        .and_then(|c| c.as_i64())
-        .map(|c| c as i32)
-    else {
-        return;
-    };
+    let ch_id = m.channel_id;
 
     let requester = match user_name.as_deref() {
         Some(name) => name,
@@ -601,13 +926,20 @@ pub(super) async fn handle_delete_voice_channel(
     }
 
     super::channel_overrides::cleanup_channel(state, ChannelKind::Voice, ch_id).await;
-    state.voice_channels.lock().await.remove(&ch_id);
+    let removed = state.voice_channels.write().await.remove(&ch_id);
     if let Err(e) = db::remove_voice_channel(&state.db, ch_id).await {
         // The in-memory removal already happened and is broadcast anyway;
         // log it because the channel would reappear after a restart.
         error!("db remove voice channel error: {e}");
     }
     broadcast_remove_voice_channel(state, ch_id).await;
+    record_audit(
+        state,
+        requester,
+        "delete-voice-channel",
+        removed.as_ref().map(|info| info.name.as_str()),
+        serde_json::json!({ "channelId": ch_id }),
+    );
     if *voice_channel == Some(ch_id) {
         *voice_channel = None;
     }
@@ -617,13 +949,10 @@ pub(super) async fn handle_delete_voice_channel(
 pub(super) async fn handle_create_category(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &CreateCategoryMsg,
     user_name: &Option<String>,
 ) {
-    let Some(name) = v.get("name").and_then(|n| n.as_str()) else {
-        return;
-    };
-
+    let name = m.name.as_str();
     if !security::validate_channel_name(name) {
         send_error(sender, errors::INVALID_CATEGORY_NAME).await;
         return;
@@ -643,9 +972,7 @@ pub(super) async fn handle_create_category(
     }
 
     // Without an explicit position the category is appended at the end.
-    let position = v.get("position").and_then(|p| p.as_i64()).map(|p| p as i32);
-
-    match db::add_category(&state.db, name, position).await {
+    match db::add_category(&state.db, name, m.position).await {
         Ok((id, position)) => {
             broadcast_new_category(state, id, name, position).await;
         }
@@ -660,15 +987,10 @@ pub(super) async fn handle_create_category(
 pub(super) async fn handle_rename_category(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: &RenameCategoryMsg,
     user_name: &Option<String>,
 ) {
-    let Some(id) = v.get("id").and_then(|i| i.as_i64()).map(|i| i as i32) else {
-        return;
-    };
-    let Some(name) = v.get("name").and_then(|n| n.as_str()) else {
-        return;
-    };
+    let (id, name) = (m.id, m.name.as_str());
 
     if !security::validate_channel_name(name) {
         send_error(sender, errors::INVALID_CATEGORY_NAME).await;
@@ -706,12 +1028,10 @@ pub(super) async fn handle_rename_category(
 pub(super) async fn handle_delete_category(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    m: CategoryTarget,
     user_name: &Option<String>,
 ) {
-    let Some(id) = v.get("id").and_then(|i| i.as_i64()).map(|i| i as i32) else {
-        return;
-    };
+    let id = m.id;
 
     let requester = match user_name.as_deref() {
         Some(n) => n,
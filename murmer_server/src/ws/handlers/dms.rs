@@ -11,7 +11,12 @@
 //! [`crate::ws::helpers::dm_involves`]), so other clients never receive the
 //! content over the wire.
 
-use crate::ws::{constants::*, errors, helpers::*};
+use crate::ws::{
+    constants::*,
+    errors,
+    helpers::*,
+    inbound::{DmHistoryMsg, DmMsg, UserKeyMsg},
+};
 use crate::{AppState, db, security};
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, stream::SplitSink};
@@ -24,46 +29,44 @@ use tracing::error;
 pub(super) async fn handle_dm(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &DmMsg,
     user_name: &Option<String>,
 ) {
     let Some(from) = user_name.clone() else {
         return;
     };
 
-    if !security::check_message_rate_limit(&state.rate_limiter, &from).await {
+    if !security::check_message_rate_limit(
+        &state.rate_limiter,
+        &from,
+        &user_role_names(state, &from).await,
+    )
+    .await
+    {
         send_error(sender, errors::MESSAGE_RATE_LIMIT).await;
         return;
     }
 
-    if super::moderation::is_muted(state, &from).await {
+    if is_muted(state, &from).await {
         send_error(sender, errors::MUTED).await;
         return;
     }
 
-    let to = match v.get("to").and_then(|t| t.as_str()).map(str::trim) {
-        Some(to) if !to.is_empty() => to.to_string(),
-        _ => {
-            send_error(sender, errors::DM_TARGET_NOT_FOUND).await;
-            return;
-        }
-    };
+    let to = msg.to.trim();
+    if to.is_empty() {
+        send_error(sender, errors::DM_TARGET_NOT_FOUND).await;
+        return;
+    }
     if to == from {
         send_error(sender, errors::CANNOT_DM_SELF).await;
         return;
     }
-    if !state.known_users.lock().await.contains(&to) {
+    if !state.known_users.read().await.contains(to) {
         send_error(sender, errors::DM_TARGET_NOT_FOUND).await;
         return;
     }
 
-    let (Some(nonce), Some(ciphertext)) = (
-        v.get("nonce").and_then(|n| n.as_str()),
-        v.get("ciphertext").and_then(|c| c.as_str()),
-    ) else {
-        send_error(sender, errors::INVALID_DM_PAYLOAD).await;
-        return;
-    };
+    let (nonce, ciphertext) = (msg.nonce.as_str(), msg.ciphertext.as_str());
     match validate_dm_payload(nonce, ciphertext) {
         Ok(()) => {}
         Err(DmPayloadError::TooLong) => {
@@ -85,17 +88,21 @@ pub(super) async fn handle_dm(
         "nonce": nonce,
         "ciphertext": ciphertext,
     });
-    if let Some(ts) = v.get("timestamp").and_then(|t| t.as_str()) {
-        out["timestamp"] = Value::String(ts.to_string());
+    if let Some(ts) = &msg.timestamp {
+        out["timestamp"] = Value::String(ts.clone());
     }
-    if let Some(time) = v.get("time").and_then(|t| t.as_str()) {
-        out["time"] = Value::String(time.to_string());
+    if let Some(time) = &msg.time {
+        out["time"] = Value::String(time.clone());
     }
     let timestamp = sanitize_message_timestamp(&mut out);
     ensure_time(&mut out, &timestamp);
+    if message_too_large(&out) {
+        send_error(sender, errors::MESSAGE_TOO_LARGE).await;
+        return;
+    }
 
     let content = out.to_string();
-    match db::insert_direct_message(&state.db, &from, &to, &content).await {
+    match db::insert_direct_message(&state.db, &from, to, &content).await {
         Ok(id) => {
             out["id"] = Value::from(id);
             // Delivered via the global broadcast; the socket loop filters the
@@ -117,28 +124,24 @@ pub(super) async fn handle_dm(
 pub(super) async fn handle_load_dm_history(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &DmHistoryMsg,
     user_name: &Option<String>,
 ) {
     let Some(user) = user_name.clone() else {
         return;
     };
-    let peer = match v.get("with").and_then(|w| w.as_str()).map(str::trim) {
-        Some(peer) if !peer.is_empty() => peer.to_string(),
-        _ => {
-            send_error(sender, errors::DM_TARGET_NOT_FOUND).await;
-            return;
-        }
-    };
+    let peer = msg.with.trim();
+    if peer.is_empty() {
+        send_error(sender, errors::DM_TARGET_NOT_FOUND).await;
+        return;
+    }
 
-    let before = v.get("before").and_then(|b| b.as_i64());
-    let limit = v
-        .get("limit")
-        .and_then(|l| l.as_i64())
+    let limit = msg
+        .limit
         .unwrap_or(DEFAULT_HISTORY_LIMIT)
         .clamp(1, MAX_HISTORY_LIMIT);
 
-    match db::fetch_dm_history(&state.db, &user, &peer, before, limit).await {
+    match db::fetch_dm_history(&state.db, &user, peer, msg.before, limit).await {
         Ok(rows) => {
             let mut msgs = Vec::new();
             for (id, content) in rows.into_iter().rev() {
@@ -168,17 +171,17 @@ pub(super) async fn handle_load_dm_history(
 pub(super) async fn handle_get_user_key(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &UserKeyMsg,
 ) {
-    let user = match v.get("user").and_then(|u| u.as_str()).map(str::trim) {
-        Some(user) if !user.is_empty() => user.to_string(),
-        _ => return,
-    };
-    let key = lookup_user_key(state, &user).await;
-    let msg = serde_json::json!({
+    let user = msg.user.trim();
+    if user.is_empty() {
+        return;
+    }
+    let key = lookup_user_key(state, user).await;
+    let reply = serde_json::json!({
         "type": "user-key",
         "user": user,
         "publicKey": key,
     });
-    let _ = sender.send(Message::Text(msg.to_string().into())).await;
+    let _ = sender.send(Message::Text(reply.to_string().into())).await;
 }
@@ -0,0 +1,173 @@
+//! Message flag handlers: users report messages, moderators review them.
+//!
+//! Anyone who can see a message may flag it once, with an optional reason.
+//! Each new flag is announced as a `flag-notice` and each resolution as a
+//! `flag-resolved`; the socket loop delivers both only to connections whose
+//! user holds `MANAGE_MESSAGES`. Moderators fetch the open backlog with
+//! `list-flags` and close out a message's flags with `resolve-flag`.
+
+use crate::channel_overrides::ChannelKind;
+use crate::permissions::MANAGE_MESSAGES;
+use crate::ws::{
+    constants::*,
+    errors,
+    helpers::*,
+    inbound::{FlagMsg, MessageTarget},
+    validation::sanitize_flag_reason,
+};
+use crate::{AppState, db};
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, stream::SplitSink};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Frame types that only moderators receive.
+pub(super) fn moderators_only(frame_type: Option<&str>) -> bool {
+    matches!(frame_type, Some("flag-notice" | "flag-resolved"))
+}
+
+/// Handle `flag-message`: record the requester's report of `messageId` with
+/// an optional `reason` and notify moderators.
+pub(super) async fn handle_flag_message(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    msg: &FlagMsg,
+    user_name: &Option<String>,
+) {
+    let Some(user) = user_name.as_deref() else {
+        send_error(sender, errors::NOT_AUTHENTICATED).await;
+        return;
+    };
+    let message_id = msg.message_id;
+    let Some(reason) = sanitize_flag_reason(&msg.reason) else {
+        send_error(sender, errors::INVALID_FLAG_REASON).await;
+        return;
+    };
+
+    let record = match db::get_message_record(&state.db, message_id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            send_error(sender, errors::FLAG_TARGET_NOT_FOUND).await;
+            return;
+        }
+        Err(e) => {
+            error!("Failed to load message {message_id} for flagging: {e}");
+            send_error(sender, errors::FLAG_FAILED).await;
+            return;
+        }
+    };
+    if !can_view_channel(state, user, ChannelKind::Text, record.channel_id).await {
+        send_error(sender, errors::FLAG_TARGET_NOT_FOUND).await;
+        return;
+    }
+
+    match db::add_flag(&state.db, message_id, user, &reason).await {
+        Ok(true) => {
+            info!(user, message_id, "Message flagged");
+            let ack = serde_json::json!({ "type": "message-flagged", "messageId": message_id });
+            let _ = sender.send(Message::Text(ack.to_string().into())).await;
+            let notice = serde_json::json!({
+                "type": "flag-notice",
+                "messageId": message_id,
+                "channelId": record.channel_id,
+                "reporter": user,
+                "reason": reason,
+                "author": record.content.get("user"),
+                "text": record.content.get("text"),
+            });
+            let _ = state.tx.send(notice.to_string());
+        }
+        Ok(false) => {
+            send_error(sender, errors::ALREADY_FLAGGED).await;
+        }
+        Err(e) => {
+            error!("Failed to flag message {message_id}: {e}");
+            send_error(sender, errors::FLAG_FAILED).await;
+        }
+    }
+}
+
+/// Handle `list-flags`: send a moderator the open flags, oldest first.
+pub(super) async fn handle_list_flags(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    user_name: &Option<String>,
+) {
+    let Some(user) = user_name.as_deref() else {
+        send_error(sender, errors::NOT_AUTHENTICATED).await;
+        return;
+    };
+    if !has_permission(state, user, MANAGE_MESSAGES).await {
+        send_error(sender, errors::FLAG_PERMISSION_DENIED).await;
+        return;
+    }
+
+    match db::list_open_flags(&state.db, MAX_FLAG_LIST, 0).await {
+        Ok(flags) => {
+            let flags: Vec<Value> = flags
+                .into_iter()
+                .map(|f| {
+                    serde_json::json!({
+                        "messageId": f.message_id,
+                        "channelId": f.channel_id,
+                        "reporter": f.reporter,
+                        "reason": f.reason,
+                        "at": f.at,
+                        "author": f.author,
+                        "text": f.text,
+                    })
+                })
+                .collect();
+            let payload = serde_json::json!({ "type": "flag-list", "flags": flags });
+            let _ = sender.send(Message::Text(payload.to_string().into())).await;
+        }
+        Err(e) => {
+            error!("Failed to list flags: {e}");
+            send_error(sender, errors::FLAG_FAILED).await;
+        }
+    }
+}
+
+/// Handle `resolve-flag`: close every open flag on `messageId`.
+pub(super) async fn handle_resolve_flag(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    msg: &MessageTarget,
+    user_name: &Option<String>,
+) {
+    let Some(user) = user_name.as_deref() else {
+        send_error(sender, errors::NOT_AUTHENTICATED).await;
+        return;
+    };
+    if !has_permission(state, user, MANAGE_MESSAGES).await {
+        send_error(sender, errors::FLAG_PERMISSION_DENIED).await;
+        return;
+    }
+    let message_id = msg.message_id;
+
+    match db::resolve_flags(&state.db, message_id, user).await {
+        Ok(0) => {
+            send_error(sender, errors::FLAG_NOT_FOUND).await;
+        }
+        Ok(count) => {
+            let payload = serde_json::json!({
+                "type": "flag-resolved",
+                "messageId": message_id,
+                "resolvedBy": user,
+            });
+            let _ = state.tx.send(payload.to_string());
+            record_audit(
+                state,
+                user,
+                "resolve-flag",
+                Some(&message_id.to_string()),
+                serde_json::json!({ "messageId": message_id, "flags": count }),
+            );
+        }
+        Err(e) => {
+            error!("Failed to resolve flags on message {message_id}: {e}");
+            send_error(sender, errors::FLAG_FAILED).await;
+        }
+    }
+}
@@ -5,8 +5,9 @@
 //! the icon travels through the regular `/upload` endpoint (which enforces
 //! the image safe-list and magic-byte validation) and is registered here by
 //! URL, mirroring custom emojis. The full identity is sent to every client
-//! after authentication and broadcast whenever it changes. Editing requires
-//! an Admin or Owner role, checked server-side against the role map.
+//! after authentication (with the server version, unless hidden) and
+//! broadcast whenever it changes. Editing requires an Admin or Owner role,
+//! checked server-side against the role map.
 
 use crate::ws::{constants::*, errors, helpers::*, validation::*};
 use crate::{AppState, db};
@@ -31,6 +32,9 @@ async fn server_identity_frame(state: &Arc<AppState>) -> Option<String> {
         "description": identity.description,
         "welcomeMessage": identity.welcome_message,
         "icon": identity.icon,
+        // Lets clients warn about servers older than themselves; null when
+        // HIDE_SERVER_VERSION is set.
+        "version": crate::config::server_version(),
     }))
     .ok()
 }
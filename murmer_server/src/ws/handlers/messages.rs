@@ -1,14 +1,24 @@
 //! Handlers for chat messages, message deletion, editing, reactions, history and search.
 
 use crate::channel_overrides::ChannelKind;
-use crate::ws::{constants::*, errors, helpers::*, validation::is_emoji_shortcode};
+use crate::word_filter::Filtered;
+use crate::ws::{
+    constants::*,
+    errors,
+    helpers::*,
+    inbound::{ChatMsg, MarkReadMsg, ReactMsg, ReactionAction, ReplyTarget},
+    validation::{
+        format_permalink, is_emoji_shortcode, is_recognized_reaction, parse_permalink,
+        parse_schedule_time, validate_reaction_emoji,
+    },
+};
 use crate::{AppState, db, security};
 use axum::extract::ws::{Message, WebSocket};
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use futures::{SinkExt, stream::SplitSink};
 use serde_json::{Map, Value};
-use std::sync::Arc;
-use tracing::error;
+use std::{collections::HashMap, sync::Arc};
+use tracing::{error, info};
 
 /// Whether `user` may see a specific text channel, applying per-channel
 /// overrides. Users who cannot see a channel receive no history/pins and are
@@ -21,6 +31,7 @@ async fn can_view_text(state: &Arc<AppState>, user_name: &Option<String>, channe
 }
 
 /// Handle channel join and load initial history.
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn handle_join(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
@@ -28,19 +39,43 @@ pub(super) async fn handle_join(
     channel_id: &mut i32,
     chan_tx: &mut tokio::sync::broadcast::Sender<String>,
     chan_rx: &mut tokio::sync::broadcast::Receiver<String>,
+    chan_sub: &mut ChannelSubscription,
     user_name: &Option<String>,
+    compress_history: bool,
 ) {
     if let Some(ch_id) = v.get("channelId").and_then(|c| c.as_i64()) {
         let ch_id = ch_id as i32;
         // Refuse to switch to a channel the user cannot see, so a non-viewer is
         // never even subscribed to the channel's live broadcast.
         if !can_view_text(state, user_name, ch_id).await {
+            send_error(sender, errors::CHANNEL_ACCESS_DENIED).await;
             return;
         }
+        // Re-joining the open channel keeps its slot; anything else needs a
+        // free one, and taking it releases the slot in the old channel.
+        if ch_id != chan_sub.channel_id() {
+            let max = db::get_channel_by_id(&state.db, ch_id)
+                .await
+                .and_then(|channel| channel.max_subscribers)
+                .and_then(|max| usize::try_from(max).ok());
+            let Some(subscription) = ChannelSubscription::acquire(state, ch_id, max) else {
+                send_error(sender, errors::CHANNEL_FULL).await;
+                return;
+            };
+            *chan_sub = subscription;
+        }
         *channel_id = ch_id;
         *chan_tx = get_or_create_channel(state, *channel_id).await;
         *chan_rx = chan_tx.subscribe();
-        db::send_history(&state.db, sender, *channel_id, None, DEFAULT_HISTORY_LIMIT).await;
+        db::send_history(
+            &state.db,
+            sender,
+            *channel_id,
+            None,
+            DEFAULT_HISTORY_LIMIT,
+            compress_history,
+        )
+        .await;
         super::pins::send_pins(state, sender, *channel_id).await;
         super::wiki::send_wiki_index(state, sender, *channel_id).await;
     }
@@ -53,6 +88,7 @@ pub(super) async fn handle_load_history(
     v: &Value,
     channel_id: i32,
     user_name: &Option<String>,
+    compress_history: bool,
 ) {
     if !can_view_text(state, user_name, channel_id).await {
         return;
@@ -71,7 +107,138 @@ pub(super) async fn handle_load_history(
         );
     }
 
-    db::send_history(&state.db, sender, channel_id, before, limit).await;
+    db::send_history(
+        &state.db,
+        sender,
+        channel_id,
+        before,
+        limit,
+        compress_history,
+    )
+    .await;
+}
+
+/// Send per-channel message counts over the configured activity window
+/// (`CHANNEL_ACTIVITY_WINDOW_HOURS`) so clients can highlight busy channels.
+/// Only channels the requester can see are included.
+pub(super) async fn handle_get_channel_activity(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    user_name: &Option<String>,
+) {
+    let window_hours = crate::config::channel_activity_window_hours();
+    let since = Utc::now() - ChronoDuration::hours(window_hours);
+    let counts = match db::count_messages_since(&state.db, since).await {
+        Ok(counts) => counts,
+        Err(e) => {
+            error!("failed to count channel activity: {e}");
+            return;
+        }
+    };
+
+    let mut channels = Map::new();
+    for (channel_id, count) in counts {
+        if can_view_text(state, user_name, channel_id).await {
+            channels.insert(channel_id.to_string(), Value::from(count));
+        }
+    }
+    let payload = serde_json::json!({
+        "type": "channel-activity",
+        "windowHours": window_hours,
+        "channels": channels,
+    });
+    let _ = sender.send(Message::Text(payload.to_string().into())).await;
+}
+
+/// Handle `get-messages`: return the current content and reactions of a list
+/// of message ids (e.g. reply targets missing from a client's cache). Ids that
+/// do not exist or lie in channels the requester cannot see are reported in
+/// `missing`, without telling the two apart.
+pub(super) async fn handle_get_messages(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let request_id = v.get("requestId").cloned().unwrap_or(Value::Null);
+    let mut ids: Vec<i64> = v
+        .get("ids")
+        .and_then(|ids| ids.as_array())
+        .map(|ids| ids.iter().filter_map(|id| id.as_i64()).collect())
+        .unwrap_or_default();
+    ids.sort_unstable();
+    ids.dedup();
+    ids.truncate(MAX_BULK_FETCH_IDS);
+
+    let rows = match db::fetch_messages_by_ids(&state.db, &ids).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("failed to fetch messages by id: {e}");
+            Vec::new()
+        }
+    };
+
+    let mut visible = HashMap::new();
+    let mut messages = Vec::new();
+    for (id, channel_id, content) in rows {
+        let can_see = match visible.get(&channel_id) {
+            Some(can_see) => *can_see,
+            None => {
+                let can_see = user_can_see_channel(
+                    state,
+                    user_name.as_deref(),
+                    ChannelKind::Text,
+                    channel_id,
+                )
+                .await;
+                visible.insert(channel_id, can_see);
+                can_see
+            }
+        };
+        if !can_see {
+            continue;
+        }
+        if let Ok(mut value) = serde_json::from_str::<Value>(&content) {
+            value["id"] = Value::from(id);
+            if value.get("channelId").is_none() {
+                value["channelId"] = Value::from(channel_id);
+            }
+            messages.push(value);
+        }
+    }
+
+    let found: Vec<i64> = messages
+        .iter()
+        .filter_map(|m| m.get("id").and_then(|id| id.as_i64()))
+        .collect();
+    let reaction_map = match db::get_reactions_for_messages(&state.db, &found).await {
+        Ok(map) => map,
+        Err(e) => {
+            error!("failed to load reactions for fetched messages: {e}");
+            HashMap::new()
+        }
+    };
+    for value in &mut messages {
+        let id = value
+            .get("id")
+            .and_then(|id| id.as_i64())
+            .unwrap_or_default();
+        if let Some(reactions) = reaction_map.get(&id)
+            && let Ok(reaction_value) = serde_json::to_value(reactions)
+        {
+            value["reactions"] = reaction_value;
+        }
+        ensure_reactions(value);
+    }
+    let missing: Vec<i64> = ids.into_iter().filter(|id| !found.contains(id)).collect();
+
+    let payload = serde_json::json!({
+        "type": "messages",
+        "requestId": request_id,
+        "messages": messages,
+        "missing": missing,
+    });
+    let _ = sender.send(Message::Text(payload.to_string().into())).await;
 }
 
 /// Handle search history request.
@@ -113,6 +280,19 @@ pub(super) async fn handle_search_history(
         .map(|c| c as i32)
         .unwrap_or(channel_id);
 
+    // Excluded channels answer like a search without hits rather than with
+    // an error, so their existence is not revealed through search.
+    if !can_search_channel(state, user_name.as_deref(), channel_to_search).await {
+        let payload = serde_json::json!({
+            "type": "search-results",
+            "requestId": request_id,
+            "channelId": channel_to_search,
+            "messages": [],
+        });
+        let _ = sender.send(Message::Text(payload.to_string().into())).await;
+        return;
+    }
+
     let mut limit = v
         .get("limit")
         .and_then(|l| l.as_i64())
@@ -179,73 +359,113 @@ pub(super) async fn handle_search_history(
 }
 
 /// Handle chat message: persist, broadcast, and schedule ephemeral deletion.
-#[tracing::instrument(skip(state, sender, v), fields(channel_id = %channel_id, user = ?user_name))]
+/// A `scheduleAt` time queues the message for later delivery instead.
+#[tracing::instrument(skip(state, sender, m), fields(channel_id = %channel_id, user = ?user_name))]
 pub(super) async fn handle_chat(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &mut Value,
+    m: ChatMsg,
     channel_id: i32,
     user_name: &Option<String>,
 ) {
-    let user = match user_name {
-        Some(u) => u,
-        None => return,
+    let ChatMsg {
+        mut text,
+        reply_to,
+        schedule_at,
+        expires_at,
+        extra,
+    } = m;
+
+    // The author is always the authenticated name; a client-supplied `user`
+    // is accepted but overwritten below.
+    let Some(user) = user_name else {
+        send_error(sender, errors::NOT_AUTHENTICATED).await;
+        return;
     };
 
     // Sending requires seeing the channel and holding SEND_MESSAGES within it
     // (per-channel overrides included). Enforced server-side so a client whose
     // permission was revoked cannot post by ignoring the disabled composer.
-    if !can_view_channel(state, user, ChannelKind::Text, channel_id).await
-        || !has_channel_permission(
-            state,
-            user,
-            ChannelKind::Text,
-            channel_id,
-            crate::permissions::SEND_MESSAGES,
-        )
-        .await
+    if !can_view_channel(state, user, ChannelKind::Text, channel_id).await {
+        send_error(sender, errors::CHANNEL_ACCESS_DENIED).await;
+        return;
+    }
+    if !has_channel_permission(
+        state,
+        user,
+        ChannelKind::Text,
+        channel_id,
+        crate::permissions::SEND_MESSAGES,
+    )
+    .await
     {
         send_error(sender, errors::SEND_PERMISSION_DENIED).await;
         return;
     }
+    if !can_post_in_channel(state, user, channel_id).await {
+        send_error(sender, errors::CHANNEL_POST_DENIED).await;
+        return;
+    }
 
-    if !security::check_message_rate_limit(&state.rate_limiter, user).await {
+    if !security::check_message_rate_limit(
+        &state.rate_limiter,
+        user,
+        &user_role_names(state, user).await,
+    )
+    .await
+    {
         send_error(sender, errors::MESSAGE_RATE_LIMIT).await;
         return;
     }
 
-    if super::moderation::is_muted(state, user).await {
+    if is_muted(state, user).await {
         send_error(sender, errors::MUTED).await;
         return;
     }
 
-    if let Some(text) = v.get("text").and_then(|t| t.as_str())
-        && text.len() > MAX_MESSAGE_LENGTH
-    {
+    if text.as_ref().is_some_and(|t| t.len() > MAX_MESSAGE_LENGTH) {
         send_error(sender, errors::MESSAGE_TOO_LONG).await;
         return;
     }
 
+    // Masked text replaces the original before anything is stored.
+    if let Some(original) = text.as_deref() {
+        match filter_text(state, user, channel_id, original).await {
+            Filtered::Clean => {}
+            Filtered::Masked(masked) => text = Some(masked),
+            Filtered::Blocked => {
+                send_error(sender, errors::CONTENT_BLOCKED).await;
+                return;
+            }
+        }
+    }
+
+    // Slow mode is per channel and independent of the global rate limit. The
+    // cooldown only starts once the message is published or scheduled, so a
+    // message rejected by the checks below does not count.
+    if let Some(retry_after) = slow_mode_remaining(state, user, channel_id).await {
+        send_error(sender, &errors::slow_mode_active(retry_after)).await;
+        return;
+    }
+
+    // The stored frame is the client's, minus fields only the server sets.
+    let mut frame = extra;
+    for server_only in ["channel", "threadId", "ephemeral", "expiresAt"] {
+        frame.remove(server_only);
+    }
+    let v = &mut Value::Object(frame);
+    v["type"] = Value::from("chat");
     v["user"] = Value::String(user.clone());
     v["channelId"] = Value::from(channel_id);
-    if let Some(map) = v.as_object_mut() {
-        map.remove("channel");
+    if let Some(text) = text {
+        v["text"] = Value::String(text);
     }
     let timestamp = sanitize_message_timestamp(v);
 
     // Replies carry only the target message id from the client; the quoted
     // snippet and thread root are rebuilt from the stored message so a client
     // cannot forge quotes or attach messages to arbitrary threads.
-    let reply_target = v.get("replyTo").and_then(|r| match r {
-        Value::Number(n) => n.as_i64(),
-        Value::Object(o) => o.get("id").and_then(|i| i.as_i64()),
-        _ => None,
-    });
-    if let Some(map) = v.as_object_mut() {
-        map.remove("replyTo");
-        map.remove("threadId");
-    }
-    if let Some(target_id) = reply_target {
+    if let Some(target_id) = reply_to.map(ReplyTarget::id) {
         match db::get_message_record(&state.db, target_id).await {
             Ok(Some(record)) if record.channel_id == channel_id => {
                 let quoted_user = record
@@ -285,56 +505,65 @@ pub(super) async fn handle_chat(
         }
     }
 
-    let mut ephemeral_expiry: Option<DateTime<Utc>> = None;
-    if let Some(raw_expiry) = v.get("expiresAt").and_then(|value| value.as_str()) {
-        if let Ok(parsed) = DateTime::parse_from_rfc3339(raw_expiry) {
-            let mut expiry = parsed.with_timezone(&Utc);
-            let min_allowed = timestamp + ChronoDuration::seconds(MIN_EPHEMERAL_SECONDS);
-            let max_allowed = timestamp + ChronoDuration::seconds(MAX_EPHEMERAL_SECONDS);
-            if expiry < min_allowed {
-                expiry = min_allowed;
-            }
-            if expiry > max_allowed {
-                expiry = max_allowed;
+    // Scheduled messages are validated now and posted later; they cannot also
+    // be ephemeral, since the expiry would be measured from the wrong time.
+    let scheduled_for = match schedule_at.as_deref() {
+        None => None,
+        Some(raw) => match parse_schedule_time(raw, Utc::now()) {
+            Some(due) => Some(due),
+            None => {
+                send_error(sender, errors::INVALID_SCHEDULE).await;
+                return;
             }
-            v["expiresAt"] = Value::String(expiry.to_rfc3339());
-            v["ephemeral"] = Value::Bool(true);
-            ephemeral_expiry = Some(expiry);
-        } else if let Some(map) = v.as_object_mut() {
-            map.remove("expiresAt");
-            map.remove("ephemeral");
+        },
+    };
+
+    let mut ephemeral_expiry: Option<DateTime<Utc>> = None;
+    if let Some(parsed) = expires_at
+        .as_deref()
+        .filter(|_| scheduled_for.is_none())
+        .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+    {
+        let mut expiry = parsed.with_timezone(&Utc);
+        let min_allowed = timestamp + ChronoDuration::seconds(MIN_EPHEMERAL_SECONDS);
+        let max_allowed = timestamp + ChronoDuration::seconds(MAX_EPHEMERAL_SECONDS);
+        if expiry < min_allowed {
+            expiry = min_allowed;
+        }
+        if expiry > max_allowed {
+            expiry = max_allowed;
         }
-    } else if let Some(map) = v.as_object_mut() {
-        map.remove("ephemeral");
+        v["expiresAt"] = Value::String(expiry.to_rfc3339());
+        v["ephemeral"] = Value::Bool(true);
+        ephemeral_expiry = Some(expiry);
     }
 
     ensure_reactions(v);
     ensure_time(v, &timestamp);
 
-    let out = serde_json::to_string(&v).unwrap_or_else(|_| v.to_string());
-    match db::insert_message(&state.db, channel_id, &out).await {
-        Ok(id) => {
-            v["id"] = Value::from(id);
-            let out_with_id = serde_json::to_string(&v).unwrap_or_else(|_| out.clone());
-            let chan_tx = get_or_create_channel(state, channel_id).await;
-            let _ = chan_tx.send(out_with_id);
-
-            // Channel broadcasts only reach clients joined to this channel, so
-            // additionally announce the message globally. Clients use this to
-            // track unread counts and mentions for channels they are not
-            // currently viewing.
-            let notify = serde_json::json!({
-                "type": "message-notify",
-                "channelId": channel_id,
-                "id": id,
-                "user": user,
-                "text": v.get("text").cloned().unwrap_or(Value::Null),
-            });
-            let _ = state.tx.send(notify.to_string());
+    // Checked on the final frame, so server-added fields such as the reply
+    // quote count towards the limit just like client-supplied ones.
+    if message_too_large(v) {
+        send_error(sender, errors::MESSAGE_TOO_LARGE).await;
+        return;
+    }
 
+    if let Some(due) = scheduled_for {
+        if schedule_chat(state, sender, channel_id, user, v, due).await {
+            record_slow_mode_post(state, user, channel_id).await;
+        }
+        return;
+    }
+
+    match publish_chat_message(state, channel_id, user, v, ephemeral_expiry).await {
+        Ok(id) => {
+            record_slow_mode_post(state, user, channel_id).await;
             if let Some(expiry) = ephemeral_expiry {
                 schedule_ephemeral_deletion(Arc::clone(state), id, channel_id, expiry);
             }
+            if let Some(text) = v.get("text").and_then(|t| t.as_str()) {
+                crate::link_preview::spawn_message_unfurl(Arc::clone(state), channel_id, id, text);
+            }
 
             // Lifetime stats (no-op unless server and user both opted in).
             super::stats::record(state, user, super::stats::chat_message_deltas(v)).await;
@@ -343,6 +572,119 @@ pub(super) async fn handle_chat(
     }
 }
 
+/// Queue a prepared chat frame for the sweeper to post at `due` and
+/// acknowledge it with the token the sender needs to cancel it. Returns
+/// whether it was queued; a sender at `MAX_PENDING_SCHEDULED_PER_USER` gets
+/// `too-many-scheduled`.
+async fn schedule_chat(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    channel_id: i32,
+    user: &str,
+    v: &Value,
+    due: DateTime<Utc>,
+) -> bool {
+    let token: String = rand::random::<[u8; 16]>()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let content = serde_json::to_string(v).unwrap_or_else(|_| v.to_string());
+    match db::add_scheduled_message(
+        &state.db,
+        &token,
+        channel_id,
+        user,
+        &content,
+        due,
+        MAX_PENDING_SCHEDULED_PER_USER,
+    )
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            send_error(sender, errors::TOO_MANY_SCHEDULED).await;
+            return false;
+        }
+        Err(e) => {
+            error!("failed to store scheduled message: {e}");
+            send_error(sender, errors::INVALID_SCHEDULE).await;
+            return false;
+        }
+    }
+    let ack = serde_json::json!({
+        "type": "scheduled",
+        "token": token,
+        "channelId": channel_id,
+        "scheduleAt": due.to_rfc3339(),
+    });
+    let _ = sender.send(Message::Text(ack.to_string().into())).await;
+    true
+}
+
+/// Handle `cancel-scheduled`: drop one of the sender's pending scheduled
+/// messages before it is posted.
+pub(super) async fn handle_cancel_scheduled(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let (Some(user), Some(token)) = (
+        user_name.as_deref(),
+        v.get("token").and_then(|t| t.as_str()),
+    ) else {
+        send_error(sender, errors::SCHEDULED_NOT_FOUND).await;
+        return;
+    };
+    match db::cancel_scheduled_message(&state.db, token, user).await {
+        Ok(true) => {
+            let ack = serde_json::json!({"type": "scheduled-cancelled", "token": token});
+            let _ = sender.send(Message::Text(ack.to_string().into())).await;
+        }
+        Ok(false) => send_error(sender, errors::SCHEDULED_NOT_FOUND).await,
+        Err(e) => {
+            error!("failed to cancel scheduled message {token}: {e}");
+            send_error(sender, errors::SCHEDULED_NOT_FOUND).await;
+        }
+    }
+}
+
+/// Handle `list-scheduled`: send the requester their pending scheduled
+/// messages, soonest first.
+pub(super) async fn handle_list_scheduled(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    user_name: &Option<String>,
+) {
+    let Some(user) = user_name.as_deref() else {
+        send_error(sender, errors::NOT_AUTHENTICATED).await;
+        return;
+    };
+    let pending = match db::list_scheduled_messages(&state.db, user).await {
+        Ok(pending) => pending,
+        Err(e) => {
+            error!("failed to list scheduled messages for {user}: {e}");
+            Vec::new()
+        }
+    };
+    let messages: Vec<Value> = pending
+        .into_iter()
+        .map(|scheduled| {
+            let text = serde_json::from_str::<Value>(&scheduled.content)
+                .ok()
+                .and_then(|frame| frame.get("text").cloned());
+            serde_json::json!({
+                "token": scheduled.token,
+                "channelId": scheduled.channel_id,
+                "scheduleAt": scheduled.due_at,
+                "text": text,
+            })
+        })
+        .collect();
+    let payload = serde_json::json!({ "type": "scheduled-list", "messages": messages });
+    let _ = sender.send(Message::Text(payload.to_string().into())).await;
+}
+
 /// Handle delete message request.
 pub(super) async fn handle_delete_message(
     state: &Arc<AppState>,
@@ -388,9 +730,13 @@ pub(super) async fn handle_delete_message(
         .and_then(|user| user.as_str())
         .map(|value| value.to_string());
 
-    let mut allowed = owner.as_deref() == Some(requester.as_str());
-    if !allowed
-        && has_channel_permission(
+    // `purge: true` removes the row even when soft deletion is configured;
+    // it is a moderator tool, so it needs Manage Messages on one's own
+    // messages too.
+    let purge = v.get("purge").and_then(|p| p.as_bool()).unwrap_or(false);
+    let own = owner.as_deref() == Some(requester.as_str());
+    if !own || purge {
+        if !has_channel_permission(
             state,
             &requester,
             ChannelKind::Text,
@@ -398,29 +744,56 @@ pub(super) async fn handle_delete_message(
             crate::permissions::MANAGE_MESSAGES,
         )
         .await
-    {
-        allowed = true;
-    }
-
-    if !allowed {
-        send_error(sender, errors::MESSAGE_PERMISSION_DENIED).await;
-        return;
+        {
+            send_error(sender, errors::MESSAGE_PERMISSION_DENIED).await;
+            return;
+        }
+        // Moderators may only remove messages of users ranked below them.
+        if !own
+            && let Some(owner) = owner.as_deref()
+            && !outranks(state, &requester, owner).await
+        {
+            send_error(sender, errors::MODERATION_TARGET_PROTECTED).await;
+            return;
+        }
     }
 
-    match db::delete_message(&state.db, message_id).await {
+    let soft = !purge && crate::config::soft_delete_messages();
+    let deleted = if soft {
+        db::soft_delete_message(&state.db, message_id, &requester).await
+    } else {
+        db::delete_message(&state.db, message_id).await
+    };
+    match deleted {
         Ok(true) => {
-            let payload = serde_json::json!({
+            let mut payload = serde_json::json!({
                 "type": "message-deleted",
                 "id": message_id,
                 "channelId": record.channel_id,
             });
+            if soft {
+                payload["tombstone"] = Value::Bool(true);
+            }
             let chan_sender = get_or_create_channel(state, record.channel_id).await;
             let _ = chan_sender.send(payload.to_string());
 
             // Only deleting one's own message counts towards the stat;
-            // moderator deletions say nothing about the requester's habits.
-            if owner.as_deref() == Some(requester.as_str()) {
+            // moderator deletions say nothing about the requester's habits
+            // and are audited instead.
+            if own {
                 super::stats::record(state, &requester, vec![(db::Stat::MessagesDeleted, 1)]).await;
+            } else {
+                record_audit(
+                    state,
+                    &requester,
+                    "delete-message",
+                    owner.as_deref(),
+                    serde_json::json!({
+                        "messageId": message_id,
+                        "channelId": record.channel_id,
+                        "purge": purge,
+                    }),
+                );
             }
         }
         Ok(false) => {
@@ -433,6 +806,119 @@ pub(super) async fn handle_delete_message(
     }
 }
 
+/// Handle a moderator purge of one user's messages, in `channelId` or across
+/// every channel when it is omitted. Requires `MANAGE_MESSAGES` (on that
+/// channel, or server-wide) and an explicit `confirm: true`; at most
+/// [`MAX_PURGE_MESSAGES`] of the newest matching messages go per request.
+/// Each affected channel receives one `messages-deleted` batch.
+pub(super) async fn handle_purge_user_messages(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let Some(requester) = user_name.clone() else {
+        send_error(sender, errors::NOT_AUTHENTICATED).await;
+        return;
+    };
+
+    let Some(target) = v
+        .get("user")
+        .and_then(|u| u.as_str())
+        .filter(|u| !u.is_empty())
+    else {
+        send_error(sender, errors::INVALID_USERNAME).await;
+        return;
+    };
+
+    if v.get("confirm").and_then(|c| c.as_bool()) != Some(true) {
+        send_error(sender, errors::PURGE_CONFIRMATION_REQUIRED).await;
+        return;
+    }
+
+    let channel_id = v
+        .get("channelId")
+        .and_then(|c| c.as_i64())
+        .map(|c| c as i32);
+    let allowed = match channel_id {
+        Some(ch_id) => {
+            if db::get_channel_by_id(&state.db, ch_id).await.is_none() {
+                send_error(sender, errors::UNKNOWN_CHANNEL).await;
+                return;
+            }
+            has_channel_permission(
+                state,
+                &requester,
+                ChannelKind::Text,
+                ch_id,
+                crate::permissions::MANAGE_MESSAGES,
+            )
+            .await
+        }
+        None => has_permission(state, &requester, crate::permissions::MANAGE_MESSAGES).await,
+    };
+    if !allowed {
+        send_error(sender, errors::MESSAGE_PERMISSION_DENIED).await;
+        return;
+    }
+    if target != requester && !outranks(state, &requester, target).await {
+        send_error(sender, errors::MODERATION_TARGET_PROTECTED).await;
+        return;
+    }
+
+    let deleted = match db::delete_messages_by_user(
+        &state.db,
+        channel_id,
+        target,
+        MAX_PURGE_MESSAGES,
+    )
+    .await
+    {
+        Ok(deleted) => deleted,
+        Err(error) => {
+            error!("failed to purge messages by {target}: {error}");
+            send_error(sender, errors::MESSAGE_DELETE_FAILED).await;
+            return;
+        }
+    };
+
+    let mut by_channel: HashMap<i32, Vec<i64>> = HashMap::new();
+    for (id, ch_id) in &deleted {
+        by_channel.entry(*ch_id).or_default().push(*id);
+    }
+    for (ch_id, ids) in by_channel {
+        let payload = serde_json::json!({
+            "type": "messages-deleted",
+            "channelId": ch_id,
+            "ids": ids,
+        });
+        let chan_sender = get_or_create_channel(state, ch_id).await;
+        let _ = chan_sender.send(payload.to_string());
+    }
+
+    record_audit(
+        state,
+        &requester,
+        "purge-user-messages",
+        Some(target),
+        serde_json::json!({ "channelId": channel_id, "deleted": deleted.len() }),
+    );
+    info!(
+        moderator = %requester,
+        target = %target,
+        channel = ?channel_id,
+        count = deleted.len(),
+        "Purged user messages"
+    );
+    let reply = serde_json::json!({
+        "type": "user-messages-purged",
+        "user": target,
+        "channelId": channel_id,
+        "deleted": deleted.len(),
+    });
+    let _ = sender.send(Message::Text(reply.to_string().into())).await;
+}
+
 /// Handle edit message request. Only the original author may edit a message.
 pub(super) async fn handle_edit_message(
     state: &Arc<AppState>,
@@ -495,9 +981,18 @@ pub(super) async fn handle_edit_message(
         return;
     }
 
+    let new_text = match filter_text(state, &requester, channel_id, new_text).await {
+        Filtered::Clean => new_text.to_string(),
+        Filtered::Masked(masked) => masked,
+        Filtered::Blocked => {
+            send_error(sender, errors::CONTENT_BLOCKED).await;
+            return;
+        }
+    };
+
     let mut content = record.content.clone();
     let edited_at = Utc::now().to_rfc3339();
-    content["text"] = Value::String(new_text.to_string());
+    content["text"] = Value::String(new_text.clone());
     content["edited"] = Value::Bool(true);
     content["editedAt"] = Value::String(edited_at.clone());
 
@@ -593,6 +1088,116 @@ pub(super) async fn handle_load_thread(
     }
 }
 
+/// Handle `get-permalink`: reply with the stable link for a message the
+/// requester can see. Messages in invisible channels answer
+/// `message-not-found` so the link cannot be used to probe for them.
+pub(super) async fn handle_get_permalink(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let Some(message_id) = v.get("messageId").and_then(|m| m.as_i64()) else {
+        send_error(sender, errors::INVALID_MESSAGE_ID).await;
+        return;
+    };
+    let channel_id = match db::get_message_channel_id(&state.db, message_id).await {
+        Ok(Some(channel_id)) => channel_id,
+        Ok(None) => {
+            send_error(sender, errors::MESSAGE_NOT_FOUND).await;
+            return;
+        }
+        Err(e) => {
+            error!("failed to look up message {message_id} for permalink: {e}");
+            send_error(sender, errors::MESSAGE_NOT_FOUND).await;
+            return;
+        }
+    };
+    if !user_can_see_channel(state, user_name.as_deref(), ChannelKind::Text, channel_id).await {
+        send_error(sender, errors::MESSAGE_NOT_FOUND).await;
+        return;
+    }
+    let payload = serde_json::json!({
+        "type": "permalink",
+        "channelId": channel_id,
+        "messageId": message_id,
+        "permalink": format_permalink(channel_id, message_id),
+    });
+    let _ = sender.send(Message::Text(payload.to_string().into())).await;
+}
+
+/// Handle `resolve-permalink`: send the linked message with the messages
+/// around it so the client can jump straight to it.
+pub(super) async fn handle_resolve_permalink(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let Some(permalink) = v.get("permalink").and_then(|p| p.as_str()) else {
+        send_error(sender, errors::INVALID_PERMALINK).await;
+        return;
+    };
+    let Some((channel_id, message_id)) = parse_permalink(permalink) else {
+        send_error(sender, errors::INVALID_PERMALINK).await;
+        return;
+    };
+    if !user_can_see_channel(state, user_name.as_deref(), ChannelKind::Text, channel_id).await {
+        send_error(sender, errors::MESSAGE_NOT_FOUND).await;
+        return;
+    }
+    let rows = match db::fetch_message_context(
+        &state.db,
+        channel_id,
+        message_id,
+        PERMALINK_CONTEXT_RADIUS,
+    )
+    .await
+    {
+        Ok(rows) if !rows.is_empty() => rows,
+        Ok(_) => {
+            send_error(sender, errors::MESSAGE_NOT_FOUND).await;
+            return;
+        }
+        Err(e) => {
+            error!("failed to resolve permalink {permalink}: {e}");
+            send_error(sender, errors::MESSAGE_NOT_FOUND).await;
+            return;
+        }
+    };
+
+    let ids: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
+    let reaction_map = match db::get_reactions_for_messages(&state.db, &ids).await {
+        Ok(map) => map,
+        Err(e) => {
+            error!("failed to load reactions for permalink context: {e}");
+            HashMap::new()
+        }
+    };
+    let mut messages = Vec::new();
+    for (id, content) in rows {
+        if let Ok(mut value) = serde_json::from_str::<Value>(&content) {
+            value["id"] = Value::from(id);
+            if let Some(reactions) = reaction_map.get(&id)
+                && let Ok(reaction_value) = serde_json::to_value(reactions)
+            {
+                value["reactions"] = reaction_value;
+            }
+            ensure_reactions(&mut value);
+            messages.push(value);
+        }
+    }
+
+    let payload = serde_json::json!({
+        "type": "permalink-context",
+        "permalink": format_permalink(channel_id, message_id),
+        "channelId": channel_id,
+        "messageId": message_id,
+        "messages": messages,
+    });
+    let _ = sender.send(Message::Text(payload.to_string().into())).await;
+}
+
 /// Handle a typing notification: rebroadcast it to everyone in the channel.
 /// Typing events are transient and never persisted; a per-connection throttle
 /// keeps a misbehaving client from flooding the channel.
@@ -623,11 +1228,64 @@ pub(super) async fn handle_typing(
     let _ = chan_tx.send(payload.to_string());
 }
 
+/// Handle `mark-read`: move the requester's read marker in `channelId` up to
+/// `messageId`. In channels with receipts enabled, an advanced marker queues
+/// a batch of `read-receipt-update` frames for the channel's recent senders.
+pub(super) async fn handle_mark_read(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    msg: &MarkReadMsg,
+    user_name: &Option<String>,
+) {
+    let Some(user) = user_name.as_deref() else {
+        send_error(sender, errors::NOT_AUTHENTICATED).await;
+        return;
+    };
+    let MarkReadMsg {
+        channel_id,
+        message_id,
+    } = *msg;
+    if !can_view_channel(state, user, ChannelKind::Text, channel_id).await {
+        send_error(sender, errors::UNKNOWN_CHANNEL).await;
+        return;
+    }
+    // Only real messages of this channel can be a read position, so a client
+    // cannot claim to have read messages that do not exist yet.
+    match db::get_message_channel_id(&state.db, message_id).await {
+        Ok(Some(id)) if id == channel_id => {}
+        Ok(_) => {
+            send_error(sender, errors::MESSAGE_NOT_FOUND).await;
+            return;
+        }
+        Err(e) => {
+            error!("Failed to look up message {message_id} for read marker: {e}");
+            send_error(sender, errors::READ_MARKER_FAILED).await;
+            return;
+        }
+    }
+
+    match db::advance_read_marker(&state.db, user, channel_id, message_id).await {
+        Ok(true) => {
+            let receipts = db::get_channel_by_id(&state.db, channel_id)
+                .await
+                .is_some_and(|channel| channel.receipts_enabled);
+            if receipts {
+                queue_read_receipts(state, channel_id);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => {
+            error!("Failed to store read marker for {user} in {channel_id}: {e}");
+            send_error(sender, errors::READ_MARKER_FAILED).await;
+        }
+    }
+}
+
 /// Handle reaction (add/remove emoji) request.
 pub(super) async fn handle_react(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &ReactMsg,
     user_name: &Option<String>,
 ) {
     let user = match user_name.clone() {
@@ -638,40 +1296,40 @@ pub(super) async fn handle_react(
         }
     };
 
-    let Some(message_id) = v.get("messageId").and_then(|m| m.as_i64()) else {
-        send_error(sender, errors::INVALID_MESSAGE_ID).await;
-        return;
-    };
-
-    let Some(action) = v.get("action").and_then(|a| a.as_str()) else {
-        send_error(sender, errors::INVALID_REACTION_ACTION).await;
+    let message_id = msg.message_id;
+    let add = msg.action == ReactionAction::Add;
+    let emoji = msg.emoji.trim();
+    // Custom emoji shortcodes (`:name:`) are checked against the registry
+    // below; anything else must be a single Unicode emoji.
+    let shortcode = is_emoji_shortcode(emoji);
+    if !shortcode && !validate_reaction_emoji(emoji) {
+        send_error(sender, errors::INVALID_EMOJI).await;
         return;
-    };
+    }
 
-    let Some(raw_emoji) = v.get("emoji").and_then(|e| e.as_str()) else {
-        send_error(sender, errors::INVALID_EMOJI).await;
+    let strict = crate::config::strict_emoji();
+    if add && !shortcode && !is_recognized_reaction(emoji, strict) {
+        send_error(sender, errors::REACTION_REJECTED).await;
         return;
-    };
+    }
 
-    let emoji = raw_emoji.trim();
-    // Custom emoji shortcodes (`:name:`) may exceed the 16-byte cap that
-    // bounds regular unicode reactions.
-    let shortcode = is_emoji_shortcode(emoji);
-    if !shortcode
-        && (emoji.is_empty()
-            || emoji.len() > 16
-            || emoji.chars().any(|c| c.is_control() || c.is_whitespace()))
+    if !security::check_reaction_cooldown(&state.rate_limiter, &user, message_id).await
+        || !security::check_reaction_rate_limit(&state.rate_limiter, &user).await
     {
-        send_error(sender, errors::INVALID_EMOJI).await;
+        send_error(sender, errors::REACTION_RATE_LIMIT).await;
         return;
     }
 
     // Adding a shortcode reaction requires the emoji to actually exist so
     // junk shortcodes cannot be planted; removal stays permissive so
     // reactions of since-deleted emojis remain removable.
-    if shortcode && action == "add" {
+    if shortcode && add {
         match db::emoji_exists(&state.db, emoji.trim_matches(':')).await {
             Ok(true) => {}
+            Ok(false) if strict => {
+                send_error(sender, errors::REACTION_REJECTED).await;
+                return;
+            }
             Ok(false) => {
                 send_error(sender, errors::INVALID_EMOJI).await;
                 return;
@@ -704,7 +1362,7 @@ pub(super) async fn handle_react(
     // requires SEND_MESSAGES there. Removing your own reaction stays allowed as
     // long as you can still see the channel.
     if !can_view_channel(state, &user, ChannelKind::Text, target_channel_id).await
-        || (action == "add"
+        || (add
             && !has_channel_permission(
                 state,
                 &user,
@@ -718,42 +1376,36 @@ pub(super) async fn handle_react(
         return;
     }
 
-    let result = match action {
-        "add" => db::add_reaction(&state.db, message_id, &user, emoji).await,
-        "remove" => db::remove_reaction(&state.db, message_id, &user, emoji).await,
-        _ => {
-            send_error(sender, errors::INVALID_REACTION_ACTION).await;
+    let change = match db::apply_reaction(
+        &state.db,
+        &state.reaction_seq,
+        message_id,
+        &user,
+        emoji,
+        add,
+    )
+    .await
+    {
+        Ok(Some(change)) => change,
+        Ok(None) => {
+            send_error(sender, errors::TOO_MANY_REACTIONS).await;
+            return;
+        }
+        Err(e) => {
+            error!("db reaction error: {e}");
+            send_error(sender, errors::REACTION_FAILED).await;
             return;
         }
     };
 
-    if let Err(e) = result {
-        error!("db reaction error: {e}");
-        send_error(sender, errors::REACTION_FAILED).await;
-        return;
-    }
-
-    if action == "add" {
+    if add {
         // Lifetime totals only count additions; taking a reaction back does
         // not subtract. Both sides are gated by their own opt-in.
         let author = target_record.content.get("user").and_then(|u| u.as_str());
         super::stats::record_reaction_added(state, &user, author, emoji).await;
     }
 
-    let reactions = match db::get_reaction_summary(&state.db, message_id).await {
-        Ok(map) => map,
-        Err(e) => {
-            error!("db reaction summary error: {e}");
-            return;
-        }
-    };
-
-    let payload = serde_json::json!({
-        "type": "reaction-update",
-        "channelId": target_channel_id,
-        "messageId": message_id,
-        "reactions": reactions,
-    });
-    let chan_sender = get_or_create_channel(state, target_channel_id).await;
-    let _ = chan_sender.send(payload.to_string());
+    // Only subscribers of the message's channel receive the update, and bursts
+    // of changes to one message collapse into a single broadcast.
+    queue_reaction_change(state, target_channel_id, message_id, change);
 }
@@ -6,6 +6,7 @@
 //! - [`channels`] – text/voice channel and category management
 //! - [`dms`] – direct messages between two users
 //! - [`emojis`] – custom server emoji management
+//! - [`flags`] – message reports and their moderator review
 //! - [`identity`] – server name, description, welcome message and icon
 //! - [`messages`] – chat, history, threads, typing, search and reactions
 //! - [`moderation`] – kick, ban and mute actions
@@ -19,6 +20,7 @@ mod channel_overrides;
 mod channels;
 mod dms;
 mod emojis;
+mod flags;
 mod identity;
 mod messages;
 mod moderation;
@@ -29,6 +31,9 @@ mod screenshare;
 mod stats;
 mod wiki;
 
+use super::encoding::{FrameEncoding, decode_msgpack, history_compression_requested};
+use super::inbound::ClientMessage;
+use super::protocol::ProtocolVersion;
 use super::{errors, helpers::*, validation::*};
 use crate::channel_overrides::ChannelKind;
 use crate::{AppState, db};
@@ -37,18 +42,20 @@ use axum::{
         ConnectInfo, State,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
-    response::IntoResponse,
+    http::{HeaderMap, Uri},
+    response::{IntoResponse, Response},
 };
 use futures::{SinkExt, StreamExt, stream::SplitSink};
+use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::broadcast;
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 
-/// Resolve the "general" channel ID from the database.
-async fn general_channel_id(state: &Arc<AppState>) -> i32 {
-    db::get_channel_id_by_name(&state.db, "general")
+/// Resolve the configured default channel's ID from the database.
+async fn resolve_default_channel_id(state: &Arc<AppState>) -> i32 {
+    db::get_channel_id_by_name(&state.db, &crate::config::default_channel())
         .await
         .unwrap_or(1)
 }
@@ -61,154 +68,200 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
 
     let (mut sender, mut receiver) = socket.split();
     let mut global_rx = state.tx.subscribe();
-    let default_channel_id = general_channel_id(&state).await;
+    let default_channel_id = resolve_default_channel_id(&state).await;
     let mut channel_id: i32 = default_channel_id;
     let mut chan_tx = get_or_create_channel(&state, channel_id).await;
     let mut chan_rx = chan_tx.subscribe();
+    let mut chan_sub = ChannelSubscription::acquire_unbounded(&state, channel_id);
     let mut user_name: Option<String> = None;
     let mut voice_channel: Option<i32> = None;
     let mut authenticated = state.password.is_none();
     let mut last_typing_broadcast: Option<std::time::Instant> = None;
+    let mut last_seen_touch: Option<std::time::Instant> = None;
+    let mut encoding = FrameEncoding::default();
+    let mut compress_history = false;
+    let mut protocol = ProtocolVersion::default();
+
+    // Server-initiated heartbeat: a client that vanished without closing the
+    // TCP connection never errors the stream, so it is detected by missing
+    // Pongs instead. The first tick of an interval fires immediately.
+    let heartbeat_timeout = crate::config::heartbeat_timeout();
+    let mut heartbeat = tokio::time::interval(crate::config::heartbeat_interval());
+    heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    heartbeat.tick().await;
+    let mut last_pong = std::time::Instant::now();
 
     loop {
         tokio::select! {
             Some(result) = receiver.next() => {
                 let text = match result {
                     Ok(Message::Text(t)) => t,
-                    _ => break,
+                    Ok(Message::Pong(_)) => {
+                        last_pong = std::time::Instant::now();
+                        continue;
+                    }
+                    // The WebSocket layer answers Pings itself.
+                    Ok(Message::Ping(_)) => continue,
+                    // MessagePack frames join the JSON pipeline below.
+                    Ok(Message::Binary(b)) => match decode_msgpack(&b) {
+                        Some(v) => v.to_string().into(),
+                        None => {
+                            debug!("Ignoring undecodable binary frame");
+                            continue;
+                        }
+                    },
+                    Ok(Message::Close(_)) | Err(_) => break,
                 };
 
-                if let Ok(mut v) = serde_json::from_str::<Value>(&text) {
+                if let Ok(v) = serde_json::from_str::<Value>(&text) {
+                    if !json_within_limits(&v, crate::config::ws_max_json_depth(), crate::config::ws_max_json_nodes()) {
+                        warn!("Rejected frame exceeding JSON depth/size limits");
+                        send_error(&mut sender, errors::PAYLOAD_TOO_COMPLEX).await;
+                        continue;
+                    }
                     if let Some(t) = v.get("type").and_then(|t| t.as_str()) {
                         if t.starts_with("voice-") {
                             debug!("Received voice message: {t}");
                         } else {
                             info!("Received message type: {t}");
+                            debug!("Received frame: {}", redact_for_log(&v));
                         }
 
                         if !authenticated && t != "presence" && t != "bot-presence" {
                             send_error(&mut sender, errors::UNAUTHENTICATED).await;
                             break;
                         }
+                        if let Some(name) = &user_name {
+                            touch_last_seen(&state, name, &mut last_seen_touch).await;
+                        }
 
-                        match t {
-                            "presence" => {
-                                if auth::handle_presence(&mut sender, &state, &mut v, &mut authenticated, &mut user_name, &client_ip, default_channel_id).await.is_err() {
+                        // Frame types with a typed model are decoded and handled
+                        // here; the rest are matched on the raw value below.
+                        match ClientMessage::deserialize(&v) {
+                            Ok(ClientMessage::Untyped) => {}
+                            // The handshake and the frames below need more of
+                            // the connection's state than `dispatch_typed` gets.
+                            Ok(ClientMessage::Presence(mut m)) => {
+                                let Some(requested) = ProtocolVersion::requested(&m.options) else {
+                                    warn!("Rejected presence with unsupported protocol version");
+                                    send_error(&mut sender, &errors::unsupported_protocol()).await;
+                                    break;
+                                };
+                                // Old clients get the legacy behaviour for
+                                // anything their version predates.
+                                requested.strip_unsupported(&mut m.options);
+                                if auth::handle_presence(&mut sender, &state, &m, &mut authenticated, &mut user_name, &client_ip, default_channel_id).await.is_err() {
                                     break;
                                 }
+                                protocol = requested;
+                                encoding = FrameEncoding::requested(&m.options);
+                                compress_history = history_compression_requested(&m.options);
+                                let _ = sender.send(Message::Text(protocol.frame().into())).await;
+                                continue;
                             }
-                            "bot-presence" => {
-                                if auth::handle_bot_presence(&mut sender, &state, &v, &mut authenticated, &mut user_name, default_channel_id).await.is_err() {
+                            Ok(ClientMessage::BotPresence(mut m)) => {
+                                let Some(requested) = ProtocolVersion::requested(&m.options) else {
+                                    warn!("Rejected bot presence with unsupported protocol version");
+                                    send_error(&mut sender, &errors::unsupported_protocol()).await;
+                                    break;
+                                };
+                                requested.strip_unsupported(&mut m.options);
+                                if auth::handle_bot_presence(&mut sender, &state, &m, &mut authenticated, &mut user_name, default_channel_id).await.is_err() {
                                     break;
                                 }
+                                protocol = requested;
+                                encoding = FrameEncoding::requested(&m.options);
+                                compress_history = history_compression_requested(&m.options);
+                                let _ = sender.send(Message::Text(protocol.frame().into())).await;
+                                continue;
                             }
-                            "join" => {
-                                messages::handle_join(&state, &mut sender, &v, &mut channel_id, &mut chan_tx, &mut chan_rx, &user_name).await;
-                            }
-                            "load-history" => {
-                                messages::handle_load_history(&state, &mut sender, &v, channel_id, &user_name).await;
-                            }
-                            "load-thread" => {
-                                messages::handle_load_thread(&state, &mut sender, &v, channel_id).await;
-                            }
-                            "pin-message" => {
-                                pins::handle_pin_message(&state, &mut sender, &v, &user_name).await;
-                            }
-                            "unpin-message" => {
-                                pins::handle_unpin_message(&state, &mut sender, &v, &user_name).await;
-                            }
-                            "wiki-get" => {
-                                wiki::handle_wiki_get(&state, &mut sender, &v).await;
-                            }
-                            "wiki-resolve" => {
-                                wiki::handle_wiki_resolve(&state, &mut sender, &v).await;
+                            Ok(ClientMessage::Chat(m)) => {
+                                messages::handle_chat(&state, &mut sender, m, channel_id, &user_name).await;
+                                continue;
                             }
-                            "wiki-create" => {
-                                wiki::handle_wiki_create(&state, &mut sender, &v, &user_name).await;
+                            Ok(ClientMessage::DeleteChannel(m)) => {
+                                channels::handle_delete_channel(&state, &mut sender, m, &user_name, &mut channel_id, &mut chan_tx, &mut chan_rx, &mut chan_sub, default_channel_id).await;
+                                continue;
                             }
-                            "wiki-update" => {
-                                wiki::handle_wiki_update(&state, &mut sender, &v, &user_name).await;
+                            Ok(msg) => {
+                                dispatch_typed(&state, &mut sender, msg, &mut voice_channel, &user_name).await;
+                                continue;
                             }
-                            "wiki-delete" => {
-                                wiki::handle_wiki_delete(&state, &mut sender, &v, &user_name).await;
+                            Err(e) => {
+                                debug!("Malformed {t} frame: {e}");
+                                send_error(&mut sender, &errors::invalid_payload(t)).await;
+                                continue;
                             }
-                            "wiki-rename" => {
-                                wiki::handle_wiki_rename(&state, &mut sender, &v, &user_name).await;
+                        }
+
+                        match t {
+                            "join" => {
+                                messages::handle_join(&state, &mut sender, &v, &mut channel_id, &mut chan_tx, &mut chan_rx, &mut chan_sub, &user_name, compress_history).await;
                             }
-                            "dm" => {
-                                dms::handle_dm(&state, &mut sender, &v, &user_name).await;
+                            "load-history" => {
+                                messages::handle_load_history(&state, &mut sender, &v, channel_id, &user_name, compress_history).await;
                             }
-                            "load-dm-history" => {
-                                dms::handle_load_dm_history(&state, &mut sender, &v, &user_name).await;
+                            "load-thread" => {
+                                if protocol.supports_threads() {
+                                    messages::handle_load_thread(&state, &mut sender, &v, channel_id).await;
+                                } else {
+                                    debug!("Ignoring load-thread from protocol {}", protocol.get());
+                                }
                             }
-                            "get-user-key" => {
-                                dms::handle_get_user_key(&state, &mut sender, &v).await;
+                            "list-flags" => {
+                                flags::handle_list_flags(&state, &mut sender, &user_name).await;
                             }
                             "typing" => {
                                 messages::handle_typing(&state, channel_id, &user_name, &mut last_typing_broadcast).await;
                             }
-                            "search-history" => {
-                                messages::handle_search_history(&state, &mut sender, &v, channel_id, &user_name).await;
-                            }
-                            "create-channel" => {
-                                channels::handle_create_channel(&state, &mut sender, &v, &user_name).await;
-                            }
-                            "delete-channel" => {
-                                if channels::handle_delete_channel(&state, &mut sender, &v, &user_name, &mut channel_id, &mut chan_tx, &mut chan_rx, default_channel_id).await.is_err() {
-                                    continue;
-                                }
-                            }
-                            "move-channel" => {
-                                channels::handle_move_channel(&state, &mut sender, &v, &user_name).await;
-                            }
-                            "reorder-channels" => {
-                                channels::handle_reorder_channels(&state, &mut sender, &v, &user_name).await;
-                            }
-                            "reorder-categories" => {
-                                channels::handle_reorder_categories(&state, &mut sender, &v, &user_name).await;
-                            }
-                            "set-channel-topic" => {
-                                channels::handle_set_channel_topic(&state, &mut sender, &v, &user_name).await;
-                            }
-                            "create-category" => {
-                                channels::handle_create_category(&state, &mut sender, &v, &user_name).await;
+                            "get-channel-activity" => {
+                                messages::handle_get_channel_activity(&state, &mut sender, &user_name).await;
                             }
-                            "rename-category" => {
-                                channels::handle_rename_category(&state, &mut sender, &v, &user_name).await;
+                            "get-messages" => {
+                                messages::handle_get_messages(&state, &mut sender, &v, &user_name).await;
                             }
-                            "delete-category" => {
-                                channels::handle_delete_category(&state, &mut sender, &v, &user_name).await;
+                            "get-permalink" => {
+                                messages::handle_get_permalink(&state, &mut sender, &v, &user_name).await;
                             }
-                            "create-voice-channel" => {
-                                channels::handle_create_voice_channel(&state, &mut sender, &v, &user_name).await;
+                            "resolve-permalink" => {
+                                messages::handle_resolve_permalink(&state, &mut sender, &v, &user_name).await;
                             }
-                            "update-voice-channel" => {
-                                channels::handle_update_voice_channel(&state, &mut sender, &v, &user_name).await;
+                            "search-history" => {
+                                messages::handle_search_history(&state, &mut sender, &v, channel_id, &user_name).await;
                             }
-                            "delete-voice-channel" => {
-                                channels::handle_delete_voice_channel(&state, &mut sender, &v, &user_name, &mut voice_channel).await;
+                            "cancel-scheduled" => {
+                                messages::handle_cancel_scheduled(&state, &mut sender, &v, &user_name).await;
                             }
-                            "chat" => {
-                                messages::handle_chat(&state, &mut sender, &mut v, channel_id, &user_name).await;
+                            "list-scheduled" => {
+                                messages::handle_list_scheduled(&state, &mut sender, &user_name).await;
                             }
                             "delete-message" => {
                                 messages::handle_delete_message(&state, &mut sender, &v, channel_id, &user_name).await;
                             }
+                            "purge-user-messages" => {
+                                messages::handle_purge_user_messages(&state, &mut sender, &v, &user_name).await;
+                            }
                             "edit-message" => {
                                 messages::handle_edit_message(&state, &mut sender, &v, channel_id, &user_name).await;
                             }
-                            "react" => {
-                                messages::handle_react(&state, &mut sender, &v, &user_name).await;
-                            }
                             "status-update" => {
                                 handle_status_update(&state, &mut sender, &v, &user_name).await;
                             }
                             "set-avatar" => {
                                 profile::handle_set_avatar(&state, &mut sender, &v, &user_name).await;
                             }
+                            "set-profile" => {
+                                profile::handle_set_profile(&state, &mut sender, &v, &user_name).await;
+                            }
+                            "get-profile" => {
+                                profile::handle_get_profile(&state, &mut sender, &v, &user_name).await;
+                            }
                             "ping" => {
                                 handle_ping(&mut sender, &v).await;
                             }
+                            "list-users" => {
+                                handle_list_users(&state, &mut sender, &v).await;
+                            }
                             "get-server-info" => {
                                 handle_get_server_info(&state, &mut sender, &user_name).await;
                             }
@@ -233,21 +286,15 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                             "reset-stats" => {
                                 stats::handle_reset_stats(&state, &mut sender, &user_name).await;
                             }
-                            "voice-join" => {
-                                handle_voice_join(&state, &mut sender, &v, &mut voice_channel, &user_name).await;
-                            }
-                            "voice-leave" => {
-                                handle_voice_leave(&state, &v, &mut voice_channel, &user_name).await;
-                            }
                             // WebRTC signaling frames are relayed verbatim, so make sure a
                             // client can only speak for itself before rebroadcasting.
                             "voice-offer" | "voice-answer" | "voice-candidate" => {
-                                if claims_own_user(&v, &user_name) {
+                                if claims_own_user(&mut sender, v.get("user").and_then(|u| u.as_str()), &user_name, true).await {
                                     let _ = state.tx.send(text.to_string());
                                 }
                             }
                             "screenshare-start" => {
-                                if claims_own_user(&v, &user_name) {
+                                if claims_own_user(&mut sender, v.get("user").and_then(|u| u.as_str()), &user_name, true).await {
                                     handle_screenshare_start(&state, &v).await;
                                     if let Some(u) = user_name.as_deref() {
                                         stats::note_screenshare_start(&state, u).await;
@@ -256,7 +303,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                                 }
                             }
                             "screenshare-stop" => {
-                                if claims_own_user(&v, &user_name) {
+                                if claims_own_user(&mut sender, v.get("user").and_then(|u| u.as_str()), &user_name, true).await {
                                     handle_screenshare_stop(&state, &v).await;
                                     if let Some(u) = user_name.as_deref() {
                                         stats::flush_screenshare_session(&state, u).await;
@@ -265,7 +312,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                                 }
                             }
                             "screenshare-offer" | "screenshare-answer" | "screenshare-candidate" => {
-                                if claims_own_user(&v, &user_name) {
+                                if claims_own_user(&mut sender, v.get("user").and_then(|u| u.as_str()), &user_name, true).await {
                                     let _ = state.tx.send(text.to_string());
                                 }
                             }
@@ -273,7 +320,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                                 screenshare::handle_set_screenshare_max_bitrate(&state, &mut sender, &v, &user_name).await;
                             }
                             "voice-mute" => {
-                                if claims_own_user(&v, &user_name) {
+                                if claims_own_user(&mut sender, v.get("user").and_then(|u| u.as_str()), &user_name, true).await {
                                     handle_voice_mute(&state, &v).await;
                                     let _ = state.tx.send(text.to_string());
                                 }
@@ -314,6 +361,12 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                             "remove-channel-override" => {
                                 channel_overrides::handle_remove_channel_override(&state, &mut sender, &v, &user_name).await;
                             }
+                            "invite-to-channel" => {
+                                channel_overrides::handle_invite_to_channel(&state, &mut sender, &v, &user_name).await;
+                            }
+                            "remove-from-channel" => {
+                                channel_overrides::handle_remove_from_channel(&state, &mut sender, &v, &user_name).await;
+                            }
                             "get-channel-overrides" => {
                                 channel_overrides::handle_get_channel_overrides(&state, &mut sender, &v, &user_name).await;
                             }
@@ -332,13 +385,22 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                         }
                     }
                 } else {
-                    error!("invalid json message: {text}");
+                    // Not parsable, so not redactable either: the text may
+                    // hold a password, so only its size is logged.
+                    error!("invalid json message ({} bytes)", text.len());
                 }
             }
+            _ = heartbeat.tick() => {
+                if last_pong.elapsed() > heartbeat_timeout {
+                    warn!("No pong within {}s, dropping connection", heartbeat_timeout.as_secs());
+                    break;
+                }
+                if sender.send(Message::Ping(Default::default())).await.is_err() { break; }
+            }
             result = chan_rx.recv() => {
                 match result {
                     Ok(msg) => {
-                        if sender.send(Message::Text(msg.into())).await.is_err() { break; }
+                        if sender.send(encoding.frame(msg)).await.is_err() { break; }
                     }
                     Err(broadcast::error::RecvError::Lagged(_)) => {}
                     Err(_) => break,
@@ -353,6 +415,8 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                             || channel_frame_hint(&msg)
                             || msg.contains("channels-refresh")
                             || msg.contains("force-disconnect")
+                            || msg.contains("\"type\":\"flag-")
+                            || msg.contains("read-receipt-update")
                         {
                             serde_json::from_str::<Value>(&msg).ok()
                         } else {
@@ -374,6 +438,22 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                             {
                                 continue;
                             }
+                            // Read receipts only go to the author they describe.
+                            if frame_type == Some("read-receipt-update")
+                                && v.get("to").and_then(|t| t.as_str()) != user_name.as_deref()
+                            {
+                                continue;
+                            }
+                            // Flag notices are for moderators only.
+                            if flags::moderators_only(frame_type) {
+                                let moderator = match user_name.as_deref() {
+                                    Some(u) => has_permission(&state, u, crate::permissions::MANAGE_MESSAGES).await,
+                                    None => false,
+                                };
+                                if !moderator {
+                                    continue;
+                                }
+                            }
                             // Channel-scoped frames must not reach a user who
                             // cannot see the channel. Channels with no overrides
                             // are visible to everyone (fast path).
@@ -398,7 +478,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
                             v.get("type").and_then(|t| t.as_str()) == Some("force-disconnect")
                                 && v.get("user").and_then(|u| u.as_str()) == user_name.as_deref()
                         });
-                        if sender.send(Message::Text(msg.into())).await.is_err() { break; }
+                        if sender.send(encoding.frame(msg)).await.is_err() { break; }
                         if targets_this_user {
                             info!("Closing connection after force-disconnect");
                             break;
@@ -411,7 +491,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, peer_addr: std::
         }
     }
 
-    handle_disconnect(&state, user_name).await;
+    handle_disconnect(&state, user_name, voice_channel).await;
     info!(%client_ip, "Client disconnected");
 }
 
@@ -424,6 +504,13 @@ fn channel_frame_hint(msg: &str) -> bool {
     msg.contains("-notify")
         || msg.contains("channel-add")
         || msg.contains("channel-topic")
+        || msg.contains("channel-rename")
+        || msg.contains("channel-purged")
+        || msg.contains("channel-retention")
+        || msg.contains("slow-mode-update")
+        || msg.contains("channel-receipts")
+        || msg.contains("channel-max-subscribers")
+        || msg.contains("channel-post-roles")
         || msg.contains("channel-remove")
         || msg.contains("voice-channel-")
         || msg.contains("voice-users")
@@ -440,7 +527,17 @@ fn channel_frame_hint(msg: &str) -> bool {
 fn channel_scope(v: &Value) -> Option<(ChannelKind, i32)> {
     let ty = v.get("type").and_then(|t| t.as_str())?;
     let kind = match ty {
-        "message-notify" | "channel-add" | "channel-topic" | "channel-remove" => ChannelKind::Text,
+        "message-notify"
+        | "channel-add"
+        | "channel-topic"
+        | "channel-rename"
+        | "channel-remove"
+        | "channel-purged"
+        | "channel-retention"
+        | "channel-post-roles"
+        | "slow-mode-update"
+        | "channel-receipts"
+        | "channel-max-subscribers" => ChannelKind::Text,
         "voice-channel-add"
         | "voice-channel-update"
         | "voice-channel-remove"
@@ -455,15 +552,146 @@ fn channel_scope(v: &Value) -> Option<(ChannelKind, i32)> {
     Some((kind, id))
 }
 
-/// Whether a relayed frame's `user` field names the connection's own
-/// authenticated user. Prevents spoofing other users in signaling frames.
-fn claims_own_user(v: &Value, user_name: &Option<String>) -> bool {
-    match user_name.as_deref() {
-        Some(name) => v.get("user").and_then(|u| u.as_str()) == Some(name),
-        None => false,
+/// Run the handler for a frame decoded into its typed model.
+async fn dispatch_typed(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    msg: ClientMessage,
+    voice_channel: &mut Option<i32>,
+    user_name: &Option<String>,
+) {
+    match msg {
+        ClientMessage::React(m) => messages::handle_react(state, sender, &m, user_name).await,
+        ClientMessage::MarkRead(m) => {
+            messages::handle_mark_read(state, sender, &m, user_name).await
+        }
+        ClientMessage::PinMessage(m) => {
+            pins::handle_pin_message(state, sender, &m, user_name).await
+        }
+        ClientMessage::UnpinMessage(m) => {
+            pins::handle_unpin_message(state, sender, &m, user_name).await
+        }
+        ClientMessage::FlagMessage(m) => {
+            flags::handle_flag_message(state, sender, &m, user_name).await
+        }
+        ClientMessage::ResolveFlag(m) => {
+            flags::handle_resolve_flag(state, sender, &m, user_name).await
+        }
+        // Voice membership always follows the authenticated name; a frame
+        // naming someone else is refused.
+        ClientMessage::VoiceJoin(m) => {
+            if claims_own_user(sender, m.user.as_deref(), user_name, false).await {
+                handle_voice_join(state, sender, m.channel_id, voice_channel, user_name).await;
+            }
+        }
+        ClientMessage::VoiceLeave(m) => {
+            if claims_own_user(sender, m.user.as_deref(), user_name, false).await {
+                handle_voice_leave(state, voice_channel, user_name).await;
+            }
+        }
+        ClientMessage::WikiGet(m) => wiki::handle_wiki_get(state, sender, &m).await,
+        ClientMessage::WikiResolve(m) => wiki::handle_wiki_resolve(state, sender, &m).await,
+        ClientMessage::WikiCreate(m) => {
+            wiki::handle_wiki_create(state, sender, &m, user_name).await
+        }
+        ClientMessage::WikiUpdate(m) => {
+            wiki::handle_wiki_update(state, sender, &m, user_name).await
+        }
+        ClientMessage::WikiDelete(m) => {
+            wiki::handle_wiki_delete(state, sender, &m, user_name).await
+        }
+        ClientMessage::WikiRename(m) => {
+            wiki::handle_wiki_rename(state, sender, &m, user_name).await
+        }
+        ClientMessage::Dm(m) => dms::handle_dm(state, sender, &m, user_name).await,
+        ClientMessage::LoadDmHistory(m) => {
+            dms::handle_load_dm_history(state, sender, &m, user_name).await
+        }
+        ClientMessage::GetUserKey(m) => dms::handle_get_user_key(state, sender, &m).await,
+        ClientMessage::CreateChannel(m) => {
+            channels::handle_create_channel(state, sender, &m, user_name).await
+        }
+        ClientMessage::RenameChannel(m) => {
+            channels::handle_rename_channel(state, sender, &m, user_name).await
+        }
+        ClientMessage::MoveChannel(m) => {
+            channels::handle_move_channel(state, sender, &m, user_name).await
+        }
+        ClientMessage::ReorderChannels(m) => {
+            channels::handle_reorder_channels(state, sender, &m, user_name).await
+        }
+        ClientMessage::ReorderCategories(m) => {
+            channels::handle_reorder_categories(state, sender, &m, user_name).await
+        }
+        ClientMessage::SetChannelTopic(m) => {
+            channels::handle_set_channel_topic(state, sender, &m, user_name).await
+        }
+        ClientMessage::SetChannelRetention(m) => {
+            channels::handle_set_channel_retention(state, sender, &m, user_name).await
+        }
+        ClientMessage::SetSlowMode(m) => {
+            channels::handle_set_slow_mode(state, sender, &m, user_name).await
+        }
+        ClientMessage::SetChannelReceipts(m) => {
+            channels::handle_set_channel_receipts(state, sender, &m, user_name).await
+        }
+        ClientMessage::SetChannelMaxSubscribers(m) => {
+            channels::handle_set_channel_max_subscribers(state, sender, &m, user_name).await
+        }
+        ClientMessage::SetChannelPostRoles(m) => {
+            channels::handle_set_channel_post_roles(state, sender, &m, user_name).await
+        }
+        ClientMessage::CreateCategory(m) => {
+            channels::handle_create_category(state, sender, &m, user_name).await
+        }
+        ClientMessage::RenameCategory(m) => {
+            channels::handle_rename_category(state, sender, &m, user_name).await
+        }
+        ClientMessage::DeleteCategory(m) => {
+            channels::handle_delete_category(state, sender, m, user_name).await
+        }
+        ClientMessage::CreateVoiceChannel(m) => {
+            channels::handle_create_voice_channel(state, sender, &m, user_name).await
+        }
+        ClientMessage::UpdateVoiceChannel(m) => {
+            channels::handle_update_voice_channel(state, sender, &m, user_name).await
+        }
+        ClientMessage::DeleteVoiceChannel(m) => {
+            channels::handle_delete_voice_channel(state, sender, m, user_name, voice_channel).await
+        }
+        // Handled in the receive loop.
+        ClientMessage::Presence(_)
+        | ClientMessage::BotPresence(_)
+        | ClientMessage::Chat(_)
+        | ClientMessage::DeleteChannel(_)
+        | ClientMessage::Untyped => {}
     }
 }
 
+/// Whether the `claimed` user of a frame is the connection's own
+/// authenticated user. Prevents spoofing other users in voice and signaling
+/// frames. Frames relayed verbatim are `required` to carry the field; others
+/// may omit it. A frame claiming another identity is answered with
+/// `identity-mismatch`.
+async fn claims_own_user(
+    sender: &mut SplitSink<WebSocket, Message>,
+    claimed: Option<&str>,
+    user_name: &Option<String>,
+    required: bool,
+) -> bool {
+    let Some(name) = user_name.as_deref() else {
+        return false;
+    };
+    let own = match claimed {
+        None => !required,
+        Some(claimed) => claimed == name,
+    };
+    if !own {
+        send_error(sender, errors::IDENTITY_MISMATCH).await;
+    }
+    own
+}
+
 /// Handle status update request.
 async fn handle_status_update(
     state: &Arc<AppState>,
@@ -491,12 +719,40 @@ async fn handle_status_update(
 
     state
         .statuses
-        .lock()
+        .write()
         .await
         .insert(user.clone(), status.to_string());
     broadcast_status(state, &user, status).await;
 }
 
+/// Answer a `list-users` request with one page of the user directory.
+/// `query` filters by name substring; `limit` is clamped to
+/// [`MAX_USER_DIRECTORY_LIMIT`](super::constants::MAX_USER_DIRECTORY_LIMIT).
+async fn handle_list_users(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+) {
+    use super::constants::{DEFAULT_USER_DIRECTORY_LIMIT, MAX_USER_DIRECTORY_LIMIT};
+
+    let query = v.get("query").and_then(|q| q.as_str()).unwrap_or("").trim();
+    let offset = v.get("offset").and_then(|o| o.as_u64()).unwrap_or(0) as usize;
+    let limit = v
+        .get("limit")
+        .and_then(|l| l.as_u64())
+        .map_or(DEFAULT_USER_DIRECTORY_LIMIT, |l| l as usize)
+        .clamp(1, MAX_USER_DIRECTORY_LIMIT);
+    let (total, users) = user_directory_page(state, query, offset, limit).await;
+    let msg = serde_json::json!({
+        "type": "user-list",
+        "query": query,
+        "offset": offset,
+        "total": total,
+        "users": users,
+    });
+    let _ = sender.send(Message::Text(msg.to_string().into())).await;
+}
+
 /// Handle ping request.
 async fn handle_ping(sender: &mut SplitSink<WebSocket, Message>, v: &Value) {
     let id = v.get("id").cloned().unwrap_or(Value::Null);
@@ -524,6 +780,8 @@ async fn handle_get_server_info(
         return;
     }
 
+    // Always included: this frame is already limited to VIEW_SERVER_INFO, so
+    // HIDE_SERVER_VERSION (which targets anonymous surfaces) does not apply.
     let msg = serde_json::json!({
         "type": "server-info",
         "version": env!("CARGO_PKG_VERSION"),
@@ -607,95 +865,95 @@ async fn handle_get_connection_stats(
 async fn handle_voice_join(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    ch_id: i32,
     voice_channel: &mut Option<i32>,
     user_name: &Option<String>,
 ) {
     let Some(u) = user_name.as_deref() else {
         return;
     };
-    if let Some(ch_id) = v.get("channelId").and_then(|c| c.as_i64()) {
-        let ch_id = ch_id as i32;
-        // A private voice channel is join-gated by View (see + join).
-        if !can_view_channel(state, u, ChannelKind::Voice, ch_id).await {
-            return;
-        }
-        let mut map = state.voice_channels.lock().await;
-        for info in map.values_mut() {
-            info.users.remove(u);
-        }
-        let joined = match map.get_mut(&ch_id) {
-            Some(entry) => {
-                entry.users.insert(u.to_string());
-                *voice_channel = Some(ch_id);
-                true
-            }
-            None => false,
-        };
-        drop(map);
-        if !joined {
+    // A private voice channel is join-gated by View (see + join).
+    if !can_view_channel(state, u, ChannelKind::Voice, ch_id).await {
+        return;
+    }
+    let left = match join_voice_channel(
+        state,
+        u,
+        ch_id,
+        crate::config::max_voice_mesh_participants(),
+    )
+    .await
+    {
+        VoiceJoin::Joined { left } => left,
+        VoiceJoin::Full => {
+            send_error(sender, errors::VOICE_MESH_LIMIT).await;
             return;
         }
-        stats::note_voice_join(state, u).await;
-        broadcast_voice(state, ch_id).await;
-        let msg = serde_json::json!({
-            "type": "voice-join",
-            "user": u,
-            "channelId": ch_id,
-        });
-        let _ = state.tx.send(msg.to_string());
+        VoiceJoin::UnknownChannel => return,
+    };
+    *voice_channel = Some(ch_id);
+    for old in left {
+        broadcast_voice(state, old).await;
+    }
+    stats::note_voice_join(state, u).await;
+    broadcast_voice(state, ch_id).await;
+    let msg = serde_json::json!({
+        "type": "voice-join",
+        "user": u,
+        "channelId": ch_id,
+    });
+    let _ = state.tx.send(msg.to_string());
 
-        // Tell the joiner whether they may speak here (Talk = SEND in the
-        // channel). Voice audio is peer-to-peer, so the client enforces this by
-        // disabling its microphone; the server enforces View/join only.
-        let can_speak = has_channel_permission(
-            state,
-            u,
-            ChannelKind::Voice,
-            ch_id,
-            crate::permissions::SEND_MESSAGES,
-        )
-        .await;
-        let perms = serde_json::json!({
-            "type": "voice-permissions",
-            "channelId": ch_id,
-            "canSpeak": can_speak,
-        });
-        let _ = sender.send(Message::Text(perms.to_string().into())).await;
+    // Tell the joiner whether they may speak here (Talk = SEND in the
+    // channel). Voice audio is peer-to-peer, so the client enforces this by
+    // disabling its microphone; the server enforces View/join only.
+    let can_speak = has_channel_permission(
+        state,
+        u,
+        ChannelKind::Voice,
+        ch_id,
+        crate::permissions::SEND_MESSAGES,
+    )
+    .await;
+    let perms = serde_json::json!({
+        "type": "voice-permissions",
+        "channelId": ch_id,
+        "canSpeak": can_speak,
+    });
+    let _ = sender.send(Message::Text(perms.to_string().into())).await;
 
-        send_active_screen_shares(state, sender, ch_id).await;
-        send_voice_mutes(state, sender, ch_id).await;
-    }
+    send_active_screen_shares(state, sender, ch_id).await;
+    send_voice_mutes(state, sender, ch_id).await;
 }
 
 /// Handle voice leave request.
 async fn handle_voice_leave(
     state: &Arc<AppState>,
-    v: &Value,
     voice_channel: &mut Option<i32>,
     user_name: &Option<String>,
 ) {
     let Some(u) = user_name.as_deref() else {
         return;
     };
-    if let Some(ch_id) = v.get("channelId").and_then(|c| c.as_i64()) {
-        let ch_id = ch_id as i32;
-        let mut map = state.voice_channels.lock().await;
-        if let Some(info) = map.get_mut(&ch_id) {
-            info.users.remove(u);
-        }
-        drop(map);
-        state.voice_mutes.lock().await.remove(u);
-        stats::flush_voice_session(state, u).await;
-        stats::flush_screenshare_session(state, u).await;
-        end_screen_shares_for_user(state, u).await;
+    // A user is in at most one voice channel, so leaving always empties their
+    // occupancy rather than trusting the requested id to be the current one.
+    *voice_channel = None;
+    end_voice_session(state, u).await;
+}
+
+/// Take `user` out of voice: leave their channel, end their screen shares
+/// and bank the session time, then tell everyone.
+async fn end_voice_session(state: &Arc<AppState>, user: &str) {
+    let left = leave_voice_channels(state, user).await;
+    state.voice_mutes.lock().await.remove(user);
+    stats::flush_voice_session(state, user).await;
+    stats::flush_screenshare_session(state, user).await;
+    end_screen_shares_for_user(state, user).await;
+    for ch_id in left {
         broadcast_voice(state, ch_id).await;
-        if *voice_channel == Some(ch_id) {
-            *voice_channel = None;
-        }
         let msg = serde_json::json!({
             "type": "voice-leave",
-            "user": u,
+            "user": user,
             "channelId": ch_id,
         });
         let _ = state.tx.send(msg.to_string());
@@ -794,7 +1052,7 @@ async fn send_voice_mutes(
     channel_id: i32,
 ) {
     let members: HashSet<String> = {
-        let channels = state.voice_channels.lock().await;
+        let channels = state.voice_channels.read().await;
         channels
             .get(&channel_id)
             .map(|info| info.users.clone())
@@ -855,43 +1113,44 @@ async fn send_active_screen_shares(
 }
 
 /// Handle client disconnect cleanup.
-async fn handle_disconnect(state: &Arc<AppState>, user_name: Option<String>) {
-    if let Some(name) = user_name {
-        state.users.lock().await.remove(&name);
-        broadcast_users(state).await;
-
-        // Bank any running voice/screen-share session time before the
-        // in-memory session markers are dropped.
-        stats::flush_voice_session(state, &name).await;
-        stats::flush_screenshare_session(state, &name).await;
-
-        let mut map = state.voice_channels.lock().await;
-        let mut ch_to_broadcast = None;
-        for (id, info) in map.iter_mut() {
-            if info.users.remove(&name) {
-                ch_to_broadcast = Some(*id);
-                break;
-            }
-        }
-        drop(map);
-
-        if let Some(ch_id) = ch_to_broadcast {
-            broadcast_voice(state, ch_id).await;
-        }
-
-        state.voice_mutes.lock().await.remove(&name);
-        state.connection_stats.lock().await.remove(&name);
-
-        // Clean up any active screen shares owned by the disconnecting user.
-        end_screen_shares_for_user(state, &name).await;
-
-        state
-            .statuses
-            .lock()
+async fn handle_disconnect(
+    state: &Arc<AppState>,
+    user_name: Option<String>,
+    voice_channel: Option<i32>,
+) {
+    let Some(name) = user_name else {
+        return;
+    };
+    let last = release_connection(state, &name).await;
+    // The voice membership this socket holds ends with it, even when another
+    // tab or device keeps the user online. A voice channel joined later from
+    // another socket is left alone.
+    let in_voice_here = match voice_channel {
+        Some(ch_id) => state
+            .voice_channels
+            .read()
             .await
-            .insert(name.clone(), "offline".to_string());
-        broadcast_status(state, &name, "offline").await;
+            .get(&ch_id)
+            .is_some_and(|info| info.users.contains(&name)),
+        None => false,
+    };
+    if last || in_voice_here {
+        end_voice_session(state, &name).await;
+    }
+    if !last {
+        return;
     }
+    state.users.lock().await.remove(&name);
+    broadcast_users(state).await;
+    state.connection_stats.lock().await.remove(&name);
+
+    record_last_seen(state, &name).await;
+    state
+        .statuses
+        .write()
+        .await
+        .insert(name.clone(), "offline".to_string());
+    broadcast_status(state, &name, "offline").await;
 }
 
 /// Axum handler that upgrades the HTTP connection to a WebSocket and spawns message processing.
@@ -900,6 +1159,23 @@ pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
     ConnectInfo(addr): ConnectInfo<std::net::SocketAddr>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state, addr))
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(rejection) = insecure_upgrade_rejection(addr.ip(), &uri, &headers) {
+        return rejection;
+    }
+    let Some(slot) = crate::security::acquire_connection_slot(&state.rate_limiter, addr.ip())
+    else {
+        return (
+            axum::http::StatusCode::TOO_MANY_REQUESTS,
+            "Too many connections from this address",
+        )
+            .into_response();
+    };
+    ws.on_upgrade(move |socket| async move {
+        // Held for the socket's lifetime; dropping it frees the IP's slot.
+        let _slot = slot;
+        handle_socket(socket, state, addr).await
+    })
 }
@@ -35,9 +35,7 @@ async fn check_allowed(
         return false;
     }
 
-    let requester_position = top_position(state, requester).await;
-    let target_position = top_position(state, target).await;
-    if requester_position <= target_position {
+    if !outranks(state, requester, target).await {
         send_error(sender, errors::MODERATION_TARGET_PROTECTED).await;
         return false;
     }
@@ -128,6 +126,13 @@ pub(super) async fn handle_kick_user(
     }
 
     broadcast_force_disconnect(state, &target, "kicked", &requester);
+    record_audit(
+        state,
+        &requester,
+        "kick-user",
+        Some(&target),
+        serde_json::json!({}),
+    );
     info!(requester, target, "User kicked");
 }
 
@@ -166,6 +171,13 @@ pub(super) async fn handle_ban_user(
     }
 
     broadcast_force_disconnect(state, &target, "banned", &requester);
+    record_audit(
+        state,
+        &requester,
+        "ban-user",
+        Some(&target),
+        serde_json::json!({}),
+    );
     info!(requester, target, "User banned");
 }
 
@@ -202,6 +214,13 @@ pub(super) async fn handle_unban_user(
                 "by": requester,
             });
             let _ = state.tx.send(msg.to_string());
+            record_audit(
+                state,
+                &requester,
+                "unban-user",
+                Some(&target),
+                serde_json::json!({}),
+            );
             info!(requester, target, "User unbanned");
         }
         Ok(false) => {
@@ -266,6 +285,13 @@ pub(super) async fn handle_mute_user(
         "until": until.map(|value| value.to_rfc3339()),
     });
     let _ = state.tx.send(msg.to_string());
+    record_audit(
+        state,
+        &requester,
+        "mute-user",
+        Some(&target),
+        serde_json::json!({ "until": until.map(|value| value.to_rfc3339()) }),
+    );
     info!(requester, target, ?until, "User muted");
 }
 
@@ -308,6 +334,13 @@ pub(super) async fn handle_unmute_user(
                 "by": requester,
             });
             let _ = state.tx.send(msg.to_string());
+            record_audit(
+                state,
+                &requester,
+                "unmute-user",
+                Some(&target),
+                serde_json::json!({}),
+            );
             info!(requester, target, "User unmuted");
         }
         Ok(_) => {
@@ -319,36 +352,3 @@ pub(super) async fn handle_unmute_user(
         }
     }
 }
-
-/// Check whether `user` is currently muted. Expired mutes are lazily removed
-/// from both the in-memory map and the database.
-pub(super) async fn is_muted(state: &Arc<AppState>, user: &str) -> bool {
-    let key = {
-        let keys = state.user_keys.lock().await;
-        keys.get(user).cloned()
-    };
-    let Some(key) = key else {
-        return false;
-    };
-
-    let entry = {
-        let mutes = state.mutes.lock().await;
-        match mutes.get(&key) {
-            Some(entry) => *entry,
-            None => return false,
-        }
-    };
-
-    match entry {
-        None => true,
-        Some(until) if until > Utc::now() => true,
-        Some(_) => {
-            // The mute has expired: clean it up.
-            state.mutes.lock().await.remove(&key);
-            if let Err(e) = db::remove_mute_by_key(&state.db, &key).await {
-                error!("Failed to remove expired mute for {user}: {e}");
-            }
-            false
-        }
-    }
-}
@@ -5,11 +5,10 @@
 //! joining a channel, so all clients converge on the persisted state.
 
 use crate::channel_overrides::ChannelKind;
-use crate::ws::{constants::*, errors, helpers::*};
+use crate::ws::{constants::*, errors, helpers::*, inbound::MessageTarget};
 use crate::{AppState, db};
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, stream::SplitSink};
-use serde_json::Value;
 use std::sync::Arc;
 use tracing::{error, info};
 
@@ -50,28 +49,17 @@ async fn broadcast_pins(state: &Arc<AppState>, channel_id: i32) {
     }
 }
 
-/// Extract the target message id or reply with a pin error.
-async fn require_message_id(sender: &mut SplitSink<WebSocket, Message>, v: &Value) -> Option<i64> {
-    let id = v.get("messageId").and_then(|m| m.as_i64());
-    if id.is_none() {
-        send_error(sender, errors::PIN_TARGET_NOT_FOUND).await;
-    }
-    id
-}
-
 /// Handle pin-message request: persist the pin and broadcast the new list.
 pub(super) async fn handle_pin_message(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &MessageTarget,
     user_name: &Option<String>,
 ) {
     let Some(user) = user_name.clone() else {
         return;
     };
-    let Some(message_id) = require_message_id(sender, v).await else {
-        return;
-    };
+    let message_id = msg.message_id;
 
     match db::get_message_record(&state.db, message_id).await {
         Ok(Some(record)) => {
@@ -116,15 +104,13 @@ pub(super) async fn handle_pin_message(
 pub(super) async fn handle_unpin_message(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &MessageTarget,
     user_name: &Option<String>,
 ) {
     let Some(user) = user_name.clone() else {
         return;
     };
-    let Some(message_id) = require_message_id(sender, v).await else {
-        return;
-    };
+    let message_id = msg.message_id;
 
     // Only act on a pin in a channel the user can see.
     if let Ok(Some(record)) = db::get_message_record(&state.db, message_id).await
@@ -1,11 +1,17 @@
-//! Handlers for the user profile (currently the avatar).
+//! Handlers for the user profile: avatar, display name and bio.
 //!
 //! Avatars travel through the regular `/upload` endpoint (which enforces the
 //! image safe-list and magic-byte validation) and are registered here by URL,
 //! mirroring the server icon. Every user may only set their own avatar; the
 //! value persists on the user's name/key binding, is broadcast on change and
 //! snapshotted to every client after authentication.
+//!
+//! Display name and bio live in the `profiles` table keyed by public key and
+//! follow the same pattern: `set-profile` edits the requester's own card and
+//! broadcasts `profile-update`, and new clients receive a `profile-snapshot`.
+//! `get-profile` fetches one user's full card on demand.
 
+use crate::identicon::identicon_url;
 use crate::ws::{constants::*, errors, helpers::*, validation::*};
 use crate::{AppState, db};
 use axum::extract::ws::{Message, WebSocket};
@@ -14,28 +20,37 @@ use serde_json::Value;
 use std::sync::Arc;
 use tracing::{error, info};
 
-/// Send all configured avatars to a newly connected client.
+/// Send all configured avatars to a newly connected client, plus the
+/// identicon URL of every bound user as the fallback for those without one.
 pub(super) async fn send_all_avatars(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
 ) {
-    let avatars = match db::get_all_avatars(&state.db).await {
-        Ok(list) => list,
-        Err(e) => {
+    let (avatars, keys) = match (
+        db::get_all_avatars(&state.db).await,
+        db::get_all_user_keys(&state.db).await,
+    ) {
+        (Ok(avatars), Ok(keys)) => (avatars, keys),
+        (Err(e), _) | (_, Err(e)) => {
             error!("Failed to load avatars: {e}");
             return;
         }
     };
-    if avatars.is_empty() {
+    if keys.is_empty() {
         return;
     }
     let map: serde_json::Map<String, Value> = avatars
         .into_iter()
         .map(|(user, avatar)| (user, Value::String(avatar)))
         .collect();
+    let identicons: serde_json::Map<String, Value> = keys
+        .into_iter()
+        .filter_map(|(user, key)| Some((user, Value::String(identicon_url(&key)?))))
+        .collect();
     if let Ok(msg) = serde_json::to_string(&serde_json::json!({
         "type": "avatar-snapshot",
         "avatars": map,
+        "identicons": identicons,
     })) {
         let _ = sender.send(Message::Text(msg.into())).await;
     }
@@ -75,21 +90,31 @@ pub(super) async fn handle_set_avatar(
         send_error(sender, errors::AVATAR_UPDATE_FAILED).await;
         return;
     };
+    update_avatar(state, sender, v, requester).await;
+}
 
+/// Apply the `avatar` field of `v` for `requester`. Returns `false` after
+/// reporting an error; a missing field is a successful no-op.
+async fn update_avatar(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    requester: &str,
+) -> bool {
     let new_avatar = match v.get("avatar") {
         Some(raw) if raw.is_null() => String::new(),
         Some(raw) => {
             let Some(url) = raw.as_str() else {
                 send_error(sender, errors::INVALID_AVATAR).await;
-                return;
+                return false;
             };
             let Some(validated) = validate_avatar_url(state, url).await else {
                 send_error(sender, errors::INVALID_AVATAR).await;
-                return;
+                return false;
             };
             validated
         }
-        None => return,
+        None => return true,
     };
 
     let old_avatar = match db::get_user_avatar(&state.db, requester).await {
@@ -97,7 +122,7 @@ pub(super) async fn handle_set_avatar(
         Err(e) => {
             error!("Failed to load current avatar for {requester}: {e}");
             send_error(sender, errors::AVATAR_UPDATE_FAILED).await;
-            return;
+            return false;
         }
     };
 
@@ -107,12 +132,12 @@ pub(super) async fn handle_set_avatar(
         // profile to attach an avatar to.
         Ok(false) => {
             send_error(sender, errors::AVATAR_UPDATE_FAILED).await;
-            return;
+            return false;
         }
         Err(e) => {
             error!("Failed to store avatar for {requester}: {e}");
             send_error(sender, errors::AVATAR_UPDATE_FAILED).await;
-            return;
+            return false;
         }
     }
 
@@ -132,4 +157,165 @@ pub(super) async fn handle_set_avatar(
     info!(requester, "Avatar updated");
     let avatar = (!new_avatar.is_empty()).then_some(new_avatar);
     broadcast_avatar(state, requester, avatar.as_deref()).await;
+    true
+}
+
+/// Send every display name and bio to a newly connected client.
+pub(super) async fn send_all_profiles(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+) {
+    let profiles = match db::get_all_profiles(&state.db).await {
+        Ok(list) => list,
+        Err(e) => {
+            error!("Failed to load profiles: {e}");
+            return;
+        }
+    };
+    if profiles.is_empty() {
+        return;
+    }
+    let map: serde_json::Map<String, Value> = profiles
+        .into_iter()
+        .map(|p| {
+            let card = serde_json::json!({ "displayName": p.display_name, "bio": p.bio });
+            (p.user_name, card)
+        })
+        .collect();
+    if let Ok(msg) = serde_json::to_string(&serde_json::json!({
+        "type": "profile-snapshot",
+        "profiles": map,
+    })) {
+        let _ = sender.send(Message::Text(msg.into())).await;
+    }
+}
+
+/// Read an optional profile text field: absent leaves it unchanged (`None`),
+/// `null` clears it, and a string is trimmed and must pass `valid`. `Err`
+/// means the value was rejected.
+fn profile_field(v: &Value, key: &str, valid: fn(&str) -> bool) -> Result<Option<String>, ()> {
+    match v.get(key) {
+        None => Ok(None),
+        Some(Value::Null) => Ok(Some(String::new())),
+        Some(raw) => match raw.as_str().map(str::trim) {
+            Some(text) if valid(text) => Ok(Some(text.to_owned())),
+            _ => Err(()),
+        },
+    }
+}
+
+/// Handle `set-profile`: update the requester's own `displayName` and `bio`.
+/// Omitted fields keep their value and `null` or an empty string clears
+/// them; an `avatar` field is applied as by `set-avatar`.
+pub(super) async fn handle_set_profile(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let Some(requester) = user_name.as_deref() else {
+        send_error(sender, errors::PROFILE_UPDATE_FAILED).await;
+        return;
+    };
+    let Ok(display_name) = profile_field(v, "displayName", validate_display_name) else {
+        send_error(sender, errors::INVALID_DISPLAY_NAME).await;
+        return;
+    };
+    let Ok(bio) = profile_field(v, "bio", validate_bio) else {
+        send_error(sender, errors::INVALID_BIO).await;
+        return;
+    };
+
+    // Bots and half-authenticated sessions have no key to hang a profile on.
+    let public_key = match db::get_user_key(&state.db, requester).await {
+        Ok(Some(key)) => key,
+        Ok(None) => {
+            send_error(sender, errors::PROFILE_UPDATE_FAILED).await;
+            return;
+        }
+        Err(e) => {
+            error!("Failed to load key for {requester}: {e}");
+            send_error(sender, errors::PROFILE_UPDATE_FAILED).await;
+            return;
+        }
+    };
+
+    if !update_avatar(state, sender, v, requester).await {
+        return;
+    }
+    if display_name.is_none() && bio.is_none() {
+        return;
+    }
+
+    let current = match db::get_profile(&state.db, requester).await {
+        Ok(profile) => profile.unwrap_or_default(),
+        Err(e) => {
+            error!("Failed to load profile for {requester}: {e}");
+            send_error(sender, errors::PROFILE_UPDATE_FAILED).await;
+            return;
+        }
+    };
+    let display_name = display_name.unwrap_or(current.display_name);
+    let bio = bio.unwrap_or(current.bio);
+    if let Err(e) = db::upsert_profile(&state.db, &public_key, &display_name, &bio).await {
+        error!("Failed to store profile for {requester}: {e}");
+        send_error(sender, errors::PROFILE_UPDATE_FAILED).await;
+        return;
+    }
+
+    info!(requester, "Profile updated");
+    let payload = serde_json::json!({
+        "type": "profile-update",
+        "user": requester,
+        "displayName": display_name,
+        "bio": bio,
+    });
+    let _ = state.tx.send(payload.to_string());
+}
+
+/// Handle `get-profile`: send the full card of `user` (the requester when
+/// omitted), including their avatar and role ids.
+pub(super) async fn handle_get_profile(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    v: &Value,
+    user_name: &Option<String>,
+) {
+    let Some(target) = v
+        .get("user")
+        .and_then(|u| u.as_str())
+        .or(user_name.as_deref())
+    else {
+        send_error(sender, errors::PROFILE_NOT_FOUND).await;
+        return;
+    };
+    let profile = match db::get_profile(&state.db, target).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => {
+            send_error(sender, errors::PROFILE_NOT_FOUND).await;
+            return;
+        }
+        Err(e) => {
+            error!("Failed to load profile for {target}: {e}");
+            send_error(sender, errors::PROFILE_NOT_FOUND).await;
+            return;
+        }
+    };
+    let role_ids = state
+        .user_roles
+        .read()
+        .await
+        .get(target)
+        .cloned()
+        .unwrap_or_default();
+    let avatar = (!profile.avatar.is_empty()).then_some(profile.avatar);
+    let payload = serde_json::json!({
+        "type": "profile",
+        "user": profile.user_name,
+        "displayName": profile.display_name,
+        "bio": profile.bio,
+        "avatar": avatar,
+        "roleIds": role_ids,
+    });
+    let _ = sender.send(Message::Text(payload.to_string().into())).await;
 }
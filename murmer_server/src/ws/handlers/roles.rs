@@ -92,7 +92,7 @@ async fn parse_color(
 
 /// Snapshot the current role definitions from the in-memory map.
 async fn snapshot_defs(state: &Arc<AppState>) -> Vec<RoleDef> {
-    state.role_defs.lock().await.values().cloned().collect()
+    state.role_defs.read().await.values().cloned().collect()
 }
 
 /// Handle create-role: define a new custom role.
@@ -169,7 +169,7 @@ pub(super) async fn handle_create_role(
         is_default: false,
         is_owner: false,
     };
-    state.role_defs.lock().await.insert(id, def);
+    state.role_defs.write().await.insert(id, def);
     broadcast_role_definitions(state).await;
     info!(requester, role = name, "Role created");
 }
@@ -190,7 +190,7 @@ pub(super) async fn handle_update_role(
         send_error(sender, errors::ROLE_NOT_FOUND).await;
         return;
     };
-    let Some(target) = state.role_defs.lock().await.get(&id).cloned() else {
+    let Some(target) = state.role_defs.read().await.get(&id).cloned() else {
         send_error(sender, errors::ROLE_NOT_FOUND).await;
         return;
     };
@@ -219,7 +219,7 @@ pub(super) async fn handle_update_role(
     if !name.eq_ignore_ascii_case(&target.name) {
         let taken = state
             .role_defs
-            .lock()
+            .read()
             .await
             .values()
             .any(|d| d.id != id && d.name.eq_ignore_ascii_case(&name));
@@ -261,7 +261,7 @@ pub(super) async fn handle_update_role(
         return;
     }
     {
-        let mut map = state.role_defs.lock().await;
+        let mut map = state.role_defs.write().await;
         if let Some(def) = map.get_mut(&id) {
             def.name = name.clone();
             def.color = color;
@@ -288,7 +288,7 @@ pub(super) async fn handle_delete_role(
         send_error(sender, errors::ROLE_NOT_FOUND).await;
         return;
     };
-    let Some(target) = state.role_defs.lock().await.get(&id).cloned() else {
+    let Some(target) = state.role_defs.read().await.get(&id).cloned() else {
         send_error(sender, errors::ROLE_NOT_FOUND).await;
         return;
     };
@@ -314,12 +314,12 @@ pub(super) async fn handle_delete_role(
         }
     }
 
-    state.role_defs.lock().await.remove(&id);
+    state.role_defs.write().await.remove(&id);
 
     // Detach the role from every in-memory assignment and note who changed so
     // their clients can update.
     let affected: Vec<(String, Vec<i64>)> = {
-        let mut assignments = state.user_roles.lock().await;
+        let mut assignments = state.user_roles.write().await;
         let mut changed = Vec::new();
         for (user, ids) in assignments.iter_mut() {
             if let Some(pos) = ids.iter().position(|&x| x == id) {
@@ -361,7 +361,7 @@ pub(super) async fn handle_reorder_roles(
         return;
     }
 
-    let defs = state.role_defs.lock().await.clone();
+    let defs = state.role_defs.read().await.clone();
     for id in &ids {
         let Some(def) = defs.get(id) else {
             send_error(sender, errors::ROLE_NOT_FOUND).await;
@@ -395,7 +395,7 @@ pub(super) async fn handle_reorder_roles(
     }
 
     {
-        let mut map = state.role_defs.lock().await;
+        let mut map = state.role_defs.write().await;
         for (index, id) in ids.iter().enumerate() {
             if let Some(def) = map.get_mut(id) {
                 def.position = n - index as i64;
@@ -451,7 +451,7 @@ pub(super) async fn handle_set_user_roles(
         return;
     }
 
-    let defs = state.role_defs.lock().await.clone();
+    let defs = state.role_defs.read().await.clone();
     for id in &ids {
         let Some(def) = defs.get(id) else {
             send_error(sender, errors::ROLE_NOT_FOUND).await;
@@ -476,9 +476,16 @@ pub(super) async fn handle_set_user_roles(
     }
     state
         .user_roles
-        .lock()
+        .write()
         .await
         .insert(target_user.to_string(), ids.clone());
     broadcast_user_roles(state, target_user, &ids).await;
+    record_audit(
+        state,
+        &requester,
+        "set-user-roles",
+        Some(target_user),
+        serde_json::json!({ "roles": ids }),
+    );
     info!(requester, target_user, "User roles updated");
 }
@@ -7,7 +7,13 @@
 //! persisted state. Page bodies travel only in `wiki-page`/`wiki-conflict`
 //! replies correlated by `requestId`.
 
-use crate::ws::{constants::*, errors, helpers::*, validation::*};
+use crate::ws::{
+    constants::*,
+    errors,
+    helpers::*,
+    inbound::{WikiCreateMsg, WikiPageMsg, WikiRenameMsg, WikiResolveMsg, WikiUpdateMsg},
+    validation::*,
+};
 use crate::{AppState, db, security};
 use axum::extract::ws::{Message, WebSocket};
 use futures::{SinkExt, stream::SplitSink};
@@ -80,13 +86,6 @@ async fn broadcast_wiki_index(state: &Arc<AppState>, channel_id: i32) {
     }
 }
 
-/// Extract the target channel id from a wiki request.
-fn channel_id_of(v: &Value) -> Option<i32> {
-    v.get("channelId")
-        .and_then(|c| c.as_i64())
-        .map(|c| c as i32)
-}
-
 /// Resolve the requester name and check the channel-management role gate.
 /// Sends a permission error and returns `None` when the check fails.
 async fn require_wiki_writer(
@@ -101,7 +100,13 @@ async fn require_wiki_writer(
             return None;
         }
     };
-    if !security::check_message_rate_limit(&state.rate_limiter, requester).await {
+    if !security::check_message_rate_limit(
+        &state.rate_limiter,
+        requester,
+        &user_role_names(state, requester).await,
+    )
+    .await
+    {
         send_error(sender, errors::MESSAGE_RATE_LIMIT).await;
         return None;
     }
@@ -118,15 +123,11 @@ async fn require_wiki_writer(
 pub(super) async fn handle_wiki_get(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &WikiPageMsg,
 ) {
-    let request_id = v.get("requestId").cloned().unwrap_or(Value::Null);
-    let Some(channel_id) = channel_id_of(v) else {
-        return;
-    };
-    let Some(slug) = v.get("slug").and_then(|s| s.as_str()) else {
-        return;
-    };
+    let request_id = &msg.request_id;
+    let channel_id = msg.channel_id;
+    let slug = msg.slug.as_str();
 
     match db::get_wiki_page(&state.db, channel_id, slug).await {
         Ok(page) => {
@@ -150,21 +151,14 @@ pub(super) async fn handle_wiki_get(
 pub(super) async fn handle_wiki_resolve(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &WikiResolveMsg,
 ) {
-    let request_id = v.get("requestId").cloned().unwrap_or(Value::Null);
-    let Some(links) = v.get("links").and_then(|l| l.as_array()) else {
-        return;
-    };
-
-    let pairs: Vec<(String, String)> = links
+    let request_id = &msg.request_id;
+    let pairs: Vec<(String, String)> = msg
+        .links
         .iter()
         .take(MAX_WIKI_RESOLVE_LINKS)
-        .filter_map(|link| {
-            let channel = link.get("channel")?.as_str()?;
-            let slug = link.get("slug")?.as_str()?;
-            Some((channel.to_owned(), slug.to_owned()))
-        })
+        .map(|link| (link.channel.clone(), link.slug.clone()))
         .collect();
 
     match db::resolve_wiki_links(&state.db, pairs.clone()).await {
@@ -197,25 +191,18 @@ pub(super) async fn handle_wiki_resolve(
 pub(super) async fn handle_wiki_create(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &WikiCreateMsg,
     user_name: &Option<String>,
 ) {
-    let Some(channel_id) = channel_id_of(v) else {
-        return;
-    };
-    let Some(slug) = v.get("slug").and_then(|s| s.as_str()) else {
-        return;
-    };
-    let Some(title) = v.get("title").and_then(|t| t.as_str()) else {
-        return;
-    };
-    let body = v.get("body").and_then(|b| b.as_str()).unwrap_or("");
+    let channel_id = msg.channel_id;
+    let slug = msg.slug.as_str();
+    let body = msg.body.as_str();
 
     if !validate_wiki_slug(slug) {
         send_error(sender, errors::INVALID_WIKI_SLUG).await;
         return;
     }
-    let title = title.trim();
+    let title = msg.title.trim();
     if !validate_wiki_title(title) {
         send_error(sender, errors::INVALID_WIKI_TITLE).await;
         return;
@@ -263,27 +250,15 @@ pub(super) async fn handle_wiki_create(
 pub(super) async fn handle_wiki_update(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &WikiUpdateMsg,
     user_name: &Option<String>,
 ) {
-    let request_id = v.get("requestId").cloned().unwrap_or(Value::Null);
-    let Some(channel_id) = channel_id_of(v) else {
-        return;
-    };
-    let Some(slug) = v.get("slug").and_then(|s| s.as_str()) else {
-        return;
-    };
-    let Some(title) = v.get("title").and_then(|t| t.as_str()) else {
-        return;
-    };
-    let Some(body) = v.get("body").and_then(|b| b.as_str()) else {
-        return;
-    };
-    let Some(expected_revision) = v.get("expectedRevision").and_then(|r| r.as_i64()) else {
-        return;
-    };
+    let request_id = &msg.request_id;
+    let channel_id = msg.channel_id;
+    let slug = msg.slug.as_str();
+    let body = msg.body.as_str();
 
-    let title = title.trim();
+    let title = msg.title.trim();
     if !validate_wiki_title(title) {
         send_error(sender, errors::INVALID_WIKI_TITLE).await;
         return;
@@ -304,7 +279,7 @@ pub(super) async fn handle_wiki_update(
         title,
         body,
         &user,
-        expected_revision,
+        msg.expected_revision,
         MAX_WIKI_REVISIONS_KEPT,
     )
     .await
@@ -345,15 +320,11 @@ pub(super) async fn handle_wiki_update(
 pub(super) async fn handle_wiki_delete(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &WikiPageMsg,
     user_name: &Option<String>,
 ) {
-    let Some(channel_id) = channel_id_of(v) else {
-        return;
-    };
-    let Some(slug) = v.get("slug").and_then(|s| s.as_str()) else {
-        return;
-    };
+    let channel_id = msg.channel_id;
+    let slug = msg.slug.as_str();
 
     let Some(user) = require_wiki_writer(state, sender, user_name).await else {
         return;
@@ -379,18 +350,12 @@ pub(super) async fn handle_wiki_delete(
 pub(super) async fn handle_wiki_rename(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
-    v: &Value,
+    msg: &WikiRenameMsg,
     user_name: &Option<String>,
 ) {
-    let Some(channel_id) = channel_id_of(v) else {
-        return;
-    };
-    let Some(slug) = v.get("slug").and_then(|s| s.as_str()) else {
-        return;
-    };
-    let Some(new_slug) = v.get("newSlug").and_then(|s| s.as_str()) else {
-        return;
-    };
+    let channel_id = msg.channel_id;
+    let slug = msg.slug.as_str();
+    let new_slug = msg.new_slug.as_str();
 
     if !validate_wiki_slug(new_slug) {
         send_error(sender, errors::INVALID_WIKI_SLUG).await;
@@ -3,15 +3,17 @@
 use crate::channel_overrides::ChannelKind;
 use crate::permissions::{self, Permissions};
 use crate::roles::RoleDef;
+use crate::word_filter::Filtered;
 use crate::{AppState, VoiceChannelState, db};
 use axum::extract::ws::{Message, WebSocket};
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use futures::SinkExt;
 use futures::stream::SplitSink;
 use serde_json::{Map, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, hash_map::Entry};
 use std::sync::Arc;
-use tracing::error;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
 
 /// Send a pre-serialized error frame (see [`crate::ws::errors`]) to one client.
 /// Send failures are ignored; the socket loop notices a dead connection itself.
@@ -28,40 +30,95 @@ pub async fn get_user_lists(state: &Arc<AppState>) -> (Vec<String>, Vec<String>)
         users.iter().cloned().collect()
     };
     let all = {
-        let known = state.known_users.lock().await;
+        let known = state.known_users.read().await;
         known.iter().cloned().collect()
     };
     (online, all)
 }
 
 /// Broadcast the current list of online users to all connected clients.
+/// Offline users are not included; clients page through them with
+/// `list-users` (see [`user_directory_page`]).
 pub async fn broadcast_users(state: &Arc<AppState>) {
-    let (online, all) = get_user_lists(state).await;
-    if let Ok(msg) = serde_json::to_string(&serde_json::json!({
-        "type": "online-users",
-        "users": online,
-        "all": all,
-    })) {
+    if let Some(msg) = online_users_frame(state).await {
         let _ = state.tx.send(msg);
     }
 }
 
-/// Send the current list of online and known users to a single client.
+/// Send the current list of online users to a single client.
 pub async fn send_users(state: &Arc<AppState>, sender: &mut SplitSink<WebSocket, Message>) {
-    let (online, all) = get_user_lists(state).await;
-    if let Ok(msg) = serde_json::to_string(&serde_json::json!({
-        "type": "online-users",
-        "users": online,
-        "all": all,
-    })) {
+    if let Some(msg) = online_users_frame(state).await {
         let _ = sender.send(Message::Text(msg.into())).await;
     }
 }
 
+/// Build the `online-users` frame listing every connected user.
+async fn online_users_frame(state: &Arc<AppState>) -> Option<String> {
+    let online: Vec<String> = state.users.lock().await.iter().cloned().collect();
+    serde_json::to_string(&serde_json::json!({
+        "type": "online-users",
+        "users": online,
+    }))
+    .ok()
+}
+
+/// One page of the user directory: known users whose name contains `query`
+/// (case-insensitively), sorted by name, each with their online flag, status,
+/// assigned role ids and, while offline, `lastSeen`. Returns the number of
+/// matches alongside the page.
+pub async fn user_directory_page(
+    state: &Arc<AppState>,
+    query: &str,
+    offset: usize,
+    limit: usize,
+) -> (usize, Vec<Value>) {
+    let needle = query.to_lowercase();
+    let mut names: Vec<String> = state
+        .known_users
+        .read()
+        .await
+        .iter()
+        .filter(|name| name.to_lowercase().contains(&needle))
+        .cloned()
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    let total = names.len();
+
+    let online = state.users.lock().await.clone();
+    let statuses = state.statuses.read().await;
+    let roles = state.user_roles.read().await;
+    let last_seen = state.last_seen.lock().await;
+    let page = names
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|name| {
+            let is_online = online.contains(&name);
+            let status = statuses
+                .get(&name)
+                .cloned()
+                .unwrap_or_else(|| if is_online { "online" } else { "offline" }.to_string());
+            let seen = if is_online {
+                None
+            } else {
+                last_seen.get(&name).map(|at| at.to_rfc3339())
+            };
+            serde_json::json!({
+                "name": name,
+                "online": is_online,
+                "status": status,
+                "roleIds": roles.get(&name).cloned().unwrap_or_default(),
+                "lastSeen": seen,
+            })
+        })
+        .collect();
+    (total, page)
+}
+
 /// Broadcast the users currently in a voice channel to all clients.
 pub async fn broadcast_voice(state: &Arc<AppState>, channel_id: i32) {
     let list: Vec<String> = {
-        let vc = state.voice_channels.lock().await;
+        let vc = state.voice_channels.read().await;
         vc.get(&channel_id)
             .map(|info| info.users.iter().cloned().collect())
             .unwrap_or_default()
@@ -88,7 +145,42 @@ pub fn sanitize_message_timestamp(value: &mut Value) -> DateTime<Utc> {
     parsed
 }
 
-/// Create a JSON descriptor for a voice channel.
+/// With `REQUIRE_SECURE_TRANSPORT` enabled, the response refusing a WebSocket
+/// upgrade that did not arrive over TLS (see
+/// [`crate::security::is_secure_transport`]); `None` lets the upgrade proceed.
+pub fn insecure_upgrade_rejection(
+    peer: std::net::IpAddr,
+    uri: &axum::http::Uri,
+    headers: &axum::http::HeaderMap,
+) -> Option<axum::response::Response> {
+    use axum::response::IntoResponse;
+
+    if !crate::config::require_secure_transport() {
+        return None;
+    }
+    let forwarded_proto = headers
+        .get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok());
+    if crate::security::is_secure_transport(
+        peer,
+        uri.scheme_str(),
+        forwarded_proto,
+        &crate::config::trusted_proxies(),
+    ) {
+        return None;
+    }
+    tracing::warn!(%peer, "Rejected WebSocket upgrade over insecure transport");
+    Some(
+        (
+            axum::http::StatusCode::FORBIDDEN,
+            "This server requires a secure connection: connect with wss://",
+        )
+            .into_response(),
+    )
+}
+
+/// Create a JSON descriptor for a voice channel. `maxParticipants` is the
+/// mesh limit (`null` when unlimited) so clients can warn before joining.
 pub fn voice_channel_descriptor(id: i32, info: &VoiceChannelState) -> Value {
     serde_json::json!({
         "id": id,
@@ -97,9 +189,66 @@ pub fn voice_channel_descriptor(id: i32, info: &VoiceChannelState) -> Value {
         "bitrate": info.bitrate,
         "categoryId": info.category_id,
         "position": info.position,
+        "maxParticipants": crate::config::max_voice_mesh_participants(),
     })
 }
 
+/// Whether `user` joining a voice channel would exceed the mesh limit. A user
+/// already in the channel (a repeated join) never counts against it.
+pub fn voice_channel_full(info: &VoiceChannelState, user: &str, limit: Option<usize>) -> bool {
+    match limit {
+        Some(max) => !info.users.contains(user) && info.users.len() >= max,
+        None => false,
+    }
+}
+
+/// Result of [`join_voice_channel`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum VoiceJoin {
+    /// The user is now in the channel; `left` lists the channels they were
+    /// moved out of so their member lists can be re-broadcast.
+    Joined { left: Vec<i32> },
+    /// The channel is at its mesh limit; the user stays where they were.
+    Full,
+    /// No such voice channel; the user stays where they were.
+    UnknownChannel,
+}
+
+/// Move `user` into voice channel `channel_id`, removing them from every other
+/// channel under the same lock so a user is never seen in two channels at
+/// once, even with concurrent joins from several connections.
+pub async fn join_voice_channel(
+    state: &Arc<AppState>,
+    user: &str,
+    channel_id: i32,
+    limit: Option<usize>,
+) -> VoiceJoin {
+    let mut map = state.voice_channels.write().await;
+    match map.get(&channel_id) {
+        None => return VoiceJoin::UnknownChannel,
+        Some(info) if voice_channel_full(info, user, limit) => return VoiceJoin::Full,
+        Some(_) => {}
+    }
+    let mut left = Vec::new();
+    for (id, info) in map.iter_mut() {
+        if *id != channel_id && info.users.remove(user) {
+            left.push(*id);
+        }
+    }
+    if let Some(info) = map.get_mut(&channel_id) {
+        info.users.insert(user.to_string());
+    }
+    VoiceJoin::Joined { left }
+}
+
+/// Remove `user` from every voice channel, returning the channels they were in.
+pub async fn leave_voice_channels(state: &Arc<AppState>, user: &str) -> Vec<i32> {
+    let mut map = state.voice_channels.write().await;
+    map.iter_mut()
+        .filter_map(|(id, info)| info.users.remove(user).then_some(*id))
+        .collect()
+}
+
 /// Serialize the full role-definition list as a `role-definitions` frame,
 /// ordered from lowest to highest position.
 fn role_definitions_frame(defs: &HashMap<i64, RoleDef>) -> Option<String> {
@@ -128,7 +277,7 @@ fn role_definitions_frame(defs: &HashMap<i64, RoleDef>) -> Option<String> {
 
 /// Broadcast the full set of role definitions to all connected clients.
 pub async fn broadcast_role_definitions(state: &Arc<AppState>) {
-    let defs = state.role_defs.lock().await;
+    let defs = state.role_defs.read().await;
     if let Some(msg) = role_definitions_frame(&defs) {
         let _ = state.tx.send(msg);
     }
@@ -139,8 +288,8 @@ pub async fn send_role_definitions(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
 ) {
-    let defs = state.role_defs.lock().await;
-    if let Some(msg) = role_definitions_frame(&defs) {
+    let frame = role_definitions_frame(&*state.role_defs.read().await);
+    if let Some(msg) = frame {
         let _ = sender.send(Message::Text(msg.into())).await;
     }
 }
@@ -161,44 +310,141 @@ pub async fn send_all_user_roles(
     state: &Arc<AppState>,
     sender: &mut SplitSink<WebSocket, Message>,
 ) {
-    let assignments = state.user_roles.lock().await.clone();
-    for (user, ids) in assignments {
-        if let Ok(msg) = serde_json::to_string(&serde_json::json!({
-            "type": "user-roles",
-            "user": user,
-            "roleIds": ids,
-        })) && sender.send(Message::Text(msg.into())).await.is_err()
-        {
+    for msg in user_roles_frames(state).await {
+        if sender.send(Message::Text(msg.into())).await.is_err() {
             break;
         }
     }
 }
 
-/// Send all known user statuses to a newly connected client.
+/// Build one `user-roles` frame per connected user with role assignments.
+async fn user_roles_frames(state: &Arc<AppState>) -> Vec<String> {
+    let assignments = state.user_roles.read().await.clone();
+    assignments
+        .into_iter()
+        .filter_map(|(user, ids)| {
+            serde_json::to_string(&serde_json::json!({
+                "type": "user-roles",
+                "user": user,
+                "roleIds": ids,
+            }))
+            .ok()
+        })
+        .collect()
+}
+
+/// Send all known user statuses to a newly connected client. Last-seen times
+/// are not included; they travel with `list-users` pages and `status-update`.
 pub async fn send_all_statuses(state: &Arc<AppState>, sender: &mut SplitSink<WebSocket, Message>) {
-    let statuses: HashMap<String, String> = state.statuses.lock().await.clone();
+    if let Some(msg) = status_snapshot_frame(state).await {
+        let _ = sender.send(Message::Text(msg.into())).await;
+    }
+}
+
+/// Build the `status-snapshot` frame, or `None` when there is nothing to
+/// report.
+async fn status_snapshot_frame(state: &Arc<AppState>) -> Option<String> {
+    let statuses: HashMap<String, String> = state.statuses.read().await.clone();
     if statuses.is_empty() {
-        return;
+        return None;
     }
-    if let Ok(msg) = serde_json::to_string(&serde_json::json!({
+    serde_json::to_string(&serde_json::json!({
         "type": "status-snapshot",
         "statuses": statuses,
-    })) {
-        let _ = sender.send(Message::Text(msg.into())).await;
-    }
+    }))
+    .ok()
 }
 
-/// Broadcast a user's status change to all clients.
+/// Broadcast a user's status change to all clients. `lastSeen` is the
+/// user's last activity while they are disconnected and `null` otherwise.
 pub async fn broadcast_status(state: &Arc<AppState>, user: &str, status: &str) {
+    let last_seen = if state.users.lock().await.contains(user) {
+        None
+    } else {
+        state
+            .last_seen
+            .lock()
+            .await
+            .get(user)
+            .map(|at| at.to_rfc3339())
+    };
     if let Ok(msg) = serde_json::to_string(&serde_json::json!({
         "type": "status-update",
         "user": user,
         "status": status,
+        "lastSeen": last_seen,
     })) {
         let _ = state.tx.send(msg);
     }
 }
 
+/// Count this socket as one of `user`'s live connections. `current` is the
+/// name the socket was already authenticated as, since presence may be sent
+/// again; switching names releases the old one and drops it from the online
+/// set when no other socket holds it.
+pub async fn claim_connection(state: &Arc<AppState>, current: Option<&str>, user: &str) {
+    if current == Some(user) {
+        return;
+    }
+    let mut connections = state.connections.lock().await;
+    if let Some(previous) = current
+        && release(&mut connections, previous)
+    {
+        state.users.lock().await.remove(previous);
+    }
+    *connections.entry(user.to_string()).or_default() += 1;
+}
+
+/// Drop one of `user`'s live connections. Returns `true` when it was the
+/// last, i.e. the user has actually gone offline.
+pub async fn release_connection(state: &Arc<AppState>, user: &str) -> bool {
+    release(&mut *state.connections.lock().await, user)
+}
+
+fn release(connections: &mut HashMap<String, usize>, user: &str) -> bool {
+    match connections.entry(user.to_string()) {
+        Entry::Occupied(mut entry) if *entry.get() > 1 => {
+            *entry.get_mut() -= 1;
+            false
+        }
+        Entry::Occupied(entry) => {
+            entry.remove();
+            true
+        }
+        Entry::Vacant(_) => true,
+    }
+}
+
+/// Note activity from a connected user, at most once per
+/// [`LAST_SEEN_TOUCH_INTERVAL`](super::constants::LAST_SEEN_TOUCH_INTERVAL)
+/// per connection. Kept in memory only; the disconnect path persists the
+/// exact final value.
+pub async fn touch_last_seen(
+    state: &Arc<AppState>,
+    user: &str,
+    last_touch: &mut Option<std::time::Instant>,
+) {
+    if last_touch.is_some_and(|prev| prev.elapsed() < super::constants::LAST_SEEN_TOUCH_INTERVAL) {
+        return;
+    }
+    *last_touch = Some(std::time::Instant::now());
+    state
+        .last_seen
+        .lock()
+        .await
+        .insert(user.to_string(), Utc::now());
+}
+
+/// Record a user's final activity time when they disconnect and persist it
+/// so it survives restarts.
+pub async fn record_last_seen(state: &Arc<AppState>, user: &str) {
+    let now = Utc::now();
+    state.last_seen.lock().await.insert(user.to_string(), now);
+    if let Err(e) = db::set_last_seen(&state.db, user, now).await {
+        error!("Failed to persist last-seen for {user}: {e}");
+    }
+}
+
 /// Broadcast to all clients that a new channel was created.
 pub async fn broadcast_new_channel(state: &Arc<AppState>, record: &crate::db::ChannelRecord) {
     if let Ok(msg) = serde_json::to_string(&serde_json::json!({
@@ -213,6 +459,17 @@ pub async fn broadcast_new_channel(state: &Arc<AppState>, record: &crate::db::Ch
     }
 }
 
+/// Broadcast a text channel's new name to all clients.
+pub async fn broadcast_rename_channel(state: &Arc<AppState>, channel_id: i32, name: &str) {
+    if let Ok(msg) = serde_json::to_string(&serde_json::json!({
+        "type": "channel-rename",
+        "channelId": channel_id,
+        "name": name,
+    })) {
+        let _ = state.tx.send(msg);
+    }
+}
+
 /// Broadcast a channel's updated topic/description to all clients.
 pub async fn broadcast_channel_topic(state: &Arc<AppState>, channel_id: i32, topic: &str) {
     if let Ok(msg) = serde_json::to_string(&serde_json::json!({
@@ -288,12 +545,19 @@ pub async fn send_channels(
 }
 
 /// Build a `channel-list` frame containing only the text channels `user` can
-/// see, each carrying a `private` flag for the lock indicator.
+/// see, each carrying a `private` flag for the lock indicator and an
+/// `isDefault` flag on the configured default channel.
 pub async fn channel_list_frame(
     state: &Arc<AppState>,
     user: Option<&str>,
 ) -> serde_json::Result<String> {
-    let list = crate::db::get_channels(&state.db).await;
+    let list = crate::db::get_channels(&state.db)
+        .await
+        .unwrap_or_else(|e| {
+            error!("failed to load channels: {e}");
+            Vec::new()
+        });
+    let default_channel = crate::config::default_channel();
     let mut channels: Vec<Value> = Vec::new();
     for ch in &list {
         if !user_can_see_channel(state, user, ChannelKind::Text, ch.id).await {
@@ -306,6 +570,11 @@ pub async fn channel_list_frame(
             "topic": ch.description,
             "position": ch.position,
             "private": channel_is_private(state, ChannelKind::Text, ch.id).await,
+            "postRoles": ch.post_roles,
+            "slowModeSeconds": ch.slow_mode_seconds,
+            "receiptsEnabled": ch.receipts_enabled,
+            "maxSubscribers": ch.max_subscribers,
+            "isDefault": ch.name == default_channel,
         }));
     }
     serde_json::to_string(&serde_json::json!({
@@ -316,6 +585,13 @@ pub async fn channel_list_frame(
 
 /// Send the list of categories to a client.
 pub async fn send_categories(state: &Arc<AppState>, sender: &mut SplitSink<WebSocket, Message>) {
+    if let Some(msg) = category_list_frame(state).await {
+        let _ = sender.send(Message::Text(msg.into())).await;
+    }
+}
+
+/// Build the `category-list` frame.
+async fn category_list_frame(state: &Arc<AppState>) -> Option<String> {
     let list = crate::db::get_categories(&state.db).await;
     let categories: Vec<Value> = list
         .iter()
@@ -327,12 +603,11 @@ pub async fn send_categories(state: &Arc<AppState>, sender: &mut SplitSink<WebSo
             })
         })
         .collect();
-    if let Ok(msg) = serde_json::to_string(&serde_json::json!({
+    serde_json::to_string(&serde_json::json!({
         "type": "category-list",
         "categories": categories,
-    })) {
-        let _ = sender.send(Message::Text(msg.into())).await;
-    }
+    }))
+    .ok()
 }
 
 /// Send the list of available voice channels to a client.
@@ -352,19 +627,29 @@ pub async fn voice_channel_list_frame(
     state: &Arc<AppState>,
     user: Option<&str>,
 ) -> serde_json::Result<String> {
-    let mut entries: Vec<(i32, VoiceChannelState)> = {
-        let map = state.voice_channels.lock().await;
-        map.iter().map(|(id, info)| (*id, info.clone())).collect()
+    // Snapshot the descriptors under a short read guard; the visibility checks
+    // below take other locks and must not run while it is held.
+    let mut entries: Vec<(i32, String, i32, Value)> = {
+        let map = state.voice_channels.read().await;
+        map.iter()
+            .map(|(id, info)| {
+                (
+                    info.position,
+                    info.name.clone(),
+                    *id,
+                    voice_channel_descriptor(*id, info),
+                )
+            })
+            .collect()
     };
-    entries.sort_by(|a, b| (a.1.position, &a.1.name).cmp(&(b.1.position, &b.1.name)));
+    entries.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
     let mut channels: Vec<Value> = Vec::new();
-    for (id, info) in &entries {
-        if !user_can_see_channel(state, user, ChannelKind::Voice, *id).await {
+    for (_, _, id, mut descriptor) in entries {
+        if !user_can_see_channel(state, user, ChannelKind::Voice, id).await {
             continue;
         }
-        let mut descriptor = voice_channel_descriptor(*id, info);
         descriptor["private"] =
-            Value::Bool(channel_is_private(state, ChannelKind::Voice, *id).await);
+            Value::Bool(channel_is_private(state, ChannelKind::Voice, id).await);
         channels.push(descriptor);
     }
     serde_json::to_string(&serde_json::json!({
@@ -373,21 +658,84 @@ pub async fn voice_channel_list_frame(
     }))
 }
 
+/// Build one `voice-users` frame per voice channel. Only the member names are
+/// copied out of the map, and the read guard is released before returning, so
+/// a slow socket never holds up joins and leaves.
+pub async fn voice_users_frames(state: &Arc<AppState>) -> Vec<String> {
+    let members: Vec<(i32, Vec<String>)> = {
+        let map = state.voice_channels.read().await;
+        map.iter()
+            .map(|(id, info)| (*id, info.users.iter().cloned().collect()))
+            .collect()
+    };
+    members
+        .into_iter()
+        .filter_map(|(id, users)| {
+            serde_json::to_string(&serde_json::json!({
+                "type": "voice-users",
+                "channelId": id,
+                "users": users,
+            }))
+            .ok()
+        })
+        .collect()
+}
+
 /// Send all voice channel member lists to a client.
 pub async fn send_all_voice(state: &Arc<AppState>, sender: &mut SplitSink<WebSocket, Message>) {
-    let map = state.voice_channels.lock().await.clone();
-    for (id, info) in map {
-        if let Ok(msg) = serde_json::to_string(&serde_json::json!({
-            "type": "voice-users",
-            "channelId": id,
-            "users": info.users.into_iter().collect::<Vec<_>>(),
-        })) && sender.send(Message::Text(msg.into())).await.is_err()
-        {
+    for msg in voice_users_frames(state).await {
+        if sender.send(Message::Text(msg.into())).await.is_err() {
             break;
         }
     }
 }
 
+/// Build the `init-state` frame, `{"type": "init-state", "frames": [...]}`:
+/// role definitions and assignments, statuses, categories, the text and voice
+/// channels `user` can see, online users, voice members and the newest
+/// history of `channel_id`, in the order the granular frames are sent. Each
+/// entry is exactly the frame it replaces, so a client can hand them to its
+/// existing handlers; later changes still arrive as individual events.
+pub async fn init_state_frame(
+    state: &Arc<AppState>,
+    user: Option<&str>,
+    channel_id: i32,
+) -> String {
+    let mut frames: Vec<String> = Vec::new();
+    frames.extend(role_definitions_frame(&*state.role_defs.read().await));
+    frames.extend(user_roles_frames(state).await);
+    frames.extend(status_snapshot_frame(state).await);
+    frames.extend(category_list_frame(state).await);
+    frames.extend(channel_list_frame(state, user).await.ok());
+    frames.extend(voice_channel_list_frame(state, user).await.ok());
+    frames.extend(online_users_frame(state).await);
+    frames.extend(voice_users_frames(state).await);
+    let limit = super::constants::DEFAULT_HISTORY_LIMIT;
+    match db::history_messages(&state.db, channel_id, None, limit).await {
+        Ok(msgs) => {
+            frames.push(serde_json::json!({"type": "history", "messages": msgs}).to_string())
+        }
+        Err(e) => error!("db history error: {e}"),
+    }
+    // The entries are serialized already; splice them in instead of parsing
+    // them back into values.
+    format!(r#"{{"type":"init-state","frames":[{}]}}"#, frames.join(","))
+}
+
+/// Send the `init-state` frame to a client that asked for it in presence.
+/// It carries the initial history, so `gzip` compresses it like a `history`
+/// frame.
+pub async fn send_init_state(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+    user: Option<&str>,
+    channel_id: i32,
+    gzip: bool,
+) {
+    let msg = init_state_frame(state, user, channel_id).await;
+    let _ = sender.send(super::encoding::history_frame(msg, gzip)).await;
+}
+
 /// Broadcast to all clients that a new category was created.
 pub async fn broadcast_new_category(state: &Arc<AppState>, id: i32, name: &str, position: i32) {
     if let Ok(msg) = serde_json::to_string(&serde_json::json!({
@@ -477,22 +825,18 @@ pub async fn broadcast_category_reorder(state: &Arc<AppState>, order: &[i32]) {
 /// Server-wide only for now; a future per-channel override phase will resolve
 /// against a channel id here without changing the call sites.
 pub async fn effective_permissions(state: &Arc<AppState>, user: &str) -> Permissions {
-    // Lock order is always role_defs before user_roles; keep it consistent
-    // with `top_position` to avoid deadlocks.
-    let defs = state.role_defs.lock().await;
+    // The assignment is read (and its lock released) before role_defs is
+    // taken, so the two locks are never held together.
+    let ids = assigned_role_ids(state, user).await;
+    let defs = state.role_defs.read().await;
     let mut mask = defs
         .values()
         .find(|d| d.is_default)
         .map(|d| d.permissions)
         .unwrap_or(0);
-    {
-        let assignments = state.user_roles.lock().await;
-        if let Some(ids) = assignments.get(user) {
-            for id in ids {
-                if let Some(def) = defs.get(id) {
-                    mask |= def.permissions;
-                }
-            }
+    for id in &ids {
+        if let Some(def) = defs.get(id) {
+            mask |= def.permissions;
         }
     }
     if mask & permissions::ADMINISTRATOR != 0 {
@@ -502,6 +846,30 @@ pub async fn effective_permissions(state: &Arc<AppState>, user: &str) -> Permiss
     }
 }
 
+/// Role ids assigned to `user` (not `@everyone`). Connected users' roles are
+/// held in `user_roles`; users who are not connected have no in-memory
+/// assignment, so theirs are read from the database through their persisted
+/// key binding. Going offline therefore strips nobody of their rank, and a
+/// message scheduled by an offline author is checked against their real roles.
+pub async fn assigned_role_ids(state: &Arc<AppState>, user: &str) -> Vec<i64> {
+    if let Some(ids) = state.user_roles.read().await.get(user) {
+        return ids.clone();
+    }
+    // A connected user without a verified key has no roles to load.
+    if state.users.lock().await.contains(user) {
+        return Vec::new();
+    }
+    match lookup_user_key(state, user).await {
+        Some(key) => db::get_user_role_ids(&state.db, &key)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Failed to load roles of {user}: {e}");
+                Vec::new()
+            }),
+        None => Vec::new(),
+    }
+}
+
 /// Whether `user` is authorised for `required`.
 ///
 /// Without an `ADMIN_TOKEN` configured, channel and wiki management stay open
@@ -516,33 +884,49 @@ pub async fn has_permission(state: &Arc<AppState>, user: &str, required: Permiss
     permissions::mask_allows(effective_permissions(state, user).await, required)
 }
 
+/// Names of the roles explicitly assigned to `user` (not `@everyone`), for
+/// role-tiered limits.
+pub async fn user_role_names(state: &Arc<AppState>, user: &str) -> Vec<String> {
+    let defs = state.role_defs.read().await;
+    let assignments = state.user_roles.read().await;
+    assignments
+        .get(user)
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| defs.get(id).map(|d| d.name.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// A user's hierarchy position: the highest `position` among their roles, with
 /// the default role's position as the floor. Administrators sit above everyone
-/// (used so moderation and role management require strictly outranking the
-/// target). Returns [`i64::MAX`] for administrators.
+/// (used so moderation, moderator message deletion and role management require
+/// strictly outranking the target). Returns [`i64::MAX`] for administrators.
 pub async fn top_position(state: &Arc<AppState>, user: &str) -> i64 {
-    let defs = state.role_defs.lock().await;
+    let ids = assigned_role_ids(state, user).await;
+    let defs = state.role_defs.read().await;
     let default = defs.values().find(|d| d.is_default);
     let mut pos = default.map(|d| d.position).unwrap_or(0);
     let mut is_admin = default
         .map(|d| d.permissions & permissions::ADMINISTRATOR != 0)
         .unwrap_or(false);
-    {
-        let assignments = state.user_roles.lock().await;
-        if let Some(ids) = assignments.get(user) {
-            for id in ids {
-                if let Some(def) = defs.get(id) {
-                    pos = pos.max(def.position);
-                    if def.permissions & permissions::ADMINISTRATOR != 0 {
-                        is_admin = true;
-                    }
-                }
-            }
+    for def in ids.iter().filter_map(|id| defs.get(id)) {
+        pos = pos.max(def.position);
+        if def.permissions & permissions::ADMINISTRATOR != 0 {
+            is_admin = true;
         }
     }
     if is_admin { i64::MAX } else { pos }
 }
 
+/// Whether `actor` strictly outranks `target` in the role hierarchy. Equal
+/// ranks never outrank each other, so two moderators cannot act on one
+/// another.
+pub async fn outranks(state: &Arc<AppState>, actor: &str, target: &str) -> bool {
+    top_position(state, actor).await > top_position(state, target).await
+}
+
 /// A user's effective permission mask **within a specific channel**: the
 /// server-wide mask with the channel's overrides applied. Administrators bypass
 /// overrides entirely. Server-wide only for non-channel checks; this is the
@@ -566,10 +950,7 @@ pub async fn channel_permissions(
         }
     };
 
-    let role_ids: Vec<i64> = {
-        let assignments = state.user_roles.lock().await;
-        assignments.get(user).cloned().unwrap_or_default()
-    };
+    let role_ids = assigned_role_ids(state, user).await;
     let user_key = lookup_user_key(state, user).await;
     overrides.apply(base, &role_ids, user_key.as_deref())
 }
@@ -590,6 +971,142 @@ pub async fn can_view_channel(
     channel_permissions(state, user, kind, channel_id).await & permissions::VIEW_CHANNELS != 0
 }
 
+/// Whether `user` may post in text channel `channel_id` under its posting
+/// restriction (`post_roles`): unrestricted channels accept everyone, while
+/// restricted ones (e.g. announcements) accept only holders of a listed role
+/// and administrators. Reading is unaffected.
+pub async fn can_post_in_channel(state: &Arc<AppState>, user: &str, channel_id: i32) -> bool {
+    let Some(allowed) = db::get_channel_by_id(&state.db, channel_id)
+        .await
+        .and_then(|record| record.post_roles)
+    else {
+        return true;
+    };
+    if effective_permissions(state, user).await & permissions::ADMINISTRATOR != 0 {
+        return true;
+    }
+    assigned_role_ids(state, user)
+        .await
+        .iter()
+        .any(|id| allowed.contains(id))
+}
+
+/// Check whether `user` is currently muted, whether or not they are
+/// connected. Expired mutes are lazily removed from both the in-memory map and
+/// the database.
+pub async fn is_muted(state: &Arc<AppState>, user: &str) -> bool {
+    if state.mutes.lock().await.is_empty() {
+        return false;
+    }
+    let Some(key) = lookup_user_key(state, user).await else {
+        return false;
+    };
+
+    let entry = {
+        let mutes = state.mutes.lock().await;
+        match mutes.get(&key) {
+            Some(entry) => *entry,
+            None => return false,
+        }
+    };
+
+    match entry {
+        None => true,
+        Some(until) if until > Utc::now() => true,
+        Some(_) => {
+            // The mute has expired: clean it up.
+            state.mutes.lock().await.remove(&key);
+            if let Err(e) = db::remove_mute_by_key(&state.db, &key).await {
+                error!("Failed to remove expired mute for {user}: {e}");
+            }
+            false
+        }
+    }
+}
+
+/// Run `text` through the word filter. Members with Manage Messages in the
+/// channel pass unfiltered when `FILTER_EXEMPT_MODERATORS` is on.
+pub async fn filter_text(
+    state: &Arc<AppState>,
+    user: &str,
+    channel_id: i32,
+    text: &str,
+) -> Filtered {
+    let verdict = match state.word_filter.read().await.as_ref() {
+        Some(filter) => filter.apply(crate::config::filter_mode(), text),
+        None => return Filtered::Clean,
+    };
+    if verdict != Filtered::Clean
+        && crate::config::filter_exempt_moderators()
+        && has_channel_permission(
+            state,
+            user,
+            ChannelKind::Text,
+            channel_id,
+            permissions::MANAGE_MESSAGES,
+        )
+        .await
+    {
+        return Filtered::Clean;
+    }
+    verdict
+}
+
+/// The slow-mode interval `user` is held to in `channel_id`, or `None` when
+/// the channel has `slow_mode_seconds` of 0 or the user can manage messages
+/// there.
+async fn slow_mode_interval(
+    state: &Arc<AppState>,
+    user: &str,
+    channel_id: i32,
+) -> Option<Duration> {
+    let interval = db::get_channel_by_id(&state.db, channel_id)
+        .await
+        .map(|record| record.slow_mode_seconds)
+        .filter(|seconds| *seconds > 0)?;
+    if has_channel_permission(
+        state,
+        user,
+        ChannelKind::Text,
+        channel_id,
+        permissions::MANAGE_MESSAGES,
+    )
+    .await
+    {
+        return None;
+    }
+    Some(Duration::from_secs(interval as u64))
+}
+
+/// Seconds `user` must still wait before posting in `channel_id` again, or
+/// `None` when they may post now. Only reads the cooldown; a post starts the
+/// next one through [`record_slow_mode_post`] once it has been accepted.
+pub async fn slow_mode_remaining(
+    state: &Arc<AppState>,
+    user: &str,
+    channel_id: i32,
+) -> Option<u64> {
+    let interval = slow_mode_interval(state, user, channel_id).await?;
+    let posts = state.slow_mode_posts.lock().await;
+    let elapsed = posts.get(&(user.to_owned(), channel_id))?.elapsed();
+    let remaining = interval
+        .checked_sub(elapsed)
+        .filter(|left| !left.is_zero())?;
+    Some(remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0))
+}
+
+/// Start `user`'s slow-mode cooldown in `channel_id` after a post there was
+/// published or scheduled. Nothing is tracked where slow mode does not apply.
+pub async fn record_slow_mode_post(state: &Arc<AppState>, user: &str, channel_id: i32) {
+    if slow_mode_interval(state, user, channel_id).await.is_some() {
+        state
+            .slow_mode_posts
+            .lock()
+            .await
+            .insert((user.to_owned(), channel_id), Instant::now());
+    }
+}
+
 /// Whether `user` holds `required` within a channel (e.g. `SEND_MESSAGES`).
 pub async fn has_channel_permission(
     state: &Arc<AppState>,
@@ -668,6 +1185,39 @@ pub async fn user_can_see_channel(
     }
 }
 
+/// Whether a (possibly anonymous) connection may search a text channel's
+/// history. Channels listed in `SEARCH_EXCLUDED_CHANNELS` are only searchable
+/// by members holding `MANAGE_MESSAGES` there.
+pub async fn can_search_channel(
+    state: &Arc<AppState>,
+    user: Option<&str>,
+    channel_id: i32,
+) -> bool {
+    let excluded = crate::config::search_excluded_channels();
+    if excluded.is_empty() {
+        return true;
+    }
+    let Some(channel) = db::get_channel_by_id(&state.db, channel_id).await else {
+        return true;
+    };
+    if !excluded.contains(&channel.name.to_lowercase()) {
+        return true;
+    }
+    match user {
+        Some(u) => {
+            has_channel_permission(
+                state,
+                u,
+                ChannelKind::Text,
+                channel_id,
+                permissions::MANAGE_MESSAGES,
+            )
+            .await
+        }
+        None => false,
+    }
+}
+
 /// Serialize the current custom emoji list as an `emoji-list` frame.
 async fn emoji_list_frame(state: &Arc<AppState>) -> Option<String> {
     let emojis = match db::get_emojis(&state.db).await {
@@ -702,11 +1252,50 @@ pub async fn send_emojis(state: &Arc<AppState>, sender: &mut SplitSink<WebSocket
     }
 }
 
-/// Broadcast the current custom emoji list to all connected clients.
+/// Broadcast the current custom emoji list to all connected clients, with
+/// the reaction allowlist that includes it.
 pub async fn broadcast_emojis(state: &Arc<AppState>) {
     if let Some(msg) = emoji_list_frame(state).await {
         let _ = state.tx.send(msg);
     }
+    if let Some(msg) = allowed_reactions_frame(state, crate::config::strict_emoji()).await {
+        let _ = state.tx.send(msg);
+    }
+}
+
+/// Serialize the reactions a client may add as an `allowed-reactions` frame:
+/// the registered custom shortcodes and, in strict mode, the built-in Unicode
+/// set. Permissive servers send no Unicode list since any short token goes.
+pub async fn allowed_reactions_frame(state: &Arc<AppState>, strict: bool) -> Option<String> {
+    let custom: Vec<String> = match db::get_emojis(&state.db).await {
+        Ok(list) => list.into_iter().map(|e| format!(":{}:", e.name)).collect(),
+        Err(e) => {
+            error!("Failed to load custom emojis: {e}");
+            return None;
+        }
+    };
+    let unicode: &[&str] = if strict {
+        super::validation::unicode_reactions()
+    } else {
+        &[]
+    };
+    serde_json::to_string(&serde_json::json!({
+        "type": "allowed-reactions",
+        "strict": strict,
+        "custom": custom,
+        "unicode": unicode,
+    }))
+    .ok()
+}
+
+/// Send the reaction allowlist to a single client.
+pub async fn send_allowed_reactions(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+) {
+    if let Some(msg) = allowed_reactions_frame(state, crate::config::strict_emoji()).await {
+        let _ = sender.send(Message::Text(msg.into())).await;
+    }
 }
 
 /// Resolve a user's public key: currently connected users first (in-memory
@@ -725,10 +1314,30 @@ pub async fn lookup_user_key(state: &Arc<AppState>, user: &str) -> Option<String
     }
 }
 
-/// Delete an ephemeral message once its expiry passes and announce the
-/// deletion to the message's channel. Runs as a background task; the delay is
-/// clamped to the ephemeral maximum so a corrupted expiry cannot schedule a
-/// task years into the future.
+/// Delete an expired ephemeral message and announce the deletion to the
+/// message's channel. A message that is already gone (deleted manually, or by
+/// the other of timer and sweeper) is silently skipped.
+async fn delete_expired_message(state: &Arc<AppState>, message_id: i64, channel_id: i32) {
+    match db::delete_message(&state.db, message_id).await {
+        Ok(true) => {
+            let payload = serde_json::json!({
+                "type": "message-deleted",
+                "id": message_id,
+                "channelId": channel_id,
+            });
+            let chan_tx = get_or_create_channel(state, channel_id).await;
+            let _ = chan_tx.send(payload.to_string());
+        }
+        Ok(false) => {}
+        Err(e) => error!("failed to delete ephemeral message {message_id}: {e}"),
+    }
+}
+
+/// Delete an ephemeral message once its expiry passes. Runs as a background
+/// task and is only the fast path: the timer is lost on restart, so the
+/// sweeper ([`spawn_message_sweeper`]) driven by the stored `expires_at` is
+/// what guarantees deletion. The delay is clamped to the ephemeral maximum so
+/// a corrupted expiry cannot schedule a task years into the future.
 pub fn schedule_ephemeral_deletion(
     state: Arc<AppState>,
     message_id: i64,
@@ -743,50 +1352,337 @@ pub fn schedule_ephemeral_deletion(
         if let Ok(duration) = delay.to_std() {
             tokio::time::sleep(duration).await;
         }
-        match db::delete_message(&state.db, message_id).await {
-            Ok(true) => {
+        delete_expired_message(&state, message_id, channel_id).await;
+    });
+}
+
+/// Delete every ephemeral message whose stored expiry has passed.
+pub async fn sweep_expired_messages(state: &Arc<AppState>) {
+    let rows = match db::get_expired_messages(&state.db, Utc::now()).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("failed to scan for expired ephemeral messages: {e}");
+            return;
+        }
+    };
+    for (id, channel_id) in rows {
+        delete_expired_message(state, id, channel_id).await;
+    }
+}
+
+/// Apply every channel's retention policy once, broadcasting a
+/// `channel-purged` frame (with the removed ids) for each channel that lost
+/// messages so clients can trim their caches.
+pub async fn purge_expired_channel_history(state: &Arc<AppState>) {
+    let policies = match db::get_channel_retentions(&state.db).await {
+        Ok(policies) => policies,
+        Err(e) => {
+            error!("failed to load channel retention policies: {e}");
+            return;
+        }
+    };
+    for (channel_id, days) in policies {
+        match db::purge_old_messages(&state.db, channel_id, days).await {
+            Ok(ids) if ids.is_empty() => {}
+            Ok(ids) => {
                 let payload = serde_json::json!({
-                    "type": "message-deleted",
-                    "id": message_id,
+                    "type": "channel-purged",
                     "channelId": channel_id,
+                    "ids": ids,
                 });
-                let chan_tx = get_or_create_channel(&state, channel_id).await;
-                let _ = chan_tx.send(payload.to_string());
+                let _ = state.tx.send(payload.to_string());
             }
-            // Already gone (deleted manually or by an earlier run).
-            Ok(false) => {}
-            Err(e) => error!("failed to delete ephemeral message {message_id}: {e}"),
+            Err(e) => error!("failed to purge old messages in channel {channel_id}: {e}"),
+        }
+    }
+}
+
+/// Start the hourly retention purge.
+pub fn spawn_retention_purger(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(super::constants::RETENTION_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            purge_expired_channel_history(&state).await;
         }
     });
 }
 
-/// Re-schedule the deletion of ephemeral messages found in the database at
-/// startup. Deletion timers only live in memory, so without this sweep an
-/// ephemeral message whose timer was lost to a server restart would never be
-/// removed.
-pub async fn resume_ephemeral_deletions(state: &Arc<AppState>) {
-    let rows = match db::get_ephemeral_messages(&state.db).await {
-        Ok(rows) => rows,
+/// Store a prepared chat frame, broadcast it to its channel, forward it to
+/// outgoing webhooks and announce it globally as `message-notify` (clients use
+/// that for unread counts and mentions in channels they are not viewing).
+/// Sets the new id on `v` and returns it.
+pub async fn publish_chat_message(
+    state: &Arc<AppState>,
+    channel_id: i32,
+    user: &str,
+    v: &mut Value,
+    expiry: Option<DateTime<Utc>>,
+) -> Result<i64, db::DbError> {
+    let out = serde_json::to_string(&v).unwrap_or_else(|_| v.to_string());
+    let id = db::insert_message_expiring(&state.db, channel_id, &out, expiry).await?;
+    v["id"] = Value::from(id);
+    let out_with_id = serde_json::to_string(&v).unwrap_or(out);
+    let chan_tx = get_or_create_channel(state, channel_id).await;
+    let _ = chan_tx.send(out_with_id.clone());
+    crate::webhooks::dispatch(state, channel_id, out_with_id);
+
+    let notify = serde_json::json!({
+        "type": "message-notify",
+        "channelId": channel_id,
+        "id": id,
+        "user": user,
+        "text": v.get("text").cloned().unwrap_or(Value::Null),
+    });
+    let _ = state.tx.send(notify.to_string());
+    Ok(id)
+}
+
+/// Post a scheduled message now, stamped with the delivery time.
+/// Re-run the checks `handle_chat` applied when the message was scheduled,
+/// since the channel, the author's roles, bans and mutes, or the word filter
+/// may have changed since. Masked text is rewritten in place.
+async fn scheduled_post_allowed(
+    state: &Arc<AppState>,
+    user: &str,
+    channel_id: i32,
+    v: &mut Value,
+) -> Result<(), &'static str> {
+    if db::get_channel_by_id(&state.db, channel_id).await.is_none() {
+        return Err("channel no longer exists");
+    }
+    let key = lookup_user_key(state, user).await;
+    if db::is_banned(&state.db, key.as_deref(), user)
+        .await
+        .unwrap_or(true)
+    {
+        return Err("author is banned");
+    }
+    if !can_view_channel(state, user, ChannelKind::Text, channel_id).await
+        || !has_channel_permission(
+            state,
+            user,
+            ChannelKind::Text,
+            channel_id,
+            permissions::SEND_MESSAGES,
+        )
+        .await
+        || !can_post_in_channel(state, user, channel_id).await
+    {
+        return Err("author may no longer post in the channel");
+    }
+    if is_muted(state, user).await {
+        return Err("author is muted");
+    }
+    if let Some(text) = v.get("text").and_then(|t| t.as_str()) {
+        match filter_text(state, user, channel_id, text).await {
+            Filtered::Clean => {}
+            Filtered::Masked(masked) => v["text"] = Value::String(masked),
+            Filtered::Blocked => return Err("blocked by the word filter"),
+        }
+    }
+    Ok(())
+}
+
+async fn deliver_scheduled_message(state: &Arc<AppState>, scheduled: db::ScheduledMessage) {
+    let Ok(mut v) = serde_json::from_str::<Value>(&scheduled.content) else {
+        error!("dropping unreadable scheduled message {}", scheduled.token);
+        return;
+    };
+    // The row is already taken, so a message that fails the checks is gone.
+    if let Err(reason) =
+        scheduled_post_allowed(state, &scheduled.user_name, scheduled.channel_id, &mut v).await
+    {
+        info!(
+            "dropping scheduled message {} from {}: {reason}",
+            scheduled.token, scheduled.user_name
+        );
+        return;
+    }
+    let now = Utc::now();
+    v["timestamp"] = Value::String(now.to_rfc3339());
+    if let Some(map) = v.as_object_mut() {
+        map.remove("time");
+    }
+    ensure_time(&mut v, &now);
+    if let Err(e) = publish_chat_message(
+        state,
+        scheduled.channel_id,
+        &scheduled.user_name,
+        &mut v,
+        None,
+    )
+    .await
+    {
+        error!("failed to post scheduled message {}: {e}", scheduled.token);
+    }
+}
+
+/// Post every scheduled message whose due time has passed.
+pub async fn deliver_due_scheduled_messages(state: &Arc<AppState>) {
+    let due = match db::take_due_scheduled_messages(&state.db, Utc::now()).await {
+        Ok(due) => due,
         Err(e) => {
-            error!("failed to scan for leftover ephemeral messages: {e}");
+            error!("failed to scan for due scheduled messages: {e}");
             return;
         }
     };
-    for (id, channel_id, content) in rows {
-        let Ok(parsed) = serde_json::from_str::<Value>(&content) else {
-            continue;
-        };
-        if parsed.get("ephemeral").and_then(|e| e.as_bool()) != Some(true) {
-            continue;
+    for scheduled in due {
+        deliver_scheduled_message(state, scheduled).await;
+    }
+}
+
+/// Start the periodic message sweeper: deletes expired ephemeral messages and
+/// posts due scheduled ones, which have no per-message timer. The first sweep
+/// runs immediately, which also catches up on everything that fell due while
+/// the server was down.
+pub fn spawn_message_sweeper(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(super::constants::MESSAGE_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweep_expired_messages(&state).await;
+            deliver_due_scheduled_messages(&state).await;
+        }
+    });
+}
+
+/// Send a message's current reaction summary to its channel's subscribers.
+pub async fn broadcast_reaction_update(state: &Arc<AppState>, channel_id: i32, message_id: i64) {
+    let reactions = match db::get_reaction_summary(&state.db, message_id).await {
+        Ok(map) => map,
+        Err(e) => {
+            error!("db reaction summary error: {e}");
+            return;
+        }
+    };
+    send_reaction_update(state, channel_id, message_id, &reactions).await;
+}
+
+async fn send_reaction_update(
+    state: &Arc<AppState>,
+    channel_id: i32,
+    message_id: i64,
+    reactions: &HashMap<String, Vec<String>>,
+) {
+    let payload = serde_json::json!({
+        "type": "reaction-update",
+        "channelId": channel_id,
+        "messageId": message_id,
+        "reactions": reactions,
+    });
+    let chan_sender = get_or_create_channel(state, channel_id).await;
+    let _ = chan_sender.send(payload.to_string());
+}
+
+/// Broadcast a message's reactions after a change whose resulting summary is
+/// unknown, coalescing bursts. The broadcast reads the summary when it fires.
+pub fn queue_reaction_update(state: &Arc<AppState>, channel_id: i32, message_id: i64) {
+    queue_reaction_broadcast(state, channel_id, message_id, None);
+}
+
+/// Broadcast a message's reactions after a change made with
+/// [`db::apply_reaction`], coalescing bursts. The summary the change returned
+/// is broadcast as is, so a burst costs no database reads beyond its writes.
+pub fn queue_reaction_change(
+    state: &Arc<AppState>,
+    channel_id: i32,
+    message_id: i64,
+    change: db::ReactionChange,
+) {
+    queue_reaction_broadcast(state, channel_id, message_id, Some(change));
+}
+
+/// The first change to a message schedules one broadcast
+/// `REACTION_BROADCAST_DEBOUNCE_MS` later; further changes inside that window
+/// ride along, keeping whichever summary has the highest `seq`. A change
+/// without a summary pins the entry to a fresh read instead, so writers that
+/// bypass `apply_reaction` can never leave an older summary to be broadcast.
+/// The slot is released before broadcasting so a racing change schedules a
+/// new broadcast rather than being lost.
+fn queue_reaction_broadcast(
+    state: &Arc<AppState>,
+    channel_id: i32,
+    message_id: i64,
+    change: Option<db::ReactionChange>,
+) {
+    let debounce = crate::config::reaction_broadcast_debounce_ms();
+    let state = state.clone();
+    if debounce == 0 {
+        tokio::spawn(async move {
+            match change {
+                Some(change) => {
+                    send_reaction_update(&state, channel_id, message_id, &change.reactions).await
+                }
+                None => broadcast_reaction_update(&state, channel_id, message_id).await,
+            }
+        });
+        return;
+    }
+    let claimed = state
+        .pending_reaction_updates
+        .lock()
+        .map(|mut pending| match pending.entry(message_id) {
+            Entry::Vacant(slot) => {
+                slot.insert(change);
+                true
+            }
+            Entry::Occupied(mut slot) => {
+                let known = slot.get_mut();
+                match (known.as_ref(), change) {
+                    (Some(prev), Some(change)) if change.seq > prev.seq => *known = Some(change),
+                    (_, None) => *known = None,
+                    _ => {}
+                }
+                false
+            }
+        })
+        .unwrap_or(true);
+    if !claimed {
+        return;
+    }
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(debounce)).await;
+        let known = state
+            .pending_reaction_updates
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.remove(&message_id))
+            .flatten();
+        match known {
+            Some(change) => {
+                send_reaction_update(&state, channel_id, message_id, &change.reactions).await
+            }
+            None => broadcast_reaction_update(&state, channel_id, message_id).await,
+        }
+    });
+}
+
+/// Build the `server-announcement` frame for a stored announcement.
+pub fn announcement_frame(announcement: &db::Announcement) -> String {
+    serde_json::json!({
+        "type": "server-announcement",
+        "id": announcement.id,
+        "message": announcement.message,
+        "level": announcement.level,
+        "createdAt": announcement.created_at,
+    })
+    .to_string()
+}
+
+/// Send the active announcement, if any, to a newly connected client.
+pub async fn send_active_announcement(
+    state: &Arc<AppState>,
+    sender: &mut SplitSink<WebSocket, Message>,
+) {
+    match db::get_active_announcement(&state.db).await {
+        Ok(Some(announcement)) => {
+            let _ = sender
+                .send(Message::Text(announcement_frame(&announcement).into()))
+                .await;
         }
-        let expiry = parsed
-            .get("expiresAt")
-            .and_then(|e| e.as_str())
-            .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
-            .map(|dt| dt.with_timezone(&Utc))
-            // No parseable expiry on an ephemeral message: delete right away.
-            .unwrap_or_else(Utc::now);
-        schedule_ephemeral_deletion(Arc::clone(state), id, channel_id, expiry);
+        Ok(None) => {}
+        Err(e) => error!("failed to load active announcement: {e}"),
     }
 }
 
@@ -802,6 +1698,76 @@ pub async fn get_or_create_channel(
         .clone()
 }
 
+/// A connection's hold on the text channel it has open, counted in
+/// [`AppState::channel_subscribers`]. Dropping it (on a switch or disconnect)
+/// releases the slot.
+pub struct ChannelSubscription {
+    subscribers: Arc<std::sync::Mutex<HashMap<i32, usize>>>,
+    channel_id: i32,
+}
+
+impl ChannelSubscription {
+    /// Subscribe to `channel_id` regardless of its limit; used for the default
+    /// channel every connection starts in.
+    pub fn acquire_unbounded(state: &AppState, channel_id: i32) -> Self {
+        Self::register(state, &mut lock_channel_subscribers(state), channel_id)
+    }
+
+    /// Subscribe to `channel_id` unless it already has `max` subscribers.
+    /// Checked and counted under one lock so simultaneous joins cannot
+    /// overshoot the limit.
+    pub fn acquire(state: &AppState, channel_id: i32, max: Option<usize>) -> Option<Self> {
+        let mut subscribers = lock_channel_subscribers(state);
+        let count = subscribers.get(&channel_id).copied().unwrap_or(0);
+        if max.is_some_and(|max| count >= max) {
+            return None;
+        }
+        Some(Self::register(state, &mut subscribers, channel_id))
+    }
+
+    fn register(state: &AppState, subscribers: &mut HashMap<i32, usize>, channel_id: i32) -> Self {
+        *subscribers.entry(channel_id).or_insert(0) += 1;
+        ChannelSubscription {
+            subscribers: state.channel_subscribers.clone(),
+            channel_id,
+        }
+    }
+
+    pub fn channel_id(&self) -> i32 {
+        self.channel_id
+    }
+}
+
+impl Drop for ChannelSubscription {
+    fn drop(&mut self) {
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(count) = subscribers.get_mut(&self.channel_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                subscribers.remove(&self.channel_id);
+            }
+        }
+    }
+}
+
+fn lock_channel_subscribers(state: &AppState) -> std::sync::MutexGuard<'_, HashMap<i32, usize>> {
+    state
+        .channel_subscribers
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Number of connections that have `channel_id` open.
+pub fn channel_subscriber_count(state: &AppState, channel_id: i32) -> usize {
+    lock_channel_subscribers(state)
+        .get(&channel_id)
+        .copied()
+        .unwrap_or(0)
+}
+
 /// Truncate quoted text for a reply snippet, respecting UTF-8 character
 /// boundaries so multi-byte characters are never split.
 pub fn reply_preview(text: &str, max_chars: usize) -> String {
@@ -848,6 +1814,12 @@ pub fn validate_dm_payload(nonce_b64: &str, ciphertext_b64: &str) -> Result<(),
     Ok(())
 }
 
+/// Whether a message frame is over `MAX_MESSAGE_BYTES` once serialized, as it
+/// would be stored. Counts UTF-8 bytes, not characters.
+pub fn message_too_large(v: &Value) -> bool {
+    v.to_string().len() > crate::config::max_message_bytes()
+}
+
 /// Whether `user` is the sender or recipient of a direct-message frame.
 /// The socket loop uses this to keep DMs private on the shared broadcast.
 pub fn dm_involves(v: &Value, user: Option<&str>) -> bool {
@@ -858,6 +1830,53 @@ pub fn dm_involves(v: &Value, user: Option<&str>) -> bool {
         || v.get("to").and_then(|t| t.as_str()) == Some(user)
 }
 
+/// Append an entry to the audit log (see [`db::write_audit`]) on a spawned
+/// task, so recording an action never delays the action itself. Failures are
+/// logged and otherwise ignored.
+pub fn record_audit(
+    state: &AppState,
+    actor: &str,
+    action: &str,
+    target: Option<&str>,
+    details: Value,
+) {
+    let db = state.db.clone();
+    let actor = actor.to_owned();
+    let action = action.to_owned();
+    let target = target.map(str::to_owned);
+    tokio::spawn(async move {
+        if let Err(e) = db::write_audit(&db, &actor, &action, target.as_deref(), &details).await {
+            error!("failed to record audit entry {action} by {actor}: {e}");
+        }
+    });
+}
+
+/// Render a frame for logging with its secrets masked: the `password` and
+/// `signature` of `presence` and the `token` of `bot-presence` become
+/// `"[redacted]"`, and `dm` frames lose their encrypted content entirely.
+pub fn redact_for_log(v: &Value) -> String {
+    let mut v = v.clone();
+    if let Some(frame) = v.as_object_mut() {
+        let masked: &[&str] = match frame.get("type").and_then(|t| t.as_str()) {
+            Some("presence") => &["password", "signature"],
+            Some("bot-presence") => &["token"],
+            Some("dm") => {
+                for field in ["nonce", "ciphertext", "content", "text"] {
+                    frame.remove(field);
+                }
+                &[]
+            }
+            _ => &[],
+        };
+        for field in masked {
+            if let Some(value) = frame.get_mut(*field) {
+                *value = Value::String("[redacted]".to_string());
+            }
+        }
+    }
+    v.to_string()
+}
+
 /// Ensure reactions field exists and is a valid empty object if missing.
 pub fn ensure_reactions(value: &mut Value) {
     if value.get("reactions").is_none() {
@@ -871,3 +1890,61 @@ pub fn ensure_time(value: &mut Value, timestamp: &DateTime<Utc>) {
         value["time"] = Value::String(timestamp.format("%H:%M:%S").to_string());
     }
 }
+
+/// Schedule receipts for a channel after a read marker there moved. The
+/// first change starts a `READ_RECEIPT_BATCH_INTERVAL` timer; changes inside
+/// the window ride along, so a burst of `mark-read`s costs one flush. The slot
+/// is released before flushing so a racing change schedules the next batch.
+pub fn queue_read_receipts(state: &Arc<AppState>, channel_id: i32) {
+    let claimed = state
+        .pending_read_receipts
+        .lock()
+        .map(|mut pending| pending.insert(channel_id))
+        .unwrap_or(true);
+    if !claimed {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(super::constants::READ_RECEIPT_BATCH_INTERVAL).await;
+        if let Ok(mut pending) = state.pending_read_receipts.lock() {
+            pending.remove(&channel_id);
+        }
+        flush_read_receipts(&state, channel_id).await;
+    });
+}
+
+/// Send each author of a channel's recent messages a `read-receipt-update`
+/// with who has read which of their messages. The socket loop delivers each
+/// frame only to the user named in `to`.
+pub async fn flush_read_receipts(state: &Arc<AppState>, channel_id: i32) {
+    let receipts =
+        match db::get_read_receipts(&state.db, channel_id, super::constants::READ_RECEIPT_WINDOW)
+            .await
+        {
+            Ok(receipts) => receipts,
+            Err(e) => {
+                error!("Failed to load read receipts for channel {channel_id}: {e}");
+                return;
+            }
+        };
+    let mut by_author: HashMap<String, Vec<Value>> = HashMap::new();
+    for receipt in receipts {
+        by_author
+            .entry(receipt.author)
+            .or_default()
+            .push(serde_json::json!({
+                "messageId": receipt.message_id,
+                "readers": receipt.readers,
+            }));
+    }
+    for (author, receipts) in by_author {
+        let payload = serde_json::json!({
+            "type": "read-receipt-update",
+            "to": author,
+            "channelId": channel_id,
+            "receipts": receipts,
+        });
+        let _ = state.tx.send(payload.to_string());
+    }
+}
@@ -0,0 +1,433 @@
+//! Typed inbound WebSocket frames.
+//!
+//! The receive loop decodes each frame once into [`ClientMessage`], tagged on
+//! its `type` field, and hands the matching struct to the handler. The wire
+//! format is unchanged: field names are the camelCase keys clients already
+//! send and unknown fields are ignored. A frame of a modelled type whose
+//! fields are missing or of the wrong JSON type is answered with
+//! `invalid-payload` before any handler runs, so handlers only validate
+//! values (lengths, ranges, permissions). The handshake, chat and channel
+//! administration frames are modelled; frame types not modelled here yet
+//! decode to [`ClientMessage::Untyped`] and are dispatched on the raw value.
+
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+/// A client frame, keyed by its `type`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ClientMessage {
+    Presence(PresenceMsg),
+    BotPresence(BotPresenceMsg),
+    Chat(ChatMsg),
+    React(ReactMsg),
+    MarkRead(MarkReadMsg),
+    PinMessage(MessageTarget),
+    UnpinMessage(MessageTarget),
+    FlagMessage(FlagMsg),
+    ResolveFlag(MessageTarget),
+    VoiceJoin(VoiceMsg),
+    VoiceLeave(VoiceMsg),
+    WikiGet(WikiPageMsg),
+    WikiResolve(WikiResolveMsg),
+    WikiCreate(WikiCreateMsg),
+    WikiUpdate(WikiUpdateMsg),
+    WikiDelete(WikiPageMsg),
+    WikiRename(WikiRenameMsg),
+    Dm(DmMsg),
+    LoadDmHistory(DmHistoryMsg),
+    GetUserKey(UserKeyMsg),
+    CreateChannel(CreateChannelMsg),
+    RenameChannel(RenameChannelMsg),
+    DeleteChannel(ChannelTarget),
+    MoveChannel(MoveChannelMsg),
+    ReorderChannels(ReorderMsg),
+    ReorderCategories(ReorderMsg),
+    SetChannelTopic(ChannelTopicMsg),
+    SetChannelRetention(ChannelRetentionMsg),
+    SetSlowMode(SlowModeMsg),
+    SetChannelReceipts(ChannelReceiptsMsg),
+    SetChannelMaxSubscribers(ChannelMaxSubscribersMsg),
+    SetChannelPostRoles(ChannelPostRolesMsg),
+    CreateCategory(CreateCategoryMsg),
+    RenameCategory(RenameCategoryMsg),
+    DeleteCategory(CategoryTarget),
+    CreateVoiceChannel(CreateVoiceChannelMsg),
+    UpdateVoiceChannel(UpdateVoiceChannelMsg),
+    DeleteVoiceChannel(ChannelTarget),
+    /// Any other frame type; handled on the raw JSON value.
+    #[serde(other)]
+    Untyped,
+}
+
+/// Deserialize a field whose `null` means something other than its absence:
+/// `None` when missing, `Some(None)` when `null`. Use with `#[serde(default)]`.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Connection options negotiated by `presence` and `bot-presence`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandshakeOptions {
+    /// Kept raw: any value the server cannot use is answered with
+    /// `unsupported-protocol` and the supported range, not `invalid-payload`.
+    #[serde(default)]
+    pub protocol_version: Value,
+    /// `msgpack` for MessagePack events; anything else means JSON.
+    pub encoding: Option<String>,
+    /// `gzip` for compressed history.
+    pub compression: Option<String>,
+    /// Ask for the bundled `init-state` snapshot.
+    #[serde(default)]
+    pub init_state: bool,
+}
+
+/// `presence`: authenticate as `user`. The fields are optional so that a
+/// frame missing several of them is answered with one `missing-auth-fields`
+/// listing all of them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceMsg {
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub public_key: Option<String>,
+    pub signature: Option<String>,
+    pub timestamp: Option<String>,
+    #[serde(flatten)]
+    pub options: HandshakeOptions,
+}
+
+/// `bot-presence`: authenticate a bot by its API token.
+#[derive(Debug, Default, Deserialize)]
+pub struct BotPresenceMsg {
+    pub token: Option<String>,
+    #[serde(flatten)]
+    pub options: HandshakeOptions,
+}
+
+/// The message a `chat` frame replies to: its id, or a quote object from
+/// older clients of which only the `id` is read.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum ReplyTarget {
+    Id(i64),
+    Quote { id: i64 },
+}
+
+impl ReplyTarget {
+    pub fn id(self) -> i64 {
+        match self {
+            ReplyTarget::Id(id) | ReplyTarget::Quote { id } => id,
+        }
+    }
+}
+
+/// `chat`: a message for the connection's current channel. The fields the
+/// server interprets are typed; everything else (`image`, `attachment`, the
+/// client's `time` and `timestamp`) is kept in `extra` and stored with the
+/// message.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMsg {
+    pub text: Option<String>,
+    pub reply_to: Option<ReplyTarget>,
+    /// RFC 3339 time to post at instead of now.
+    pub schedule_at: Option<String>,
+    /// RFC 3339 expiry for an ephemeral message.
+    pub expires_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A frame addressing one channel (`delete-channel`,
+/// `delete-voice-channel`).
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelTarget {
+    pub channel_id: i32,
+}
+
+/// A frame addressing one category (`delete-category`).
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CategoryTarget {
+    pub id: i32,
+}
+
+/// A frame addressing one message (`pin-message`, `unpin-message`,
+/// `resolve-flag`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageTarget {
+    pub message_id: i64,
+}
+
+/// Whether a `react` frame adds or removes the requester's reaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReactionAction {
+    Add,
+    Remove,
+}
+
+/// `react`: toggle one emoji on a message. `emoji` is a Unicode emoji or a
+/// `:shortcode:`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactMsg {
+    pub message_id: i64,
+    pub action: ReactionAction,
+    pub emoji: String,
+}
+
+/// `mark-read`: advance the requester's read marker in a channel.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkReadMsg {
+    pub channel_id: i32,
+    pub message_id: i64,
+}
+
+/// `flag-message`: report a message to the moderators.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagMsg {
+    pub message_id: i64,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// `voice-join` / `voice-leave`. `user`, when sent, must be the
+/// connection's own name.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceMsg {
+    pub channel_id: i32,
+    pub user: Option<String>,
+}
+
+/// `wiki-get` / `wiki-delete`: one page of a channel's wiki. `requestId` is
+/// echoed back verbatim so the client can match the answer.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiPageMsg {
+    #[serde(default)]
+    pub request_id: Value,
+    pub channel_id: i32,
+    pub slug: String,
+}
+
+/// One `[[channel/page]]` link in a `wiki-resolve` batch.
+#[derive(Debug, Deserialize)]
+pub struct WikiLinkRef {
+    pub channel: String,
+    pub slug: String,
+}
+
+/// `wiki-resolve`: existence check for a batch of links.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiResolveMsg {
+    #[serde(default)]
+    pub request_id: Value,
+    pub links: Vec<WikiLinkRef>,
+}
+
+/// `wiki-create`: a new page; `body` may be omitted for an empty page.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiCreateMsg {
+    pub channel_id: i32,
+    pub slug: String,
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+}
+
+/// `wiki-update`: compare-and-swap save against `expectedRevision`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiUpdateMsg {
+    #[serde(default)]
+    pub request_id: Value,
+    pub channel_id: i32,
+    pub slug: String,
+    pub title: String,
+    pub body: String,
+    pub expected_revision: i64,
+}
+
+/// `wiki-rename`: move a page to a new slug.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiRenameMsg {
+    pub channel_id: i32,
+    pub slug: String,
+    pub new_slug: String,
+}
+
+/// `dm`: an end-to-end encrypted direct message. The optional send times
+/// are copied into the stored frame. A missing `nonce` or `ciphertext` is
+/// left empty so it fails payload validation with `invalid-dm-payload`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DmMsg {
+    pub to: String,
+    #[serde(default)]
+    pub nonce: String,
+    #[serde(default)]
+    pub ciphertext: String,
+    pub timestamp: Option<String>,
+    pub time: Option<String>,
+}
+
+/// `load-dm-history`: a page of the conversation `with` another user.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DmHistoryMsg {
+    pub with: String,
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// `get-user-key`: the public key bound to `user`.
+#[derive(Debug, Deserialize)]
+pub struct UserKeyMsg {
+    pub user: String,
+}
+
+/// `create-channel`: a new text channel, optionally in a category and
+/// private to its creator and managers.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateChannelMsg {
+    pub name: String,
+    pub category_id: Option<i32>,
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// `rename-channel`: give a text channel a new name.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameChannelMsg {
+    pub channel_id: i32,
+    pub name: String,
+}
+
+/// `move-channel`: put a text or `voice` channel into a category, or take it
+/// out of its category when `categoryId` is missing or `null`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveChannelMsg {
+    pub channel_id: i32,
+    pub category_id: Option<i32>,
+    #[serde(default)]
+    pub voice: bool,
+}
+
+/// `reorder-channels` / `reorder-categories`: the new order of one
+/// category's channels, or of the category list (which ignores
+/// `categoryId` and `voice`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderMsg {
+    pub order: Vec<i32>,
+    pub category_id: Option<i32>,
+    #[serde(default)]
+    pub voice: bool,
+}
+
+/// `set-channel-topic`: the channel description; empty clears it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelTopicMsg {
+    pub channel_id: i32,
+    pub topic: String,
+}
+
+/// `set-channel-retention`: days to keep messages, or forever when
+/// `retentionDays` is missing or `null`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelRetentionMsg {
+    pub channel_id: i32,
+    pub retention_days: Option<i64>,
+}
+
+/// `set-slow-mode`: seconds between posts; 0 turns slow mode off.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowModeMsg {
+    pub channel_id: i32,
+    pub seconds: i64,
+}
+
+/// `set-channel-receipts`: turn read receipts on or off.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelReceiptsMsg {
+    pub channel_id: i32,
+    pub enabled: bool,
+}
+
+/// `set-channel-max-subscribers`: cap on open connections, or no cap when
+/// `max` is missing or `null`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelMaxSubscribersMsg {
+    pub channel_id: i32,
+    pub max: Option<i64>,
+}
+
+/// `set-channel-post-roles`: roles allowed to post, or everyone when
+/// `roleIds` is missing or `null`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelPostRolesMsg {
+    pub channel_id: i32,
+    pub role_ids: Option<Vec<i64>>,
+}
+
+/// `create-category`: appended at the end without a `position`.
+#[derive(Debug, Deserialize)]
+pub struct CreateCategoryMsg {
+    pub name: String,
+    pub position: Option<i32>,
+}
+
+/// `rename-category`.
+#[derive(Debug, Deserialize)]
+pub struct RenameCategoryMsg {
+    pub id: i32,
+    pub name: String,
+}
+
+/// `create-voice-channel`. A missing `quality` or `bitrate` takes the
+/// default; a `null` bitrate leaves the channel uncapped.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateVoiceChannelMsg {
+    pub name: String,
+    pub quality: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub bitrate: Option<Option<i64>>,
+    pub category_id: Option<i32>,
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// `update-voice-channel`: only the fields sent change; a `null` bitrate
+/// removes the cap.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateVoiceChannelMsg {
+    pub channel_id: i32,
+    pub quality: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub bitrate: Option<Option<i64>>,
+}
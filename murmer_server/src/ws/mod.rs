@@ -4,13 +4,19 @@
 //! - [`handlers`] – message dispatch and domain-specific handlers
 //! - [`helpers`] – broadcast, send and permission utilities
 //! - [`constants`] – tuning knobs (limits, allowed roles, defaults)
-//! - [`errors`] – pre-built JSON error response strings
+//! - [`encoding`] – JSON / MessagePack frame encoding per connection
+//! - [`inbound`] – typed client frames decoded once per message
+//! - [`protocol`] – protocol version negotiation and feature gates
+//! - [`errors`] – error code registry and pre-built error frames
 //! - [`validation`] – input validation for status, quality and bitrate
 
-mod constants;
-mod errors;
+pub(crate) mod constants;
+pub mod encoding;
+pub mod errors;
 mod handlers;
 pub mod helpers;
+pub mod inbound;
+pub mod protocol;
 pub mod validation;
 
 pub use handlers::ws_handler;
@@ -0,0 +1,104 @@
+//! Protocol version negotiation.
+//!
+//! A client states the protocol it speaks with `"protocolVersion": <n>` in its
+//! `presence` (or `bot-presence`) frame. Frames without the field come from
+//! clients that predate negotiation and are treated as
+//! [`LEGACY_PROTOCOL_VERSION`]. Versions below [`MIN_PROTOCOL_VERSION`] (or
+//! values that are not a positive integer) are refused with
+//! `unsupported-protocol`, which carries the supported range, and the
+//! connection is closed. Newer clients are accepted and talk
+//! [`PROTOCOL_VERSION`].
+//!
+//! The negotiated version is kept per connection in `handle_socket` and gates
+//! features old clients do not understand; they get the legacy behaviour:
+//!
+//! - version 2: MessagePack event encoding and gzipped `history`
+//!   (`encoding` / `compression` in `presence`; JSON text otherwise) and
+//!   `load-thread` (ignored otherwise) and the bundled `init-state` snapshot
+//!   (`initState` in `presence`; granular frames otherwise).
+//!
+//! After a successful handshake the server sends
+//! `{"type": "protocol", "version", "minVersion", "maxVersion"}`: the
+//! negotiated version and the range this server supports.
+
+use serde_json::Value;
+
+use super::inbound::HandshakeOptions;
+
+/// Newest protocol version this server speaks.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Oldest protocol version this server accepts.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Version assumed for a `presence` frame without `protocolVersion`.
+pub const LEGACY_PROTOCOL_VERSION: u32 = 1;
+/// First version with MessagePack events and gzipped history.
+pub const BINARY_ENCODING_MIN_PROTOCOL: u32 = 2;
+/// First version with `load-thread`.
+pub const THREADS_MIN_PROTOCOL: u32 = 2;
+/// First version with the bundled `init-state` snapshot.
+pub const INIT_STATE_MIN_PROTOCOL: u32 = 2;
+
+/// The protocol version negotiated for one connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProtocolVersion(u32);
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        ProtocolVersion(LEGACY_PROTOCOL_VERSION)
+    }
+}
+
+impl ProtocolVersion {
+    /// The version requested by a `presence` frame, capped at
+    /// [`PROTOCOL_VERSION`], or `None` when it must be refused: the field is
+    /// present but not an integer of at least [`MIN_PROTOCOL_VERSION`].
+    pub fn requested(options: &HandshakeOptions) -> Option<Self> {
+        let version = match &options.protocol_version {
+            Value::Null => LEGACY_PROTOCOL_VERSION,
+            raw => u32::try_from(raw.as_u64()?).ok()?,
+        };
+        (version >= MIN_PROTOCOL_VERSION).then(|| ProtocolVersion(version.min(PROTOCOL_VERSION)))
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Whether the client may ask for MessagePack events and gzipped history.
+    pub fn supports_binary_encoding(self) -> bool {
+        self.0 >= BINARY_ENCODING_MIN_PROTOCOL
+    }
+
+    /// Whether the client may load threads.
+    pub fn supports_threads(self) -> bool {
+        self.0 >= THREADS_MIN_PROTOCOL
+    }
+
+    /// Whether the client may ask for the bundled `init-state` snapshot.
+    pub fn supports_init_state(self) -> bool {
+        self.0 >= INIT_STATE_MIN_PROTOCOL
+    }
+
+    /// Drop the opt-ins in a `presence` frame that this version may not use,
+    /// so everything reading them falls back to the legacy behaviour.
+    pub fn strip_unsupported(self, options: &mut HandshakeOptions) {
+        if !self.supports_binary_encoding() {
+            options.encoding = None;
+            options.compression = None;
+        }
+        if !self.supports_init_state() {
+            options.init_state = false;
+        }
+    }
+
+    /// The `protocol` frame sent after a successful handshake.
+    pub fn frame(self) -> String {
+        serde_json::json!({
+            "type": "protocol",
+            "version": self.0,
+            "minVersion": MIN_PROTOCOL_VERSION,
+            "maxVersion": PROTOCOL_VERSION,
+        })
+        .to_string()
+    }
+}
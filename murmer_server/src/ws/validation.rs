@@ -1,11 +1,33 @@
 //! Validation helpers for WebSocket message parameters.
 
 use super::constants::{
-    MAX_ALLOWED_VOICE_BITRATE, MAX_EMOJI_NAME_LEN, MAX_ROLE_NAME_LENGTH,
-    MAX_SERVER_DESCRIPTION_LENGTH, MAX_SERVER_NAME_LENGTH, MAX_TOPIC_LENGTH,
-    MAX_WELCOME_MESSAGE_LENGTH, MAX_WIKI_SLUG_LENGTH, MAX_WIKI_TITLE_LENGTH, MIN_EMOJI_NAME_LEN,
+    MAX_ALLOWED_VOICE_BITRATE, MAX_BIO_LENGTH, MAX_CHANNEL_SUBSCRIBERS, MAX_DISPLAY_NAME_LENGTH,
+    MAX_EMOJI_NAME_LEN, MAX_FLAG_REASON_LENGTH, MAX_REACTION_EMOJI_BYTES, MAX_RETENTION_DAYS,
+    MAX_ROLE_NAME_LENGTH, MAX_SCHEDULE_SECONDS, MAX_SERVER_DESCRIPTION_LENGTH,
+    MAX_SERVER_NAME_LENGTH, MAX_SLOW_MODE_SECONDS, MAX_TOPIC_LENGTH, MAX_WELCOME_MESSAGE_LENGTH,
+    MAX_WIKI_SLUG_LENGTH, MAX_WIKI_TITLE_LENGTH, MIN_EMOJI_NAME_LEN, MIN_RETENTION_DAYS,
     UPLOAD_IMAGE_EXTENSIONS, USER_STATUSES,
 };
+use super::inbound::PresenceMsg;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+use std::sync::OnceLock;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Build the stable permalink for a message: `<channelId>/<messageId>`.
+/// Ids never change (renames keep the channel id), so the link stays valid
+/// for as long as the message exists.
+pub fn format_permalink(channel_id: i32, message_id: i64) -> String {
+    format!("{channel_id}/{message_id}")
+}
+
+/// Parse a permalink produced by [`format_permalink`].
+pub fn parse_permalink(value: &str) -> Option<(i32, i64)> {
+    let (channel, message) = value.trim().split_once('/')?;
+    let channel_id = channel.parse::<i32>().ok().filter(|id| *id > 0)?;
+    let message_id = message.parse::<i64>().ok().filter(|id| *id > 0)?;
+    Some((channel_id, message_id))
+}
 
 /// Normalize a user status string to a valid status value.
 ///
@@ -38,6 +60,41 @@ pub fn validate_channel_topic(value: &str) -> bool {
     value.len() <= MAX_TOPIC_LENGTH && !value.chars().any(char::is_control)
 }
 
+/// Clean up the reason given when flagging a message: control characters
+/// (newlines included) become spaces, runs of whitespace collapse to one and
+/// the ends are trimmed. An empty reason is allowed; `None` means it is over
+/// [`MAX_FLAG_REASON_LENGTH`] characters once cleaned.
+pub fn sanitize_flag_reason(value: &str) -> Option<String> {
+    let cleaned = value
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (cleaned.chars().count() <= MAX_FLAG_REASON_LENGTH).then_some(cleaned)
+}
+
+/// Validate a channel message retention period in days (1 to 3650).
+pub fn validate_retention_days(days: i64) -> bool {
+    (MIN_RETENTION_DAYS..=MAX_RETENTION_DAYS).contains(&days)
+}
+
+/// Parse a schedule-send time: an RFC 3339 string after `now` and at most
+/// [`MAX_SCHEDULE_SECONDS`] (30 days) ahead of it.
+pub fn parse_schedule_time(raw: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let due = DateTime::parse_from_rfc3339(raw).ok()?.with_timezone(&Utc);
+    (due > now && due <= now + Duration::seconds(MAX_SCHEDULE_SECONDS)).then_some(due)
+}
+
+/// Validate a channel slow mode interval in seconds (0 turns it off).
+pub fn validate_slow_mode_seconds(seconds: i64) -> bool {
+    (0..=MAX_SLOW_MODE_SECONDS).contains(&seconds)
+}
+
+/// Validate a channel subscriber limit (1 to 10000).
+pub fn validate_max_subscribers(max: i64) -> bool {
+    (1..=MAX_CHANNEL_SUBSCRIBERS).contains(&max)
+}
+
 /// Validate a custom emoji name: lowercase alphanumerics and underscores,
 /// 2 to 32 characters (`^[a-z0-9_]{2,32}$` without a regex dependency).
 pub fn validate_emoji_name(value: &str) -> bool {
@@ -56,12 +113,70 @@ pub fn is_emoji_shortcode(value: &str) -> bool {
         .is_some_and(validate_emoji_name)
 }
 
+/// Whether a grapheme is, or is built on, an emoji known to the `emojis`
+/// crate: the whole sequence (flags, keycaps, ZWJ sequences, skin-tone
+/// variants) or, for a combination newer than the crate's Unicode data, one
+/// of its scalars. Joiners, modifiers and selectors are not emoji on their
+/// own, so a lone one is rejected.
+fn contains_emoji(grapheme: &str) -> bool {
+    let mut buf = [0u8; 4];
+    emojis::get(grapheme).is_some()
+        || grapheme
+            .chars()
+            .any(|c| emojis::get(c.encode_utf8(&mut buf)).is_some())
+}
+
+/// Validate a Unicode (non-shortcode) reaction: exactly one grapheme
+/// cluster, so flags and ZWJ sequences count as one emoji whatever their
+/// byte length, that [contains an emoji](contains_emoji) and no control or
+/// whitespace characters.
+pub fn validate_reaction_emoji(value: &str) -> bool {
+    let mut graphemes = value.graphemes(true);
+    graphemes.next().is_some()
+        && graphemes.next().is_none()
+        && value.len() <= MAX_REACTION_EMOJI_BYTES
+        && contains_emoji(value)
+        && !value.chars().any(|c| c.is_control() || c.is_whitespace())
+}
+
+/// Whether a reaction key is a real Unicode emoji (fully, minimally or
+/// unqualified, including skin-tone variants). Used when `STRICT_EMOJI` is on.
+pub fn is_unicode_emoji(value: &str) -> bool {
+    emojis::get(value).is_some()
+}
+
+/// Whether a non-shortcode reaction key passes the server's reaction rules:
+/// any token in permissive mode, only Unicode emoji in strict mode. Shortcodes
+/// are checked against the custom emoji registry instead.
+pub fn is_recognized_reaction(value: &str, strict: bool) -> bool {
+    !strict || is_unicode_emoji(value)
+}
+
+/// The built-in Unicode reaction set offered to clients in strict mode: every
+/// fully-qualified emoji (skin-tone variants are accepted but not listed).
+pub fn unicode_reactions() -> &'static [&'static str] {
+    static SET: OnceLock<Vec<&'static str>> = OnceLock::new();
+    SET.get_or_init(|| emojis::iter().map(|e| e.as_str()).collect())
+}
+
 /// Validate the server display name: may be empty (unset), otherwise within
 /// the length limit and free of control characters.
 pub fn validate_server_name(value: &str) -> bool {
     value.len() <= MAX_SERVER_NAME_LENGTH && !value.chars().any(char::is_control)
 }
 
+/// Validate a profile display name: may be empty (falls back to the user
+/// name), otherwise within the character limit and free of control characters.
+pub fn validate_display_name(value: &str) -> bool {
+    value.chars().count() <= MAX_DISPLAY_NAME_LENGTH && !value.chars().any(char::is_control)
+}
+
+/// Validate a profile bio: may be empty, within the character limit; newlines
+/// are allowed, other control characters are not.
+pub fn validate_bio(value: &str) -> bool {
+    value.chars().count() <= MAX_BIO_LENGTH && !value.chars().any(|c| c.is_control() && c != '\n')
+}
+
 /// Validate the server description: may be empty (unset), within the length
 /// limit; newlines are allowed, other control characters are not.
 pub fn validate_server_description(value: &str) -> bool {
@@ -150,10 +265,111 @@ pub fn validate_role_color(value: &str) -> bool {
     (3..=8).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
 }
 
+/// Check that a parsed frame stays within the nesting depth and total value
+/// count limits. Walks the tree iteratively so a hostile payload cannot
+/// exhaust the stack here either, and stops as soon as a limit is crossed.
+pub fn json_within_limits(value: &Value, max_depth: usize, max_nodes: usize) -> bool {
+    let mut stack = vec![(value, 1usize)];
+    let mut nodes = 0usize;
+    while let Some((value, depth)) = stack.pop() {
+        nodes += 1;
+        if depth > max_depth || nodes > max_nodes {
+            return false;
+        }
+        match value {
+            Value::Array(items) => stack.extend(items.iter().map(|v| (v, depth + 1))),
+            Value::Object(map) => stack.extend(map.values().map(|v| (v, depth + 1))),
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Check a `presence` frame for the fields authentication needs and return
+/// the claimed user name. `user` is always required. The key proof fields
+/// are required together: all three when `key_required` (a password server
+/// that has not yet authenticated the connection), and all three as soon as
+/// any one of them is sent. On failure returns every missing field in frame
+/// order.
+pub fn presence_user(m: &PresenceMsg, key_required: bool) -> Result<&str, Vec<&'static str>> {
+    let proof = [
+        ("publicKey", &m.public_key),
+        ("signature", &m.signature),
+        ("timestamp", &m.timestamp),
+    ];
+    let mut missing = Vec::new();
+    if m.user.is_none() {
+        missing.push("user");
+    }
+    if key_required || proof.iter().any(|(_, field)| field.is_some()) {
+        missing.extend(
+            proof
+                .iter()
+                .filter(|(_, field)| field.is_none())
+                .map(|(name, _)| *name),
+        );
+    }
+    match m.user.as_deref() {
+        Some(user) if missing.is_empty() => Ok(user),
+        _ => Err(missing),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn presence_reports_every_missing_auth_field() {
+        let fields = ["user", "publicKey", "signature", "timestamp"];
+        for mask in 0u8..16 {
+            let mut frame = serde_json::json!({"type": "presence"});
+            for (i, field) in fields.iter().enumerate() {
+                if mask & (1 << i) != 0 {
+                    frame[*field] = Value::from("x");
+                }
+            }
+            let frame: PresenceMsg = serde_json::from_value(frame).expect("presence");
+            let absent: Vec<&str> = (0..4)
+                .filter(|i| mask & (1 << i) == 0)
+                .map(|i| fields[i])
+                .collect();
+
+            // A password server needs the whole key proof.
+            match presence_user(&frame, true) {
+                Ok(user) => assert!(absent.is_empty() && user == "x"),
+                Err(missing) => assert_eq!(missing, absent, "mask {mask:04b}"),
+            }
+
+            // An open server only needs it once part of it is sent.
+            let any_key = mask & 0b1110 != 0;
+            let expected: Vec<&str> = absent
+                .iter()
+                .copied()
+                .filter(|f| *f == "user" || any_key)
+                .collect();
+            match presence_user(&frame, false) {
+                Ok(_) => assert!(expected.is_empty(), "mask {mask:04b}"),
+                Err(missing) => assert_eq!(missing, expected, "mask {mask:04b}"),
+            }
+        }
+    }
+
+    #[test]
+    fn presence_fields_must_be_strings() {
+        // Caught when the frame is decoded, before any field is checked.
+        let frame = serde_json::json!({
+            "type": "presence",
+            "user": "alice",
+            "publicKey": 5,
+            "signature": "sig",
+            "timestamp": "1",
+        });
+        assert!(serde_json::from_value::<PresenceMsg>(frame).is_err());
+        let frame = serde_json::json!({"type": "presence", "user": ["alice"]});
+        assert!(serde_json::from_value::<PresenceMsg>(frame).is_err());
+    }
+
     #[test]
     fn role_name_limits() {
         assert!(validate_role_name("Dude"));
@@ -173,6 +389,45 @@ mod tests {
         assert!(!validate_role_color("#12"));
     }
 
+    #[test]
+    fn retention_bounds() {
+        assert!(validate_retention_days(1));
+        assert!(validate_retention_days(3650));
+        assert!(!validate_retention_days(0));
+        assert!(!validate_retention_days(3651));
+    }
+
+    #[test]
+    fn schedule_times_must_fall_within_the_horizon() {
+        let now = Utc::now();
+        let at = |offset: Duration| (now + offset).to_rfc3339();
+        assert!(parse_schedule_time(&at(Duration::minutes(5)), now).is_some());
+        assert!(parse_schedule_time(&at(Duration::days(30)), now).is_some());
+        assert!(parse_schedule_time(&at(Duration::days(31)), now).is_none());
+        assert!(parse_schedule_time(&at(Duration::minutes(-1)), now).is_none());
+        assert!(parse_schedule_time("tomorrow", now).is_none());
+        assert!(parse_schedule_time("1700000000", now).is_none());
+    }
+
+    #[test]
+    fn profile_fields() {
+        assert!(validate_display_name(""));
+        assert!(validate_display_name(&"é".repeat(32)));
+        assert!(!validate_display_name(&"é".repeat(33)));
+        assert!(!validate_display_name("tab\there"));
+        assert!(validate_bio("line one\nline two"));
+        assert!(!validate_bio("bell\u{7}"));
+        assert!(!validate_bio(&"x".repeat(301)));
+    }
+
+    #[test]
+    fn slow_mode_bounds() {
+        assert!(validate_slow_mode_seconds(0));
+        assert!(validate_slow_mode_seconds(21_600));
+        assert!(!validate_slow_mode_seconds(-1));
+        assert!(!validate_slow_mode_seconds(21_601));
+    }
+
     #[test]
     fn wiki_slug_accepts_canonical_forms() {
         assert!(validate_wiki_slug("getting-started"));
@@ -234,4 +489,22 @@ mod tests {
         assert!(!validate_wiki_title("bad\u{7}title"));
         assert!(!validate_wiki_title(&"x".repeat(MAX_WIKI_TITLE_LENGTH + 1)));
     }
+
+    #[test]
+    fn json_limits_reject_deep_and_wide_frames() {
+        let frame: Value =
+            serde_json::from_str(r#"{"type":"chat","text":"hi","meta":{"a":[1,2]}}"#)
+                .expect("valid json");
+        assert!(json_within_limits(&frame, 32, 10_000));
+        assert!(!json_within_limits(&frame, 2, 10_000));
+        assert!(!json_within_limits(&frame, 32, 5));
+
+        let nested = format!("{}{}", "[".repeat(100), "]".repeat(100));
+        let nested: Value = serde_json::from_str(&nested).expect("within serde's own limit");
+        assert!(!json_within_limits(&nested, 32, 10_000));
+
+        let wide: Value =
+            serde_json::from_str(&format!("[{}0]", "0,".repeat(20_000))).expect("valid json");
+        assert!(!json_within_limits(&wide, 32, 10_000));
+    }
 }
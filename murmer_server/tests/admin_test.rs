@@ -0,0 +1,725 @@
+//! Tests for the `ADMIN_TOKEN`-guarded role endpoints in `admin.rs`, the
+//! audit log they feed, and the open message flag listing.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Json, State},
+    http::{Request, StatusCode, header},
+    response::IntoResponse,
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use murmer_server::admin::{
+    self, AnnounceBody, EmojiBody, RemoveRoleBody, RoleBody, RolePermissionsBody,
+};
+use murmer_server::db::DbCall;
+use murmer_server::permissions::{ADMINISTRATOR, BAN_MEMBERS, DEFAULT_EVERYONE, MANAGE_CHANNELS};
+use murmer_server::{AppState, VoiceChannelState, db};
+use tower::ServiceExt;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".to_string()),
+        ..common::state_with_roles().await
+    })
+}
+
+fn bearer(token: &str) -> TypedHeader<Authorization<Bearer>> {
+    TypedHeader(Authorization::bearer(token).expect("valid bearer"))
+}
+
+async fn patch_permissions(
+    state: &Arc<AppState>,
+    token: &str,
+    role: &str,
+    permissions: u64,
+) -> StatusCode {
+    admin::set_role_permissions(
+        State(state.clone()),
+        bearer(token),
+        Json(RolePermissionsBody {
+            role: role.to_string(),
+            permissions,
+        }),
+    )
+    .await
+    .into_response()
+    .status()
+}
+
+#[tokio::test]
+async fn role_permissions_are_editable_by_name() {
+    let state = make_state().await;
+    let mask = DEFAULT_EVERYONE | MANAGE_CHANNELS | BAN_MEMBERS;
+
+    assert_eq!(
+        patch_permissions(&state, "token", "mod", mask).await,
+        StatusCode::OK
+    );
+
+    let def = db::get_role_def_by_name(&state.db, "Mod")
+        .await
+        .expect("query")
+        .expect("Mod exists");
+    assert_eq!(def.permissions, mask);
+    assert_eq!(state.role_defs.read().await[&def.id].permissions, mask);
+}
+
+#[tokio::test]
+async fn role_permissions_reject_bad_requests() {
+    let state = make_state().await;
+
+    assert_eq!(
+        patch_permissions(&state, "wrong", "Mod", DEFAULT_EVERYONE).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        patch_permissions(&state, "token", "Nobody", DEFAULT_EVERYONE).await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        patch_permissions(&state, "token", "Mod", 1 << 62).await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        patch_permissions(&state, "token", "Owner", 0).await,
+        StatusCode::CONFLICT
+    );
+    let owner = db::get_role_def_by_name(&state.db, "Owner")
+        .await
+        .expect("query")
+        .expect("Owner exists");
+    assert_eq!(owner.permissions, ADMINISTRATOR);
+}
+
+#[tokio::test]
+async fn oversized_role_body_is_rejected() {
+    let state = make_state().await;
+    let padding = "x".repeat(admin::ROLE_BODY_LIMIT);
+    let body = format!(r#"{{"key":"{padding}","role":"Mod"}}"#);
+
+    let response = admin::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::post("/role")
+                .header(header::AUTHORIZATION, "Bearer token")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+async fn delete_role(state: &Arc<AppState>, key: &str, role: Option<&str>) -> StatusCode {
+    admin::remove_role(
+        State(state.clone()),
+        bearer("token"),
+        Json(RemoveRoleBody {
+            key: key.to_string(),
+            role: role.map(str::to_string),
+        }),
+    )
+    .await
+    .into_response()
+    .status()
+}
+
+#[tokio::test]
+async fn removing_a_role_updates_connected_users() {
+    let state = make_state().await;
+    let mod_def = db::assign_named_role(&state.db, "key-a", "Mod", None)
+        .await
+        .expect("assign");
+    state
+        .user_keys
+        .lock()
+        .await
+        .insert("alice".into(), "key-a".into());
+    state
+        .user_roles
+        .write()
+        .await
+        .insert("alice".into(), vec![mod_def.id]);
+
+    assert_eq!(
+        delete_role(&state, "key-a", Some("Mod")).await,
+        StatusCode::OK
+    );
+    assert!(
+        db::get_user_role_ids(&state.db, "key-a")
+            .await
+            .expect("query")
+            .is_empty()
+    );
+    assert!(state.user_roles.read().await["alice"].is_empty());
+
+    // Nothing left to remove.
+    assert_eq!(
+        delete_role(&state, "key-a", Some("Mod")).await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        delete_role(&state, "key-a", None).await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        delete_role(&state, "key-a", Some("Nobody")).await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn lists_role_assignments_in_pages() {
+    let state = make_state().await;
+    db::assign_named_role(&state.db, "key-a", "Mod", None)
+        .await
+        .expect("assign");
+    db::assign_named_role(&state.db, "key-b", "Admin", None)
+        .await
+        .expect("assign");
+    state
+        .user_keys
+        .lock()
+        .await
+        .insert("alice".into(), "key-a".into());
+
+    let page = db::list_role_assignments(&state.db, 1, 0)
+        .await
+        .expect("list");
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].key, "key-a");
+    assert_eq!(page[0].role, "Mod");
+
+    let response = admin::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::get("/roles?limit=10")
+                .header(header::AUTHORIZATION, "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let entries: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(entries.as_array().map(Vec::len), Some(2));
+    assert_eq!(entries[0]["users"], serde_json::json!(["alice"]));
+    assert_eq!(entries[1]["role"], "Admin");
+}
+
+#[tokio::test]
+async fn announcements_broadcast_and_stay_active_until_cleared() {
+    let state = make_state().await;
+    let mut rx = state.tx.subscribe();
+    let post = |message: String, level: &str| {
+        let state = state.clone();
+        let level = level.to_string();
+        async move {
+            admin::announce(
+                State(state),
+                bearer("token"),
+                Json(AnnounceBody { message, level }),
+            )
+            .await
+            .into_response()
+            .status()
+        }
+    };
+
+    assert_eq!(
+        post("Maintenance at 22:00".into(), "shutdown").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        post("x".repeat(admin::MAX_ANNOUNCEMENT_CHARS + 1), "info").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        post("Maintenance at 22:00".into(), "warning").await,
+        StatusCode::OK
+    );
+
+    let frame: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(frame["type"], "server-announcement");
+    assert_eq!(frame["level"], "warning");
+    let active = db::get_active_announcement(&state.db)
+        .await
+        .expect("query")
+        .expect("announcement is active");
+    assert_eq!(active.message, "Maintenance at 22:00");
+
+    let clear = || async {
+        admin::clear_announcement(State(state.clone()), bearer("token"))
+            .await
+            .into_response()
+            .status()
+    };
+    assert_eq!(clear().await, StatusCode::OK);
+    assert_eq!(clear().await, StatusCode::NOT_FOUND);
+    assert!(
+        db::get_active_announcement(&state.db)
+            .await
+            .expect("query")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn stats_snapshot_reports_counts() {
+    let state = make_state().await;
+    let _socket = state.tx.subscribe();
+    state.users.lock().await.insert("alice".into());
+    state.known_users.write().await.insert("alice".into());
+    state.voice_channels.write().await.insert(
+        7,
+        VoiceChannelState {
+            name: "lounge".into(),
+            users: ["alice".to_string()].into_iter().collect(),
+            quality: "standard".into(),
+            bitrate: None,
+            category_id: None,
+            position: 0,
+        },
+    );
+
+    let response = admin::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::get("/stats")
+                .header(header::AUTHORIZATION, "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    for key in [
+        "connectedSockets",
+        "onlineUsers",
+        "knownUsers",
+        "textChannels",
+        "voiceChannels",
+    ] {
+        assert!(stats.get(key).is_some(), "missing {key}");
+    }
+    assert_eq!(stats["connectedSockets"], 1);
+    assert_eq!(stats["onlineUsers"], 1);
+    assert_eq!(stats["textChannels"], 1);
+    assert_eq!(stats["voiceChannels"][0]["participants"], 1);
+}
+
+#[tokio::test]
+async fn resync_loads_db_channels_and_keeps_occupancy() {
+    let state = make_state().await;
+    let lounge = db::add_voice_channel(&state.db, "lounge", "standard", None, None)
+        .await
+        .expect("insert")
+        .expect("new channel");
+    let stale = |name: &str, users: &[&str]| VoiceChannelState {
+        name: name.into(),
+        users: users.iter().map(|u| u.to_string()).collect(),
+        quality: "standard".into(),
+        bitrate: None,
+        category_id: None,
+        position: 0,
+    };
+    {
+        let mut voice = state.voice_channels.write().await;
+        voice.insert(lounge.id, stale("outdated", &["alice"]));
+        voice.insert(999, stale("deleted", &["bob"]));
+    }
+    // Added behind the server's back, so it is not in memory yet.
+    let studio = db::add_voice_channel(&state.db, "studio", "high", Some(128), None)
+        .await
+        .expect("insert")
+        .expect("new channel");
+    let mut rx = state.tx.subscribe();
+
+    let unauthorized = admin::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::post("/admin/resync")
+                .header(header::AUTHORIZATION, "Bearer wrong")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+    let response = admin::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::post("/admin/resync")
+                .header(header::AUTHORIZATION, "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary["removedVoiceChannels"], 1);
+
+    let voice = state.voice_channels.read().await;
+    let lounge_state = &voice[&lounge.id];
+    assert_eq!(lounge_state.name, "lounge");
+    assert!(lounge_state.users.contains("alice"));
+    assert_eq!(voice[&studio.id].quality, "high");
+    assert!(voice[&studio.id].users.is_empty());
+    assert!(!voice.contains_key(&999));
+    drop(voice);
+
+    assert_eq!(rx.recv().await.unwrap(), r#"{"type":"channels-refresh"}"#);
+}
+
+#[tokio::test]
+async fn failed_resync_leaves_channel_state_alone() {
+    let state = make_state().await;
+    let lounge = db::add_voice_channel(&state.db, "lounge", "standard", None, None)
+        .await
+        .expect("insert")
+        .expect("new channel");
+    state.voice_channels.write().await.insert(
+        lounge.id,
+        VoiceChannelState {
+            name: "lounge".into(),
+            users: ["alice".to_string()].into(),
+            quality: "standard".into(),
+            bitrate: None,
+            category_id: None,
+            position: 0,
+        },
+    );
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel");
+    state
+        .channels
+        .lock()
+        .await
+        .insert(general, state.tx.clone());
+    // Make the voice channel query fail without killing the connection.
+    state
+        .db
+        .call_db(|conn| conn.execute_batch("ALTER TABLE voice_channels RENAME TO gone"))
+        .await
+        .expect("rename");
+    let mut rx = state.tx.subscribe();
+
+    let response = admin::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::post("/admin/resync")
+                .header(header::AUTHORIZATION, "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(
+        state.voice_channels.read().await[&lounge.id]
+            .users
+            .contains("alice")
+    );
+    assert!(state.channels.lock().await.contains_key(&general));
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn registers_custom_emoji_from_upload() {
+    let state = make_state().await;
+    let add = |name: &str, url: &str| {
+        let state = state.clone();
+        let body = EmojiBody {
+            name: name.to_string(),
+            url: url.to_string(),
+        };
+        async move {
+            admin::add_emoji(State(state), bearer("token"), Json(body))
+                .await
+                .into_response()
+                .status()
+        }
+    };
+
+    assert_eq!(
+        add("bad name", "/files/x.png").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        add("party", "https://example.com/x.png").await,
+        StatusCode::BAD_REQUEST
+    );
+    // The referenced upload must exist.
+    let key = format!("admin-emoji-{}.png", std::process::id());
+    let url = format!("/files/{key}");
+    assert_eq!(add("party", &url).await, StatusCode::BAD_REQUEST);
+
+    std::fs::create_dir_all(&state.upload_dir).unwrap();
+    let path = state.upload_dir.join(&key);
+    std::fs::write(&path, b"\x89PNG\r\n\x1a\n").unwrap();
+    let mut rx = state.tx.subscribe();
+    let created = add("Party", &url).await;
+    let duplicate = add("party", &url).await;
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(created, StatusCode::CREATED);
+    assert_eq!(duplicate, StatusCode::CONFLICT);
+    assert!(db::emoji_exists(&state.db, "party").await.unwrap());
+    let frame: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(frame["type"], "emoji-list");
+    assert_eq!(frame["emojis"][0]["name"], "party");
+}
+
+#[tokio::test]
+async fn history_pages_backwards_with_reactions() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel");
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let content = serde_json::json!({"type": "chat", "user": "alice", "text": format!("m{i}")});
+        ids.push(
+            db::insert_message(&state.db, general, &content.to_string())
+                .await
+                .expect("insert"),
+        );
+    }
+    db::add_reaction(&state.db, ids[4], "bob", "👍")
+        .await
+        .expect("react");
+
+    let get = |uri: String, token: &'static str| {
+        let state = state.clone();
+        async move {
+            let response = admin::router()
+                .with_state(state)
+                .oneshot(
+                    Request::get(uri)
+                        .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (
+                status,
+                serde_json::from_slice::<serde_json::Value>(&body).ok(),
+            )
+        }
+    };
+
+    let (status, _) = get(format!("/history?channelId={general}"), "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = get("/history?channelId=9999".into(), "token").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, page) = get(format!("/history?channelId={general}&limit=3"), "token").await;
+    assert_eq!(status, StatusCode::OK);
+    let page = page.unwrap();
+    let texts: Vec<_> = page["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["text"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(texts, ["m2", "m3", "m4"]);
+    assert_eq!(page["messages"][2]["reactions"]["👍"][0], "bob");
+    assert_eq!(page["oldestId"], ids[2]);
+
+    let (_, page) = get(
+        format!("/history?channelId={general}&limit=3&before={}", ids[2]),
+        "token",
+    )
+    .await;
+    let page = page.unwrap();
+    assert_eq!(page["messages"].as_array().unwrap().len(), 2);
+    assert_eq!(page["oldestId"], ids[0]);
+
+    let (_, page) = get(
+        format!("/history?channelId={general}&before={}", ids[0]),
+        "token",
+    )
+    .await;
+    let page = page.unwrap();
+    assert!(page["messages"].as_array().unwrap().is_empty());
+    assert!(page["oldestId"].is_null());
+}
+
+#[tokio::test]
+async fn history_defaults_and_clamps_the_limit() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel");
+    for i in 0..60 {
+        let content = serde_json::json!({"type": "chat", "user": "alice", "text": format!("m{i}")});
+        db::insert_message(&state.db, general, &content.to_string())
+            .await
+            .expect("insert");
+    }
+    let count = |query: String| {
+        let state = state.clone();
+        async move {
+            let response = admin::router()
+                .with_state(state)
+                .oneshot(
+                    Request::get(format!("/history?channelId={general}{query}"))
+                        .header(header::AUTHORIZATION, "Bearer token")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            page["messages"].as_array().unwrap().len()
+        }
+    };
+
+    assert_eq!(count(String::new()).await, 50);
+    assert_eq!(count("&limit=0".into()).await, 1);
+    assert_eq!(count("&limit=100000".into()).await, 60);
+}
+
+async fn get_audit(state: &Arc<AppState>, token: &str, query: &str) -> axum::response::Response {
+    admin::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::get(format!("/audit{query}"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Audit entries are written on a spawned task; poll until `count` landed.
+async fn wait_for_audit(state: &Arc<AppState>, count: usize) -> Vec<db::AuditEntry> {
+    for _ in 0..100 {
+        let entries = db::list_audit(&state.db, 10, 0).await.expect("list");
+        if entries.len() >= count {
+            return entries;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("audit log never reached {count} entries");
+}
+
+#[tokio::test]
+async fn role_changes_are_audited_and_listed_newest_first() {
+    let state = make_state().await;
+    let status = admin::set_role(
+        State(state.clone()),
+        bearer("token"),
+        Json(RoleBody {
+            key: "key-a".to_string(),
+            role: "Mod".to_string(),
+            color: None,
+        }),
+    )
+    .await
+    .into_response()
+    .status();
+    assert_eq!(status, StatusCode::OK);
+    wait_for_audit(&state, 1).await;
+    assert_eq!(
+        delete_role(&state, "key-a", Some("Mod")).await,
+        StatusCode::OK
+    );
+    let entries = wait_for_audit(&state, 2).await;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].action, "remove-role");
+    assert_eq!(entries[1].action, "assign-role");
+    assert_eq!(entries[1].actor, admin::ADMIN_ACTOR);
+    assert_eq!(entries[1].target.as_deref(), Some("key-a"));
+    assert_eq!(entries[1].details["role"], "Mod");
+
+    assert_eq!(
+        get_audit(&state, "wrong", "").await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    let response = get_audit(&state, "token", "?limit=1&offset=1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page.as_array().map(Vec::len), Some(1));
+    assert_eq!(page[0]["action"], "assign-role");
+    assert_eq!(page[0]["details"], serde_json::json!({ "role": "Mod" }));
+}
+
+#[tokio::test]
+async fn open_flags_are_listed_for_the_admin_token() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel exists");
+    let id = db::insert_message(&state.db, general, r#"{"user":"troll","text":"spam"}"#)
+        .await
+        .expect("insert message");
+    db::add_flag(&state.db, id, "alice", "spam")
+        .await
+        .expect("flag");
+
+    let get_flags = |token: &'static str| {
+        admin::router().with_state(state.clone()).oneshot(
+            Request::get("/flags")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    assert_eq!(
+        get_flags("wrong").await.unwrap().status(),
+        StatusCode::UNAUTHORIZED
+    );
+    let response = get_flags("token").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let flags: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(flags.as_array().map(Vec::len), Some(1));
+    assert_eq!(flags[0]["messageId"], id);
+    assert_eq!(flags[0]["reporter"], "alice");
+    assert_eq!(flags[0]["author"], "troll");
+
+    db::resolve_flags(&state.db, id, "mod")
+        .await
+        .expect("resolve");
+    let response = get_flags("token").await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.as_ref(), b"[]");
+}
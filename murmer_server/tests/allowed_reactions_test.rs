@@ -0,0 +1,55 @@
+//! Tests for the reaction allowlist: which keys strict and permissive mode
+//! accept, and the `allowed-reactions` frame clients build their picker from.
+
+use std::sync::Arc;
+
+use murmer_server::ws::helpers::allowed_reactions_frame;
+use murmer_server::ws::validation::{is_recognized_reaction, unicode_reactions};
+use murmer_server::{AppState, db};
+use serde_json::Value;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(common::state().await)
+}
+
+#[test]
+fn strict_mode_only_recognizes_unicode_emoji() {
+    for token in ["👍", "👍🏽", "lol", "+1"] {
+        assert!(is_recognized_reaction(token, false), "{token}");
+    }
+    assert!(is_recognized_reaction("👍", true));
+    assert!(is_recognized_reaction("👍🏽", true));
+    assert!(!is_recognized_reaction("lol", true));
+    assert!(!is_recognized_reaction("+1", true));
+
+    let set = unicode_reactions();
+    assert!(set.contains(&"👍"));
+    assert!(set.iter().all(|e| is_recognized_reaction(e, true)));
+}
+
+#[tokio::test]
+async fn allowed_reactions_frame_lists_the_set_for_each_mode() {
+    let state = make_state().await;
+    db::add_emoji(&state.db, "party_parrot", "/files/parrot.png", "alice")
+        .await
+        .expect("add emoji");
+
+    let frame = |raw: Option<String>| -> Value {
+        serde_json::from_str(&raw.expect("frame")).expect("json")
+    };
+
+    let strict = frame(allowed_reactions_frame(&state, true).await);
+    assert_eq!(strict["type"], "allowed-reactions");
+    assert_eq!(strict["strict"], true);
+    assert_eq!(strict["custom"], serde_json::json!([":party_parrot:"]));
+    let unicode = strict["unicode"].as_array().expect("unicode list");
+    assert_eq!(unicode.len(), unicode_reactions().len());
+    assert!(unicode.contains(&Value::from("👍")));
+
+    let permissive = frame(allowed_reactions_frame(&state, false).await);
+    assert_eq!(permissive["strict"], false);
+    assert_eq!(permissive["custom"], serde_json::json!([":party_parrot:"]));
+    assert_eq!(permissive["unicode"], serde_json::json!([]));
+}
@@ -2,45 +2,26 @@
 //! replies/threads, message editing, pins, channel management, typing and
 //! custom emoji listing.
 
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::sync::Arc;
 
 use axum::{
     Router,
     body::Body,
     http::{Request, StatusCode, header},
 };
-use murmer_server::{AppState, RateLimiter, bot, db};
+use murmer_server::{AppState, bot, db};
 use serde_json::{Value, json};
-use tokio::sync::{Mutex, broadcast};
+use tokio::sync::broadcast;
 use tower::ServiceExt;
 
+mod common;
+
 const ADMIN_TOKEN: &str = "test-admin-token";
 
 async fn make_app() -> (Router, Arc<AppState>) {
-    let database = db::init(":memory:").await.expect("in-memory db");
-    let (tx, _) = broadcast::channel(64);
     let state = Arc::new(AppState {
-        tx,
-        channels: Arc::new(Mutex::new(HashMap::new())),
-        db: database,
-        users: Arc::new(Mutex::new(Default::default())),
-        known_users: Arc::new(Mutex::new(Default::default())),
-        voice_channels: Arc::new(Mutex::new(HashMap::new())),
-        role_defs: Arc::new(Mutex::new(HashMap::new())),
-        user_roles: Arc::new(Mutex::new(HashMap::new())),
-        channel_overrides: Arc::new(Mutex::new(HashMap::new())),
-        statuses: Arc::new(Mutex::new(HashMap::new())),
-        user_keys: Arc::new(Mutex::new(HashMap::new())),
-        mutes: Arc::new(Mutex::new(HashMap::new())),
-        active_screen_shares: Arc::new(Mutex::new(HashMap::new())),
-        voice_mutes: Arc::new(Mutex::new(HashMap::new())),
-        connection_stats: Arc::new(Mutex::new(HashMap::new())),
-        voice_session_starts: Arc::new(Mutex::new(HashMap::new())),
-        screenshare_session_starts: Arc::new(Mutex::new(HashMap::new())),
-        upload_dir: PathBuf::from("uploads"),
-        password: None,
         admin_token: Some(ADMIN_TOKEN.to_string()),
-        rate_limiter: RateLimiter::new(),
+        ..common::state().await
     });
     (bot::routes::router().with_state(Arc::clone(&state)), state)
 }
@@ -511,3 +492,12 @@ async fn search_and_emojis() {
     assert_eq!(emojis.len(), 1);
     assert_eq!(emojis[0]["name"], "blob_wave");
 }
+
+#[tokio::test]
+async fn server_info_reports_version() {
+    let (app, _state) = make_app().await;
+    let token = create_bot(&app, "InfoBot", ALL_PERMS).await;
+    let (status, body) = request(&app, "GET", "/api/v1/server/info", &token, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["data"]["version"], env!("CARGO_PKG_VERSION"));
+}
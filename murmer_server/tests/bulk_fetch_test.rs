@@ -0,0 +1,42 @@
+use murmer_server::db;
+
+#[tokio::test]
+async fn fetch_by_ids_returns_existing_messages_only() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let other = db::add_channel(&db, "other", None)
+        .await
+        .expect("create channel")
+        .expect("name is free")
+        .id;
+
+    let first = db::insert_message(&db, general, r#"{"text":"first"}"#)
+        .await
+        .expect("insert message");
+    let second = db::insert_message(&db, other, r#"{"text":"second"}"#)
+        .await
+        .expect("insert message");
+    let deleted = db::insert_message(&db, general, r#"{"text":"gone"}"#)
+        .await
+        .expect("insert message");
+    db::delete_message(&db, deleted).await.expect("delete");
+
+    let rows = db::fetch_messages_by_ids(&db, &[second, 9_999, first, deleted])
+        .await
+        .expect("fetch");
+    assert_eq!(
+        rows,
+        vec![
+            (first, general, r#"{"text":"first"}"#.to_string()),
+            (second, other, r#"{"text":"second"}"#.to_string()),
+        ]
+    );
+    assert!(
+        db::fetch_messages_by_ids(&db, &[])
+            .await
+            .expect("fetch nothing")
+            .is_empty()
+    );
+}
@@ -0,0 +1,44 @@
+use chrono::{Duration, Utc};
+use murmer_server::db::{self, DbCall};
+
+#[tokio::test]
+async fn activity_counts_only_messages_within_the_window() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let quiet = db::add_channel(&db, "quiet", None)
+        .await
+        .expect("create channel")
+        .expect("name is free")
+        .id;
+
+    for text in ["one", "two", "three"] {
+        db::insert_message(&db, general, &format!(r#"{{"text":"{text}"}}"#))
+            .await
+            .expect("insert message");
+    }
+    let old = db::insert_message(&db, quiet, r#"{"text":"ancient"}"#)
+        .await
+        .expect("insert message");
+    // Backdate one message to well outside a 24 hour window.
+    db.call_db(move |conn| {
+        conn.execute(
+            "UPDATE messages SET created_at = '2000-01-01T00:00:00.000Z' WHERE id = ?1",
+            [old],
+        )
+    })
+    .await
+    .expect("backdate message");
+
+    let counts = db::count_messages_since(&db, Utc::now() - Duration::hours(24))
+        .await
+        .expect("count");
+    assert_eq!(counts.get(&general), Some(&3));
+    assert_eq!(counts.get(&quiet), None);
+
+    let future = db::count_messages_since(&db, Utc::now() + Duration::hours(1))
+        .await
+        .expect("count");
+    assert!(future.is_empty());
+}
@@ -1,4 +1,5 @@
-//! Tests for the custom sort order of channels and categories.
+//! Tests for the custom sort order of channels and categories, and for
+//! channels outliving their category.
 
 use murmer_server::db;
 
@@ -31,6 +32,7 @@ async fn new_channels_append_per_category() {
 
     let names: Vec<String> = db::get_channels(&db)
         .await
+        .expect("list channels")
         .into_iter()
         .map(|c| c.name)
         .collect();
@@ -85,7 +87,7 @@ async fn reorder_channels_sets_category_and_positions() {
             .await
             .expect("reorder")
     );
-    let channels = db::get_channels(&db).await;
+    let channels = db::get_channels(&db).await.expect("list channels");
     let b_row = channels.iter().find(|c| c.id == b.id).expect("b");
     let a_row = channels.iter().find(|c| c.id == a.id).expect("a");
     assert_eq!((b_row.category_id, b_row.position), (Some(cat), 0));
@@ -97,7 +99,7 @@ async fn reorder_channels_sets_category_and_positions() {
             .await
             .expect("reorder")
     );
-    let unchanged = db::get_channels(&db).await;
+    let unchanged = db::get_channels(&db).await.expect("list channels");
     let a_row = unchanged.iter().find(|c| c.id == a.id).expect("a");
     assert_eq!((a_row.category_id, a_row.position), (Some(cat), 1));
 }
@@ -124,6 +126,7 @@ async fn reorder_voice_channels() {
     );
     let names: Vec<String> = db::get_voice_channels(&db)
         .await
+        .expect("list channels")
         .into_iter()
         .map(|c| c.name)
         .collect();
@@ -158,3 +161,69 @@ async fn reorder_categories() {
             .expect("reorder")
     );
 }
+
+/// A manual order that puts a later-sorting name first is persisted: it
+/// survives reopening the database, where only the schema seed runs again.
+#[tokio::test]
+async fn manual_order_survives_reopening() {
+    let dir = std::env::temp_dir().join(format!("murmer-channel-order-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = dir.join("murmer.db");
+    let path = path.to_str().expect("utf-8 path");
+
+    let db = db::init(path).await.expect("file db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("seeded");
+    let news = db::add_channel(&db, "zz-announcements", None)
+        .await
+        .expect("add")
+        .expect("created");
+    assert!(
+        db::reorder_channels(&db, None, vec![news.id, general], false)
+            .await
+            .expect("reorder")
+    );
+    drop(db);
+
+    let db = db::init(path).await.expect("reopen");
+    let names: Vec<String> = db::get_channels(&db)
+        .await
+        .expect("list channels")
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    assert_eq!(names, ["zz-announcements", "general"]);
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Deleting a category keeps its text and voice channels, uncategorized.
+#[tokio::test]
+async fn deleting_a_category_uncategorizes_its_channels() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+
+    let (cat, _) = db::add_category(&db, "Team", None).await.expect("category");
+    let text = db::add_channel(&db, "plans", Some(cat))
+        .await
+        .expect("add")
+        .expect("created");
+    let voice = db::add_voice_channel(&db, "Standup", "standard", None, Some(cat))
+        .await
+        .expect("add")
+        .expect("created");
+
+    assert!(db::remove_category(&db, cat).await.expect("delete"));
+    assert!(db::get_categories(&db).await.is_empty());
+
+    let channels = db::get_channels(&db).await.expect("list channels");
+    let text_row = channels.iter().find(|c| c.id == text.id).expect("kept");
+    assert_eq!(text_row.category_id, None);
+    let voices = db::get_voice_channels(&db).await.expect("list channels");
+    let voice_row = voices.iter().find(|c| c.id == voice.id).expect("kept");
+    assert_eq!(voice_row.category_id, None);
+
+    // Deleting it again reports that it no longer exists.
+    assert!(!db::remove_category(&db, cat).await.expect("delete"));
+}
@@ -1,41 +1,21 @@
 //! Integration tests for per-channel override resolution: private channels,
 //! role/user allows, deny of Write, and the manager/administrator bypass.
 
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, sync::Arc};
 
 use murmer_server::channel_overrides::{ChannelKind, OverridePair, OverrideSet};
 use murmer_server::permissions::{
     ADMINISTRATOR, DEFAULT_EVERYONE, MANAGE_CHANNELS, SEND_MESSAGES, VIEW_CHANNELS,
 };
 use murmer_server::ws::helpers::{can_view_channel, has_channel_permission};
-use murmer_server::{AppState, RateLimiter, RoleDef, db};
-use tokio::sync::{Mutex, broadcast};
+use murmer_server::{AppState, RoleDef};
+
+mod common;
 
 async fn make_state() -> Arc<AppState> {
-    let database = db::init(":memory:").await.expect("in-memory db");
-    let (tx, _) = broadcast::channel(64);
     Arc::new(AppState {
-        tx,
-        channels: Arc::new(Mutex::new(HashMap::new())),
-        db: database,
-        users: Arc::new(Mutex::new(Default::default())),
-        known_users: Arc::new(Mutex::new(Default::default())),
-        voice_channels: Arc::new(Mutex::new(HashMap::new())),
-        role_defs: Arc::new(Mutex::new(HashMap::new())),
-        user_roles: Arc::new(Mutex::new(HashMap::new())),
-        channel_overrides: Arc::new(Mutex::new(HashMap::new())),
-        statuses: Arc::new(Mutex::new(HashMap::new())),
-        user_keys: Arc::new(Mutex::new(HashMap::new())),
-        mutes: Arc::new(Mutex::new(HashMap::new())),
-        active_screen_shares: Arc::new(Mutex::new(HashMap::new())),
-        voice_mutes: Arc::new(Mutex::new(HashMap::new())),
-        connection_stats: Arc::new(Mutex::new(HashMap::new())),
-        voice_session_starts: Arc::new(Mutex::new(HashMap::new())),
-        screenshare_session_starts: Arc::new(Mutex::new(HashMap::new())),
-        upload_dir: PathBuf::from("uploads"),
-        password: None,
         admin_token: Some("token".to_string()),
-        rate_limiter: RateLimiter::new(),
+        ..common::state().await
     })
 }
 
@@ -55,7 +35,7 @@ const CH: i32 = 1;
 
 /// Seed base roles: @everyone (view+send), a "member" role, an admin role.
 async fn seed_roles(state: &Arc<AppState>) {
-    let mut defs = state.role_defs.lock().await;
+    let mut defs = state.role_defs.write().await;
     defs.insert(1, role(1, DEFAULT_EVERYONE, true, false));
     defs.insert(2, role(2, DEFAULT_EVERYONE, false, false)); // member
     defs.insert(3, role(3, ADMINISTRATOR, false, true)); // owner
@@ -84,7 +64,7 @@ async fn private_channel_hides_from_non_members_but_shows_to_granted() {
     // Grant role 2 (member) an explicit user; assign it to "member".
     state
         .user_roles
-        .lock()
+        .write()
         .await
         .insert("member".to_string(), vec![2]);
 
@@ -149,17 +129,17 @@ async fn managers_and_admins_bypass_a_private_channel() {
     seed_roles(&state).await;
     // Give "boss" a manager role and "owner" the administrator role.
     {
-        let mut defs = state.role_defs.lock().await;
+        let mut defs = state.role_defs.write().await;
         defs.insert(4, role(4, DEFAULT_EVERYONE | MANAGE_CHANNELS, false, false));
     }
     state
         .user_roles
-        .lock()
+        .write()
         .await
         .insert("boss".to_string(), vec![4]);
     state
         .user_roles
-        .lock()
+        .write()
         .await
         .insert("owner".to_string(), vec![3]);
 
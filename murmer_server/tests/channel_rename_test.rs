@@ -0,0 +1,61 @@
+//! Tests for renaming text channels: history stays with the channel and
+//! names remain unique.
+
+use murmer_server::db::{self, RenameChannelResult};
+
+#[tokio::test]
+async fn history_follows_a_renamed_channel() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let channel = db::add_channel(&db, "random", None)
+        .await
+        .expect("add")
+        .expect("created");
+    let id = db::insert_message(&db, channel.id, r#"{"type":"chat","text":"hello"}"#)
+        .await
+        .expect("insert");
+
+    assert!(matches!(
+        db::rename_channel(&db, channel.id, "offtopic").await,
+        Ok(RenameChannelResult::Renamed)
+    ));
+
+    assert_eq!(
+        db::get_channel_id_by_name(&db, "offtopic").await,
+        Some(channel.id)
+    );
+    assert_eq!(db::get_channel_id_by_name(&db, "random").await, None);
+    let history = db::history_messages(&db, channel.id, None, 50)
+        .await
+        .expect("history");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["id"], id);
+    assert_eq!(history[0]["text"], "hello");
+}
+
+#[tokio::test]
+async fn renaming_refuses_taken_names_and_unknown_channels() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let channel = db::add_channel(&db, "random", None)
+        .await
+        .expect("add")
+        .expect("created");
+
+    assert!(matches!(
+        db::rename_channel(&db, channel.id, "general").await,
+        Ok(RenameChannelResult::NameTaken)
+    ));
+    assert_eq!(
+        db::get_channel_id_by_name(&db, "random").await,
+        Some(channel.id)
+    );
+
+    // Keeping the current name is not a clash with itself.
+    assert!(matches!(
+        db::rename_channel(&db, channel.id, "random").await,
+        Ok(RenameChannelResult::Renamed)
+    ));
+    assert!(matches!(
+        db::rename_channel(&db, 9999, "nowhere").await,
+        Ok(RenameChannelResult::NotFound)
+    ));
+}
@@ -0,0 +1,150 @@
+use chrono::{TimeZone, Utc};
+use murmer_server::db::{self, DbCall, ImportedMessage};
+use serde_json::json;
+
+#[tokio::test]
+async fn purge_removes_only_messages_past_retention() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+
+    let mut ids = Vec::new();
+    for text in ["old", "older", "fresh"] {
+        ids.push(
+            db::insert_message(&db, general, &format!(r#"{{"text":"{text}"}}"#))
+                .await
+                .expect("insert message"),
+        );
+    }
+    let (first, second) = (ids[0], ids[1]);
+    db.call_db(move |conn| {
+        conn.execute(
+            "UPDATE messages SET created_at = '2000-01-01T00:00:00.000Z' WHERE id IN (?1, ?2)",
+            [first, second],
+        )
+    })
+    .await
+    .expect("backdate messages");
+    db::add_reaction(&db, first, "alice", "👍")
+        .await
+        .expect("react");
+
+    assert!(
+        db::get_channel_retentions(&db)
+            .await
+            .expect("list")
+            .is_empty()
+    );
+    assert!(
+        db::set_channel_retention(&db, general, Some(30))
+            .await
+            .expect("set retention")
+    );
+    assert_eq!(
+        db::get_channel_retentions(&db).await.expect("list"),
+        vec![(general, 30)]
+    );
+
+    let purged = db::purge_old_messages(&db, general, 30)
+        .await
+        .expect("purge");
+    assert_eq!(purged, vec![first, second]);
+    let remaining = db::fetch_history(&db, general, None, 10)
+        .await
+        .expect("history");
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].0, ids[2]);
+    assert!(
+        db::get_reactions_for_messages(&db, &[first])
+            .await
+            .expect("reactions")
+            .is_empty()
+    );
+
+    // Nothing left to purge; clearing the policy keeps history forever.
+    assert_eq!(
+        db::purge_old_messages(&db, general, 30)
+            .await
+            .expect("purge again"),
+        Vec::<i64>::new()
+    );
+    assert!(
+        db::set_channel_retention(&db, general, None)
+            .await
+            .expect("clear retention")
+    );
+    assert!(
+        db::get_channel_retentions(&db)
+            .await
+            .expect("list")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn importing_an_old_message_purges_only_that_message() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let mut live = Vec::new();
+    for text in ["one", "two"] {
+        live.push(
+            db::insert_message(&db, general, &format!(r#"{{"text":"{text}"}}"#))
+                .await
+                .expect("insert message"),
+        );
+    }
+    db::add_reaction(&db, live[0], "alice", "👍")
+        .await
+        .expect("react");
+
+    // The import gets a newer id than the live rows but keeps its old date.
+    let outcome = db::import_messages(
+        &db,
+        vec![ImportedMessage {
+            external_id: "old:1".into(),
+            channel: "general".into(),
+            content: json!({ "type": "chat", "user": "bob", "text": "from 2001" }),
+            created_at: Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap(),
+            reactions: Default::default(),
+        }],
+    )
+    .await
+    .expect("import");
+    assert_eq!(outcome.inserted, vec![true]);
+    let imported = db::fetch_history(&db, general, None, 10)
+        .await
+        .expect("history")
+        .into_iter()
+        .map(|(id, _)| id)
+        .max()
+        .expect("imported row");
+    assert!(imported > live[1]);
+
+    db::set_channel_retention(&db, general, Some(30))
+        .await
+        .expect("set retention");
+    assert_eq!(
+        db::purge_old_messages(&db, general, 30)
+            .await
+            .expect("purge"),
+        vec![imported]
+    );
+    let remaining: Vec<i64> = db::fetch_history(&db, general, None, 10)
+        .await
+        .expect("history")
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(remaining.len(), 2);
+    assert!(live.iter().all(|id| remaining.contains(id)));
+    assert_eq!(
+        db::get_reactions_for_messages(&db, &[live[0]])
+            .await
+            .expect("reactions")
+            .len(),
+        1
+    );
+}
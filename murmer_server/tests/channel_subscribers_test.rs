@@ -0,0 +1,141 @@
+//! Tests for per-channel subscriber limits: a `join` beyond a channel's
+//! `max_subscribers` is refused with `channel-full`, and a slot frees up when
+//! a subscriber switches away or disconnects.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{Router, routing::get};
+use futures::{SinkExt, StreamExt};
+use murmer_server::ws::helpers::{ChannelSubscription, channel_subscriber_count};
+use murmer_server::{AppState, db, ws};
+use serde_json::{Value, json};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
+
+mod common;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(common::state_with_roles().await)
+}
+
+async fn serve(state: Arc<AppState>) -> SocketAddr {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+/// Wait for the next text frame whose type is one of `types`.
+async fn next_of(socket: &mut Socket, types: &[&str]) -> Value {
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection open")
+            .expect("valid frame");
+        if let Message::Text(text) = frame {
+            let v: Value = serde_json::from_str(&text).unwrap();
+            if types.iter().any(|t| v["type"] == *t) {
+                return v;
+            }
+        }
+    }
+}
+
+/// Connect and finish the handshake as `user`.
+async fn connect(addr: SocketAddr, user: &str) -> Socket {
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+    let presence = json!({"type": "presence", "user": user, "protocolVersion": 2});
+    socket
+        .send(Message::Text(presence.to_string().into()))
+        .await
+        .unwrap();
+    next_of(&mut socket, &["protocol"]).await;
+    socket
+}
+
+/// Ask to join `channel_id`; returns the `history` or `error` answer.
+async fn join(socket: &mut Socket, channel_id: i32) -> Value {
+    let frame = json!({"type": "join", "channelId": channel_id});
+    socket
+        .send(Message::Text(frame.to_string().into()))
+        .await
+        .unwrap();
+    next_of(socket, &["history", "error"]).await
+}
+
+#[tokio::test]
+async fn subscriptions_are_counted_until_dropped() {
+    let state = make_state().await;
+    let first = ChannelSubscription::acquire(&state, 7, Some(2)).expect("free slot");
+    let second = ChannelSubscription::acquire(&state, 7, Some(2)).expect("free slot");
+    assert!(ChannelSubscription::acquire(&state, 7, Some(2)).is_none());
+    // Without a limit the count never blocks.
+    let third = ChannelSubscription::acquire_unbounded(&state, 7);
+    assert_eq!(channel_subscriber_count(&state, 7), 3);
+
+    drop((first, second, third));
+    assert_eq!(channel_subscriber_count(&state, 7), 0);
+    assert!(state.channel_subscribers.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn cap_blocks_an_extra_joiner() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel");
+    let busy = db::add_channel(&state.db, "busy", None)
+        .await
+        .expect("db")
+        .expect("new channel")
+        .id;
+    assert!(
+        db::set_channel_max_subscribers(&state.db, busy, Some(1))
+            .await
+            .expect("db")
+    );
+    let addr = serve(state.clone()).await;
+
+    let mut alice = connect(addr, "alice").await;
+    let mut bob = connect(addr, "bob").await;
+
+    assert_eq!(join(&mut alice, busy).await["type"], "history");
+    // Re-joining keeps the slot rather than competing for a second one.
+    assert_eq!(join(&mut alice, busy).await["type"], "history");
+    let refused = join(&mut bob, busy).await;
+    assert_eq!(refused["message"], "channel-full");
+    assert_eq!(channel_subscriber_count(&state, busy), 1);
+
+    // Switching away frees the slot.
+    assert_eq!(join(&mut alice, general).await["type"], "history");
+    assert_eq!(join(&mut bob, busy).await["type"], "history");
+    assert_eq!(join(&mut alice, busy).await["message"], "channel-full");
+
+    // So does disconnecting.
+    drop(bob);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while channel_subscriber_count(&state, busy) != 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("slot released on disconnect");
+    assert_eq!(join(&mut alice, busy).await["type"], "history");
+}
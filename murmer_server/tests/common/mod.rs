@@ -0,0 +1,28 @@
+//! Shared setup for the integration tests. Each test binary compiles its own
+//! copy, so helpers unused by one of them are expected.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use murmer_server::{AppState, db};
+use tokio::sync::broadcast;
+
+/// Empty state over a fresh in-memory database. Tests override the fields
+/// they care about with struct update syntax.
+pub async fn state() -> AppState {
+    let database = db::init(":memory:").await.expect("in-memory db");
+    let (tx, _) = broadcast::channel(64);
+    AppState::new(tx, database, PathBuf::from("uploads"))
+}
+
+/// [`state`] with the built-in role definitions loaded, as at startup.
+pub async fn state_with_roles() -> AppState {
+    let state = state().await;
+    let defs = db::list_role_defs(&state.db).await.expect("list roles");
+    state
+        .role_defs
+        .write()
+        .await
+        .extend(defs.into_iter().map(|def| (def.id, def)));
+    state
+}
@@ -0,0 +1,67 @@
+use murmer_server::config::{Config, load_config_file_from, parse_config_file};
+use serial_test::serial;
+use temp_env::with_vars;
+
+fn write_config(name: &str, contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("murmer-{}-{name}.toml", std::process::id()));
+    std::fs::write(&path, contents).expect("write config file");
+    path
+}
+
+#[test]
+#[serial]
+fn file_values_apply_unless_env_overrides() {
+    let path = write_config(
+        "precedence",
+        r#"
+bind_address = "127.0.0.1:4000"
+database_path = "from-file.db"
+max_messages_per_minute = 12
+cors_allow_origins = ["http://a.example", "http://b.example"]
+"#,
+    );
+    load_config_file_from(&path).expect("config file loads");
+    std::fs::remove_file(&path).ok();
+
+    with_vars(
+        [
+            ("BIND_ADDRESS", None::<&str>),
+            ("DATABASE_PATH", None),
+            ("MAX_MESSAGES_PER_MINUTE", None),
+            ("CORS_ALLOW_ORIGINS", None),
+        ],
+        || {
+            let config = Config::from_env().expect("config");
+            assert_eq!(config.bind_addr, "127.0.0.1:4000".parse().unwrap());
+            assert_eq!(config.database_path, "from-file.db");
+            assert_eq!(murmer_server::security::get_max_messages_per_minute(), 12);
+            assert_eq!(
+                murmer_server::config::var("CORS_ALLOW_ORIGINS").as_deref(),
+                Some("http://a.example,http://b.example")
+            );
+        },
+    );
+
+    with_vars(
+        [
+            ("BIND_ADDRESS", Some("127.0.0.1:5000")),
+            ("DATABASE_PATH", Some("from-env.db")),
+        ],
+        || {
+            let config = Config::from_env().expect("config");
+            assert_eq!(config.bind_addr, "127.0.0.1:5000".parse().unwrap());
+            assert_eq!(config.database_path, "from-env.db");
+        },
+    );
+}
+
+#[test]
+fn malformed_file_reports_path() {
+    assert!(parse_config_file("bind_address = ").is_err());
+    assert!(parse_config_file("[section]\nkey = 1").is_err());
+
+    let path = write_config("malformed", "bind_address = \"unterminated");
+    let err = load_config_file_from(&path).expect_err("parse failure");
+    std::fs::remove_file(&path).ok();
+    assert!(format!("{err}").contains(&path.display().to_string()));
+}
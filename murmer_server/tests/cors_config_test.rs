@@ -0,0 +1,234 @@
+//! Tests for the CORS settings: origin lists and subdomain patterns, parsing
+//! of `CORS_ALLOW_METHODS`, `CORS_ALLOW_HEADERS` and
+//! `CORS_ALLOW_CREDENTIALS`, and the preflight response the resulting layer
+//! produces.
+
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderValue, Method, Request, header},
+    routing::get,
+};
+use murmer_server::config::{
+    Config, CorsOrigins, default_cors_headers, default_cors_methods, parse_bool_setting,
+    parse_cors_headers, parse_cors_methods, parse_cors_origins,
+};
+use serial_test::serial;
+use temp_env::with_vars;
+use tower::ServiceExt;
+
+fn allows(origins: &CorsOrigins, origin: &str) -> bool {
+    origins.allows(&HeaderValue::from_str(origin).unwrap())
+}
+
+#[test]
+fn subdomain_patterns_match_only_subdomains() {
+    let origins = parse_cors_origins("https://*.example.com, http://localhost:1420", false)
+        .unwrap()
+        .unwrap();
+    assert!(allows(&origins, "https://app.example.com"));
+    assert!(allows(&origins, "https://a.b.example.com"));
+    assert!(allows(&origins, "https://App.Example.com"));
+    assert!(allows(&origins, "http://localhost:1420"));
+
+    // The apex, other schemes, look-alike domains and ports do not match.
+    assert!(!allows(&origins, "https://example.com"));
+    assert!(!allows(&origins, "http://app.example.com"));
+    assert!(!allows(&origins, "https://app.example.com.evil.test"));
+    assert!(!allows(&origins, "https://appexample.com"));
+    assert!(!allows(&origins, "https://evil-example.com"));
+    assert!(!allows(&origins, "https://app.example.com:8443"));
+    assert!(!allows(&origins, "https://.example.com"));
+    assert!(!allows(&origins, "https://a..example.com"));
+    assert!(!allows(&origins, "https://a b.example.com"));
+    assert!(!allows(&origins, "http://localhost:1421"));
+
+    let ported = parse_cors_origins("https://*.example.com:8443", false)
+        .unwrap()
+        .unwrap();
+    assert!(allows(&ported, "https://app.example.com:8443"));
+    assert!(!allows(&ported, "https://app.example.com"));
+}
+
+#[test]
+fn malformed_patterns_and_bare_wildcards_are_refused() {
+    for bad in [
+        "https://app*.example.com",
+        "https://*.com",
+        "*://app.example.com",
+        "https://*example.com",
+        "https://app.*.example.com",
+        "https://*.example.com:port",
+    ] {
+        let err = parse_cors_origins(bad, false).unwrap_err();
+        assert!(format!("{err:#}").contains("invalid pattern"), "{bad}");
+    }
+
+    let err = parse_cors_origins("*", false).unwrap_err();
+    assert!(err.to_string().contains("CORS_ALLOW_ANY_ORIGIN"));
+    assert_eq!(
+        parse_cors_origins("*", true).unwrap(),
+        Some(CorsOrigins::Any)
+    );
+    assert!(parse_cors_origins("*, https://a.example", true).is_err());
+    assert_eq!(parse_cors_origins(" , ", false).unwrap(), None);
+}
+
+#[test]
+fn methods_parse_case_insensitively_and_reject_junk() {
+    assert_eq!(
+        parse_cors_methods(" get, Put ,DELETE,put").unwrap(),
+        Some(vec![Method::GET, Method::PUT, Method::DELETE])
+    );
+    assert_eq!(parse_cors_methods(" , ").unwrap(), None);
+
+    let err = parse_cors_methods("GET, BAD METHOD").unwrap_err();
+    assert!(format!("{err:#}").contains("'BAD METHOD'"));
+    assert!(parse_cors_methods("*").is_err());
+    assert!(default_cors_methods().contains(&Method::DELETE));
+}
+
+#[test]
+fn headers_parse_and_reject_junk() {
+    let headers = parse_cors_headers("Content-Type, x-custom-header")
+        .unwrap()
+        .unwrap();
+    assert_eq!(headers[0], header::CONTENT_TYPE);
+    assert_eq!(headers[1].as_str(), "x-custom-header");
+    assert_eq!(parse_cors_headers("").unwrap(), None);
+
+    let err = parse_cors_headers("bad header").unwrap_err();
+    assert!(format!("{err:#}").contains("CORS_ALLOW_HEADERS"));
+    assert!(parse_cors_headers("x-ok, *").is_err());
+    assert!(default_cors_headers().contains(&header::AUTHORIZATION));
+}
+
+#[test]
+fn credentials_flag_accepts_common_spellings() {
+    for raw in ["true", "1", "YES", " on "] {
+        assert!(parse_bool_setting("CORS_ALLOW_CREDENTIALS", raw).unwrap());
+    }
+    for raw in ["false", "0", "no", "Off"] {
+        assert!(!parse_bool_setting("CORS_ALLOW_CREDENTIALS", raw).unwrap());
+    }
+    let err = parse_bool_setting("CORS_ALLOW_CREDENTIALS", "maybe").unwrap_err();
+    assert!(err.to_string().contains("CORS_ALLOW_CREDENTIALS"));
+}
+
+fn preflight(config: &Config) -> axum::http::HeaderMap {
+    preflight_from(config, "http://app.example")
+}
+
+fn preflight_from(config: &Config, origin: &str) -> axum::http::HeaderMap {
+    let app: Router = Router::new()
+        .route("/x", get(|| async { "ok" }))
+        .layer(config.cors_layer().expect("cors enabled"));
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        app.oneshot(
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/x")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .headers()
+        .clone()
+    })
+}
+
+#[test]
+#[serial]
+fn configured_values_reach_the_preflight_response() {
+    with_vars(
+        [
+            ("CORS_ALLOW_ORIGINS", Some("http://app.example")),
+            ("CORS_ALLOW_METHODS", Some("GET,PUT")),
+            ("CORS_ALLOW_HEADERS", Some("x-custom-header")),
+            ("CORS_ALLOW_CREDENTIALS", Some("true")),
+        ],
+        || {
+            let headers = preflight(&Config::from_env().expect("config"));
+            assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET,PUT");
+            assert_eq!(
+                headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+                "x-custom-header"
+            );
+            assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        },
+    );
+
+    with_vars(
+        [
+            ("CORS_ALLOW_ORIGINS", Some("http://app.example")),
+            ("CORS_ALLOW_METHODS", None),
+            ("CORS_ALLOW_HEADERS", None),
+            ("CORS_ALLOW_CREDENTIALS", None),
+        ],
+        || {
+            let headers = preflight(&Config::from_env().expect("config"));
+            let methods = headers[header::ACCESS_CONTROL_ALLOW_METHODS]
+                .to_str()
+                .unwrap();
+            assert!(methods.contains("DELETE") && !methods.contains("PUT"));
+            assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        },
+    );
+
+    with_vars([("CORS_ALLOW_METHODS", Some("GET,NOT VALID"))], || {
+        let err = Config::from_env().expect_err("invalid method");
+        assert!(format!("{err:#}").contains("CORS_ALLOW_METHODS"));
+    });
+}
+
+#[test]
+#[serial]
+fn patterns_and_any_origin_reach_the_layer() {
+    with_vars(
+        [
+            ("CORS_ALLOW_ORIGINS", Some("https://*.app.example")),
+            ("CORS_ALLOW_ANY_ORIGIN", None),
+            ("CORS_ALLOW_CREDENTIALS", None),
+        ],
+        || {
+            let config = Config::from_env().expect("config");
+            let headers = preflight_from(&config, "https://pr-1.app.example");
+            assert_eq!(
+                headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+                "https://pr-1.app.example"
+            );
+            let headers = preflight_from(&config, "https://app.example");
+            assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        },
+    );
+
+    with_vars(
+        [
+            ("CORS_ALLOW_ORIGINS", Some("*")),
+            ("CORS_ALLOW_ANY_ORIGIN", Some("true")),
+            ("CORS_ALLOW_CREDENTIALS", None),
+        ],
+        || {
+            let config = Config::from_env().expect("config");
+            let headers = preflight_from(&config, "https://anything.test");
+            assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        },
+    );
+
+    // `*` disables credentialed CORS, so asking for both is a startup error.
+    with_vars(
+        [
+            ("CORS_ALLOW_ORIGINS", Some("*")),
+            ("CORS_ALLOW_ANY_ORIGIN", Some("true")),
+            ("CORS_ALLOW_CREDENTIALS", Some("true")),
+        ],
+        || {
+            let err = Config::from_env().expect_err("credentials with any origin");
+            assert!(err.to_string().contains("CORS_ALLOW_CREDENTIALS"));
+        },
+    );
+}
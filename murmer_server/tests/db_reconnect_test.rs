@@ -0,0 +1,56 @@
+use murmer_server::db::{self, DbCall, DbError};
+
+#[tokio::test]
+async fn reopens_after_connection_thread_dies() {
+    let dir = std::env::temp_dir().join(format!("murmer-reconnect-{}", std::process::id()));
+    let path = dir.join("murmer.db");
+    let database = db::init(path.to_str().unwrap()).await.expect("open db");
+    let general = db::get_channel_id_by_name(&database, "general")
+        .await
+        .expect("default channel");
+    db::insert_message(&database, general, r#"{"text":"before"}"#)
+        .await
+        .expect("insert");
+
+    // A panicking query takes the connection thread down with it. That call
+    // already ran, so it is reported rather than replayed.
+    let clone = database.clone();
+    let crashed = clone
+        .call_db(|_| -> rusqlite::Result<()> { panic!("query blew up") })
+        .await;
+    assert!(matches!(crashed, Err(DbError::ConnectionClosed)));
+
+    // The next call on any clone reconnects and sees the persisted data.
+    let history = db::fetch_history(&database, general, None, 10)
+        .await
+        .expect("query after reconnect");
+    assert_eq!(history.len(), 1);
+    db::insert_message(&clone, general, r#"{"text":"after"}"#)
+        .await
+        .expect("insert after reconnect");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn in_memory_database_is_not_silently_replaced() {
+    let database = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&database, "general")
+        .await
+        .expect("default channel");
+    db::insert_message(&database, general, r#"{"text":"before"}"#)
+        .await
+        .expect("insert");
+
+    let crashed = database
+        .call_db(|_| -> rusqlite::Result<()> { panic!("query blew up") })
+        .await;
+    assert!(matches!(crashed, Err(DbError::ConnectionClosed)));
+
+    // Reopening ":memory:" would yield an empty database, so the loss is
+    // reported on every call instead of quietly dropping the history.
+    for _ in 0..2 {
+        let after = db::fetch_history(&database, general, None, 10).await;
+        assert!(matches!(after, Err(DbError::ConnectionClosed)));
+    }
+}
@@ -0,0 +1,129 @@
+//! `DEFAULT_CHANNEL`: the configured channel is seeded in place of
+//! `general`, flagged in `channel-list` and cannot be deleted.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{Router, routing::get};
+use futures::{SinkExt, StreamExt};
+use murmer_server::{AppState, config, db, ws};
+use serde_json::{Value, json};
+use serial_test::serial;
+use temp_env::with_var;
+use tokio::runtime::Runtime;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
+
+mod common;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn make_state(password: Option<&str>) -> Arc<AppState> {
+    Arc::new(AppState {
+        password: password.map(str::to_string),
+        ..common::state().await
+    })
+}
+
+async fn connect(state: Arc<AppState>) -> Socket {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+    socket
+}
+
+async fn send(socket: &mut Socket, frame: Value) {
+    socket
+        .send(Message::Text(frame.to_string().into()))
+        .await
+        .unwrap();
+}
+
+/// Read text frames until one of type `wanted` arrives.
+async fn next_of_type(socket: &mut Socket, wanted: &str) -> Value {
+    loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection still open")
+            .expect("valid frame");
+        match frame {
+            Message::Text(text) => {
+                let v: Value = serde_json::from_str(&text).unwrap();
+                if v["type"] == wanted {
+                    return v;
+                }
+            }
+            Message::Close(_) => panic!("server closed the connection"),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+#[serial]
+fn unset_or_invalid_names_fall_back_to_general() {
+    with_var("DEFAULT_CHANNEL", None::<&str>, || {
+        assert_eq!(config::default_channel(), "general");
+    });
+    with_var("DEFAULT_CHANNEL", Some(" lobby "), || {
+        assert_eq!(config::default_channel(), "lobby");
+    });
+    for invalid in ["   ", "no/slashes", &"x".repeat(51)] {
+        with_var("DEFAULT_CHANNEL", Some(invalid), || {
+            assert_eq!(config::default_channel(), "general");
+        });
+    }
+}
+
+#[test]
+#[serial]
+fn custom_default_channel_is_seeded_and_cannot_be_deleted() {
+    with_var("DEFAULT_CHANNEL", Some("lobby"), || {
+        Runtime::new().expect("runtime").block_on(async {
+            let state = make_state(None).await;
+            let lobby = db::get_channel_id_by_name(&state.db, "lobby")
+                .await
+                .expect("configured channel is seeded");
+            assert!(
+                db::get_channel_id_by_name(&state.db, "general")
+                    .await
+                    .is_none()
+            );
+
+            let mut socket = connect(state.clone()).await;
+            send(&mut socket, json!({"type": "presence", "user": "alice"})).await;
+            let list = next_of_type(&mut socket, "channel-list").await;
+            let flagged: Vec<&Value> = list["channels"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|c| c["isDefault"] == true)
+                .collect();
+            assert_eq!(flagged.len(), 1);
+            assert_eq!(flagged[0]["id"], lobby);
+
+            send(
+                &mut socket,
+                json!({"type": "delete-channel", "channelId": lobby}),
+            )
+            .await;
+            let error = next_of_type(&mut socket, "error").await;
+            assert_eq!(error["message"], "cannot-delete-general");
+            assert!(db::get_channel_by_id(&state.db, lobby).await.is_some());
+        });
+    });
+}
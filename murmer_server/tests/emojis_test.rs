@@ -1,5 +1,5 @@
 use murmer_server::db;
-use murmer_server::ws::validation::{is_emoji_shortcode, validate_emoji_name};
+use murmer_server::ws::validation::{is_emoji_shortcode, is_unicode_emoji, validate_emoji_name};
 
 #[test]
 fn accepts_valid_emoji_names() {
@@ -32,6 +32,18 @@ fn detects_emoji_shortcodes() {
     assert!(!is_emoji_shortcode("👍"));
 }
 
+#[test]
+fn recognises_unicode_emoji() {
+    assert!(is_unicode_emoji("👍"));
+    assert!(is_unicode_emoji("👍🏽"));
+    assert!(is_unicode_emoji("❤️"));
+    assert!(is_unicode_emoji("❤"));
+    assert!(is_unicode_emoji("🏳️‍🌈"));
+    assert!(!is_unicode_emoji("lol"));
+    assert!(!is_unicode_emoji("👍👍"));
+    assert!(!is_unicode_emoji(":party:"));
+}
+
 #[tokio::test]
 async fn emoji_round_trip() {
     let db = db::init(":memory:").await.expect("in-memory db");
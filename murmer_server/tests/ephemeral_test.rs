@@ -1,10 +1,10 @@
-use murmer_server::db;
+use chrono::{Duration, Utc};
+use murmer_server::db::{self, DbCall};
 
-/// The startup sweep (`resume_ephemeral_deletions`) relies on this scan to
-/// find ephemeral messages whose in-memory deletion timer was lost to a
-/// restart.
+/// The sweeper finds expired ephemeral messages through the indexed
+/// `expires_at` column, never through regular messages.
 #[tokio::test]
-async fn ephemeral_scan_returns_only_flagged_messages() {
+async fn expired_scan_returns_only_due_ephemeral_messages() {
     let db = db::init(":memory:").await.expect("in-memory db");
     let channel = db::get_channel_id_by_name(&db, "general")
         .await
@@ -13,16 +13,63 @@ async fn ephemeral_scan_returns_only_flagged_messages() {
     db::insert_message(&db, channel, r#"{"type":"chat","user":"a","text":"hello"}"#)
         .await
         .expect("insert plain message");
-    let ephemeral_id = db::insert_message(
+    let due = db::insert_message_expiring(
         &db,
         channel,
-        r#"{"type":"chat","user":"a","text":"psst","ephemeral":true,"expiresAt":"2000-01-01T00:00:00Z"}"#,
+        r#"{"type":"chat","user":"a","text":"psst","ephemeral":true}"#,
+        Some(Utc::now() - Duration::seconds(1)),
     )
     .await
-    .expect("insert ephemeral message");
+    .expect("insert expired message");
+    db::insert_message_expiring(
+        &db,
+        channel,
+        r#"{"type":"chat","user":"a","text":"later","ephemeral":true}"#,
+        Some(Utc::now() + Duration::hours(1)),
+    )
+    .await
+    .expect("insert pending message");
+
+    let rows = db::get_expired_messages(&db, Utc::now())
+        .await
+        .expect("scan");
+    assert_eq!(rows, vec![(due, channel)]);
+
+    assert!(db::delete_message(&db, due).await.expect("delete"));
+    assert!(
+        db::get_expired_messages(&db, Utc::now())
+            .await
+            .expect("rescan")
+            .is_empty()
+    );
+    assert_eq!(
+        db::get_expired_messages(&db, Utc::now() + Duration::hours(2))
+            .await
+            .expect("scan ahead")
+            .len(),
+        1
+    );
+}
+
+/// Ephemeral messages stored before `expires_at` existed are backfilled from
+/// their JSON `expiresAt` when the schema runs, so a restart cannot orphan
+/// them.
+#[tokio::test]
+async fn legacy_ephemeral_messages_are_backfilled() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let channel = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+
+    let legacy = db::insert_message(
+        &db,
+        channel,
+        r#"{"type":"chat","user":"a","text":"psst","ephemeral":true,"expiresAt":"2000-01-01T00:00:00+00:00"}"#,
+    )
+    .await
+    .expect("insert legacy ephemeral message");
     // A message that merely quotes the flag in its text must not match: the
-    // quotes inside the JSON string are escaped in the stored serialization,
-    // so the LIKE prefilter cannot hit them.
+    // quotes inside the JSON string are escaped in the stored serialization.
     db::insert_message(
         &db,
         channel,
@@ -36,17 +83,23 @@ async fn ephemeral_scan_returns_only_flagged_messages() {
     .await
     .expect("insert message quoting the flag");
 
-    let rows = db::get_ephemeral_messages(&db).await.expect("scan");
-    assert_eq!(rows.len(), 1);
-    assert_eq!(rows[0].0, ephemeral_id);
-    assert_eq!(rows[0].1, channel);
+    db::run_schema(&db).await.expect("rerun schema");
 
-    let removed = db::delete_message(&db, ephemeral_id).await.expect("delete");
-    assert!(removed);
-    assert!(
-        db::get_ephemeral_messages(&db)
+    let expires_at: Option<String> = db
+        .call_db(move |conn| {
+            conn.query_row(
+                "SELECT expires_at FROM messages WHERE id = ?1",
+                [legacy],
+                |row| row.get(0),
+            )
+        })
+        .await
+        .expect("read expiry");
+    assert_eq!(expires_at.as_deref(), Some("2000-01-01T00:00:00.000Z"));
+    assert_eq!(
+        db::get_expired_messages(&db, Utc::now())
             .await
-            .expect("rescan")
-            .is_empty()
+            .expect("scan"),
+        vec![(legacy, channel)]
     );
 }
@@ -0,0 +1,46 @@
+//! Tests for the WebSocket error registry: every pre-serialized constant
+//! carries its stable numeric code next to the message string.
+
+use std::collections::HashSet;
+
+use murmer_server::ws::errors::{self, ErrorCode, FRAMES, error_payload};
+use serde_json::Value;
+
+#[test]
+fn every_constant_round_trips_to_its_code() {
+    let registered: HashSet<ErrorCode> = ErrorCode::ALL.iter().copied().collect();
+    for (code, frame) in FRAMES {
+        let v: Value = serde_json::from_str(frame).expect("valid JSON");
+        assert_eq!(v["type"], "error", "{frame}");
+        assert_eq!(v["code"], *code as u16, "{frame}");
+        assert_eq!(v["message"], code.message(), "{frame}");
+        assert!(registered.contains(code));
+        assert_eq!(*frame, error_payload(*code));
+    }
+    assert_eq!(
+        errors::UNAUTHENTICATED,
+        error_payload(ErrorCode::Unauthenticated)
+    );
+}
+
+#[test]
+fn codes_and_messages_are_unique() {
+    let numbers: HashSet<u16> = ErrorCode::ALL.iter().map(|c| *c as u16).collect();
+    let messages: HashSet<&str> = ErrorCode::ALL.iter().map(|c| c.message()).collect();
+    assert_eq!(numbers.len(), ErrorCode::ALL.len());
+    assert_eq!(messages.len(), ErrorCode::ALL.len());
+    // Only errors with extra fields lack a constant.
+    assert_eq!(FRAMES.len() + 5, ErrorCode::ALL.len());
+}
+
+#[test]
+fn errors_with_fields_keep_their_code() {
+    let v: Value = serde_json::from_str(&errors::slow_mode_active(7)).unwrap();
+    assert_eq!(v["code"], ErrorCode::SlowModeActive as u16);
+    assert_eq!(v["message"], "slow-mode-active");
+    assert_eq!(v["retryAfter"], 7);
+
+    let v: Value = serde_json::from_str(&errors::invalid_timestamp(30)).unwrap();
+    assert_eq!(v["code"], ErrorCode::InvalidTimestamp as u16);
+    assert_eq!(v["allowedSkewSeconds"], 30);
+}
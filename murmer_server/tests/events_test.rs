@@ -0,0 +1,113 @@
+//! Tests for the read-only `GET /events/{channel}` Server-Sent Events mirror.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::{Body, BodyDataStream},
+    http::{Request, StatusCode, header},
+    response::Response,
+};
+use futures::StreamExt;
+use murmer_server::ws::helpers::get_or_create_channel;
+use murmer_server::{AppState, db, events};
+use tower::ServiceExt;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".to_string()),
+        ..common::state_with_roles().await
+    })
+}
+
+async fn open(state: &Arc<AppState>, uri: &str, token: &str) -> Response {
+    events::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::get(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn next_chunk(body: &mut BodyDataStream) -> String {
+    let chunk = tokio::time::timeout(Duration::from_secs(1), body.next())
+        .await
+        .expect("event within a second")
+        .expect("stream still open")
+        .expect("body chunk");
+    String::from_utf8(chunk.to_vec()).unwrap()
+}
+
+async fn general(state: &Arc<AppState>) -> i32 {
+    db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel exists")
+}
+
+#[tokio::test]
+async fn requires_admin_token_and_known_channel() {
+    let state = make_state().await;
+    let channel = general(&state).await;
+    let uri = format!("/events/{channel}");
+    assert_eq!(
+        open(&state, &uri, "wrong").await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        open(&state, "/events/9999", "token").await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn streams_channel_broadcasts_and_unsubscribes_on_disconnect() {
+    let state = make_state().await;
+    let channel = general(&state).await;
+    let response = open(&state, &format!("/events/{channel}"), "token").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+
+    let tx = get_or_create_channel(&state, channel).await;
+    assert_eq!(tx.receiver_count(), 1);
+    tx.send(r#"{"type":"chat","user":"alice","text":"hi"}"#.into())
+        .unwrap();
+    let mut body = response.into_body().into_data_stream();
+    assert_eq!(
+        next_chunk(&mut body).await,
+        "data: {\"type\":\"chat\",\"user\":\"alice\",\"text\":\"hi\"}\n\n"
+    );
+
+    drop(body);
+    assert_eq!(tx.receiver_count(), 0);
+}
+
+#[tokio::test]
+async fn lagging_subscriber_is_told_to_refetch() {
+    let state = make_state().await;
+    let channel = general(&state).await;
+    let response = open(&state, &format!("/events/{channel}"), "token").await;
+    let tx = get_or_create_channel(&state, channel).await;
+    // The channel buffer holds 100 frames, rounded up to 128.
+    for i in 0..150 {
+        tx.send(format!(r#"{{"type":"chat","text":"{i}"}}"#))
+            .unwrap();
+    }
+
+    let mut body = response.into_body().into_data_stream();
+    assert_eq!(
+        next_chunk(&mut body).await,
+        ": lagged 22; refetch history\n\n"
+    );
+    assert_eq!(
+        next_chunk(&mut body).await,
+        "data: {\"type\":\"chat\",\"text\":\"22\"}\n\n"
+    );
+}
@@ -0,0 +1,269 @@
+//! Tests for the streaming `GET /export` endpoint and `POST /import`.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use murmer_server::export::{self, EXPORT_BATCH_SIZE};
+use murmer_server::{AppState, db};
+use tower::ServiceExt;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".to_string()),
+        ..common::state_with_roles().await
+    })
+}
+
+async fn get(state: &Arc<AppState>, uri: &str, token: &str) -> (StatusCode, String, String) {
+    let response = export::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::get(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let disposition = response
+        .headers()
+        .get(header::CONTENT_DISPOSITION)
+        .map(|v| v.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        disposition,
+        String::from_utf8(body.to_vec()).unwrap(),
+    )
+}
+
+async fn post(state: &Arc<AppState>, channel_id: i32, user: &str, text: &str) -> i64 {
+    let content = serde_json::json!({
+        "type": "chat",
+        "user": user,
+        "text": text,
+        "timestamp": "2026-01-02T03:04:05+00:00",
+    });
+    db::insert_message(&state.db, channel_id, &content.to_string())
+        .await
+        .expect("insert")
+}
+
+async fn import(
+    state: &Arc<AppState>,
+    source: &str,
+    body: &str,
+    token: &str,
+) -> (StatusCode, serde_json::Value) {
+    let response = export::router()
+        .with_state(state.clone())
+        .oneshot(
+            Request::post(format!("/import?source={source}"))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn rejects_bad_token_and_unknown_channel() {
+    let state = make_state().await;
+    let (status, _, _) = get(&state, "/export", "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _, _) = get(&state, "/export?channel=9999", "token").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn json_export_spans_batches_with_reactions() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel");
+    let other = db::add_channel(&state.db, "other", None)
+        .await
+        .expect("insert")
+        .expect("new channel")
+        .id;
+    let total = EXPORT_BATCH_SIZE as usize + 3;
+    let mut first = 0;
+    for i in 0..total {
+        let id = post(&state, general, "alice", &format!("m{i}")).await;
+        if i == 0 {
+            first = id;
+        }
+    }
+    post(&state, other, "bob", "elsewhere").await;
+    db::add_reaction(&state.db, first, "bob", "🎉")
+        .await
+        .expect("react");
+
+    let (status, disposition, body) =
+        get(&state, &format!("/export?channel={general}"), "token").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        disposition,
+        format!("attachment; filename=\"murmer-channel-{general}.json\"")
+    );
+    let messages: Vec<serde_json::Value> = serde_json::from_str(&body).expect("valid json");
+    assert_eq!(messages.len(), total);
+    assert_eq!(messages[0]["id"], first);
+    assert_eq!(messages[0]["reactions"]["🎉"][0], "bob");
+    assert_eq!(messages[total - 1]["text"], format!("m{}", total - 1));
+
+    let (_, _, body) = get(&state, "/export", "token").await;
+    let messages: Vec<serde_json::Value> = serde_json::from_str(&body).expect("valid json");
+    assert_eq!(messages.len(), total + 1);
+    assert_eq!(messages[total]["channelId"], other);
+}
+
+#[tokio::test]
+async fn csv_export_flattens_and_quotes_fields() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel");
+    let plain = post(&state, general, "alice", "hello").await;
+    let quoted = post(&state, general, "bob", "a, \"b\"\nc").await;
+
+    let (status, disposition, body) = get(&state, "/export?format=csv", "token").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(disposition, "attachment; filename=\"murmer-export.csv\"");
+    assert_eq!(
+        body,
+        format!(
+            "id,channel,user,timestamp,content\n\
+             {plain},{general},alice,2026-01-02T03:04:05+00:00,hello\n\
+             {quoted},{general},bob,2026-01-02T03:04:05+00:00,\"a, \"\"b\"\"\nc\"\n"
+        )
+    );
+}
+
+#[tokio::test]
+async fn import_round_trips_an_export_idempotently() {
+    let source = make_state().await;
+    let general = db::get_channel_id_by_name(&source.db, "general")
+        .await
+        .expect("default channel");
+    let archive = db::add_channel(&source.db, "archive", None)
+        .await
+        .expect("insert")
+        .expect("new channel")
+        .id;
+    let first = post(&source, general, "alice", "hello").await;
+    post(&source, archive, "bob", "old news").await;
+    db::add_reaction(&source.db, first, "bob", "👍")
+        .await
+        .expect("react");
+    let (_, _, dump) = get(&source, "/export", "token").await;
+
+    let target = make_state().await;
+    let mut rx = target.tx.subscribe();
+    let (status, _) = import(&target, "old", &dump, "wrong").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, summary) = import(&target, "old", &dump, "token").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary["imported"], 2);
+    assert_eq!(summary["skipped"], 0);
+    let created: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(created["type"], "channel-add");
+    assert_eq!(created["name"], "archive");
+
+    let (_, _, copy) = get(&target, "/export", "token").await;
+    let copy: Vec<serde_json::Value> = serde_json::from_str(&copy).unwrap();
+    assert_eq!(copy.len(), 2);
+    assert_eq!(copy[0]["channel"], "general");
+    assert_eq!(copy[0]["reactions"]["👍"][0], "bob");
+    assert_eq!(copy[1]["channel"], "archive");
+    assert_eq!(copy[1]["timestamp"], "2026-01-02T03:04:05+00:00");
+    let archive_copy = db::get_channel_id_by_name(&target.db, "archive")
+        .await
+        .expect("archive created");
+    assert_eq!(copy[1]["channelId"], archive_copy);
+
+    let (_, summary) = import(&target, "old", &dump, "token").await;
+    assert_eq!(summary["imported"], 0);
+    assert_eq!(summary["skipped"], 2);
+    assert_eq!(summary["errors"][0]["reason"], "duplicate");
+}
+
+#[tokio::test]
+async fn import_reports_malformed_records() {
+    let state = make_state().await;
+    let ts = "2026-01-02T03:04:05Z";
+    let records = serde_json::json!([
+        {"id": 1, "channel": "general", "user": "alice", "text": "ok", "timestamp": ts},
+        "not an object",
+        {"id": 2, "channel": "bad/name", "user": "alice", "timestamp": ts},
+        {"id": 3, "channel": "general", "timestamp": ts},
+        {"id": 4, "channel": "general", "user": "alice", "timestamp": "yesterday"},
+        {"channel": "general", "user": "alice", "timestamp": ts},
+        {"id": 6, "channel": "general", "user": "alice", "timestamp": ts, "reactions": [1]},
+    ]);
+    let (status, summary) = import(&state, "old", &records.to_string(), "token").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary["imported"], 1);
+    assert_eq!(summary["skipped"], 6);
+    let reasons: Vec<_> = summary["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["index"].as_u64().unwrap(), e["reason"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            (1, "not-an-object"),
+            (2, "invalid-channel"),
+            (3, "missing-user"),
+            (4, "invalid-timestamp"),
+            (5, "missing-id"),
+            (6, "invalid-reactions"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn imports_from_different_instances_do_not_collide() {
+    let state = make_state().await;
+    let ts = "2026-01-02T03:04:05Z";
+    let records = serde_json::json!([
+        {"id": 1, "channel": "general", "user": "alice", "text": "first", "timestamp": ts},
+        {"id": 2, "channel": "general", "user": "alice", "text": "second", "timestamp": ts},
+    ])
+    .to_string();
+
+    let (_, summary) = import(&state, "alpha", &records, "token").await;
+    assert_eq!(summary["imported"], 2);
+    let (_, summary) = import(&state, "beta", &records, "token").await;
+    assert_eq!(summary["imported"], 2);
+    let (_, summary) = import(&state, "alpha", &records, "token").await;
+    assert_eq!(summary["imported"], 0);
+    assert_eq!(summary["skipped"], 2);
+
+    for source in ["", "a:b", "has%20space"] {
+        let (status, _) = import(&state, source, &records, "token").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "source {source:?}");
+    }
+}
@@ -0,0 +1,27 @@
+use axum::{
+    body::{Body, to_bytes},
+    http::{Method, Request, StatusCode},
+};
+use murmer_server::health;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn head_probes_return_ok_without_body() {
+    for path in ["/", "/healthz"] {
+        for method in [Method::GET, Method::HEAD] {
+            let response = health::router::<()>()
+                .oneshot(
+                    Request::builder()
+                        .method(method.clone())
+                        .uri(path)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{method} {path}");
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert!(body.is_empty(), "{method} {path} returned a body");
+        }
+    }
+}
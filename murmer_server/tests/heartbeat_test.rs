@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use murmer_server::config::{heartbeat_interval, heartbeat_timeout};
+use serial_test::serial;
+use temp_env::with_vars;
+
+#[test]
+#[serial]
+fn heartbeat_defaults() {
+    with_vars(
+        [
+            ("HEARTBEAT_INTERVAL", None::<&str>),
+            ("HEARTBEAT_TIMEOUT", None),
+        ],
+        || {
+            assert_eq!(heartbeat_interval(), Duration::from_secs(30));
+            assert_eq!(heartbeat_timeout(), Duration::from_secs(90));
+        },
+    );
+}
+
+#[test]
+#[serial]
+fn heartbeat_timeout_never_undercuts_interval() {
+    with_vars(
+        [
+            ("HEARTBEAT_INTERVAL", Some("60")),
+            ("HEARTBEAT_TIMEOUT", Some("10")),
+        ],
+        || assert_eq!(heartbeat_timeout(), Duration::from_secs(60)),
+    );
+    with_vars(
+        [
+            ("HEARTBEAT_INTERVAL", Some("0")),
+            ("HEARTBEAT_TIMEOUT", Some("5")),
+        ],
+        || {
+            assert_eq!(heartbeat_interval(), Duration::from_secs(1));
+            assert_eq!(heartbeat_timeout(), Duration::from_secs(5));
+        },
+    );
+}
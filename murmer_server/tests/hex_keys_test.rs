@@ -0,0 +1,157 @@
+//! Ed25519 keys and signatures may be sent as hex as well as base64: both
+//! verify, replay protection spans the two forms, and hex keys are stored in
+//! the canonical base64 form.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{Router, routing::get};
+use base64::{Engine as _, engine::general_purpose};
+use ed25519_dalek::{Signer, SigningKey};
+use futures::{SinkExt, StreamExt};
+use murmer_server::security::{self, ProofError};
+use murmer_server::{AppState, RateLimiter, db, ws};
+use serde_json::{Value, json};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
+
+mod common;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn make_state(password: Option<&str>) -> Arc<AppState> {
+    Arc::new(AppState {
+        password: password.map(str::to_string),
+        ..common::state().await
+    })
+}
+
+async fn connect(state: Arc<AppState>) -> Socket {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+    socket
+}
+
+async fn send(socket: &mut Socket, frame: Value) {
+    socket
+        .send(Message::Text(frame.to_string().into()))
+        .await
+        .unwrap();
+}
+
+/// Read text frames until one of type `wanted` arrives.
+async fn next_of_type(socket: &mut Socket, wanted: &str) -> Value {
+    loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection still open")
+            .expect("valid frame");
+        match frame {
+            Message::Text(text) => {
+                let v: Value = serde_json::from_str(&text).unwrap();
+                if v["type"] == wanted {
+                    return v;
+                }
+            }
+            Message::Close(_) => panic!("server closed the connection"),
+            _ => {}
+        }
+    }
+}
+
+fn now_millis() -> String {
+    chrono::Utc::now().timestamp_millis().to_string()
+}
+
+#[tokio::test]
+async fn hex_keys_and_signatures_verify() {
+    let signing = SigningKey::from_bytes(&[3u8; 32]);
+    let pk_hex = hex::encode(signing.verifying_key().as_bytes());
+    let pk_b64 = general_purpose::STANDARD.encode(signing.verifying_key().as_bytes());
+    let limiter = RateLimiter::new();
+
+    let ts = now_millis();
+    let sig_hex = hex::encode(signing.sign(ts.as_bytes()).to_bytes());
+    assert!(
+        security::verify_signed_proof(&limiter, &pk_hex, &sig_hex, &ts, &ts, "test")
+            .await
+            .is_ok()
+    );
+    assert!(security::verify_ed25519(&pk_hex, &sig_hex, ts.as_bytes()));
+    assert_eq!(
+        security::canonical_public_key(&pk_hex).as_deref(),
+        Some(pk_b64.as_str())
+    );
+
+    // The same proof re-sent from elsewhere with the key in base64 is still
+    // a replay (the same origin gets one retry within the grace period).
+    let sig_b64 = general_purpose::STANDARD.encode(signing.sign(ts.as_bytes()).to_bytes());
+    assert_eq!(
+        security::verify_signed_proof(&limiter, &pk_b64, &sig_b64, &ts, &ts, "elsewhere").await,
+        Err(ProofError::Replay)
+    );
+
+    // Mixed forms verify too.
+    let ts = (ts.parse::<i64>().unwrap() + 1).to_string();
+    let sig_b64 = general_purpose::STANDARD.encode(signing.sign(ts.as_bytes()).to_bytes());
+    assert!(
+        security::verify_signed_proof(&limiter, &pk_hex, &sig_b64, &ts, &ts, "test")
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn keys_that_are_neither_base64_nor_hex_are_invalid_encoding() {
+    let limiter = RateLimiter::new();
+    let ts = now_millis();
+    // 64 characters, but not all hex digits and not base64 either.
+    let bogus = format!("{}!", "g".repeat(63));
+    assert_eq!(
+        security::verify_signed_proof(&limiter, &bogus, &bogus, &ts, &ts, "test").await,
+        Err(ProofError::InvalidEncoding)
+    );
+    // Hex of the wrong length is not read as hex.
+    assert!(security::canonical_public_key(&"ab".repeat(31)).is_none());
+}
+
+#[tokio::test]
+async fn hex_presence_binds_the_canonical_key() {
+    let state = make_state(None).await;
+    let mut socket = connect(state.clone()).await;
+    let signing = SigningKey::from_bytes(&[4u8; 32]);
+    let ts = now_millis();
+    send(
+        &mut socket,
+        json!({
+            "type": "presence",
+            "user": "carol",
+            "publicKey": hex::encode(signing.verifying_key().as_bytes()),
+            "signature": hex::encode(signing.sign(ts.as_bytes()).to_bytes()),
+            "timestamp": ts,
+        }),
+    )
+    .await;
+    next_of_type(&mut socket, "online-users").await;
+
+    let bound = db::get_user_key(&state.db, "carol").await.unwrap();
+    assert_eq!(
+        bound,
+        Some(general_purpose::STANDARD.encode(signing.verifying_key().as_bytes()))
+    );
+}
@@ -0,0 +1,86 @@
+use std::io::Read;
+
+use axum::extract::ws::Message;
+use flate2::read::GzDecoder;
+use murmer_server::db;
+use murmer_server::ws::encoding::{
+    MIN_COMPRESSED_HISTORY_BYTES, history_compression_requested, history_frame,
+};
+use murmer_server::ws::inbound::HandshakeOptions;
+use serde_json::{Value, json};
+
+async fn history_json(count: usize) -> String {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let channel = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    for i in 0..count {
+        let content = json!({
+            "type": "chat",
+            "user": format!("user{}", i % 7),
+            "text": format!("message number {i}, talking about the release schedule again"),
+            "timestamp": format!("2026-10-15T12:{:02}:{:02}Z", i / 60 % 60, i % 60),
+        });
+        db::insert_message(&db, channel, &content.to_string())
+            .await
+            .expect("insert message");
+    }
+    let msgs = db::history_messages(&db, channel, None, count as i64)
+        .await
+        .expect("history");
+    json!({"type": "history", "messages": msgs}).to_string()
+}
+
+#[test]
+fn compression_is_opt_in_through_presence() {
+    let requested = |presence: Value| {
+        let options: HandshakeOptions = serde_json::from_value(presence).expect("options");
+        history_compression_requested(&options)
+    };
+    assert!(requested(
+        json!({"type": "presence", "compression": "gzip"})
+    ));
+    assert!(!requested(json!({"type": "presence"})));
+    assert!(!requested(
+        json!({"type": "presence", "compression": "brotli"})
+    ));
+}
+
+/// A full 200-message history page shrinks substantially and decompresses
+/// back to the exact JSON frame.
+#[tokio::test]
+async fn full_history_page_is_gzipped() {
+    let payload = history_json(200).await;
+    let Message::Binary(bytes) = history_frame(payload.clone(), true) else {
+        panic!("expected a binary frame");
+    };
+    assert_eq!(&bytes[..2], &[0x1f, 0x8b], "gzip magic marks the frame");
+
+    let mut decoded = String::new();
+    GzDecoder::new(&bytes[..])
+        .read_to_string(&mut decoded)
+        .expect("valid gzip");
+    assert_eq!(decoded, payload);
+
+    let ratio = bytes.len() as f64 / payload.len() as f64;
+    println!(
+        "200-message history: {} bytes -> {} bytes gzipped ({:.0}%)",
+        payload.len(),
+        bytes.len(),
+        ratio * 100.0
+    );
+    assert!(ratio < 0.25, "compressed to {:.0}%", ratio * 100.0);
+}
+
+#[tokio::test]
+async fn small_or_unrequested_history_stays_text() {
+    let small = history_json(1).await;
+    assert!(small.len() < MIN_COMPRESSED_HISTORY_BYTES);
+    assert!(matches!(history_frame(small, true), Message::Text(_)));
+
+    let large = history_json(200).await;
+    let Message::Text(text) = history_frame(large.clone(), false) else {
+        panic!("expected a text frame");
+    };
+    assert_eq!(text.as_str(), large);
+}
@@ -0,0 +1,108 @@
+//! Tests for the `GET /identicon/{key}.png` default avatars: key parsing,
+//! deterministic mirrored rendering and the on-disk cache.
+
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use base64::{
+    Engine as _,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use murmer_server::AppState;
+use murmer_server::identicon::{
+    self, IDENTICON_SIZE, identicon_url, parse_identicon_key, render_identicon,
+};
+use tower::ServiceExt;
+
+mod common;
+
+async fn make_state(upload_dir: PathBuf) -> Arc<AppState> {
+    Arc::new(AppState {
+        upload_dir,
+        ..common::state().await
+    })
+}
+
+#[test]
+fn keys_parse_from_either_base64_alphabet() {
+    // 0xfb 0xff encodes with `+`/`/` in standard base64 and `-`/`_` URL-safe.
+    let key = [0xfbu8; 32];
+    let standard = STANDARD.encode(key);
+    assert!(standard.contains('+') || standard.contains('/'));
+    assert_eq!(parse_identicon_key(&format!("{standard}.png")), Some(key));
+    let url_safe = URL_SAFE_NO_PAD.encode(key);
+    assert_eq!(parse_identicon_key(&format!("{url_safe}.png")), Some(key));
+    assert_eq!(
+        identicon_url(&standard),
+        Some(format!("/identicon/{url_safe}.png"))
+    );
+
+    assert!(parse_identicon_key(&standard).is_none());
+    assert!(parse_identicon_key(&format!("{}.png", STANDARD.encode([1u8; 16]))).is_none());
+    assert!(parse_identicon_key("../secret.png").is_none());
+    assert!(identicon_url("not a key").is_none());
+}
+
+#[test]
+fn rendering_is_deterministic_and_mirrored() {
+    let hash = [0xa5u8; 32];
+    let png = render_identicon(&hash);
+    assert_eq!(png, render_identicon(&hash));
+    assert_ne!(png, render_identicon(&[0x5au8; 32]));
+
+    let img = image::load_from_memory(&png).expect("valid png").to_rgb8();
+    assert_eq!(img.dimensions(), (IDENTICON_SIZE, IDENTICON_SIZE));
+    for y in 0..IDENTICON_SIZE {
+        for x in 0..IDENTICON_SIZE / 2 {
+            assert_eq!(
+                img.get_pixel(x, y),
+                img.get_pixel(IDENTICON_SIZE - 1 - x, y)
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn identicons_are_served_and_cached() {
+    let dir = std::env::temp_dir().join(format!("murmer-identicon-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let state = make_state(dir.clone()).await;
+    let request = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+    let url = identicon_url(&STANDARD.encode([7u8; 32])).expect("valid key");
+    let response = identicon::router()
+        .with_state(state.clone())
+        .oneshot(request(&url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+    assert!(
+        response.headers()[header::CACHE_CONTROL]
+            .to_str()
+            .unwrap()
+            .contains("immutable")
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    let cached: Vec<_> = std::fs::read_dir(dir.join("identicons"))
+        .expect("cache dir")
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(cached.len(), 1);
+    assert_eq!(std::fs::read(&cached[0]).unwrap(), body.to_vec());
+
+    let response = identicon::router()
+        .with_state(state)
+        .oneshot(request("/identicon/short.png"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
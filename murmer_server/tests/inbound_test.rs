@@ -0,0 +1,130 @@
+//! Tests for decoding inbound WebSocket frames into typed messages.
+
+use murmer_server::ws::inbound::{ClientMessage, ReactionAction};
+use serde::Deserialize;
+use serde_json::json;
+
+fn decode(v: serde_json::Value) -> Result<ClientMessage, serde_json::Error> {
+    ClientMessage::deserialize(&v)
+}
+
+#[test]
+fn modelled_frames_decode_with_camel_case_fields() {
+    let msg = decode(json!({
+        "type": "react",
+        "messageId": 42,
+        "action": "remove",
+        "emoji": "👍",
+        "extra": "ignored",
+    }))
+    .expect("valid react frame");
+    let ClientMessage::React(react) = msg else {
+        panic!("expected a react message, got {msg:?}");
+    };
+    assert_eq!(react.message_id, 42);
+    assert_eq!(react.action, ReactionAction::Remove);
+    assert_eq!(react.emoji, "👍");
+
+    let msg = decode(json!({"type": "flag-message", "messageId": 7})).unwrap();
+    let ClientMessage::FlagMessage(flag) = msg else {
+        panic!("expected a flag message, got {msg:?}");
+    };
+    assert_eq!(flag.reason, "");
+
+    let msg = decode(json!({"type": "wiki-get", "channelId": 3, "slug": "home"})).unwrap();
+    let ClientMessage::WikiGet(page) = msg else {
+        panic!("expected a wiki-get message, got {msg:?}");
+    };
+    assert!(page.request_id.is_null());
+}
+
+#[test]
+fn chat_keeps_unmodelled_fields_for_storage() {
+    let msg = decode(json!({
+        "type": "chat",
+        "text": "hi",
+        "replyTo": {"id": 9, "user": "bob", "text": "quoted"},
+        "scheduleAt": "2030-01-01T00:00:00Z",
+        "image": "/files/cat.png",
+    }))
+    .expect("valid chat frame");
+    let ClientMessage::Chat(chat) = msg else {
+        panic!("expected a chat message, got {msg:?}");
+    };
+    assert_eq!(chat.text.as_deref(), Some("hi"));
+    assert_eq!(chat.reply_to.map(|r| r.id()), Some(9));
+    assert_eq!(chat.schedule_at.as_deref(), Some("2030-01-01T00:00:00Z"));
+    assert_eq!(chat.extra.get("image"), Some(&json!("/files/cat.png")));
+    assert!(!chat.extra.contains_key("type"));
+    assert!(!chat.extra.contains_key("replyTo"));
+}
+
+#[test]
+fn presence_and_channel_admin_frames_are_modelled() {
+    let msg = decode(json!({
+        "type": "presence",
+        "user": "alice",
+        "protocolVersion": "2",
+        "initState": true,
+    }))
+    .unwrap();
+    let ClientMessage::Presence(presence) = msg else {
+        panic!("expected a presence message, got {msg:?}");
+    };
+    assert_eq!(presence.user.as_deref(), Some("alice"));
+    // Left raw so negotiation can answer with the supported range.
+    assert_eq!(presence.options.protocol_version, json!("2"));
+    assert!(presence.options.init_state);
+
+    // A `null` bitrate removes the cap; a missing one leaves it alone.
+    let msg =
+        decode(json!({"type": "update-voice-channel", "channelId": 4, "bitrate": null})).unwrap();
+    let ClientMessage::UpdateVoiceChannel(update) = msg else {
+        panic!("expected an update-voice-channel message, got {msg:?}");
+    };
+    assert_eq!(update.bitrate, Some(None));
+    let msg = decode(json!({"type": "update-voice-channel", "channelId": 4})).unwrap();
+    let ClientMessage::UpdateVoiceChannel(update) = msg else {
+        panic!("expected an update-voice-channel message, got {msg:?}");
+    };
+    assert_eq!(update.bitrate, None);
+
+    let msg = decode(json!({"type": "set-channel-max-subscribers", "channelId": 2, "max": null}))
+        .unwrap();
+    let ClientMessage::SetChannelMaxSubscribers(limit) = msg else {
+        panic!("expected a set-channel-max-subscribers message, got {msg:?}");
+    };
+    assert_eq!((limit.channel_id, limit.max), (2, None));
+}
+
+#[test]
+fn other_frame_types_stay_untyped() {
+    assert!(matches!(
+        decode(json!({"type": "typing"})),
+        Ok(ClientMessage::Untyped)
+    ));
+    assert!(matches!(
+        decode(json!({"type": "no-such-frame"})),
+        Ok(ClientMessage::Untyped)
+    ));
+}
+
+#[test]
+fn malformed_modelled_frames_are_errors() {
+    for frame in [
+        json!({"type": "react", "messageId": "42", "action": "add", "emoji": "👍"}),
+        json!({"type": "react", "messageId": 42, "action": "toggle", "emoji": "👍"}),
+        json!({"type": "mark-read", "channelId": 1}),
+        json!({"type": "wiki-update", "channelId": 1, "slug": "a", "title": "A", "body": ""}),
+        json!({"type": "voice-join", "channelId": 1, "user": 5}),
+        json!({"type": "chat", "text": 5}),
+        json!({"type": "chat", "replyTo": "9"}),
+        json!({"type": "presence", "user": "alice", "initState": "yes"}),
+        json!({"type": "set-slow-mode", "channelId": 1}),
+        json!({"type": "set-channel-receipts", "channelId": 1, "enabled": "on"}),
+        json!({"type": "reorder-channels", "order": [1, "2"]}),
+        json!({"type": "delete-channel"}),
+    ] {
+        assert!(decode(frame.clone()).is_err(), "{frame} should not decode");
+    }
+}
@@ -0,0 +1,113 @@
+//! Tests for last-seen tracking of offline users.
+
+use std::sync::Arc;
+
+use chrono::{TimeZone, Utc};
+use murmer_server::ws::helpers::{
+    broadcast_status, init_state_frame, record_last_seen, touch_last_seen, user_directory_page,
+};
+use murmer_server::{AppState, db};
+use serde_json::Value;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".to_string()),
+        ..common::state_with_roles().await
+    })
+}
+
+#[tokio::test]
+async fn last_seen_persists_and_round_trips() {
+    let state = make_state().await;
+    let first = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    let later = Utc.with_ymd_and_hms(2024, 5, 2, 8, 30, 0).unwrap();
+    db::set_last_seen(&state.db, "alice", first).await.unwrap();
+    db::set_last_seen(&state.db, "alice", later).await.unwrap();
+    db::set_last_seen(&state.db, "bot", first).await.unwrap();
+
+    let mut all = db::get_all_last_seen(&state.db).await.unwrap();
+    all.sort();
+    assert_eq!(
+        all,
+        [("alice".to_string(), later), ("bot".to_string(), first)]
+    );
+}
+
+#[tokio::test]
+async fn status_updates_carry_last_seen_only_while_offline() {
+    let state = make_state().await;
+    let mut rx = state.tx.subscribe();
+
+    state.users.lock().await.insert("alice".into());
+    record_last_seen(&state, "alice").await;
+    broadcast_status(&state, "alice", "online").await;
+    let online: Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(online["type"], "status-update");
+    assert!(online["lastSeen"].is_null());
+
+    state.users.lock().await.remove("alice");
+    broadcast_status(&state, "alice", "offline").await;
+    let offline: Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    let seen = offline["lastSeen"]
+        .as_str()
+        .expect("offline users report lastSeen");
+    assert_eq!(
+        chrono::DateTime::parse_from_rfc3339(seen).unwrap(),
+        state.last_seen.lock().await["alice"]
+    );
+
+    let persisted = db::get_all_last_seen(&state.db).await.unwrap();
+    assert_eq!(persisted.len(), 1);
+    assert_eq!(persisted[0].0, "alice");
+}
+
+#[tokio::test]
+async fn directory_pages_carry_last_seen_but_snapshots_do_not() {
+    let state = make_state().await;
+    {
+        let mut known = state.known_users.write().await;
+        known.insert("alice".into());
+        known.insert("bob".into());
+    }
+    state.users.lock().await.insert("bob".into());
+    record_last_seen(&state, "alice").await;
+    record_last_seen(&state, "bob").await;
+    state
+        .statuses
+        .write()
+        .await
+        .insert("bob".into(), "busy".into());
+
+    let (total, page) = user_directory_page(&state, "", 0, 10).await;
+    assert_eq!(total, 2);
+    assert_eq!(page[0]["name"], "alice");
+    assert!(page[0]["lastSeen"].is_string());
+    assert_eq!(page[1]["name"], "bob");
+    assert!(page[1]["lastSeen"].is_null());
+
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel");
+    let init: Value =
+        serde_json::from_str(&init_state_frame(&state, Some("bob"), general).await).unwrap();
+    let snapshot = init["frames"]
+        .as_array()
+        .expect("frames")
+        .iter()
+        .find(|f| f["type"] == "status-snapshot")
+        .expect("status snapshot");
+    assert!(snapshot.get("lastSeen").is_none());
+}
+
+#[tokio::test]
+async fn activity_refreshes_last_seen_at_most_once_per_interval() {
+    let state = make_state().await;
+    let mut last_touch = None;
+    touch_last_seen(&state, "alice", &mut last_touch).await;
+    let first = state.last_seen.lock().await["alice"];
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    touch_last_seen(&state, "alice", &mut last_touch).await;
+    assert_eq!(state.last_seen.lock().await["alice"], first);
+}
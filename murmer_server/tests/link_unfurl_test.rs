@@ -0,0 +1,80 @@
+//! Tests for unfurling links posted in chat: URL detection, the address
+//! guard in front of every fetch and the `link_previews` cache.
+
+use murmer_server::config::link_previews_enabled;
+use murmer_server::db;
+use murmer_server::link_preview::{STORED_PREVIEW_TTL, first_url, unfurl};
+use serial_test::serial;
+use temp_env::with_var;
+
+#[test]
+fn first_url_skips_text_and_trailing_punctuation() {
+    assert_eq!(
+        first_url("see https://example.com/a?b=1, then http://other.org"),
+        Some("https://example.com/a?b=1")
+    );
+    assert_eq!(
+        first_url("(docs at https://example.com/guide)."),
+        Some("https://example.com/guide")
+    );
+    assert_eq!(
+        first_url("<https://example.com/x>"),
+        Some("https://example.com/x")
+    );
+    assert_eq!(first_url("ftp://example.com and www.example.com"), None);
+    assert_eq!(first_url("no links here"), None);
+}
+
+#[tokio::test]
+async fn internal_targets_are_never_fetched_or_cached() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    for url in [
+        "http://127.0.0.1/",
+        "http://localhost/admin",
+        "http://10.0.0.1/",
+        "http://192.168.1.1/",
+        "http://169.254.169.254/latest/meta-data/",
+        "http://[::1]/",
+        "http://2130706433/",
+        "http://example.com:8080/",
+        "file:///etc/passwd",
+    ] {
+        assert!(unfurl(&db, url).await.is_none(), "{url} should be refused");
+        assert_eq!(
+            db::get_link_preview(&db, url, STORED_PREVIEW_TTL)
+                .await
+                .expect("cache lookup"),
+            None
+        );
+    }
+}
+
+#[tokio::test]
+async fn stored_previews_are_reused() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let url = "https://example.invalid/article";
+    db::store_link_preview(
+        &db,
+        url,
+        r#"{"url":"https://example.invalid/article","title":"Cached"}"#,
+    )
+    .await
+    .expect("store");
+
+    // `.invalid` never resolves, so only the cache can answer.
+    let preview = unfurl(&db, url).await.expect("cached preview");
+    let json = serde_json::to_value(&preview).expect("serialize");
+    assert_eq!(json["title"], "Cached");
+    assert_eq!(json["url"], url);
+}
+
+#[test]
+#[serial]
+fn unfurling_is_opt_in() {
+    with_var("ENABLE_LINK_PREVIEWS", None::<&str>, || {
+        assert!(!link_previews_enabled())
+    });
+    with_var("ENABLE_LINK_PREVIEWS", Some("on"), || {
+        assert!(link_previews_enabled())
+    });
+}
@@ -0,0 +1,76 @@
+//! The read-heavy presence maps (`known_users`, `statuses`, `role_defs`,
+//! `user_roles`, `voice_channels`) are `RwLock`s: the work a presence join
+//! fans out must proceed while other connections hold read guards on them.
+
+use std::{sync::Arc, time::Duration};
+
+use murmer_server::AppState;
+use murmer_server::ws::helpers::{
+    effective_permissions, get_user_lists, user_directory_page, voice_channel_list_frame,
+};
+use tokio::time::{Instant, sleep, timeout};
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".to_string()),
+        ..common::state_with_roles().await
+    })
+}
+
+/// Hold read guards on every presence map, as slow senders elsewhere would,
+/// and run the reads of a presence join under them.
+#[tokio::test]
+async fn presence_reads_proceed_under_held_read_guards() {
+    let state = make_state().await;
+    state.known_users.write().await.insert("alice".into());
+    let _known = state.known_users.read().await;
+    let _statuses = state.statuses.read().await;
+    let _defs = state.role_defs.read().await;
+    let _roles = state.user_roles.read().await;
+    let _voice = state.voice_channels.read().await;
+
+    timeout(Duration::from_secs(1), async {
+        let (_, all) = get_user_lists(&state).await;
+        assert_eq!(all, ["alice"]);
+        let (total, _) = user_directory_page(&state, "", 0, 10).await;
+        assert_eq!(total, 1);
+        effective_permissions(&state, "alice").await;
+        voice_channel_list_frame(&state, Some("alice"))
+            .await
+            .expect("frame");
+    })
+    .await
+    .expect("reads must not wait for other readers");
+}
+
+/// Many concurrent joins whose reads each hold the maps for a while finish in
+/// roughly the time of one, not the sum of all of them.
+#[tokio::test]
+async fn concurrent_presence_reads_overlap() {
+    const JOINS: u32 = 50;
+    const HOLD: Duration = Duration::from_millis(20);
+    let state = make_state().await;
+
+    let started = Instant::now();
+    let joins: Vec<_> = (0..JOINS)
+        .map(|_| {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let _statuses = state.statuses.read().await;
+                let _roles = state.user_roles.read().await;
+                sleep(HOLD).await;
+            })
+        })
+        .collect();
+    for join in joins {
+        join.await.expect("join task");
+    }
+    let elapsed = started.elapsed();
+    assert!(
+        elapsed < HOLD * JOINS / 4,
+        "{JOINS} joins took {elapsed:?}; serialized they would take {:?}",
+        HOLD * JOINS
+    );
+}
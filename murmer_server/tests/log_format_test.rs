@@ -0,0 +1,11 @@
+use murmer_server::config::json_logs;
+use serial_test::serial;
+use temp_env::with_var;
+
+#[test]
+#[serial]
+fn json_logging_is_opt_in() {
+    with_var("LOG_FORMAT", None::<&str>, || assert!(!json_logs()));
+    with_var("LOG_FORMAT", Some("JSON"), || assert!(json_logs()));
+    with_var("LOG_FORMAT", Some("compact"), || assert!(!json_logs()));
+}
@@ -0,0 +1,35 @@
+use murmer_server::ws::helpers::redact_for_log;
+use serde_json::json;
+
+#[test]
+fn presence_credentials_never_reach_the_log_line() {
+    let frame = json!({
+        "type": "presence",
+        "user": "alice",
+        "password": "hunter2",
+        "signature": "c2lnbmF0dXJl",
+        "timestamp": "1700000000000",
+    });
+    let line = format!("Received frame: {}", redact_for_log(&frame));
+    assert!(!line.contains("hunter2"));
+    assert!(!line.contains("c2lnbmF0dXJl"));
+    assert!(line.contains("\"password\":\"[redacted]\""));
+    assert!(line.contains("\"user\":\"alice\""));
+}
+
+#[test]
+fn bot_tokens_and_dm_content_are_hidden() {
+    let bot = json!({"type": "bot-presence", "token": "bot-secret"});
+    assert!(!redact_for_log(&bot).contains("bot-secret"));
+
+    let dm = json!({"type": "dm", "to": "bob", "nonce": "bm9uY2U=", "ciphertext": "Y2lwaGVy"});
+    let line = redact_for_log(&dm);
+    assert!(!line.contains("bm9uY2U=") && !line.contains("Y2lwaGVy"));
+    assert!(line.contains("\"to\":\"bob\""));
+}
+
+#[test]
+fn other_frames_are_logged_unchanged() {
+    let chat = json!({"type": "chat", "text": "password: hunter2"});
+    assert_eq!(redact_for_log(&chat), chat.to_string());
+}
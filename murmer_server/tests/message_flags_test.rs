@@ -0,0 +1,54 @@
+//! Tests for message flags: one report per user and message, the open-flag
+//! listing and resolution, and reason sanitizing.
+
+use murmer_server::db;
+use murmer_server::ws::validation::sanitize_flag_reason;
+
+#[tokio::test]
+async fn flags_are_unique_per_reporter_and_resolve_together() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let id = db::insert_message(&db, general, r#"{"user":"troll","text":"spam"}"#)
+        .await
+        .expect("insert message");
+
+    assert!(db::add_flag(&db, id, "alice", "spam").await.expect("flag"));
+    assert!(!db::add_flag(&db, id, "alice", "again").await.expect("flag"));
+    assert!(db::add_flag(&db, id, "bob", "").await.expect("flag"));
+    // Flags survive the message they point at, without its details.
+    assert!(db::add_flag(&db, 9999, "bob", "gone").await.expect("flag"));
+
+    let open = db::list_open_flags(&db, 10, 0).await.expect("list");
+    assert_eq!(open.len(), 3);
+    let alice = open.iter().find(|f| f.reporter == "alice").expect("alice");
+    assert_eq!(alice.reason, "spam");
+    assert_eq!(alice.channel_id, Some(general));
+    assert_eq!(alice.author.as_deref(), Some("troll"));
+    assert_eq!(alice.text.as_deref(), Some("spam"));
+    let orphan = open.iter().find(|f| f.message_id == 9999).expect("orphan");
+    assert!(orphan.channel_id.is_none() && orphan.author.is_none());
+
+    assert_eq!(db::resolve_flags(&db, id, "mod").await.expect("resolve"), 2);
+    assert_eq!(db::resolve_flags(&db, id, "mod").await.expect("resolve"), 0);
+    let open = db::list_open_flags(&db, 10, 0).await.expect("list");
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].message_id, 9999);
+
+    // A resolved flag still counts as the reporter's one flag.
+    assert!(!db::add_flag(&db, id, "alice", "spam").await.expect("flag"));
+}
+
+#[test]
+fn flag_reasons_are_cleaned_and_capped() {
+    assert_eq!(
+        sanitize_flag_reason("  rude\n\n\tand\u{7}  spam ").as_deref(),
+        Some("rude and spam")
+    );
+    assert_eq!(sanitize_flag_reason("").as_deref(), Some(""));
+    // The limit is 500 characters, not bytes.
+    let longest = "é".repeat(500);
+    assert_eq!(sanitize_flag_reason(&longest), Some(longest.clone()));
+    assert!(sanitize_flag_reason(&format!("{longest}x")).is_none());
+}
@@ -0,0 +1,27 @@
+use murmer_server::ws::helpers::message_too_large;
+use serde_json::json;
+use serial_test::serial;
+use temp_env::with_var;
+
+#[test]
+#[serial]
+fn limit_counts_serialized_utf8_bytes() {
+    with_var("MAX_MESSAGE_BYTES", Some("64"), || {
+        let frame = |text: &str| json!({"type": "chat", "text": text});
+        // `{"type":"chat","text":""}` is 25 bytes, leaving 39 for the text.
+        assert!(!message_too_large(&frame(&"a".repeat(39))));
+        assert!(message_too_large(&frame(&"a".repeat(40))));
+        // 13 three-byte characters are 39 bytes but only 13 chars.
+        assert!(!message_too_large(&frame(&"€".repeat(13))));
+        assert!(message_too_large(&frame(&"€".repeat(14))));
+    });
+}
+
+#[test]
+#[serial]
+fn defaults_to_eight_kib() {
+    with_var("MAX_MESSAGE_BYTES", None::<&str>, || {
+        assert!(!message_too_large(&json!({"text": "x".repeat(4000)})));
+        assert!(message_too_large(&json!({"text": "x".repeat(8 * 1024)})));
+    });
+}
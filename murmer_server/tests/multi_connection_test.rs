@@ -0,0 +1,187 @@
+//! Tests for per-user connection counting: a user with several sockets open
+//! stays online until the last one closes, while the voice membership of a
+//! closing socket ends with it.
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{Router, routing::get};
+use futures::SinkExt;
+use murmer_server::ws::helpers::{claim_connection, release_connection};
+use murmer_server::{AppState, VoiceChannelState, ws};
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(common::state_with_roles().await)
+}
+
+async fn serve(state: Arc<AppState>) -> SocketAddr {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+/// Poll until `check` holds, failing after a few seconds.
+async fn wait_for<F>(state: &Arc<AppState>, what: &str, check: F)
+where
+    F: Fn(&HashMap<String, usize>) -> bool,
+{
+    for _ in 0..100 {
+        if check(&*state.connections.lock().await) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("timed out waiting for {what}");
+}
+
+async fn status(state: &Arc<AppState>, user: &str) -> Option<String> {
+    state.statuses.read().await.get(user).cloned()
+}
+
+#[tokio::test]
+async fn user_stays_online_until_last_connection_closes() {
+    let state = make_state().await;
+    let addr = serve(state.clone()).await;
+    let mut sockets = Vec::new();
+    for _ in 0..2 {
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .expect("connect");
+        socket
+            .send(Message::Text(
+                r#"{"type":"presence","user":"alice"}"#.into(),
+            ))
+            .await
+            .unwrap();
+        sockets.push(socket);
+    }
+    wait_for(&state, "both connections", |c| c.get("alice") == Some(&2)).await;
+    assert!(state.users.lock().await.contains("alice"));
+
+    let mut first = sockets.remove(0);
+    first.close(None).await.unwrap();
+    wait_for(&state, "first disconnect", |c| c.get("alice") == Some(&1)).await;
+    assert!(state.users.lock().await.contains("alice"));
+    assert_eq!(status(&state, "alice").await.as_deref(), Some("online"));
+
+    let mut second = sockets.remove(0);
+    second.close(None).await.unwrap();
+    wait_for(&state, "last disconnect", |c| c.is_empty()).await;
+    // The offline cleanup runs right after the count drops.
+    for _ in 0..100 {
+        if !state.users.lock().await.contains("alice") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(!state.users.lock().await.contains("alice"));
+    assert_eq!(status(&state, "alice").await.as_deref(), Some("offline"));
+}
+
+#[tokio::test]
+async fn repeated_presence_counts_the_socket_once() {
+    let state = make_state().await;
+    claim_connection(&state, None, "alice").await;
+    claim_connection(&state, Some("alice"), "alice").await;
+    assert_eq!(state.connections.lock().await.get("alice"), Some(&1));
+
+    // Switching names on one socket hands its count over.
+    state.users.lock().await.insert("alice".into());
+    claim_connection(&state, Some("alice"), "bob").await;
+    assert!(!state.connections.lock().await.contains_key("alice"));
+    assert!(!state.users.lock().await.contains("alice"));
+
+    claim_connection(&state, None, "bob").await;
+    assert!(!release_connection(&state, "bob").await);
+    assert!(release_connection(&state, "bob").await);
+    assert!(state.connections.lock().await.is_empty());
+}
+
+async fn voice_roster(state: &Arc<AppState>, channel_id: i32) -> HashSet<String> {
+    state.voice_channels.read().await[&channel_id].users.clone()
+}
+
+#[tokio::test]
+async fn closing_the_socket_in_voice_leaves_voice_only() {
+    let state = make_state().await;
+    state.voice_channels.write().await.insert(
+        1,
+        VoiceChannelState {
+            name: "lounge".into(),
+            users: HashSet::new(),
+            quality: "standard".into(),
+            bitrate: None,
+            category_id: None,
+            position: 0,
+        },
+    );
+    let addr = serve(state.clone()).await;
+    let mut sockets = Vec::new();
+    for _ in 0..3 {
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .expect("connect");
+        socket
+            .send(Message::Text(
+                r#"{"type":"presence","user":"alice"}"#.into(),
+            ))
+            .await
+            .unwrap();
+        sockets.push(socket);
+    }
+    wait_for(&state, "three connections", |c| c.get("alice") == Some(&3)).await;
+    let mut in_voice = sockets.remove(0);
+    in_voice
+        .send(Message::Text(
+            r#"{"type":"voice-join","channelId":1}"#.into(),
+        ))
+        .await
+        .unwrap();
+    for _ in 0..100 {
+        if voice_roster(&state, 1).await.contains("alice") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(voice_roster(&state, 1).await.contains("alice"));
+
+    // Closing a socket that never joined voice keeps the call going.
+    let mut idle = sockets.remove(0);
+    idle.close(None).await.unwrap();
+    wait_for(&state, "idle disconnect", |c| c.get("alice") == Some(&2)).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(voice_roster(&state, 1).await.contains("alice"));
+
+    // Closing the socket in voice ends the call but not the session.
+    in_voice.close(None).await.unwrap();
+    wait_for(&state, "voice disconnect", |c| c.get("alice") == Some(&1)).await;
+    for _ in 0..100 {
+        if voice_roster(&state, 1).await.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(voice_roster(&state, 1).await.is_empty());
+    assert!(state.users.lock().await.contains("alice"));
+    assert_eq!(status(&state, "alice").await.as_deref(), Some("online"));
+}
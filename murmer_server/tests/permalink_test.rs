@@ -0,0 +1,55 @@
+use murmer_server::db;
+use murmer_server::ws::validation::{format_permalink, parse_permalink};
+
+#[test]
+fn permalinks_round_trip() {
+    let link = format_permalink(3, 42);
+    assert_eq!(link, "3/42");
+    assert_eq!(parse_permalink(&link), Some((3, 42)));
+    assert_eq!(parse_permalink("3"), None);
+    assert_eq!(parse_permalink("0/42"), None);
+    assert_eq!(parse_permalink("3/-1"), None);
+    assert_eq!(parse_permalink("general/42"), None);
+}
+
+#[tokio::test]
+async fn permalink_resolves_to_message_with_context() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let other = db::add_channel(&db, "other", None)
+        .await
+        .expect("create channel")
+        .expect("name is free")
+        .id;
+
+    let mut ids = Vec::new();
+    for n in 0..7 {
+        ids.push(
+            db::insert_message(&db, general, &format!(r#"{{"text":"m{n}"}}"#))
+                .await
+                .expect("insert"),
+        );
+        // Interleaved messages in another channel never show up as context.
+        db::insert_message(&db, other, r#"{"text":"elsewhere"}"#)
+            .await
+            .expect("insert");
+    }
+
+    let (channel_id, message_id) = parse_permalink(&format_permalink(general, ids[3])).unwrap();
+    let rows = db::fetch_message_context(&db, channel_id, message_id, 2)
+        .await
+        .expect("context");
+    let got: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
+    assert_eq!(got, ids[1..=5].to_vec());
+    assert_eq!(rows[2].1, r#"{"text":"m3"}"#);
+
+    // A permalink naming the wrong channel does not resolve.
+    assert!(
+        db::fetch_message_context(&db, other, ids[3], 2)
+            .await
+            .expect("context")
+            .is_empty()
+    );
+}
@@ -1,41 +1,24 @@
 //! Integration tests for the in-memory permission resolver: effective
-//! permissions (union of `@everyone` + assigned roles), the hierarchy position,
-//! and the no-`ADMIN_TOKEN` channel/wiki fallback.
+//! permissions (union of `@everyone` + assigned roles), the hierarchy position
+//! and who may moderate whom, per-channel posting restrictions, and the
+//! no-`ADMIN_TOKEN` channel/wiki fallback.
 
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::sync::Arc;
 
 use murmer_server::permissions::{
     ADMINISTRATOR, DEFAULT_EVERYONE, MANAGE_CHANNELS, MANAGE_EMOJIS, SEND_MESSAGES, VIEW_CHANNELS,
 };
-use murmer_server::ws::helpers::{effective_permissions, has_permission, top_position};
-use murmer_server::{AppState, RateLimiter, RoleDef, db};
-use tokio::sync::{Mutex, broadcast};
+use murmer_server::ws::helpers::{
+    can_post_in_channel, effective_permissions, has_permission, outranks, top_position,
+};
+use murmer_server::{AppState, RoleDef, db};
+
+mod common;
 
 async fn make_state(admin_token: Option<&str>) -> Arc<AppState> {
-    let database = db::init(":memory:").await.expect("in-memory db");
-    let (tx, _) = broadcast::channel(64);
     Arc::new(AppState {
-        tx,
-        channels: Arc::new(Mutex::new(HashMap::new())),
-        db: database,
-        users: Arc::new(Mutex::new(Default::default())),
-        known_users: Arc::new(Mutex::new(Default::default())),
-        voice_channels: Arc::new(Mutex::new(HashMap::new())),
-        role_defs: Arc::new(Mutex::new(HashMap::new())),
-        user_roles: Arc::new(Mutex::new(HashMap::new())),
-        channel_overrides: Arc::new(Mutex::new(HashMap::new())),
-        statuses: Arc::new(Mutex::new(HashMap::new())),
-        user_keys: Arc::new(Mutex::new(HashMap::new())),
-        mutes: Arc::new(Mutex::new(HashMap::new())),
-        active_screen_shares: Arc::new(Mutex::new(HashMap::new())),
-        voice_mutes: Arc::new(Mutex::new(HashMap::new())),
-        connection_stats: Arc::new(Mutex::new(HashMap::new())),
-        voice_session_starts: Arc::new(Mutex::new(HashMap::new())),
-        screenshare_session_starts: Arc::new(Mutex::new(HashMap::new())),
-        upload_dir: PathBuf::from("uploads"),
-        password: None,
         admin_token: admin_token.map(str::to_string),
-        rate_limiter: RateLimiter::new(),
+        ..common::state().await
     })
 }
 
@@ -52,12 +35,12 @@ fn role(id: i64, permissions: u64, position: i64, is_default: bool, is_owner: bo
 }
 
 async fn seed(state: &Arc<AppState>, defs: Vec<RoleDef>, assignments: &[(&str, Vec<i64>)]) {
-    let mut map = state.role_defs.lock().await;
+    let mut map = state.role_defs.write().await;
     for def in defs {
         map.insert(def.id, def);
     }
     drop(map);
-    let mut ur = state.user_roles.lock().await;
+    let mut ur = state.user_roles.write().await;
     for (user, ids) in assignments {
         ur.insert((*user).to_string(), ids.clone());
     }
@@ -134,3 +117,92 @@ async fn channel_management_is_open_without_admin_token() {
     assert!(has_permission(&state, "anyone", MANAGE_CHANNELS).await);
     assert!(!has_permission(&state, "anyone", MANAGE_EMOJIS).await);
 }
+
+#[tokio::test]
+async fn cannot_moderate_an_equal_or_higher_role() {
+    let state = make_state(Some("token")).await;
+    seed(
+        &state,
+        vec![
+            role(1, DEFAULT_EVERYONE, 0, true, false),
+            role(2, DEFAULT_EVERYONE, 1, false, false),
+            role(3, DEFAULT_EVERYONE, 2, false, false),
+            role(4, ADMINISTRATOR, 3, false, true),
+        ],
+        &[
+            ("mod", vec![2]),
+            ("other-mod", vec![2]),
+            ("admin", vec![3]),
+            ("owner", vec![4]),
+        ],
+    )
+    .await;
+
+    assert!(outranks(&state, "mod", "member").await);
+    assert!(!outranks(&state, "mod", "other-mod").await);
+    assert!(!outranks(&state, "mod", "admin").await);
+    assert!(!outranks(&state, "member", "member").await);
+    assert!(outranks(&state, "admin", "mod").await);
+    assert!(outranks(&state, "owner", "admin").await);
+    assert!(!outranks(&state, "admin", "owner").await);
+}
+
+#[tokio::test]
+async fn offline_users_keep_their_persisted_rank() {
+    let state = make_state(Some("token")).await;
+    let defs = db::list_role_defs(&state.db).await.expect("list roles");
+    let mod_id = defs.iter().find(|d| d.name == "Mod").expect("Mod").id;
+    seed(&state, defs, &[("mod", vec![mod_id])]).await;
+
+    // "boss" is not connected, so only the database knows their Admin role.
+    db::bind_user_key(&state.db, "boss", "boss-key")
+        .await
+        .expect("bind key");
+    db::assign_named_role(&state.db, "boss-key", "Admin", None)
+        .await
+        .expect("assign");
+
+    assert!(!outranks(&state, "mod", "boss").await);
+    assert!(outranks(&state, "boss", "mod").await);
+    assert!(outranks(&state, "mod", "stranger").await);
+}
+
+#[tokio::test]
+async fn restricted_channels_only_accept_listed_roles_and_administrators() {
+    let state = make_state(Some("token")).await;
+    let defs = db::list_role_defs(&state.db).await.expect("list roles");
+    let id_of = |name: &str| defs.iter().find(|d| d.name == name).expect(name).id;
+    let (admin_id, owner_id) = (id_of("Admin"), id_of("Owner"));
+    seed(
+        &state,
+        defs,
+        &[("admin", vec![admin_id]), ("owner", vec![owner_id])],
+    )
+    .await;
+    let channel = db::add_channel(&state.db, "announcements", None)
+        .await
+        .expect("add channel")
+        .expect("new channel");
+    assert!(channel.post_roles.is_none());
+    assert!(can_post_in_channel(&state, "member", channel.id).await);
+
+    assert!(
+        db::set_channel_post_roles(&state.db, channel.id, Some(&[admin_id]))
+            .await
+            .expect("restrict")
+    );
+    let stored = db::get_channel_by_id(&state.db, channel.id)
+        .await
+        .expect("channel");
+    assert_eq!(stored.post_roles, Some(vec![admin_id]));
+
+    assert!(!can_post_in_channel(&state, "member", channel.id).await);
+    assert!(can_post_in_channel(&state, "admin", channel.id).await);
+    // ADMINISTRATOR bypasses the list without holding a listed role.
+    assert!(can_post_in_channel(&state, "owner", channel.id).await);
+
+    db::set_channel_post_roles(&state.db, channel.id, None)
+        .await
+        .expect("lift");
+    assert!(can_post_in_channel(&state, "member", channel.id).await);
+}
@@ -0,0 +1,138 @@
+//! Drives `presence` frames with missing authentication fields over a real
+//! WebSocket: each is answered with `missing-auth-fields` naming the absent
+//! fields, and the connection stays open for a corrected retry.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{Router, routing::get};
+use base64::{Engine as _, engine::general_purpose};
+use ed25519_dalek::{Signer, SigningKey};
+use futures::{SinkExt, StreamExt};
+use murmer_server::{AppState, ws};
+use serde_json::{Value, json};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
+
+mod common;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn make_state(password: Option<&str>) -> Arc<AppState> {
+    Arc::new(AppState {
+        password: password.map(str::to_string),
+        ..common::state().await
+    })
+}
+
+async fn connect(state: Arc<AppState>) -> Socket {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+    socket
+}
+
+async fn send(socket: &mut Socket, frame: Value) {
+    socket
+        .send(Message::Text(frame.to_string().into()))
+        .await
+        .unwrap();
+}
+
+/// Read text frames until one of type `wanted` arrives.
+async fn next_of_type(socket: &mut Socket, wanted: &str) -> Value {
+    loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection still open")
+            .expect("valid frame");
+        match frame {
+            Message::Text(text) => {
+                let v: Value = serde_json::from_str(&text).unwrap();
+                if v["type"] == wanted {
+                    return v;
+                }
+            }
+            Message::Close(_) => panic!("server closed the connection"),
+            _ => {}
+        }
+    }
+}
+
+/// Send `frame` and expect a `missing-auth-fields` error listing `fields`.
+/// Each test ends with a successful presence on the same socket, which shows
+/// none of the rejected frames closed it.
+async fn expect_missing(socket: &mut Socket, frame: Value, fields: &[&str]) {
+    send(socket, frame).await;
+    let error = next_of_type(socket, "error").await;
+    assert_eq!(error["message"], "missing-auth-fields");
+    assert_eq!(error["fields"], json!(fields));
+}
+
+fn signed_presence(user: &str) -> Value {
+    let signing = SigningKey::from_bytes(&[9u8; 32]);
+    let timestamp = chrono::Utc::now().timestamp_millis().to_string();
+    json!({
+        "type": "presence",
+        "user": user,
+        "password": "hunter2",
+        "publicKey": general_purpose::STANDARD.encode(signing.verifying_key().as_bytes()),
+        "signature": general_purpose::STANDARD.encode(signing.sign(timestamp.as_bytes()).to_bytes()),
+        "timestamp": timestamp,
+    })
+}
+
+#[tokio::test]
+async fn password_servers_name_each_missing_key_field() {
+    let mut socket = connect(make_state(Some("hunter2")).await).await;
+    let full = signed_presence("alice");
+
+    expect_missing(
+        &mut socket,
+        json!({"type": "presence", "user": "alice", "password": "hunter2"}),
+        &["publicKey", "signature", "timestamp"],
+    )
+    .await;
+    for field in ["user", "publicKey", "signature", "timestamp"] {
+        let mut frame = full.clone();
+        frame.as_object_mut().unwrap().remove(field);
+        expect_missing(&mut socket, frame, &[field]).await;
+    }
+
+    // The same connection can still authenticate.
+    send(&mut socket, signed_presence("alice")).await;
+    let users = next_of_type(&mut socket, "online-users").await;
+    assert!(users.to_string().contains("alice"));
+}
+
+#[tokio::test]
+async fn open_servers_only_require_a_complete_proof_once_started() {
+    let mut socket = connect(make_state(None).await).await;
+
+    expect_missing(&mut socket, json!({"type": "presence"}), &["user"]).await;
+    expect_missing(
+        &mut socket,
+        json!({"type": "presence", "user": "bob", "signature": "c2ln"}),
+        &["publicKey", "timestamp"],
+    )
+    .await;
+
+    // Anonymous presence without any key field is still accepted.
+    send(&mut socket, json!({"type": "presence", "user": "bob"})).await;
+    let users = next_of_type(&mut socket, "online-users").await;
+    assert!(users.to_string().contains("bob"));
+}
@@ -0,0 +1,77 @@
+//! Tests for profile persistence: display name and bio keyed by public key,
+//! read back by name together with the avatar.
+
+use murmer_server::db;
+
+#[tokio::test]
+async fn profiles_follow_the_key_binding() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+
+    // Unbound names have no profile at all.
+    assert_eq!(db::get_profile(&db, "alice").await.expect("get"), None);
+
+    db::bind_user_key(&db, "alice", "pk-alice")
+        .await
+        .expect("bind");
+    let empty = db::get_profile(&db, "alice")
+        .await
+        .expect("get")
+        .expect("bound user has a card");
+    assert_eq!(empty.user_name, "alice");
+    assert!(empty.display_name.is_empty() && empty.bio.is_empty() && empty.avatar.is_empty());
+
+    db::upsert_profile(&db, "pk-alice", "Alice A.", "Hi\nthere")
+        .await
+        .expect("upsert");
+    db::set_user_avatar(&db, "alice", "/files/1-a.png")
+        .await
+        .expect("avatar");
+    let card = db::get_profile(&db, "alice")
+        .await
+        .expect("get")
+        .expect("profile");
+    assert_eq!(card.display_name, "Alice A.");
+    assert_eq!(card.bio, "Hi\nthere");
+    assert_eq!(card.avatar, "/files/1-a.png");
+
+    db::upsert_profile(&db, "pk-alice", "Alice", "")
+        .await
+        .expect("replace");
+    let card = db::get_profile(&db, "alice")
+        .await
+        .expect("get")
+        .expect("profile");
+    assert_eq!(
+        (card.display_name.as_str(), card.bio.as_str()),
+        ("Alice", "")
+    );
+}
+
+#[tokio::test]
+async fn snapshot_lists_only_users_with_profile_text() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    for (name, key) in [
+        ("alice", "pk-alice"),
+        ("bob", "pk-bob"),
+        ("carol", "pk-carol"),
+    ] {
+        db::bind_user_key(&db, name, key).await.expect("bind");
+    }
+    db::upsert_profile(&db, "pk-bob", "", "bio only")
+        .await
+        .expect("upsert");
+    db::upsert_profile(&db, "pk-alice", "Al", "")
+        .await
+        .expect("upsert");
+    db::upsert_profile(&db, "pk-carol", "", "")
+        .await
+        .expect("upsert");
+
+    let names: Vec<String> = db::get_all_profiles(&db)
+        .await
+        .expect("snapshot")
+        .into_iter()
+        .map(|p| p.user_name)
+        .collect();
+    assert_eq!(names, ["alice", "bob"]);
+}
@@ -0,0 +1,151 @@
+//! Tests for protocol version negotiation in `presence`: accepted versions
+//! get a `protocol` frame with the supported range, versions below the
+//! minimum are refused with `unsupported-protocol`.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{Router, routing::get};
+use futures::{SinkExt, StreamExt};
+use murmer_server::ws::inbound::HandshakeOptions;
+use murmer_server::ws::protocol::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, ProtocolVersion};
+use murmer_server::{AppState, ws};
+use serde_json::{Value, json};
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(common::state_with_roles().await)
+}
+
+async fn serve(state: Arc<AppState>) -> SocketAddr {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+/// Send `presence` and return the first `protocol` or `error` frame.
+async fn negotiate(addr: SocketAddr, presence: Value) -> Value {
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+    socket
+        .send(Message::Text(presence.to_string().into()))
+        .await
+        .unwrap();
+    loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection open")
+            .expect("valid frame");
+        if let Message::Text(text) = frame {
+            let v: Value = serde_json::from_str(&text).unwrap();
+            if v["type"] == "protocol" || v["type"] == "error" {
+                return v;
+            }
+        }
+    }
+}
+
+fn options(presence: Value) -> HandshakeOptions {
+    serde_json::from_value(presence).expect("options")
+}
+
+#[test]
+fn requested_versions() {
+    let version =
+        |presence: Value| ProtocolVersion::requested(&options(presence)).map(ProtocolVersion::get);
+    assert_eq!(version(json!({"type": "presence"})), Some(1));
+    assert_eq!(version(json!({"protocolVersion": null})), Some(1));
+    assert_eq!(version(json!({"protocolVersion": 2})), Some(2));
+    // Newer clients talk the newest version this server knows.
+    assert_eq!(
+        version(json!({"protocolVersion": 99})),
+        Some(PROTOCOL_VERSION)
+    );
+    assert_eq!(version(json!({"protocolVersion": 0})), None);
+    assert_eq!(version(json!({"protocolVersion": -1})), None);
+    assert_eq!(version(json!({"protocolVersion": "2"})), None);
+    assert_eq!(version(json!({"protocolVersion": 1.5})), None);
+}
+
+#[test]
+fn legacy_clients_lose_newer_features() {
+    let legacy = ProtocolVersion::default();
+    assert!(!legacy.supports_threads());
+    assert!(!legacy.supports_binary_encoding());
+    assert!(!legacy.supports_init_state());
+    let mut presence = options(json!({
+        "type": "presence",
+        "encoding": "msgpack",
+        "compression": "gzip",
+        "initState": true,
+    }));
+    legacy.strip_unsupported(&mut presence);
+    assert_eq!(presence.encoding, None);
+    assert_eq!(presence.compression, None);
+    assert!(!presence.init_state);
+
+    let current =
+        ProtocolVersion::requested(&options(json!({"protocolVersion": PROTOCOL_VERSION})))
+            .expect("supported");
+    assert!(current.supports_threads());
+    assert!(current.supports_binary_encoding());
+    assert!(current.supports_init_state());
+    let mut presence =
+        options(json!({"type": "presence", "encoding": "msgpack", "initState": true}));
+    current.strip_unsupported(&mut presence);
+    assert_eq!(presence.encoding.as_deref(), Some("msgpack"));
+    assert!(presence.init_state);
+}
+
+#[tokio::test]
+async fn accepted_versions_get_the_supported_range() {
+    let addr = serve(make_state().await).await;
+    for (requested, negotiated) in [(None, 1), (Some(2), 2), (Some(7), PROTOCOL_VERSION)] {
+        let mut presence = json!({"type": "presence", "user": "alice"});
+        if let Some(requested) = requested {
+            presence["protocolVersion"] = json!(requested);
+        }
+        let frame = negotiate(addr, presence).await;
+        assert_eq!(
+            frame,
+            json!({
+                "type": "protocol",
+                "version": negotiated,
+                "minVersion": MIN_PROTOCOL_VERSION,
+                "maxVersion": PROTOCOL_VERSION,
+            })
+        );
+    }
+}
+
+#[tokio::test]
+async fn versions_below_the_minimum_are_refused() {
+    let state = make_state().await;
+    let addr = serve(state.clone()).await;
+    let frame = negotiate(
+        addr,
+        json!({"type": "presence", "user": "alice", "protocolVersion": 0}),
+    )
+    .await;
+    assert_eq!(frame["message"], "unsupported-protocol");
+    assert_eq!(frame["minVersion"], MIN_PROTOCOL_VERSION);
+    assert_eq!(frame["maxVersion"], PROTOCOL_VERSION);
+    // Refused before the user was registered.
+    assert!(!state.users.lock().await.contains("alice"));
+}
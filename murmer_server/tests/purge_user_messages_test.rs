@@ -0,0 +1,70 @@
+use murmer_server::db;
+
+async fn post(db: &db::Db, channel: i32, user: &str) -> i64 {
+    db::insert_message(db, channel, &format!(r#"{{"user":"{user}","text":"hi"}}"#))
+        .await
+        .expect("insert message")
+}
+
+#[tokio::test]
+async fn deletes_newest_messages_by_user_with_pins_and_reactions() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let random = db::add_channel(&db, "random", None)
+        .await
+        .expect("add channel")
+        .expect("new channel")
+        .id;
+
+    let spam_general = post(&db, general, "spammer").await;
+    let keep = post(&db, general, "alice").await;
+    let spam_random = post(&db, random, "spammer").await;
+    let newest = post(&db, general, "spammer").await;
+    db::add_pin(&db, spam_general, general, "mod", 50)
+        .await
+        .expect("pin");
+    db::add_reaction(&db, spam_general, "alice", "👍")
+        .await
+        .expect("react");
+
+    // Scoped to one channel and capped: only the newest match goes.
+    let deleted = db::delete_messages_by_user(&db, Some(general), "spammer", 1)
+        .await
+        .expect("purge");
+    assert_eq!(deleted, vec![(newest, general)]);
+
+    // Unscoped: every remaining match, newest first.
+    let deleted = db::delete_messages_by_user(&db, None, "spammer", 1000)
+        .await
+        .expect("purge");
+    assert_eq!(
+        deleted,
+        vec![(spam_random, random), (spam_general, general)]
+    );
+
+    let remaining = db::fetch_history(&db, general, None, 10)
+        .await
+        .expect("history");
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].0, keep);
+    assert!(
+        db::get_pins_for_channel(&db, general)
+            .await
+            .expect("pins")
+            .is_empty()
+    );
+    assert!(
+        db::get_reactions_for_messages(&db, &[spam_general])
+            .await
+            .expect("reactions")
+            .is_empty()
+    );
+    assert!(
+        db::delete_messages_by_user(&db, None, "spammer", 1000)
+            .await
+            .expect("purge again")
+            .is_empty()
+    );
+}
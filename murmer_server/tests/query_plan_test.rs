@@ -0,0 +1,81 @@
+//! Guards the query plans of the hottest reads: loading channel history and
+//! the reactions of a page of messages. Both must be index searches with no
+//! temporary sort, however large the tables grow.
+
+use murmer_server::db::{self, DbCall};
+
+const HISTORY_QUERIES: [&str; 2] = [
+    "SELECT id, content FROM messages WHERE channel_id = ?1 AND id < ?2 ORDER BY id DESC LIMIT 50",
+    "SELECT id, content FROM messages WHERE channel_id = ?1 ORDER BY id DESC LIMIT 50",
+];
+
+const REACTIONS_QUERY: &str =
+    "SELECT message_id, emoji, user_name FROM reactions WHERE message_id IN (?1, ?2)";
+
+/// Seed a few thousand messages over several channels, with reactions, and
+/// refresh the planner statistics so the plans reflect a populated server.
+async fn seeded_db() -> db::Db {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    db.call_db(|conn| {
+        let tx = conn.transaction()?;
+        for name in ["random", "dev", "ops"] {
+            tx.execute("INSERT INTO channels (name) VALUES (?1)", [name])?;
+        }
+        for i in 0..4000i64 {
+            let channel = i % 4 + 1;
+            tx.execute(
+                "INSERT INTO messages (channel_id, content, created_at) \
+                 VALUES (?1, '{\"user\":\"u\",\"text\":\"t\"}', '2024-01-01T00:00:00.000Z')",
+                [channel],
+            )?;
+            if i % 3 == 0 {
+                tx.execute(
+                    "INSERT INTO reactions (message_id, user_name, emoji) VALUES (?1, 'u', '👍')",
+                    [i + 1],
+                )?;
+            }
+        }
+        tx.execute_batch("ANALYZE")?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+    .expect("seed");
+    db
+}
+
+async fn plan(db: &db::Db, sql: &'static str) -> String {
+    db.call_db(move |conn| {
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {sql}"))?;
+        let params = [1i64, 2000].into_iter().take(stmt.parameter_count());
+        let details = stmt
+            .query_map(rusqlite::params_from_iter(params), |row| {
+                row.get::<_, String>(3)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(details.join("; "))
+    })
+    .await
+    .expect("explain")
+}
+
+#[tokio::test]
+async fn history_reads_search_the_channel_index_without_sorting() {
+    let db = seeded_db().await;
+    for sql in HISTORY_QUERIES {
+        let plan = plan(&db, sql).await;
+        assert!(
+            plan.contains("SEARCH messages USING INDEX idx_messages_channel_id_id "),
+            "{sql}: {plan}"
+        );
+        assert!(!plan.contains("TEMP B-TREE"), "{sql}: {plan}");
+    }
+}
+
+#[tokio::test]
+async fn reaction_lookups_search_by_message_id() {
+    let db = seeded_db().await;
+    let plan = plan(&db, REACTIONS_QUERY).await;
+    assert!(plan.contains("SEARCH reactions USING"), "{plan}");
+    assert!(plan.contains("(message_id=?)"), "{plan}");
+}
@@ -0,0 +1,147 @@
+//! `reaction-update` coalescing: a burst of reaction changes to one message
+//! produces a single broadcast carrying the final summary.
+
+use std::{sync::Arc, time::Duration};
+
+use murmer_server::ws::helpers::{
+    get_or_create_channel, queue_reaction_change, queue_reaction_update,
+};
+use murmer_server::{AppState, db};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(common::state().await)
+}
+
+#[tokio::test]
+async fn reaction_burst_is_coalesced_into_one_broadcast() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("general exists");
+    let message_id = db::insert_message(&state.db, general, r#"{"type":"chat","text":"hi"}"#)
+        .await
+        .expect("insert");
+    let mut rx = get_or_create_channel(&state, general).await.subscribe();
+
+    for user in ["alice", "bob", "carol"] {
+        db::add_reaction(&state.db, message_id, user, "👍")
+            .await
+            .expect("react");
+        queue_reaction_update(&state, general, message_id);
+    }
+
+    let frame = timeout(Duration::from_secs(2), rx.recv())
+        .await
+        .expect("broadcast arrives")
+        .expect("channel open");
+    let v: Value = serde_json::from_str(&frame).unwrap();
+    assert_eq!(v["type"], "reaction-update");
+    assert_eq!(v["messageId"], message_id);
+    assert_eq!(v["reactions"]["👍"].as_array().map(Vec::len), Some(3));
+
+    // No further broadcasts for the same burst.
+    assert!(
+        timeout(Duration::from_millis(500), rx.recv())
+            .await
+            .is_err()
+    );
+}
+
+async fn next_reactions(rx: &mut broadcast::Receiver<String>) -> Value {
+    let frame = timeout(Duration::from_secs(2), rx.recv())
+        .await
+        .expect("broadcast arrives")
+        .expect("channel open");
+    let v: Value = serde_json::from_str(&frame).unwrap();
+    assert_eq!(v["type"], "reaction-update");
+    v["reactions"].clone()
+}
+
+/// A burst through `apply_reaction` costs one round trip per reaction: the
+/// summary comes back with each write, so the coalesced broadcast reads
+/// nothing. Writing and then reading the summary costs two.
+#[tokio::test]
+async fn reaction_burst_broadcasts_without_rereading() {
+    const BURST: u64 = 20;
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("general exists");
+    let message_id = db::insert_message(&state.db, general, r#"{"type":"chat","text":"hi"}"#)
+        .await
+        .expect("insert");
+    let mut rx = get_or_create_channel(&state, general).await.subscribe();
+
+    let before = state.db.round_trips();
+    for i in 0..BURST {
+        let change = db::apply_reaction(
+            &state.db,
+            &state.reaction_seq,
+            message_id,
+            &format!("user{i}"),
+            "👍",
+            true,
+        )
+        .await
+        .expect("react")
+        .expect("under the cap");
+        queue_reaction_change(&state, general, message_id, change);
+    }
+    let reactions = next_reactions(&mut rx).await;
+    assert_eq!(state.db.round_trips() - before, BURST);
+    assert_eq!(
+        reactions["👍"].as_array().map(Vec::len),
+        Some(BURST as usize)
+    );
+
+    let before = state.db.round_trips();
+    for i in 0..BURST {
+        db::remove_reaction(&state.db, message_id, &format!("user{i}"), "👍")
+            .await
+            .expect("unreact");
+        db::get_reaction_summary(&state.db, message_id)
+            .await
+            .expect("summary");
+    }
+    assert_eq!(state.db.round_trips() - before, 2 * BURST);
+}
+
+/// A change queued without a summary makes the broadcast re-read, so a
+/// write that bypassed `apply_reaction` is never hidden by a cached summary.
+#[tokio::test]
+async fn unknown_change_forces_a_fresh_read() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("general exists");
+    let message_id = db::insert_message(&state.db, general, r#"{"type":"chat","text":"hi"}"#)
+        .await
+        .expect("insert");
+    let mut rx = get_or_create_channel(&state, general).await.subscribe();
+
+    let change = db::apply_reaction(
+        &state.db,
+        &state.reaction_seq,
+        message_id,
+        "alice",
+        "👍",
+        true,
+    )
+    .await
+    .expect("react")
+    .expect("under the cap");
+    queue_reaction_change(&state, general, message_id, change);
+    db::add_reaction(&state.db, message_id, "bob", "🎉")
+        .await
+        .expect("react");
+    queue_reaction_update(&state, general, message_id);
+
+    let reactions = next_reactions(&mut rx).await;
+    assert_eq!(reactions["👍"], serde_json::json!(["alice"]));
+    assert_eq!(reactions["🎉"], serde_json::json!(["bob"]));
+}
@@ -0,0 +1,129 @@
+use murmer_server::db::{self, MAX_DISTINCT_REACTIONS};
+use murmer_server::ws::validation::{is_unicode_emoji, validate_reaction_emoji};
+
+#[tokio::test]
+async fn caps_distinct_emojis_per_message() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let channel = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let message = db::insert_message(&db, channel, r#"{"type":"chat","user":"a","text":"hi"}"#)
+        .await
+        .expect("insert message");
+
+    for i in 0..MAX_DISTINCT_REACTIONS {
+        assert!(
+            db::add_reaction(&db, message, "alice", &format!(":e{i}:"))
+                .await
+                .expect("add")
+        );
+    }
+    // The 21st distinct emoji is refused…
+    assert!(
+        !db::add_reaction(&db, message, "bob", ":new:")
+            .await
+            .expect("add")
+    );
+    // …but existing ones can still be joined, removed and re-added.
+    assert!(
+        db::add_reaction(&db, message, "bob", ":e0:")
+            .await
+            .expect("add")
+    );
+    db::remove_reaction(&db, message, "alice", ":e1:")
+        .await
+        .expect("remove");
+    assert!(
+        db::add_reaction(&db, message, "alice", ":e1:")
+            .await
+            .expect("add")
+    );
+
+    let summary = db::get_reaction_summary(&db, message)
+        .await
+        .expect("summary");
+    assert_eq!(summary.len() as i64, MAX_DISTINCT_REACTIONS);
+    assert!(!summary.contains_key(":new:"));
+}
+
+#[test]
+fn reactions_are_single_emoji_graphemes() {
+    // Multi-codepoint emoji are one grapheme however many bytes they take.
+    for emoji in ["👍", "👍🏽", "🇺🇦", "🏳️‍🌈", "👨‍👩‍👧‍👦", "👩🏻‍❤️‍💋‍👨🏼", "❤️", "1️⃣"]
+    {
+        assert!(validate_reaction_emoji(emoji), "{emoji} should be accepted");
+    }
+    assert!("👨‍👩‍👧‍👦".len() > 16 && "👩🏻‍❤️‍💋‍👨🏼".len() > 16);
+
+    // Text, several emoji, lone modifiers and whitespace are not reactions.
+    for value in [
+        "",
+        "a",
+        "lol",
+        "+1",
+        "👍👍",
+        "🇺🇦🇺🇦",
+        "\u{200D}",
+        "\u{1F3FD}",
+        "👍 ",
+        "👍\u{0007}",
+    ] {
+        assert!(
+            !validate_reaction_emoji(value),
+            "{value:?} should be rejected"
+        );
+    }
+    // One grapheme padded with combining marks stays bounded.
+    let padded = format!("👍{}", "\u{0301}".repeat(40));
+    assert!(!validate_reaction_emoji(&padded));
+}
+
+#[test]
+fn emoji_sequences_are_recognised() {
+    for emoji in [
+        // ZWJ families and profession sequences, with and without skin tones.
+        "👨‍👩‍👧",
+        "👨‍👩‍👧‍👦",
+        "👩‍👩‍👦‍👦",
+        "🧑🏽‍🚀",
+        // Regional-indicator and tag-sequence flags, and a ZWJ flag.
+        "🇺🇸",
+        "🇯🇵",
+        "🏴\u{E0067}\u{E0062}\u{E0073}\u{E0063}\u{E0074}\u{E007F}",
+        "🏳️‍🌈",
+        // Keycaps, fully and minimally qualified.
+        "1️⃣",
+        "1\u{20E3}",
+        "#️⃣",
+        "*️⃣",
+        // Every skin-tone modifier applied to a base.
+        "👋🏻",
+        "👋🏼",
+        "👋🏽",
+        "👋🏾",
+        "👋🏿",
+    ] {
+        assert!(validate_reaction_emoji(emoji), "{emoji} should be accepted");
+        assert!(is_unicode_emoji(emoji), "{emoji} should be a Unicode emoji");
+    }
+
+    // The pieces of those sequences are not emoji on their own.
+    for piece in [
+        "🇺",
+        "🇦🇦",
+        "#",
+        "1",
+        "\u{20E3}",
+        "\u{FE0F}",
+        "\u{200D}",
+        "\u{E0067}",
+        "🏻",
+        "🏿",
+    ] {
+        assert!(
+            !validate_reaction_emoji(piece),
+            "{piece:?} should be rejected"
+        );
+        assert!(!is_unicode_emoji(piece), "{piece:?} is not an emoji");
+    }
+}
@@ -0,0 +1,199 @@
+//! Tests for read markers and read receipts: markers only move forward,
+//! receipts list readers per recent message, and each author gets one frame
+//! addressed to them, batched per server state.
+
+use std::sync::Arc;
+
+use murmer_server::AppState;
+use murmer_server::db::{self, MessageReceipt};
+use murmer_server::ws::helpers::{flush_read_receipts, queue_read_receipts};
+use serde_json::Value;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".into()),
+        ..common::state().await
+    })
+}
+
+async fn post(db: &db::Db, channel_id: i32, user: &str) -> i64 {
+    let content = serde_json::json!({ "type": "chat", "user": user, "text": "hi" });
+    db::insert_message(db, channel_id, &content.to_string())
+        .await
+        .expect("insert message")
+}
+
+#[tokio::test]
+async fn markers_only_move_forward() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+
+    assert!(
+        db::advance_read_marker(&db, "bob", general, 5)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !db::advance_read_marker(&db, "bob", general, 5)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !db::advance_read_marker(&db, "bob", general, 3)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        db::get_read_marker(&db, "bob", general).await.unwrap(),
+        Some(5)
+    );
+    assert!(
+        db::advance_read_marker(&db, "bob", general, 9)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        db::get_read_marker(&db, "bob", general).await.unwrap(),
+        Some(9)
+    );
+    assert_eq!(
+        db::get_read_marker(&db, "carol", general).await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn receipts_list_readers_per_message() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let first = post(&db, general, "alice").await;
+    let second = post(&db, general, "alice").await;
+    let third = post(&db, general, "bob").await;
+
+    assert!(
+        db::get_read_receipts(&db, general, 50)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    db::advance_read_marker(&db, "bob", general, third)
+        .await
+        .unwrap();
+    db::advance_read_marker(&db, "carol", general, first)
+        .await
+        .unwrap();
+    // Reading your own message is not a receipt.
+    db::advance_read_marker(&db, "alice", general, second)
+        .await
+        .unwrap();
+
+    let receipts = db::get_read_receipts(&db, general, 50).await.unwrap();
+    assert_eq!(
+        receipts,
+        vec![
+            MessageReceipt {
+                message_id: first,
+                author: "alice".into(),
+                readers: vec!["bob".into(), "carol".into()],
+            },
+            MessageReceipt {
+                message_id: second,
+                author: "alice".into(),
+                readers: vec!["bob".into()],
+            },
+        ]
+    );
+
+    // Only the newest messages of the window are considered.
+    let receipts = db::get_read_receipts(&db, general, 2).await.unwrap();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].message_id, second);
+}
+
+#[tokio::test]
+async fn receipts_are_addressed_to_each_author() {
+    let state = make_state().await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel exists");
+    assert!(
+        !db::get_channel_by_id(&state.db, general)
+            .await
+            .unwrap()
+            .receipts_enabled
+    );
+    assert!(
+        db::set_channel_receipts(&state.db, general, true)
+            .await
+            .unwrap()
+    );
+    assert!(
+        db::get_channel_by_id(&state.db, general)
+            .await
+            .unwrap()
+            .receipts_enabled
+    );
+
+    let from_alice = post(&state.db, general, "alice").await;
+    let from_bob = post(&state.db, general, "bob").await;
+    db::advance_read_marker(&state.db, "carol", general, from_bob)
+        .await
+        .unwrap();
+
+    let mut rx = state.tx.subscribe();
+    flush_read_receipts(&state, general).await;
+    let mut frames: Vec<Value> = Vec::new();
+    while let Ok(raw) = rx.try_recv() {
+        frames.push(serde_json::from_str(&raw).unwrap());
+    }
+    frames.sort_by_key(|f| f["to"].as_str().unwrap().to_string());
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0]["type"], "read-receipt-update");
+    assert_eq!(frames[0]["to"], "alice");
+    assert_eq!(frames[0]["channelId"], general);
+    assert_eq!(
+        frames[0]["receipts"],
+        serde_json::json!([{ "messageId": from_alice, "readers": ["carol"] }])
+    );
+    assert_eq!(frames[1]["to"], "bob");
+    assert_eq!(
+        frames[1]["receipts"],
+        serde_json::json!([{ "messageId": from_bob, "readers": ["carol"] }])
+    );
+}
+
+#[tokio::test]
+async fn pending_receipt_batches_are_tracked_per_state() {
+    let first = make_state().await;
+    let second = make_state().await;
+    let general = db::get_channel_id_by_name(&first.db, "general")
+        .await
+        .expect("default channel");
+
+    queue_read_receipts(&first, general);
+    assert!(
+        first
+            .pending_read_receipts
+            .lock()
+            .unwrap()
+            .contains(&general)
+    );
+    // Another server state with the same channel id schedules its own batch.
+    assert!(second.pending_read_receipts.lock().unwrap().is_empty());
+    queue_read_receipts(&second, general);
+    assert!(
+        second
+            .pending_read_receipts
+            .lock()
+            .unwrap()
+            .contains(&general)
+    );
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use murmer_server::{AppState, db, word_filter::WordFilter, ws::helpers};
+
+mod common;
+
+const FRAME: &str = r#"{"type":"chat","user":"alice","text":"later"}"#;
+/// Pending scheduled messages allowed per user in these tests.
+const LIMIT: i64 = 25;
+
+#[tokio::test]
+async fn scheduled_messages_are_released_at_their_due_time() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let due = Utc::now() + Duration::minutes(5);
+    db::add_scheduled_message(&db, "tok-1", general, "alice", FRAME, due, LIMIT)
+        .await
+        .expect("schedule");
+
+    // Not yet due: the sweeper may not post it.
+    assert!(
+        db::take_due_scheduled_messages(&db, Utc::now())
+            .await
+            .expect("sweep early")
+            .is_empty()
+    );
+
+    let released = db::take_due_scheduled_messages(&db, due)
+        .await
+        .expect("sweep at due time");
+    assert_eq!(released.len(), 1);
+    assert_eq!(released[0].token, "tok-1");
+    assert_eq!(released[0].channel_id, general);
+    assert_eq!(released[0].user_name, "alice");
+    assert_eq!(released[0].content, FRAME);
+
+    // Taking removes the row, so a later sweep cannot post it again.
+    assert!(
+        db::take_due_scheduled_messages(&db, due)
+            .await
+            .expect("sweep again")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn only_the_sender_can_cancel_a_scheduled_message() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let due = Utc::now() + Duration::minutes(5);
+    db::add_scheduled_message(&db, "tok-2", general, "alice", FRAME, due, LIMIT)
+        .await
+        .expect("schedule");
+
+    assert!(
+        !db::cancel_scheduled_message(&db, "tok-2", "mallory")
+            .await
+            .expect("foreign cancel")
+    );
+    assert!(
+        db::cancel_scheduled_message(&db, "tok-2", "alice")
+            .await
+            .expect("cancel")
+    );
+    assert!(
+        !db::cancel_scheduled_message(&db, "tok-2", "alice")
+            .await
+            .expect("cancel again")
+    );
+    assert!(
+        db::take_due_scheduled_messages(&db, due)
+            .await
+            .expect("sweep")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn pending_scheduled_messages_are_capped_per_user() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let due = Utc::now() + Duration::minutes(5);
+    for token in ["a", "b"] {
+        assert!(
+            db::add_scheduled_message(&db, token, general, "alice", FRAME, due, 2)
+                .await
+                .expect("schedule")
+        );
+    }
+    assert!(
+        !db::add_scheduled_message(&db, "c", general, "alice", FRAME, due, 2)
+            .await
+            .expect("schedule over the cap")
+    );
+    // The cap is per user, and cancelling frees a slot.
+    assert!(
+        db::add_scheduled_message(&db, "d", general, "bob", FRAME, due, 2)
+            .await
+            .expect("schedule for bob")
+    );
+    assert!(
+        db::cancel_scheduled_message(&db, "a", "alice")
+            .await
+            .expect("cancel")
+    );
+    assert!(
+        db::add_scheduled_message(&db, "c", general, "alice", FRAME, due, 2)
+            .await
+            .expect("schedule after cancel")
+    );
+}
+
+#[tokio::test]
+async fn each_sender_lists_only_their_own_scheduled_messages() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let now = Utc::now();
+    for (token, user, minutes) in [
+        ("late", "alice", 30),
+        ("soon", "alice", 5),
+        ("bob", "bob", 1),
+    ] {
+        db::add_scheduled_message(
+            &db,
+            token,
+            general,
+            user,
+            FRAME,
+            now + Duration::minutes(minutes),
+            LIMIT,
+        )
+        .await
+        .expect("schedule");
+    }
+
+    let alice = db::list_scheduled_messages(&db, "alice")
+        .await
+        .expect("list");
+    let tokens: Vec<&str> = alice.iter().map(|s| s.token.as_str()).collect();
+    assert_eq!(tokens, ["soon", "late"]);
+    assert!(
+        db::list_scheduled_messages(&db, "mallory")
+            .await
+            .expect("list")
+            .is_empty()
+    );
+}
+
+/// Schedules one message per case, all already due, and returns the state
+/// with alice allowed to post, bob muted, carol banned, and dave's target
+/// channel removed.
+async fn state_with_due_messages() -> (Arc<AppState>, i32) {
+    let state = Arc::new(AppState {
+        word_filter: Arc::new(tokio::sync::RwLock::new(Some(
+            WordFilter::new(["forbidden"]).expect("filter"),
+        ))),
+        ..common::state_with_roles().await
+    });
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel exists");
+    let doomed = db::add_channel(&state.db, "doomed", None)
+        .await
+        .expect("add channel")
+        .expect("new channel")
+        .id;
+    {
+        let mut keys = state.user_keys.lock().await;
+        for user in ["alice", "bob", "carol", "dave", "erin"] {
+            keys.insert(user.to_string(), format!("key-{user}"));
+        }
+    }
+    state.mutes.lock().await.insert("key-bob".to_string(), None);
+    db::add_ban(&state.db, "key-carol", "carol", "admin")
+        .await
+        .expect("ban");
+
+    let due = Utc::now() - Duration::seconds(1);
+    for (token, channel, user, text) in [
+        ("ok", general, "alice", "hello later"),
+        ("muted", general, "bob", "from bob"),
+        ("banned", general, "carol", "from carol"),
+        ("gone", doomed, "dave", "from dave"),
+        ("filtered", general, "erin", "a forbidden word"),
+    ] {
+        let frame = serde_json::json!({"type": "chat", "user": user, "text": text});
+        db::add_scheduled_message(
+            &state.db,
+            token,
+            channel,
+            user,
+            &frame.to_string(),
+            due,
+            LIMIT,
+        )
+        .await
+        .expect("schedule");
+    }
+    db::remove_channel(&state.db, doomed)
+        .await
+        .expect("remove channel");
+    (state, general)
+}
+
+async fn posted_texts(state: &AppState, channel_id: i32) -> Vec<String> {
+    db::fetch_history(&state.db, channel_id, None, 50)
+        .await
+        .expect("history")
+        .into_iter()
+        .map(|(_, content)| {
+            let v: serde_json::Value = serde_json::from_str(&content).expect("json");
+            v["text"].as_str().unwrap_or_default().to_string()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn the_sweeper_rechecks_scheduled_messages_before_posting() {
+    let (state, general) = state_with_due_messages().await;
+    helpers::deliver_due_scheduled_messages(&state).await;
+
+    let texts = posted_texts(&state, general).await;
+    assert!(texts.contains(&"hello later".to_string()), "{texts:?}");
+    for dropped in ["from bob", "from carol", "from dave"] {
+        assert!(!texts.iter().any(|t| t == dropped), "{texts:?}");
+    }
+    assert!(!texts.iter().any(|t| t.contains("forbidden")), "{texts:?}");
+    // Dropped messages are consumed, not left for the next sweep.
+    assert!(
+        db::take_due_scheduled_messages(&state.db, Utc::now())
+            .await
+            .expect("sweep again")
+            .is_empty()
+    );
+}
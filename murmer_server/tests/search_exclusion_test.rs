@@ -0,0 +1,89 @@
+//! `SEARCH_EXCLUDED_CHANNELS`: excluded channels are hidden from search for
+//! regular members but stay searchable for moderators.
+
+use std::sync::Arc;
+
+use murmer_server::permissions::{DEFAULT_EVERYONE, DEFAULT_MOD};
+use murmer_server::ws::helpers::can_search_channel;
+use murmer_server::{AppState, RoleDef, db};
+use serial_test::serial;
+use temp_env::with_var;
+use tokio::runtime::Runtime;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".to_string()),
+        ..common::state().await
+    })
+}
+
+fn role(id: i64, permissions: u64, position: i64, is_default: bool) -> RoleDef {
+    RoleDef {
+        id,
+        name: format!("role-{id}"),
+        color: None,
+        permissions,
+        position,
+        is_default,
+        is_owner: false,
+    }
+}
+
+#[test]
+#[serial]
+fn excluded_channel_is_only_searchable_by_moderators() {
+    with_var("SEARCH_EXCLUDED_CHANNELS", Some(" Audit ,system"), || {
+        Runtime::new().expect("runtime").block_on(async {
+            let state = make_state().await;
+            {
+                let mut defs = state.role_defs.write().await;
+                defs.insert(1, role(1, DEFAULT_EVERYONE, 0, true));
+                defs.insert(2, role(2, DEFAULT_MOD, 10, false));
+            }
+            state
+                .user_roles
+                .write()
+                .await
+                .insert("mod".to_string(), vec![2]);
+
+            let audit = db::add_channel(&state.db, "audit", None)
+                .await
+                .expect("create channel")
+                .expect("name is free")
+                .id;
+            let general = db::get_channel_id_by_name(&state.db, "general")
+                .await
+                .expect("default channel exists");
+            db::insert_message(&state.db, audit, r#"{"text":"secret findings"}"#)
+                .await
+                .expect("insert message");
+            let hits = db::search_messages(&state.db, audit, "secret", 10)
+                .await
+                .expect("search");
+            assert_eq!(hits.len(), 1);
+
+            assert!(!can_search_channel(&state, Some("member"), audit).await);
+            assert!(!can_search_channel(&state, None, audit).await);
+            assert!(can_search_channel(&state, Some("mod"), audit).await);
+            assert!(can_search_channel(&state, Some("member"), general).await);
+        });
+    });
+}
+
+#[test]
+#[serial]
+fn nothing_is_excluded_by_default() {
+    with_var("SEARCH_EXCLUDED_CHANNELS", None::<&str>, || {
+        Runtime::new().expect("runtime").block_on(async {
+            let state = make_state().await;
+            let audit = db::add_channel(&state.db, "audit", None)
+                .await
+                .expect("create channel")
+                .expect("name is free")
+                .id;
+            assert!(can_search_channel(&state, Some("member"), audit).await);
+        });
+    });
+}
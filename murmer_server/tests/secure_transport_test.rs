@@ -0,0 +1,98 @@
+use axum::http::{HeaderMap, HeaderValue, StatusCode, Uri};
+use murmer_server::security::is_secure_transport;
+use murmer_server::ws::helpers::insecure_upgrade_rejection;
+use serial_test::serial;
+use std::net::IpAddr;
+use temp_env::with_vars;
+
+fn ip(raw: &str) -> IpAddr {
+    raw.parse().expect("valid ip")
+}
+
+fn forwarded(proto: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-forwarded-proto", HeaderValue::from_str(proto).unwrap());
+    headers
+}
+
+#[test]
+#[serial]
+fn insecure_upgrade_is_rejected_when_required() {
+    with_vars(
+        [
+            ("REQUIRE_SECURE_TRANSPORT", Some("true")),
+            ("TRUSTED_PROXIES", Some("10.0.0.2")),
+        ],
+        || {
+            let uri = Uri::from_static("/ws");
+            let rejection = insecure_upgrade_rejection(ip("203.0.113.9"), &uri, &HeaderMap::new())
+                .expect("plain ws from a remote client is refused");
+            assert_eq!(rejection.status(), StatusCode::FORBIDDEN);
+
+            // Only a trusted proxy may vouch for TLS.
+            assert!(
+                insecure_upgrade_rejection(ip("203.0.113.9"), &uri, &forwarded("https")).is_some()
+            );
+            assert!(
+                insecure_upgrade_rejection(ip("10.0.0.2"), &uri, &forwarded("https")).is_none()
+            );
+            assert!(insecure_upgrade_rejection(ip("10.0.0.2"), &uri, &forwarded("http")).is_some());
+
+            // Local clients are exempt.
+            assert!(insecure_upgrade_rejection(ip("127.0.0.1"), &uri, &HeaderMap::new()).is_none());
+        },
+    );
+}
+
+#[test]
+#[serial]
+fn insecure_upgrade_is_allowed_by_default() {
+    with_vars([("REQUIRE_SECURE_TRANSPORT", None::<&str>)], || {
+        assert!(
+            insecure_upgrade_rejection(
+                ip("203.0.113.9"),
+                &Uri::from_static("/ws"),
+                &HeaderMap::new()
+            )
+            .is_none()
+        );
+    });
+}
+
+#[test]
+fn loopback_proxy_forwarding_plain_http_is_not_secure() {
+    // A proxy on the same host forwarding an internet client over http must
+    // not inherit the loopback exemption.
+    assert!(!is_secure_transport(
+        ip("127.0.0.1"),
+        None,
+        Some("http"),
+        &[]
+    ));
+    // An appending proxy passes the client's own header through first; only
+    // the last value is the trusted peer's.
+    assert!(!is_secure_transport(
+        ip("127.0.0.1"),
+        None,
+        Some("https, http"),
+        &[]
+    ));
+    assert!(!is_secure_transport(
+        ip("10.0.0.2"),
+        None,
+        Some("https, http"),
+        &[ip("10.0.0.2")]
+    ));
+    assert!(is_secure_transport(
+        ip("127.0.0.1"),
+        None,
+        Some("http, https"),
+        &[]
+    ));
+    assert!(is_secure_transport(
+        ip("203.0.113.9"),
+        Some("wss"),
+        None,
+        &[]
+    ));
+}
@@ -1,13 +1,15 @@
 use murmer_server::{
     RateLimiter,
     security::{
-        check_and_store_nonce, check_auth_rate_limit, check_message_rate_limit,
-        validate_channel_name, validate_timestamp, validate_user_name,
+        acquire_connection_slot, check_and_store_nonce, check_auth_rate_limit,
+        check_channel_creation_rate_limit, check_message_rate_limit, check_reaction_cooldown,
+        check_reaction_rate_limit, get_auth_timestamp_window_seconds, get_max_channels,
+        get_max_messages_for_role, validate_channel_name, validate_timestamp, validate_user_name,
     },
 };
 use serial_test::serial;
 use std::time::Duration;
-use temp_env::with_var;
+use temp_env::{with_var, with_vars};
 use tokio::runtime::Runtime;
 use tokio::time::sleep;
 
@@ -26,9 +28,9 @@ fn rejects_message_when_limit_reached() {
         with_runtime(|rt| {
             rt.block_on(async {
                 let limiter = RateLimiter::new();
-                assert!(check_message_rate_limit(&limiter, "alice").await);
-                assert!(check_message_rate_limit(&limiter, "alice").await);
-                assert!(!check_message_rate_limit(&limiter, "alice").await);
+                assert!(check_message_rate_limit(&limiter, "alice", &[]).await);
+                assert!(check_message_rate_limit(&limiter, "alice", &[]).await);
+                assert!(!check_message_rate_limit(&limiter, "alice", &[]).await);
             });
         });
     });
@@ -51,15 +53,73 @@ fn rejects_auth_when_limit_reached() {
 #[test]
 #[serial]
 fn allows_nonce_reuse_after_expiry() {
-    with_var("NONCE_EXPIRY_SECONDS", Some("1"), || {
+    with_vars(
+        [
+            ("NONCE_EXPIRY_SECONDS", Some("1")),
+            ("NONCE_RETRY_GRACE_SECONDS", Some("0")),
+        ],
+        || {
+            with_runtime(|rt| {
+                rt.block_on(async {
+                    let limiter = RateLimiter::new();
+                    assert!(check_and_store_nonce(&limiter, "nonce-1", "10.0.0.1").await);
+                    assert!(!check_and_store_nonce(&limiter, "nonce-1", "10.0.0.1").await);
+
+                    sleep(Duration::from_secs(2)).await;
+                    assert!(check_and_store_nonce(&limiter, "nonce-1", "10.0.0.1").await);
+                });
+            });
+        },
+    );
+}
+
+#[test]
+#[serial]
+fn nonce_retries_are_off_by_default() {
+    with_var("NONCE_RETRY_GRACE_SECONDS", None::<&str>, || {
+        with_runtime(|rt| {
+            rt.block_on(async {
+                let limiter = RateLimiter::new();
+                assert!(check_and_store_nonce(&limiter, "nonce-1", "10.0.0.1").await);
+                assert!(!check_and_store_nonce(&limiter, "nonce-1", "10.0.0.1").await);
+            });
+        });
+    });
+}
+
+#[test]
+#[serial]
+fn allows_one_same_origin_retry_within_grace() {
+    with_var("NONCE_RETRY_GRACE_SECONDS", Some("10"), || {
         with_runtime(|rt| {
             rt.block_on(async {
                 let limiter = RateLimiter::new();
-                assert!(check_and_store_nonce(&limiter, "nonce-1").await);
-                assert!(!check_and_store_nonce(&limiter, "nonce-1").await);
+                assert!(check_and_store_nonce(&limiter, "nonce-1", "10.0.0.1").await);
+                // A different origin reusing the nonce is a replay.
+                assert!(!check_and_store_nonce(&limiter, "nonce-1", "10.0.0.2").await);
+                // The original client may retry its handshake once...
+                assert!(check_and_store_nonce(&limiter, "nonce-1", "10.0.0.1").await);
+                // ...but not twice.
+                assert!(!check_and_store_nonce(&limiter, "nonce-1", "10.0.0.1").await);
+            });
+        });
+    });
+}
 
-                sleep(Duration::from_secs(2)).await;
-                assert!(check_and_store_nonce(&limiter, "nonce-1").await);
+#[test]
+#[serial]
+fn throttles_rapid_reaction_toggles_per_message() {
+    with_var("REACTION_TOGGLE_COOLDOWN_MS", Some("100"), || {
+        with_runtime(|rt| {
+            rt.block_on(async {
+                let limiter = RateLimiter::new();
+                assert!(check_reaction_cooldown(&limiter, "alice", 1).await);
+                assert!(!check_reaction_cooldown(&limiter, "alice", 1).await);
+                // Other messages and other users are unaffected.
+                assert!(check_reaction_cooldown(&limiter, "alice", 2).await);
+                assert!(check_reaction_cooldown(&limiter, "bob", 1).await);
+                sleep(Duration::from_millis(150)).await;
+                assert!(check_reaction_cooldown(&limiter, "alice", 1).await);
             });
         });
     });
@@ -86,6 +146,7 @@ fn validates_user_names() {
 }
 
 #[test]
+#[serial]
 fn validates_timestamps() {
     let now = chrono::Utc::now().timestamp_millis();
     assert!(validate_timestamp(&now.to_string()).is_ok());
@@ -93,3 +154,135 @@ fn validates_timestamps() {
     assert!(validate_timestamp(&(now - 30_000).to_string()).is_ok());
     assert!(validate_timestamp("not-a-number").is_err());
 }
+
+#[test]
+#[serial]
+fn timestamp_window_is_configurable() {
+    with_var("AUTH_TIMESTAMP_WINDOW_SECONDS", None::<&str>, || {
+        assert_eq!(get_auth_timestamp_window_seconds(), 60);
+    });
+    with_var("AUTH_TIMESTAMP_WINDOW_SECONDS", Some("5"), || {
+        let now = chrono::Utc::now().timestamp_millis();
+        // Half a second either side of the boundary absorbs test runtime.
+        assert!(validate_timestamp(&(now - 4_500).to_string()).is_ok());
+        assert!(validate_timestamp(&(now + 4_500).to_string()).is_ok());
+        assert!(validate_timestamp(&(now - 5_500).to_string()).is_err());
+        assert!(validate_timestamp(&(now + 5_500).to_string()).is_err());
+    });
+    with_var("AUTH_TIMESTAMP_WINDOW_SECONDS", Some("86400"), || {
+        assert_eq!(get_auth_timestamp_window_seconds(), 3600);
+    });
+}
+
+#[test]
+#[serial]
+fn refuses_connections_beyond_per_ip_limit() {
+    with_var("MAX_CONNECTIONS_PER_IP", Some("3"), || {
+        let limiter = RateLimiter::new();
+        let abusive: std::net::IpAddr = "203.0.113.7".parse().unwrap();
+        let other: std::net::IpAddr = "198.51.100.2".parse().unwrap();
+
+        let mut slots: Vec<_> = (0..3)
+            .map(|_| acquire_connection_slot(&limiter, abusive).expect("under limit"))
+            .collect();
+        assert!(acquire_connection_slot(&limiter, abusive).is_none());
+        // Another IP is unaffected.
+        assert!(acquire_connection_slot(&limiter, other).is_some());
+
+        // Closing a connection frees its slot.
+        slots.pop();
+        assert!(acquire_connection_slot(&limiter, abusive).is_some());
+    });
+}
+
+#[test]
+#[serial]
+fn rejects_reactions_beyond_per_minute_limit() {
+    with_var("MAX_REACTIONS_PER_MINUTE", Some("3"), || {
+        with_runtime(|rt| {
+            rt.block_on(async {
+                let limiter = RateLimiter::new();
+                for _ in 0..3 {
+                    assert!(check_reaction_rate_limit(&limiter, "alice").await);
+                }
+                assert!(!check_reaction_rate_limit(&limiter, "alice").await);
+                assert!(check_reaction_rate_limit(&limiter, "bob").await);
+            });
+        });
+    });
+}
+
+#[test]
+#[serial]
+fn elevated_roles_get_higher_message_limits() {
+    with_vars(
+        [
+            ("MAX_MESSAGES_PER_MINUTE", Some("2")),
+            ("ROLE_MESSAGE_LIMITS", Some("Trusted=3, Mod=1")),
+        ],
+        || {
+            assert_eq!(get_max_messages_for_role(None), 2);
+            assert_eq!(get_max_messages_for_role(Some("Member")), 2);
+            assert_eq!(get_max_messages_for_role(Some("Admin")), 4);
+            assert_eq!(get_max_messages_for_role(Some("trusted")), 3);
+            // An explicit entry overrides the built-in doubling.
+            assert_eq!(get_max_messages_for_role(Some("Mod")), 1);
+
+            with_runtime(|rt| {
+                rt.block_on(async {
+                    let limiter = RateLimiter::new();
+                    let admin = vec!["Member".to_string(), "Admin".to_string()];
+                    for _ in 0..4 {
+                        assert!(check_message_rate_limit(&limiter, "root", &admin).await);
+                    }
+                    assert!(!check_message_rate_limit(&limiter, "root", &admin).await);
+
+                    // A member without roles still stops at the default.
+                    assert!(check_message_rate_limit(&limiter, "bob", &[]).await);
+                    assert!(check_message_rate_limit(&limiter, "bob", &[]).await);
+                    assert!(!check_message_rate_limit(&limiter, "bob", &[]).await);
+                });
+            });
+        },
+    );
+}
+
+#[test]
+#[serial]
+fn rejects_channel_creation_beyond_per_minute_limit() {
+    with_var("MAX_CHANNEL_CREATIONS_PER_MINUTE", None::<&str>, || {
+        with_runtime(|rt| {
+            rt.block_on(async {
+                let limiter = RateLimiter::new();
+                for _ in 0..10 {
+                    assert!(check_channel_creation_rate_limit(&limiter, "alice").await);
+                }
+                assert!(!check_channel_creation_rate_limit(&limiter, "alice").await);
+                assert!(check_channel_creation_rate_limit(&limiter, "bob").await);
+            });
+        });
+    });
+    with_var("MAX_CHANNEL_CREATIONS_PER_MINUTE", Some("1"), || {
+        with_runtime(|rt| {
+            rt.block_on(async {
+                let limiter = RateLimiter::new();
+                assert!(check_channel_creation_rate_limit(&limiter, "alice").await);
+                assert!(!check_channel_creation_rate_limit(&limiter, "alice").await);
+            });
+        });
+    });
+}
+
+#[test]
+#[serial]
+fn channel_cap_defaults_and_can_be_disabled() {
+    with_var("MAX_CHANNELS", None::<&str>, || {
+        assert_eq!(get_max_channels(), 500)
+    });
+    with_var("MAX_CHANNELS", Some("25"), || {
+        assert_eq!(get_max_channels(), 25)
+    });
+    with_var("MAX_CHANNELS", Some("0"), || {
+        assert_eq!(get_max_channels(), 0)
+    });
+}
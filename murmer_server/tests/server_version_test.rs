@@ -0,0 +1,34 @@
+use murmer_server::config::{allow_file_uploads, server_version};
+use serial_test::serial;
+use temp_env::with_var;
+
+#[test]
+#[serial]
+fn version_is_advertised_unless_hidden() {
+    with_var("HIDE_SERVER_VERSION", None::<&str>, || {
+        assert_eq!(server_version(), Some(env!("CARGO_PKG_VERSION")));
+    });
+    with_var("HIDE_SERVER_VERSION", Some("true"), || {
+        assert_eq!(server_version(), None);
+    });
+    with_var("HIDE_SERVER_VERSION", Some(" ON "), || {
+        assert_eq!(server_version(), None);
+    });
+    with_var("HIDE_SERVER_VERSION", Some("false"), || {
+        assert!(server_version().is_some());
+    });
+}
+
+#[test]
+#[serial]
+fn unparseable_boolean_settings_keep_their_default() {
+    with_var("HIDE_SERVER_VERSION", Some("maybe"), || {
+        assert!(server_version().is_some());
+    });
+    with_var("ALLOW_FILE_UPLOADS", Some("maybe"), || {
+        assert!(allow_file_uploads());
+    });
+    with_var("ALLOW_FILE_UPLOADS", Some("Off"), || {
+        assert!(!allow_file_uploads());
+    });
+}
@@ -0,0 +1,186 @@
+//! Tests for per-channel slow mode: one post per interval per member and
+//! channel, moderator exemption, 0 meaning off, and rejected messages not
+//! starting the cooldown.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{Router, routing::get};
+use futures::{SinkExt, StreamExt};
+use murmer_server::ws::helpers::{record_slow_mode_post, slow_mode_remaining};
+use murmer_server::{AppState, db, ws};
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".into()),
+        ..common::state().await
+    })
+}
+
+/// Load the built-in roles and give `moderator` the `Mod` role.
+async fn seed_roles(state: &Arc<AppState>) {
+    let defs = db::list_role_defs(&state.db).await.expect("list roles");
+    let mod_id = defs.iter().find(|d| d.name == "Mod").expect("Mod").id;
+    state
+        .role_defs
+        .write()
+        .await
+        .extend(defs.into_iter().map(|d| (d.id, d)));
+    state
+        .user_roles
+        .write()
+        .await
+        .insert("moderator".into(), vec![mod_id]);
+}
+
+/// Post as `user` the way `handle_chat` does: check the cooldown, then start
+/// the next one. Returns the seconds left when the post is held back.
+async fn post(state: &Arc<AppState>, user: &str, channel_id: i32) -> Option<u64> {
+    let retry = slow_mode_remaining(state, user, channel_id).await;
+    if retry.is_none() {
+        record_slow_mode_post(state, user, channel_id).await;
+    }
+    retry
+}
+
+#[tokio::test]
+async fn members_wait_between_posts_and_moderators_do_not() {
+    let state = make_state().await;
+    seed_roles(&state).await;
+    let channel = db::add_channel(&state.db, "busy", None)
+        .await
+        .expect("add channel")
+        .expect("new channel");
+    assert_eq!(channel.slow_mode_seconds, 0);
+    // Off by default: posts are never held back or tracked.
+    assert!(post(&state, "member", channel.id).await.is_none());
+    assert!(post(&state, "member", channel.id).await.is_none());
+    assert!(state.slow_mode_posts.lock().await.is_empty());
+
+    assert!(
+        db::set_channel_slow_mode(&state.db, channel.id, 30)
+            .await
+            .expect("enable")
+    );
+    let stored = db::get_channel_by_id(&state.db, channel.id)
+        .await
+        .expect("channel");
+    assert_eq!(stored.slow_mode_seconds, 30);
+
+    assert!(post(&state, "member", channel.id).await.is_none());
+    let retry = post(&state, "member", channel.id)
+        .await
+        .expect("second post is held back");
+    assert!((29..=30).contains(&retry));
+    // Cooldowns are per member.
+    assert!(post(&state, "other", channel.id).await.is_none());
+    // Moderators post freely.
+    for _ in 0..3 {
+        assert!(post(&state, "moderator", channel.id).await.is_none());
+    }
+
+    db::set_channel_slow_mode(&state.db, channel.id, 0)
+        .await
+        .expect("disable");
+    assert!(post(&state, "member", channel.id).await.is_none());
+}
+
+#[tokio::test]
+async fn slow_mode_applies_per_channel() {
+    let state = make_state().await;
+    seed_roles(&state).await;
+    let slow = db::add_channel(&state.db, "slow", None)
+        .await
+        .expect("add channel")
+        .expect("new channel");
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel exists");
+    db::set_channel_slow_mode(&state.db, slow.id, 60)
+        .await
+        .expect("enable");
+
+    assert!(post(&state, "member", slow.id).await.is_none());
+    assert!(post(&state, "member", slow.id).await.is_some());
+    assert!(post(&state, "member", general).await.is_none());
+    assert!(
+        !db::set_channel_slow_mode(&state.db, 9999, 10)
+            .await
+            .expect("update")
+    );
+}
+
+async fn serve(state: Arc<AppState>) -> SocketAddr {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn rejected_messages_do_not_start_the_cooldown() {
+    let state = make_state().await;
+    seed_roles(&state).await;
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel exists");
+    db::set_channel_slow_mode(&state.db, general, 60)
+        .await
+        .expect("enable");
+    let addr = serve(state.clone()).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+
+    socket
+        .send(Message::Text(
+            r#"{"type":"presence","user":"member"}"#.into(),
+        ))
+        .await
+        .unwrap();
+
+    // Each chat frame is answered with either the message or an error.
+    let mut send_chat = async |frame: &str| -> Value {
+        socket.send(Message::Text(frame.into())).await.unwrap();
+        loop {
+            let frame = tokio::time::timeout(Duration::from_secs(5), socket.next())
+                .await
+                .expect("server answered")
+                .expect("connection open")
+                .expect("valid frame");
+            if let Message::Text(text) = frame {
+                let v: Value = serde_json::from_str(&text).unwrap();
+                if v["type"] == "chat" || v["type"] == "error" {
+                    return v;
+                }
+            }
+        }
+    };
+
+    let rejected = send_chat(r#"{"type":"chat","text":"re","replyTo":424242}"#).await;
+    assert_eq!(rejected["message"], "reply-target-not-found");
+    let rejected = send_chat(r#"{"type":"chat","text":"later","scheduleAt":"soon"}"#).await;
+    assert_eq!(rejected["message"], "invalid-schedule");
+    assert!(state.slow_mode_posts.lock().await.is_empty());
+
+    let posted = send_chat(r#"{"type":"chat","text":"hello"}"#).await;
+    assert_eq!(posted["type"], "chat");
+    let held = send_chat(r#"{"type":"chat","text":"again"}"#).await;
+    assert_eq!(held["message"], "slow-mode-active");
+    assert!((59..=60).contains(&held["retryAfter"].as_u64().unwrap()));
+}
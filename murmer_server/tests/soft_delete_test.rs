@@ -0,0 +1,111 @@
+//! Tests for soft-deleted messages: tombstones stay in history with their id,
+//! thread and reactions, and can still be purged for good.
+
+use murmer_server::config::soft_delete_messages;
+use murmer_server::db;
+use serde_json::json;
+use serial_test::serial;
+use temp_env::with_var;
+
+#[tokio::test]
+async fn tombstones_stay_in_history() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let root = db::insert_message(&db, 1, r#"{"type":"chat","user":"alice","text":"root"}"#)
+        .await
+        .expect("insert");
+    let reply = json!({
+        "type": "chat",
+        "user": "bob",
+        "text": "secret",
+        "channelId": 1,
+        "time": "12:00",
+        "timestamp": "2026-01-01T12:00:00Z",
+        "replyTo": { "id": root, "user": "alice", "text": "root" },
+        "threadId": root,
+        "attachment": { "url": "/files/a.png" },
+    });
+    let id = db::insert_message(&db, 1, &reply.to_string())
+        .await
+        .expect("insert");
+    db::add_reaction(&db, id, "alice", "👍")
+        .await
+        .expect("react");
+    db::add_pin(&db, id, 1, "alice", 50).await.expect("pin");
+
+    assert!(
+        db::soft_delete_message(&db, id, "bob")
+            .await
+            .expect("delete")
+    );
+    // A tombstone cannot be deleted twice.
+    assert!(
+        !db::soft_delete_message(&db, id, "bob")
+            .await
+            .expect("delete")
+    );
+
+    let history = db::history_messages(&db, 1, None, 50)
+        .await
+        .expect("history");
+    assert_eq!(history.len(), 2);
+    assert_eq!(
+        history[1],
+        json!({
+            "type": "chat",
+            "channelId": 1,
+            "time": "12:00",
+            "timestamp": "2026-01-01T12:00:00Z",
+            "threadId": root,
+            "deleted": true,
+            "id": id,
+            "reactions": { "👍": ["alice"] },
+        })
+    );
+    assert!(
+        db::get_pins_for_channel(&db, 1)
+            .await
+            .expect("pins")
+            .is_empty()
+    );
+    let thread = db::fetch_thread(&db, 1, root, 50).await.expect("thread");
+    assert!(thread.iter().any(|(message, _)| *message == id));
+}
+
+#[tokio::test]
+async fn tombstones_can_be_purged() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let id = db::insert_message(&db, 1, r#"{"type":"chat","user":"bob","text":"hi"}"#)
+        .await
+        .expect("insert");
+    assert!(
+        db::soft_delete_message(&db, id, "mod")
+            .await
+            .expect("delete")
+    );
+    assert!(db::delete_message(&db, id).await.expect("purge"));
+    assert!(
+        db::history_messages(&db, 1, None, 50)
+            .await
+            .expect("history")
+            .is_empty()
+    );
+    assert!(
+        !db::soft_delete_message(&db, id, "mod")
+            .await
+            .expect("delete")
+    );
+}
+
+#[test]
+#[serial]
+fn soft_delete_is_opt_in() {
+    with_var("MESSAGE_DELETE_MODE", None::<&str>, || {
+        assert!(!soft_delete_messages())
+    });
+    with_var("MESSAGE_DELETE_MODE", Some("Soft"), || {
+        assert!(soft_delete_messages())
+    });
+    with_var("MESSAGE_DELETE_MODE", Some("hard"), || {
+        assert!(!soft_delete_messages())
+    });
+}
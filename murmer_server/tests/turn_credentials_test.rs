@@ -0,0 +1,140 @@
+//! Tests for `GET /turn-credentials`: the coturn REST password derivation
+//! and the signature requirement.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::Response,
+};
+use base64::{Engine as _, engine::general_purpose};
+use ed25519_dalek::{Signer, SigningKey};
+use murmer_server::turn::{self, TURN_CREDENTIALS_PATH, issue_credentials, turn_password};
+use murmer_server::{AppState, db, security};
+use serial_test::serial;
+use temp_env::with_vars;
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        upload_dir: std::env::temp_dir(),
+        ..common::state().await
+    })
+}
+
+async fn get_credentials(state: &Arc<AppState>, headers: &[(&str, String)]) -> Response {
+    let mut request = Request::get(TURN_CREDENTIALS_PATH);
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+    turn::router()
+        .with_state(state.clone())
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn signed_headers(signing: &SigningKey, user: &str) -> Vec<(&'static str, String)> {
+    let timestamp = chrono::Utc::now().timestamp_millis().to_string();
+    let message = security::signed_request_message("GET", TURN_CREDENTIALS_PATH, &timestamp);
+    let signature = general_purpose::STANDARD.encode(signing.sign(message.as_bytes()).to_bytes());
+    vec![
+        (security::USER_HEADER, user.to_string()),
+        (security::TIMESTAMP_HEADER, timestamp),
+        (security::SIGNATURE_HEADER, signature),
+    ]
+}
+
+#[test]
+fn password_is_base64_hmac_sha1_of_the_username() {
+    // Reference value from Python's hmac/hashlib.
+    assert_eq!(
+        turn_password("north", "1700003600:alice"),
+        "wjwSXO2ch1B6VaLTLMy2Avn5O9o="
+    );
+
+    let issued = issue_credentials(
+        "north",
+        vec!["turn:turn.example.com:3478".into()],
+        "alice",
+        1_700_000_000,
+        3600,
+    );
+    assert_eq!(issued.username, "1700003600:alice");
+    assert_eq!(issued.credential, "wjwSXO2ch1B6VaLTLMy2Avn5O9o=");
+    assert_eq!(issued.ttl, 3600);
+    assert_eq!(issued.urls, ["turn:turn.example.com:3478"]);
+}
+
+#[test]
+#[serial]
+fn credentials_require_configuration_and_a_signature() {
+    let signing = SigningKey::from_bytes(&[5u8; 32]);
+    let public_key = general_purpose::STANDARD.encode(signing.verifying_key().as_bytes());
+
+    with_vars(
+        [
+            ("TURN_SECRET", None::<&str>),
+            ("TURN_URLS", None),
+            ("TURN_CREDENTIAL_TTL_SECONDS", None),
+        ],
+        || {
+            Runtime::new().unwrap().block_on(async {
+                let state = make_state().await;
+                let response = get_credentials(&state, &signed_headers(&signing, "alice")).await;
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            })
+        },
+    );
+
+    with_vars(
+        [
+            ("TURN_SECRET", Some("north")),
+            (
+                "TURN_URLS",
+                Some("turn:turn.example.com:3478, turns:turn.example.com:5349"),
+            ),
+            ("TURN_CREDENTIAL_TTL_SECONDS", Some("600")),
+        ],
+        || {
+            Runtime::new().unwrap().block_on(async {
+                let state = make_state().await;
+                db::bind_user_key(&state.db, "alice", &public_key)
+                    .await
+                    .expect("bind key");
+
+                let response = get_credentials(&state, &[]).await;
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+                let stranger = SigningKey::from_bytes(&[6u8; 32]);
+                let response = get_credentials(&state, &signed_headers(&stranger, "alice")).await;
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+                let response = get_credentials(&state, &signed_headers(&signing, "alice")).await;
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("body");
+                let json: serde_json::Value = serde_json::from_slice(&body).expect("json");
+                assert_eq!(json["ttl"], 600);
+                assert_eq!(
+                    json["urls"],
+                    serde_json::json!([
+                        "turn:turn.example.com:3478",
+                        "turns:turn.example.com:5349"
+                    ])
+                );
+                let username = json["username"].as_str().expect("username");
+                let (expiry, user) = username.split_once(':').expect("expiry:user");
+                assert_eq!(user, "alice");
+                let expiry: i64 = expiry.parse().expect("unix expiry");
+                let now = chrono::Utc::now().timestamp();
+                assert!((now + 595..=now + 605).contains(&expiry));
+                assert_eq!(json["credential"], turn_password("north", username));
+            })
+        },
+    );
+}
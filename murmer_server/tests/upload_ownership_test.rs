@@ -0,0 +1,175 @@
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{Duration, Utc};
+use ed25519_dalek::{Signer, SigningKey};
+use murmer_server::{db, security};
+
+#[tokio::test]
+async fn upload_records_track_their_uploader() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    db::record_upload(&db, "1-report.pdf", Some("alice"), Some("alice-key"), None)
+        .await
+        .expect("record upload");
+    db::record_upload(&db, "2-anon.txt", None, None, None)
+        .await
+        .expect("record anonymous upload");
+
+    let record = db::get_upload(&db, "1-report.pdf")
+        .await
+        .expect("lookup")
+        .expect("record exists");
+    assert_eq!(record.uploader.as_deref(), Some("alice"));
+    assert_eq!(record.uploader_key.as_deref(), Some("alice-key"));
+    let anon = db::get_upload(&db, "2-anon.txt")
+        .await
+        .expect("lookup")
+        .expect("record exists");
+    assert_eq!(anon.uploader, None);
+
+    // Recording a taken key again fails instead of handing the file over.
+    assert!(
+        db::record_upload(
+            &db,
+            "1-report.pdf",
+            Some("mallory"),
+            Some("mallory-key"),
+            None
+        )
+        .await
+        .is_err()
+    );
+    let record = db::get_upload(&db, "1-report.pdf")
+        .await
+        .expect("lookup")
+        .expect("record exists");
+    assert_eq!(record.uploader.as_deref(), Some("alice"));
+
+    assert!(
+        db::remove_upload(&db, "1-report.pdf")
+            .await
+            .expect("remove")
+    );
+    assert!(
+        db::get_upload(&db, "1-report.pdf")
+            .await
+            .expect("lookup")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn only_unreferenced_uploads_are_reaped() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    for key in ["1-shared.png", "2-avatar.png", "3-orphan.zip"] {
+        db::record_upload(&db, key, Some("alice"), Some("alice-key"), None)
+            .await
+            .expect("record upload");
+    }
+    // Clients post absolute URLs; the reference check matches the path part.
+    db::insert_message(
+        &db,
+        general,
+        r#"{"type":"chat","image":"http://host:3001/files/1-shared.png"}"#,
+    )
+    .await
+    .expect("insert message");
+    db::bind_user_key(&db, "alice", "key").await.expect("bind");
+    db::set_user_avatar(&db, "alice", "/files/2-avatar.png")
+        .await
+        .expect("set avatar");
+
+    let reaped = db::unreferenced_uploads_before(&db, Utc::now() + Duration::hours(1), 100)
+        .await
+        .expect("query");
+    assert_eq!(reaped, vec!["3-orphan.zip".to_string()]);
+
+    // Nothing is old enough yet with a cutoff in the past.
+    assert!(
+        db::unreferenced_uploads_before(&db, Utc::now() - Duration::days(1), 100)
+            .await
+            .expect("query")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn edits_and_deletions_release_upload_references() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    for key in ["1-a.png", "2-holiday photo.jpg", "3-notes.pdf"] {
+        db::record_upload(&db, key, Some("alice"), Some("alice-key"), None)
+            .await
+            .expect("record upload");
+    }
+    let reaped = || async {
+        db::unreferenced_uploads_before(&db, Utc::now() + Duration::hours(1), 100)
+            .await
+            .expect("query")
+    };
+
+    let id = db::insert_message(
+        &db,
+        general,
+        r#"{"type":"chat","text":"see (/files/1-a.png).","image":"/files/2-holiday photo.jpg"}"#,
+    )
+    .await
+    .expect("insert message");
+    let other = db::insert_message(
+        &db,
+        general,
+        r#"{"type":"chat","attachment":{"url":"http://host/files/3-notes.pdf?dl=1"}}"#,
+    )
+    .await
+    .expect("insert message");
+    assert!(reaped().await.is_empty());
+
+    // Editing the link out releases the file.
+    db::update_message_content(&db, id, r#"{"type":"chat","text":"see (/files/1-a.png)."}"#)
+        .await
+        .expect("edit message");
+    assert_eq!(reaped().await, vec!["2-holiday photo.jpg".to_string()]);
+
+    // So do a soft delete and a hard delete.
+    db::soft_delete_message(&db, id, "alice")
+        .await
+        .expect("soft delete");
+    db::delete_message(&db, other).await.expect("delete");
+    assert_eq!(reaped().await.len(), 3);
+    assert_eq!(
+        db::unreferenced_uploads_before(&db, Utc::now() + Duration::hours(1), 1)
+            .await
+            .expect("query")
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn signed_requests_verify_against_the_signing_key() {
+    let signing = SigningKey::from_bytes(&[7u8; 32]);
+    let public_key = general_purpose::STANDARD.encode(signing.verifying_key().as_bytes());
+    let message =
+        security::signed_request_message("DELETE", "/upload/1-report.pdf", "1700000000000");
+    let signature = general_purpose::STANDARD.encode(signing.sign(message.as_bytes()).to_bytes());
+
+    assert!(security::verify_ed25519(
+        &public_key,
+        &signature,
+        message.as_bytes()
+    ));
+    let other = security::signed_request_message("DELETE", "/upload/2-other.pdf", "1700000000000");
+    assert!(!security::verify_ed25519(
+        &public_key,
+        &signature,
+        other.as_bytes()
+    ));
+    assert!(!security::verify_ed25519(
+        "not-base64",
+        &signature,
+        message.as_bytes()
+    ));
+}
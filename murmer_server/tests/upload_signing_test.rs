@@ -0,0 +1,367 @@
+//! Tests for the signature requirement, daily quota, per-uploader content
+//! deduplication, failed recording and image dimension limit on
+//! `POST /upload`.
+
+use std::{io::Cursor, path::PathBuf, sync::Arc};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    response::Response,
+    routing::{delete, post},
+};
+use base64::{Engine as _, engine::general_purpose};
+use ed25519_dalek::{Signer, SigningKey};
+use murmer_server::db::DbCall;
+use murmer_server::{AppState, db, security, upload};
+use serial_test::serial;
+use temp_env::with_var;
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+mod common;
+
+const BOUNDARY: &str = "murmer-test-boundary";
+
+async fn make_state(upload_dir: PathBuf) -> Arc<AppState> {
+    Arc::new(AppState {
+        upload_dir,
+        admin_token: Some("token".to_string()),
+        ..common::state().await
+    })
+}
+
+fn multipart_body(filename: &str, content: &[u8]) -> Vec<u8> {
+    let mut body = format!(
+        "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+async fn send_file(
+    state: &Arc<AppState>,
+    headers: &[(&str, String)],
+    filename: &str,
+    content: &[u8],
+) -> Response {
+    let mut request = Request::post("/upload").header(
+        header::CONTENT_TYPE,
+        format!("multipart/form-data; boundary={BOUNDARY}"),
+    );
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+    Router::new()
+        .route("/upload", post(upload::upload))
+        .with_state(state.clone())
+        .oneshot(
+            request
+                .body(Body::from(multipart_body(filename, content)))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn send_upload(state: &Arc<AppState>, headers: &[(&str, String)], content: &str) -> Response {
+    send_file(state, headers, "notes.txt", content.as_bytes()).await
+}
+
+async fn post_upload(state: &Arc<AppState>, headers: &[(&str, String)]) -> StatusCode {
+    send_upload(state, headers, "hello").await.status()
+}
+
+async fn upload_url(response: Response) -> String {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body");
+    let json: serde_json::Value = serde_json::from_slice(&body).expect("json");
+    json["url"].as_str().expect("url").to_string()
+}
+
+/// Identity headers for `POST /upload` signed `age_ms` milliseconds ago.
+/// Distinct ages keep the requests' replay nonces apart.
+fn signed_headers(signing: &SigningKey, user: &str, age_ms: i64) -> Vec<(&'static str, String)> {
+    signed_request_headers(signing, user, "POST", "/upload", age_ms)
+}
+
+fn signed_request_headers(
+    signing: &SigningKey,
+    user: &str,
+    method: &str,
+    path: &str,
+    age_ms: i64,
+) -> Vec<(&'static str, String)> {
+    let timestamp = (chrono::Utc::now().timestamp_millis() - age_ms).to_string();
+    let message = security::signed_request_message(method, path, &timestamp);
+    let signature = general_purpose::STANDARD.encode(signing.sign(message.as_bytes()).to_bytes());
+    vec![
+        (security::USER_HEADER, user.to_string()),
+        (security::TIMESTAMP_HEADER, timestamp),
+        (security::SIGNATURE_HEADER, signature),
+    ]
+}
+
+fn temp_upload_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("murmer-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("upload dir");
+    dir
+}
+
+#[tokio::test]
+#[serial]
+async fn uploads_require_a_fresh_signature_and_record_the_key() {
+    let dir = temp_upload_dir("upload-signing");
+    let state = make_state(dir.clone()).await;
+
+    let signing = SigningKey::from_bytes(&[9u8; 32]);
+    let public_key = general_purpose::STANDARD.encode(signing.verifying_key().as_bytes());
+    db::bind_user_key(&state.db, "alice", &public_key)
+        .await
+        .expect("bind key");
+
+    // No identity at all, or only part of it, is refused before any bytes
+    // are stored.
+    assert_eq!(post_upload(&state, &[]).await, StatusCode::UNAUTHORIZED);
+    let mut partial = signed_headers(&signing, "alice", 0);
+    partial.pop();
+    assert_eq!(
+        post_upload(&state, &partial).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // A key the name is not bound to does not verify.
+    let stranger = SigningKey::from_bytes(&[3u8; 32]);
+    let forged = signed_headers(&stranger, "alice", 1000);
+    assert_eq!(post_upload(&state, &forged).await, StatusCode::UNAUTHORIZED);
+
+    let headers = signed_headers(&signing, "alice", 0);
+    assert_eq!(post_upload(&state, &headers).await, StatusCode::OK);
+    let key = std::fs::read_dir(&dir)
+        .expect("list uploads")
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .find(|name| name.ends_with("-notes.txt"))
+        .expect("stored file");
+    let record = db::get_upload(&state.db, &key)
+        .await
+        .expect("lookup")
+        .expect("record exists");
+    assert_eq!(record.uploader.as_deref(), Some("alice"));
+    assert_eq!(record.uploader_key.as_deref(), Some(public_key.as_str()));
+
+    // Replaying the same signed headers is rejected by the nonce store.
+    assert_eq!(
+        post_upload(&state, &headers).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // The admin token still uploads without a signature.
+    let admin = [(header::AUTHORIZATION.as_str(), "Bearer token".to_string())];
+    assert_eq!(post_upload(&state, &admin).await, StatusCode::OK);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[serial]
+fn daily_quota_blocks_the_upload_that_would_exceed_it() {
+    // Each test upload stores the 5 bytes "hello"; two fit in 12, three do not.
+    with_var("UPLOAD_QUOTA_BYTES_PER_DAY", Some("12"), || {
+        Runtime::new().expect("runtime").block_on(async {
+            let dir = temp_upload_dir("upload-quota");
+            let state = make_state(dir.clone()).await;
+            let signing = SigningKey::from_bytes(&[5u8; 32]);
+            let public_key = general_purpose::STANDARD.encode(signing.verifying_key().as_bytes());
+            db::bind_user_key(&state.db, "bob", &public_key)
+                .await
+                .expect("bind key");
+
+            for (age_ms, content) in [(0, "hello"), (1000, "world")] {
+                let headers = signed_headers(&signing, "bob", age_ms);
+                let response = send_upload(&state, &headers, content).await;
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+            let over = send_upload(&state, &signed_headers(&signing, "bob", 2000), "again").await;
+            assert_eq!(over.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            assert!(over.headers().contains_key(upload::QUOTA_EXCEEDED_HEADER));
+
+            // Only the two stored uploads count; the rejected one was
+            // refused before anything was written.
+            let today = chrono::Utc::now().date_naive();
+            let used = db::upload_bytes_used(&state.db, &public_key, today)
+                .await
+                .expect("usage");
+            assert_eq!(used, 10);
+
+            // Usage is per day: tomorrow starts from zero again.
+            let tomorrow = today.succ_opt().expect("tomorrow");
+            assert!(
+                db::reserve_upload_bytes(&state.db, &public_key, tomorrow, 12, 12)
+                    .await
+                    .expect("reserve")
+            );
+
+            // The admin token is not subject to the quota.
+            let admin = [(header::AUTHORIZATION.as_str(), "Bearer token".to_string())];
+            assert_eq!(post_upload(&state, &admin).await, StatusCode::OK);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        });
+    });
+}
+
+#[tokio::test]
+#[serial]
+async fn identical_uploads_share_one_stored_file() {
+    let dir = temp_upload_dir("upload-dedup");
+    let state = make_state(dir.clone()).await;
+    let signing = SigningKey::from_bytes(&[7u8; 32]);
+    let public_key = general_purpose::STANDARD.encode(signing.verifying_key().as_bytes());
+    db::bind_user_key(&state.db, "carol", &public_key)
+        .await
+        .expect("bind key");
+
+    let first = send_upload(&state, &signed_headers(&signing, "carol", 0), "same").await;
+    assert_eq!(first.status(), StatusCode::OK);
+    let first_url = upload_url(first).await;
+    let second = send_upload(&state, &signed_headers(&signing, "carol", 1000), "same").await;
+    assert_eq!(second.status(), StatusCode::OK);
+    assert_eq!(upload_url(second).await, first_url);
+
+    let other = send_upload(&state, &signed_headers(&signing, "carol", 2000), "diff").await;
+    assert_ne!(upload_url(other).await, first_url);
+
+    // Only the first copy is on disk and recorded.
+    let key = first_url.trim_start_matches("/files/");
+    let stored = std::fs::read_dir(&dir)
+        .expect("list uploads")
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with("-notes.txt"))
+        .count();
+    assert_eq!(stored, 2);
+    let record = db::get_upload(&state.db, key)
+        .await
+        .expect("lookup")
+        .expect("record exists");
+    let hash = record.hash.expect("hash recorded");
+
+    // A second record for the same bytes and uploader is refused rather
+    // than duplicated.
+    assert!(
+        !db::record_upload(
+            &state.db,
+            "9-copy.txt",
+            Some("carol"),
+            Some(&public_key),
+            Some(&hash)
+        )
+        .await
+        .expect("record")
+    );
+    assert!(
+        db::get_upload(&state.db, "9-copy.txt")
+            .await
+            .expect("lookup")
+            .is_none()
+    );
+
+    // Someone else uploading the same bytes gets a copy they own, which the
+    // first uploader deleting theirs leaves alone.
+    let dave = SigningKey::from_bytes(&[8u8; 32]);
+    let dave_key = general_purpose::STANDARD.encode(dave.verifying_key().as_bytes());
+    db::bind_user_key(&state.db, "dave", &dave_key)
+        .await
+        .expect("bind key");
+    let copy = send_upload(&state, &signed_headers(&dave, "dave", 0), "same").await;
+    let copy_url = upload_url(copy).await;
+    assert_ne!(copy_url, first_url);
+    let copy_key = copy_url.trim_start_matches("/files/");
+    let copy_record = db::get_upload(&state.db, copy_key)
+        .await
+        .expect("lookup")
+        .expect("record exists");
+    assert_eq!(copy_record.uploader.as_deref(), Some("dave"));
+
+    let path = format!("/upload/{key}");
+    let mut request = Request::delete(&path);
+    for (name, value) in signed_request_headers(&signing, "carol", "DELETE", &path, 3000) {
+        request = request.header(name, value);
+    }
+    let deleted = Router::new()
+        .route("/upload/{key}", delete(upload::delete_upload))
+        .with_state(state.clone())
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert!(!dir.join(key).exists());
+    assert!(dir.join(copy_key).exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[serial]
+fn uploads_that_cannot_be_recorded_are_discarded() {
+    with_var("UPLOAD_QUOTA_BYTES_PER_DAY", Some("100"), || {
+        Runtime::new().expect("runtime").block_on(async {
+            let dir = temp_upload_dir("upload-unrecorded");
+            let state = make_state(dir.clone()).await;
+            let signing = SigningKey::from_bytes(&[9u8; 32]);
+            let public_key = general_purpose::STANDARD.encode(signing.verifying_key().as_bytes());
+            db::bind_user_key(&state.db, "erin", &public_key)
+                .await
+                .expect("bind key");
+            state
+                .db
+                .call_db(|conn| conn.execute_batch("DROP TABLE uploads;"))
+                .await
+                .expect("drop uploads");
+
+            let response = send_upload(&state, &signed_headers(&signing, "erin", 0), "lost").await;
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            let stored = std::fs::read_dir(&dir).expect("list uploads").count();
+            assert_eq!(stored, 0);
+            let today = chrono::Utc::now().date_naive();
+            let used = db::upload_bytes_used(&state.db, &public_key, today)
+                .await
+                .expect("usage");
+            assert_eq!(used, 0);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        });
+    });
+}
+
+#[test]
+#[serial]
+fn images_over_the_dimension_limit_are_refused() {
+    let png = |width, height| {
+        let mut out = Vec::new();
+        image::RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+            .expect("encode png");
+        out
+    };
+    with_var("MAX_IMAGE_DIMENSION", Some("32"), || {
+        Runtime::new().expect("runtime").block_on(async {
+            let dir = temp_upload_dir("upload-dimensions");
+            let state = make_state(dir.clone()).await;
+            let admin = [(header::AUTHORIZATION.as_str(), "Bearer token".to_string())];
+
+            let wide = send_file(&state, &admin, "wide.png", &png(40, 8)).await;
+            assert_eq!(wide.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let tall = send_file(&state, &admin, "tall.png", &png(8, 40)).await;
+            assert_eq!(tall.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let fits = send_file(&state, &admin, "fits.png", &png(32, 32)).await;
+            assert_eq!(fits.status(), StatusCode::OK);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        });
+    });
+}
@@ -0,0 +1,79 @@
+use image::{ImageFormat, RgbImage};
+use murmer_server::upload::{
+    THUMBNAIL_MAX_EDGE, detect_file_type, image_dimensions, render_thumbnail,
+};
+use std::io::Cursor;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    RgbImage::new(width, height)
+        .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+        .expect("encode png");
+    out
+}
+
+/// A valid 1x1 PNG whose header claims `width` x `height`: a few dozen bytes
+/// that would decode to a huge canvas.
+fn declared_png(width: u32, height: u32) -> Vec<u8> {
+    let mut data = png(1, 1);
+    // Signature (8), IHDR length (4) and type (4), then width and height.
+    data[16..20].copy_from_slice(&width.to_be_bytes());
+    data[20..24].copy_from_slice(&height.to_be_bytes());
+    let crc = crc32(&data[12..29]);
+    data[29..33].copy_from_slice(&crc.to_be_bytes());
+    data
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[test]
+fn large_images_are_downscaled_to_webp() {
+    let thumb = render_thumbnail(&png(1280, 640))
+        .expect("decodes")
+        .expect("needs a thumbnail");
+    let decoded = image::load_from_memory_with_format(&thumb, ImageFormat::WebP).expect("webp");
+    assert_eq!(decoded.width(), THUMBNAIL_MAX_EDGE);
+    assert_eq!(decoded.height(), THUMBNAIL_MAX_EDGE / 2);
+}
+
+#[test]
+fn small_images_are_their_own_thumbnail() {
+    assert!(render_thumbnail(&png(64, 64)).expect("decodes").is_none());
+}
+
+#[test]
+fn undecodable_images_yield_an_error() {
+    // Passes the PNG magic-byte check but is not a valid image.
+    let mut bogus = png(8, 8);
+    bogus.truncate(20);
+    assert!(render_thumbnail(&bogus).is_err());
+}
+
+#[test]
+fn documents_are_detected_by_magic_bytes() {
+    assert_eq!(detect_file_type(b"%PDF-1.7\n"), Some("application/pdf"));
+    assert_eq!(detect_file_type(b"PK\x03\x04rest"), Some("application/zip"));
+    assert_eq!(detect_file_type(b"PK\x05\x06\0\0"), Some("application/zip"));
+    assert_eq!(detect_file_type(b"plain text"), None);
+}
+
+#[test]
+fn dimensions_come_from_the_header_alone() {
+    let bomb = declared_png(100_000, 100_000);
+    assert!(bomb.len() < 1024);
+    assert_eq!(image_dimensions(&bomb).expect("header"), (100_000, 100_000));
+    assert_eq!(image_dimensions(&png(40, 20)).expect("header"), (40, 20));
+}
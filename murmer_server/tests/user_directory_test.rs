@@ -0,0 +1,61 @@
+//! Tests for the paged `list-users` directory.
+
+use std::sync::Arc;
+
+use murmer_server::AppState;
+use murmer_server::ws::helpers::user_directory_page;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        admin_token: Some("token".to_string()),
+        ..common::state_with_roles().await
+    })
+}
+
+fn names(page: &[serde_json::Value]) -> Vec<&str> {
+    page.iter().map(|u| u["name"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn pages_filter_and_annotate_known_users() {
+    let state = make_state().await;
+    {
+        let mut known = state.known_users.write().await;
+        for name in ["carol", "Alice", "bob", "alfred", "dave"] {
+            known.insert(name.to_string());
+        }
+    }
+    state.users.lock().await.insert("bob".into());
+    state
+        .statuses
+        .write()
+        .await
+        .insert("carol".into(), "away".into());
+    state
+        .user_roles
+        .write()
+        .await
+        .insert("Alice".into(), vec![3]);
+
+    let (total, page) = user_directory_page(&state, "", 0, 2).await;
+    assert_eq!(total, 5);
+    assert_eq!(names(&page), ["alfred", "Alice"]);
+    assert_eq!(page[1]["roleIds"], serde_json::json!([3]));
+    assert_eq!(page[0]["status"], "offline");
+
+    let (_, page) = user_directory_page(&state, "", 2, 2).await;
+    assert_eq!(names(&page), ["bob", "carol"]);
+    assert_eq!(page[0]["online"], true);
+    assert_eq!(page[0]["status"], "online");
+    assert_eq!(page[1]["status"], "away");
+
+    let (total, page) = user_directory_page(&state, "AL", 0, 10).await;
+    assert_eq!(total, 2);
+    assert_eq!(names(&page), ["alfred", "Alice"]);
+
+    let (total, page) = user_directory_page(&state, "", 10, 10).await;
+    assert_eq!(total, 5);
+    assert!(page.is_empty());
+}
@@ -0,0 +1,53 @@
+//! Tests for `GET /version` and the build metadata behind it.
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+};
+use murmer_server::version::{self, version_info};
+use serial_test::serial;
+use temp_env::with_var;
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+#[test]
+fn version_matches_the_crate() {
+    let info = version_info();
+    assert_eq!(info.name, env!("CARGO_PKG_NAME"));
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(!info.git_sha.is_empty());
+    assert!(chrono::DateTime::parse_from_rfc3339(&info.build_time).is_ok());
+}
+
+fn get_version() -> (StatusCode, Option<serde_json::Value>) {
+    Runtime::new().unwrap().block_on(async {
+        let response = Router::<()>::new()
+            .merge(version::router())
+            .oneshot(Request::get("/version").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        (status, serde_json::from_slice(&body).ok())
+    })
+}
+
+#[test]
+#[serial]
+fn version_endpoint_reports_the_build() {
+    with_var("HIDE_SERVER_VERSION", None::<&str>, || {
+        let (status, json) = get_version();
+        assert_eq!(status, StatusCode::OK);
+        let json = json.expect("json");
+        assert_eq!(json["name"], "murmer_server");
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["git_sha"], version::GIT_SHA);
+        assert!(json["build_time"].is_string());
+    });
+    with_var("HIDE_SERVER_VERSION", Some("true"), || {
+        assert_eq!(get_version().0, StatusCode::NOT_FOUND);
+    });
+}
@@ -0,0 +1,34 @@
+use std::collections::HashSet;
+
+use murmer_server::VoiceChannelState;
+use murmer_server::ws::helpers::voice_channel_full;
+
+fn channel(users: &[&str]) -> VoiceChannelState {
+    VoiceChannelState {
+        name: "lounge".into(),
+        users: users.iter().map(|u| u.to_string()).collect::<HashSet<_>>(),
+        quality: "standard".into(),
+        bitrate: None,
+        category_id: None,
+        position: 0,
+    }
+}
+
+#[test]
+fn rejects_participant_beyond_mesh_limit() {
+    let limit = Some(3);
+    let mut info = channel(&[]);
+    for user in ["alice", "bob", "carol"] {
+        assert!(!voice_channel_full(&info, user, limit));
+        info.users.insert(user.to_string());
+    }
+    assert!(voice_channel_full(&info, "dave", limit));
+    // Someone already inside may re-join.
+    assert!(!voice_channel_full(&info, "alice", limit));
+}
+
+#[test]
+fn unlimited_without_a_configured_cap() {
+    let info = channel(&["alice", "bob", "carol", "dave"]);
+    assert!(!voice_channel_full(&info, "erin", None));
+}
@@ -0,0 +1,225 @@
+//! Tests for the single-voice-channel invariant: a user is a member of at
+//! most one voice channel at any time, and moving between channels updates
+//! the roster of both. Voice frames cannot be sent on another user's behalf.
+
+use std::{collections::HashSet, net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{Router, routing::get};
+use futures::{SinkExt, StreamExt};
+use murmer_server::ws::helpers::{VoiceJoin, join_voice_channel, leave_voice_channels};
+use murmer_server::{AppState, VoiceChannelState, ws};
+use serde_json::Value;
+use tokio::sync::RwLock;
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+
+async fn make_state(channels: &[i32]) -> Arc<AppState> {
+    let voice_channels = channels
+        .iter()
+        .map(|id| {
+            (
+                *id,
+                VoiceChannelState {
+                    name: format!("voice-{id}"),
+                    users: HashSet::new(),
+                    quality: "standard".into(),
+                    bitrate: None,
+                    category_id: None,
+                    position: *id,
+                },
+            )
+        })
+        .collect();
+    Arc::new(AppState {
+        voice_channels: Arc::new(RwLock::new(voice_channels)),
+        ..common::state_with_roles().await
+    })
+}
+
+async fn serve(state: Arc<AppState>) -> SocketAddr {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+async fn memberships(state: &Arc<AppState>, user: &str) -> Vec<i32> {
+    let map = state.voice_channels.read().await;
+    let mut ids: Vec<i32> = map
+        .iter()
+        .filter(|(_, info)| info.users.contains(user))
+        .map(|(id, _)| *id)
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[tokio::test]
+async fn join_moves_user_and_reports_previous_channel() {
+    let state = make_state(&[1, 2]).await;
+    assert_eq!(
+        join_voice_channel(&state, "alice", 1, None).await,
+        VoiceJoin::Joined { left: vec![] }
+    );
+    assert_eq!(
+        join_voice_channel(&state, "alice", 2, None).await,
+        VoiceJoin::Joined { left: vec![1] }
+    );
+    assert_eq!(memberships(&state, "alice").await, vec![2]);
+
+    // A join that cannot happen leaves the user where they were.
+    assert_eq!(
+        join_voice_channel(&state, "alice", 99, None).await,
+        VoiceJoin::UnknownChannel
+    );
+    // Unknown ids are never added as ad-hoc channels.
+    assert!(!state.voice_channels.read().await.contains_key(&99));
+    join_voice_channel(&state, "bob", 1, None).await;
+    assert_eq!(
+        join_voice_channel(&state, "alice", 1, Some(1)).await,
+        VoiceJoin::Full
+    );
+    assert_eq!(memberships(&state, "alice").await, vec![2]);
+
+    assert_eq!(leave_voice_channels(&state, "alice").await, vec![2]);
+    assert!(memberships(&state, "alice").await.is_empty());
+}
+
+#[tokio::test]
+async fn switching_channels_broadcasts_both_rosters() {
+    let state = make_state(&[1, 2]).await;
+    let mut rx = state.tx.subscribe();
+    let addr = serve(state.clone()).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+    for frame in [
+        r#"{"type":"presence","user":"alice"}"#,
+        r#"{"type":"voice-join","channelId":1}"#,
+        r#"{"type":"voice-join","channelId":2}"#,
+    ] {
+        socket.send(Message::Text(frame.into())).await.unwrap();
+    }
+
+    // Collect rosters until the second join is announced; it is sent after
+    // both `voice-users` frames.
+    let mut rosters: Vec<(i64, Vec<String>)> = Vec::new();
+    loop {
+        let raw = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("broadcast arrives")
+            .expect("channel open");
+        let v: Value = serde_json::from_str(&raw).unwrap();
+        match v["type"].as_str() {
+            Some("voice-users") => rosters.push((
+                v["channelId"].as_i64().unwrap(),
+                serde_json::from_value(v["users"].clone()).unwrap(),
+            )),
+            Some("voice-join") if v["channelId"] == 2 => break,
+            _ => {}
+        }
+    }
+
+    let alice = vec!["alice".to_string()];
+    assert_eq!(rosters[0], (1, alice.clone()));
+    assert!(rosters[1..].contains(&(1, Vec::new())), "{rosters:?}");
+    assert_eq!(rosters.last(), Some(&(2, alice)));
+    assert_eq!(memberships(&state, "alice").await, vec![2]);
+}
+
+#[tokio::test]
+async fn voice_frames_naming_another_user_are_rejected() {
+    let state = make_state(&[1]).await;
+    let addr = serve(state.clone()).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+    for frame in [
+        r#"{"type":"presence","user":"mallory"}"#,
+        r#"{"type":"voice-join","user":"alice","channelId":1}"#,
+        r#"{"type":"voice-offer","user":"alice","target":"bob","channelId":1}"#,
+    ] {
+        socket.send(Message::Text(frame.into())).await.unwrap();
+    }
+
+    let mut mismatches = 0;
+    while mismatches < 2 {
+        let frame = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection open")
+            .expect("valid frame");
+        if let Message::Text(text) = frame {
+            let v: Value = serde_json::from_str(&text).unwrap();
+            if v["message"] == "identity-mismatch" {
+                mismatches += 1;
+            }
+        }
+    }
+    assert!(memberships(&state, "alice").await.is_empty());
+    assert!(memberships(&state, "mallory").await.is_empty());
+
+    // The same join under the connection's own name goes through.
+    socket
+        .send(Message::Text(
+            r#"{"type":"voice-join","user":"mallory","channelId":1}"#.into(),
+        ))
+        .await
+        .unwrap();
+    for _ in 0..100 {
+        if !memberships(&state, "mallory").await.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(memberships(&state, "mallory").await, vec![1]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn rapid_concurrent_joins_never_double_book() {
+    let channels = [1, 2, 3, 4];
+    let state = make_state(&channels).await;
+
+    // Several connections for the same user racing across channels, with an
+    // observer checking the invariant throughout.
+    let mut tasks = Vec::new();
+    for worker in 0..8 {
+        let state = state.clone();
+        tasks.push(tokio::spawn(async move {
+            for i in 0..200 {
+                let ch = channels[(worker + i) % channels.len()];
+                join_voice_channel(&state, "alice", ch, None).await;
+                if i % 17 == 0 {
+                    leave_voice_channels(&state, "alice").await;
+                }
+            }
+        }));
+    }
+    let observer = {
+        let state = state.clone();
+        tokio::spawn(async move {
+            for _ in 0..500 {
+                assert!(memberships(&state, "alice").await.len() <= 1);
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+    for task in tasks {
+        task.await.expect("worker");
+    }
+    observer.await.expect("observer");
+    assert!(memberships(&state, "alice").await.len() <= 1);
+}
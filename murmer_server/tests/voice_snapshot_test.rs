@@ -0,0 +1,85 @@
+//! Voice list frames are built from a short snapshot of `voice_channels`, so
+//! no guard on the map is held while the frames are written to a socket.
+
+use std::{collections::HashSet, sync::Arc};
+
+use murmer_server::ws::helpers::{voice_channel_list_frame, voice_users_frames};
+use murmer_server::{AppState, VoiceChannelState};
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+mod common;
+
+const CHANNELS: i32 = 500;
+
+async fn make_state() -> Arc<AppState> {
+    let voice_channels = (0..CHANNELS)
+        .map(|id| {
+            let users: HashSet<String> = (0..(id % 4)).map(|n| format!("user{id}-{n}")).collect();
+            let info = VoiceChannelState {
+                name: format!("voice-{id:03}"),
+                users,
+                quality: "standard".into(),
+                bitrate: None,
+                category_id: None,
+                position: CHANNELS - id,
+            };
+            (id, info)
+        })
+        .collect();
+    Arc::new(AppState {
+        voice_channels: Arc::new(RwLock::new(voice_channels)),
+        ..common::state().await
+    })
+}
+
+#[tokio::test]
+async fn voice_users_frames_cover_every_channel_without_holding_the_lock() {
+    let state = make_state().await;
+    let frames = voice_users_frames(&state).await;
+
+    // While the frames are still being "sent", joins and leaves can proceed.
+    state
+        .voice_channels
+        .try_write()
+        .expect("no guard outlives the snapshot")
+        .get_mut(&0)
+        .expect("channel 0")
+        .users
+        .insert("late".into());
+
+    assert_eq!(frames.len(), CHANNELS as usize);
+    let mut seen = HashSet::new();
+    for frame in &frames {
+        let value: Value = serde_json::from_str(frame).expect("json");
+        assert_eq!(value["type"], "voice-users");
+        let id = value["channelId"].as_i64().expect("channelId") as i32;
+        let mut users: Vec<String> = serde_json::from_value(value["users"].clone()).unwrap();
+        users.sort();
+        let mut expected: Vec<String> = (0..(id % 4)).map(|n| format!("user{id}-{n}")).collect();
+        expected.sort();
+        assert_eq!(users, expected, "members of channel {id}");
+        assert!(seen.insert(id));
+    }
+}
+
+#[tokio::test]
+async fn voice_channel_list_is_sorted_and_releases_the_lock() {
+    let state = make_state().await;
+    let frame = voice_channel_list_frame(&state, None).await.expect("frame");
+    assert!(state.voice_channels.try_write().is_ok());
+
+    let value: Value = serde_json::from_str(&frame).expect("json");
+    let channels = value["channels"].as_array().expect("channels");
+    assert_eq!(channels.len(), CHANNELS as usize);
+    let positions: Vec<i64> = channels
+        .iter()
+        .map(|c| c["position"].as_i64().unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|w| w[0] <= w[1]));
+    let first = &channels[0];
+    assert_eq!(first["id"], CHANNELS - 1);
+    assert_eq!(first["name"], format!("voice-{:03}", CHANNELS - 1));
+    assert_eq!(first["quality"], "standard");
+    assert_eq!(first["private"], false);
+}
@@ -0,0 +1,63 @@
+use murmer_server::{db, webhooks};
+
+#[test]
+fn signature_matches_hmac_sha256_reference() {
+    // RFC 4231, test case 2.
+    assert_eq!(
+        webhooks::sign("Jefe", "what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
+
+#[tokio::test]
+async fn webhooks_are_scoped_to_their_channel() {
+    let db = db::init(":memory:").await.expect("in-memory db");
+    let general = db::get_channel_id_by_name(&db, "general")
+        .await
+        .expect("default channel exists");
+    let other = db::add_channel(&db, "other", None)
+        .await
+        .expect("create channel")
+        .expect("name is free")
+        .id;
+
+    let hook = db::add_outgoing_webhook(&db, general, "https://example.com/hook", "s3cret")
+        .await
+        .expect("register webhook");
+    db::add_outgoing_webhook(&db, other, "https://example.com/other", "x")
+        .await
+        .expect("register second webhook");
+
+    let for_general = db::get_channel_webhooks(&db, general).await.expect("list");
+    assert_eq!(for_general.len(), 1);
+    assert_eq!(for_general[0].url, "https://example.com/hook");
+    assert_eq!(for_general[0].secret, "s3cret");
+
+    assert!(
+        db::remove_outgoing_webhook(&db, hook.id)
+            .await
+            .expect("remove")
+    );
+    assert!(
+        !db::remove_outgoing_webhook(&db, hook.id)
+            .await
+            .expect("remove again")
+    );
+    assert!(
+        db::get_channel_webhooks(&db, general)
+            .await
+            .expect("list")
+            .is_empty()
+    );
+
+    // Deleting a channel drops its webhooks with it.
+    db::remove_channel(&db, other)
+        .await
+        .expect("delete channel");
+    assert!(
+        db::list_outgoing_webhooks(&db)
+            .await
+            .expect("list all")
+            .is_empty()
+    );
+}
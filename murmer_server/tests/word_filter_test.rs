@@ -0,0 +1,167 @@
+//! Tests for the chat word filter: whole-word matching, both filter modes and
+//! reloading the list through the admin endpoint.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use murmer_server::config::{filter_exempt_moderators, filter_mode};
+use murmer_server::word_filter::{FilterMode, Filtered, WordFilter};
+use murmer_server::{AppState, admin};
+use serial_test::serial;
+use temp_env::with_var;
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(AppState {
+        upload_dir: std::env::temp_dir(),
+        admin_token: Some("token".to_string()),
+        ..common::state().await
+    })
+}
+
+fn filter() -> WordFilter {
+    WordFilter::from_wordlist("# banned\nheck\n\n  Darn  \nbad word\n").expect("word list")
+}
+
+#[test]
+fn matches_whole_words_only() {
+    let filter = filter();
+    assert_eq!(filter.len(), 3);
+
+    assert!(filter.is_match("heck"));
+    assert!(filter.is_match("oh heck, again"));
+    assert!(filter.is_match("(darn)"));
+    assert!(filter.is_match("that_is a bad word!"));
+    // Inside another word, or joined to letters, digits or `_`.
+    assert!(!filter.is_match("check"));
+    assert!(!filter.is_match("hecks"));
+    assert!(!filter.is_match("darn2"));
+    assert!(!filter.is_match("_heck"));
+    assert!(!filter.is_match("bad wordsmith"));
+    // The comment line is not an entry.
+    assert!(!filter.is_match("banned"));
+}
+
+#[test]
+fn matching_ignores_case() {
+    let filter = WordFilter::new(["heck", "ÉCLAIR"]).expect("word list");
+    assert!(filter.is_match("HECK"));
+    assert!(filter.is_match("HeCk!"));
+    assert!(filter.is_match("an éclair"));
+    assert_eq!(filter.mask("Éclair time"), "****** time");
+}
+
+#[test]
+fn block_mode_rejects_matches() {
+    let filter = filter();
+    assert_eq!(
+        filter.apply(FilterMode::Block, "well HECK"),
+        Filtered::Blocked
+    );
+    assert_eq!(
+        filter.apply(FilterMode::Block, "check please"),
+        Filtered::Clean
+    );
+}
+
+#[test]
+fn mask_mode_replaces_each_match() {
+    let filter = filter();
+    assert_eq!(
+        filter.apply(FilterMode::Mask, "Heck, a bad word and a check. darn!"),
+        Filtered::Masked("****, a ******** and a check. ****!".into())
+    );
+    assert_eq!(
+        filter.apply(FilterMode::Mask, "checked and darned"),
+        Filtered::Clean
+    );
+}
+
+#[test]
+fn a_longer_entry_does_not_hide_a_shorter_one() {
+    // "heck yes" fails the boundary check in "heck yesterday", but "heck"
+    // on its own still matches.
+    let filter = WordFilter::new(["heck", "heck yes"]).expect("word list");
+    assert_eq!(filter.mask("heck yesterday"), "**** yesterday");
+    assert_eq!(filter.mask("heck yes!"), "********!");
+}
+
+#[test]
+#[serial]
+fn filter_settings() {
+    with_var("FILTER_MODE", None::<&str>, || {
+        assert_eq!(filter_mode(), FilterMode::Block)
+    });
+    with_var("FILTER_MODE", Some("Mask"), || {
+        assert_eq!(filter_mode(), FilterMode::Mask)
+    });
+    with_var("FILTER_EXEMPT_MODERATORS", None::<&str>, || {
+        assert!(!filter_exempt_moderators())
+    });
+    with_var("FILTER_EXEMPT_MODERATORS", Some("yes"), || {
+        assert!(filter_exempt_moderators())
+    });
+}
+
+#[test]
+#[serial]
+fn reload_wordlist_swaps_the_filter() {
+    let path = std::env::temp_dir().join(format!("murmer-wordlist-{}.txt", std::process::id()));
+    std::fs::write(&path, "heck\ndarn\n").expect("write word list");
+
+    with_var("WORDLIST_PATH", Some(path.to_str().unwrap()), || {
+        Runtime::new().unwrap().block_on(async {
+            let state = make_state().await;
+            let reload = |token: &str| {
+                Request::post("/admin/reload-wordlist")
+                    .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap()
+            };
+
+            let response = admin::router()
+                .with_state(state.clone())
+                .oneshot(reload("wrong"))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert!(state.word_filter.read().await.is_none());
+
+            let response = admin::router()
+                .with_state(state.clone())
+                .oneshot(reload("token"))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("body");
+            let json: serde_json::Value = serde_json::from_slice(&body).expect("json");
+            assert_eq!(json["words"], 2);
+            assert!(
+                state
+                    .word_filter
+                    .read()
+                    .await
+                    .as_ref()
+                    .is_some_and(|filter| filter.is_match("darn"))
+            );
+
+            // A list that cannot be read keeps the current filter.
+            std::fs::remove_file(&path).expect("remove word list");
+            let response = admin::router()
+                .with_state(state.clone())
+                .oneshot(reload("token"))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            assert!(state.word_filter.read().await.is_some());
+        })
+    });
+}
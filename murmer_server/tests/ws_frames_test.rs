@@ -0,0 +1,340 @@
+//! Drives a real WebSocket connection to check that control and binary
+//! frames do not end the session, covers MessagePack frame encoding and the
+//! bundled `init-state` snapshot, checks that chat authorship comes from the
+//! authenticated identity and that malformed typed frames are answered with
+//! `invalid-payload`.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{Router, routing::get};
+use futures::{SinkExt, StreamExt};
+use murmer_server::ws::encoding::{FrameEncoding, decode_msgpack};
+use murmer_server::ws::inbound::HandshakeOptions;
+use murmer_server::{AppState, db, ws};
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+
+async fn make_state() -> Arc<AppState> {
+    Arc::new(common::state_with_roles().await)
+}
+
+async fn serve(state: Arc<AppState>) -> SocketAddr {
+    let app = Router::new()
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn ping_and_binary_frames_keep_the_connection_open() {
+    let addr = serve(make_state().await).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+
+    socket
+        .send(Message::Ping(b"are you there".to_vec().into()))
+        .await
+        .unwrap();
+    socket
+        // 0xc1 is never valid MessagePack.
+        .send(Message::Binary(vec![0xc1, 0x00].into()))
+        .await
+        .unwrap();
+    socket
+        .send(Message::Text(r#"{"type":"ping","id":7}"#.into()))
+        .await
+        .unwrap();
+
+    let mut got_pong_frame = false;
+    loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection still open")
+            .expect("valid frame");
+        match frame {
+            Message::Pong(payload) => {
+                assert_eq!(&payload[..], b"are you there");
+                got_pong_frame = true;
+            }
+            Message::Text(text) => {
+                let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if v["type"] == "pong" {
+                    assert_eq!(v["id"], 7);
+                    break;
+                }
+            }
+            Message::Close(_) => panic!("server closed the connection"),
+            _ => {}
+        }
+    }
+    assert!(
+        got_pong_frame,
+        "ping frame was answered before the JSON pong"
+    );
+}
+
+#[tokio::test]
+async fn msgpack_frames_are_dispatched_like_json() {
+    let addr = serve(make_state().await).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+
+    let ping = rmp_serde::to_vec_named(&serde_json::json!({"type": "ping", "id": 8})).unwrap();
+    socket.send(Message::Binary(ping.into())).await.unwrap();
+
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+        .await
+        .expect("server answered")
+        .expect("connection still open")
+        .expect("valid frame");
+    // Direct replies stay JSON text.
+    let Message::Text(text) = frame else {
+        panic!("expected a text reply, got {frame:?}");
+    };
+    let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(v["type"], "pong");
+    assert_eq!(v["id"], 8);
+}
+
+#[test]
+fn msgpack_encoding_is_negotiated_and_round_trips() {
+    let requested = |presence: serde_json::Value| {
+        let options: HandshakeOptions = serde_json::from_value(presence).expect("options");
+        FrameEncoding::requested(&options)
+    };
+    assert_eq!(
+        requested(serde_json::json!({"type": "presence", "encoding": "msgpack"})),
+        FrameEncoding::MsgPack
+    );
+    assert_eq!(
+        requested(serde_json::json!({"type": "presence"})),
+        FrameEncoding::Json
+    );
+
+    let event = serde_json::json!({"type": "voice-offer", "user": "alice", "sdp": "v=0"});
+    match FrameEncoding::MsgPack.frame(event.to_string()) {
+        axum::extract::ws::Message::Binary(bytes) => {
+            assert_eq!(decode_msgpack(&bytes), Some(event.clone()));
+        }
+        other => panic!("expected binary frame, got {other:?}"),
+    }
+    assert!(matches!(
+        FrameEncoding::Json.frame(event.to_string()),
+        axum::extract::ws::Message::Text(_)
+    ));
+}
+
+#[tokio::test]
+async fn chat_author_is_the_authenticated_user() {
+    let state = make_state().await;
+    let mut rx = state.tx.subscribe();
+    let addr = serve(state.clone()).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+
+    // Before presence there is no identity to post as.
+    socket
+        .send(Message::Text(
+            r#"{"type":"chat","user":"bob","text":"early"}"#.into(),
+        ))
+        .await
+        .unwrap();
+    loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection open")
+            .expect("valid frame");
+        if let Message::Text(text) = frame {
+            let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if v["type"] == "error" {
+                assert_eq!(v["message"], "not-authenticated");
+                break;
+            }
+        }
+    }
+
+    for frame in [
+        r#"{"type":"presence","user":"alice"}"#,
+        r#"{"type":"chat","user":"bob","text":"hello"}"#,
+    ] {
+        socket.send(Message::Text(frame.into())).await.unwrap();
+    }
+    // Channel members get the message itself; the global broadcast carries a
+    // `message-notify` summary.
+    let echoed = loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection open")
+            .expect("valid frame");
+        if let Message::Text(text) = frame {
+            let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if v["type"] == "chat" {
+                break v;
+            }
+        }
+    };
+    assert_eq!(echoed["user"], "alice");
+    assert_eq!(echoed["text"], "hello");
+    let notify = loop {
+        let raw = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .expect("broadcast arrives")
+            .expect("channel open");
+        let v: serde_json::Value = serde_json::from_str(&raw).unwrap();
+        if v["type"] == "message-notify" {
+            break v;
+        }
+    };
+    assert_eq!(notify["user"], "alice");
+
+    let general = db::get_channel_id_by_name(&state.db, "general")
+        .await
+        .expect("default channel");
+    let history = db::history_messages(&state.db, general, None, 10)
+        .await
+        .expect("history");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["user"], "alice");
+}
+
+#[tokio::test]
+async fn malformed_typed_frames_get_invalid_payload() {
+    let addr = serve(make_state().await).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+
+    socket
+        .send(Message::Text(
+            r#"{"type":"react","messageId":"not a number","action":"add","emoji":"👍"}"#.into(),
+        ))
+        .await
+        .unwrap();
+
+    loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection still open")
+            .expect("valid frame");
+        let Message::Text(text) = frame else {
+            continue;
+        };
+        let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+        if v["type"] == "error" {
+            assert_eq!(v["message"], "invalid-payload");
+            assert_eq!(v["frameType"], "react");
+            break;
+        }
+    }
+}
+
+/// Send `presence` followed by a ping and collect the text frames that
+/// arrive before the pong.
+async fn frames_after_presence(
+    addr: SocketAddr,
+    presence: serde_json::Value,
+) -> Vec<serde_json::Value> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+        .await
+        .expect("connect");
+    socket
+        .send(Message::Text(presence.to_string().into()))
+        .await
+        .unwrap();
+    socket
+        .send(Message::Text(r#"{"type":"ping","id":9}"#.into()))
+        .await
+        .unwrap();
+    let mut frames = Vec::new();
+    loop {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("server answered")
+            .expect("connection still open")
+            .expect("valid frame");
+        if let Message::Text(text) = frame {
+            let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if v["type"] == "pong" {
+                return frames;
+            }
+            frames.push(v);
+        }
+    }
+}
+
+fn types(frames: &[serde_json::Value]) -> Vec<&str> {
+    frames.iter().filter_map(|f| f["type"].as_str()).collect()
+}
+
+#[tokio::test]
+async fn init_state_bundles_the_snapshot_for_clients_that_ask() {
+    let addr = serve(make_state().await).await;
+
+    let bundled = frames_after_presence(
+        addr,
+        serde_json::json!({"type": "presence", "user": "alice", "protocolVersion": 2, "initState": true}),
+    )
+    .await;
+    let top = types(&bundled);
+    assert_eq!(top.iter().filter(|t| **t == "init-state").count(), 1);
+    for granular in [
+        "role-definitions",
+        "category-list",
+        "channel-list",
+        "history",
+    ] {
+        assert!(!top.contains(&granular), "{granular} sent on its own");
+    }
+    let init = bundled.iter().find(|f| f["type"] == "init-state").unwrap();
+    let inner = types(init["frames"].as_array().expect("frames"));
+    for expected in [
+        "role-definitions",
+        "status-snapshot",
+        "category-list",
+        "channel-list",
+        "voice-channel-list",
+        "online-users",
+        "history",
+    ] {
+        assert!(
+            inner.contains(&expected),
+            "{expected} missing from init-state"
+        );
+    }
+    // Frames outside the snapshot still arrive individually.
+    assert!(top.contains(&"server-identity"));
+
+    // Clients that do not ask keep getting the granular frames.
+    let granular =
+        frames_after_presence(addr, serde_json::json!({"type": "presence", "user": "bob"})).await;
+    let top = types(&granular);
+    assert!(!top.contains(&"init-state"));
+    for expected in [
+        "role-definitions",
+        "channel-list",
+        "voice-channel-list",
+        "history",
+    ] {
+        assert!(top.contains(&expected), "{expected} missing");
+    }
+}